@@ -39,16 +39,18 @@
 //! // Config::from_partial(&PartialConfig::default_mainnet());
 //! ```
 use std::collections::HashSet;
-use std::net::SocketAddr;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::path::PathBuf;
 use std::time::Duration;
 
 use log::warn;
 use serde::{Deserialize, Deserializer};
 
-use crate::defaults::{Defaults, Testnet1};
+use crate::defaults::{Defaults, Development, Testnet1};
 use partial_struct::PartialStruct;
-use witnet_data_structures::chain::{ConsensusConstants, Environment, PartialConsensusConstants};
+use witnet_data_structures::chain::{
+    ConsensusConstants, Environment, Hash, PartialConsensusConstants,
+};
 use witnet_protected::Protected;
 
 /// The total configuration object that contains all other, more
@@ -86,6 +88,46 @@ pub struct Config {
     #[partial_struct(ty = "PartialMining")]
     #[partial_struct(serde(default))]
     pub mining: Mining,
+
+    /// Memory caps for pools and caches
+    #[partial_struct(ty = "PartialMemory")]
+    #[partial_struct(serde(default))]
+    pub memory: Memory,
+
+    /// Pool of unconfirmed transactions configuration
+    #[partial_struct(ty = "PartialMempool")]
+    #[partial_struct(serde(default))]
+    pub mempool: Mempool,
+
+    /// Trusted checkpoints configuration
+    #[partial_struct(ty = "PartialCheckpoints")]
+    #[partial_struct(serde(default))]
+    pub checkpoints: Checkpoints,
+
+    /// Chain state snapshots configuration
+    #[partial_struct(ty = "PartialSnapshots")]
+    #[partial_struct(serde(default))]
+    pub snapshots: Snapshots,
+
+    /// Block pruning configuration
+    #[partial_struct(ty = "PartialPruning")]
+    #[partial_struct(serde(default))]
+    pub pruning: Pruning,
+
+    /// Alerts configuration
+    #[partial_struct(ty = "PartialAlerts")]
+    #[partial_struct(serde(default))]
+    pub alerts: Alerts,
+
+    /// Clock synchronization configuration
+    #[partial_struct(ty = "PartialNtp")]
+    #[partial_struct(serde(default))]
+    pub ntp: Ntp,
+
+    /// Data retrieval configuration
+    #[partial_struct(ty = "PartialRad")]
+    #[partial_struct(serde(default))]
+    pub rad: Rad,
 }
 
 /// Connection-specific configuration.
@@ -98,11 +140,13 @@ pub struct Connections {
     pub server_addr: SocketAddr,
 
     /// Maximum number of concurrent connections the server should
-    /// accept
+    /// accept. When it is reached, the inbound peer with the worst
+    /// quality score is evicted to accept a new one
     pub inbound_limit: u16,
 
-    /// Maximum number of opened connections to other peers this node
-    /// (acting as a client) should maintain
+    /// Number of opened connections to other peers this node (acting
+    /// as a client) should maintain. Peers are dialed until it is
+    /// reached
     pub outbound_limit: u16,
 
     /// List of other peer addresses this node knows at start, it is
@@ -112,6 +156,28 @@ pub struct Connections {
     #[partial_struct(serde(default))]
     pub known_peers: HashSet<SocketAddr>,
 
+    /// Hosts (`hostname:port`) dialed when no known peer is
+    /// available. All the addresses they resolve to are tried with
+    /// Happy Eyeballs, so IPv6 and IPv4 are raced against each other
+    pub seed_hosts: Vec<String>,
+
+    /// DNS seeds (`hostname` or `hostname:port`) resolved when the
+    /// address book is empty. Every address they resolve to is added
+    /// to the known peers, with the default port of the environment
+    /// if none is given
+    pub dns_seeds: Vec<String>,
+
+    /// Peer addresses added to the known peers when the DNS seeds
+    /// give no address
+    pub fallback_seeds: Vec<SocketAddr>,
+
+    /// Additional listeners of the server, bound besides
+    /// `server_addr`. Each of them can restrict the inbound
+    /// connections it accepts
+    #[partial_struct(skip)]
+    #[partial_struct(serde(default))]
+    pub listeners: Vec<Listener>,
+
     /// Period of the bootstrap peers task
     #[partial_struct(serde(
         default,
@@ -144,8 +210,152 @@ pub struct Connections {
     ))]
     pub handshake_timeout: Duration,
 
-    /// Number of seconds before giving up waiting for requested blocks
+    /// Number of seconds a peer has to deliver the blocks and transactions requested to it
     pub blocks_timeout: i64,
+
+    /// Suffix appended to the user agent announced to other peers
+    pub user_agent_suffix: String,
+
+    /// Minimum protocol version accepted from peers. It can only
+    /// raise the `min_protocol_version` consensus constant, never
+    /// lower it
+    pub min_peer_version: u32,
+
+    /// Peers announcing a user agent that matches any of these
+    /// patterns (`*` is a wildcard) are disconnected and forgotten
+    pub avoid_user_agents: Vec<String>,
+
+    /// Peers announcing a user agent that matches any of these
+    /// patterns are preferred, even if they also match one of the
+    /// patterns to avoid: they are dialed again before any other
+    /// peer and they are the last inbound peers to be evicted
+    pub prefer_user_agents: Vec<String>,
+
+    /// Discover other nodes of the local network using mDNS.
+    /// Only available in the development environment
+    pub lan_discovery: bool,
+
+    /// Map the server port in the NAT gateway using NAT-PMP and
+    /// advertise the external address to other peers
+    pub port_mapping: bool,
+
+    /// Address of the NAT-PMP gateway. The default gateway of the
+    /// host is used if it is not set
+    #[partial_struct(skip)]
+    #[partial_struct(serde(default))]
+    pub port_mapping_gateway: Option<Ipv4Addr>,
+
+    /// Encrypt the sessions with the peers that also support the
+    /// encrypted transport
+    pub encrypted_transport: bool,
+
+    /// Period between the pings sent to each peer to measure its
+    /// latency, `0` disables them. Pings not answered within this
+    /// period are missed
+    #[partial_struct(serde(
+        default,
+        deserialize_with = "from_secs",
+        rename = "ping_interval_seconds"
+    ))]
+    pub ping_interval: Duration,
+
+    /// Peers that miss this many consecutive pings are
+    /// disconnected, `0` never disconnects them
+    pub max_missed_pongs: u32,
+
+    /// Maximum number of addresses sent to a peer in answer to a
+    /// `GetPeers` message
+    pub gossip_max_addresses: usize,
+
+    /// Only the addresses seen within this time are sent to other
+    /// peers, `0` sends them all
+    #[partial_struct(serde(
+        default,
+        deserialize_with = "from_secs",
+        rename = "gossip_max_address_age_seconds"
+    ))]
+    pub gossip_max_address_age: Duration,
+
+    /// Each peer gets at most one `GetPeers` message answered and
+    /// one unsolicited `Peers` message accepted within this time
+    #[partial_struct(serde(
+        default,
+        deserialize_with = "from_secs",
+        rename = "gossip_interval_seconds"
+    ))]
+    pub gossip_interval: Duration,
+
+    /// Period between the feeler connections, short-lived
+    /// connections to random peers of the new bucket of the
+    /// address book to check whether they are reachable, `0`
+    /// disables them
+    #[partial_struct(serde(
+        default,
+        deserialize_with = "from_secs",
+        rename = "feeler_period_seconds"
+    ))]
+    pub feeler_period: Duration,
+
+    /// Peers of the new bucket are forgotten after this many
+    /// consecutive failed feeler connections, `0` never forgets them
+    pub feeler_max_failures: u32,
+
+    /// Percentage of the outbound peers that must agree on a beacon
+    /// for it to be the consensus of the network
+    pub consensus_percentage: u32,
+
+    /// Maximum upload rate of each session, in bytes per second,
+    /// `0` means no limit
+    pub max_upload_rate: u64,
+
+    /// Maximum download rate of each session, in bytes per second,
+    /// `0` means no limit
+    pub max_download_rate: u64,
+
+    /// Maximum upload rate of all the sessions together, in bytes
+    /// per second, `0` means no limit
+    pub max_total_upload_rate: u64,
+
+    /// Maximum download rate of all the sessions together, in bytes
+    /// per second, `0` means no limit
+    pub max_total_download_rate: u64,
+
+    /// Listeners bound to IPv6 addresses also accept IPv4
+    /// connections. Disable it to bind IPv4 and IPv6 listeners to
+    /// the same port
+    pub dual_stack: bool,
+
+    /// If not empty, only these IP addresses or CIDR ranges (e.g.
+    /// `10.0.0.0/8`) are connected to, inbound or outbound
+    pub whitelist: Vec<String>,
+
+    /// These IP addresses or CIDR ranges are never connected to,
+    /// inbound or outbound, even if they are whitelisted
+    pub blacklist: Vec<String>,
+
+    /// Address of a SOCKS5 proxy, such as Tor, used for all the
+    /// outbound connections. When set, the server address is not
+    /// advertised to other peers
+    #[partial_struct(skip)]
+    #[partial_struct(serde(default))]
+    pub proxy: Option<SocketAddr>,
+}
+
+/// Additional listener of the P2P server
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct Listener {
+    /// Socket address the listener should bind to
+    pub addr: SocketAddr,
+
+    /// Maximum number of inbound connections accepted by this
+    /// listener, on top of the global `inbound_limit`
+    #[serde(default)]
+    pub inbound_limit: Option<u16>,
+
+    /// If not empty, only connections from these IP addresses are
+    /// accepted by this listener
+    #[serde(default)]
+    pub whitelist: Vec<IpAddr>,
 }
 
 fn from_secs<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
@@ -158,6 +368,16 @@ where
     })
 }
 
+fn from_millis<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(match u64::deserialize(deserializer) {
+        Ok(millis) => Some(Duration::from_millis(millis)),
+        Err(_) => None,
+    })
+}
+
 /// Available storage backends
 #[derive(Debug, Deserialize, Clone, PartialEq)]
 pub enum StorageBackend {
@@ -173,6 +393,23 @@ impl Default for StorageBackend {
     }
 }
 
+/// Available compaction styles of the RocksDB backend
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub enum CompactionStyle {
+    #[serde(rename = "level")]
+    Level,
+    #[serde(rename = "universal")]
+    Universal,
+    #[serde(rename = "fifo")]
+    Fifo,
+}
+
+impl Default for CompactionStyle {
+    fn default() -> Self {
+        CompactionStyle::Level
+    }
+}
+
 /// Storage-specific configuration
 #[derive(PartialStruct, Debug, Clone, PartialEq)]
 #[partial_struct(derive(Deserialize, Default, Debug, Clone, PartialEq))]
@@ -190,6 +427,21 @@ pub struct Storage {
     /// Path to the directory that will contain the database. Used
     /// only if backend is RocksDB.
     pub db_path: PathBuf,
+    /// Size of the cache of uncompressed blocks of the database,
+    /// shared by all the column families. Used only if backend is
+    /// RocksDB.
+    pub block_cache_bytes: usize,
+    /// Size of the memory buffer of each column family, which is
+    /// written to disk when full. Used only if backend is RocksDB.
+    pub write_buffer_bytes: usize,
+    /// Compaction style of the database. Used only if backend is
+    /// RocksDB.
+    #[partial_struct(skip)]
+    #[partial_struct(serde(default))]
+    pub compaction_style: CompactionStyle,
+    /// Maximum number of concurrent background compactions. Used
+    /// only if backend is RocksDB.
+    pub max_background_compactions: u32,
 }
 
 fn as_protected_string<'de, D>(deserializer: D) -> Result<Option<Protected>, D::Error>
@@ -219,13 +471,249 @@ pub struct Mining {
     pub enabled: bool,
 }
 
+/// Approximate memory caps (in bytes) for pools and caches.
+/// A cap of `0` means that the component is not limited.
+#[derive(PartialStruct, Debug, Clone, PartialEq)]
+#[partial_struct(derive(Deserialize, Default, Debug, Clone, PartialEq))]
+pub struct Memory {
+    /// Memory cap for the pool of unconfirmed transactions. When exceeded, the transactions
+    /// with smaller fees are evicted
+    pub mempool_max_bytes: usize,
+    /// Memory cap for the block candidates of the current epoch. When exceeded, new candidates
+    /// are discarded
+    pub candidates_max_bytes: usize,
+    /// Memory cap for the known peers. When exceeded, the oldest peers are forgotten
+    pub peers_max_bytes: usize,
+    /// Memory cap for the cache of successful signature verifications. When exceeded, the
+    /// oldest verifications are forgotten
+    pub signature_cache_max_bytes: usize,
+    /// Memory cap for the blocks received before their parent. When exceeded, the oldest
+    /// orphans are evicted
+    pub orphans_max_bytes: usize,
+    /// Memory cap for the headers and blocks of a synchronization in progress. When exceeded, no
+    /// more headers are requested until the pending blocks are consolidated
+    pub sync_max_bytes: usize,
+}
+
+/// Pool of unconfirmed transactions configuration
+#[derive(PartialStruct, Debug, Clone, PartialEq)]
+#[partial_struct(derive(Deserialize, Default, Debug, Clone, PartialEq))]
+pub struct Mempool {
+    /// Cap for the sum of the weights of the unconfirmed transactions. When exceeded, the
+    /// transactions with smaller fees per unit of weight are evicted. `0` means no cap
+    pub max_weight: u64,
+    /// Number of epochs an unconfirmed transaction is kept before it expires. `0` means that
+    /// transactions never expire
+    pub expiry_epochs: u32,
+    /// Minimum fee, in nanowits per 1000 units of weight, for value transfers and data requests
+    /// to be accepted and relayed to the peers
+    pub min_relay_fee: u64,
+}
+
+/// Configuration of the feed of signed checkpoints used to detect minority forks
+#[derive(PartialStruct, Debug, Clone, PartialEq)]
+#[partial_struct(derive(Deserialize, Default, Debug, Clone, PartialEq))]
+pub struct Checkpoints {
+    /// URL of a feed of signed checkpoints in JSON format. Checkpoints
+    /// are not fetched if it is not set
+    #[partial_struct(skip)]
+    #[partial_struct(serde(default))]
+    pub feed_url: Option<String>,
+
+    /// Public keys trusted to sign checkpoints, hex-encoded in
+    /// compressed format (33 bytes)
+    pub trusted_keys: Vec<String>,
+
+    /// Period of the fetch checkpoints task
+    #[partial_struct(serde(
+        default,
+        deserialize_with = "from_secs",
+        rename = "poll_period_seconds"
+    ))]
+    pub poll_period: Duration,
+}
+
+/// Configuration of the chain state snapshots exported at superblock boundaries, and of the
+/// snapshot imported to skip the synchronization from the genesis block
+#[derive(PartialStruct, Debug, Clone, PartialEq)]
+#[partial_struct(derive(Deserialize, Default, Debug, Clone, PartialEq))]
+pub struct Snapshots {
+    /// Directory where the snapshots are exported. Snapshots are not
+    /// exported if it is not set
+    #[partial_struct(skip)]
+    #[partial_struct(serde(default))]
+    pub export_path: Option<PathBuf>,
+
+    /// Snapshot imported when the local chain is not initialized
+    #[partial_struct(skip)]
+    #[partial_struct(serde(default))]
+    pub import_path: Option<PathBuf>,
+
+    /// Integrity hash of the snapshot to import, obtained from a
+    /// trusted source
+    #[partial_struct(skip)]
+    #[partial_struct(serde(default))]
+    pub trusted_hash: Option<Hash>,
+}
+
+/// Configuration of the pruning of the bodies of old blocks
+#[derive(PartialStruct, Debug, Clone, PartialEq)]
+#[partial_struct(derive(Deserialize, Default, Debug, Clone, PartialEq))]
+pub struct Pruning {
+    /// Binary flag telling whether to prune the bodies of old blocks
+    pub enabled: bool,
+
+    /// Number of epochs before the chain tip whose blocks are kept
+    /// whole
+    pub keep_epochs: u32,
+}
+
+/// Configuration of the alerts about operator-critical events
+#[derive(PartialStruct, Debug, Clone, PartialEq)]
+#[partial_struct(derive(Deserialize, Default, Debug, Clone, PartialEq))]
+pub struct Alerts {
+    /// Binary flag telling whether to enable the AlertsManager or not
+    pub enabled: bool,
+
+    /// Period of the alert conditions check
+    #[partial_struct(serde(
+        default,
+        deserialize_with = "from_secs",
+        rename = "check_period_seconds"
+    ))]
+    pub check_period: Duration,
+
+    /// Minimum time between two notifications of the same alert
+    /// while its condition persists
+    #[partial_struct(serde(
+        default,
+        deserialize_with = "from_secs",
+        rename = "repeat_period_seconds"
+    ))]
+    pub repeat_period: Duration,
+
+    /// Time without any connected peer before alerting
+    #[partial_struct(serde(
+        default,
+        deserialize_with = "from_secs",
+        rename = "no_peers_timeout_seconds"
+    ))]
+    pub no_peers_timeout: Duration,
+
+    /// Time without changes in the chain tip before alerting
+    #[partial_struct(serde(
+        default,
+        deserialize_with = "from_secs",
+        rename = "stalled_chain_timeout_seconds"
+    ))]
+    pub stalled_chain_timeout: Duration,
+
+    /// Minimum free space (in bytes) in the disk of the database
+    pub min_free_disk_bytes: u64,
+
+    /// Maximum difference between the local clock and the clocks of
+    /// the peers
+    #[partial_struct(serde(
+        default,
+        deserialize_with = "from_secs",
+        rename = "max_clock_drift_seconds"
+    ))]
+    pub max_clock_drift: Duration,
+
+    /// URL where alerts are posted in JSON format
+    #[partial_struct(skip)]
+    #[partial_struct(serde(default))]
+    pub webhook_url: Option<String>,
+
+    /// Shell command run for every alert, with the details of the
+    /// alert in the `WITNET_ALERT_*` environment variables
+    #[partial_struct(skip)]
+    #[partial_struct(serde(default))]
+    pub command: Option<String>,
+}
+
+/// Configuration of the synchronization of the clock with NTP servers
+#[derive(PartialStruct, Debug, Clone, PartialEq)]
+#[partial_struct(derive(Deserialize, Default, Debug, Clone, PartialEq))]
+pub struct Ntp {
+    /// Binary flag telling whether to correct the clock with the NTP
+    /// servers or not
+    pub enabled: bool,
+
+    /// NTP servers, as `host:port`. The clock offset is the median of
+    /// the offsets measured with each of them
+    pub servers: Vec<String>,
+
+    /// Period of the clock offset update
+    #[partial_struct(serde(
+        default,
+        deserialize_with = "from_secs",
+        rename = "update_period_seconds"
+    ))]
+    pub update_period: Duration,
+
+    /// Clock offset above which a warning is logged, as the local
+    /// clock should be fixed
+    #[partial_struct(serde(
+        default,
+        deserialize_with = "from_secs",
+        rename = "max_offset_seconds"
+    ))]
+    pub max_offset: Duration,
+}
+
+/// Configuration of the HTTP client used by the RAD engine to
+/// retrieve the sources of data requests
+#[derive(PartialStruct, Debug, Clone, PartialEq)]
+#[partial_struct(derive(Deserialize, Default, Debug, Clone, PartialEq))]
+pub struct Rad {
+    /// Timeout of every attempt to retrieve a source which does not
+    /// declare its own timeout
+    #[partial_struct(serde(default, deserialize_with = "from_secs", rename = "timeout_seconds"))]
+    pub timeout: Duration,
+
+    /// Upper bound of the timeout of every attempt, which also
+    /// applies to the timeouts declared by the sources
+    #[partial_struct(serde(
+        default,
+        deserialize_with = "from_secs",
+        rename = "max_timeout_seconds"
+    ))]
+    pub max_timeout: Duration,
+
+    /// Number of threads retrieving sources in parallel
+    pub workers: usize,
+
+    /// Maximum number of times a retrieval is retried after a
+    /// connection error, a timeout or a server error
+    pub max_retries: u32,
+
+    /// Delay before the first retry, doubled on every subsequent one
+    #[partial_struct(serde(
+        default,
+        deserialize_with = "from_millis",
+        rename = "retry_backoff_milliseconds"
+    ))]
+    pub retry_backoff: Duration,
+
+    /// Maximum number of redirects followed by a retrieval
+    pub max_redirects: usize,
+
+    /// Value of the `User-Agent` header of the retrievals, the user
+    /// agent of the node if not set
+    #[partial_struct(skip)]
+    #[partial_struct(serde(default))]
+    pub user_agent: Option<String>,
+}
+
 impl Config {
     pub fn from_partial(config: &PartialConfig) -> Self {
-        let defaults = match config.environment {
+        let defaults: &dyn Defaults = match config.environment {
             Environment::Mainnet => {
                 panic!("Config with mainnet environment is currently not allowed");
             }
             Environment::Testnet1 => &Testnet1,
+            Environment::Development => &Development,
         };
 
         let consensus_constants = match config.environment {
@@ -240,8 +728,8 @@ impl Config {
                 }
                 consensus_constants_from_partial(&consensus_constants_no_changes, defaults)
             }
-            // In testnet, allow to override the consensus constants
-            Environment::Testnet1 => {
+            // In testnet and development, allow to override the consensus constants
+            Environment::Testnet1 | Environment::Development => {
                 consensus_constants_from_partial(&config.consensus_constants, defaults)
             }
         };
@@ -253,6 +741,14 @@ impl Config {
             consensus_constants,
             jsonrpc: JsonRPC::from_partial(&config.jsonrpc, defaults),
             mining: Mining::from_partial(&config.mining, defaults),
+            memory: Memory::from_partial(&config.memory, defaults),
+            mempool: Mempool::from_partial(&config.mempool, defaults),
+            checkpoints: Checkpoints::from_partial(&config.checkpoints, defaults),
+            snapshots: Snapshots::from_partial(&config.snapshots, defaults),
+            pruning: Pruning::from_partial(&config.pruning, defaults),
+            alerts: Alerts::from_partial(&config.alerts, defaults),
+            ntp: Ntp::from_partial(&config.ntp, defaults),
+            rad: Rad::from_partial(&config.rad, defaults),
         }
     }
 }
@@ -286,6 +782,10 @@ pub fn consensus_constants_from_partial(
             .max_block_weight
             .to_owned()
             .unwrap_or_else(|| defaults.consensus_constants_max_block_weight()),
+        min_protocol_version: config
+            .min_protocol_version
+            .to_owned()
+            .unwrap_or_else(|| defaults.consensus_constants_min_protocol_version()),
     }
 }
 
@@ -315,6 +815,19 @@ impl Connections {
                 .union(&defaults.connections_known_peers())
                 .cloned()
                 .collect(),
+            seed_hosts: config
+                .seed_hosts
+                .to_owned()
+                .unwrap_or_else(|| defaults.connections_seed_hosts()),
+            dns_seeds: config
+                .dns_seeds
+                .to_owned()
+                .unwrap_or_else(|| defaults.connections_dns_seeds()),
+            fallback_seeds: config
+                .fallback_seeds
+                .to_owned()
+                .unwrap_or_else(|| defaults.connections_fallback_seeds()),
+            listeners: config.listeners.clone(),
             bootstrap_peers_period: config
                 .bootstrap_peers_period
                 .to_owned()
@@ -334,8 +847,102 @@ impl Connections {
                 .blocks_timeout
                 .to_owned()
                 .unwrap_or_else(|| defaults.connections_blocks_timeout()),
+            user_agent_suffix: config
+                .user_agent_suffix
+                .to_owned()
+                .unwrap_or_else(|| defaults.connections_user_agent_suffix()),
+            min_peer_version: config
+                .min_peer_version
+                .to_owned()
+                .unwrap_or_else(|| defaults.connections_min_peer_version()),
+            avoid_user_agents: config
+                .avoid_user_agents
+                .to_owned()
+                .unwrap_or_else(|| defaults.connections_avoid_user_agents()),
+            prefer_user_agents: config
+                .prefer_user_agents
+                .to_owned()
+                .unwrap_or_else(|| defaults.connections_prefer_user_agents()),
+            lan_discovery: config
+                .lan_discovery
+                .to_owned()
+                .unwrap_or_else(|| defaults.connections_lan_discovery()),
+            port_mapping: config
+                .port_mapping
+                .to_owned()
+                .unwrap_or_else(|| defaults.connections_port_mapping()),
+            port_mapping_gateway: config.port_mapping_gateway,
+            encrypted_transport: config
+                .encrypted_transport
+                .to_owned()
+                .unwrap_or_else(|| defaults.connections_encrypted_transport()),
+            ping_interval: config
+                .ping_interval
+                .unwrap_or_else(|| defaults.connections_ping_interval()),
+            max_missed_pongs: config
+                .max_missed_pongs
+                .to_owned()
+                .unwrap_or_else(|| defaults.connections_max_missed_pongs()),
+            gossip_max_addresses: config
+                .gossip_max_addresses
+                .to_owned()
+                .unwrap_or_else(|| defaults.connections_gossip_max_addresses()),
+            gossip_max_address_age: config
+                .gossip_max_address_age
+                .unwrap_or_else(|| defaults.connections_gossip_max_address_age()),
+            gossip_interval: config
+                .gossip_interval
+                .unwrap_or_else(|| defaults.connections_gossip_interval()),
+            feeler_period: config
+                .feeler_period
+                .unwrap_or_else(|| defaults.connections_feeler_period()),
+            feeler_max_failures: config
+                .feeler_max_failures
+                .to_owned()
+                .unwrap_or_else(|| defaults.connections_feeler_max_failures()),
+            consensus_percentage: config
+                .consensus_percentage
+                .to_owned()
+                .unwrap_or_else(|| defaults.connections_consensus_percentage()),
+            max_upload_rate: config
+                .max_upload_rate
+                .to_owned()
+                .unwrap_or_else(|| defaults.connections_max_upload_rate()),
+            max_download_rate: config
+                .max_download_rate
+                .to_owned()
+                .unwrap_or_else(|| defaults.connections_max_download_rate()),
+            max_total_upload_rate: config
+                .max_total_upload_rate
+                .to_owned()
+                .unwrap_or_else(|| defaults.connections_max_total_upload_rate()),
+            max_total_download_rate: config
+                .max_total_download_rate
+                .to_owned()
+                .unwrap_or_else(|| defaults.connections_max_total_download_rate()),
+            dual_stack: config
+                .dual_stack
+                .to_owned()
+                .unwrap_or_else(|| defaults.connections_dual_stack()),
+            whitelist: config
+                .whitelist
+                .to_owned()
+                .unwrap_or_else(|| defaults.connections_whitelist()),
+            blacklist: config
+                .blacklist
+                .to_owned()
+                .unwrap_or_else(|| defaults.connections_blacklist()),
+            proxy: config.proxy,
         }
     }
+
+    /// Addresses the server listens to: `server_addr` followed by
+    /// the addresses of the additional listeners
+    pub fn listen_addresses(&self) -> Vec<SocketAddr> {
+        std::iter::once(self.server_addr)
+            .chain(self.listeners.iter().map(|listener| listener.addr))
+            .collect()
+    }
 }
 
 impl Storage {
@@ -347,6 +954,19 @@ impl Storage {
                 .db_path
                 .to_owned()
                 .unwrap_or_else(|| defaults.storage_db_path()),
+            block_cache_bytes: config
+                .block_cache_bytes
+                .to_owned()
+                .unwrap_or_else(|| defaults.storage_block_cache_bytes()),
+            write_buffer_bytes: config
+                .write_buffer_bytes
+                .to_owned()
+                .unwrap_or_else(|| defaults.storage_write_buffer_bytes()),
+            compaction_style: config.compaction_style.clone(),
+            max_background_compactions: config
+                .max_background_compactions
+                .to_owned()
+                .unwrap_or_else(|| defaults.storage_max_background_compactions()),
         }
     }
 }
@@ -377,6 +997,189 @@ impl Mining {
     }
 }
 
+impl Mempool {
+    pub fn from_partial(config: &PartialMempool, defaults: &dyn Defaults) -> Self {
+        Mempool {
+            max_weight: config
+                .max_weight
+                .to_owned()
+                .unwrap_or_else(|| defaults.mempool_max_weight()),
+            expiry_epochs: config
+                .expiry_epochs
+                .to_owned()
+                .unwrap_or_else(|| defaults.mempool_expiry_epochs()),
+            min_relay_fee: config
+                .min_relay_fee
+                .to_owned()
+                .unwrap_or_else(|| defaults.mempool_min_relay_fee()),
+        }
+    }
+}
+
+impl Memory {
+    pub fn from_partial(config: &PartialMemory, defaults: &dyn Defaults) -> Self {
+        Memory {
+            mempool_max_bytes: config
+                .mempool_max_bytes
+                .to_owned()
+                .unwrap_or_else(|| defaults.memory_mempool_max_bytes()),
+            candidates_max_bytes: config
+                .candidates_max_bytes
+                .to_owned()
+                .unwrap_or_else(|| defaults.memory_candidates_max_bytes()),
+            peers_max_bytes: config
+                .peers_max_bytes
+                .to_owned()
+                .unwrap_or_else(|| defaults.memory_peers_max_bytes()),
+            signature_cache_max_bytes: config
+                .signature_cache_max_bytes
+                .to_owned()
+                .unwrap_or_else(|| defaults.memory_signature_cache_max_bytes()),
+            orphans_max_bytes: config
+                .orphans_max_bytes
+                .to_owned()
+                .unwrap_or_else(|| defaults.memory_orphans_max_bytes()),
+            sync_max_bytes: config
+                .sync_max_bytes
+                .to_owned()
+                .unwrap_or_else(|| defaults.memory_sync_max_bytes()),
+        }
+    }
+}
+
+impl Checkpoints {
+    pub fn from_partial(config: &PartialCheckpoints, defaults: &dyn Defaults) -> Self {
+        Checkpoints {
+            feed_url: config.feed_url.clone(),
+            trusted_keys: config
+                .trusted_keys
+                .to_owned()
+                .unwrap_or_else(|| defaults.checkpoints_trusted_keys()),
+            poll_period: config
+                .poll_period
+                .to_owned()
+                .unwrap_or_else(|| defaults.checkpoints_poll_period()),
+        }
+    }
+}
+
+impl Snapshots {
+    pub fn from_partial(config: &PartialSnapshots, _defaults: &dyn Defaults) -> Self {
+        Snapshots {
+            export_path: config.export_path.clone(),
+            import_path: config.import_path.clone(),
+            trusted_hash: config.trusted_hash,
+        }
+    }
+}
+
+impl Pruning {
+    pub fn from_partial(config: &PartialPruning, defaults: &dyn Defaults) -> Self {
+        Pruning {
+            enabled: config
+                .enabled
+                .to_owned()
+                .unwrap_or_else(|| defaults.pruning_enabled()),
+            keep_epochs: config
+                .keep_epochs
+                .to_owned()
+                .unwrap_or_else(|| defaults.pruning_keep_epochs()),
+        }
+    }
+}
+
+impl Alerts {
+    pub fn from_partial(config: &PartialAlerts, defaults: &dyn Defaults) -> Self {
+        Alerts {
+            enabled: config
+                .enabled
+                .to_owned()
+                .unwrap_or_else(|| defaults.alerts_enabled()),
+            check_period: config
+                .check_period
+                .to_owned()
+                .unwrap_or_else(|| defaults.alerts_check_period()),
+            repeat_period: config
+                .repeat_period
+                .to_owned()
+                .unwrap_or_else(|| defaults.alerts_repeat_period()),
+            no_peers_timeout: config
+                .no_peers_timeout
+                .to_owned()
+                .unwrap_or_else(|| defaults.alerts_no_peers_timeout()),
+            stalled_chain_timeout: config
+                .stalled_chain_timeout
+                .to_owned()
+                .unwrap_or_else(|| defaults.alerts_stalled_chain_timeout()),
+            min_free_disk_bytes: config
+                .min_free_disk_bytes
+                .to_owned()
+                .unwrap_or_else(|| defaults.alerts_min_free_disk_bytes()),
+            max_clock_drift: config
+                .max_clock_drift
+                .to_owned()
+                .unwrap_or_else(|| defaults.alerts_max_clock_drift()),
+            webhook_url: config.webhook_url.clone(),
+            command: config.command.clone(),
+        }
+    }
+}
+
+impl Ntp {
+    pub fn from_partial(config: &PartialNtp, defaults: &dyn Defaults) -> Self {
+        Ntp {
+            enabled: config
+                .enabled
+                .to_owned()
+                .unwrap_or_else(|| defaults.ntp_enabled()),
+            servers: config
+                .servers
+                .to_owned()
+                .unwrap_or_else(|| defaults.ntp_servers()),
+            update_period: config
+                .update_period
+                .to_owned()
+                .unwrap_or_else(|| defaults.ntp_update_period()),
+            max_offset: config
+                .max_offset
+                .to_owned()
+                .unwrap_or_else(|| defaults.ntp_max_offset()),
+        }
+    }
+}
+
+impl Rad {
+    pub fn from_partial(config: &PartialRad, defaults: &dyn Defaults) -> Self {
+        Rad {
+            timeout: config
+                .timeout
+                .to_owned()
+                .unwrap_or_else(|| defaults.rad_timeout()),
+            max_timeout: config
+                .max_timeout
+                .to_owned()
+                .unwrap_or_else(|| defaults.rad_max_timeout()),
+            workers: config
+                .workers
+                .to_owned()
+                .unwrap_or_else(|| defaults.rad_workers()),
+            max_retries: config
+                .max_retries
+                .to_owned()
+                .unwrap_or_else(|| defaults.rad_max_retries()),
+            retry_backoff: config
+                .retry_backoff
+                .to_owned()
+                .unwrap_or_else(|| defaults.rad_retry_backoff()),
+            max_redirects: config
+                .max_redirects
+                .to_owned()
+                .unwrap_or_else(|| defaults.rad_max_redirects()),
+            user_agent: config.user_agent.clone(),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -395,10 +1198,20 @@ mod tests {
             backend: StorageBackend::RocksDB,
             password: None,
             db_path: Some(PathBuf::from("other")),
+            block_cache_bytes: Some(1024),
+            write_buffer_bytes: None,
+            compaction_style: CompactionStyle::Fifo,
+            max_background_compactions: None,
         };
         let config = Storage::from_partial(&partial_config, &Testnet1);
 
         assert_eq!(config.db_path.to_str(), Some("other"));
+        assert_eq!(config.block_cache_bytes, 1024);
+        assert_eq!(
+            config.write_buffer_bytes,
+            Testnet1.storage_write_buffer_bytes()
+        );
+        assert_eq!(config.compaction_style, CompactionStyle::Fifo);
     }
 
     #[test]
@@ -410,6 +1223,10 @@ mod tests {
         assert_eq!(config.inbound_limit, Testnet1.connections_inbound_limit());
         assert_eq!(config.outbound_limit, Testnet1.connections_outbound_limit());
         assert_eq!(config.known_peers, Testnet1.connections_known_peers());
+        assert!(config.seed_hosts.is_empty());
+        assert_eq!(config.dns_seeds, Testnet1.connections_dns_seeds());
+        assert_eq!(config.fallback_seeds, Testnet1.connections_fallback_seeds());
+        assert!(config.listeners.is_empty());
         assert_eq!(
             config.bootstrap_peers_period,
             Testnet1.connections_bootstrap_peers_period()
@@ -427,21 +1244,62 @@ mod tests {
             Testnet1.connections_handshake_timeout()
         );
         assert_eq!(config.blocks_timeout, Testnet1.connections_blocks_timeout());
+        assert_eq!(config.lan_discovery, Testnet1.connections_lan_discovery());
+        assert_eq!(config.port_mapping, Testnet1.connections_port_mapping());
+        assert_eq!(config.port_mapping_gateway, None);
+        assert_eq!(
+            config.encrypted_transport,
+            Testnet1.connections_encrypted_transport()
+        );
+        assert_eq!(config.proxy, None);
     }
 
     #[test]
     fn test_connections_from_partial() {
         let addr: SocketAddr = "127.0.0.1:3000".parse().unwrap();
+        let listener = Listener {
+            addr: "[::]:3000".parse().unwrap(),
+            inbound_limit: Some(2),
+            whitelist: vec![addr.ip()],
+        };
         let partial_config = PartialConnections {
             server_addr: Some(addr),
             inbound_limit: Some(3),
             outbound_limit: Some(4),
             known_peers: [addr].iter().cloned().collect(),
+            seed_hosts: Some(vec!["seed.witnet.io:21337".to_string()]),
+            dns_seeds: Some(vec!["dnsseed.witnet.io".to_string()]),
+            fallback_seeds: Some(vec![addr]),
+            listeners: vec![listener.clone()],
             bootstrap_peers_period: Some(Duration::from_secs(10)),
             storage_peers_period: Some(Duration::from_secs(60)),
             discovery_peers_period: Some(Duration::from_secs(100)),
             handshake_timeout: Some(Duration::from_secs(3)),
             blocks_timeout: Some(5),
+            user_agent_suffix: Some("operator".to_string()),
+            min_peer_version: Some(2),
+            avoid_user_agents: Some(vec!["buggy/*".to_string()]),
+            prefer_user_agents: Some(vec!["buggy/0.2.1".to_string()]),
+            lan_discovery: Some(true),
+            port_mapping: Some(true),
+            port_mapping_gateway: Some(Ipv4Addr::new(192, 168, 0, 1)),
+            encrypted_transport: Some(true),
+            ping_interval: Some(Duration::from_secs(20)),
+            max_missed_pongs: Some(5),
+            gossip_max_addresses: Some(100),
+            gossip_max_address_age: Some(Duration::from_secs(3600)),
+            gossip_interval: Some(Duration::from_secs(60)),
+            feeler_period: Some(Duration::from_secs(300)),
+            feeler_max_failures: Some(5),
+            consensus_percentage: Some(66),
+            max_upload_rate: Some(100_000),
+            max_download_rate: Some(200_000),
+            max_total_upload_rate: Some(1_000_000),
+            max_total_download_rate: Some(2_000_000),
+            dual_stack: Some(false),
+            whitelist: Some(vec!["10.0.0.0/8".to_string()]),
+            blacklist: Some(vec!["10.0.0.1".to_string()]),
+            proxy: Some("127.0.0.1:9050".parse().unwrap()),
         };
         let config = Connections::from_partial(&partial_config, &Testnet1);
 
@@ -449,11 +1307,42 @@ mod tests {
         assert_eq!(config.inbound_limit, 3);
         assert_eq!(config.outbound_limit, 4);
         assert!(config.known_peers.contains(&addr));
+        assert_eq!(config.seed_hosts, vec!["seed.witnet.io:21337".to_string()]);
+        assert_eq!(config.dns_seeds, vec!["dnsseed.witnet.io".to_string()]);
+        assert_eq!(config.fallback_seeds, vec![addr]);
+        assert_eq!(config.listeners, vec![listener]);
         assert_eq!(config.bootstrap_peers_period, Duration::from_secs(10));
         assert_eq!(config.storage_peers_period, Duration::from_secs(60));
         assert_eq!(config.discovery_peers_period, Duration::from_secs(100));
         assert_eq!(config.handshake_timeout, Duration::from_secs(3));
         assert_eq!(config.blocks_timeout, 5);
+        assert_eq!(config.user_agent_suffix, "operator");
+        assert_eq!(config.min_peer_version, 2);
+        assert_eq!(config.avoid_user_agents, vec!["buggy/*".to_string()]);
+        assert_eq!(config.prefer_user_agents, vec!["buggy/0.2.1".to_string()]);
+        assert!(config.lan_discovery);
+        assert!(config.port_mapping);
+        assert_eq!(
+            config.port_mapping_gateway,
+            Some(Ipv4Addr::new(192, 168, 0, 1))
+        );
+        assert!(config.encrypted_transport);
+        assert_eq!(config.ping_interval, Duration::from_secs(20));
+        assert_eq!(config.max_missed_pongs, 5);
+        assert_eq!(config.gossip_max_addresses, 100);
+        assert_eq!(config.gossip_max_address_age, Duration::from_secs(3600));
+        assert_eq!(config.gossip_interval, Duration::from_secs(60));
+        assert_eq!(config.feeler_period, Duration::from_secs(300));
+        assert_eq!(config.feeler_max_failures, 5);
+        assert_eq!(config.consensus_percentage, 66);
+        assert_eq!(config.max_upload_rate, 100_000);
+        assert_eq!(config.max_download_rate, 200_000);
+        assert_eq!(config.max_total_upload_rate, 1_000_000);
+        assert_eq!(config.max_total_download_rate, 2_000_000);
+        assert!(!config.dual_stack);
+        assert_eq!(config.whitelist, vec!["10.0.0.0/8".to_string()]);
+        assert_eq!(config.blacklist, vec!["10.0.0.1".to_string()]);
+        assert_eq!(config.proxy, Some("127.0.0.1:9050".parse().unwrap()));
     }
 
     #[test]
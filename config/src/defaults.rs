@@ -7,7 +7,7 @@ use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::path::PathBuf;
 use std::time::Duration;
 
-use witnet_data_structures::chain::Hash;
+use witnet_data_structures::chain::{Environment, Hash, MAX_BLOCK_WEIGHT};
 
 // When changing the defaults, remember to update the documentation!
 // https://github.com/witnet/witnet-rust/blob/master/docs/configuration/toml-file.md
@@ -38,6 +38,21 @@ pub trait Defaults {
     /// Default path for the database
     fn storage_db_path(&self) -> PathBuf;
 
+    /// Default cache of uncompressed blocks of the database: 32 MiB
+    fn storage_block_cache_bytes(&self) -> usize {
+        32 * 1024 * 1024
+    }
+
+    /// Default memory buffer of each column family of the database: 64 MiB
+    fn storage_write_buffer_bytes(&self) -> usize {
+        64 * 1024 * 1024
+    }
+
+    /// Default number of concurrent background compactions of the database
+    fn storage_max_background_compactions(&self) -> u32 {
+        2
+    }
+
     /// Default period for bootstrap peers
     fn connections_bootstrap_peers_period(&self) -> Duration {
         Duration::from_secs(5)
@@ -97,9 +112,165 @@ pub trait Defaults {
         true
     }
 
+    /// Default memory cap for the transactions pool: 64 MiB
+    fn memory_mempool_max_bytes(&self) -> usize {
+        64 * 1024 * 1024
+    }
+
+    /// Default memory cap for the block candidates: 16 MiB
+    fn memory_candidates_max_bytes(&self) -> usize {
+        16 * 1024 * 1024
+    }
+
+    /// Default memory cap for the known peers: 1 MiB
+    fn memory_peers_max_bytes(&self) -> usize {
+        1024 * 1024
+    }
+
+    /// Default memory cap for the signature cache: 2 MiB
+    fn memory_signature_cache_max_bytes(&self) -> usize {
+        2 * 1024 * 1024
+    }
+
+    /// Default memory cap for the orphan blocks: 16 MiB
+    fn memory_orphans_max_bytes(&self) -> usize {
+        16 * 1024 * 1024
+    }
+
+    /// Default memory cap for the headers and blocks of a synchronization: 32 MiB
+    fn memory_sync_max_bytes(&self) -> usize {
+        32 * 1024 * 1024
+    }
+
+    /// Default weight cap for the transactions pool: the weight of 100 blocks
+    fn mempool_max_weight(&self) -> u64 {
+        100 * u64::from(MAX_BLOCK_WEIGHT)
+    }
+
+    /// Default number of epochs before an unconfirmed transaction expires: 960, one day with
+    /// 90 second epochs
+    fn mempool_expiry_epochs(&self) -> u32 {
+        960
+    }
+
+    /// Default minimum relay fee: 1 nanowit per unit of weight
+    fn mempool_min_relay_fee(&self) -> u64 {
+        1000
+    }
+
+    /// No public keys trusted to sign checkpoints by default
+    fn checkpoints_trusted_keys(&self) -> Vec<String> {
+        vec![]
+    }
+
+    /// Default period for fetching the signed checkpoints: 10 minutes
+    fn checkpoints_poll_period(&self) -> Duration {
+        Duration::from_secs(600)
+    }
+
+    /// Pruning of old blocks, disabled by default
+    fn pruning_enabled(&self) -> bool {
+        false
+    }
+
+    /// Default number of epochs whose blocks are kept whole when pruning: 2880, three days with
+    /// 90 second epochs
+    fn pruning_keep_epochs(&self) -> u32 {
+        2880
+    }
+
+    /// AlertsManager, enabled by default
+    fn alerts_enabled(&self) -> bool {
+        true
+    }
+
+    /// Default period for checking the alert conditions: 1 minute
+    fn alerts_check_period(&self) -> Duration {
+        Duration::from_secs(60)
+    }
+
+    /// Default period for repeating an alert while its condition persists: 1 hour
+    fn alerts_repeat_period(&self) -> Duration {
+        Duration::from_secs(3600)
+    }
+
+    /// Default time without peers before alerting: 10 minutes
+    fn alerts_no_peers_timeout(&self) -> Duration {
+        Duration::from_secs(600)
+    }
+
+    /// Default time without changes in the chain tip before alerting: 30 minutes
+    fn alerts_stalled_chain_timeout(&self) -> Duration {
+        Duration::from_secs(1800)
+    }
+
+    /// Default minimum free disk space: 1 GiB
+    fn alerts_min_free_disk_bytes(&self) -> u64 {
+        1024 * 1024 * 1024
+    }
+
+    /// Default maximum clock drift with respect to the peers: 10 seconds
+    fn alerts_max_clock_drift(&self) -> Duration {
+        Duration::from_secs(10)
+    }
+
+    /// Clock synchronization with NTP servers, enabled by default
+    fn ntp_enabled(&self) -> bool {
+        true
+    }
+
+    /// Default NTP servers
+    fn ntp_servers(&self) -> Vec<String> {
+        vec![
+            "0.pool.ntp.org:123".to_string(),
+            "1.pool.ntp.org:123".to_string(),
+            "2.pool.ntp.org:123".to_string(),
+        ]
+    }
+
+    /// Default period for updating the clock offset: 10 minutes
+    fn ntp_update_period(&self) -> Duration {
+        Duration::from_secs(600)
+    }
+
+    /// Default clock offset above which a warning is logged: 2 seconds
+    fn ntp_max_offset(&self) -> Duration {
+        Duration::from_secs(2)
+    }
+
+    /// Default timeout of every attempt to retrieve a source: 10 seconds
+    fn rad_timeout(&self) -> Duration {
+        Duration::from_secs(10)
+    }
+
+    /// Default upper bound of the timeout of every attempt to retrieve a source: 60 seconds
+    fn rad_max_timeout(&self) -> Duration {
+        Duration::from_secs(60)
+    }
+
+    /// Default number of threads retrieving sources: 4
+    fn rad_workers(&self) -> usize {
+        4
+    }
+
+    /// Default maximum number of retries of a retrieval: 2
+    fn rad_max_retries(&self) -> u32 {
+        2
+    }
+
+    /// Default delay before the first retry of a retrieval: 500 milliseconds
+    fn rad_retry_backoff(&self) -> Duration {
+        Duration::from_millis(500)
+    }
+
+    /// Default maximum number of redirects followed by a retrieval: 5
+    fn rad_max_redirects(&self) -> usize {
+        5
+    }
+
     fn consensus_constants_max_block_weight(&self) -> u32 {
         // TODO: Replace  with real max_block_weight value used in mainnet
-        10_000
+        MAX_BLOCK_WEIGHT
     }
 
     /// Default number of seconds before giving up waiting for requested blocks: `400`.
@@ -107,6 +278,136 @@ pub trait Defaults {
     fn connections_blocks_timeout(&self) -> i64 {
         400
     }
+
+    /// Default user agent suffix: none
+    fn connections_user_agent_suffix(&self) -> String {
+        String::new()
+    }
+
+    /// Default minimum protocol version of the peers: none on top of the consensus constant
+    fn connections_min_peer_version(&self) -> u32 {
+        0
+    }
+
+    /// Default minimum protocol version of the peers: 1, the first version
+    fn consensus_constants_min_protocol_version(&self) -> u32 {
+        1
+    }
+
+    /// Default seed hosts: none
+    fn connections_seed_hosts(&self) -> Vec<String> {
+        vec![]
+    }
+
+    /// Default DNS seeds: none
+    fn connections_dns_seeds(&self) -> Vec<String> {
+        vec![]
+    }
+
+    /// Default hardcoded seeds used when the DNS seeds fail: none
+    fn connections_fallback_seeds(&self) -> Vec<SocketAddr> {
+        vec![]
+    }
+
+    /// Default user agent patterns to avoid: none
+    fn connections_avoid_user_agents(&self) -> Vec<String> {
+        vec![]
+    }
+
+    /// Default preferred user agent patterns: none
+    fn connections_prefer_user_agents(&self) -> Vec<String> {
+        vec![]
+    }
+
+    /// LAN discovery using mDNS, disabled by default
+    fn connections_lan_discovery(&self) -> bool {
+        false
+    }
+
+    /// Port mapping using NAT-PMP, disabled by default
+    fn connections_port_mapping(&self) -> bool {
+        false
+    }
+
+    /// Encrypted transport of the sessions, disabled by default
+    fn connections_encrypted_transport(&self) -> bool {
+        false
+    }
+
+    /// Default period between the pings sent to each peer
+    fn connections_ping_interval(&self) -> Duration {
+        Duration::from_secs(30)
+    }
+
+    /// Default number of consecutive missed pings before disconnecting a peer
+    fn connections_max_missed_pongs(&self) -> u32 {
+        3
+    }
+
+    /// Default maximum number of addresses sent in answer to a `GetPeers` message
+    fn connections_gossip_max_addresses(&self) -> usize {
+        250
+    }
+
+    /// Default maximum age of the addresses sent to other peers
+    fn connections_gossip_max_address_age(&self) -> Duration {
+        Duration::from_secs(24 * 60 * 60)
+    }
+
+    /// Default minimum time between two `GetPeers` messages answered to the same peer
+    fn connections_gossip_interval(&self) -> Duration {
+        Duration::from_secs(10)
+    }
+
+    /// Default maximum upload rate of each session, no limit
+    fn connections_max_upload_rate(&self) -> u64 {
+        0
+    }
+
+    /// Default maximum download rate of each session, no limit
+    fn connections_max_download_rate(&self) -> u64 {
+        0
+    }
+
+    /// Default maximum upload rate of all the sessions together, no limit
+    fn connections_max_total_upload_rate(&self) -> u64 {
+        0
+    }
+
+    /// Default maximum download rate of all the sessions together, no limit
+    fn connections_max_total_download_rate(&self) -> u64 {
+        0
+    }
+
+    /// Dual-stack IPv6 listeners, enabled by default
+    fn connections_dual_stack(&self) -> bool {
+        true
+    }
+
+    /// Default IP ranges connected to: all of them
+    fn connections_whitelist(&self) -> Vec<String> {
+        vec![]
+    }
+
+    /// Default IP ranges never connected to: none
+    fn connections_blacklist(&self) -> Vec<String> {
+        vec![]
+    }
+
+    /// Default period between the feeler connections
+    fn connections_feeler_period(&self) -> Duration {
+        Duration::from_secs(120)
+    }
+
+    /// Default number of consecutive failed feeler connections before forgetting a peer
+    fn connections_feeler_max_failures(&self) -> u32 {
+        3
+    }
+
+    /// Default percentage of the outbound peers needed for the beacons consensus
+    fn connections_consensus_percentage(&self) -> u32 {
+        51
+    }
 }
 
 /// Struct that will implement all the mainnet defaults
@@ -115,9 +416,15 @@ pub struct Mainnet;
 /// Struct that will implement all the testnet-1 defaults
 pub struct Testnet1;
 
+/// Struct that will implement all the development defaults
+pub struct Development;
+
 impl Defaults for Mainnet {
     fn connections_server_addr(&self) -> SocketAddr {
-        SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 11337)
+        SocketAddr::new(
+            IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
+            Environment::Mainnet.default_port(),
+        )
     }
 
     fn jsonrpc_server_address(&self) -> SocketAddr {
@@ -137,7 +444,10 @@ impl Defaults for Mainnet {
 
 impl Defaults for Testnet1 {
     fn connections_server_addr(&self) -> SocketAddr {
-        SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 21337)
+        SocketAddr::new(
+            IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
+            Environment::Testnet1.default_port(),
+        )
     }
 
     fn jsonrpc_server_address(&self) -> SocketAddr {
@@ -158,3 +468,28 @@ impl Defaults for Testnet1 {
         1_548_855_420
     }
 }
+
+impl Defaults for Development {
+    fn connections_server_addr(&self) -> SocketAddr {
+        SocketAddr::new(
+            IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
+            Environment::Development.default_port(),
+        )
+    }
+
+    fn jsonrpc_server_address(&self) -> SocketAddr {
+        SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 31338)
+    }
+
+    fn storage_db_path(&self) -> PathBuf {
+        PathBuf::from(".witnet-rust-development")
+    }
+
+    fn connections_bootstrap_peers_period(&self) -> Duration {
+        Duration::from_secs(15)
+    }
+
+    fn consensus_constants_checkpoint_zero_timestamp(&self) -> i64 {
+        1_548_855_420
+    }
+}
@@ -98,9 +98,11 @@ inbound_limit = 999
     #[test]
     fn test_configure_environment() {
         let config = super::from_str("environment = 'mainnet'").unwrap();
+        let development = super::from_str("environment = 'development'").unwrap();
         let result = super::from_str("environment = 'wrong'");
 
         assert_eq!(config.environment, Environment::Mainnet);
+        assert_eq!(development.environment, Environment::Development);
         assert!(result.is_err());
     }
 
@@ -112,6 +114,11 @@ inbound_limit = 999
 [connections]
 server_addr = '127.0.0.1:1234'
 known_peers = ['192.168.1.12:1234']
+
+[[connections.listeners]]
+addr = '[::]:1234'
+inbound_limit = 8
+whitelist = ['192.168.1.12']
     ",
         )
         .unwrap();
@@ -123,6 +130,12 @@ known_peers = ['192.168.1.12:1234']
             Some("127.0.0.1:1234".parse().unwrap())
         );
         assert_eq!(config.connections.known_peers.len(), 1);
+        assert_eq!(config.connections.listeners.len(), 1);
+        assert_eq!(
+            config.connections.listeners[0].addr,
+            "[::]:1234".parse().unwrap()
+        );
+        assert_eq!(config.connections.listeners[0].inbound_limit, Some(8));
     }
 
     #[test]
@@ -132,12 +145,20 @@ known_peers = ['192.168.1.12:1234']
             r"
 [storage]
 db_path = 'dbfiles'
+block_cache_bytes = 1024
+write_buffer_bytes = 2048
+compaction_style = 'universal'
+max_background_compactions = 4
     ",
         )
         .unwrap();
 
         assert_eq!(empty_config.storage, PartialStorage::default());
         assert_eq!(config.storage.db_path, Some(PathBuf::from("dbfiles")));
+        assert_eq!(config.storage.block_cache_bytes, Some(1024));
+        assert_eq!(config.storage.write_buffer_bytes, Some(2048));
+        assert_eq!(config.storage.compaction_style, CompactionStyle::Universal);
+        assert_eq!(config.storage.max_background_compactions, Some(4));
     }
 
     #[test]
@@ -224,4 +245,185 @@ enabled = false
         assert_eq!(empty_config.mining, PartialMining::default());
         assert_eq!(config_disabled.mining.enabled, Some(false),);
     }
+
+    #[test]
+    fn test_configure_memory() {
+        let empty_config = super::from_str("[memory]").unwrap();
+        let config = super::from_str(
+            r"
+[memory]
+mempool_max_bytes = 1048576
+peers_max_bytes = 0
+orphans_max_bytes = 2097152
+    ",
+        )
+        .unwrap();
+
+        assert_eq!(empty_config.memory, PartialMemory::default());
+        assert_eq!(config.memory.mempool_max_bytes, Some(1_048_576));
+        assert_eq!(config.memory.candidates_max_bytes, None);
+        assert_eq!(config.memory.peers_max_bytes, Some(0));
+        assert_eq!(config.memory.signature_cache_max_bytes, None);
+        assert_eq!(config.memory.orphans_max_bytes, Some(2_097_152));
+        assert_eq!(config.memory.sync_max_bytes, None);
+    }
+
+    #[test]
+    fn test_configure_checkpoints() {
+        use std::time::Duration;
+
+        let empty_config = super::from_str("[checkpoints]").unwrap();
+        let config = super::from_str(
+            r#"
+[checkpoints]
+feed_url = "https://checkpoints.example.com/testnet-1.json"
+trusted_keys = ["02aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"]
+poll_period_seconds = 60
+    "#,
+        )
+        .unwrap();
+
+        assert_eq!(empty_config.checkpoints, PartialCheckpoints::default());
+        assert_eq!(
+            config.checkpoints.feed_url,
+            Some("https://checkpoints.example.com/testnet-1.json".to_string())
+        );
+        assert_eq!(
+            config.checkpoints.trusted_keys,
+            Some(vec![format!("02{}", "a".repeat(64))])
+        );
+        assert_eq!(
+            config.checkpoints.poll_period,
+            Some(Duration::from_secs(60))
+        );
+    }
+
+    #[test]
+    fn test_configure_snapshots() {
+        use std::path::PathBuf;
+
+        let empty_config = super::from_str("[snapshots]").unwrap();
+        let config = super::from_str(
+            r#"
+[snapshots]
+export_path = "/var/lib/witnet/snapshots"
+import_path = "chain_state_1230.json"
+trusted_hash = "3a5f1b6c8d2e4f60718293a4b5c6d7e8f90a1b2c3d4e5f60718293a4b5c6d7e8"
+    "#,
+        )
+        .unwrap();
+
+        assert_eq!(empty_config.snapshots, PartialSnapshots::default());
+        assert_eq!(
+            config.snapshots.export_path,
+            Some(PathBuf::from("/var/lib/witnet/snapshots"))
+        );
+        assert_eq!(
+            config.snapshots.import_path,
+            Some(PathBuf::from("chain_state_1230.json"))
+        );
+        assert_eq!(
+            config.snapshots.trusted_hash.map(|hash| hash.to_string()),
+            Some("3a5f1b6c8d2e4f60718293a4b5c6d7e8f90a1b2c3d4e5f60718293a4b5c6d7e8".to_string())
+        );
+    }
+
+    #[test]
+    fn test_configure_pruning() {
+        let empty_config = super::from_str("[pruning]").unwrap();
+        let config = super::from_str(
+            r#"
+[pruning]
+enabled = true
+keep_epochs = 960
+    "#,
+        )
+        .unwrap();
+
+        assert_eq!(empty_config.pruning, PartialPruning::default());
+        assert_eq!(config.pruning.enabled, Some(true));
+        assert_eq!(config.pruning.keep_epochs, Some(960));
+    }
+
+    #[test]
+    fn test_configure_alerts() {
+        use std::time::Duration;
+
+        let empty_config = super::from_str("[alerts]").unwrap();
+        let config = super::from_str(
+            r#"
+[alerts]
+enabled = true
+stalled_chain_timeout_seconds = 900
+min_free_disk_bytes = 0
+command = "mail -s \"$WITNET_ALERT_KIND\" ops@example.com"
+    "#,
+        )
+        .unwrap();
+
+        assert_eq!(empty_config.alerts, PartialAlerts::default());
+        assert_eq!(config.alerts.enabled, Some(true));
+        assert_eq!(
+            config.alerts.stalled_chain_timeout,
+            Some(Duration::from_secs(900))
+        );
+        assert_eq!(config.alerts.check_period, None);
+        assert_eq!(config.alerts.min_free_disk_bytes, Some(0));
+        assert_eq!(config.alerts.webhook_url, None);
+        assert_eq!(
+            config.alerts.command,
+            Some("mail -s \"$WITNET_ALERT_KIND\" ops@example.com".to_string())
+        );
+    }
+
+    #[test]
+    fn test_configure_ntp() {
+        use std::time::Duration;
+
+        let empty_config = super::from_str("[ntp]").unwrap();
+        let config = super::from_str(
+            r#"
+[ntp]
+servers = ["time.example.com:123"]
+update_period_seconds = 60
+    "#,
+        )
+        .unwrap();
+
+        assert_eq!(empty_config.ntp, PartialNtp::default());
+        assert_eq!(config.ntp.enabled, None);
+        assert_eq!(
+            config.ntp.servers,
+            Some(vec!["time.example.com:123".to_string()])
+        );
+        assert_eq!(config.ntp.update_period, Some(Duration::from_secs(60)));
+        assert_eq!(config.ntp.max_offset, None);
+    }
+
+    #[test]
+    fn test_configure_rad() {
+        use std::time::Duration;
+
+        let empty_config = super::from_str("[rad]").unwrap();
+        let config = super::from_str(
+            r#"
+[rad]
+timeout_seconds = 5
+max_timeout_seconds = 30
+workers = 8
+retry_backoff_milliseconds = 250
+user_agent = "my-witness"
+    "#,
+        )
+        .unwrap();
+
+        assert_eq!(empty_config.rad, PartialRad::default());
+        assert_eq!(config.rad.timeout, Some(Duration::from_secs(5)));
+        assert_eq!(config.rad.max_timeout, Some(Duration::from_secs(30)));
+        assert_eq!(config.rad.workers, Some(8));
+        assert_eq!(config.rad.max_retries, None);
+        assert_eq!(config.rad.retry_backoff, Some(Duration::from_millis(250)));
+        assert_eq!(config.rad.max_redirects, None);
+        assert_eq!(config.rad.user_agent, Some("my-witness".to_string()));
+    }
 }
@@ -0,0 +1,259 @@
+//! Network crawling logic
+//!
+//! Every node is visited with a short-lived TCP connection which speaks the
+//! regular P2P protocol: the messages are framed with the node's
+//! [`P2PCodec`](witnet_node::actors::codec::P2PCodec).
+
+use std::collections::{HashSet, VecDeque};
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::time::{Duration, Instant};
+
+use failure::Fail;
+use log::{debug, info};
+use tokio::codec::{Decoder, Encoder};
+
+use witnet_data_structures::{
+    builders::from_address,
+    chain::Hash,
+    proto::ProtobufConvert,
+    types::{Command, Message, Peers, Version},
+};
+use witnet_node::{
+    actors::codec::{BytesMut, P2PCodec},
+    build_info,
+};
+use witnet_util::timestamp::get_timestamp;
+
+use crate::report::{CrawlReport, NodeReport};
+
+/// Errors found while talking to a node
+#[derive(Debug, Fail)]
+pub enum CrawlerError {
+    /// The node closed the connection
+    #[fail(display = "Connection closed by peer")]
+    ConnectionClosed,
+    /// The node belongs to a different network
+    #[fail(
+        display = "Magic number mismatch (expected {}, received {})",
+        expected, received
+    )]
+    MagicMismatch { expected: u16, received: u16 },
+    /// The node did not answer in time
+    #[fail(display = "Timeout while waiting for {}", _0)]
+    Timeout(&'static str),
+}
+
+/// Network crawler
+pub struct Crawler {
+    magic: u16,
+    consensus_constants: Hash,
+    timeout: Duration,
+    max_nodes: usize,
+}
+
+impl Crawler {
+    /// Create a new crawler for the network identified by `magic` and the hash of its
+    /// `consensus_constants`
+    pub fn new(magic: u16, consensus_constants: Hash, timeout: Duration, max_nodes: usize) -> Self {
+        Crawler {
+            magic,
+            consensus_constants,
+            timeout,
+            max_nodes,
+        }
+    }
+
+    /// Walk the network starting from the given seeds, breadth first
+    pub fn crawl(&self, seeds: Vec<SocketAddr>) -> CrawlReport {
+        let started = get_timestamp();
+        // Addresses which have been visited or are waiting in the queue
+        let mut seen: HashSet<SocketAddr> = seeds.iter().cloned().collect();
+        let mut queue: VecDeque<SocketAddr> = seeds.into_iter().collect();
+        let mut nodes = vec![];
+
+        while let Some(address) = queue.pop_front() {
+            if nodes.len() >= self.max_nodes {
+                break;
+            }
+
+            let (report, peers) = self.visit(address);
+            info!(
+                "Visited {} ({}), {} peers",
+                address,
+                report
+                    .user_agent
+                    .as_ref()
+                    .map_or("unreachable", String::as_str),
+                peers.len()
+            );
+            nodes.push(report);
+
+            for peer in peers {
+                if seen.insert(peer) {
+                    queue.push_back(peer);
+                }
+            }
+        }
+
+        CrawlReport::new(started, get_timestamp(), nodes)
+    }
+
+    /// Visit a node and return its report along with the peers it knows
+    fn visit(&self, address: SocketAddr) -> (NodeReport, Vec<SocketAddr>) {
+        let timestamp = get_timestamp();
+        let mut connection =
+            match Connection::open(address, self.magic, self.consensus_constants, self.timeout) {
+                Ok(connection) => connection,
+                Err(e) => {
+                    return (
+                        NodeReport::unreachable(address, e.to_string(), timestamp),
+                        vec![],
+                    )
+                }
+            };
+
+        let version = match connection.handshake() {
+            Ok(version) => version,
+            Err(e) => {
+                return (
+                    NodeReport::unreachable(address, e.to_string(), timestamp),
+                    vec![],
+                )
+            }
+        };
+        let mut report = NodeReport::reachable(address, &version, timestamp);
+
+        match connection.get_peers() {
+            Ok(peers) => {
+                report.peers = peers.len();
+                (report, peers)
+            }
+            Err(e) => {
+                debug!("Could not get peers from {}: {}", address, e);
+                report.error = Some(e.to_string());
+                (report, vec![])
+            }
+        }
+    }
+}
+
+/// Blocking P2P connection to a single node
+struct Connection {
+    stream: TcpStream,
+    buffer: BytesMut,
+    magic: u16,
+    consensus_constants: Hash,
+    local_addr: SocketAddr,
+    remote_addr: SocketAddr,
+    timeout: Duration,
+}
+
+impl Connection {
+    /// Connect to a node
+    fn open(
+        address: SocketAddr,
+        magic: u16,
+        consensus_constants: Hash,
+        timeout: Duration,
+    ) -> Result<Self, failure::Error> {
+        let stream = TcpStream::connect_timeout(&address, timeout)?;
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_write_timeout(Some(timeout))?;
+        let local_addr = stream.local_addr()?;
+
+        Ok(Connection {
+            stream,
+            buffer: BytesMut::new(),
+            magic,
+            consensus_constants,
+            local_addr,
+            remote_addr: address,
+            timeout,
+        })
+    }
+
+    /// Exchange `Version` and `Verack` messages, returning the `Version` of the node
+    fn handshake(&mut self) -> Result<Version, failure::Error> {
+        let mut version_msg = Message::build_version(
+            self.magic,
+            self.local_addr,
+            self.remote_addr,
+            build_info::user_agent("witnet-crawler"),
+            0,
+        );
+        // Nodes reject the peers with other consensus constants
+        if let Command::Version(version) = &mut version_msg.kind {
+            version.consensus_constants = self.consensus_constants.as_ref().to_vec();
+        }
+        self.send(&version_msg)?;
+
+        let deadline = Instant::now() + self.timeout;
+        let mut version = None;
+        let mut verack = false;
+        while version.is_none() || !verack {
+            match self.receive(deadline, "handshake")?.kind {
+                Command::Version(v) => {
+                    self.send(&Message::build_verack(self.magic))?;
+                    version = Some(v);
+                }
+                Command::Verack(_) => verack = true,
+                // Ignore any other message until the handshake is finished
+                _ => {}
+            }
+        }
+
+        Ok(version.unwrap())
+    }
+
+    /// Send a `GetPeers` message and wait for the `Peers` response
+    fn get_peers(&mut self) -> Result<Vec<SocketAddr>, failure::Error> {
+        self.send(&Message::build_get_peers(self.magic))?;
+
+        let deadline = Instant::now() + self.timeout;
+        loop {
+            if let Command::Peers(Peers { peers }) = self.receive(deadline, "peers")?.kind {
+                return Ok(peers.iter().map(from_address).collect());
+            }
+        }
+    }
+
+    fn send(&mut self, msg: &Message) -> Result<(), failure::Error> {
+        let mut bytes = BytesMut::new();
+        P2PCodec.encode(BytesMut::from(msg.to_pb_bytes()?), &mut bytes)?;
+        self.stream.write_all(&bytes)?;
+
+        Ok(())
+    }
+
+    fn receive(
+        &mut self,
+        deadline: Instant,
+        waiting_for: &'static str,
+    ) -> Result<Message, failure::Error> {
+        loop {
+            if let Some(bytes) = P2PCodec.decode(&mut self.buffer)? {
+                let msg = Message::from_pb_bytes(&bytes)?;
+                if msg.magic != self.magic {
+                    Err(CrawlerError::MagicMismatch {
+                        expected: self.magic,
+                        received: msg.magic,
+                    })?
+                }
+
+                return Ok(msg);
+            }
+
+            if Instant::now() > deadline {
+                Err(CrawlerError::Timeout(waiting_for))?
+            }
+
+            let mut chunk = [0; 4096];
+            let read = self.stream.read(&mut chunk)?;
+            if read == 0 {
+                Err(CrawlerError::ConnectionClosed)?
+            }
+            self.buffer.extend_from_slice(&chunk[..read]);
+        }
+    }
+}
@@ -0,0 +1,118 @@
+//! Witnet network crawler
+//!
+//! Walks the P2P network by performing `Version`/`Verack` handshakes and
+//! `GetPeers` requests, and writes a JSON report with the version,
+//! capabilities and reachability of every node found.
+#![deny(rust_2018_idioms)]
+#![deny(non_upper_case_globals)]
+#![deny(non_camel_case_types)]
+#![deny(non_snake_case)]
+#![deny(unused_mut)]
+
+use std::fs::File;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::process::exit;
+use std::time::Duration;
+
+use log::info;
+use structopt::StructOpt;
+
+use witnet_config::{config::Config, loaders::toml};
+use witnet_data_structures::chain::Hashable;
+
+mod crawler;
+mod report;
+
+/// Witnet network crawler
+#[derive(Debug, StructOpt)]
+#[structopt(name = "witnet-crawler")]
+struct Cli {
+    #[structopt(
+        name = "config",
+        long = "config",
+        short = "c",
+        help = "Path to the configuration file"
+    )]
+    #[structopt(parse(from_os_str))]
+    config: Option<PathBuf>,
+    #[structopt(
+        name = "seed",
+        long = "seed",
+        short = "s",
+        help = "Address of a peer to start crawling from (defaults to the known peers in the configuration)"
+    )]
+    seeds: Vec<SocketAddr>,
+    #[structopt(
+        name = "max-nodes",
+        long = "max-nodes",
+        default_value = "1000",
+        help = "Maximum number of nodes to visit"
+    )]
+    max_nodes: usize,
+    #[structopt(
+        name = "timeout",
+        long = "timeout",
+        default_value = "5",
+        help = "Timeout in seconds for every connection"
+    )]
+    timeout: u64,
+    #[structopt(
+        name = "output",
+        long = "output",
+        short = "o",
+        help = "Path of the JSON report (defaults to stdout)"
+    )]
+    #[structopt(parse(from_os_str))]
+    output: Option<PathBuf>,
+}
+
+fn main() {
+    init_logger();
+
+    if let Err(e) = run(Cli::from_args()) {
+        eprintln!("Error: {}", e);
+        for cause in e.iter_causes() {
+            eprintln!("Cause: {}", cause);
+        }
+        exit(1);
+    }
+}
+
+fn run(cli: Cli) -> Result<(), failure::Error> {
+    let config = match cli.config {
+        Some(path) => Config::from_partial(&toml::from_file(&path)?),
+        None => Config::default(),
+    };
+    let seeds = if cli.seeds.is_empty() {
+        config.connections.known_peers.iter().cloned().collect()
+    } else {
+        cli.seeds
+    };
+
+    let crawler = crawler::Crawler::new(
+        config.environment.magic(),
+        config.consensus_constants.hash(),
+        Duration::from_secs(cli.timeout),
+        cli.max_nodes,
+    );
+    let report = crawler.crawl(seeds);
+    info!(
+        "Crawled {} nodes, {} of them reachable",
+        report.summary.nodes, report.summary.reachable
+    );
+
+    match cli.output {
+        Some(path) => serde_json::to_writer_pretty(File::create(path)?, &report)?,
+        None => println!("{}", serde_json::to_string_pretty(&report)?),
+    }
+
+    Ok(())
+}
+
+fn init_logger() {
+    let env = env_logger::Env::default().default_filter_or("info");
+    let mut logger = env_logger::Builder::from_env(env);
+
+    logger.init();
+}
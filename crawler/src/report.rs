@@ -0,0 +1,180 @@
+//! Crawl report data structures
+
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+
+use serde::{Deserialize, Serialize};
+
+use witnet_data_structures::types::Version;
+
+/// Information gathered about a single node
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeReport {
+    /// Address of the node
+    pub address: SocketAddr,
+    /// Whether the handshake with the node succeeded
+    pub reachable: bool,
+    /// Protocol version announced by the node
+    pub version: Option<u32>,
+    /// User agent announced by the node
+    pub user_agent: Option<String>,
+    /// Capabilities announced by the node
+    pub capabilities: Option<u64>,
+    /// Last epoch announced by the node
+    pub last_epoch: Option<u32>,
+    /// Number of peers returned by the node
+    pub peers: usize,
+    /// Error found while crawling the node, if any
+    pub error: Option<String>,
+    /// Timestamp of the visit
+    pub timestamp: i64,
+}
+
+impl NodeReport {
+    /// Report of a node which completed the handshake
+    pub fn reachable(address: SocketAddr, version: &Version, timestamp: i64) -> Self {
+        NodeReport {
+            address,
+            reachable: true,
+            version: Some(version.version),
+            user_agent: Some(version.user_agent.clone()),
+            capabilities: Some(version.capabilities),
+            last_epoch: Some(version.last_epoch),
+            peers: 0,
+            error: None,
+            timestamp,
+        }
+    }
+
+    /// Report of a node which could not be reached
+    pub fn unreachable(address: SocketAddr, error: String, timestamp: i64) -> Self {
+        NodeReport {
+            address,
+            reachable: false,
+            version: None,
+            user_agent: None,
+            capabilities: None,
+            last_epoch: None,
+            peers: 0,
+            error: Some(error),
+            timestamp,
+        }
+    }
+}
+
+/// Aggregated figures of a crawl
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Summary {
+    /// Number of visited nodes
+    pub nodes: usize,
+    /// Number of nodes which completed the handshake
+    pub reachable: usize,
+    /// Number of reachable nodes per user agent
+    pub user_agents: BTreeMap<String, usize>,
+    /// Number of reachable nodes per protocol version
+    pub versions: BTreeMap<u32, usize>,
+}
+
+impl Summary {
+    /// Compute the summary of a list of node reports
+    pub fn new(nodes: &[NodeReport]) -> Self {
+        let mut summary = Summary {
+            nodes: nodes.len(),
+            ..Summary::default()
+        };
+
+        for node in nodes.iter().filter(|node| node.reachable) {
+            summary.reachable += 1;
+            if let Some(user_agent) = &node.user_agent {
+                *summary.user_agents.entry(user_agent.clone()).or_insert(0) += 1;
+            }
+            if let Some(version) = node.version {
+                *summary.versions.entry(version).or_insert(0) += 1;
+            }
+        }
+
+        summary
+    }
+}
+
+/// Full crawl report
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CrawlReport {
+    /// Timestamp of the start of the crawl
+    pub started: i64,
+    /// Timestamp of the end of the crawl
+    pub finished: i64,
+    /// Aggregated figures
+    pub summary: Summary,
+    /// Information about every visited node
+    pub nodes: Vec<NodeReport>,
+}
+
+impl CrawlReport {
+    /// Build a report from the visited nodes
+    pub fn new(started: i64, finished: i64, nodes: Vec<NodeReport>) -> Self {
+        CrawlReport {
+            started,
+            finished,
+            summary: Summary::new(&nodes),
+            nodes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use witnet_data_structures::types::{Address, IpAddress};
+
+    fn version(user_agent: &str) -> Version {
+        let address = Address {
+            ip: IpAddress::Ipv4 { ip: 0 },
+            port: 0,
+        };
+
+        Version {
+            version: 1,
+            timestamp: 0,
+            capabilities: 1,
+            sender_address: address,
+            receiver_address: address,
+            user_agent: user_agent.to_string(),
+            last_epoch: 10,
+            nonce: 0,
+            ephemeral_key: vec![],
+            consensus_constants: vec![],
+        }
+    }
+
+    #[test]
+    fn summary_counts_reachable_nodes() {
+        let nodes = vec![
+            NodeReport::reachable("127.0.0.1:1".parse().unwrap(), &version("a"), 0),
+            NodeReport::reachable("127.0.0.1:2".parse().unwrap(), &version("a"), 0),
+            NodeReport::reachable("127.0.0.1:3".parse().unwrap(), &version("b"), 0),
+            NodeReport::unreachable("127.0.0.1:4".parse().unwrap(), "refused".to_string(), 0),
+        ];
+        let summary = Summary::new(&nodes);
+
+        assert_eq!(summary.nodes, 4);
+        assert_eq!(summary.reachable, 3);
+        assert_eq!(summary.user_agents["a"], 2);
+        assert_eq!(summary.user_agents["b"], 1);
+        assert_eq!(summary.versions[&1], 3);
+    }
+
+    #[test]
+    fn report_serialization() {
+        let nodes = vec![NodeReport::unreachable(
+            "127.0.0.1:4".parse().unwrap(),
+            "refused".to_string(),
+            0,
+        )];
+        let report = CrawlReport::new(0, 1, nodes);
+        let json = serde_json::to_string(&report).unwrap();
+        let report2: CrawlReport = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(report, report2);
+    }
+}
@@ -0,0 +1,375 @@
+//! Authenticated encryption with XChaCha20-Poly1305
+//!
+//! Meant for the encryption of the many small values of the wallet database. XChaCha20-Poly1305
+//! is the AEAD construction of RFC 8439 with the extended 24-byte nonce of XChaCha20. The nonce is
+//! long enough to be chosen at random for every value, so [`seal`] and [`open`] take care of it:
+//! the random nonce is stored in front of the ciphertext.
+//!
+//! Associated data is authenticated but not encrypted. It is used to bind a value to its
+//! context, e.g. the key under which it is stored in the database, so that encrypted values
+//! cannot be swapped.
+//!
+//! ```
+//! use witnet_crypto::aead;
+//!
+//! let key = [0x42; aead::KEY_LENGTH];
+//! let sealed = aead::seal(&key, b"account/0/balance", b"1000").unwrap();
+//!
+//! assert_eq!(aead::open(&key, b"account/0/balance", &sealed).unwrap().as_ref(), b"1000");
+//! assert!(aead::open(&key, b"account/1/balance", &sealed).is_err());
+//! ```
+//!
+//! Values too big to be held in memory are encrypted in chunks with [`StreamEncryptor`], following
+//! the STREAM construction: the nonce of each chunk is made of a random prefix shared by the whole
+//! stream, the index of the chunk and a flag marking the last chunk, so chunks cannot be
+//! reordered, dropped or appended without the decryption failing.
+
+use crypto::{
+    chacha20::ChaCha20, mac::Mac, poly1305::Poly1305, symmetriccipher::SynchronousStreamCipher,
+};
+use failure::Fail;
+use rand::{rngs::OsRng, RngCore};
+use witnet_protected::{constant_time_eq, Protected};
+
+/// Length of the keys in bytes
+pub const KEY_LENGTH: usize = 32;
+/// Length of the nonces in bytes
+pub const NONCE_LENGTH: usize = 24;
+/// Length of the authentication tags in bytes
+pub const TAG_LENGTH: usize = 16;
+/// Length of the random nonce prefix of a stream in bytes
+pub const STREAM_PREFIX_LENGTH: usize = NONCE_LENGTH - 5;
+
+/// Error that can be raised when encrypting/decrypting
+#[derive(Debug, Fail)]
+pub enum Error {
+    /// The key is not 32 bytes long
+    #[fail(display = "Invalid key length {}, expected 32", length)]
+    InvalidKeyLength {
+        /// Length of the key
+        length: usize,
+    },
+    /// The ciphertext is too short to contain a nonce and a tag
+    #[fail(display = "Ciphertext is too short")]
+    Truncated,
+    /// The ciphertext or its associated data were modified, or the key is wrong
+    #[fail(display = "Ciphertext authentication failed")]
+    Authentication,
+    /// The stream has too many chunks to give each of them a different nonce
+    #[fail(display = "Too many chunks in the stream")]
+    StreamTooLong,
+    /// Wrapper for random generation errors
+    #[fail(display = "Random generation error")]
+    Rng(rand::Error),
+}
+
+/// Encrypt `plaintext` with a given nonce, returning the ciphertext followed by the tag.
+///
+/// The same nonce must never be used twice with the same key. Prefer [`seal`], which chooses a
+/// random nonce.
+pub fn encrypt(
+    key: &[u8],
+    nonce: &[u8; NONCE_LENGTH],
+    aad: &[u8],
+    plaintext: &[u8],
+) -> Result<Vec<u8>, Error> {
+    check_key(key)?;
+    let mut ciphertext = vec![0; plaintext.len() + TAG_LENGTH];
+    let (body, tag) = ciphertext.split_at_mut(plaintext.len());
+    let mut cipher = ChaCha20::new_xchacha20(key, nonce);
+    let mut mac = poly1305_init(&mut cipher, aad);
+
+    cipher.process(plaintext, body);
+    poly1305_finish(&mut mac, aad.len(), body, tag);
+
+    Ok(ciphertext)
+}
+
+/// Decrypt a ciphertext produced by [`encrypt`] with the same nonce and associated data
+pub fn decrypt(
+    key: &[u8],
+    nonce: &[u8; NONCE_LENGTH],
+    aad: &[u8],
+    ciphertext: &[u8],
+) -> Result<Protected, Error> {
+    check_key(key)?;
+    if ciphertext.len() < TAG_LENGTH {
+        Err(Error::Truncated)?
+    }
+    let (body, tag) = ciphertext.split_at(ciphertext.len() - TAG_LENGTH);
+    let mut cipher = ChaCha20::new_xchacha20(key, nonce);
+    let mut mac = poly1305_init(&mut cipher, aad);
+
+    let mut expected_tag = [0; TAG_LENGTH];
+    poly1305_finish(&mut mac, aad.len(), body, &mut expected_tag);
+    if !constant_time_eq(&expected_tag, tag) {
+        Err(Error::Authentication)?
+    }
+
+    let mut plaintext = Protected::new(vec![0; body.len()]);
+    cipher.process(body, plaintext.as_mut());
+
+    Ok(plaintext)
+}
+
+/// Encrypt `plaintext` with a random nonce, which is prepended to the ciphertext
+pub fn seal(key: &[u8], aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut nonce = [0; NONCE_LENGTH];
+    OsRng::new().map_err(Error::Rng)?.fill_bytes(&mut nonce);
+
+    let mut sealed = nonce.to_vec();
+    sealed.extend(encrypt(key, &nonce, aad, plaintext)?);
+
+    Ok(sealed)
+}
+
+/// Decrypt a value produced by [`seal`] with the same associated data
+pub fn open(key: &[u8], aad: &[u8], sealed: &[u8]) -> Result<Protected, Error> {
+    if sealed.len() < NONCE_LENGTH + TAG_LENGTH {
+        Err(Error::Truncated)?
+    }
+    let mut nonce = [0; NONCE_LENGTH];
+    nonce.copy_from_slice(&sealed[..NONCE_LENGTH]);
+
+    decrypt(key, &nonce, aad, &sealed[NONCE_LENGTH..])
+}
+
+/// Encryption of a stream of chunks with the STREAM construction
+pub struct StreamEncryptor {
+    key: Protected,
+    aad: Vec<u8>,
+    prefix: [u8; STREAM_PREFIX_LENGTH],
+    counter: u32,
+}
+
+impl StreamEncryptor {
+    /// Start a stream with a random nonce prefix. The associated data is authenticated with
+    /// every chunk.
+    pub fn new(key: &[u8], aad: &[u8]) -> Result<Self, Error> {
+        check_key(key)?;
+        let mut prefix = [0; STREAM_PREFIX_LENGTH];
+        OsRng::new().map_err(Error::Rng)?.fill_bytes(&mut prefix);
+
+        Ok(StreamEncryptor {
+            key: Protected::new(key),
+            aad: aad.to_vec(),
+            prefix,
+            counter: 0,
+        })
+    }
+
+    /// Nonce prefix of the stream, needed to decrypt it
+    pub fn nonce_prefix(&self) -> [u8; STREAM_PREFIX_LENGTH] {
+        self.prefix
+    }
+
+    /// Encrypt the next chunk of the stream
+    pub fn encrypt_chunk(&mut self, chunk: &[u8]) -> Result<Vec<u8>, Error> {
+        let nonce = stream_nonce(&self.prefix, self.counter, false);
+        self.counter = self.counter.checked_add(1).ok_or(Error::StreamTooLong)?;
+
+        encrypt(&self.key, &nonce, &self.aad, chunk)
+    }
+
+    /// Encrypt the last chunk of the stream, which may be empty
+    pub fn encrypt_last(self, chunk: &[u8]) -> Result<Vec<u8>, Error> {
+        let nonce = stream_nonce(&self.prefix, self.counter, true);
+
+        encrypt(&self.key, &nonce, &self.aad, chunk)
+    }
+}
+
+/// Decryption of a stream of chunks produced by a [`StreamEncryptor`]
+pub struct StreamDecryptor {
+    key: Protected,
+    aad: Vec<u8>,
+    prefix: [u8; STREAM_PREFIX_LENGTH],
+    counter: u32,
+}
+
+impl StreamDecryptor {
+    /// Start the decryption of a stream, given its nonce prefix and associated data
+    pub fn new(
+        key: &[u8],
+        nonce_prefix: &[u8; STREAM_PREFIX_LENGTH],
+        aad: &[u8],
+    ) -> Result<Self, Error> {
+        check_key(key)?;
+
+        Ok(StreamDecryptor {
+            key: Protected::new(key),
+            aad: aad.to_vec(),
+            prefix: *nonce_prefix,
+            counter: 0,
+        })
+    }
+
+    /// Decrypt the next chunk of the stream
+    pub fn decrypt_chunk(&mut self, chunk: &[u8]) -> Result<Protected, Error> {
+        let nonce = stream_nonce(&self.prefix, self.counter, false);
+        self.counter = self.counter.checked_add(1).ok_or(Error::StreamTooLong)?;
+
+        decrypt(&self.key, &nonce, &self.aad, chunk)
+    }
+
+    /// Decrypt the last chunk of the stream. A stream is only complete if its last chunk is
+    /// decrypted successfully.
+    pub fn decrypt_last(self, chunk: &[u8]) -> Result<Protected, Error> {
+        let nonce = stream_nonce(&self.prefix, self.counter, true);
+
+        decrypt(&self.key, &nonce, &self.aad, chunk)
+    }
+}
+
+fn check_key(key: &[u8]) -> Result<(), Error> {
+    if key.len() != KEY_LENGTH {
+        Err(Error::InvalidKeyLength { length: key.len() })?
+    }
+
+    Ok(())
+}
+
+/// Nonce of a chunk of a stream: `prefix || big-endian counter || last flag`
+fn stream_nonce(
+    prefix: &[u8; STREAM_PREFIX_LENGTH],
+    counter: u32,
+    last: bool,
+) -> [u8; NONCE_LENGTH] {
+    let mut nonce = [0; NONCE_LENGTH];
+    nonce[..STREAM_PREFIX_LENGTH].copy_from_slice(prefix);
+    nonce[STREAM_PREFIX_LENGTH..NONCE_LENGTH - 1].copy_from_slice(&counter.to_be_bytes());
+    nonce[NONCE_LENGTH - 1] = last as u8;
+
+    nonce
+}
+
+/// The Poly1305 key is the first 32 bytes of the keystream block 0, and the encryption starts
+/// with block 1. The associated data is authenticated first, padded to 16 bytes.
+fn poly1305_init(cipher: &mut ChaCha20, aad: &[u8]) -> Poly1305 {
+    let mut block = [0; 64];
+    cipher.process(&[0; 64], &mut block);
+    let mut mac = Poly1305::new(&block[..32]);
+    witnet_protected::zero_out(&mut block);
+
+    mac.input(aad);
+    mac.input(padding(aad.len()));
+
+    mac
+}
+
+/// Authenticate the ciphertext, padded to 16 bytes, and the lengths of both inputs
+fn poly1305_finish(mac: &mut Poly1305, aad_len: usize, ciphertext: &[u8], tag: &mut [u8]) {
+    mac.input(ciphertext);
+    mac.input(padding(ciphertext.len()));
+    mac.input(&(aad_len as u64).to_le_bytes());
+    mac.input(&(ciphertext.len() as u64).to_le_bytes());
+    mac.raw_result(tag);
+}
+
+fn padding(len: usize) -> &'static [u8] {
+    &[0; 16][..(16 - len % 16) % 16]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: [u8; KEY_LENGTH] = [0x42; KEY_LENGTH];
+
+    #[test]
+    fn test_rfc8439_vector() {
+        // AEAD test vector of section 2.8.2 of RFC 8439, which uses the 12-byte nonce of ChaCha20
+        let key: Vec<u8> = (0x80..=0x9f).collect();
+        let nonce = hex::decode("070000004041424344454647").unwrap();
+        let aad = hex::decode("50515253c0c1c2c3c4c5c6c7").unwrap();
+        let plaintext: &[u8] = b"Ladies and Gentlemen of the class of '99: If I could offer you \
+            only one tip for the future, sunscreen would be it.";
+
+        let mut cipher = ChaCha20::new(&key, &nonce);
+        let mut mac = poly1305_init(&mut cipher, &aad);
+        let mut ciphertext = vec![0; plaintext.len()];
+        cipher.process(plaintext, &mut ciphertext);
+        let mut tag = [0; TAG_LENGTH];
+        poly1305_finish(&mut mac, aad.len(), &ciphertext, &mut tag);
+
+        assert_eq!(
+            hex::encode(&ciphertext[..32]),
+            "d31a8d34648e60db7b86afbc53ef7ec2a4aded51296e08fea9e2b5a736ee62d6"
+        );
+        assert_eq!(hex::encode(tag), "1ae10b594f09e26a7e902ecbd0600691");
+    }
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let nonce = [7; NONCE_LENGTH];
+        let ciphertext = encrypt(&KEY, &nonce, b"aad", b"secret").unwrap();
+        assert_eq!(ciphertext.len(), 6 + TAG_LENGTH);
+        assert_eq!(
+            decrypt(&KEY, &nonce, b"aad", &ciphertext).unwrap().as_ref(),
+            b"secret"
+        );
+
+        // Wrong associated data, nonce, key or ciphertext
+        assert!(decrypt(&KEY, &nonce, b"other", &ciphertext).is_err());
+        assert!(decrypt(&KEY, &[8; NONCE_LENGTH], b"aad", &ciphertext).is_err());
+        assert!(decrypt(&[0; KEY_LENGTH], &nonce, b"aad", &ciphertext).is_err());
+        let mut tampered = ciphertext.clone();
+        tampered[0] ^= 1;
+        assert!(decrypt(&KEY, &nonce, b"aad", &tampered).is_err());
+        assert!(decrypt(&KEY, &nonce, b"aad", &ciphertext[..TAG_LENGTH - 1]).is_err());
+
+        assert!(encrypt(&KEY[..16], &nonce, b"aad", b"secret").is_err());
+    }
+
+    #[test]
+    fn test_seal_open() {
+        let sealed = seal(&KEY, b"aad", b"secret").unwrap();
+        assert_eq!(sealed.len(), NONCE_LENGTH + 6 + TAG_LENGTH);
+        assert_eq!(open(&KEY, b"aad", &sealed).unwrap().as_ref(), b"secret");
+
+        // Every value gets a different nonce
+        assert_ne!(sealed, seal(&KEY, b"aad", b"secret").unwrap());
+
+        assert!(open(&KEY, b"aad", &sealed[..NONCE_LENGTH + TAG_LENGTH - 1]).is_err());
+        assert_eq!(
+            open(&KEY, b"", &seal(&KEY, b"", b"").unwrap())
+                .unwrap()
+                .as_ref(),
+            b""
+        );
+    }
+
+    #[test]
+    fn test_stream() {
+        let mut encryptor = StreamEncryptor::new(&KEY, b"aad").unwrap();
+        let prefix = encryptor.nonce_prefix();
+        let chunks = [
+            encryptor.encrypt_chunk(b"first").unwrap(),
+            encryptor.encrypt_chunk(b"second").unwrap(),
+            encryptor.encrypt_last(b"last").unwrap(),
+        ];
+
+        let mut decryptor = StreamDecryptor::new(&KEY, &prefix, b"aad").unwrap();
+        assert_eq!(
+            decryptor.decrypt_chunk(&chunks[0]).unwrap().as_ref(),
+            b"first"
+        );
+        assert_eq!(
+            decryptor.decrypt_chunk(&chunks[1]).unwrap().as_ref(),
+            b"second"
+        );
+        assert_eq!(
+            decryptor.decrypt_last(&chunks[2]).unwrap().as_ref(),
+            b"last"
+        );
+
+        // Reordered chunks
+        let mut decryptor = StreamDecryptor::new(&KEY, &prefix, b"aad").unwrap();
+        assert!(decryptor.decrypt_chunk(&chunks[1]).is_err());
+
+        // Truncated stream: a chunk which is not the last one cannot end the stream
+        let mut decryptor = StreamDecryptor::new(&KEY, &prefix, b"aad").unwrap();
+        assert!(decryptor.decrypt_chunk(&chunks[0]).is_ok());
+        assert!(decryptor.decrypt_last(&chunks[1]).is_err());
+    }
+}
@@ -0,0 +1,536 @@
+//! BLS signatures
+//!
+//! BLS signatures over the BN256 curve, meant for the committee which signs the superblocks
+//! relayed to other chains. BN256 (alt_bn128) is the curve of the pairing precompiled contract of
+//! Ethereum, so the approval of the committee can be verified on chain. Signatures are points of
+//! G1 (64 bytes) and public keys are points of G2 (128 bytes), both serialized as the precompiled
+//! contracts expect them. The signature of a message is `sk * H(message)`, where `H` hashes into
+//! G1, and it is valid if `e(signature, G) = e(H(message), pk)`.
+//!
+//! Signatures of the same message can be aggregated by adding them, and the result is verified
+//! against the sum of the public keys of the signers with a single pairing check. This is what
+//! makes the approval of a whole committee cheap to verify by a smart contract.
+//!
+//! Adding public keys is only safe if the owner of every key has proven that it knows the secret
+//! key, otherwise a member could choose its key to cancel the keys of the others. Committee
+//! members must publish a [proof of possession](SecretKey::prove_possession) along with their
+//! public key.
+//!
+//! For threshold signatures, a secret key is [split](split_secret_key) into shares with Shamir's
+//! secret sharing. Any `threshold` of the signatures made with the shares can be
+//! [combined](combine_signature_shares) into the signature of the original key, which is
+//! verified against its public key.
+//!
+//! Secret keys and the coefficients used to split them are zeroed out when they are dropped, and
+//! their serialization is [`Protected`].
+
+use failure::Fail;
+use rand::RngCore;
+use witnet_protected::Protected;
+
+use crate::{
+    bn256::{pairing_product_is_one, Field, Fq, Fr, G1, G2},
+    hash::calculate_keccak256,
+};
+
+/// Length of a serialized secret key in bytes
+pub const SECRET_KEY_LENGTH: usize = 32;
+/// Length of a serialized public key in bytes
+pub const PUBLIC_KEY_LENGTH: usize = G2::BYTES;
+/// Length of a serialized signature in bytes
+pub const SIGNATURE_LENGTH: usize = G1::BYTES;
+
+/// Domain separation tag of the hash into G1 of the signed messages
+const SIGNATURE_DST: &[u8] = b"WITNET_BLS_SIG_BN256G1_KECCAK256_TAI_";
+/// Domain separation tag of the hash into G1 of the public keys in proofs of possession
+const POSSESSION_DST: &[u8] = b"WITNET_BLS_POP_BN256G1_KECCAK256_TAI_";
+
+/// The error type for operations with BLS signatures
+#[derive(Debug, PartialEq, Fail)]
+pub enum BlsError {
+    #[fail(display = "Malformed BLS secret key")]
+    /// The secret key cannot be decoded, or it is zero
+    MalformedSecretKey,
+    #[fail(display = "Malformed BLS public key")]
+    /// The public key cannot be decoded, or it is the identity
+    MalformedPublicKey,
+    #[fail(display = "Malformed BLS signature")]
+    /// The signature cannot be decoded
+    MalformedSignature,
+    #[fail(display = "Invalid BLS signature")]
+    /// The signature does not match the public key and the message
+    InvalidSignature,
+    #[fail(display = "Cannot aggregate an empty set")]
+    /// There are no public keys or signatures to aggregate
+    NothingToAggregate,
+    #[fail(
+        display = "Cannot split a key into {} shares with threshold {}",
+        shares, threshold
+    )]
+    /// The threshold is zero or greater than the number of shares
+    InvalidThreshold {
+        /// Number of shares needed to sign
+        threshold: usize,
+        /// Number of shares
+        shares: usize,
+    },
+    #[fail(display = "Invalid share index {}", index)]
+    /// Share indexes start at 1
+    InvalidShareIndex {
+        /// Index of the share
+        index: u32,
+    },
+    #[fail(display = "Share {} appears more than once", index)]
+    /// Two signature shares have the same index
+    DuplicateShare {
+        /// Index of the share
+        index: u32,
+    },
+    #[fail(display = "{} signature shares are needed, found {}", threshold, found)]
+    /// There are fewer signature shares than the threshold
+    NotEnoughShares {
+        /// Number of shares needed to sign
+        threshold: usize,
+        /// Number of shares received
+        found: usize,
+    },
+}
+
+/// BLS secret key
+#[derive(Clone, PartialEq)]
+pub struct SecretKey(Fr);
+
+impl Drop for SecretKey {
+    fn drop(&mut self) {
+        zero_out_scalar(&mut self.0);
+    }
+}
+
+impl std::fmt::Debug for SecretKey {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(fmt, "SecretKey(***)")
+    }
+}
+
+impl SecretKey {
+    /// Generate a random secret key
+    pub fn generate() -> Self {
+        SecretKey(random_scalar())
+    }
+
+    /// Decode a secret key from its canonical big-endian encoding
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, BlsError> {
+        if bytes.len() != SECRET_KEY_LENGTH {
+            Err(BlsError::MalformedSecretKey)?
+        }
+        let mut array = [0; SECRET_KEY_LENGTH];
+        array.copy_from_slice(bytes);
+        let scalar = Fr::from_bytes(&array);
+        witnet_protected::zero_out(&mut array);
+
+        scalar
+            .filter(|scalar| !scalar.is_zero())
+            .map(SecretKey)
+            .ok_or(BlsError::MalformedSecretKey)
+    }
+
+    /// Serialize the secret key
+    pub fn to_bytes(&self) -> Protected {
+        let mut bytes = self.0.to_bytes();
+        let protected = Protected::new(&bytes[..]);
+        witnet_protected::zero_out(&mut bytes);
+
+        protected
+    }
+
+    /// Public key of this secret key
+    pub fn public_key(&self) -> PublicKey {
+        PublicKey(G2::generator().mul(&self.0))
+    }
+
+    /// Sign a message
+    pub fn sign(&self, message: &[u8]) -> Signature {
+        Signature(hash_to_g1(message, SIGNATURE_DST).mul(&self.0))
+    }
+
+    /// Prove the possession of this secret key by signing its public key
+    pub fn prove_possession(&self) -> Signature {
+        let public_key = self.public_key().to_bytes();
+
+        Signature(hash_to_g1(&public_key, POSSESSION_DST).mul(&self.0))
+    }
+}
+
+/// BLS public key
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PublicKey(G2);
+
+impl PublicKey {
+    /// Decode a public key, which must be a point of G2 other than the identity
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, BlsError> {
+        G2::from_bytes(bytes)
+            .filter(|point| !point.is_identity())
+            .map(PublicKey)
+            .ok_or(BlsError::MalformedPublicKey)
+    }
+
+    /// Serialize the public key as `x.c1 || x.c0 || y.c1 || y.c0`
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.0.to_bytes()
+    }
+
+    /// Aggregate the public keys of the signers of an aggregated signature. Each key must have
+    /// been checked with [`verify_possession`](PublicKey::verify_possession) beforehand.
+    pub fn aggregate(keys: &[PublicKey]) -> Result<Self, BlsError> {
+        if keys.is_empty() {
+            Err(BlsError::NothingToAggregate)?
+        }
+        let sum = keys.iter().fold(G2::identity(), |sum, key| sum + key.0);
+        if sum.is_identity() {
+            Err(BlsError::MalformedPublicKey)?
+        }
+
+        Ok(PublicKey(sum))
+    }
+
+    /// Verify the signature of a message, which can be an aggregated signature if this is an
+    /// aggregated key
+    pub fn verify(&self, message: &[u8], signature: &Signature) -> Result<(), BlsError> {
+        if check_pairing(&self.0, hash_to_g1(message, SIGNATURE_DST), &signature.0) {
+            Ok(())
+        } else {
+            Err(BlsError::InvalidSignature)
+        }
+    }
+
+    /// Verify a proof of possession of the secret key of this public key
+    pub fn verify_possession(&self, proof: &Signature) -> Result<(), BlsError> {
+        let message = hash_to_g1(&self.to_bytes(), POSSESSION_DST);
+        if check_pairing(&self.0, message, &proof.0) {
+            Ok(())
+        } else {
+            Err(BlsError::InvalidSignature)
+        }
+    }
+}
+
+/// BLS signature
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Signature(G1);
+
+impl Signature {
+    /// Decode a signature, which must be a point of G1
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, BlsError> {
+        G1::from_bytes(bytes)
+            .map(Signature)
+            .ok_or(BlsError::MalformedSignature)
+    }
+
+    /// Serialize the signature as `x || y`
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.0.to_bytes()
+    }
+
+    /// Aggregate the signatures of the same message by different signers
+    pub fn aggregate(signatures: &[Signature]) -> Result<Self, BlsError> {
+        if signatures.is_empty() {
+            Err(BlsError::NothingToAggregate)?
+        }
+        let sum = signatures
+            .iter()
+            .fold(G1::identity(), |sum, signature| sum + signature.0);
+
+        Ok(Signature(sum))
+    }
+}
+
+/// Share of a secret key, see [`split_secret_key`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyShare {
+    /// Index of the share, starting at 1
+    pub index: u32,
+    /// Secret key of the share
+    pub secret_key: SecretKey,
+}
+
+impl KeyShare {
+    /// Public key of the share, which verifies the signatures made with it
+    pub fn public_key(&self) -> PublicKey {
+        self.secret_key.public_key()
+    }
+
+    /// Sign a message with this share
+    pub fn sign(&self, message: &[u8]) -> SignatureShare {
+        SignatureShare {
+            index: self.index,
+            signature: self.secret_key.sign(message),
+        }
+    }
+}
+
+/// Signature made with a [`KeyShare`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SignatureShare {
+    /// Index of the share which made the signature
+    pub index: u32,
+    /// Signature of the share
+    pub signature: Signature,
+}
+
+/// Split a secret key into `shares` shares, so that the signatures of any `threshold` of them can
+/// be combined into a signature of the secret key
+pub fn split_secret_key(
+    secret_key: &SecretKey,
+    threshold: usize,
+    shares: usize,
+) -> Result<Vec<KeyShare>, BlsError> {
+    if threshold == 0 || threshold > shares || shares > u32::max_value() as usize {
+        Err(BlsError::InvalidThreshold { threshold, shares })?
+    }
+
+    // The secret key is the value at 0 of a random polynomial of degree `threshold - 1`, and each
+    // share is its value at the index of the share
+    let mut coefficients = vec![secret_key.0];
+    coefficients.extend((1..threshold).map(|_| random_scalar()));
+
+    let key_shares = (1..=shares as u32)
+        .map(|index| {
+            let x = Fr::from_u64(u64::from(index));
+            let y = coefficients
+                .iter()
+                .rev()
+                .fold(Fr::zero(), |y, coefficient| y * x + *coefficient);
+
+            KeyShare {
+                index,
+                secret_key: SecretKey(y),
+            }
+        })
+        .collect();
+    coefficients.iter_mut().for_each(zero_out_scalar);
+
+    Ok(key_shares)
+}
+
+/// Combine `threshold` signature shares of the same message into a signature of the secret key
+/// that was split. Shares beyond the first `threshold` ones are ignored.
+///
+/// The shares are not verified, so an invalid share results in an invalid signature. To find out
+/// which shares are wrong, verify each of them with the public key of its [`KeyShare`].
+pub fn combine_signature_shares(
+    threshold: usize,
+    shares: &[SignatureShare],
+) -> Result<Signature, BlsError> {
+    if shares.len() < threshold || threshold == 0 {
+        Err(BlsError::NotEnoughShares {
+            threshold,
+            found: shares.len(),
+        })?
+    }
+    let shares = &shares[..threshold];
+    for (i, share) in shares.iter().enumerate() {
+        if share.index == 0 {
+            Err(BlsError::InvalidShareIndex { index: share.index })?
+        }
+        if shares[..i].iter().any(|other| other.index == share.index) {
+            Err(BlsError::DuplicateShare { index: share.index })?
+        }
+    }
+
+    // Lagrange interpolation at 0 of the signatures, which are the values of the polynomial of the
+    // secret key multiplied by the hash of the message
+    let sum = shares.iter().fold(G1::identity(), |sum, share| {
+        let x = Fr::from_u64(u64::from(share.index));
+        let (numerator, denominator) = shares
+            .iter()
+            .filter(|other| other.index != share.index)
+            .map(|other| Fr::from_u64(u64::from(other.index)))
+            .fold((Fr::one(), Fr::one()), |(num, den), other| {
+                (num * other, den * (other - x))
+            });
+        // The indexes are distinct, so the denominator is not zero
+        let coefficient = numerator * denominator.inverse().unwrap();
+
+        sum + share.signature.0.mul(&coefficient)
+    });
+
+    Ok(Signature(sum))
+}
+
+/// Check that `e(signature, G) = e(message, public_key)`
+fn check_pairing(public_key: &G2, message: G1, signature: &G1) -> bool {
+    pairing_product_is_one(&[(*signature, -G2::generator()), (message, *public_key)])
+}
+
+/// Hash into G1 by try and increment: `x` is `keccak256(dst || message || counter)` reduced
+/// modulo the field prime, for the first big-endian 32-bit counter such that `x^3 + 3` is a
+/// square, and `y` is the lower of its square roots. Half of the values of `x` are on the curve,
+/// and the hash is cheap to compute in a smart contract.
+fn hash_to_g1(message: &[u8], dst: &[u8]) -> G1 {
+    (0..=u32::max_value())
+        .find_map(|counter| {
+            let hash = calculate_keccak256(&[dst, message, &counter.to_be_bytes()].concat());
+            let x = Fq::from_bytes_reduced(&hash.0);
+            let y = (x.square() * x + Fq::from_u64(3)).sqrt()?;
+            let y = if (-y).to_bytes() < y.to_bytes() {
+                -y
+            } else {
+                y
+            };
+
+            G1::new(x, y)
+        })
+        .expect("Some counter gives a point of the curve")
+}
+
+/// Overwrite a secret scalar with zero, in a way that is not optimized away
+fn zero_out_scalar(scalar: &mut Fr) {
+    unsafe { std::ptr::write_volatile(scalar, Fr::zero()) }
+}
+
+/// Random non-zero scalar, drawn uniformly by rejecting the integers of 254 bits which are not
+/// lower than the order of the groups
+fn random_scalar() -> Fr {
+    let mut rng = rand::thread_rng();
+    loop {
+        let mut bytes = [0; 32];
+        rng.fill_bytes(&mut bytes);
+        bytes[0] &= 0x3f;
+        let scalar = Fr::from_bytes(&bytes);
+        witnet_protected::zero_out(&mut bytes);
+        if let Some(scalar) = scalar.filter(|scalar| !scalar.is_zero()) {
+            break scalar;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MESSAGE: &[u8] = b"superblock";
+
+    #[test]
+    fn test_sign_and_verify() {
+        let secret_key = SecretKey::generate();
+        let public_key = secret_key.public_key();
+        let signature = secret_key.sign(MESSAGE);
+
+        assert_eq!(public_key.verify(MESSAGE, &signature), Ok(()));
+        assert_eq!(
+            public_key.verify(b"other", &signature),
+            Err(BlsError::InvalidSignature)
+        );
+        assert_eq!(
+            SecretKey::generate()
+                .public_key()
+                .verify(MESSAGE, &signature),
+            Err(BlsError::InvalidSignature)
+        );
+        // Proofs of possession are not valid signatures, and vice versa
+        let proof = secret_key.prove_possession();
+        assert_eq!(public_key.verify_possession(&proof), Ok(()));
+        assert!(public_key.verify_possession(&signature).is_err());
+        assert!(public_key.verify(&public_key.to_bytes(), &proof).is_err());
+    }
+
+    #[test]
+    fn test_serialization() {
+        let secret_key = SecretKey::generate();
+        let public_key = secret_key.public_key();
+        let signature = secret_key.sign(MESSAGE);
+
+        assert_eq!(secret_key.to_bytes().len(), SECRET_KEY_LENGTH);
+        assert_eq!(
+            SecretKey::from_bytes(&secret_key.to_bytes()),
+            Ok(secret_key)
+        );
+        assert_eq!(public_key.to_bytes().len(), PUBLIC_KEY_LENGTH);
+        assert_eq!(
+            PublicKey::from_bytes(&public_key.to_bytes()),
+            Ok(public_key)
+        );
+        assert_eq!(signature.to_bytes().len(), SIGNATURE_LENGTH);
+        assert_eq!(Signature::from_bytes(&signature.to_bytes()), Ok(signature));
+
+        assert_eq!(
+            SecretKey::from_bytes(&[0; SECRET_KEY_LENGTH]),
+            Err(BlsError::MalformedSecretKey)
+        );
+        assert_eq!(
+            PublicKey::from_bytes(&[1; PUBLIC_KEY_LENGTH]),
+            Err(BlsError::MalformedPublicKey)
+        );
+        assert_eq!(
+            Signature::from_bytes(&signature.to_bytes()[1..]),
+            Err(BlsError::MalformedSignature)
+        );
+    }
+
+    #[test]
+    fn test_aggregate() {
+        let secret_keys: Vec<_> = (0..4).map(|_| SecretKey::generate()).collect();
+        let public_keys: Vec<_> = secret_keys.iter().map(SecretKey::public_key).collect();
+        let signatures: Vec<_> = secret_keys.iter().map(|sk| sk.sign(MESSAGE)).collect();
+
+        let public_key = PublicKey::aggregate(&public_keys).unwrap();
+        let signature = Signature::aggregate(&signatures).unwrap();
+        assert_eq!(public_key.verify(MESSAGE, &signature), Ok(()));
+
+        // A signature missing from the aggregation
+        let partial = Signature::aggregate(&signatures[1..]).unwrap();
+        assert!(public_key.verify(MESSAGE, &partial).is_err());
+
+        assert_eq!(PublicKey::aggregate(&[]), Err(BlsError::NothingToAggregate));
+        assert_eq!(Signature::aggregate(&[]), Err(BlsError::NothingToAggregate));
+    }
+
+    #[test]
+    fn test_threshold_signature() {
+        let secret_key = SecretKey::generate();
+        let public_key = secret_key.public_key();
+        let shares = split_secret_key(&secret_key, 3, 5).unwrap();
+        let signature_shares: Vec<_> = shares.iter().map(|share| share.sign(MESSAGE)).collect();
+
+        for (share, signature_share) in shares.iter().zip(&signature_shares) {
+            assert_eq!(
+                share
+                    .public_key()
+                    .verify(MESSAGE, &signature_share.signature),
+                Ok(())
+            );
+        }
+
+        // Any 3 shares produce the signature of the secret key
+        for subset in &[[0, 1, 2], [4, 2, 0], [1, 3, 4]] {
+            let subset: Vec<_> = subset.iter().map(|&i| signature_shares[i]).collect();
+            let signature = combine_signature_shares(3, &subset).unwrap();
+            assert_eq!(signature, secret_key.sign(MESSAGE));
+            assert_eq!(public_key.verify(MESSAGE, &signature), Ok(()));
+        }
+
+        assert_eq!(
+            combine_signature_shares(3, &signature_shares[..2]),
+            Err(BlsError::NotEnoughShares {
+                threshold: 3,
+                found: 2
+            })
+        );
+        let duplicated = [
+            signature_shares[0],
+            signature_shares[1],
+            signature_shares[0],
+        ];
+        assert_eq!(
+            combine_signature_shares(3, &duplicated),
+            Err(BlsError::DuplicateShare { index: 1 })
+        );
+        assert_eq!(
+            split_secret_key(&secret_key, 6, 5),
+            Err(BlsError::InvalidThreshold {
+                threshold: 6,
+                shares: 5
+            })
+        );
+    }
+}
@@ -0,0 +1,1094 @@
+//! Arithmetic of the BN256 curve
+//!
+//! BN256, also known as alt_bn128, is the pairing-friendly curve of the Ethereum precompiled
+//! contracts for point addition, scalar multiplication and pairing checks (EIP-196 and EIP-197).
+//! G1 is the curve `y^2 = x^3 + 3` over the base field `Fq`, and G2 is its sextic twist
+//! `y^2 = x^3 + 3 / (9 + i)` over the quadratic extension `Fq2 = Fq[i] / (i^2 + 1)`. The pairing
+//! is the optimal ate pairing into `Fq12`, computed with affine line functions: it is simple
+//! rather than fast, which is enough for checking the signatures of a committee.
+//!
+//! Points are serialized as the precompiled contracts expect them: the big-endian coordinates
+//! `x || y` for G1, and `x.c1 || x.c0 || y.c1 || y.c0` for G2, with the identity encoded as
+//! zeros.
+
+use std::ops::{Add, Mul, Neg, Sub};
+
+/// Little-endian 64-bit limbs of a 256-bit integer
+type Limbs = [u64; 4];
+
+/// `a + b + carry`, returning the result and the new carry
+fn adc(a: u64, b: u64, carry: u64) -> (u64, u64) {
+    let t = u128::from(a) + u128::from(b) + u128::from(carry);
+
+    (t as u64, (t >> 64) as u64)
+}
+
+/// `a - b - borrow`, returning the result and the new borrow
+fn sbb(a: u64, b: u64, borrow: u64) -> (u64, u64) {
+    let t = u128::from(a).wrapping_sub(u128::from(b) + u128::from(borrow));
+
+    (t as u64, (t >> 127) as u64)
+}
+
+/// `a + b * c + carry`, returning the result and the new carry
+fn mac(a: u64, b: u64, c: u64, carry: u64) -> (u64, u64) {
+    let t = u128::from(a) + u128::from(b) * u128::from(c) + u128::from(carry);
+
+    (t as u64, (t >> 64) as u64)
+}
+
+fn add_limbs(a: &Limbs, b: &Limbs) -> (Limbs, u64) {
+    let mut sum = [0; 4];
+    let mut carry = 0;
+    for i in 0..4 {
+        let (s, c) = adc(a[i], b[i], carry);
+        sum[i] = s;
+        carry = c;
+    }
+
+    (sum, carry)
+}
+
+fn sub_limbs(a: &Limbs, b: &Limbs) -> (Limbs, u64) {
+    let mut difference = [0; 4];
+    let mut borrow = 0;
+    for i in 0..4 {
+        let (d, next_borrow) = sbb(a[i], b[i], borrow);
+        difference[i] = d;
+        borrow = next_borrow;
+    }
+
+    (difference, borrow)
+}
+
+/// `a + b mod modulus`. Both moduli are below 2^254, so the sum cannot overflow.
+fn add_mod(a: &Limbs, b: &Limbs, modulus: &Limbs) -> Limbs {
+    let (sum, _) = add_limbs(a, b);
+    let (reduced, borrow) = sub_limbs(&sum, modulus);
+
+    if borrow == 0 {
+        reduced
+    } else {
+        sum
+    }
+}
+
+/// `a - b mod modulus`
+fn sub_mod(a: &Limbs, b: &Limbs, modulus: &Limbs) -> Limbs {
+    let (difference, borrow) = sub_limbs(a, b);
+
+    if borrow == 0 {
+        difference
+    } else {
+        add_limbs(&difference, modulus).0
+    }
+}
+
+/// Montgomery multiplication `a * b / 2^256 mod modulus`, where `inv = -modulus^-1 mod 2^64`
+fn mont_mul(a: &Limbs, b: &Limbs, modulus: &Limbs, inv: u64) -> Limbs {
+    let mut t = [0; 6];
+    for &b_i in b {
+        let mut carry = 0;
+        for j in 0..4 {
+            let (lo, c) = mac(t[j], a[j], b_i, carry);
+            t[j] = lo;
+            carry = c;
+        }
+        let (lo, c) = adc(t[4], carry, 0);
+        t[4] = lo;
+        t[5] = c;
+
+        // Add a multiple of the modulus that makes the lowest limb zero, and shift it out
+        let k = t[0].wrapping_mul(inv);
+        let (_, mut carry) = mac(t[0], k, modulus[0], 0);
+        for j in 1..4 {
+            let (lo, c) = mac(t[j], k, modulus[j], carry);
+            t[j - 1] = lo;
+            carry = c;
+        }
+        let (lo, c) = adc(t[4], carry, 0);
+        t[3] = lo;
+        t[4] = t[5] + c;
+    }
+    let result = [t[0], t[1], t[2], t[3]];
+    let (reduced, borrow) = sub_limbs(&result, modulus);
+
+    if t[4] != 0 || borrow == 0 {
+        reduced
+    } else {
+        result
+    }
+}
+
+fn limbs_from_be_bytes(bytes: &[u8; 32]) -> Limbs {
+    let mut limbs = [0; 4];
+    for (i, chunk) in bytes.chunks(8).enumerate() {
+        let mut limb = [0; 8];
+        limb.copy_from_slice(chunk);
+        limbs[3 - i] = u64::from_be_bytes(limb);
+    }
+
+    limbs
+}
+
+fn limbs_to_be_bytes(limbs: &Limbs) -> [u8; 32] {
+    let mut bytes = [0; 32];
+    for (i, chunk) in bytes.chunks_mut(8).enumerate() {
+        chunk.copy_from_slice(&limbs[3 - i].to_be_bytes());
+    }
+
+    bytes
+}
+
+/// `base` raised to a little-endian exponent, by square and multiply
+fn pow<T: Copy + Mul<Output = T>>(base: T, one: T, exponent: &[u64]) -> T {
+    bits_be(exponent).fold(one, |result, bit| {
+        let result = result * result;
+        if bit {
+            result * base
+        } else {
+            result
+        }
+    })
+}
+
+/// Bits of a little-endian integer, from the most significant one
+fn bits_be(limbs: &[u64]) -> impl Iterator<Item = bool> + '_ {
+    limbs
+        .iter()
+        .rev()
+        .flat_map(|limb| (0..64).rev().map(move |i| (limb >> i) & 1 == 1))
+}
+
+/// Operations shared by the fields of the curves, so that the point arithmetic can be written once
+pub trait Field:
+    Copy + PartialEq + Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self> + Neg<Output = Self>
+{
+    /// Additive identity
+    fn zero() -> Self;
+    /// Multiplicative identity
+    fn one() -> Self;
+    /// Multiplicative inverse, or `None` for zero
+    fn inverse(&self) -> Option<Self>;
+
+    /// Whether this is the additive identity
+    fn is_zero(&self) -> bool {
+        *self == Self::zero()
+    }
+
+    /// `self * self`
+    fn square(&self) -> Self {
+        *self * *self
+    }
+
+    /// `self + self`
+    fn double(&self) -> Self {
+        *self + *self
+    }
+
+    /// `self` raised to a little-endian exponent
+    fn pow(&self, exponent: &[u64]) -> Self {
+        pow(*self, Self::one(), exponent)
+    }
+}
+
+macro_rules! prime_field {
+    ($(#[$attr:meta])* $name:ident, modulus: $modulus:expr, r: $r:expr, r2: $r2:expr, inv: $inv:expr) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+        pub struct $name(Limbs);
+
+        impl $name {
+            /// The prime modulus of the field
+            pub const MODULUS: Limbs = $modulus;
+            /// `2^256 mod MODULUS`, the Montgomery form of one
+            const R: Limbs = $r;
+            /// `2^512 mod MODULUS`, which converts an integer into Montgomery form
+            const R2: Limbs = $r2;
+            /// `-MODULUS^-1 mod 2^64`
+            const INV: u64 = $inv;
+
+            /// Element of the field equal to an integer
+            pub fn from_u64(n: u64) -> Self {
+                $name(mont_mul(&[n, 0, 0, 0], &Self::R2, &Self::MODULUS, Self::INV))
+            }
+
+            /// Decode a big-endian integer, which must be lower than the modulus
+            pub fn from_bytes(bytes: &[u8; 32]) -> Option<Self> {
+                let limbs = limbs_from_be_bytes(bytes);
+                let (_, borrow) = sub_limbs(&limbs, &Self::MODULUS);
+                if borrow == 0 {
+                    None
+                } else {
+                    Some($name(mont_mul(&limbs, &Self::R2, &Self::MODULUS, Self::INV)))
+                }
+            }
+
+            /// Encode as a big-endian integer
+            pub fn to_bytes(self) -> [u8; 32] {
+                limbs_to_be_bytes(&self.to_limbs())
+            }
+
+            /// The element as a little-endian integer lower than the modulus
+            pub fn to_limbs(self) -> Limbs {
+                mont_mul(&self.0, &[1, 0, 0, 0], &Self::MODULUS, Self::INV)
+            }
+        }
+
+        impl Field for $name {
+            fn zero() -> Self {
+                $name([0; 4])
+            }
+
+            fn one() -> Self {
+                $name(Self::R)
+            }
+
+            fn inverse(&self) -> Option<Self> {
+                if self.is_zero() {
+                    None
+                } else {
+                    // Fermat's little theorem
+                    let (exponent, _) = sub_limbs(&Self::MODULUS, &[2, 0, 0, 0]);
+                    Some(self.pow(&exponent))
+                }
+            }
+        }
+
+        impl Add for $name {
+            type Output = Self;
+
+            fn add(self, other: Self) -> Self {
+                $name(add_mod(&self.0, &other.0, &Self::MODULUS))
+            }
+        }
+
+        impl Sub for $name {
+            type Output = Self;
+
+            fn sub(self, other: Self) -> Self {
+                $name(sub_mod(&self.0, &other.0, &Self::MODULUS))
+            }
+        }
+
+        impl Mul for $name {
+            type Output = Self;
+
+            fn mul(self, other: Self) -> Self {
+                $name(mont_mul(&self.0, &other.0, &Self::MODULUS, Self::INV))
+            }
+        }
+
+        impl Neg for $name {
+            type Output = Self;
+
+            fn neg(self) -> Self {
+                Self::zero() - self
+            }
+        }
+    };
+}
+
+prime_field!(
+    /// Element of the base field of the curve, in Montgomery form
+    Fq,
+    modulus: [
+        0x3c20_8c16_d87c_fd47,
+        0x9781_6a91_6871_ca8d,
+        0xb850_45b6_8181_585d,
+        0x3064_4e72_e131_a029,
+    ],
+    r: [
+        0xd35d_438d_c58f_0d9d,
+        0x0a78_eb28_f5c7_0b3d,
+        0x666e_a36f_7879_462c,
+        0x0e0a_77c1_9a07_df2f,
+    ],
+    r2: [
+        0xf32c_fc5b_538a_fa89,
+        0xb5e7_1911_d445_01fb,
+        0x47ab_1eff_0a41_7ff6,
+        0x06d8_9f71_cab8_351f,
+    ],
+    inv: 0x87d2_0782_e486_6389
+);
+
+prime_field!(
+    /// Element of the scalar field, the order of G1 and G2, in Montgomery form
+    Fr,
+    modulus: [
+        0x43e1_f593_f000_0001,
+        0x2833_e848_79b9_7091,
+        0xb850_45b6_8181_585d,
+        0x3064_4e72_e131_a029,
+    ],
+    r: [
+        0xac96_341c_4fff_fffb,
+        0x36fc_7695_9f60_cd29,
+        0x666e_a36f_7879_462e,
+        0x0e0a_77c1_9a07_df2f,
+    ],
+    r2: [
+        0x1bb8_e645_ae21_6da7,
+        0x53fe_3ab1_e35c_59e3,
+        0x8c49_833d_53bb_8085,
+        0x0216_d0b1_7f4e_44a5,
+    ],
+    inv: 0xc2e1_f593_efff_ffff
+);
+
+impl Fq {
+    /// Reduce a big-endian integer of 256 bits modulo the field prime
+    pub fn from_bytes_reduced(bytes: &[u8; 32]) -> Self {
+        let mut limbs = limbs_from_be_bytes(bytes);
+        loop {
+            let (reduced, borrow) = sub_limbs(&limbs, &Self::MODULUS);
+            if borrow == 1 {
+                break Fq(mont_mul(&limbs, &Self::R2, &Self::MODULUS, Self::INV));
+            }
+            limbs = reduced;
+        }
+    }
+
+    /// Square root, if there is one. The modulus is 3 mod 4, so it is `self^((p + 1) / 4)`.
+    pub fn sqrt(&self) -> Option<Self> {
+        let (exponent, _) = add_limbs(&Self::MODULUS, &[1, 0, 0, 0]);
+        let exponent = [
+            exponent[0] >> 2 | exponent[1] << 62,
+            exponent[1] >> 2 | exponent[2] << 62,
+            exponent[2] >> 2 | exponent[3] << 62,
+            exponent[3] >> 2,
+        ];
+        let root = self.pow(&exponent);
+
+        if root.square() == *self {
+            Some(root)
+        } else {
+            None
+        }
+    }
+}
+
+/// Element of the quadratic extension `Fq2 = Fq[i] / (i^2 + 1)`, which is `c0 + c1 * i`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Fq2 {
+    /// Real coefficient
+    pub c0: Fq,
+    /// Imaginary coefficient
+    pub c1: Fq,
+}
+
+impl Fq2 {
+    /// `c0 + c1 * i`
+    pub fn new(c0: Fq, c1: Fq) -> Self {
+        Fq2 { c0, c1 }
+    }
+
+    /// `c0 - c1 * i`, which is also the Frobenius map `self^p`
+    pub fn conjugate(&self) -> Self {
+        Fq2::new(self.c0, -self.c1)
+    }
+
+    /// Multiply by an element of the base field
+    pub fn scale(&self, k: Fq) -> Self {
+        Fq2::new(self.c0 * k, self.c1 * k)
+    }
+}
+
+impl Field for Fq2 {
+    fn zero() -> Self {
+        Fq2::new(Fq::zero(), Fq::zero())
+    }
+
+    fn one() -> Self {
+        Fq2::new(Fq::one(), Fq::zero())
+    }
+
+    fn inverse(&self) -> Option<Self> {
+        let norm = self.c0.square() + self.c1.square();
+
+        norm.inverse().map(|t| self.conjugate().scale(t))
+    }
+}
+
+impl Add for Fq2 {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Fq2::new(self.c0 + other.c0, self.c1 + other.c1)
+    }
+}
+
+impl Sub for Fq2 {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Fq2::new(self.c0 - other.c0, self.c1 - other.c1)
+    }
+}
+
+impl Mul for Fq2 {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        Fq2::new(
+            self.c0 * other.c0 - self.c1 * other.c1,
+            self.c0 * other.c1 + self.c1 * other.c0,
+        )
+    }
+}
+
+impl Neg for Fq2 {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Fq2::new(-self.c0, -self.c1)
+    }
+}
+
+/// Element of `Fq12 = Fq[w] / (w^12 - 18 * w^6 + 82)`, as the coefficients of a polynomial in `w`.
+///
+/// `w^6` is `9 + i`, so `Fq2` is embedded as `a + b * i = (a - 9 * b) + b * w^6`, and the twist
+/// maps the point `(x, y)` of G2 to `(x * w^2, y * w^3)` on the curve of G1 over `Fq12`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Fq12([Fq; 12]);
+
+impl Fq12 {
+    /// Multiplicative identity
+    pub fn one() -> Self {
+        let mut coefficients = [Fq::zero(); 12];
+        coefficients[0] = Fq::one();
+
+        Fq12(coefficients)
+    }
+
+    /// `self` raised to a little-endian exponent
+    pub fn pow(&self, exponent: &[u64]) -> Self {
+        pow(*self, Self::one(), exponent)
+    }
+
+    /// `a * w^power`, for `power < 6`
+    fn from_fq2(a: Fq2, power: usize) -> Self {
+        let mut coefficients = [Fq::zero(); 12];
+        coefficients[power] = a.c0 - a.c1 * Fq::from_u64(9);
+        coefficients[power + 6] = a.c1;
+
+        Fq12(coefficients)
+    }
+}
+
+impl Add for Fq12 {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        let mut coefficients = self.0;
+        for (c, o) in coefficients.iter_mut().zip(other.0.iter()) {
+            *c = *c + *o;
+        }
+
+        Fq12(coefficients)
+    }
+}
+
+impl Mul for Fq12 {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        let mut product = [Fq::zero(); 23];
+        for (i, a) in self.0.iter().enumerate() {
+            for (j, b) in other.0.iter().enumerate() {
+                product[i + j] = product[i + j] + *a * *b;
+            }
+        }
+        // Reduce with `w^12 = 18 * w^6 - 82`, from the highest power
+        let eighteen = Fq::from_u64(18);
+        let eighty_two = Fq::from_u64(82);
+        for k in (12..23).rev() {
+            let c = product[k];
+            product[k - 6] = product[k - 6] + c * eighteen;
+            product[k - 12] = product[k - 12] - c * eighty_two;
+        }
+        let mut coefficients = [Fq::zero(); 12];
+        coefficients.copy_from_slice(&product[..12]);
+
+        Fq12(coefficients)
+    }
+}
+
+/// Fields over which the curves are defined
+pub trait CurveField: Field {
+    /// Coefficient `b` of the curve `y^2 = x^3 + b`
+    fn curve_b() -> Self;
+}
+
+impl CurveField for Fq {
+    fn curve_b() -> Self {
+        Fq::from_u64(3)
+    }
+}
+
+impl CurveField for Fq2 {
+    fn curve_b() -> Self {
+        // 3 / (9 + i)
+        Fq2::new(
+            Fq([
+                0x3bf9_38e3_77b8_02a8,
+                0x020b_1b27_3633_535d,
+                0x26b7_edf0_4975_5260,
+                0x2514_c632_4384_a86d,
+            ]),
+            Fq([
+                0x38e7_eccc_d1dc_ff67,
+                0x65f0_b37d_93ce_0d3e,
+                0xd749_d0dd_22ac_00aa,
+                0x0141_b9ce_4a68_8d4d,
+            ]),
+        )
+    }
+}
+
+/// Point of a curve `y^2 = x^3 + b` in affine coordinates
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Affine<F> {
+    x: F,
+    y: F,
+    infinity: bool,
+}
+
+/// Point of G1
+pub type G1 = Affine<Fq>;
+/// Point of G2
+pub type G2 = Affine<Fq2>;
+
+/// Point in Jacobian coordinates `(X, Y, Z)`, which is the affine point `(X / Z^2, Y / Z^3)`, so
+/// that additions do not need inversions
+#[derive(Clone, Copy, Debug)]
+struct Jacobian<F> {
+    x: F,
+    y: F,
+    z: F,
+}
+
+impl<F: CurveField> Affine<F> {
+    /// The point at infinity
+    pub fn identity() -> Self {
+        Affine {
+            x: F::zero(),
+            y: F::one(),
+            infinity: true,
+        }
+    }
+
+    /// Point with these coordinates, if it is on the curve
+    pub fn new(x: F, y: F) -> Option<Self> {
+        if y.square() == x.square() * x + F::curve_b() {
+            Some(Affine {
+                x,
+                y,
+                infinity: false,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Whether this is the point at infinity
+    pub fn is_identity(&self) -> bool {
+        self.infinity
+    }
+
+    /// Whether the order of the point divides the order of the groups, which is always the case
+    /// in G1 but not in the twist curve of G2
+    pub fn is_in_subgroup(&self) -> bool {
+        self.mul_limbs(&Fr::MODULUS).is_identity()
+    }
+
+    /// Multiply the point by a scalar. Every bit of the scalar costs a doubling and an addition,
+    /// so that the operations do not depend on the bits of a secret key.
+    pub fn mul(&self, scalar: &Fr) -> Self {
+        self.mul_limbs(&scalar.to_limbs())
+    }
+
+    fn mul_limbs(&self, scalar: &[u64]) -> Self {
+        let base = self.to_jacobian();
+        bits_be(scalar)
+            .fold(Jacobian::identity(), |result, bit| {
+                let result = result.double();
+                let sum = result.add(&base);
+                if bit {
+                    sum
+                } else {
+                    result
+                }
+            })
+            .to_affine()
+    }
+
+    fn to_jacobian(self) -> Jacobian<F> {
+        if self.infinity {
+            Jacobian::identity()
+        } else {
+            Jacobian {
+                x: self.x,
+                y: self.y,
+                z: F::one(),
+            }
+        }
+    }
+}
+
+impl<F: CurveField> Add for Affine<F> {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        self.to_jacobian().add(&other.to_jacobian()).to_affine()
+    }
+}
+
+impl<F: CurveField> Neg for Affine<F> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        if self.infinity {
+            self
+        } else {
+            Affine {
+                x: self.x,
+                y: -self.y,
+                infinity: false,
+            }
+        }
+    }
+}
+
+impl<F: CurveField> Jacobian<F> {
+    fn identity() -> Self {
+        Jacobian {
+            x: F::one(),
+            y: F::one(),
+            z: F::zero(),
+        }
+    }
+
+    fn is_identity(&self) -> bool {
+        self.z.is_zero()
+    }
+
+    fn to_affine(self) -> Affine<F> {
+        match self.z.inverse() {
+            None => Affine::identity(),
+            Some(z_inv) => {
+                let z_inv2 = z_inv.square();
+                Affine {
+                    x: self.x * z_inv2,
+                    y: self.y * z_inv2 * z_inv,
+                    infinity: false,
+                }
+            }
+        }
+    }
+
+    /// Doubling for curves with `a = 0` ("dbl-2009-l")
+    fn double(&self) -> Self {
+        if self.is_identity() {
+            return *self;
+        }
+        let a = self.x.square();
+        let b = self.y.square();
+        let c = b.square();
+        let d = ((self.x + b).square() - a - c).double();
+        let e = a.double() + a;
+        let f = e.square();
+        let x = f - d.double();
+        let eight_c = c.double().double().double();
+
+        Jacobian {
+            x,
+            y: e * (d - x) - eight_c,
+            z: (self.y * self.z).double(),
+        }
+    }
+
+    /// Addition ("add-2007-bl")
+    fn add(&self, other: &Self) -> Self {
+        if self.is_identity() {
+            return *other;
+        }
+        if other.is_identity() {
+            return *self;
+        }
+        let z1z1 = self.z.square();
+        let z2z2 = other.z.square();
+        let u1 = self.x * z2z2;
+        let u2 = other.x * z1z1;
+        let s1 = self.y * other.z * z2z2;
+        let s2 = other.y * self.z * z1z1;
+        let h = u2 - u1;
+        let r = (s2 - s1).double();
+        if h.is_zero() {
+            return if r.is_zero() {
+                self.double()
+            } else {
+                Jacobian::identity()
+            };
+        }
+        let i = h.double().square();
+        let j = h * i;
+        let v = u1 * i;
+        let x = r.square() - j - v.double();
+
+        Jacobian {
+            x,
+            y: r * (v - x) - (s1 * j).double(),
+            z: ((self.z + other.z).square() - z1z1 - z2z2) * h,
+        }
+    }
+}
+
+impl G1 {
+    /// Length of a serialized point
+    pub const BYTES: usize = 64;
+
+    /// The generator `(1, 2)`
+    #[cfg(test)]
+    pub fn generator() -> Self {
+        Affine {
+            x: Fq::from_u64(1),
+            y: Fq::from_u64(2),
+            infinity: false,
+        }
+    }
+
+    /// Decode a point serialized as `x || y`, where zeros are the identity
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != Self::BYTES {
+            return None;
+        }
+        if bytes.iter().all(|b| *b == 0) {
+            return Some(Self::identity());
+        }
+        let x = Fq::from_bytes(&array_32(&bytes[..32]))?;
+        let y = Fq::from_bytes(&array_32(&bytes[32..]))?;
+
+        Self::new(x, y)
+    }
+
+    /// Serialize the point as `x || y`
+    pub fn to_bytes(self) -> Vec<u8> {
+        if self.infinity {
+            return vec![0; Self::BYTES];
+        }
+        [self.x.to_bytes(), self.y.to_bytes()].concat()
+    }
+}
+
+impl G2 {
+    /// Length of a serialized point
+    pub const BYTES: usize = 128;
+
+    /// The generator of EIP-197
+    pub fn generator() -> Self {
+        let coordinate = |c0: &str, c1: &str| {
+            let c0 = Fq::from_bytes(&array_32(&hex::decode(c0).unwrap())).unwrap();
+            let c1 = Fq::from_bytes(&array_32(&hex::decode(c1).unwrap())).unwrap();
+            Fq2::new(c0, c1)
+        };
+
+        Affine {
+            x: coordinate(
+                "1800deef121f1e76426a00665e5c4479674322d4f75edadd46debd5cd992f6ed",
+                "198e9393920d483a7260bfb731fb5d25f1aa493335a9e71297e485b7aef312c2",
+            ),
+            y: coordinate(
+                "12c85ea5db8c6deb4aab71808dcb408fe3d1e7690c43d37b4ce6cc0166fa7daa",
+                "090689d0585ff075ec9e99ad690c3395bc4b313370b38ef355acdadcd122975b",
+            ),
+            infinity: false,
+        }
+    }
+
+    /// Decode a point serialized as `x.c1 || x.c0 || y.c1 || y.c0`, where zeros are the
+    /// identity. Points of the twist curve which are not in G2 are rejected.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != Self::BYTES {
+            return None;
+        }
+        if bytes.iter().all(|b| *b == 0) {
+            return Some(Self::identity());
+        }
+        let coordinate = |bytes: &[u8]| -> Option<Fq2> {
+            let c1 = Fq::from_bytes(&array_32(&bytes[..32]))?;
+            let c0 = Fq::from_bytes(&array_32(&bytes[32..]))?;
+            Some(Fq2::new(c0, c1))
+        };
+        let x = coordinate(&bytes[..64])?;
+        let y = coordinate(&bytes[64..])?;
+
+        Self::new(x, y).filter(Self::is_in_subgroup)
+    }
+
+    /// Serialize the point as `x.c1 || x.c0 || y.c1 || y.c0`
+    pub fn to_bytes(self) -> Vec<u8> {
+        if self.infinity {
+            return vec![0; Self::BYTES];
+        }
+        [
+            self.x.c1.to_bytes(),
+            self.x.c0.to_bytes(),
+            self.y.c1.to_bytes(),
+            self.y.c0.to_bytes(),
+        ]
+        .concat()
+    }
+
+    /// The Frobenius endomorphism of the curve of G1 over `Fq12`, pulled back to the twist
+    fn frobenius(&self) -> Self {
+        // (9 + i)^((p - 1) / 3)
+        let gamma_x = Fq2::new(
+            Fq([
+                0xb577_3b10_4563_ab30,
+                0x347f_91c8_a9aa_6454,
+                0x7a00_7127_242e_0991,
+                0x1956_bcd8_1182_14ec,
+            ]),
+            Fq([
+                0x6e84_9f1e_a0aa_4757,
+                0xaa1c_7b6d_89f8_9141,
+                0xb6e7_13cd_fae0_ca3a,
+                0x2669_4fbb_4e82_ebc3,
+            ]),
+        );
+        // (9 + i)^((p - 1) / 2)
+        let gamma_y = Fq2::new(
+            Fq([
+                0xe4bb_dd0c_2936_b629,
+                0xbb30_f162_e133_bacb,
+                0x31a9_d1b6_f964_5366,
+                0x2535_70be_a500_f8dd,
+            ]),
+            Fq([
+                0xa1d7_7ce4_5ffe_77c7,
+                0x07af_fd11_7826_d1db,
+                0x6d16_bd27_bb7e_dc6b,
+                0x2c87_2002_85de_fecc,
+            ]),
+        );
+
+        Affine {
+            x: self.x.conjugate() * gamma_x,
+            y: self.y.conjugate() * gamma_y,
+            infinity: self.infinity,
+        }
+    }
+}
+
+fn array_32(bytes: &[u8]) -> [u8; 32] {
+    let mut array = [0; 32];
+    array.copy_from_slice(bytes);
+
+    array
+}
+
+/// `6 * u + 2`, where `u` is the parameter of the BN curve
+const ATE_LOOP_COUNT: u128 = 29_793_968_203_157_093_288;
+
+/// `(p^12 - 1) / r`, in little-endian limbs
+const FINAL_EXPONENT: [u64; 44] = [
+    0x8696_4b64_ca86_f120,
+    0x40a4_efb7_e545_23a4,
+    0x837f_a978_96e8_4abb,
+    0x3611_02b6_b9b2_b918,
+    0xc0de_81de_f356_92da,
+    0xbe04_c7e8_a6c3_c760,
+    0xd766_f9c9_d570_bb7f,
+    0xc230_974d_8356_1841,
+    0x5bba_1668_c3be_69a3,
+    0x7f38_11c4_1052_6294,
+    0x29ba_ee7d_dadd_a71c,
+    0xbf81_3b8d_145d_a900,
+    0x641b_badf_423f_9a2c,
+    0xa80b_b4ea_44ea_cc5e,
+    0xcd65_6648_14fd_e37c,
+    0x4a03_64b9_5802_91d2,
+    0xee93_dfb1_0826_f0dd,
+    0x6b42_db8d_c551_4724,
+    0xbb10_cf43_0b0f_3785,
+    0x4049_4e40_6f80_4216,
+    0x55cf_e107_acf3_aafb,
+    0x2088_ec80_e0eb_ae87,
+    0x846a_3ed0_11a3_37a0,
+    0x48a4_5a4a_1e3a_5195,
+    0xe566_4568_dfc5_0e16,
+    0xab6a_4129_4c0c_c4eb,
+    0x82d0_d602_d268_c7da,
+    0x6668_449a_ed3c_c48a,
+    0x5062_cd0f_b201_5dfc,
+    0x7f29_40a8_b1dd_b3d1,
+    0x77f5_b63a_2a22_6448,
+    0xfef0_7813_61e4_43ae,
+    0xf977_870e_88d5_c6c8,
+    0x7903_64a6_1f67_6baa,
+    0x5887_e72e_cead_dea3,
+    0x1377_e563_a09a_1b70,
+    0x0c54_efee_1bd8_c3b2,
+    0x3ec3_d15a_d524_d8f7,
+    0xdaf1_5466_b238_3a5d,
+    0xe1e3_0a73_bb94_fec0,
+    0x6a1c_7101_5f3f_7be2,
+    0x842d_43bf_6369_b1ff,
+    0x20fd_dadf_107d_20bc,
+    0x0000_002f_4b6d_c970,
+];
+
+/// Whether the product of the pairings `e(p, q)` of all the pairs is one. This is the check of the
+/// pairing precompiled contract, and it is cheaper than comparing pairings because the final
+/// exponentiation is shared.
+pub fn pairing_product_is_one(pairs: &[(G1, G2)]) -> bool {
+    let f = pairs
+        .iter()
+        .fold(Fq12::one(), |f, (p, q)| f * miller_loop(p, q));
+
+    f.pow(&FINAL_EXPONENT) == Fq12::one()
+}
+
+/// Optimal ate pairing, used to test its bilinearity
+#[cfg(test)]
+fn pairing(p: &G1, q: &G2) -> Fq12 {
+    miller_loop(p, q).pow(&FINAL_EXPONENT)
+}
+
+fn miller_loop(p: &G1, q: &G2) -> Fq12 {
+    if p.is_identity() || q.is_identity() {
+        return Fq12::one();
+    }
+    let mut r = *q;
+    let mut f = Fq12::one();
+    // The highest bit of the loop count is accounted for by starting at `q`
+    for i in (0..64).rev() {
+        f = f * f * line(&r, &r, p);
+        r = r + r;
+        if (ATE_LOOP_COUNT >> i) & 1 == 1 {
+            f = f * line(&r, q, p);
+            r = r + *q;
+        }
+    }
+    let q1 = q.frobenius();
+    let q2 = -q1.frobenius();
+    f = f * line(&r, &q1, p);
+    r = r + q1;
+
+    f * line(&r, &q2, p)
+}
+
+/// Value at `p` of the line through the twisted `r` and `s`, or of the tangent if they are equal
+fn line(r: &G2, s: &G2, p: &G1) -> Fq12 {
+    let slope = if r.x != s.x {
+        // The x coordinates differ, so the inverse exists
+        (s.y - r.y) * (s.x - r.x).inverse().unwrap()
+    } else if r.y == s.y {
+        // There are no points of order 2, so `y` is not zero
+        let x2 = r.x.square();
+        (x2.double() + x2) * r.y.double().inverse().unwrap()
+    } else {
+        // Vertical line `x_p - x_r * w^2`
+        let mut coefficients = [Fq::zero(); 12];
+        coefficients[0] = p.x;
+
+        return Fq12(coefficients) + Fq12::from_fq2(-r.x, 2);
+    };
+    // The twisted slope is `slope * w`, so the line is
+    // `slope * x_p * w + (y_r - slope * x_r) * w^3 - y_p`
+    let mut coefficients = [Fq::zero(); 12];
+    coefficients[0] = -p.y;
+
+    Fq12(coefficients) + Fq12::from_fq2(slope.scale(p.x), 1) + Fq12::from_fq2(r.y - slope * r.x, 3)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generators() {
+        assert!(G1::new(G1::generator().x, G1::generator().y).is_some());
+        assert!(G2::new(G2::generator().x, G2::generator().y).is_some());
+        assert!(G1::generator().is_in_subgroup());
+        assert!(G2::generator().is_in_subgroup());
+
+        let two = Fr::from_u64(2);
+        let three = Fr::from_u64(3);
+        let g = G2::generator();
+        assert_eq!(g.mul(&two), g + g);
+        assert_eq!(g.mul(&three), g + g + g);
+        assert_eq!(g.mul(&Fr::zero()), G2::identity());
+        assert_eq!(g + -g, G2::identity());
+    }
+
+    #[test]
+    fn test_fields() {
+        let a = Fq::from_u64(123_456_789);
+        assert_eq!(a * a.inverse().unwrap(), Fq::one());
+        assert_eq!(a.square().sqrt().map(|r| r == a || r == -a), Some(true));
+        assert_eq!(Fq::from_bytes(&a.to_bytes()), Some(a));
+        assert_eq!(Fq::from_bytes(&limbs_to_be_bytes(&Fq::MODULUS)), None);
+        assert_eq!(Fq::from_bytes_reduced(&[0xff; 32]).to_bytes()[0], 0x0e);
+        let b = Fq2::new(a, Fq::from_u64(7));
+        assert_eq!(b * b.inverse().unwrap(), Fq2::one());
+        assert_eq!(Fr::from_u64(2) - Fr::from_u64(3), -Fr::one());
+    }
+
+    #[test]
+    fn test_serialization() {
+        let p = G1::generator().mul(&Fr::from_u64(5));
+        let q = G2::generator().mul(&Fr::from_u64(5));
+        assert_eq!(G1::from_bytes(&p.to_bytes()), Some(p));
+        assert_eq!(G2::from_bytes(&q.to_bytes()), Some(q));
+        assert_eq!(G1::from_bytes(&[0; 64]), Some(G1::identity()));
+        assert_eq!(G1::from_bytes(&[1; 64]), None);
+        assert_eq!(G2::from_bytes(&[1; 128]), None);
+        assert_eq!(
+            hex::encode(G1::generator().to_bytes()),
+            format!("{:064x}{:064x}", 1, 2)
+        );
+    }
+
+    #[test]
+    fn test_pairing_of_generators() {
+        // Coefficients of `e(G1, G2)` in `w`, as computed by the straightforward pairing over
+        // `Fq12` of py_ecc, which lifts G2 to the curve of G1 instead of working on the twist
+        let expected = [
+            "28c6e04df059260df7d2d2a1f9b5f77676d1939847852c4ed50d2318744c1d5f",
+            "17bb74adab1705c26133af1dac87044a3833ac011018e8158da48382bbd2dcd6",
+            "0d3bd72f54d742f78ea9e6015c8ea2f2e7fbb728c9c905ec531dcf7de5b246f0",
+            "090cb8ee97e091a667af03882b06c3ecb4e437993cbd1b05b98c7f9dfcfe9c40",
+            "016b6d855b5cbf76f9829a309db52f5c442f65ae29f996af59d65f85f4afe78a",
+            "0a0272204db51dadc0342bd318b9302a44faec12ff500bdd4d4b012ffe45f36f",
+            "084f330485b09e866bc2f2ea2b897394deaf3f12aa31f28cb0552990967d4704",
+            "27ed208e7a0b55ae6e710bbfbd2fd922669c026360e37cc5b2ab862411536104",
+            "2067586885c3318eeffa1938c754fe3c60224ee5ae15e66af6b5104c47c8c5d8",
+            "279db296f9d479292532c7c493d8e0722b6efae42158387564889c79fc038ee3",
+            "2b03614464f04dd772d86df88674c270ffc8747ea13e72da95e3594468f222c4",
+            "108c19d15f9446f744d0f110405d3856d6cc3bda6c4d537663729f5257628417",
+        ];
+        let e = pairing(&G1::generator(), &G2::generator());
+        let coefficients: Vec<_> = e.0.iter().map(|c| hex::encode(c.to_bytes())).collect();
+
+        assert_eq!(coefficients, expected);
+    }
+
+    #[test]
+    fn test_pairing_bilinearity() {
+        let a = Fr::from_u64(6);
+        let b = Fr::from_u64(35);
+        let p = G1::generator();
+        let q = G2::generator();
+        let e = pairing(&p, &q);
+        assert_ne!(e, Fq12::one());
+
+        let e_ab = pairing(&p.mul(&a), &q.mul(&b));
+        assert_eq!(e_ab, pairing(&p.mul(&(a * b)), &q));
+        assert_eq!(e_ab, e.pow(&(a * b).to_limbs()));
+        assert!(pairing_product_is_one(&[(p.mul(&a), q), (-p, q.mul(&a))]));
+        assert!(!pairing_product_is_one(&[(p.mul(&a), q), (-p, q.mul(&b))]));
+    }
+}
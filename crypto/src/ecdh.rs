@@ -0,0 +1,75 @@
+//! Elliptic-curve Diffie-Hellman key agreement
+//!
+//! Two peers derive the same shared secret from their own secret key and the
+//! public key of the other peer. The shared secret is the SHA-256 hash of the
+//! compressed shared point, as computed by libsecp256k1.
+//!
+//! The shared secret should not be used directly as a symmetric key. Instead,
+//! session keys should be derived from it with [`hkdf_sha256`](crate::hkdf::hkdf_sha256):
+//!
+//! ```
+//! use secp256k1::{PublicKey, SecretKey};
+//! use witnet_crypto::{ecdh::ecdh, hkdf::hkdf_sha256, signature::secp256k1_context};
+//!
+//! let secp = secp256k1_context();
+//! let alice = SecretKey::from_slice(&[0xcd; 32]).unwrap();
+//! let bob = SecretKey::from_slice(&[0xab; 32]).unwrap();
+//!
+//! let shared_secret = ecdh(&alice, &PublicKey::from_secret_key(secp, &bob));
+//! assert_eq!(
+//!     shared_secret.as_ref(),
+//!     ecdh(&bob, &PublicKey::from_secret_key(secp, &alice)).as_ref()
+//! );
+//!
+//! // Derive an encryption key and an authentication key for the session
+//! let keys = hkdf_sha256(shared_secret.as_ref(), b"salt", b"session keys", 64).unwrap();
+//! let (encryption_key, authentication_key) = keys.split_at(32);
+//! ```
+
+use secp256k1::{ecdh::SharedSecret, PublicKey, SecretKey};
+
+use witnet_protected::Protected;
+
+/// Derive the secret shared by the owner of `secret_key` and the owner of
+/// `public_key`
+pub fn ecdh(secret_key: &SecretKey, public_key: &PublicKey) -> Protected {
+    let shared_secret = SharedSecret::new(public_key, secret_key);
+
+    Protected::new(&shared_secret[..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{hash::calculate_sha256, signature::secp256k1_context};
+
+    #[test]
+    fn test_ecdh() {
+        let secp = secp256k1_context();
+        let secret_key_a = SecretKey::from_slice(&[0xcd; 32]).unwrap();
+        let secret_key_b = SecretKey::from_slice(&[0xab; 32]).unwrap();
+        let public_key_a = PublicKey::from_secret_key(secp, &secret_key_a);
+        let public_key_b = PublicKey::from_secret_key(secp, &secret_key_b);
+
+        let shared_secret = ecdh(&secret_key_a, &public_key_b);
+        assert_eq!(
+            shared_secret.as_ref(),
+            ecdh(&secret_key_b, &public_key_a).as_ref()
+        );
+
+        // The shared secret is the hash of the compressed shared point
+        let mut shared_point = public_key_b;
+        shared_point.mul_assign(secp, &secret_key_a[..]).unwrap();
+        assert_eq!(
+            shared_secret.as_ref(),
+            &calculate_sha256(&shared_point.serialize()).0[..]
+        );
+
+        // Other keys derive another secret
+        let secret_key_c = SecretKey::from_slice(&[0x01; 32]).unwrap();
+        assert_ne!(
+            shared_secret.as_ref(),
+            ecdh(&secret_key_c, &public_key_b).as_ref()
+        );
+    }
+}
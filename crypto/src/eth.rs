@@ -0,0 +1,114 @@
+//! Ethereum compatibility helpers
+//!
+//! The Witnet Bridge relays data requests and their results between Witnet and the Witnet
+//! Requests Board (WRB) contract on Ethereum. Both chains use secp256k1 keys, so the same key
+//! pair can identify a bridge node on both sides. Ethereum addresses are the last 20 bytes of the
+//! [Keccak256](crate::hash::calculate_keccak256) hash of the uncompressed public key, without its
+//! `0x04` prefix.
+//!
+//! ```
+//! use secp256k1::{PublicKey, SecretKey};
+//! use witnet_crypto::{eth, signature::secp256k1_context};
+//!
+//! let secret_key = SecretKey::from_slice(&[0x46; 32]).unwrap();
+//! let public_key = PublicKey::from_secret_key(secp256k1_context(), &secret_key);
+//!
+//! assert_eq!(
+//!     eth::to_checksum_address(&eth::eth_address(&public_key)),
+//!     "0x9d8A62f656a8d1615C1294fd71e9CFb3E4855A4F"
+//! );
+//! ```
+
+use secp256k1::PublicKey;
+
+use crate::hash::calculate_keccak256;
+
+/// Length of an Ethereum address in bytes
+pub const ADDRESS_LENGTH: usize = 20;
+
+/// Ethereum address of a public key
+pub fn eth_address(public_key: &PublicKey) -> [u8; ADDRESS_LENGTH] {
+    let uncompressed = public_key.serialize_uncompressed();
+    let hash = calculate_keccak256(&uncompressed[1..]);
+
+    let mut address = [0; ADDRESS_LENGTH];
+    address.copy_from_slice(&hash.0[32 - ADDRESS_LENGTH..]);
+
+    address
+}
+
+/// Encode an address as hexadecimal with the mixed-case checksum of EIP-55, prefixed with `0x`.
+///
+/// Each letter of the encoding is uppercase if the corresponding nibble of the Keccak256 hash of
+/// the lowercase encoding is 8 or greater, so that most typos change the case pattern.
+pub fn to_checksum_address(address: &[u8; ADDRESS_LENGTH]) -> String {
+    let lowercase = hex::encode(address);
+    let hash = calculate_keccak256(lowercase.as_bytes());
+
+    let checksummed: String = lowercase
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            let nibble = (hash.0[i / 2] >> (4 * (1 - i % 2))) & 0x0f;
+            if nibble >= 8 {
+                c.to_ascii_uppercase()
+            } else {
+                c
+            }
+        })
+        .collect();
+
+    format!("0x{}", checksummed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signature::secp256k1_context;
+    use secp256k1::SecretKey;
+
+    fn address_of(secret_key: &[u8]) -> String {
+        let secret_key = SecretKey::from_slice(secret_key).unwrap();
+        let public_key = PublicKey::from_secret_key(secp256k1_context(), &secret_key);
+
+        to_checksum_address(&eth_address(&public_key))
+    }
+
+    #[test]
+    fn test_eth_address() {
+        let mut one = [0; 32];
+        one[31] = 1;
+        let mut two = [0; 32];
+        two[31] = 2;
+
+        assert_eq!(
+            address_of(&one),
+            "0x7E5F4552091A69125d5DfCb7b8C2659029395Bdf"
+        );
+        assert_eq!(
+            address_of(&two),
+            "0x2B5AD5c4795c026514f8317c7a215E218DcCD6cF"
+        );
+        // Sender of the example transaction of EIP-155
+        assert_eq!(
+            address_of(&[0x46; 32]),
+            "0x9d8A62f656a8d1615C1294fd71e9CFb3E4855A4F"
+        );
+    }
+
+    #[test]
+    fn test_checksum_address() {
+        // Test vectors of EIP-55
+        for expected in &[
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+            "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+            "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+            "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+        ] {
+            let mut address = [0; ADDRESS_LENGTH];
+            address.copy_from_slice(&hex::decode(&expected[2..]).unwrap());
+
+            assert_eq!(&to_checksum_address(&address), expected);
+        }
+    }
+}
@@ -1,7 +1,7 @@
 //! Various hash functions
 
 use crypto::digest::Digest;
-use crypto::sha2;
+use crypto::{ripemd160, sha2, sha3};
 
 /// Secure hashing algorithm v2
 #[derive(Copy, Clone, Debug, PartialEq, Hash)]
@@ -27,3 +27,142 @@ pub fn calculate_sha256(bytes: &[u8]) -> Sha256 {
     hasher.result(&mut hash);
     Sha256(hash)
 }
+
+/// Calculate the SHA256 hash of `bytes` tagged with a domain, that is,
+/// `SHA256(SHA256(tag) || SHA256(tag) || bytes)`. Hashes calculated with
+/// different tags cannot collide, even if `bytes` are the same.
+pub fn calculate_tagged_sha256(tag: &str, bytes: &[u8]) -> Sha256 {
+    let Sha256(tag_hash) = calculate_sha256(tag.as_bytes());
+    let mut hasher = sha2::Sha256::new();
+    hasher.input(&tag_hash);
+    hasher.input(&tag_hash);
+    hasher.input(bytes);
+    let mut hash = [0; 32];
+    hasher.result(&mut hash);
+    Sha256(hash)
+}
+
+/// SHA-2 hash with a 512-bit output
+#[derive(Copy, Clone)]
+pub struct Sha512(pub [u8; 64]);
+
+impl AsRef<[u8]> for Sha512 {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl std::fmt::Debug for Sha512 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Sha512").field(&&self.0[..]).finish()
+    }
+}
+
+impl PartialEq for Sha512 {
+    fn eq(&self, other: &Self) -> bool {
+        self.0[..] == other.0[..]
+    }
+}
+
+/// Calculate the SHA512 hash
+pub fn calculate_sha512(bytes: &[u8]) -> Sha512 {
+    let mut hasher = sha2::Sha512::new();
+    hasher.input(&bytes);
+    let mut hash = [0; 64];
+    hasher.result(&mut hash);
+    Sha512(hash)
+}
+
+/// RIPEMD hash with a 160-bit output
+#[derive(Copy, Clone, Debug, PartialEq, Hash)]
+pub struct Ripemd160(pub [u8; 20]);
+
+impl AsRef<[u8]> for Ripemd160 {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// Calculate the RIPEMD160 hash
+pub fn calculate_ripemd160(bytes: &[u8]) -> Ripemd160 {
+    let mut hasher = ripemd160::Ripemd160::new();
+    hasher.input(&bytes);
+    let mut hash = [0; 20];
+    hasher.result(&mut hash);
+    Ripemd160(hash)
+}
+
+/// Keccak hash with a 256-bit output, as used by Ethereum. It differs from the standardized SHA3-256
+/// in the padding
+#[derive(Copy, Clone, Debug, PartialEq, Hash)]
+pub struct Keccak256(pub [u8; 32]);
+
+impl AsRef<[u8]> for Keccak256 {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// Calculate the Keccak256 hash
+pub fn calculate_keccak256(bytes: &[u8]) -> Keccak256 {
+    let mut hasher = sha3::Sha3::keccak256();
+    hasher.input(&bytes);
+    let mut hash = [0; 32];
+    hasher.result(&mut hash);
+    Keccak256(hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sha256() {
+        assert_eq!(calculate_sha256(b""), EMPTY_SHA256);
+        assert_eq!(
+            hex::encode(calculate_sha256(b"abc")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_tagged_sha256() {
+        assert_eq!(
+            hex::encode(calculate_tagged_sha256("witnet-block", b"abc")),
+            "bcd0ce9b5d90e4f0dd6951c900190da50c83688c1839fbb91b5bc5e84edaa4ee"
+        );
+        assert_ne!(
+            calculate_tagged_sha256("witnet-block", b"abc"),
+            calculate_tagged_sha256("witnet-transaction", b"abc")
+        );
+    }
+
+    #[test]
+    fn test_sha512() {
+        assert_eq!(
+            hex::encode(calculate_sha512(b"abc")),
+            "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a\
+             2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f"
+        );
+    }
+
+    #[test]
+    fn test_ripemd160() {
+        assert_eq!(
+            hex::encode(calculate_ripemd160(b"abc")),
+            "8eb208f7e05d987a9b044a8e98c6b087f15a0bfc"
+        );
+    }
+
+    #[test]
+    fn test_keccak256() {
+        assert_eq!(
+            hex::encode(calculate_keccak256(b"")),
+            "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+        );
+        assert_eq!(
+            hex::encode(calculate_keccak256(b"abc")),
+            "4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45"
+        );
+    }
+}
@@ -0,0 +1,88 @@
+//! HKDF Key Derivation Function
+//!
+//! HMAC-based extract-and-expand key derivation function (RFC 5869), used to
+//! derive symmetric keys from shared secrets, e.g. the output of
+//! [`ecdh`](crate::ecdh::ecdh).
+
+use crypto::{
+    hkdf::{hkdf_expand, hkdf_extract},
+    sha2,
+};
+use failure::Fail;
+
+use witnet_protected::Protected;
+
+/// Length of the output of SHA-256, in bytes
+const HASH_LENGTH: usize = 32;
+
+/// Maximum length of the keying material that can be derived with HKDF-SHA256
+pub const MAX_OUTPUT_LENGTH: usize = 255 * HASH_LENGTH;
+
+/// The error type for HKDF operations
+#[derive(Debug, PartialEq, Fail)]
+pub enum HkdfError {
+    #[fail(display = "Cannot derive {} bytes, the maximum is 8160 bytes", length)]
+    /// The requested output is longer than `MAX_OUTPUT_LENGTH`
+    OutputTooLong {
+        /// Requested length
+        length: usize,
+    },
+}
+
+/// Derive `length` bytes of keying material from the input keying material
+/// `ikm` with HKDF-SHA256. The `info` parameter binds the derived keys to
+/// their context, so that different keys can be derived from the same secret.
+pub fn hkdf_sha256(
+    ikm: &[u8],
+    salt: &[u8],
+    info: &[u8],
+    length: usize,
+) -> Result<Protected, HkdfError> {
+    if length > MAX_OUTPUT_LENGTH {
+        Err(HkdfError::OutputTooLong { length })?
+    }
+
+    let mut prk = Protected::new(vec![0; HASH_LENGTH]);
+    hkdf_extract(sha2::Sha256::new(), salt, ikm, prk.as_mut());
+
+    let mut okm = Protected::new(vec![0; length]);
+    hkdf_expand(sha2::Sha256::new(), prk.as_ref(), info, okm.as_mut());
+
+    Ok(okm)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hkdf_sha256_rfc5869() {
+        // Test case 1
+        let ikm = [0x0b; 22];
+        let salt: Vec<u8> = (0x00..=0x0c).collect();
+        let info: Vec<u8> = (0xf0..=0xf9).collect();
+        let okm = hkdf_sha256(&ikm, &salt, &info, 42).unwrap();
+
+        assert_eq!(
+            hex::encode(okm.as_ref()),
+            "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865"
+        );
+
+        // Test case 3: empty salt and info
+        let okm = hkdf_sha256(&ikm, &[], &[], 42).unwrap();
+
+        assert_eq!(
+            hex::encode(okm.as_ref()),
+            "8da4e775a563c18f715f802a063c5a31b8a11f5c5ee1879ec3454e5f3c738d2d9d201395faa4b61a96c8"
+        );
+    }
+
+    #[test]
+    fn test_hkdf_sha256_output_too_long() {
+        assert!(hkdf_sha256(b"secret", &[], &[], MAX_OUTPUT_LENGTH).is_ok());
+        match hkdf_sha256(b"secret", &[], &[], MAX_OUTPUT_LENGTH + 1) {
+            Err(HkdfError::OutputTooLong { length }) => assert_eq!(length, MAX_OUTPUT_LENGTH + 1),
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+}
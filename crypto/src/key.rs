@@ -13,6 +13,9 @@ use failure::Fail;
 use hmac::{Hmac, Mac};
 use secp256k1::{PublicKey, Secp256k1, SecretKey};
 use sha2;
+use witnet_protected::{constant_time_eq, Protected};
+
+use crate::signature::secp256k1_context;
 
 const HARDENED_BIT: u32 = 1 << 31;
 
@@ -92,6 +95,9 @@ pub enum KeyDerivationError {
     /// Invalid seed length
     #[fail(display = "The length of the seed is invalid, must be between 128/256 bits")]
     InvalidSeedLength,
+    /// Hardened children can only be derived from a secret key
+    #[fail(display = "Hardened child keys cannot be derived from a public key")]
+    HardenedChildFromPublicKey,
     /// Secp256k1 internal error
     #[fail(display = "Error in secp256k1 crate")]
     Secp256k1Error(secp256k1::Error),
@@ -111,7 +117,7 @@ pub type PK = PublicKey;
 pub type SignContext<C> = Secp256k1<C>;
 
 /// Extended Key is just a Key with a Chain Code
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, Debug)]
 pub struct ExtendedSK {
     /// Secret key
     pub secret_key: SK,
@@ -119,11 +125,41 @@ pub struct ExtendedSK {
     pub chain_code: [u8; 32],
 }
 
+impl PartialEq for ExtendedSK {
+    fn eq(&self, other: &Self) -> bool {
+        // Both comparisons are always made, so the time does not reveal which one failed
+        constant_time_eq(&self.secret_key[..], &other.secret_key[..])
+            & constant_time_eq(&self.chain_code, &other.chain_code)
+    }
+}
+
+impl Eq for ExtendedSK {}
+
+/// Extended Public Key: the public key of an [Extended Secret Key](ExtendedSK) with the same
+/// Chain Code, used to derive the public keys of non-hardened children
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ExtendedPK {
+    /// Public key
+    pub key: PK,
+    /// Chain code
+    pub chain_code: [u8; 32],
+}
+
 /// A child number for a derived key
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub struct ChildNumber(u32);
 
 impl ChildNumber {
+    /// Create a hardened child number from an index lower than 2^31
+    pub fn hardened(index: u32) -> Self {
+        ChildNumber(index | HARDENED_BIT)
+    }
+
+    /// Create a non-hardened child number from an index lower than 2^31
+    pub fn normal(index: u32) -> Self {
+        ChildNumber(index & !HARDENED_BIT)
+    }
+
     /// check if a child is hardened
     pub fn is_hardened(self) -> bool {
         self.0 & HARDENED_BIT == HARDENED_BIT
@@ -146,11 +182,8 @@ impl ExtendedSK {
         Ok(extended_sk)
     }
     /// get the secret
-    pub fn secret(&self) -> [u8; 32] {
-        let mut secret: [u8; 32] = [0; 32];
-        secret.copy_from_slice(&self.secret_key[..]);
-
-        secret
+    pub fn secret(&self) -> Protected {
+        Protected::new(&self.secret_key[..])
     }
 
     /// Try to get a private child key from parent
@@ -162,8 +195,7 @@ impl ExtendedSK {
             hmac512.input(&self.secret_key[..]);
         } else {
             hmac512.input(
-                &PublicKey::from_secret_key(&secp256k1::Secp256k1::new(), &self.secret_key)
-                    .serialize(),
+                &PublicKey::from_secret_key(secp256k1_context(), &self.secret_key).serialize(),
             );
         }
 
@@ -180,6 +212,45 @@ impl ExtendedSK {
     }
 }
 
+impl ExtendedPK {
+    /// Get the extended public key of an extended secret key
+    pub fn from_secret_key(extended_sk: &ExtendedSK) -> Self {
+        ExtendedPK {
+            key: PublicKey::from_secret_key(secp256k1_context(), &extended_sk.secret_key),
+            chain_code: extended_sk.chain_code,
+        }
+    }
+
+    /// Try to derive an extended public key from a given path of non-hardened children
+    pub fn derive(&self, path: Vec<ChildNumber>) -> Result<ExtendedPK, KeyDerivationError> {
+        let mut extended_pk = self.clone();
+        for child in path {
+            extended_pk = extended_pk.child(child)?
+        }
+
+        Ok(extended_pk)
+    }
+
+    /// Try to get a public child key from parent. Fails for hardened children.
+    pub fn child(&self, child: ChildNumber) -> Result<ExtendedPK, KeyDerivationError> {
+        if child.is_hardened() {
+            Err(KeyDerivationError::HardenedChildFromPublicKey)?
+        }
+
+        let mut hmac512: Hmac<sha2::Sha512> =
+            Hmac::new_varkey(&self.chain_code).map_err(|_| KeyDerivationError::InvalidKeyLength)?;
+        hmac512.input(&self.key.serialize());
+
+        let (chain_code, tweak) = get_chain_code_and_secret(&child.to_bytes(), hmac512)?;
+
+        let mut key = self.key;
+        key.add_exp_assign(secp256k1_context(), &tweak[..])
+            .map_err(KeyDerivationError::Secp256k1Error)?;
+
+        Ok(ExtendedPK { key, chain_code })
+    }
+}
+
 fn get_chain_code_and_secret(
     seed: &[u8],
     mut hmac512: Hmac<sha2::Sha512>,
@@ -296,4 +367,90 @@ mod tests {
             "Secret key is invalid"
         );
     }
+
+    fn from_hex(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_bip32_vector_1() {
+        // Test vector 1 from BIP32: chain m/0H/1/2H/2/1000000000
+        let seed = from_hex("000102030405060708090a0b0c0d0e0f");
+        let path = vec![
+            ChildNumber::hardened(0),
+            ChildNumber::normal(1),
+            ChildNumber::hardened(2),
+            ChildNumber::normal(2),
+            ChildNumber::normal(1_000_000_000),
+        ];
+        // (secret key, chain code, public key) of m and every child in the path
+        let expected = [
+            (
+                "e8f32e723decf4051aefac8e2c93c9c5b214313817cdb01a1494b917c8436b35",
+                "873dff81c02f525623fd1fe5167eac3a55a049de3d314bb42ee227ffed37d508",
+                "0339a36013301597daef41fbe593a02cc513d0b55527ec2df1050e2e8ff49c85c2",
+            ),
+            (
+                "edb2e14f9ee77d26dd93b4ecede8d16ed408ce149b6cd80b0715a2d911a0afea",
+                "47fdacbd0f1097043b78c63c20c34ef4ed9a111d980047ad16282c7ae6236141",
+                "035a784662a4a20a65bf6aab9ae98a6c068a81c52e4b032c0fb5400c706cfccc56",
+            ),
+            (
+                "3c6cb8d0f6a264c91ea8b5030fadaa8e538b020f0a387421a12de9319dc93368",
+                "2a7857631386ba23dacac34180dd1983734e444fdbf774041578e9b6adb37c19",
+                "03501e454bf00751f24b1b489aa925215d66af2234e3891c3b21a52bedb3cd711c",
+            ),
+            (
+                "cbce0d719ecf7431d88e6a89fa1483e02e35092af60c042b1df2ff59fa424dca",
+                "04466b9cc8e161e966409ca52986c584f07e9dc81f735db683c3ff6ec7b1503f",
+                "0357bfe1e341d01c69fe5654309956cbea516822fba8a601743a012a7896ee8dc2",
+            ),
+            (
+                "0f479245fb19a38a1954c5c7c0ebab2f9bdfd96a17563ef28a6a4b1a2a764ef4",
+                "cfb71883f01676f587d023cc53a35bc7f88f724b1f8c2892ac1275ac822a3edd",
+                "02e8445082a72f29b75ca48748a914df60622a609cacfce8ed0e35804560741d29",
+            ),
+            (
+                "471b76e389e528d6de6d816857e012c5455051cad6660850e58372a6c3e6e7c8",
+                "c783e67b921d2beb8f6b389cc646d7263b4145701dadd2161548a8b078e65e9e",
+                "022a471424da5e657499d1ff51cb43c47481a03b1e77f951fe64cec9f5a48f7011",
+            ),
+        ];
+
+        let mut extended_sk = MasterKeyGen::new(seed).generate().unwrap();
+        for (i, (secret_key, chain_code, public_key)) in expected.iter().enumerate() {
+            if i > 0 {
+                extended_sk = extended_sk.child(path[i - 1]).unwrap();
+            }
+            let extended_pk = ExtendedPK::from_secret_key(&extended_sk);
+
+            assert_eq!(extended_sk.secret().to_vec(), from_hex(secret_key));
+            assert_eq!(extended_sk.chain_code.to_vec(), from_hex(chain_code));
+            assert_eq!(extended_pk.key.serialize().to_vec(), from_hex(public_key));
+        }
+    }
+
+    #[test]
+    fn test_public_key_derivation() {
+        let seed = from_hex("000102030405060708090a0b0c0d0e0f");
+        let account = MasterKeyGen::new(seed)
+            .generate()
+            .unwrap()
+            .derive(vec![ChildNumber::hardened(0), ChildNumber::normal(1)])
+            .unwrap();
+        let path = vec![ChildNumber::normal(2), ChildNumber::normal(1_000_000_000)];
+
+        // Deriving the public key of a normal child does not need the secret key
+        let from_secret = ExtendedPK::from_secret_key(&account.derive(path.clone()).unwrap());
+        let from_public = ExtendedPK::from_secret_key(&account).derive(path).unwrap();
+        assert_eq!(from_secret, from_public);
+
+        assert_eq!(
+            ExtendedPK::from_secret_key(&account).child(ChildNumber::hardened(0)),
+            Err(KeyDerivationError::HardenedChildFromPublicKey)
+        );
+    }
 }
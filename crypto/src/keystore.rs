@@ -0,0 +1,350 @@
+//! Encrypted keyfiles
+//!
+//! Secret keys and wallet seeds are stored at rest in a versioned keyfile. The encryption key is
+//! derived from a password using Argon2id, and the secret is encrypted with AES-256-GCM. The
+//! metadata of the keyfile (version, KDF parameters and nonce) is authenticated as additional
+//! data, so the `mac` field of the keyfile also protects it against tampering.
+//!
+//! Keyfiles can be serialized into JSON with `serde`, with binary fields encoded as hex strings:
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "kdf": { "mem_cost": 65536, "time_cost": 3, "lanes": 4, "salt": "..." },
+//!   "nonce": "...",
+//!   "ciphertext": "...",
+//!   "mac": "..."
+//! }
+//! ```
+use crypto::{
+    aead::{AeadDecryptor, AeadEncryptor},
+    aes::KeySize,
+    aes_gcm::AesGcm,
+};
+use failure::Fail;
+use serde::{Deserialize, Serialize};
+
+use witnet_protected::Protected;
+
+use crate::cipher;
+
+/// Current version of the keyfile format
+pub const KEYFILE_VERSION: u32 = 1;
+
+const KEY_LENGTH: usize = 32;
+const SALT_LENGTH: usize = 32;
+const NONCE_LENGTH: usize = 12;
+const MAC_LENGTH: usize = 16;
+
+/// Maximum memory size of the key derivation, in KiB (1 GiB). Keyfiles asking for more are
+/// rejected before deriving the key, so that a crafted keyfile cannot exhaust the memory or the
+/// CPU of the wallet
+pub const MAX_MEM_COST: u32 = 1024 * 1024;
+/// Maximum number of iterations of the key derivation
+pub const MAX_TIME_COST: u32 = 64;
+/// Maximum degree of parallelism of the key derivation
+pub const MAX_LANES: u32 = 64;
+
+/// Error that can be raised when encrypting/decrypting a keyfile
+#[derive(Debug, Fail)]
+pub enum Error {
+    /// The version of the keyfile is not supported
+    #[fail(display = "Unsupported keyfile version: {}", version)]
+    UnsupportedVersion {
+        /// Version found in the keyfile
+        version: u32,
+    },
+    /// The keyfile is malformed
+    #[fail(display = "Malformed keyfile")]
+    MalformedKeyfile,
+    /// Wrong password, or the keyfile has been tampered with
+    #[fail(display = "Wrong password or corrupted keyfile")]
+    Decryption,
+    /// The key derivation parameters exceed the maximum allowed ones
+    #[fail(
+        display = "Key derivation parameters out of bounds (mem_cost: {}, time_cost: {}, lanes: {})",
+        mem_cost, time_cost, lanes
+    )]
+    KdfParamsOutOfBounds {
+        /// Memory size found in the keyfile, in KiB
+        mem_cost: u32,
+        /// Number of iterations found in the keyfile
+        time_cost: u32,
+        /// Degree of parallelism found in the keyfile
+        lanes: u32,
+    },
+    /// Wrapper for key derivation errors
+    #[fail(display = "Key derivation error")]
+    Kdf(argon2::Error),
+    /// Wrapper for random generation errors
+    #[fail(display = "Random generation error")]
+    Rng(cipher::Error),
+}
+
+/// Parameters of the Argon2id key derivation function
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KdfParams {
+    /// Memory size, in KiB
+    pub mem_cost: u32,
+    /// Number of iterations
+    pub time_cost: u32,
+    /// Degree of parallelism
+    pub lanes: u32,
+    /// Random salt
+    #[serde(with = "hex_bytes")]
+    pub salt: Vec<u8>,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        KdfParams {
+            mem_cost: 65536,
+            time_cost: 3,
+            lanes: 4,
+            salt: vec![],
+        }
+    }
+}
+
+/// Encrypted keyfile
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Keyfile {
+    /// Version of the keyfile format
+    pub version: u32,
+    /// Parameters used to derive the encryption key from the password
+    pub kdf: KdfParams,
+    /// AES-256-GCM nonce
+    #[serde(with = "hex_bytes")]
+    pub nonce: Vec<u8>,
+    /// Encrypted secret
+    #[serde(with = "hex_bytes")]
+    pub ciphertext: Vec<u8>,
+    /// Authentication tag of the ciphertext and the metadata
+    #[serde(with = "hex_bytes")]
+    pub mac: Vec<u8>,
+}
+
+/// Encrypt a secret key with a password, using the default KDF parameters
+pub fn encrypt_key(key: &[u8], password: &[u8]) -> Result<Keyfile, Error> {
+    encrypt_key_with_params(key, password, KdfParams::default())
+}
+
+/// Encrypt a secret key with a password, using the given KDF parameters. A random salt is
+/// generated if `params.salt` is empty.
+pub fn encrypt_key_with_params(
+    key: &[u8],
+    password: &[u8],
+    mut params: KdfParams,
+) -> Result<Keyfile, Error> {
+    if params.salt.is_empty() {
+        params.salt = cipher::generate_random(SALT_LENGTH).map_err(Error::Rng)?;
+    }
+    let nonce = cipher::generate_random(NONCE_LENGTH).map_err(Error::Rng)?;
+    let secret = derive_key(password, &params)?;
+    let aad = metadata(KEYFILE_VERSION, &params, &nonce);
+
+    let mut ciphertext = vec![0; key.len()];
+    let mut mac = vec![0; MAC_LENGTH];
+    AesGcm::new(KeySize::KeySize256, &secret, &nonce, &aad).encrypt(key, &mut ciphertext, &mut mac);
+
+    Ok(Keyfile {
+        version: KEYFILE_VERSION,
+        kdf: params,
+        nonce,
+        ciphertext,
+        mac,
+    })
+}
+
+/// Decrypt the secret key stored in a keyfile
+pub fn decrypt_key(keyfile: &Keyfile, password: &[u8]) -> Result<Protected, Error> {
+    if keyfile.version != KEYFILE_VERSION {
+        Err(Error::UnsupportedVersion {
+            version: keyfile.version,
+        })?
+    }
+    if keyfile.nonce.len() != NONCE_LENGTH || keyfile.mac.len() != MAC_LENGTH {
+        Err(Error::MalformedKeyfile)?
+    }
+
+    let secret = derive_key(password, &keyfile.kdf)?;
+    let aad = metadata(keyfile.version, &keyfile.kdf, &keyfile.nonce);
+
+    let mut key = Protected::new(vec![0; keyfile.ciphertext.len()]);
+    let authentic = AesGcm::new(KeySize::KeySize256, &secret, &keyfile.nonce, &aad).decrypt(
+        &keyfile.ciphertext,
+        key.as_mut(),
+        &keyfile.mac,
+    );
+
+    if authentic {
+        Ok(key)
+    } else {
+        Err(Error::Decryption)
+    }
+}
+
+fn derive_key(password: &[u8], params: &KdfParams) -> Result<Protected, Error> {
+    if params.mem_cost > MAX_MEM_COST
+        || params.time_cost > MAX_TIME_COST
+        || params.lanes > MAX_LANES
+    {
+        Err(Error::KdfParamsOutOfBounds {
+            mem_cost: params.mem_cost,
+            time_cost: params.time_cost,
+            lanes: params.lanes,
+        })?
+    }
+
+    let config = argon2::Config {
+        variant: argon2::Variant::Argon2id,
+        version: argon2::Version::Version13,
+        mem_cost: params.mem_cost,
+        time_cost: params.time_cost,
+        lanes: params.lanes,
+        thread_mode: argon2::ThreadMode::Sequential,
+        secret: &[],
+        ad: &[],
+        hash_length: KEY_LENGTH as u32,
+    };
+
+    argon2::hash_raw(password, &params.salt, &config)
+        .map(Protected::new)
+        .map_err(Error::Kdf)
+}
+
+/// Metadata of the keyfile, authenticated as additional data of the cipher
+fn metadata(version: u32, params: &KdfParams, nonce: &[u8]) -> Vec<u8> {
+    let mut aad = vec![];
+    aad.extend_from_slice(&version.to_be_bytes());
+    aad.extend_from_slice(&params.mem_cost.to_be_bytes());
+    aad.extend_from_slice(&params.time_cost.to_be_bytes());
+    aad.extend_from_slice(&params.lanes.to_be_bytes());
+    aad.extend_from_slice(&(params.salt.len() as u32).to_be_bytes());
+    aad.extend_from_slice(&params.salt);
+    aad.extend_from_slice(nonce);
+
+    aad
+}
+
+/// Serialize binary fields as hex strings
+mod hex_bytes {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&hex::encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let string = String::deserialize(deserializer)?;
+
+        hex::decode(string).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Cheap parameters, to keep the tests fast
+    fn test_params() -> KdfParams {
+        KdfParams {
+            mem_cost: 64,
+            time_cost: 1,
+            lanes: 1,
+            salt: vec![],
+        }
+    }
+
+    #[test]
+    fn test_encrypt_decrypt_key() {
+        let key = [0xcd; 32];
+        let keyfile = encrypt_key_with_params(&key, b"password", test_params()).unwrap();
+
+        assert_eq!(keyfile.version, KEYFILE_VERSION);
+        assert_eq!(keyfile.kdf.salt.len(), SALT_LENGTH);
+        assert_ne!(keyfile.ciphertext, key.to_vec());
+        assert_eq!(
+            decrypt_key(&keyfile, b"password").unwrap().as_ref(),
+            &key[..]
+        );
+
+        match decrypt_key(&keyfile, b"wrong password") {
+            Err(Error::Decryption) => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_tampered_metadata() {
+        let key = [0xcd; 32];
+        let keyfile = encrypt_key_with_params(&key, b"password", test_params()).unwrap();
+
+        let mut tampered = keyfile.clone();
+        tampered.kdf.time_cost = 2;
+        assert!(decrypt_key(&tampered, b"password").is_err());
+
+        let mut tampered = keyfile.clone();
+        tampered.nonce[0] ^= 1;
+        assert!(decrypt_key(&tampered, b"password").is_err());
+
+        let mut tampered = keyfile.clone();
+        tampered.version = 2;
+        match decrypt_key(&tampered, b"password") {
+            Err(Error::UnsupportedVersion { version: 2 }) => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+
+        let mut tampered = keyfile.clone();
+        tampered.mac.pop();
+        match decrypt_key(&tampered, b"password") {
+            Err(Error::MalformedKeyfile) => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+
+        // Costly parameters are rejected before deriving the key
+        let mut tampered = keyfile;
+        tampered.kdf.mem_cost = u32::max_value();
+        match decrypt_key(&tampered, b"password") {
+            Err(Error::KdfParamsOutOfBounds { .. }) => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_kdf_params_bounds() {
+        let key = [0xcd; 32];
+        let params = |mem_cost, time_cost, lanes| KdfParams {
+            mem_cost,
+            time_cost,
+            lanes,
+            salt: vec![],
+        };
+
+        for params in vec![
+            params(MAX_MEM_COST + 1, 1, 1),
+            params(64, MAX_TIME_COST + 1, 1),
+            params(64, 1, MAX_LANES + 1),
+        ] {
+            match encrypt_key_with_params(&key, b"password", params) {
+                Err(Error::KdfParamsOutOfBounds { .. }) => {}
+                other => panic!("Unexpected result: {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_keyfile_json() {
+        let key = [0xcd; 32];
+        let keyfile = encrypt_key_with_params(&key, b"password", test_params()).unwrap();
+        let json = serde_json::to_string(&keyfile).unwrap();
+
+        assert!(json.contains(&format!("\"mac\":\"{}\"", hex::encode(&keyfile.mac))));
+        let deserialized: Keyfile = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, keyfile);
+        assert_eq!(
+            decrypt_key(&deserialized, b"password").unwrap().as_ref(),
+            &key[..]
+        );
+    }
+}
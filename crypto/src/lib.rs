@@ -10,12 +10,25 @@
 /// Hash functions
 pub mod hash;
 
+pub mod aead;
+pub mod bls;
+/// Arithmetic of the BN256 curve of the BLS signatures
+mod bn256;
 pub mod cipher;
+pub mod ecdh;
+pub mod eth;
+pub mod hkdf;
 /// Merkle tree implementation
 pub mod merkle;
 
 pub mod key;
+pub mod keystore;
 /// Cryptographic keys, signatures and mnemonic phrases
 pub mod mnemonic;
+pub mod multisig;
 pub mod pbkdf2;
 pub mod signature;
+pub mod signature_cache;
+pub mod transport;
+/// Verifiable random function
+pub mod vrf;
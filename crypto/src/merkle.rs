@@ -11,6 +11,11 @@
 //!  ^   ^   ^  |
 //! a b c d e f g
 //! ```
+//!
+//! * [`MerkleTree`](MerkleTree) keeps the leaves of the tree to generate audit paths and
+//! consistency proofs, which can be verified without the tree using
+//! [`verify_audit_path`](verify_audit_path) and
+//! [`verify_consistency_proof`](verify_consistency_proof).
 
 use crate::hash::{calculate_sha256, Sha256, EMPTY_SHA256};
 
@@ -46,8 +51,7 @@ fn merkle_tree_root_with_hashing_function<T: Copy>(hash_concat: fn(T, T) -> T, h
         }
         n => {
             // n nodes: split into 2 and calculate the root for each half
-            // split at the first power of two greater or equal to n / 2
-            let (left, right) = hashes.split_at(((n + 1) / 2).next_power_of_two());
+            let (left, right) = hashes.split_at(split_point(n));
             let left_hash = merkle_tree_root_with_hashing_function(hash_concat, left);
             let right_hash = merkle_tree_root_with_hashing_function(hash_concat, right);
 
@@ -57,6 +61,12 @@ fn merkle_tree_root_with_hashing_function<T: Copy>(hash_concat: fn(T, T) -> T, h
     }
 }
 
+/// Split point of a subtree with `n > 1` nodes: the first power of two greater or equal to
+/// `n / 2`, which is the largest power of two lower than `n`
+fn split_point(n: usize) -> usize {
+    ((n + 1) / 2).next_power_of_two()
+}
+
 /// Progressive merkle tree.
 ///
 /// Usage:
@@ -165,6 +175,274 @@ impl<T: Copy> ProgressiveMerkleTree<T> {
     }
 }
 
+/// Merkle tree which keeps all of its leaves, so that it can generate audit paths and
+/// consistency proofs.
+///
+/// Usage:
+///
+/// ```
+/// use witnet_crypto::hash::Sha256;
+/// use witnet_crypto::merkle::{
+///     sha256_concat, verify_audit_path, verify_consistency_proof, MerkleTree,
+/// };
+///
+/// let mut mt = MerkleTree::sha256();
+/// for i in 0..5 {
+///     mt.push(Sha256([i; 32]));
+/// }
+/// let old_root = mt.root_at(3).unwrap();
+///
+/// // Prove that the leaf at index 2 belongs to the tree
+/// let path = mt.audit_path(2).unwrap();
+/// assert!(verify_audit_path(sha256_concat, mt.root(), Sha256([2; 32]), 2, 5, &path));
+///
+/// // Prove that the tree with 3 leaves is a prefix of the tree with 5 leaves
+/// let proof = mt.consistency_proof(3).unwrap();
+/// assert!(verify_consistency_proof(sha256_concat, old_root, 3, mt.root(), 5, &proof));
+/// ```
+///
+/// The shape of the tree is the same as in [`merkle_tree_root`](merkle_tree_root), which is
+/// also the shape used by RFC 6962, so proofs are generated and verified with the algorithms
+/// described there.
+#[derive(Debug)]
+pub struct MerkleTree<T: 'static> {
+    leaves: Vec<T>,
+    hash_concat: fn(T, T) -> T,
+    empty_hash: &'static T,
+}
+
+impl MerkleTree<Sha256> {
+    /// Merkle tree using SHA256 function
+    pub fn sha256() -> Self {
+        Self::new(sha256_concat, &EMPTY_SHA256)
+    }
+}
+
+impl<T: Copy> MerkleTree<T> {
+    /// Merkle tree using a custom hashing function. `hash_concat` hashes the concatenation of
+    /// two nodes and `empty_hash` is the root of an empty tree.
+    pub fn new(hash_concat: fn(T, T) -> T, empty_hash: &'static T) -> Self {
+        Self {
+            leaves: vec![],
+            hash_concat,
+            empty_hash,
+        }
+    }
+
+    /// Push a new leaf to the end of the tree
+    pub fn push(&mut self, x: T) {
+        self.leaves.push(x);
+    }
+
+    /// Leaves of the tree
+    pub fn leaves(&self) -> &[T] {
+        &self.leaves
+    }
+
+    /// Number of leaves of the tree
+    pub fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    /// Returns true if the tree has no leaves
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    /// Calculate the current root of the merkle tree
+    pub fn root(&self) -> T {
+        self.subtree_root(&self.leaves)
+    }
+
+    /// Calculate the root that the tree had when it had `tree_size` leaves, or `None` if the
+    /// tree is smaller than that
+    pub fn root_at(&self, tree_size: usize) -> Option<T> {
+        if tree_size > self.leaves.len() {
+            None
+        } else {
+            Some(self.subtree_root(&self.leaves[..tree_size]))
+        }
+    }
+
+    /// Audit path of the leaf at `index`: the siblings of the nodes in the path from the leaf
+    /// to the root, starting from the bottom. Returns `None` if the index is out of bounds.
+    pub fn audit_path(&self, index: usize) -> Option<Vec<T>> {
+        self.audit_path_at(index, self.leaves.len())
+    }
+
+    /// Audit path of the leaf at `index` in the tree with the first `tree_size` leaves
+    pub fn audit_path_at(&self, index: usize, tree_size: usize) -> Option<Vec<T>> {
+        if index >= tree_size || tree_size > self.leaves.len() {
+            None
+        } else {
+            let mut path = vec![];
+            self.subtree_audit_path(index, &self.leaves[..tree_size], &mut path);
+
+            Some(path)
+        }
+    }
+
+    /// Proof that the tree with the first `old_size` leaves is a prefix of the current tree.
+    /// Returns `None` if `old_size` is 0 or greater than the size of the tree.
+    pub fn consistency_proof(&self, old_size: usize) -> Option<Vec<T>> {
+        if old_size == 0 || old_size > self.leaves.len() {
+            None
+        } else {
+            let mut proof = vec![];
+            self.subtree_consistency_proof(old_size, &self.leaves, true, &mut proof);
+
+            Some(proof)
+        }
+    }
+
+    fn subtree_root(&self, hashes: &[T]) -> T {
+        if hashes.is_empty() {
+            *self.empty_hash
+        } else {
+            merkle_tree_root_with_hashing_function(self.hash_concat, hashes)
+        }
+    }
+
+    fn subtree_audit_path(&self, index: usize, hashes: &[T], path: &mut Vec<T>) {
+        if hashes.len() > 1 {
+            let (left, right) = hashes.split_at(split_point(hashes.len()));
+            if index < left.len() {
+                self.subtree_audit_path(index, left, path);
+                path.push(self.subtree_root(right));
+            } else {
+                self.subtree_audit_path(index - left.len(), right, path);
+                path.push(self.subtree_root(left));
+            }
+        }
+    }
+
+    fn subtree_consistency_proof(
+        &self,
+        old_size: usize,
+        hashes: &[T],
+        complete: bool,
+        proof: &mut Vec<T>,
+    ) {
+        if old_size == hashes.len() {
+            // The old tree is a complete subtree of the new tree. Its root is only needed when it
+            // is not the old tree itself, which is already known by the verifier
+            if !complete {
+                proof.push(self.subtree_root(hashes));
+            }
+        } else {
+            let (left, right) = hashes.split_at(split_point(hashes.len()));
+            if old_size <= left.len() {
+                self.subtree_consistency_proof(old_size, left, complete, proof);
+                proof.push(self.subtree_root(right));
+            } else {
+                self.subtree_consistency_proof(old_size - left.len(), right, false, proof);
+                proof.push(self.subtree_root(left));
+            }
+        }
+    }
+}
+
+/// Verify that `leaf` is the leaf at `index` of the tree with `tree_size` leaves and root
+/// `root`, using an audit path generated by [`MerkleTree::audit_path`](MerkleTree::audit_path)
+pub fn verify_audit_path<T: Copy + PartialEq>(
+    hash_concat: fn(T, T) -> T,
+    root: T,
+    leaf: T,
+    index: usize,
+    tree_size: usize,
+    path: &[T],
+) -> bool {
+    if index >= tree_size {
+        return false;
+    }
+
+    // Index of the current node in its level, and index of the last node of the level
+    let mut node = index;
+    let mut last_node = tree_size - 1;
+    let mut hash = leaf;
+    for &sibling in path {
+        if last_node == 0 {
+            // The path is longer than the height of the tree
+            return false;
+        }
+        if node % 2 == 1 || node == last_node {
+            hash = hash_concat(sibling, hash);
+            // The rightmost node may have been promoted to the next levels
+            while node % 2 == 0 && node != 0 {
+                node >>= 1;
+                last_node >>= 1;
+            }
+        } else {
+            hash = hash_concat(hash, sibling);
+        }
+        node >>= 1;
+        last_node >>= 1;
+    }
+
+    last_node == 0 && hash == root
+}
+
+/// Verify that the tree with `old_size` leaves and root `old_root` is a prefix of the tree with
+/// `new_size` leaves and root `new_root`, using a proof generated by
+/// [`MerkleTree::consistency_proof`](MerkleTree::consistency_proof)
+pub fn verify_consistency_proof<T: Copy + PartialEq>(
+    hash_concat: fn(T, T) -> T,
+    old_root: T,
+    old_size: usize,
+    new_root: T,
+    new_size: usize,
+    proof: &[T],
+) -> bool {
+    if old_size == 0 || old_size > new_size {
+        return false;
+    }
+    if old_size == new_size {
+        return proof.is_empty() && old_root == new_root;
+    }
+
+    // When the old tree is a complete subtree of the new tree, its root is omitted in the proof
+    let mut proof: Vec<T> = proof.to_vec();
+    if old_size.is_power_of_two() {
+        proof.insert(0, old_root);
+    }
+    let (&first, rest) = match proof.split_first() {
+        Some(split) => split,
+        None => return false,
+    };
+
+    // Index of the last node of the old tree and the new tree in the current level
+    let mut old_node = old_size - 1;
+    let mut new_node = new_size - 1;
+    // Skip the levels where the rightmost node of the old tree is a right child
+    while old_node % 2 == 1 {
+        old_node >>= 1;
+        new_node >>= 1;
+    }
+
+    let mut old_hash = first;
+    let mut new_hash = first;
+    for &sibling in rest {
+        if new_node == 0 {
+            // The proof is longer than the height of the tree
+            return false;
+        }
+        if old_node % 2 == 1 || old_node == new_node {
+            old_hash = hash_concat(sibling, old_hash);
+            new_hash = hash_concat(sibling, new_hash);
+            while old_node % 2 == 0 && old_node != 0 {
+                old_node >>= 1;
+                new_node >>= 1;
+            }
+        } else {
+            new_hash = hash_concat(new_hash, sibling);
+        }
+        old_node >>= 1;
+        new_node >>= 1;
+    }
+
+    new_node == 0 && old_hash == old_root && new_hash == new_root
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -178,4 +456,126 @@ mod test {
         let root = merkle_tree_root_with_hashing_function(dummy, &hashes);
         assert_eq!(root, x);
     }
+
+    fn tree(size: u8) -> MerkleTree<Sha256> {
+        let mut mt = MerkleTree::sha256();
+        for i in 0..size {
+            mt.push(Sha256([i; 32]));
+        }
+
+        mt
+    }
+
+    #[test]
+    fn merkle_tree_root_matches() {
+        let mut progressive = ProgressiveMerkleTree::sha256();
+        for size in 0..20 {
+            let mt = tree(size);
+            assert_eq!(mt.root(), merkle_tree_root(mt.leaves()));
+            assert_eq!(mt.root(), progressive.root());
+            progressive.push(Sha256([size; 32]));
+        }
+    }
+
+    #[test]
+    fn audit_paths() {
+        let mt = tree(17);
+        for tree_size in 1..=mt.len() {
+            let root = mt.root_at(tree_size).unwrap();
+            for index in 0..tree_size {
+                let leaf = mt.leaves()[index];
+                let path = mt.audit_path_at(index, tree_size).unwrap();
+                assert!(verify_audit_path(
+                    sha256_concat,
+                    root,
+                    leaf,
+                    index,
+                    tree_size,
+                    &path
+                ));
+
+                // Wrong index or leaf
+                let other = (index + 1) % tree_size;
+                if other != index {
+                    assert!(!verify_audit_path(
+                        sha256_concat,
+                        root,
+                        leaf,
+                        other,
+                        tree_size,
+                        &path
+                    ));
+                    let other_leaf = mt.leaves()[other];
+                    assert!(!verify_audit_path(
+                        sha256_concat,
+                        root,
+                        other_leaf,
+                        index,
+                        tree_size,
+                        &path
+                    ));
+                }
+                // Paths which are too long
+                let mut long_path = path.clone();
+                long_path.push(leaf);
+                assert!(!verify_audit_path(
+                    sha256_concat,
+                    root,
+                    leaf,
+                    index,
+                    tree_size,
+                    &long_path
+                ));
+            }
+        }
+
+        assert_eq!(mt.audit_path(17), None);
+        assert_eq!(mt.audit_path_at(3, 18), None);
+    }
+
+    #[test]
+    fn consistency_proofs() {
+        let mt = tree(17);
+        for new_size in 1..=mt.len() {
+            let new_tree = tree(new_size as u8);
+            let new_root = new_tree.root();
+            for old_size in 1..=new_size {
+                let old_root = mt.root_at(old_size).unwrap();
+                let proof = new_tree.consistency_proof(old_size).unwrap();
+                assert!(verify_consistency_proof(
+                    sha256_concat,
+                    old_root,
+                    old_size,
+                    new_root,
+                    new_size,
+                    &proof
+                ));
+
+                // Tampered proofs and roots
+                if !proof.is_empty() {
+                    let mut tampered = proof.clone();
+                    tampered[0] = Sha256([0xff; 32]);
+                    assert!(!verify_consistency_proof(
+                        sha256_concat,
+                        old_root,
+                        old_size,
+                        new_root,
+                        new_size,
+                        &tampered
+                    ));
+                }
+                assert!(!verify_consistency_proof(
+                    sha256_concat,
+                    Sha256([0xff; 32]),
+                    old_size,
+                    new_root,
+                    new_size,
+                    &proof
+                ));
+            }
+        }
+
+        assert_eq!(mt.consistency_proof(0), None);
+        assert_eq!(mt.consistency_proof(18), None);
+    }
 }
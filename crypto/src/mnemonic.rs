@@ -14,8 +14,13 @@
 //! // Seed that can be used to generate a master secret key
 //! let seed = mnemonic.seed(passphrase);
 //! ```
+//!
+//! Phrases and passphrases are normalized to Unicode NFKD before use, as
+//! mandated by BIP39, so the same words typed with composed or decomposed
+//! characters always yield the same seed.
 use bip39;
-use failure::Error;
+use failure::{format_err, Error};
+use unicode_normalization::UnicodeNormalization;
 
 /// BIP39 Mnemonic
 pub struct Mnemonic(bip39::Mnemonic);
@@ -26,17 +31,52 @@ impl Mnemonic {
         self.0.phrase()
     }
 
+    /// Get the entropy encoded by the mnemonic words
+    pub fn entropy(&self) -> &[u8] {
+        self.0.entropy()
+    }
+
     /// Get the binary seed used for generating a master secret key
     pub fn seed<'a, S: Into<&'a str>>(&self, passphrase: S) -> Seed {
-        Seed(bip39::Seed::new(&self.0, passphrase.into()))
+        let passphrase = normalize(passphrase.into());
+
+        Seed(bip39::Seed::new(&self.0, &passphrase))
     }
 
     /// Get a mnemonic from a existing pharse
     pub fn from_phrase(phrase: String, language: Lang) -> Result<Mnemonic, Error> {
-        bip39::Mnemonic::from_phrase(phrase, language.into()).map(Mnemonic)
+        bip39::Mnemonic::from_phrase(normalize(&phrase), language.into()).map(Mnemonic)
+    }
+
+    /// Get a mnemonic from its entropy, which must be 128, 160, 192, 224 or 256 bits long
+    pub fn from_entropy(entropy: &[u8], language: Lang) -> Result<Mnemonic, Error> {
+        bip39::Mnemonic::from_entropy(entropy, language.into()).map(Mnemonic)
+    }
+
+    /// Check that all the words of a phrase belong to the wordlist of the
+    /// language and that its checksum is valid
+    pub fn validate(phrase: &str, language: Lang) -> Result<(), Error> {
+        bip39::Mnemonic::validate(&normalize(phrase), language.into())
+    }
+
+    /// Get a mnemonic from a existing phrase, detecting the language of its words
+    pub fn from_phrase_detect(phrase: String) -> Result<(Mnemonic, Lang), Error> {
+        let phrase = normalize(&phrase);
+        let lang = Lang::detect(&phrase)
+            .ok_or_else(|| format_err!("The mnemonic words do not belong to any known language"))?;
+
+        bip39::Mnemonic::from_phrase(phrase, lang.into()).map(|m| (Mnemonic(m), lang))
     }
 }
 
+/// Normalize a phrase or passphrase to Unicode NFKD and collapse its
+/// whitespace into single ASCII spaces
+fn normalize(s: &str) -> String {
+    let normalized: String = s.nfkd().collect();
+
+    normalized.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
 /// BIP39 Seed generated from a Mnemonic
 pub struct Seed(bip39::Seed);
 
@@ -78,16 +118,65 @@ pub enum Length {
 }
 
 /// The language in which Mnemonics are generated
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum Lang {
     /// English language
     English,
+    /// Simplified Chinese language
+    ChineseSimplified,
+    /// Traditional Chinese language
+    ChineseTraditional,
+    /// French language
+    French,
+    /// Italian language
+    Italian,
+    /// Japanese language
+    Japanese,
+    /// Korean language
+    Korean,
+    /// Spanish language
+    Spanish,
+}
+
+impl Lang {
+    /// All the languages with a standard BIP39 wordlist
+    pub const ALL: [Lang; 8] = [
+        Lang::English,
+        Lang::ChineseSimplified,
+        Lang::ChineseTraditional,
+        Lang::French,
+        Lang::Italian,
+        Lang::Japanese,
+        Lang::Korean,
+        Lang::Spanish,
+    ];
+
+    /// Detect the language of a mnemonic phrase
+    ///
+    /// Returns the first language whose wordlist contains every word of the
+    /// phrase and whose checksum is valid. Some words are shared between the
+    /// Chinese wordlists, so in case of ambiguity the simplified variant wins.
+    pub fn detect(phrase: &str) -> Option<Lang> {
+        let phrase = normalize(phrase);
+
+        Lang::ALL
+            .iter()
+            .cloned()
+            .find(|lang| bip39::Mnemonic::validate(&phrase, (*lang).into()).is_ok())
+    }
 }
 
 impl Into<bip39::Language> for Lang {
     fn into(self) -> bip39::Language {
         match self {
             Lang::English => bip39::Language::English,
+            Lang::ChineseSimplified => bip39::Language::ChineseSimplified,
+            Lang::ChineseTraditional => bip39::Language::ChineseTraditional,
+            Lang::French => bip39::Language::French,
+            Lang::Italian => bip39::Language::Italian,
+            Lang::Japanese => bip39::Language::Japanese,
+            Lang::Korean => bip39::Language::Korean,
+            Lang::Spanish => bip39::Language::Spanish,
         }
     }
 }
@@ -133,10 +222,7 @@ impl MnemonicGen {
             Length::Words21 => bip39::MnemonicType::Words21,
             Length::Words24 => bip39::MnemonicType::Words24,
         };
-        let lang = match self.lang {
-            Lang::English => bip39::Language::English,
-        };
-        let mnemonic = bip39::Mnemonic::new(mnemonic_type, lang);
+        let mnemonic = bip39::Mnemonic::new(mnemonic_type, self.lang.into());
 
         Mnemonic(mnemonic)
     }
@@ -178,4 +264,207 @@ mod tests {
 
         assert_eq!(bytes, seed.as_bytes());
     }
+
+    #[test]
+    fn test_seed_test_vector() {
+        let phrase = "abandon abandon abandon abandon abandon abandon abandon abandon \
+                      abandon abandon abandon about";
+        let mnemonic = Mnemonic::from_phrase(phrase.to_string(), Lang::English).unwrap();
+        let seed = mnemonic.seed("TREZOR");
+        let expected = [
+            0xc5, 0x52, 0x57, 0xc3, 0x60, 0xc0, 0x7c, 0x72, 0x02, 0x9a, 0xeb, 0xc1, 0xb5, 0x3c,
+            0x05, 0xed, 0x03, 0x62, 0xad, 0xa3, 0x8e, 0xad, 0x3e, 0x3e, 0x9e, 0xfa, 0x37, 0x08,
+            0xe5, 0x34, 0x95, 0x53, 0x1f, 0x09, 0xa6, 0x98, 0x75, 0x99, 0xd1, 0x82, 0x64, 0xc1,
+            0xe1, 0xc9, 0x2f, 0x2c, 0xf1, 0x41, 0x63, 0x0c, 0x7a, 0x3c, 0x4a, 0xb7, 0xc8, 0x1b,
+            0x2f, 0x00, 0x16, 0x98, 0xe7, 0x46, 0x3b, 0x04,
+        ];
+
+        assert_eq!(seed.as_bytes(), &expected[..]);
+    }
+
+    #[test]
+    fn test_from_entropy() {
+        let mnemonic = Mnemonic::from_entropy(&[0; 16], Lang::English).unwrap();
+
+        assert_eq!(
+            mnemonic.words(),
+            "abandon abandon abandon abandon abandon abandon abandon abandon \
+             abandon abandon abandon about"
+        );
+        assert_eq!(mnemonic.entropy(), &[0; 16]);
+        assert!(Mnemonic::from_entropy(&[0; 15], Lang::English).is_err());
+    }
+
+    #[test]
+    fn test_seed_test_vector_24_words() {
+        let mnemonic = Mnemonic::from_entropy(&[0; 32], Lang::English).unwrap();
+        let words: Vec<&str> = mnemonic.words().split_whitespace().collect();
+        assert_eq!(words.len(), 24);
+        assert_eq!(words[23], "art");
+
+        let seed = mnemonic.seed("TREZOR");
+        let expected = [
+            0xbd, 0xa8, 0x54, 0x46, 0xc6, 0x84, 0x13, 0x70, 0x70, 0x90, 0xa5, 0x20, 0x22, 0xed,
+            0xd2, 0x6a, 0x1c, 0x94, 0x62, 0x29, 0x50, 0x29, 0xf2, 0xe6, 0x0c, 0xd7, 0xc4, 0xf2,
+            0xbb, 0xd3, 0x09, 0x71, 0x70, 0xaf, 0x7a, 0x4d, 0x73, 0x24, 0x5c, 0xaf, 0xa9, 0xc3,
+            0xcc, 0xa8, 0xd5, 0x61, 0xa7, 0xc3, 0xde, 0x6f, 0x5d, 0x4a, 0x10, 0xbe, 0x8e, 0xd2,
+            0xa5, 0xe6, 0x08, 0xd6, 0x8f, 0x92, 0xfc, 0xc8,
+        ];
+
+        assert_eq!(seed.as_bytes(), &expected[..]);
+    }
+
+    #[test]
+    fn test_validate_checksum() {
+        let valid = "abandon abandon abandon abandon abandon abandon abandon abandon \
+                     abandon abandon abandon about";
+        let invalid = "abandon abandon abandon abandon abandon abandon abandon abandon \
+                       abandon abandon abandon abandon";
+
+        assert!(Mnemonic::validate(valid, Lang::English).is_ok());
+        assert!(Mnemonic::validate(invalid, Lang::English).is_err());
+        assert!(Mnemonic::from_phrase(invalid.to_string(), Lang::English).is_err());
+    }
+
+    #[test]
+    fn test_seed_to_master_key() {
+        let mnemonic = Mnemonic::from_entropy(&[0; 16], Lang::English).unwrap();
+        let seed = mnemonic.seed("TREZOR");
+        let master_key = crate::key::MasterKeyGen::new(seed).generate().unwrap();
+        let expected_secret_key = [
+            203, 237, 199, 91, 13, 100, 18, 200, 92, 121, 188, 19, 135, 81, 18, 239, 145, 47, 209,
+            231, 86, 99, 27, 90, 0, 51, 8, 102, 242, 47, 241, 132,
+        ];
+
+        assert_eq!(&master_key.secret()[..], &expected_secret_key[..]);
+    }
+
+    #[test]
+    fn test_detect_all_languages() {
+        for lang in Lang::ALL.iter().cloned() {
+            let mnemonic = MnemonicGen::new().with_lang(lang).generate();
+            let (imported, detected) =
+                Mnemonic::from_phrase_detect(mnemonic.words().to_string()).unwrap();
+
+            assert_eq!(imported.words(), mnemonic.words());
+            // The Chinese wordlists overlap, so a traditional phrase may be
+            // detected as simplified; the seed must be the same anyway
+            assert_eq!(imported.seed("").as_bytes(), mnemonic.seed("").as_bytes());
+            if lang != Lang::ChineseTraditional {
+                assert_eq!(detected, lang);
+            }
+        }
+    }
+
+    #[test]
+    fn test_detect_unknown_language() {
+        assert_eq!(Lang::detect("not a valid mnemonic phrase at all"), None);
+        assert!(Mnemonic::from_phrase_detect("foo bar".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_passphrase_nfkd() {
+        let mnemonic = MnemonicGen::new().generate();
+        // "é" as a single code point and as "e" followed by a combining accent
+        let composed = mnemonic.seed("caf\u{e9}");
+        let decomposed = mnemonic.seed("cafe\u{301}");
+
+        assert_eq!(composed.as_bytes(), decomposed.as_bytes());
+    }
+
+    #[test]
+    fn test_phrase_nfkd() {
+        let mnemonic = MnemonicGen::new().with_lang(Lang::Spanish).generate();
+        let composed: String = mnemonic.words().nfc().collect();
+        let imported = Mnemonic::from_phrase(composed, Lang::Spanish).unwrap();
+
+        assert_eq!(imported.seed("").as_bytes(), mnemonic.seed("").as_bytes());
+    }
+
+    #[test]
+    fn test_seed_test_vectors_japanese() {
+        // Test vectors of the Japanese wordlist: the words are separated by ideographic spaces,
+        // and both the phrase and the passphrase must be normalized to NFKD
+        let passphrase = "㍍ガバヴァぱばぐゞちぢ十人十色";
+        let vectors = [
+            (
+                [0x00; 16],
+                "あいこくしん\u{3000}あいこくしん\u{3000}あいこくしん\u{3000}あいこくしん\u{3000}あいこくしん\u{3000}\
+                 あいこくしん\u{3000}あいこくしん\u{3000}あいこくしん\u{3000}あいこくしん\u{3000}あいこくしん\u{3000}\
+                 あいこくしん\u{3000}あおぞら",
+                "a262d6fb6122ecf45be09c50492b31f92e9beb7d9a845987a02cefda57a15f9c467a17872029a9e92299b5cbdf306e3a0ee620245cbd508959b6cb7ca637bd55",
+            ),
+            (
+                [0x7f; 16],
+                "そつう\u{3000}れきだい\u{3000}ほんやく\u{3000}わかす\u{3000}りくつ\u{3000}ばいか\u{3000}\
+                 ろせん\u{3000}やちん\u{3000}そつう\u{3000}れきだい\u{3000}ほんやく\u{3000}わかめ",
+                "aee025cbe6ca256862f889e48110a6a382365142f7d16f2b9545285b3af64e542143a577e9c144e101a6bdca18f8d97ec3366ebf5b088b1c1af9bc31346e60d9",
+            ),
+        ];
+
+        for (entropy, phrase, expected_seed) in vectors.iter() {
+            let mnemonic = Mnemonic::from_phrase(phrase.to_string(), Lang::Japanese).unwrap();
+            assert_eq!(mnemonic.entropy(), entropy);
+            assert_eq!(
+                hex::encode(mnemonic.seed(passphrase).as_bytes()),
+                *expected_seed
+            );
+
+            let from_entropy = Mnemonic::from_entropy(entropy, Lang::Japanese).unwrap();
+            assert_eq!(from_entropy.words(), normalize(phrase));
+        }
+    }
+
+    #[test]
+    fn test_seed_test_vector_every_wordlist() {
+        // Mnemonics of the entropy `[0x80; 16]` in the other wordlists, with the seeds derived
+        // from them with the passphrase "TREZOR" as specified by BIP39
+        let vectors = [
+            (
+                Lang::ChineseSimplified,
+                "壤 对 据 人 三 谈 我 表 壤 对 据 不",
+                "07b6eada2601141ef9748bdf5af296a134f0f9215a946813b84338dcfba93c8247b0c3429a91e0a1b85a93bd9f1275a9524acecadc9b516c3cf4c8990f44052c",
+            ),
+            (
+                Lang::ChineseTraditional,
+                "壤 對 據 人 三 談 我 表 壤 對 據 不",
+                "33f373da1a6b4300dad5cc70d2329ed614512e3c8a423673c294110521326ca66753b9663bdd7c844f17d81609a410a61809dd5113823009f729e2f2f940cab9",
+            ),
+            (
+                Lang::French,
+                "indexer acompte bolide abrasif agréable dédale abusif appuyer indexer acompte bolide abolir",
+                "0c1ece83a464688d74744723d609e30e191d05ab8c082cf34bb2405bc4363dbcf6a9f83707b577d230728b3943920f876ec844e86dd0d117152c23802d25be3f",
+            ),
+            (
+                Lang::Italian,
+                "misurare afoso bravura accadere alogeno dottore acrilico arazzo misurare afoso bravura abisso",
+                "4025269bc4f7550bbc3c61592944946b0d4ac855a5e4582bf86069cc0c9429455cc40d84ba215ed1cec28e27ffc88460c38b9c4e8c486ae878d7c85e95b222bf",
+            ),
+            (
+                Lang::Korean,
+                "실현 감소 기법 가상 걱정 무슨 가족 공간 실현 감소 기법 가득",
+                "1bb52039a6cc288cf806740836002abce493724edac3d3b9458e3581427df76414b422171ef115d823a01c6b39fa68bd0fed20bf5e64dec008fcb22e4b7f26bb",
+            ),
+            (
+                Lang::Spanish,
+                "lino admitir bolero abrir álbum dejar acelga aprender lino admitir bolero abogado",
+                "a89366f7f9c4bd98afca8edf1242507506562b8eb8a3a60468cafcb6f3037aba1e4d9a7497f6d49fa94aca87c95703873741441a719325af371f8eda9b59dc83",
+            ),
+        ];
+
+        for (lang, phrase, expected_seed) in vectors.iter() {
+            let mnemonic = Mnemonic::from_entropy(&[0x80; 16], *lang).unwrap();
+            assert_eq!(mnemonic.words(), normalize(phrase));
+            assert_eq!(
+                hex::encode(mnemonic.seed("TREZOR").as_bytes()),
+                *expected_seed
+            );
+
+            let imported = Mnemonic::from_phrase(phrase.to_string(), *lang).unwrap();
+            assert_eq!(
+                imported.seed("TREZOR").as_bytes(),
+                mnemonic.seed("TREZOR").as_bytes()
+            );
+        }
+    }
 }
@@ -0,0 +1,710 @@
+//! Aggregated multi-signatures
+//!
+//! MuSig-style Schnorr multi-signatures over secp256k1: a group of signers aggregates their
+//! public keys into a single public key, and together they produce a single signature which is
+//! verified against the aggregated key like a signature from one signer.
+//!
+//! Each public key is weighted by a coefficient derived from the whole set of keys, so a signer
+//! cannot choose its key to cancel the keys of the others. Signing takes three rounds:
+//!
+//! 1. Every signer sends a commitment to its nonce, [`nonce_commitment`](Signer::nonce_commitment).
+//! 2. Once all the commitments have been received, every signer sends its public nonce,
+//!    [`public_nonce`](Signer::public_nonce), which is checked against its commitment.
+//! 3. Once all the public nonces have been received, every signer sends its partial signature,
+//!    [`partial_signature`](Signer::partial_signature). This consumes the signer, so that its
+//!    nonce never signs twice, and returns a [`Combiner`] which collects the partial signatures
+//!    of the others into the final [`signature`](Combiner::signature).
+//!
+//! A signature is the compressed nonce point `R` (33 bytes) followed by the scalar `s` (32
+//! bytes), and it is valid if `s * G = R + e * X`, where `X` is the aggregated key and
+//! `e = H(R || X || message)`.
+
+use failure::Fail;
+use secp256k1::{PublicKey, Secp256k1, SecretKey};
+
+use crate::{
+    hash::{calculate_tagged_sha256, Sha256},
+    signature::secp256k1_context,
+};
+
+/// Length of a multi-signature in bytes
+pub const SIGNATURE_LENGTH: usize = POINT_LENGTH + SCALAR_LENGTH;
+
+const POINT_LENGTH: usize = 33;
+const SCALAR_LENGTH: usize = 32;
+
+/// Tag of the hash of the set of public keys
+const KEYS_TAG: &str = "witnet-musig-keys";
+/// Tag of the hash which derives the coefficient of each public key
+const COEFFICIENT_TAG: &str = "witnet-musig-coefficient";
+/// Tag of the commitments to the nonces
+const NONCE_TAG: &str = "witnet-musig-nonce";
+/// Tag of the hash which derives the challenge of a signature
+const CHALLENGE_TAG: &str = "witnet-musig-challenge";
+
+/// The error type for operations with multi-signatures
+#[derive(Debug, PartialEq, Fail)]
+pub enum MultisigError {
+    #[fail(display = "Cannot aggregate an empty set of public keys")]
+    /// There are no public keys to aggregate
+    NoKeys,
+    #[fail(display = "The same public key cannot be aggregated twice")]
+    /// A public key appears more than once in the set of keys
+    DuplicateKey,
+    #[fail(display = "The secret key does not belong to any of the aggregated keys")]
+    /// The signer is not one of the owners of the aggregated key
+    UnknownSigner,
+    #[fail(display = "There is no signer with index {}", index)]
+    /// The index does not match any of the aggregated keys
+    InvalidIndex {
+        /// Index of the signer
+        index: usize,
+    },
+    #[fail(display = "Nonce commitments from all the signers are needed")]
+    /// Public nonces cannot be revealed until all the commitments have been received
+    MissingCommitments,
+    #[fail(display = "Public nonces from all the signers are needed")]
+    /// Partial signatures cannot be computed until all the public nonces have been received
+    MissingNonces,
+    #[fail(display = "Partial signatures from all the signers are needed")]
+    /// The signature cannot be combined until all the partial signatures have been received
+    MissingPartialSignatures,
+    #[fail(display = "Signer {} already sent a different nonce commitment", index)]
+    /// A signer cannot replace its nonce commitment, which would let it choose its nonce after
+    /// seeing the others
+    ConflictingCommitment {
+        /// Index of the signer
+        index: usize,
+    },
+    #[fail(
+        display = "The public nonce of signer {} does not match its commitment",
+        index
+    )]
+    /// The public nonce of a signer does not match the commitment it sent before
+    NonceCommitmentMismatch {
+        /// Index of the signer
+        index: usize,
+    },
+    #[fail(display = "Invalid partial signature from signer {}", index)]
+    /// The partial signature of a signer is not valid
+    InvalidPartialSignature {
+        /// Index of the signer
+        index: usize,
+    },
+    #[fail(display = "A hash or a sum of scalars is not a valid scalar")]
+    /// A derived scalar is zero or not lower than the curve order, which happens with
+    /// negligible probability
+    InvalidScalar,
+    #[fail(display = "Malformed multi-signature")]
+    /// The signature cannot be decoded
+    MalformedSignature,
+    #[fail(display = "Invalid multi-signature")]
+    /// The signature does not match the aggregated key and the message
+    InvalidSignature,
+}
+
+/// Aggregation of a set of public keys into a single public key
+#[derive(Clone, Debug, PartialEq)]
+pub struct AggregatedKey {
+    /// The public keys, sorted by their serialization
+    keys: Vec<PublicKey>,
+    /// Coefficient of each public key
+    coefficients: Vec<SecretKey>,
+    /// Aggregated public key
+    public_key: PublicKey,
+}
+
+impl AggregatedKey {
+    /// Aggregate a set of public keys. The result does not depend on the order of the keys.
+    pub fn new(keys: &[PublicKey]) -> Result<Self, MultisigError> {
+        if keys.is_empty() {
+            Err(MultisigError::NoKeys)?
+        }
+        let mut keys = keys.to_vec();
+        keys.sort_by(|a, b| a.serialize()[..].cmp(&b.serialize()[..]));
+        if keys.windows(2).any(|pair| pair[0] == pair[1]) {
+            Err(MultisigError::DuplicateKey)?
+        }
+
+        let serialized_keys: Vec<u8> = keys
+            .iter()
+            .flat_map(|key| key.serialize().to_vec())
+            .collect();
+        let Sha256(keys_hash) = calculate_tagged_sha256(KEYS_TAG, &serialized_keys);
+
+        let secp = secp256k1_context();
+        let mut coefficients = Vec::with_capacity(keys.len());
+        let mut weighted_keys = Vec::with_capacity(keys.len());
+        for key in &keys {
+            let mut bytes = keys_hash.to_vec();
+            bytes.extend_from_slice(&key.serialize());
+            let coefficient = scalar_from_hash(&calculate_tagged_sha256(COEFFICIENT_TAG, &bytes))?;
+
+            weighted_keys.push(mul_point(secp, key, &coefficient[..])?);
+            coefficients.push(coefficient);
+        }
+        let public_key = sum_points(&weighted_keys)?;
+
+        Ok(AggregatedKey {
+            keys,
+            coefficients,
+            public_key,
+        })
+    }
+
+    /// Aggregated public key, which verifies the signatures of the group
+    pub fn public_key(&self) -> PublicKey {
+        self.public_key
+    }
+
+    /// Aggregated public keys, sorted. The index of a signer is the index of its key here.
+    pub fn keys(&self) -> &[PublicKey] {
+        &self.keys
+    }
+
+    /// Index of a public key in the sorted list of keys
+    pub fn index_of(&self, key: &PublicKey) -> Option<usize> {
+        self.keys.iter().position(|k| k == key)
+    }
+}
+
+/// Schnorr signature produced by a group of signers
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Signature {
+    /// Aggregated nonce point
+    pub r: PublicKey,
+    /// Sum of the partial signatures
+    pub s: SecretKey,
+}
+
+impl Signature {
+    /// Decode a signature from `r || s`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, MultisigError> {
+        if bytes.len() != SIGNATURE_LENGTH {
+            Err(MultisigError::MalformedSignature)?
+        }
+        let r = PublicKey::from_slice(&bytes[..POINT_LENGTH])
+            .map_err(|_| MultisigError::MalformedSignature)?;
+        let s = SecretKey::from_slice(&bytes[POINT_LENGTH..])
+            .map_err(|_| MultisigError::MalformedSignature)?;
+
+        Ok(Signature { r, s })
+    }
+
+    /// Encode the signature as `r || s`
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(SIGNATURE_LENGTH);
+        bytes.extend_from_slice(&self.r.serialize());
+        bytes.extend_from_slice(&self.s[..]);
+
+        bytes
+    }
+}
+
+/// State of one of the signers during the signing of a message
+pub struct Signer {
+    aggregated_key: AggregatedKey,
+    index: usize,
+    secret_key: SecretKey,
+    message: Vec<u8>,
+    nonce: SecretKey,
+    commitments: Vec<Option<Sha256>>,
+    public_nonces: Vec<Option<PublicKey>>,
+}
+
+impl Signer {
+    /// Start signing `message` with one of the secret keys of the aggregated key. A fresh random
+    /// nonce is generated for every signer, which is consumed by its partial signature.
+    pub fn new(
+        aggregated_key: AggregatedKey,
+        secret_key: SecretKey,
+        message: &[u8],
+    ) -> Result<Self, MultisigError> {
+        let secp = secp256k1_context();
+        let index = aggregated_key
+            .index_of(&PublicKey::from_secret_key(secp, &secret_key))
+            .ok_or(MultisigError::UnknownSigner)?;
+        let signers = aggregated_key.keys.len();
+
+        let nonce = SecretKey::new(&mut secp256k1::rand::thread_rng());
+        let public_nonce = PublicKey::from_secret_key(secp, &nonce);
+        let mut commitments = vec![None; signers];
+        commitments[index] = Some(nonce_commitment(&public_nonce));
+        let mut public_nonces = vec![None; signers];
+        public_nonces[index] = Some(public_nonce);
+
+        Ok(Signer {
+            aggregated_key,
+            index,
+            secret_key,
+            message: message.to_vec(),
+            nonce,
+            commitments,
+            public_nonces,
+        })
+    }
+
+    /// Index of this signer in the aggregated key
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Round 1: commitment to the nonce of this signer, to be sent to the other signers
+    pub fn nonce_commitment(&self) -> Sha256 {
+        nonce_commitment(&PublicKey::from_secret_key(
+            secp256k1_context(),
+            &self.nonce,
+        ))
+    }
+
+    /// Round 1: store the nonce commitment received from the signer at `index`
+    pub fn add_nonce_commitment(
+        &mut self,
+        index: usize,
+        commitment: Sha256,
+    ) -> Result<(), MultisigError> {
+        self.check_index(index)?;
+        match self.commitments[index] {
+            Some(previous) if previous != commitment => {
+                Err(MultisigError::ConflictingCommitment { index })?
+            }
+            _ => self.commitments[index] = Some(commitment),
+        }
+
+        Ok(())
+    }
+
+    /// Round 2: public nonce of this signer, to be sent to the other signers. It is only
+    /// revealed after receiving the commitments of all the signers.
+    pub fn public_nonce(&self) -> Result<PublicKey, MultisigError> {
+        if self.commitments.iter().any(Option::is_none) {
+            Err(MultisigError::MissingCommitments)?
+        }
+
+        Ok(PublicKey::from_secret_key(secp256k1_context(), &self.nonce))
+    }
+
+    /// Round 2: store the public nonce received from the signer at `index`, which must match
+    /// its commitment. As commitments cannot be replaced, neither can the nonces.
+    pub fn add_public_nonce(
+        &mut self,
+        index: usize,
+        public_nonce: PublicKey,
+    ) -> Result<(), MultisigError> {
+        self.check_index(index)?;
+        if self.commitments[index] != Some(nonce_commitment(&public_nonce)) {
+            Err(MultisigError::NonceCommitmentMismatch { index })?
+        }
+        self.public_nonces[index] = Some(public_nonce);
+
+        Ok(())
+    }
+
+    /// Round 3: partial signature of this signer, to be sent to the other signers. It can only
+    /// be computed after receiving the public nonces of all the signers. The signer is consumed,
+    /// so that its nonce is never used to sign another challenge, and the returned `Combiner`
+    /// collects the partial signatures of the other signers.
+    pub fn partial_signature(self) -> Result<(SecretKey, Combiner), MultisigError> {
+        let public_nonces = self
+            .public_nonces
+            .iter()
+            .cloned()
+            .collect::<Option<Vec<_>>>()
+            .ok_or(MultisigError::MissingNonces)?;
+        let r = sum_points(&public_nonces)?;
+        let challenge = challenge(&r, &self.aggregated_key.public_key, &self.message)?;
+
+        // s_i = k_i + e * a_i * x_i (mod n)
+        let mut partial_signature = self.secret_key;
+        partial_signature
+            .mul_assign(&self.aggregated_key.coefficients[self.index][..])
+            .map_err(|_| MultisigError::InvalidScalar)?;
+        partial_signature
+            .mul_assign(&challenge[..])
+            .map_err(|_| MultisigError::InvalidScalar)?;
+        partial_signature
+            .add_assign(&self.nonce[..])
+            .map_err(|_| MultisigError::InvalidScalar)?;
+
+        let mut partial_signatures = vec![None; public_nonces.len()];
+        partial_signatures[self.index] = Some(partial_signature);
+
+        Ok((
+            partial_signature,
+            Combiner {
+                aggregated_key: self.aggregated_key,
+                public_nonces,
+                r,
+                challenge,
+                partial_signatures,
+            },
+        ))
+    }
+
+    fn check_index(&self, index: usize) -> Result<(), MultisigError> {
+        check_index(&self.aggregated_key, index)
+    }
+}
+
+/// State of one of the signers once it has sent its partial signature, collecting the partial
+/// signatures of the others
+pub struct Combiner {
+    aggregated_key: AggregatedKey,
+    public_nonces: Vec<PublicKey>,
+    r: PublicKey,
+    challenge: SecretKey,
+    partial_signatures: Vec<Option<SecretKey>>,
+}
+
+impl Combiner {
+    /// Round 3: verify and store the partial signature received from the signer at `index`
+    pub fn add_partial_signature(
+        &mut self,
+        index: usize,
+        partial_signature: SecretKey,
+    ) -> Result<(), MultisigError> {
+        check_index(&self.aggregated_key, index)?;
+        let secp = secp256k1_context();
+        let invalid = || MultisigError::InvalidPartialSignature { index };
+
+        // s_i * G = R_i + e * a_i * X_i
+        let mut weight = self.aggregated_key.coefficients[index];
+        weight
+            .mul_assign(&self.challenge[..])
+            .map_err(|_| MultisigError::InvalidScalar)?;
+        let expected = mul_point(secp, &self.aggregated_key.keys[index], &weight[..])?
+            .combine(&self.public_nonces[index])
+            .map_err(|_| invalid())?;
+        if PublicKey::from_secret_key(secp, &partial_signature) != expected {
+            Err(invalid())?
+        }
+        self.partial_signatures[index] = Some(partial_signature);
+
+        Ok(())
+    }
+
+    /// Combine the partial signatures of all the signers into the signature of the group
+    pub fn signature(&self) -> Result<Signature, MultisigError> {
+        let partial_signatures = self
+            .partial_signatures
+            .iter()
+            .cloned()
+            .collect::<Option<Vec<_>>>()
+            .ok_or(MultisigError::MissingPartialSignatures)?;
+        let (first, rest) = partial_signatures
+            .split_first()
+            .ok_or(MultisigError::MissingPartialSignatures)?;
+        let mut s = *first;
+        for partial_signature in rest {
+            s.add_assign(&partial_signature[..])
+                .map_err(|_| MultisigError::InvalidScalar)?;
+        }
+
+        Ok(Signature { r: self.r, s })
+    }
+}
+
+fn check_index(aggregated_key: &AggregatedKey, index: usize) -> Result<(), MultisigError> {
+    if index < aggregated_key.keys.len() {
+        Ok(())
+    } else {
+        Err(MultisigError::InvalidIndex { index })
+    }
+}
+
+/// Verify a multi-signature of `message` with the aggregated public key of the signers
+pub fn verify(
+    public_key: &PublicKey,
+    message: &[u8],
+    signature: &Signature,
+) -> Result<(), MultisigError> {
+    let secp = secp256k1_context();
+    let challenge = challenge(&signature.r, public_key, message)?;
+
+    // s * G = R + e * X
+    let expected = mul_point(secp, public_key, &challenge[..])?
+        .combine(&signature.r)
+        .map_err(|_| MultisigError::InvalidSignature)?;
+    if PublicKey::from_secret_key(secp, &signature.s) == expected {
+        Ok(())
+    } else {
+        Err(MultisigError::InvalidSignature)
+    }
+}
+
+fn nonce_commitment(public_nonce: &PublicKey) -> Sha256 {
+    calculate_tagged_sha256(NONCE_TAG, &public_nonce.serialize())
+}
+
+/// Challenge of a signature: `e = H(R || X || message)`
+fn challenge(
+    r: &PublicKey,
+    public_key: &PublicKey,
+    message: &[u8],
+) -> Result<SecretKey, MultisigError> {
+    let mut bytes = r.serialize().to_vec();
+    bytes.extend_from_slice(&public_key.serialize());
+    bytes.extend_from_slice(message);
+
+    scalar_from_hash(&calculate_tagged_sha256(CHALLENGE_TAG, &bytes))
+}
+
+fn scalar_from_hash(hash: &Sha256) -> Result<SecretKey, MultisigError> {
+    let Sha256(bytes) = hash;
+
+    SecretKey::from_slice(bytes).map_err(|_| MultisigError::InvalidScalar)
+}
+
+fn mul_point(
+    secp: &Secp256k1<secp256k1::All>,
+    point: &PublicKey,
+    scalar: &[u8],
+) -> Result<PublicKey, MultisigError> {
+    let mut result = *point;
+    result
+        .mul_assign(secp, scalar)
+        .map_err(|_| MultisigError::InvalidScalar)?;
+
+    Ok(result)
+}
+
+/// Sum a non-empty list of points
+fn sum_points(points: &[PublicKey]) -> Result<PublicKey, MultisigError> {
+    let (first, rest) = points.split_first().ok_or(MultisigError::NoKeys)?;
+
+    rest.iter().try_fold(*first, |sum, point| {
+        sum.combine(point).map_err(|_| MultisigError::InvalidScalar)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secret_keys() -> Vec<SecretKey> {
+        [0xcd, 0xab, 0x01]
+            .iter()
+            .map(|&byte| SecretKey::from_slice(&[byte; 32]).unwrap())
+            .collect()
+    }
+
+    fn public_keys(secret_keys: &[SecretKey]) -> Vec<PublicKey> {
+        secret_keys
+            .iter()
+            .map(|secret_key| PublicKey::from_secret_key(secp256k1_context(), secret_key))
+            .collect()
+    }
+
+    /// Run the three rounds between all the signers
+    fn sign(secret_keys: &[SecretKey], message: &[u8]) -> (AggregatedKey, Signature) {
+        let aggregated_key = AggregatedKey::new(&public_keys(secret_keys)).unwrap();
+        let mut signers: Vec<Signer> = secret_keys
+            .iter()
+            .map(|secret_key| Signer::new(aggregated_key.clone(), *secret_key, message).unwrap())
+            .collect();
+
+        let commitments: Vec<_> = signers
+            .iter()
+            .map(|signer| (signer.index(), signer.nonce_commitment()))
+            .collect();
+        for signer in &mut signers {
+            for (index, commitment) in &commitments {
+                signer.add_nonce_commitment(*index, *commitment).unwrap();
+            }
+        }
+
+        let public_nonces: Vec<_> = signers
+            .iter()
+            .map(|signer| (signer.index(), signer.public_nonce().unwrap()))
+            .collect();
+        for signer in &mut signers {
+            for (index, public_nonce) in &public_nonces {
+                signer.add_public_nonce(*index, *public_nonce).unwrap();
+            }
+        }
+
+        let (partial_signatures, mut combiners): (Vec<_>, Vec<_>) = signers
+            .into_iter()
+            .map(|signer| {
+                let index = signer.index();
+                let (partial_signature, combiner) = signer.partial_signature().unwrap();
+                ((index, partial_signature), combiner)
+            })
+            .unzip();
+        for combiner in &mut combiners {
+            for (index, partial_signature) in &partial_signatures {
+                combiner
+                    .add_partial_signature(*index, *partial_signature)
+                    .unwrap();
+            }
+        }
+
+        // All the signers obtain the same signature
+        let signature = combiners[0].signature().unwrap();
+        for combiner in &combiners {
+            assert_eq!(combiner.signature().unwrap(), signature);
+        }
+
+        (aggregated_key, signature)
+    }
+
+    #[test]
+    fn test_aggregated_key() {
+        let public_keys = public_keys(&secret_keys());
+        let aggregated_key = AggregatedKey::new(&public_keys).unwrap();
+
+        let mut reversed = public_keys.clone();
+        reversed.reverse();
+        assert_eq!(AggregatedKey::new(&reversed).unwrap(), aggregated_key);
+
+        // The aggregated key is not the plain sum of the keys
+        assert_ne!(
+            aggregated_key.public_key(),
+            sum_points(&public_keys).unwrap()
+        );
+        assert_ne!(
+            AggregatedKey::new(&public_keys[..2]).unwrap().public_key(),
+            aggregated_key.public_key()
+        );
+
+        assert_eq!(AggregatedKey::new(&[]), Err(MultisigError::NoKeys));
+        assert_eq!(
+            AggregatedKey::new(&[public_keys[0], public_keys[1], public_keys[0]]),
+            Err(MultisigError::DuplicateKey)
+        );
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let message = b"witnet";
+        let (aggregated_key, signature) = sign(&secret_keys(), message);
+        let public_key = aggregated_key.public_key();
+
+        assert_eq!(verify(&public_key, message, &signature), Ok(()));
+        assert_eq!(
+            verify(&public_key, b"other", &signature),
+            Err(MultisigError::InvalidSignature)
+        );
+
+        let bytes = signature.to_bytes();
+        assert_eq!(bytes.len(), SIGNATURE_LENGTH);
+        assert_eq!(Signature::from_bytes(&bytes), Ok(signature));
+        assert_eq!(
+            Signature::from_bytes(&bytes[1..]),
+            Err(MultisigError::MalformedSignature)
+        );
+
+        // A subset of the signers cannot sign for the whole group
+        let (_, subset_signature) = sign(&secret_keys()[..2], message);
+        assert_eq!(
+            verify(&public_key, message, &subset_signature),
+            Err(MultisigError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn test_signing_rounds() {
+        let secret_keys = secret_keys();
+        let aggregated_key = AggregatedKey::new(&public_keys(&secret_keys)).unwrap();
+        let mut signer = Signer::new(aggregated_key.clone(), secret_keys[0], b"witnet").unwrap();
+        let other = Signer::new(aggregated_key.clone(), secret_keys[1], b"witnet").unwrap();
+
+        // Nonces are not revealed before receiving all the commitments
+        assert_eq!(
+            signer.public_nonce(),
+            Err(MultisigError::MissingCommitments)
+        );
+        assert_eq!(
+            signer.add_nonce_commitment(3, other.nonce_commitment()),
+            Err(MultisigError::InvalidIndex { index: 3 })
+        );
+
+        // Public nonces must match the commitments
+        signer
+            .add_nonce_commitment(other.index(), other.nonce_commitment())
+            .unwrap();
+        let third_index = 3 - signer.index() - other.index();
+        let third = Signer::new(aggregated_key.clone(), secret_keys[2], b"witnet").unwrap();
+        assert_eq!(third.index(), third_index);
+        signer
+            .add_nonce_commitment(third_index, third.nonce_commitment())
+            .unwrap();
+        assert_eq!(
+            signer.add_public_nonce(third_index, signer.public_nonce().unwrap()),
+            Err(MultisigError::NonceCommitmentMismatch { index: third_index })
+        );
+
+        // Commitments can be received twice, but not replaced
+        signer
+            .add_nonce_commitment(third_index, third.nonce_commitment())
+            .unwrap();
+        assert_eq!(
+            signer.add_nonce_commitment(third_index, other.nonce_commitment()),
+            Err(MultisigError::ConflictingCommitment { index: third_index })
+        );
+
+        // Partial signatures are not computed before receiving all the public nonces
+        assert_eq!(
+            signer.partial_signature().err(),
+            Some(MultisigError::MissingNonces)
+        );
+
+        // Signers outside the group are rejected
+        let outsider = SecretKey::from_slice(&[0x02; 32]).unwrap();
+        assert!(Signer::new(aggregated_key, outsider, b"witnet").is_err());
+    }
+
+    #[test]
+    fn test_invalid_partial_signature() {
+        let secret_keys = &secret_keys()[..2];
+        let aggregated_key = AggregatedKey::new(&public_keys(secret_keys)).unwrap();
+        let mut signers: Vec<Signer> = secret_keys
+            .iter()
+            .map(|secret_key| Signer::new(aggregated_key.clone(), *secret_key, b"witnet").unwrap())
+            .collect();
+
+        let commitments: Vec<_> = signers
+            .iter()
+            .map(|signer| (signer.index(), signer.nonce_commitment()))
+            .collect();
+        let public_nonces: Vec<_> = signers
+            .iter_mut()
+            .map(|signer| {
+                for (index, commitment) in &commitments {
+                    signer.add_nonce_commitment(*index, *commitment).unwrap();
+                }
+                (signer.index(), signer.public_nonce().unwrap())
+            })
+            .collect();
+        for signer in &mut signers {
+            for (index, public_nonce) in &public_nonces {
+                signer.add_public_nonce(*index, *public_nonce).unwrap();
+            }
+        }
+
+        let second = signers.pop().unwrap();
+        let first = signers.pop().unwrap();
+        let other_index = second.index();
+        let (own_partial_signature, mut combiner) = first.partial_signature().unwrap();
+        assert_eq!(
+            combiner.signature(),
+            Err(MultisigError::MissingPartialSignatures)
+        );
+
+        // The partial signature of one signer is not valid for another one
+        assert_eq!(
+            combiner.add_partial_signature(other_index, own_partial_signature),
+            Err(MultisigError::InvalidPartialSignature { index: other_index })
+        );
+
+        let (other_partial_signature, _) = second.partial_signature().unwrap();
+        combiner
+            .add_partial_signature(other_index, other_partial_signature)
+            .unwrap();
+        let signature = combiner.signature().unwrap();
+        assert_eq!(
+            verify(&aggregated_key.public_key(), b"witnet", &signature),
+            Ok(())
+        );
+    }
+}
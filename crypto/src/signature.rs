@@ -1,37 +1,164 @@
 //! Signature module
 
 use failure::Fail;
-use secp256k1::{Message, PublicKey, Secp256k1, SecretKey};
+use lazy_static::lazy_static;
+use rayon::prelude::*;
+use secp256k1::{All, Message, PublicKey, Secp256k1, SecretKey};
+
+use crate::hash::{calculate_sha256, calculate_tagged_sha256, Sha256};
 
 /// Signature
 pub type Signature = secp256k1::Signature;
 
+/// Domain of the signatures of block proofs
+pub const BLOCK_DOMAIN: &str = "witnet-block";
+
+/// Domain of the signatures of transaction inputs
+pub const TRANSACTION_DOMAIN: &str = "witnet-transaction";
+
+/// Domain of the signatures of handshake proofs
+pub const HANDSHAKE_DOMAIN: &str = "witnet-handshake";
+
+/// Signature from which the public key of the signer can be recovered
+pub type RecoverableSignature = secp256k1::RecoverableSignature;
+
+lazy_static! {
+    /// Context shared by all the secp256k1 operations, randomized when it is first used
+    static ref CONTEXT: Secp256k1<All> = {
+        let mut secp = Secp256k1::new();
+        secp.randomize(&mut secp256k1::rand::thread_rng());
+
+        secp
+    };
+}
+
+/// Global secp256k1 context, with signing and verification capabilities.
+///
+/// Creating a context is expensive, so this one should be used instead of creating new ones.
+pub fn secp256k1_context() -> &'static Secp256k1<All> {
+    &CONTEXT
+}
+
 /// The error type for operations with signatures
 #[derive(Debug, PartialEq, Fail)]
 pub enum SignatureError {
     #[fail(display = "Fail in verify process")]
     /// Fail in verify process
     VerifyError,
+    #[fail(display = "The message to sign or verify must be 32 bytes long")]
+    /// The message is not 32 bytes long
+    InvalidMessage,
+    #[fail(display = "Fail in public key recovery process")]
+    /// Fail in public key recovery process
+    RecoverError,
+}
+
+/// The error type for [`verify_batch`](verify_batch)
+#[derive(Debug, PartialEq, Fail)]
+#[fail(display = "Signature at index {} is not valid: {}", index, error)]
+pub struct BatchError {
+    /// Index of the first invalid signature of the batch
+    pub index: usize,
+    /// Reason why the signature is not valid
+    pub error: SignatureError,
+}
+
+/// Sign a 32-byte message with provided secret key
+pub fn sign(secret_key: SecretKey, data: &[u8]) -> Result<Signature, SignatureError> {
+    let msg = Message::from_slice(data).map_err(|_| SignatureError::InvalidMessage)?;
+
+    Ok(secp256k1_context().sign(&msg, &secret_key))
+}
+
+/// Sign a SHA-256 hash with provided secret key
+pub fn sign_hash(secret_key: SecretKey, hash: &Sha256) -> Signature {
+    let Sha256(data) = hash;
+    let msg = Message::from_slice(data).expect("SHA-256 hashes are 32 bytes long");
+
+    secp256k1_context().sign(&msg, &secret_key)
 }
 
-/// Sign data with provided secret key
-pub fn sign(secret_key: SecretKey, data: &[u8]) -> Signature {
-    let msg = Message::from_slice(data).unwrap();
-    let secp = Secp256k1::new();
-    secp.sign(&msg, &secret_key)
+/// Sign data of any length with provided secret key, hashing it with SHA-256 first
+pub fn sign_data(secret_key: SecretKey, data: &[u8]) -> Signature {
+    sign_hash(secret_key, &calculate_sha256(data))
 }
-/// Verify signature with a provided public key
-pub fn verify(public_key: &PublicKey, data: &[u8], sig: &Signature) -> Result<(), failure::Error> {
-    let msg = Message::from_slice(data).unwrap();
-    let secp = Secp256k1::new();
 
-    secp.verify(&msg, sig, public_key)
-        .map_err(|_| SignatureError::VerifyError.into())
+/// Sign data of any length within a domain, e.g. [`BLOCK_DOMAIN`](BLOCK_DOMAIN), with provided
+/// secret key. The signed message is the tagged hash of the data, so a signature is never valid
+/// in a domain other than the one it was made for.
+pub fn sign_tagged(secret_key: SecretKey, domain: &str, data: &[u8]) -> Signature {
+    sign_hash(secret_key, &calculate_tagged_sha256(domain, data))
+}
+
+/// Verify signature of data signed within a domain with [`sign_tagged`](sign_tagged)
+pub fn verify_tagged(
+    public_key: &PublicKey,
+    domain: &str,
+    data: &[u8],
+    sig: &Signature,
+) -> Result<(), SignatureError> {
+    let Sha256(message) = calculate_tagged_sha256(domain, data);
+
+    verify(public_key, &message, sig)
+}
+
+/// Verify signature of a 32-byte message with a provided public key
+pub fn verify(public_key: &PublicKey, data: &[u8], sig: &Signature) -> Result<(), SignatureError> {
+    let msg = Message::from_slice(data).map_err(|_| SignatureError::InvalidMessage)?;
+
+    secp256k1_context()
+        .verify(&msg, sig, public_key)
+        .map_err(|_| SignatureError::VerifyError)
+}
+
+/// Verify a batch of signatures of 32-byte messages, each of them with its public key.
+///
+/// Signatures are verified in parallel. If there are invalid signatures, the error contains the
+/// lowest index among them.
+pub fn verify_batch(batch: &[(PublicKey, &[u8], Signature)]) -> Result<(), BatchError> {
+    let first_error = batch
+        .par_iter()
+        .enumerate()
+        .filter_map(|(index, (public_key, data, sig))| {
+            verify(public_key, data, sig)
+                .err()
+                .map(|error| BatchError { index, error })
+        })
+        .min_by_key(|error| error.index);
+
+    match first_error {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+/// Sign a 32-byte message with provided secret key, producing a signature from which the public
+/// key can be recovered with [`recover`](recover)
+pub fn sign_recoverable(
+    secret_key: SecretKey,
+    data: &[u8],
+) -> Result<RecoverableSignature, SignatureError> {
+    let msg = Message::from_slice(data).map_err(|_| SignatureError::InvalidMessage)?;
+
+    Ok(secp256k1_context().sign_recoverable(&msg, &secret_key))
+}
+
+/// Recover the public key which signed a 32-byte message
+pub fn recover(data: &[u8], sig: &RecoverableSignature) -> Result<PublicKey, SignatureError> {
+    let msg = Message::from_slice(data).map_err(|_| SignatureError::InvalidMessage)?;
+
+    secp256k1_context()
+        .recover(&msg, sig)
+        .map_err(|_| SignatureError::RecoverError)
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::signature::{sign, verify};
+    use crate::hash::calculate_sha256;
+    use crate::signature::{
+        recover, sign, sign_data, sign_hash, sign_recoverable, sign_tagged, verify, verify_batch,
+        verify_tagged, BatchError, SignatureError, BLOCK_DOMAIN, TRANSACTION_DOMAIN,
+    };
     use secp256k1::{PublicKey, Secp256k1, SecretKey};
 
     #[test]
@@ -41,7 +168,7 @@ mod tests {
         let secret_key = SecretKey::from_slice(&[0xcd; 32]).expect("32 bytes, within curve order");
         let public_key = PublicKey::from_secret_key(&secp, &secret_key);
 
-        let signature = sign(secret_key, &data);
+        let signature = sign(secret_key, &data).unwrap();
         let signature_expected = "3044\
                                   0220\
                                   3dc4fa74655c21b7ffc0740e29bfd88647e8dfe2b68c507cf96264e4e7439c1f\
@@ -52,4 +179,119 @@ mod tests {
 
         assert!(verify(&public_key, &data, &signature).is_ok());
     }
+
+    #[test]
+    fn test_invalid_message_length() {
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[0xcd; 32]).expect("32 bytes, within curve order");
+        let public_key = PublicKey::from_secret_key(&secp, &secret_key);
+        let signature = sign(secret_key, &[0xab; 32]).unwrap();
+
+        assert_eq!(
+            sign(secret_key, &[0xab; 31]),
+            Err(SignatureError::InvalidMessage)
+        );
+        assert_eq!(
+            verify(&public_key, &[0xab; 33], &signature),
+            Err(SignatureError::InvalidMessage)
+        );
+    }
+
+    #[test]
+    fn test_sign_data() {
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[0xcd; 32]).expect("32 bytes, within curve order");
+        let public_key = PublicKey::from_secret_key(&secp, &secret_key);
+        let data = b"data of any length";
+        let hash = calculate_sha256(data);
+
+        let signature = sign_data(secret_key, data);
+
+        assert_eq!(signature, sign_hash(secret_key, &hash));
+        assert!(verify(&public_key, &hash.0, &signature).is_ok());
+    }
+
+    #[test]
+    fn test_sign_tagged() {
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[0xcd; 32]).expect("32 bytes, within curve order");
+        let public_key = PublicKey::from_secret_key(&secp, &secret_key);
+        let data = [0xab; 32];
+
+        let signature = sign_tagged(secret_key, BLOCK_DOMAIN, &data);
+
+        assert!(verify_tagged(&public_key, BLOCK_DOMAIN, &data, &signature).is_ok());
+        // The signature is not valid in other domains, nor for the raw data
+        assert_eq!(
+            verify_tagged(&public_key, TRANSACTION_DOMAIN, &data, &signature),
+            Err(SignatureError::VerifyError)
+        );
+        assert_eq!(
+            verify(&public_key, &data, &signature),
+            Err(SignatureError::VerifyError)
+        );
+    }
+
+    #[test]
+    fn test_sign_recoverable_and_recover() {
+        let data = [0xab; 32];
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[0xcd; 32]).expect("32 bytes, within curve order");
+        let public_key = PublicKey::from_secret_key(&secp, &secret_key);
+
+        let signature = sign_recoverable(secret_key, &data).unwrap();
+
+        assert_eq!(recover(&data, &signature), Ok(public_key));
+        assert!(verify(&public_key, &data, &signature.to_standard()).is_ok());
+        // A different message recovers a different public key
+        assert_ne!(recover(&[0xac; 32], &signature), Ok(public_key));
+        assert_eq!(
+            recover(&data[1..], &signature),
+            Err(SignatureError::InvalidMessage)
+        );
+    }
+
+    #[test]
+    fn test_verify_batch() {
+        let secp = Secp256k1::new();
+        let keys: Vec<(SecretKey, PublicKey)> = (1..=8)
+            .map(|i| {
+                let secret_key = SecretKey::from_slice(&[i; 32]).unwrap();
+                (secret_key, PublicKey::from_secret_key(&secp, &secret_key))
+            })
+            .collect();
+        let messages: Vec<[u8; 32]> = (1..=8).map(|i| [i; 32]).collect();
+        let mut batch: Vec<_> = keys
+            .iter()
+            .zip(messages.iter())
+            .map(|((secret_key, public_key), message)| {
+                let signature = sign(*secret_key, message).unwrap();
+                (*public_key, &message[..], signature)
+            })
+            .collect();
+
+        assert_eq!(verify_batch(&batch), Ok(()));
+        assert_eq!(verify_batch(&[]), Ok(()));
+
+        // Swap the public keys of two entries: the lowest invalid index is reported
+        let public_key_5 = batch[5].0;
+        batch[5].0 = batch[2].0;
+        batch[2].0 = public_key_5;
+        assert_eq!(
+            verify_batch(&batch),
+            Err(BatchError {
+                index: 2,
+                error: SignatureError::VerifyError,
+            })
+        );
+
+        batch[1].1 = &messages[1][..31];
+        assert_eq!(
+            verify_batch(&batch),
+            Err(BatchError {
+                index: 1,
+                error: SignatureError::InvalidMessage,
+            })
+        );
+    }
 }
@@ -0,0 +1,235 @@
+//! Cache of successful signature verifications
+//!
+//! Transactions are verified when they are admitted into the transactions pool, and then again
+//! when they arrive in a block. Remembering which signatures were valid avoids verifying them
+//! twice. Only successful verifications are cached, so an invalid signature is always rejected.
+use std::collections::{HashSet, VecDeque};
+
+use secp256k1::PublicKey;
+use serde::{Deserialize, Serialize};
+
+use crate::hash::{calculate_tagged_sha256, Sha256};
+use crate::signature::{verify, Signature, SignatureError};
+
+/// Approximate number of bytes used by each entry: its key is stored both in the set and in
+/// the eviction queue
+pub const ENTRY_SIZE: usize = 2 * 32;
+
+/// Counters of the usage of a [`SignatureCache`](SignatureCache)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SignatureCacheStats {
+    /// Number of verifications found in the cache
+    pub hits: u64,
+    /// Number of verifications not found in the cache
+    pub misses: u64,
+    /// Number of entries evicted to keep the cache within its cap
+    pub evictions: u64,
+    /// Number of entries in the cache
+    pub entries: usize,
+}
+
+impl SignatureCacheStats {
+    /// Ratio of verifications found in the cache, `0.0` if there were none
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f64 / lookups as f64
+        }
+    }
+}
+
+/// Cache of signature verifications keyed by message, public key and signature.
+///
+/// When the memory cap is reached, the oldest entries are evicted first.
+#[derive(Debug, Default)]
+pub struct SignatureCache {
+    /// Memory cap in bytes, `0` means no cap
+    max_bytes: usize,
+    entries: HashSet<[u8; 32]>,
+    /// Entries in insertion order
+    queue: VecDeque<[u8; 32]>,
+    stats: SignatureCacheStats,
+}
+
+impl SignatureCache {
+    /// Create an empty cache using approximately up to `max_bytes` bytes, `0` means no cap
+    pub fn new(max_bytes: usize) -> Self {
+        SignatureCache {
+            max_bytes,
+            ..SignatureCache::default()
+        }
+    }
+
+    /// Verify signature of a 32-byte message with a provided public key, unless the same
+    /// verification already succeeded
+    pub fn verify(
+        &mut self,
+        public_key: &PublicKey,
+        data: &[u8],
+        sig: &Signature,
+    ) -> Result<(), SignatureError> {
+        let key = entry_key(public_key, data, sig);
+
+        if self.entries.contains(&key) {
+            self.stats.hits += 1;
+            return Ok(());
+        }
+
+        self.stats.misses += 1;
+        verify(public_key, data, sig)?;
+        self.insert(key);
+
+        Ok(())
+    }
+
+    /// Verify signature of data signed within a domain, unless the same verification already
+    /// succeeded. See [`verify_tagged`](crate::signature::verify_tagged).
+    pub fn verify_tagged(
+        &mut self,
+        public_key: &PublicKey,
+        domain: &str,
+        data: &[u8],
+        sig: &Signature,
+    ) -> Result<(), SignatureError> {
+        let Sha256(message) = calculate_tagged_sha256(domain, data);
+
+        self.verify(public_key, &message, sig)
+    }
+
+    /// Usage counters of this cache
+    pub fn stats(&self) -> SignatureCacheStats {
+        SignatureCacheStats {
+            entries: self.entries.len(),
+            ..self.stats
+        }
+    }
+
+    /// Approximate number of bytes used by the entries of this cache
+    pub fn memory_usage(&self) -> usize {
+        self.entries.len() * ENTRY_SIZE
+    }
+
+    /// Memory cap of this cache in bytes, `0` means no cap
+    pub fn max_bytes(&self) -> usize {
+        self.max_bytes
+    }
+
+    fn insert(&mut self, key: [u8; 32]) {
+        if self.max_bytes != 0 {
+            let max_entries = self.max_bytes / ENTRY_SIZE;
+            if max_entries == 0 {
+                return;
+            }
+            while self.queue.len() >= max_entries {
+                if let Some(oldest) = self.queue.pop_front() {
+                    self.entries.remove(&oldest);
+                    self.stats.evictions += 1;
+                }
+            }
+        }
+
+        if self.entries.insert(key) {
+            self.queue.push_back(key);
+        }
+    }
+}
+
+/// Digest identifying a verification, so that entries have a small fixed size
+fn entry_key(public_key: &PublicKey, data: &[u8], sig: &Signature) -> [u8; 32] {
+    let mut bytes = Vec::with_capacity(data.len() + 33 + 64);
+    bytes.extend_from_slice(data);
+    bytes.extend_from_slice(&public_key.serialize());
+    bytes.extend_from_slice(&sig.serialize_compact());
+
+    let Sha256(key) = calculate_tagged_sha256("witnet-signature-cache", &bytes);
+
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signature::{sign, sign_tagged, TRANSACTION_DOMAIN};
+    use secp256k1::{Secp256k1, SecretKey};
+
+    fn key_pair(byte: u8) -> (SecretKey, PublicKey) {
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[byte; 32]).unwrap();
+        let public_key = PublicKey::from_secret_key(&secp, &secret_key);
+
+        (secret_key, public_key)
+    }
+
+    #[test]
+    fn test_cache_hits() {
+        let (secret_key, public_key) = key_pair(0xcd);
+        let signature = sign_tagged(secret_key, TRANSACTION_DOMAIN, b"tx");
+        let mut cache = SignatureCache::new(0);
+
+        assert_eq!(cache.stats().hit_rate(), 0.0);
+        for _ in 0..4 {
+            assert_eq!(
+                cache.verify_tagged(&public_key, TRANSACTION_DOMAIN, b"tx", &signature),
+                Ok(())
+            );
+        }
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (3, 1, 1));
+        assert_eq!(stats.hit_rate(), 0.75);
+    }
+
+    #[test]
+    fn test_invalid_signatures_are_not_cached() {
+        let (secret_key, public_key) = key_pair(0xcd);
+        let (_, other_public_key) = key_pair(0xef);
+        let data = [0xab; 32];
+        let signature = sign(secret_key, &data).unwrap();
+        let mut cache = SignatureCache::new(0);
+
+        assert_eq!(cache.verify(&public_key, &data, &signature), Ok(()));
+        for _ in 0..2 {
+            assert_eq!(
+                cache.verify(&other_public_key, &data, &signature),
+                Err(SignatureError::VerifyError)
+            );
+            assert_eq!(
+                cache.verify(&public_key, &[0xac; 32], &signature),
+                Err(SignatureError::VerifyError)
+            );
+        }
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (0, 5, 1));
+    }
+
+    #[test]
+    fn test_cache_is_bounded() {
+        let (secret_key, public_key) = key_pair(0xcd);
+        let mut cache = SignatureCache::new(3 * ENTRY_SIZE);
+        let signed = |byte: u8| {
+            let data = [byte; 32];
+            (data, sign(secret_key, &data).unwrap())
+        };
+
+        // The all-zero message cannot be signed, so the messages start at 1
+        for byte in 1..=5 {
+            let (data, signature) = signed(byte);
+            assert_eq!(cache.verify(&public_key, &data, &signature), Ok(()));
+        }
+        let stats = cache.stats();
+        assert_eq!((stats.entries, stats.evictions), (3, 2));
+        assert_eq!(cache.memory_usage(), 3 * ENTRY_SIZE);
+
+        // The oldest entries were evicted
+        let (data, signature) = signed(5);
+        assert_eq!(cache.verify(&public_key, &data, &signature), Ok(()));
+        assert_eq!(cache.stats().hits, 1);
+        let (data, signature) = signed(1);
+        assert_eq!(cache.verify(&public_key, &data, &signature), Ok(()));
+        assert_eq!(cache.stats().hits, 1);
+        assert_eq!(cache.stats().entries, 3);
+    }
+}
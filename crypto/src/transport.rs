@@ -0,0 +1,329 @@
+//! Encrypted transport of the P2P sessions
+//!
+//! Peers supporting the encrypted transport announce an ephemeral public key in their `Version`
+//! message. Once both peers know the ephemeral key of the other one, they derive a key for each
+//! direction of the session from their [`ecdh`](crate::ecdh::ecdh) shared secret with
+//! [`hkdf_sha256`](crate::hkdf::hkdf_sha256), and every following message is encrypted and
+//! authenticated with XChaCha20-Poly1305. The nonce of each message is its index in its
+//! direction, so messages cannot be replayed, reordered or dropped without the decryption
+//! failing.
+//!
+//! The [`handshake_hash`](handshake_hash) of both `Version` messages is bound to the keys as
+//! well: if any of them is tampered with, the peers derive different keys and the decryption of
+//! the first encrypted message fails.
+//!
+//! The ephemeral keys are not signed: the transport protects the sessions from eavesdropping and
+//! tampering, but an active attacker present during the handshake can still impersonate a peer.
+//!
+//! ```
+//! use witnet_crypto::transport::{handshake_hash, EphemeralKey};
+//!
+//! let initiator_key = EphemeralKey::generate();
+//! let responder_key = EphemeralKey::generate();
+//! let handshake = handshake_hash(b"initiator version", b"responder version");
+//!
+//! let mut initiator = initiator_key
+//!     .agree(&responder_key.public_key_bytes(), true, &handshake)
+//!     .unwrap();
+//! let mut responder = responder_key
+//!     .agree(&initiator_key.public_key_bytes(), false, &handshake)
+//!     .unwrap();
+//!
+//! let ciphertext = initiator.sender.encrypt(b"ping").unwrap();
+//! assert_eq!(responder.receiver.decrypt(&ciphertext).unwrap().as_ref(), b"ping");
+//! ```
+
+use failure::Fail;
+use secp256k1::{PublicKey, SecretKey};
+
+use witnet_protected::Protected;
+
+use crate::{
+    aead,
+    ecdh::ecdh,
+    hash::{calculate_sha256, Sha256},
+    hkdf::{hkdf_sha256, HkdfError},
+    signature::secp256k1_context,
+};
+
+/// Length of the serialized ephemeral public keys (compressed format)
+pub const EPHEMERAL_KEY_LENGTH: usize = 33;
+
+/// Context of the keys derived for the transport
+const KEYS_INFO: &[u8] = b"witnet-p2p-transport";
+
+/// The error type for the encrypted transport
+#[derive(Debug, Fail)]
+pub enum TransportError {
+    /// The ephemeral public key announced by the peer is not a valid public key
+    #[fail(display = "Invalid ephemeral public key")]
+    InvalidEphemeralKey,
+    /// The keys of the session could not be derived
+    #[fail(display = "Key derivation failed: {}", _0)]
+    KeyDerivation(#[cause] HkdfError),
+    /// A message could not be encrypted or decrypted
+    #[fail(display = "Transport encryption failed: {}", _0)]
+    Encryption(#[cause] aead::Error),
+}
+
+/// Ephemeral key pair generated for a single session
+pub struct EphemeralKey {
+    secret_key: SecretKey,
+    public_key: PublicKey,
+}
+
+impl EphemeralKey {
+    /// Generate a random ephemeral key pair
+    pub fn generate() -> Self {
+        let secret_key = SecretKey::new(&mut secp256k1::rand::thread_rng());
+        let public_key = PublicKey::from_secret_key(secp256k1_context(), &secret_key);
+
+        EphemeralKey {
+            secret_key,
+            public_key,
+        }
+    }
+
+    /// Public key announced to the peer, in compressed format
+    pub fn public_key_bytes(&self) -> Vec<u8> {
+        self.public_key.serialize().to_vec()
+    }
+
+    /// Derive the ciphers of the session from the ephemeral public key announced by the peer and
+    /// the [`handshake_hash`](handshake_hash) of the `Version` messages of the session. The
+    /// `initiator` is the peer that opened the connection.
+    pub fn agree(
+        &self,
+        peer_public_key: &[u8],
+        initiator: bool,
+        handshake_hash: &Sha256,
+    ) -> Result<TransportCiphers, TransportError> {
+        let peer_public_key = parse_public_key(peer_public_key)?;
+        let shared_secret = ecdh(&self.secret_key, &peer_public_key);
+
+        // Both public keys are bound to the derived keys, in the same order for both peers
+        let (initiator_key, responder_key) = if initiator {
+            (self.public_key, peer_public_key)
+        } else {
+            (peer_public_key, self.public_key)
+        };
+        let mut salt = initiator_key.serialize().to_vec();
+        salt.extend_from_slice(&responder_key.serialize());
+        salt.extend_from_slice(handshake_hash.as_ref());
+
+        let keys = hkdf_sha256(
+            shared_secret.as_ref(),
+            &salt,
+            KEYS_INFO,
+            2 * aead::KEY_LENGTH,
+        )
+        .map_err(TransportError::KeyDerivation)?;
+        let (initiator_to_responder, responder_to_initiator) =
+            keys.as_ref().split_at(aead::KEY_LENGTH);
+        let initiator_to_responder = CipherState::new(initiator_to_responder);
+        let responder_to_initiator = CipherState::new(responder_to_initiator);
+
+        Ok(if initiator {
+            TransportCiphers {
+                sender: initiator_to_responder,
+                receiver: responder_to_initiator,
+            }
+        } else {
+            TransportCiphers {
+                sender: responder_to_initiator,
+                receiver: initiator_to_responder,
+            }
+        })
+    }
+}
+
+/// Check that an ephemeral public key announced by a peer is a valid compressed public key
+pub fn validate_public_key(public_key: &[u8]) -> Result<(), TransportError> {
+    parse_public_key(public_key).map(|_| ())
+}
+
+fn parse_public_key(public_key: &[u8]) -> Result<PublicKey, TransportError> {
+    if public_key.len() != EPHEMERAL_KEY_LENGTH {
+        Err(TransportError::InvalidEphemeralKey)?
+    }
+
+    PublicKey::from_slice(public_key).map_err(|_| TransportError::InvalidEphemeralKey)
+}
+
+/// Hash of the serialized `Version` messages sent by the initiator and by the responder of a
+/// session. Each message is prefixed with its length, so the boundary between them is unambiguous
+pub fn handshake_hash(initiator_version: &[u8], responder_version: &[u8]) -> Sha256 {
+    let mut transcript = vec![];
+    for version in &[initiator_version, responder_version] {
+        transcript.extend_from_slice(&(version.len() as u64).to_be_bytes());
+        transcript.extend_from_slice(version);
+    }
+
+    calculate_sha256(&transcript)
+}
+
+/// Ciphers of both directions of a session
+pub struct TransportCiphers {
+    /// Cipher of the messages sent to the peer
+    pub sender: CipherState,
+    /// Cipher of the messages received from the peer
+    pub receiver: CipherState,
+}
+
+/// Key of a direction of a session and index of its next message
+pub struct CipherState {
+    key: Protected,
+    counter: u64,
+}
+
+impl CipherState {
+    fn new(key: &[u8]) -> Self {
+        CipherState {
+            key: Protected::new(key),
+            counter: 0,
+        }
+    }
+
+    /// Encrypt the next message of the session
+    pub fn encrypt(&mut self, plaintext: &[u8]) -> Result<Vec<u8>, TransportError> {
+        let nonce = self.next_nonce()?;
+
+        aead::encrypt(self.key.as_ref(), &nonce, &[], plaintext).map_err(TransportError::Encryption)
+    }
+
+    /// Decrypt the next message of the session
+    pub fn decrypt(&mut self, ciphertext: &[u8]) -> Result<Protected, TransportError> {
+        let nonce = self.next_nonce()?;
+
+        aead::decrypt(self.key.as_ref(), &nonce, &[], ciphertext)
+            .map_err(TransportError::Encryption)
+    }
+
+    /// The nonce of each message is its index, so it is never reused with the same key
+    fn next_nonce(&mut self) -> Result<[u8; aead::NONCE_LENGTH], TransportError> {
+        if self.counter == u64::max_value() {
+            Err(TransportError::Encryption(aead::Error::StreamTooLong))?
+        }
+        let mut nonce = [0; aead::NONCE_LENGTH];
+        nonce[aead::NONCE_LENGTH - 8..].copy_from_slice(&self.counter.to_be_bytes());
+        self.counter += 1;
+
+        Ok(nonce)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session() -> (TransportCiphers, TransportCiphers) {
+        let initiator_key = EphemeralKey::generate();
+        let responder_key = EphemeralKey::generate();
+        let handshake = handshake_hash(b"initiator version", b"responder version");
+
+        (
+            initiator_key
+                .agree(&responder_key.public_key_bytes(), true, &handshake)
+                .unwrap(),
+            responder_key
+                .agree(&initiator_key.public_key_bytes(), false, &handshake)
+                .unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_transport_both_directions() {
+        let (mut initiator, mut responder) = session();
+
+        for message in &[&b"version"[..], b"", b"block"] {
+            let ciphertext = initiator.sender.encrypt(message).unwrap();
+            assert_eq!(ciphertext.len(), message.len() + aead::TAG_LENGTH);
+            assert_eq!(
+                responder.receiver.decrypt(&ciphertext).unwrap().as_ref(),
+                *message
+            );
+
+            let ciphertext = responder.sender.encrypt(message).unwrap();
+            assert_eq!(
+                initiator.receiver.decrypt(&ciphertext).unwrap().as_ref(),
+                *message
+            );
+        }
+    }
+
+    #[test]
+    fn test_transport_directions_use_different_keys() {
+        let (mut initiator, _) = session();
+
+        let ciphertext = initiator.sender.encrypt(b"ping").unwrap();
+        assert!(initiator.receiver.decrypt(&ciphertext).is_err());
+    }
+
+    #[test]
+    fn test_transport_rejects_tampered_and_replayed_messages() {
+        let (mut initiator, mut responder) = session();
+
+        let mut ciphertext = initiator.sender.encrypt(b"ping").unwrap();
+        ciphertext[0] ^= 1;
+        assert!(responder.receiver.decrypt(&ciphertext).is_err());
+
+        let (mut initiator, mut responder) = session();
+        let ciphertext = initiator.sender.encrypt(b"ping").unwrap();
+        assert!(responder.receiver.decrypt(&ciphertext).is_ok());
+        assert!(responder.receiver.decrypt(&ciphertext).is_err());
+    }
+
+    #[test]
+    fn test_transport_tampered_handshake() {
+        let initiator_key = EphemeralKey::generate();
+        let responder_key = EphemeralKey::generate();
+
+        // The responder received a different `Version` message than the one the initiator sent
+        let mut initiator = initiator_key
+            .agree(
+                &responder_key.public_key_bytes(),
+                true,
+                &handshake_hash(b"initiator version", b"responder version"),
+            )
+            .unwrap();
+        let mut responder = responder_key
+            .agree(
+                &initiator_key.public_key_bytes(),
+                false,
+                &handshake_hash(b"tampered version", b"responder version"),
+            )
+            .unwrap();
+
+        let ciphertext = initiator.sender.encrypt(b"ping").unwrap();
+        assert!(responder.receiver.decrypt(&ciphertext).is_err());
+    }
+
+    #[test]
+    fn test_handshake_hash_is_unambiguous() {
+        assert_ne!(
+            handshake_hash(b"version", b"version"),
+            handshake_hash(b"versionversion", b"")
+        );
+        assert_ne!(
+            handshake_hash(b"initiator", b"responder"),
+            handshake_hash(b"responder", b"initiator")
+        );
+    }
+
+    #[test]
+    fn test_transport_invalid_ephemeral_key() {
+        let key = EphemeralKey::generate();
+        let handshake = handshake_hash(b"", b"");
+
+        assert!(key.agree(&[], true, &handshake).is_err());
+        assert!(key
+            .agree(&key.public_key_bytes()[1..], true, &handshake)
+            .is_err());
+        // 0x05 is not the prefix of a compressed public key
+        assert!(key
+            .agree(&[0x05; EPHEMERAL_KEY_LENGTH], true, &handshake)
+            .is_err());
+        assert!(validate_public_key(&[0x05; EPHEMERAL_KEY_LENGTH]).is_err());
+        assert!(validate_public_key(&key.public_key_bytes()).is_ok());
+    }
+}
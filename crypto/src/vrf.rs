@@ -0,0 +1,302 @@
+//! Verifiable Random Function
+//!
+//! ECVRF over secp256k1 with SHA-256, following the `ECVRF-SECP256K1-SHA256-TAI` ciphersuite of
+//! the IETF VRF draft: messages are hashed to the curve using try-and-increment. The nonce is
+//! derived deterministically from the secret key and the hashed message.
+//!
+//! A proof is the compressed point `gamma` (33 bytes), followed by the challenge `c` (16 bytes)
+//! and the scalar `s` (32 bytes).
+
+use failure::Fail;
+use secp256k1::{PublicKey, Secp256k1, SecretKey};
+
+use crate::{
+    hash::{calculate_sha256, Sha256},
+    signature::secp256k1_context,
+};
+
+/// Length of a VRF proof in bytes
+pub const PROOF_LENGTH: usize = POINT_LENGTH + CHALLENGE_LENGTH + SCALAR_LENGTH;
+
+/// Identifier of the `ECVRF-SECP256K1-SHA256-TAI` ciphersuite
+const SUITE: u8 = 0xFE;
+const POINT_LENGTH: usize = 33;
+const CHALLENGE_LENGTH: usize = 16;
+const SCALAR_LENGTH: usize = 32;
+/// Order of the secp256k1 curve
+const CURVE_ORDER: [u8; 32] = [
+    0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFE,
+    0xBA, 0xAE, 0xDC, 0xE6, 0xAF, 0x48, 0xA0, 0x3B, 0xBF, 0xD2, 0x5E, 0x8C, 0xD0, 0x36, 0x41, 0x41,
+];
+
+/// The error type for operations with VRF proofs
+#[derive(Debug, PartialEq, Fail)]
+pub enum VrfError {
+    #[fail(display = "Malformed VRF proof")]
+    /// The proof cannot be decoded
+    MalformedProof,
+    #[fail(display = "Invalid VRF proof")]
+    /// The proof does not match the public key and the message
+    InvalidProof,
+    #[fail(display = "Failed to hash the message to a point of the curve")]
+    /// No valid point was found when hashing the message to the curve
+    HashToCurve,
+}
+
+/// Decoded VRF proof
+struct Proof {
+    gamma: PublicKey,
+    c: [u8; CHALLENGE_LENGTH],
+    s: SecretKey,
+}
+
+impl Proof {
+    fn from_bytes(bytes: &[u8]) -> Result<Self, VrfError> {
+        if bytes.len() != PROOF_LENGTH {
+            return Err(VrfError::MalformedProof);
+        }
+        let gamma =
+            PublicKey::from_slice(&bytes[..POINT_LENGTH]).map_err(|_| VrfError::MalformedProof)?;
+        let mut c = [0; CHALLENGE_LENGTH];
+        c.copy_from_slice(&bytes[POINT_LENGTH..POINT_LENGTH + CHALLENGE_LENGTH]);
+        let s = SecretKey::from_slice(&bytes[POINT_LENGTH + CHALLENGE_LENGTH..])
+            .map_err(|_| VrfError::MalformedProof)?;
+
+        Ok(Proof { gamma, c, s })
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(PROOF_LENGTH);
+        bytes.extend_from_slice(&self.gamma.serialize());
+        bytes.extend_from_slice(&self.c);
+        bytes.extend_from_slice(&self.s[..]);
+
+        bytes
+    }
+}
+
+/// Prove that `message` was evaluated with the secret key. The output of the VRF can be
+/// obtained from the proof using [`proof_to_hash`](proof_to_hash).
+pub fn prove(secret_key: &SecretKey, message: &[u8]) -> Result<Vec<u8>, VrfError> {
+    let secp = secp256k1_context();
+    let public_key = PublicKey::from_secret_key(secp, secret_key);
+
+    let h = hash_to_curve(&public_key, message)?;
+    let gamma = mul_point(secp, &h, &secret_key[..])?;
+
+    let k = nonce(secret_key, &h);
+    let u = PublicKey::from_secret_key(secp, &k);
+    let v = mul_point(secp, &h, &k[..])?;
+
+    let c = hash_points(&[&h, &gamma, &u, &v]);
+
+    // s = k + c * x (mod q)
+    let mut s = *secret_key;
+    s.mul_assign(&scalar_from_challenge(&c))
+        .map_err(|_| VrfError::InvalidProof)?;
+    s.add_assign(&k[..]).map_err(|_| VrfError::InvalidProof)?;
+
+    Ok(Proof { gamma, c, s }.to_bytes())
+}
+
+/// Verify a proof for `message` with the public key, returning the output of the VRF
+pub fn verify(public_key: &PublicKey, proof: &[u8], message: &[u8]) -> Result<Sha256, VrfError> {
+    let secp = secp256k1_context();
+    let proof = Proof::from_bytes(proof)?;
+    let h = hash_to_curve(public_key, message)?;
+    let minus_c = negate_scalar(&scalar_from_challenge(&proof.c));
+
+    // U = s * G - c * Y
+    let mut u = mul_point(secp, public_key, &minus_c)?;
+    u.add_exp_assign(secp, &proof.s[..])
+        .map_err(|_| VrfError::InvalidProof)?;
+
+    // V = s * H - c * Gamma
+    let v = mul_point(secp, &h, &proof.s[..])?
+        .combine(&mul_point(secp, &proof.gamma, &minus_c)?)
+        .map_err(|_| VrfError::InvalidProof)?;
+
+    if hash_points(&[&h, &proof.gamma, &u, &v]) == proof.c {
+        Ok(gamma_to_hash(&proof.gamma))
+    } else {
+        Err(VrfError::InvalidProof)
+    }
+}
+
+/// Output of the VRF for a proof. The proof must be verified with [`verify`](verify) before
+/// trusting its output.
+pub fn proof_to_hash(proof: &[u8]) -> Result<Sha256, VrfError> {
+    Proof::from_bytes(proof).map(|proof| gamma_to_hash(&proof.gamma))
+}
+
+fn gamma_to_hash(gamma: &PublicKey) -> Sha256 {
+    let mut bytes = vec![SUITE, 0x03];
+    bytes.extend_from_slice(&gamma.serialize());
+
+    calculate_sha256(&bytes)
+}
+
+/// Hash the message to a point of the curve, using try-and-increment
+fn hash_to_curve(public_key: &PublicKey, message: &[u8]) -> Result<PublicKey, VrfError> {
+    let mut bytes = vec![SUITE, 0x01];
+    bytes.extend_from_slice(&public_key.serialize());
+    bytes.extend_from_slice(message);
+    bytes.push(0);
+    let counter_index = bytes.len() - 1;
+
+    for counter in 0..=255 {
+        bytes[counter_index] = counter;
+        let Sha256(hash) = calculate_sha256(&bytes);
+        let mut point = [0x02; POINT_LENGTH];
+        point[1..].copy_from_slice(&hash);
+        if let Ok(point) = PublicKey::from_slice(&point) {
+            return Ok(point);
+        }
+    }
+
+    Err(VrfError::HashToCurve)
+}
+
+/// Challenge of the proof: truncated hash of the points
+fn hash_points(points: &[&PublicKey]) -> [u8; CHALLENGE_LENGTH] {
+    let mut bytes = vec![SUITE, 0x02];
+    for point in points {
+        bytes.extend_from_slice(&point.serialize());
+    }
+    let Sha256(hash) = calculate_sha256(&bytes);
+    let mut c = [0; CHALLENGE_LENGTH];
+    c.copy_from_slice(&hash[..CHALLENGE_LENGTH]);
+
+    c
+}
+
+/// Deterministic nonce derived from the secret key and the hashed message
+fn nonce(secret_key: &SecretKey, h: &PublicKey) -> SecretKey {
+    let mut bytes = secret_key[..].to_vec();
+    bytes.extend_from_slice(&h.serialize());
+    bytes.push(0);
+    let counter_index = bytes.len() - 1;
+
+    // The hash is not a valid scalar with negligible probability, so the loop always finishes
+    loop {
+        let Sha256(hash) = calculate_sha256(&bytes);
+        if let Ok(k) = SecretKey::from_slice(&hash) {
+            return k;
+        }
+        bytes[counter_index] = bytes[counter_index].wrapping_add(1);
+    }
+}
+
+fn mul_point(
+    secp: &Secp256k1<secp256k1::All>,
+    point: &PublicKey,
+    scalar: &[u8],
+) -> Result<PublicKey, VrfError> {
+    let mut result = *point;
+    result
+        .mul_assign(secp, scalar)
+        .map_err(|_| VrfError::InvalidProof)?;
+
+    Ok(result)
+}
+
+fn scalar_from_challenge(c: &[u8; CHALLENGE_LENGTH]) -> [u8; SCALAR_LENGTH] {
+    let mut scalar = [0; SCALAR_LENGTH];
+    scalar[SCALAR_LENGTH - CHALLENGE_LENGTH..].copy_from_slice(c);
+
+    scalar
+}
+
+/// Calculate `q - scalar`, for scalars lower than the curve order `q`
+fn negate_scalar(scalar: &[u8; SCALAR_LENGTH]) -> [u8; SCALAR_LENGTH] {
+    let mut result = [0; SCALAR_LENGTH];
+    let mut borrow = 0;
+    for ((digit, order), scalar) in result
+        .iter_mut()
+        .zip(CURVE_ORDER.iter())
+        .zip(scalar.iter())
+        .rev()
+    {
+        let difference = i16::from(*order) - i16::from(*scalar) - borrow;
+        borrow = if difference < 0 { 1 } else { 0 };
+        *digit = (difference + 256 * borrow) as u8;
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys() -> (SecretKey, PublicKey) {
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[0xcd; 32]).expect("32 bytes, within curve order");
+        let public_key = PublicKey::from_secret_key(&secp, &secret_key);
+
+        (secret_key, public_key)
+    }
+
+    #[test]
+    fn test_prove_and_verify() {
+        let (secret_key, public_key) = keys();
+        let message = b"witnet";
+
+        let proof = prove(&secret_key, message).unwrap();
+        assert_eq!(proof.len(), PROOF_LENGTH);
+        // Proofs are deterministic
+        assert_eq!(proof, prove(&secret_key, message).unwrap());
+
+        let output = verify(&public_key, &proof, message).unwrap();
+        assert_eq!(output, proof_to_hash(&proof).unwrap());
+
+        // Different messages have different outputs
+        let other_proof = prove(&secret_key, b"other").unwrap();
+        assert_ne!(output, proof_to_hash(&other_proof).unwrap());
+    }
+
+    #[test]
+    fn test_verify_invalid_proofs() {
+        let (secret_key, public_key) = keys();
+        let secp = Secp256k1::new();
+        let other_public_key =
+            PublicKey::from_secret_key(&secp, &SecretKey::from_slice(&[0xab; 32]).unwrap());
+        let proof = prove(&secret_key, b"witnet").unwrap();
+
+        assert_eq!(
+            verify(&public_key, &proof, b"other"),
+            Err(VrfError::InvalidProof)
+        );
+        assert_eq!(
+            verify(&other_public_key, &proof, b"witnet"),
+            Err(VrfError::InvalidProof)
+        );
+
+        let mut tampered_proof = proof.clone();
+        tampered_proof[POINT_LENGTH] ^= 1;
+        assert_eq!(
+            verify(&public_key, &tampered_proof, b"witnet"),
+            Err(VrfError::InvalidProof)
+        );
+
+        assert_eq!(
+            verify(&public_key, &proof[1..], b"witnet"),
+            Err(VrfError::MalformedProof)
+        );
+        assert_eq!(
+            proof_to_hash(&[0; PROOF_LENGTH]),
+            Err(VrfError::MalformedProof)
+        );
+    }
+
+    #[test]
+    fn test_negate_scalar() {
+        let mut one = [0; SCALAR_LENGTH];
+        one[SCALAR_LENGTH - 1] = 1;
+        let mut expected = CURVE_ORDER;
+        expected[SCALAR_LENGTH - 1] = 0x40;
+
+        assert_eq!(negate_scalar(&one), expected);
+        assert_eq!(negate_scalar(&[0; SCALAR_LENGTH]), CURVE_ORDER);
+    }
+}
@@ -1,16 +1,20 @@
-use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
 use std::u32::MAX as U32_MAX;
 
 use rand::{thread_rng, Rng};
 
+use witnet_crypto::{hash::Sha256, merkle::merkle_tree_root};
 use witnet_util::timestamp::get_timestamp;
 
 use super::{
-    chain::{Block, BlockHeader, CheckpointBeacon, InventoryEntry, LeadershipProof, Transaction},
+    chain::{
+        Block, BlockHeader, CheckpointBeacon, Environment, Hash, Hashable, InventoryEntry,
+        KeyedSignature, LeadershipProof, Output, SuperBlock, Transaction,
+    },
     error::BuildersError,
     types::{
-        Address, Command, GetPeers, InventoryAnnouncement, InventoryRequest, IpAddress, LastBeacon,
-        Message, Peers, Ping, Pong, Verack, Version,
+        Address, Command, GetBlocks, GetPeers, Headers, InventoryAnnouncement, InventoryRequest,
+        IpAddress, LastBeacon, Message, Peers, Ping, Pong, Reject, Verack, Version,
     },
 };
 
@@ -23,24 +27,37 @@ pub const PROTOCOL_VERSION: u32 = 0x0000_0001;
 /// Capabilities
 pub const CAPABILITIES: u64 = 0x0000_0000_0000_0001;
 
-/// User agent
+/// Capability bit of the nodes supporting the encrypted transport
+pub const ENCRYPTED_TRANSPORT_CAPABILITY: u64 = 0x0000_0000_0000_0002;
+
+/// Capability bit of the nodes which prune the bodies of old blocks, and therefore can only serve
+/// their headers
+pub const PRUNED_CAPABILITY: u64 = 0x0000_0000_0000_0004;
+
+/// User agent (prefix of the full user agent announced by the node)
 pub const USER_AGENT: &str = "full-node-desktop-edition";
 
 /// Genesis block
 pub const GENESIS: u64 = 0x0123_4567_89AB_CDEF;
 
+/// Maximum number of locator hashes allowed in a GetBlocks message
+pub const MAX_LOCATOR_HASHES: usize = 101;
+
+/// Maximum number of block headers allowed in a Headers message
+pub const MAX_HEADERS: usize = 2000;
+
 ////////////////////////////////////////////////////////////////////////////////////////
 // BUILDER PUBLIC FUNCTIONS
 ////////////////////////////////////////////////////////////////////////////////////////
 impl Message {
-    /// Function to build Ping messages
+    /// Function to build Ping messages with a random nonce
     pub fn build_ping(magic: u16) -> Message {
-        Message::build_message(
-            magic,
-            Command::Ping(Ping {
-                nonce: random_nonce(),
-            }),
-        )
+        Message::build_ping_with_nonce(magic, random_nonce())
+    }
+
+    /// Function to build Ping messages with a given nonce, so that the messages are reproducible
+    pub fn build_ping_with_nonce(magic: u16, nonce: u64) -> Message {
+        Message::build_message(magic, Command::Ping(Ping { nonce }))
     }
 
     /// Function to build Pong messages
@@ -69,12 +86,33 @@ impl Message {
         )
     }
 
-    /// Function to build Version messages
+    /// Function to build Version messages with a random nonce
     pub fn build_version(
         magic: u16,
         sender_addr: SocketAddr,
         receiver_addr: SocketAddr,
+        user_agent: String,
         last_epoch: u32,
+    ) -> Message {
+        Message::build_version_with_nonce(
+            magic,
+            sender_addr,
+            receiver_addr,
+            user_agent,
+            last_epoch,
+            random_nonce(),
+        )
+    }
+
+    /// Function to build Version messages with a given nonce, so that the messages are
+    /// reproducible
+    pub fn build_version_with_nonce(
+        magic: u16,
+        sender_addr: SocketAddr,
+        receiver_addr: SocketAddr,
+        user_agent: String,
+        last_epoch: u32,
+        nonce: u64,
     ) -> Message {
         Message::build_message(
             magic,
@@ -84,9 +122,38 @@ impl Message {
                 capabilities: CAPABILITIES,
                 sender_address: to_address(sender_addr),
                 receiver_address: to_address(receiver_addr),
-                user_agent: USER_AGENT.to_string(),
+                user_agent,
+                last_epoch,
+                nonce,
+                ephemeral_key: vec![],
+                consensus_constants: vec![],
+            }),
+        )
+    }
+
+    /// Function to build Version messages announcing the support of the encrypted transport,
+    /// with the ephemeral public key of the session
+    pub fn build_encrypted_version(
+        magic: u16,
+        sender_addr: SocketAddr,
+        receiver_addr: SocketAddr,
+        user_agent: String,
+        last_epoch: u32,
+        ephemeral_key: Vec<u8>,
+    ) -> Message {
+        Message::build_message(
+            magic,
+            Command::Version(Version {
+                version: PROTOCOL_VERSION,
+                timestamp: get_timestamp(),
+                capabilities: CAPABILITIES | ENCRYPTED_TRANSPORT_CAPABILITY,
+                sender_address: to_address(sender_addr),
+                receiver_address: to_address(receiver_addr),
+                user_agent,
                 last_epoch,
                 nonce: random_nonce(),
+                ephemeral_key,
+                consensus_constants: vec![],
             }),
         )
     }
@@ -96,6 +163,17 @@ impl Message {
         Message::build_message(magic, Command::Verack(Verack))
     }
 
+    /// Function to build Reject messages
+    pub fn build_reject(magic: u16, reason: String, min_version: u32) -> Message {
+        Message::build_message(
+            magic,
+            Command::Reject(Reject {
+                reason,
+                min_version,
+            }),
+        )
+    }
+
     /// Function to build InventoryAnnouncement messages
     pub fn build_inventory_announcement(
         magic: u16,
@@ -166,6 +244,56 @@ impl Message {
         )
     }
 
+    /// Function to build GetBlocks messages
+    pub fn build_get_blocks(
+        magic: u16,
+        locator_hashes: Vec<Hash>,
+    ) -> Result<Message, failure::Error> {
+        // Check there are some locator hashes, but not too many
+        if locator_hashes.is_empty() {
+            Err(BuildersError::NoLocatorHashes)?
+        }
+        if locator_hashes.len() > MAX_LOCATOR_HASHES {
+            Err(BuildersError::TooManyLocatorHashes {
+                count: locator_hashes.len(),
+                limit: MAX_LOCATOR_HASHES,
+            })?
+        }
+
+        Ok(Message::build_message(
+            magic,
+            Command::GetBlocks(GetBlocks { locator_hashes }),
+        ))
+    }
+
+    /// Function to build Headers messages
+    pub fn build_headers(magic: u16, headers: Vec<BlockHeader>) -> Result<Message, failure::Error> {
+        // An empty Headers message is valid: it means the peer has no newer blocks
+        if headers.len() > MAX_HEADERS {
+            Err(BuildersError::TooManyHeaders {
+                count: headers.len(),
+                limit: MAX_HEADERS,
+            })?
+        }
+
+        Ok(Message::build_message(
+            magic,
+            Command::Headers(Headers { headers }),
+        ))
+    }
+
+    /// Function to check that the magic number of a message belongs to the given environment
+    pub fn check_magic(&self, environment: &Environment) -> Result<(), BuildersError> {
+        if self.magic == environment.magic() {
+            Ok(())
+        } else {
+            Err(BuildersError::WrongMagic {
+                magic: self.magic,
+                environment: environment.clone(),
+            })
+        }
+    }
+
     /// Function to build a message from a command
     fn build_message(magic: u16, command: Command) -> Message {
         Message {
@@ -175,6 +303,42 @@ impl Message {
     }
 }
 
+////////////////////////////////////////////////////////////////////////////////////////
+// SUPERBLOCK BUILDER
+////////////////////////////////////////////////////////////////////////////////////////
+impl SuperBlock {
+    /// Function to build an unsigned SuperBlock from the blocks of a superblock period
+    pub fn build(index: u32, blocks: &[Block]) -> SuperBlock {
+        let last_block = blocks.last().map(Hashable::hash).unwrap_or_default();
+        let block_hashes: Vec<Hash> = blocks.iter().map(Hashable::hash).collect();
+        let tally_hashes: Vec<Hash> = blocks
+            .iter()
+            .flat_map(|block| block.txns.iter())
+            .filter(|transaction| {
+                transaction.body.outputs.iter().any(|output| match output {
+                    Output::Tally(_) => true,
+                    _ => false,
+                })
+            })
+            .map(Hashable::hash)
+            .collect();
+
+        SuperBlock {
+            index,
+            last_block,
+            blocks_merkle_root: hashes_merkle_root(&block_hashes),
+            tallies_merkle_root: hashes_merkle_root(&tally_hashes),
+            signatures: vec![],
+        }
+    }
+
+    /// Add the signature of a committee member to this SuperBlock
+    pub fn with_signature(mut self, signature: KeyedSignature) -> SuperBlock {
+        self.signatures.push(signature);
+        self
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////////////
 // AUX FUNCTIONS
 ////////////////////////////////////////////////////////////////////////////////////////
@@ -183,36 +347,73 @@ fn random_nonce() -> u64 {
     thread_rng().gen()
 }
 
-/// Function to build address witnet type from socket addr
-fn to_address(socket_addr: SocketAddr) -> Address {
-    match socket_addr {
-        SocketAddr::V4(addr) => Address {
-            ip: {
-                let ip = u32::from(addr.ip().to_owned());
-                IpAddress::Ipv4 { ip }
+/// Function to calculate the merkle root of a list of hashes
+fn hashes_merkle_root(hashes: &[Hash]) -> Hash {
+    let hashes: Vec<Sha256> = hashes
+        .iter()
+        .map(|hash| match hash {
+            Hash::SHA256(x) => Sha256(*x),
+        })
+        .collect();
+
+    Hash::from(merkle_tree_root(&hashes))
+}
+
+/// Function to build a Witnet [Address](types::Address) from a
+/// [SocketAddr](std::net::SocketAddr)
+pub fn to_address(socket_addr: SocketAddr) -> Address {
+    Address::from(socket_addr)
+}
+
+/// Function to build a [SocketAddr](std::net::SocketAddr) from a
+/// Witnet [Address](types::Address)
+pub fn from_address(addr: &Address) -> SocketAddr {
+    SocketAddr::from(*addr)
+}
+
+impl From<SocketAddrV4> for Address {
+    fn from(addr: SocketAddrV4) -> Self {
+        Address {
+            ip: IpAddress::Ipv4 {
+                ip: u32::from(*addr.ip()),
             },
             port: addr.port(),
-        },
-        SocketAddr::V6(addr) => Address {
-            ip: {
-                let ip = u128::from(addr.ip().to_owned());
-                IpAddress::Ipv6 {
-                    ip0: ((ip >> 96) & u128::from(U32_MAX)) as u32,
-                    ip1: ((ip >> 64) & u128::from(U32_MAX)) as u32,
-                    ip2: ((ip >> 32) & u128::from(U32_MAX)) as u32,
-                    ip3: (ip & u128::from(U32_MAX)) as u32,
-                }
+        }
+    }
+}
+
+/// The flow info and the scope id of the address are not kept
+impl From<SocketAddrV6> for Address {
+    fn from(addr: SocketAddrV6) -> Self {
+        let ip = u128::from(*addr.ip());
+
+        Address {
+            ip: IpAddress::Ipv6 {
+                ip0: ((ip >> 96) & u128::from(U32_MAX)) as u32,
+                ip1: ((ip >> 64) & u128::from(U32_MAX)) as u32,
+                ip2: ((ip >> 32) & u128::from(U32_MAX)) as u32,
+                ip3: (ip & u128::from(U32_MAX)) as u32,
             },
             port: addr.port(),
-        },
+        }
     }
 }
 
-/// Function to build a [SocketAddr](std::net::SocketAddr) from a
-/// Witnet [Address](types::Address)
-pub fn from_address(addr: &Address) -> SocketAddr {
-    let ip: IpAddr = addr.ip.into();
-    SocketAddr::from((ip, addr.port))
+impl From<SocketAddr> for Address {
+    fn from(addr: SocketAddr) -> Self {
+        match addr {
+            SocketAddr::V4(addr) => Address::from(addr),
+            SocketAddr::V6(addr) => Address::from(addr),
+        }
+    }
+}
+
+impl From<Address> for SocketAddr {
+    fn from(addr: Address) -> Self {
+        let ip: IpAddr = addr.ip.into();
+
+        SocketAddr::from((ip, addr.port))
+    }
 }
 
 impl From<IpAddress> for IpAddr {
@@ -288,4 +489,31 @@ mod tests {
 
         assert_eq!(socket_addr, expected);
     }
+
+    #[test]
+    fn test_socket_addr_v6_roundtrip() {
+        let socket_addr: SocketAddrV6 = "[2001:db8::ff00:42:8329]:21337".parse().unwrap();
+        let witnet_addr = Address::from(socket_addr);
+
+        assert_eq!(
+            witnet_addr.ip,
+            IpAddress::Ipv6 {
+                ip0: 0x2001_0db8,
+                ip1: 0,
+                ip2: 0x0000_ff00,
+                ip3: 0x0042_8329
+            }
+        );
+        assert_eq!(witnet_addr.port, 21337);
+        assert_eq!(SocketAddr::from(witnet_addr), SocketAddr::V6(socket_addr));
+    }
+
+    #[test]
+    fn test_socket_addr_v4_roundtrip() {
+        let socket_addr: SocketAddrV4 = "192.168.1.1:21337".parse().unwrap();
+        let witnet_addr = Address::from(socket_addr);
+
+        assert_eq!(witnet_addr, to_address(SocketAddr::V4(socket_addr)));
+        assert_eq!(SocketAddr::from(witnet_addr), SocketAddr::V4(socket_addr));
+    }
 }
@@ -2,31 +2,44 @@ use failure::Fail;
 use partial_struct::PartialStruct;
 use protobuf::Message;
 use secp256k1::{
-    PublicKey as Secp256k1_PublicKey, SecretKey as Secp256k1_SecretKey,
-    Signature as Secp256k1_Signature,
+    PublicKey as Secp256k1_PublicKey, RecoverableSignature as Secp256k1_RecoverableSignature,
+    RecoveryId, SecretKey as Secp256k1_SecretKey, Signature as Secp256k1_Signature,
 };
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::{
     cell::Cell,
-    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
     convert::AsRef,
     fmt,
+    ops::Bound,
     str::FromStr,
 };
 use witnet_crypto::{
+    bls,
     hash::{calculate_sha256, Sha256},
     key::ExtendedSK,
+    multisig,
+    signature::{secp256k1_context, verify_tagged, TRANSACTION_DOMAIN},
+    vrf,
 };
+use witnet_protected::{constant_time_eq, zero_out};
 use witnet_util::parser::parse_hex;
 
 use super::{
-    data_request::DataRequestPool,
-    error::OutputPointerParseError,
+    data_request::{DataRequestPool, DataRequestPoolDiff},
+    error::{
+        EpochCalculationError, KeyedSignatureError, OutputPointerParseError, SnapshotError,
+        TransactionError, UtxoError,
+    },
     proto::{schema::witnet, ProtobufConvert},
     serializers::decoders::{TryFrom, TryInto},
+    wit::Wit,
 };
 
+/// Types with a canonical hash. Chain types are hashed through their protobuf serialization, so
+/// their ids are the same everywhere they are computed: mining, validation and inventory handling.
 pub trait Hashable {
+    /// Canonical hash of the value
     fn hash(&self) -> Hash;
 }
 
@@ -52,6 +65,9 @@ pub enum Environment {
     /// "testnet" environment
     #[serde(rename = "testnet-1")]
     Testnet1,
+    /// "development" environment, meant for local networks
+    #[serde(rename = "development")]
+    Development,
 }
 
 impl Default for Environment {
@@ -60,10 +76,54 @@ impl Default for Environment {
     }
 }
 
+impl Environment {
+    /// All the known environments
+    pub const ALL: [Environment; 3] = [
+        Environment::Mainnet,
+        Environment::Testnet1,
+        Environment::Development,
+    ];
+
+    /// Magic number used to identify the environment in protocol messages
+    pub fn magic(&self) -> u16 {
+        match self {
+            Environment::Mainnet => 0x5741,
+            Environment::Testnet1 => 0x5754,
+            Environment::Development => 0x5744,
+        }
+    }
+
+    /// Find the environment a magic number belongs to, if any
+    pub fn from_magic(magic: u16) -> Option<Environment> {
+        Environment::ALL
+            .iter()
+            .find(|environment| environment.magic() == magic)
+            .cloned()
+    }
+
+    /// Human readable part of the bech32 encoded addresses
+    pub fn bech32_prefix(&self) -> &'static str {
+        match self {
+            Environment::Mainnet => "wit",
+            Environment::Testnet1 => "twit",
+            Environment::Development => "dwit",
+        }
+    }
+
+    /// Default port for the P2P server
+    pub fn default_port(&self) -> u16 {
+        match self {
+            Environment::Mainnet => 11337,
+            Environment::Testnet1 => 21337,
+            Environment::Development => 31337,
+        }
+    }
+}
+
 /// Consensus-critical configuration
 #[derive(PartialStruct, Debug, Clone, PartialEq, Serialize, Deserialize, ProtobufConvert)]
 #[partial_struct(derive(Deserialize, Default, Debug, Clone, PartialEq))]
-#[protobuf_convert(pb = "witnet::ConsensusConstants")]
+#[protobuf_convert(pb = "witnet::chain::ConsensusConstants")]
 pub struct ConsensusConstants {
     /// Timestamp at checkpoint 0 (the start of epoch 0)
     pub checkpoint_zero_timestamp: i64,
@@ -93,13 +153,17 @@ pub struct ConsensusConstants {
     /// This is only configurable in testnet, in mainnet the default
     /// will be used.
     pub max_block_weight: u32,
+
+    /// Minimum protocol version accepted from peers. Sessions with peers announcing an older
+    /// version are rejected during the handshake.
+    pub min_protocol_version: u32,
 }
 
 /// Checkpoint beacon structure
 #[derive(
     Copy, Clone, Debug, Default, Eq, Hash, PartialEq, Serialize, Deserialize, ProtobufConvert,
 )]
-#[protobuf_convert(pb = "witnet::CheckpointBeacon")]
+#[protobuf_convert(pb = "witnet::chain::CheckpointBeacon")]
 pub struct CheckpointBeacon {
     /// The serial number for an epoch
     pub checkpoint: Epoch,
@@ -107,12 +171,104 @@ pub struct CheckpointBeacon {
     pub hash_prev_block: Hash,
 }
 
+/// Checkpoint beacon signed by a trusted source, used to detect when the node is following a
+/// minority fork
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SignedCheckpoint {
+    /// Beacon of a block of the chain considered valid by the signer
+    pub beacon: CheckpointBeacon,
+    /// Signature of the hash of the beacon
+    pub signature: KeyedSignature,
+}
+
+/// Result of comparing a trusted checkpoint with the local chain
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CheckpointStatus {
+    /// The local chain contains the block of the checkpoint
+    Matching,
+    /// The local chain has not reached the epoch of the checkpoint yet
+    NotReached,
+    /// The local chain contains a different block (or no block at all) in the epoch of the
+    /// checkpoint
+    Mismatching {
+        /// Hash of the local block in the epoch of the checkpoint, if any
+        local_hash: Option<Hash>,
+    },
+}
+
 /// Epoch id (starting from 0)
 pub type Epoch = u32;
 
+/// Constants needed to convert between epochs and timestamps
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct EpochConstants {
+    /// Timestamp at checkpoint 0 (the start of epoch 0)
+    pub checkpoint_zero_timestamp: i64,
+
+    /// Seconds between the start of an epoch and the start of the next one
+    pub checkpoints_period: u16,
+}
+
+impl EpochConstants {
+    /// Calculate the last checkpoint (current epoch) at the supplied timestamp
+    pub fn epoch_at(&self, timestamp: i64) -> Result<Epoch, EpochCalculationError> {
+        if self.checkpoints_period == 0 {
+            return Err(EpochCalculationError::ZeroCheckpointsPeriod);
+        }
+
+        let elapsed = timestamp
+            .checked_sub(self.checkpoint_zero_timestamp)
+            .ok_or(EpochCalculationError::Overflow)?;
+        if elapsed < 0 {
+            Err(EpochCalculationError::CheckpointZeroInTheFuture(
+                self.checkpoint_zero_timestamp,
+            ))
+        } else {
+            let epoch = elapsed as u64 / u64::from(self.checkpoints_period);
+            if epoch > u64::from(Epoch::max_value()) {
+                Err(EpochCalculationError::Overflow)
+            } else {
+                Ok(epoch as Epoch)
+            }
+        }
+    }
+
+    /// Calculate the timestamp for a checkpoint (the start of an epoch)
+    pub fn timestamp_of(&self, epoch: Epoch) -> Result<i64, EpochCalculationError> {
+        // Calculate (period * epoch + zero) with overflow checks
+        i64::from(self.checkpoints_period)
+            .checked_mul(i64::from(epoch))
+            .and_then(|x| x.checked_add(self.checkpoint_zero_timestamp))
+            .ok_or(EpochCalculationError::Overflow)
+    }
+
+    /// Calculate the number of whole seconds from the supplied timestamp until the start of the
+    /// next epoch
+    pub fn seconds_to_next_checkpoint(&self, timestamp: i64) -> Result<u64, EpochCalculationError> {
+        let current_epoch = self.epoch_at(timestamp)?;
+        let next_checkpoint = self.timestamp_of(
+            current_epoch
+                .checked_add(1)
+                .ok_or(EpochCalculationError::Overflow)?,
+        )?;
+
+        // The next checkpoint is always after `timestamp`
+        Ok((next_checkpoint - timestamp) as u64)
+    }
+}
+
+impl<'a> From<&'a ConsensusConstants> for EpochConstants {
+    fn from(consensus_constants: &'a ConsensusConstants) -> Self {
+        EpochConstants {
+            checkpoint_zero_timestamp: consensus_constants.checkpoint_zero_timestamp,
+            checkpoints_period: consensus_constants.checkpoints_period,
+        }
+    }
+}
+
 /// Block data structure
 #[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize, ProtobufConvert)]
-#[protobuf_convert(pb = "witnet::Block")]
+#[protobuf_convert(pb = "witnet::chain::Block")]
 pub struct Block {
     /// The header of the block
     pub block_header: BlockHeader,
@@ -122,11 +278,25 @@ pub struct Block {
     pub txns: Vec<Transaction>,
 }
 
+impl Block {
+    /// Returns the size a block will have on the wire in bytes
+    pub fn size(&self) -> u32 {
+        self.to_pb().write_to_bytes().unwrap().len() as u32
+    }
+
+    /// Returns the weight of the block: the sum of the weights of its transactions.
+    /// The header and the proof have a fixed size, so they do not count towards the weight.
+    pub fn weight(&self) -> u32 {
+        self.txns
+            .iter()
+            .fold(0, |acc, txn| acc.saturating_add(txn.weight()))
+    }
+}
+
 /// Struct that keeps a block candidate and its modifications in the blockchain
 #[derive(Debug, Clone)]
 pub struct BlockInChain {
     pub block: Block,
-    pub utxo_set: UnspentOutputsPool,
     pub data_request_pool: DataRequestPool,
 }
 
@@ -138,13 +308,37 @@ impl<T: AsRef<[u8]>> Hashable for T {
 
 impl Hashable for Block {
     fn hash(&self) -> Hash {
-        calculate_sha256(&self.block_header.to_pb_bytes().unwrap()).into()
+        self.block_header.hash()
+    }
+}
+
+impl Hashable for BlockHeader {
+    fn hash(&self) -> Hash {
+        self.to_pb_hash()
     }
 }
 
 impl Hashable for CheckpointBeacon {
     fn hash(&self) -> Hash {
-        calculate_sha256(&self.to_pb_bytes().unwrap()).into()
+        self.to_pb_hash()
+    }
+}
+
+impl Hashable for SuperBlock {
+    /// The committee signatures are not part of the hash, as they sign it
+    fn hash(&self) -> Hash {
+        let unsigned = SuperBlock {
+            signatures: vec![],
+            ..self.clone()
+        };
+
+        unsigned.to_pb_hash()
+    }
+}
+
+impl Hashable for ConsensusConstants {
+    fn hash(&self) -> Hash {
+        self.to_pb_hash()
     }
 }
 
@@ -154,15 +348,28 @@ impl Hashable for TransactionBody {
     }
 }
 
+/// The hash of a transaction is its id. It only covers the transaction body, so the id does not
+/// change when the signatures are modified, and it can be used safely to identify transactions in
+/// inventories, merkle roots and output pointers. See [`Transaction::full_hash`] for a hash which
+/// includes the signatures.
 impl Hashable for Transaction {
     fn hash(&self) -> Hash {
         self.body.hash()
     }
 }
 
+impl Hashable for InventoryItem {
+    fn hash(&self) -> Hash {
+        match self {
+            InventoryItem::Block(block) => block.hash(),
+            InventoryItem::Transaction(transaction) => transaction.hash(),
+        }
+    }
+}
+
 /// Block header structure
 #[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize, ProtobufConvert, Default)]
-#[protobuf_convert(pb = "witnet::Block_BlockHeader")]
+#[protobuf_convert(pb = "witnet::chain::BlockHeader")]
 pub struct BlockHeader {
     /// The block version number indicating the block validation rules
     pub version: u32,
@@ -174,18 +381,48 @@ pub struct BlockHeader {
 
 /// Proof of leadership structure
 #[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize, ProtobufConvert, Default)]
-#[protobuf_convert(pb = "witnet::Block_LeadershipProof")]
+#[protobuf_convert(pb = "witnet::chain::LeadershipProof")]
 pub struct LeadershipProof {
     /// An enveloped signature of the block header except the `proof` part
     pub block_sig: KeyedSignature,
+    /// VRF proof of the hash of the beacon, made with the same key as `block_sig`, whose output
+    /// proves the eligibility of the miner for the epoch of the block
+    pub proof: VrfProof,
+}
+
+/// Number of epochs in every superblock period
+pub const SUPERBLOCK_PERIOD: Epoch = 10;
+
+/// Superblock structure: an aggregated commitment to all the blocks of a
+/// superblock period, meant to be relayed to other chains
+#[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize, ProtobufConvert, Default)]
+#[protobuf_convert(pb = "witnet::chain::SuperBlock")]
+pub struct SuperBlock {
+    /// Serial number of the superblock
+    pub index: u32,
+    /// Hash of the last block included in the superblock period
+    pub last_block: Hash,
+    /// Merkle root of the hashes of all the blocks in the period
+    pub blocks_merkle_root: Hash,
+    /// Merkle root of the hashes of all the tally transactions in the period
+    pub tallies_merkle_root: Hash,
+    /// Signatures of the superblock hash by the members of the committee. Votes use
+    /// [`Signature::Bls`](Signature::Bls) so that they can be aggregated for the relay.
+    pub signatures: Vec<KeyedSignature>,
 }
 
 /// Digital signatures structure (based on supported cryptosystems)
 #[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize, ProtobufConvert)]
-#[protobuf_convert(pb = "witnet::Signature")]
+#[protobuf_convert(pb = "witnet::chain::Signature")]
 pub enum Signature {
     /// ECDSA over secp256k1
     Secp256k1(Secp256k1Signature),
+    /// Recoverable ECDSA over secp256k1
+    Secp256k1Recoverable(Secp256k1RecoverableSignature),
+    /// Schnorr multi-signature over secp256k1, aggregated from several signers
+    Secp256k1Multisig(Secp256k1MultiSignature),
+    /// BLS signature over BN256, which can be aggregated with others of the same message
+    Bls(BlsSignature),
 }
 
 impl Default for Signature {
@@ -196,12 +433,38 @@ impl Default for Signature {
 
 /// ECDSA (over secp256k1) signature
 #[derive(Debug, Default, Eq, PartialEq, Clone, Serialize, Deserialize, ProtobufConvert)]
-#[protobuf_convert(pb = "witnet::Secp256k1Signature")]
+#[protobuf_convert(pb = "witnet::chain::Secp256k1Signature")]
 pub struct Secp256k1Signature {
     /// The signature serialized in DER
     pub der: Vec<u8>,
 }
 
+/// Recoverable ECDSA (over secp256k1) signature, from which the public key of the signer can be
+/// derived
+#[derive(Debug, Default, Eq, PartialEq, Clone, Serialize, Deserialize, ProtobufConvert)]
+#[protobuf_convert(pb = "witnet::chain::Secp256k1RecoverableSignature")]
+pub struct Secp256k1RecoverableSignature {
+    /// The compact signature followed by the recovery id: `r || s || v`
+    pub signature: Vec<u8>,
+}
+
+/// Schnorr multi-signature (over secp256k1), which is verified with the aggregated public key of
+/// its signers
+#[derive(Debug, Default, Eq, PartialEq, Clone, Serialize, Deserialize, ProtobufConvert)]
+#[protobuf_convert(pb = "witnet::chain::Secp256k1MultiSignature")]
+pub struct Secp256k1MultiSignature {
+    /// The aggregated nonce point followed by the aggregated scalar: `R || s`
+    pub signature: Vec<u8>,
+}
+
+/// BLS (over BN256) signature, used by the committee members to vote for superblocks
+#[derive(Debug, Default, Eq, PartialEq, Clone, Serialize, Deserialize, ProtobufConvert)]
+#[protobuf_convert(pb = "witnet::chain::BlsSignature")]
+pub struct BlsSignature {
+    /// The G1 point of the signature, serialized as `x || y`
+    pub signature: Vec<u8>,
+}
+
 /// The error type for operations on a [`Secp256k1Signature`](Secp256k1Signature)
 #[derive(Debug, PartialEq, Fail)]
 pub enum Secp256k1ConversionError {
@@ -231,11 +494,98 @@ impl TryInto<Secp256k1_Signature> for Signature {
     fn try_into(self) -> Result<Secp256k1_Signature, Self::Error> {
         let x = match self {
             Signature::Secp256k1(y) => Secp256k1Signature::try_into(y)?,
+            Signature::Secp256k1Recoverable(y) => {
+                let recoverable: Secp256k1_RecoverableSignature = y.try_into()?;
+
+                recoverable.to_standard()
+            }
+            // Multi-signatures are Schnorr signatures and BLS signatures use another curve, none
+            // of them can be converted into ECDSA ones
+            Signature::Secp256k1Multisig(_) | Signature::Bls(_) => {
+                Err(Secp256k1ConversionError::FailSignatureConversion)?
+            }
         };
         Ok(x)
     }
 }
 
+impl From<multisig::Signature> for Signature {
+    fn from(signature: multisig::Signature) -> Self {
+        Signature::Secp256k1Multisig(Secp256k1MultiSignature::from(signature))
+    }
+}
+
+impl From<multisig::Signature> for Secp256k1MultiSignature {
+    fn from(signature: multisig::Signature) -> Self {
+        Secp256k1MultiSignature {
+            signature: signature.to_bytes(),
+        }
+    }
+}
+
+impl TryInto<multisig::Signature> for Secp256k1MultiSignature {
+    type Error = failure::Error;
+
+    fn try_into(self) -> Result<multisig::Signature, Self::Error> {
+        multisig::Signature::from_bytes(&self.signature)
+            .map_err(|_| Secp256k1ConversionError::FailSignatureConversion.into())
+    }
+}
+
+impl From<bls::Signature> for Signature {
+    fn from(signature: bls::Signature) -> Self {
+        Signature::Bls(BlsSignature::from(signature))
+    }
+}
+
+impl From<bls::Signature> for BlsSignature {
+    fn from(signature: bls::Signature) -> Self {
+        BlsSignature {
+            signature: signature.to_bytes(),
+        }
+    }
+}
+
+impl TryInto<bls::Signature> for BlsSignature {
+    type Error = failure::Error;
+
+    fn try_into(self) -> Result<bls::Signature, Self::Error> {
+        Ok(bls::Signature::from_bytes(&self.signature)?)
+    }
+}
+
+impl From<Secp256k1_RecoverableSignature> for Signature {
+    fn from(secp256k1_signature: Secp256k1_RecoverableSignature) -> Self {
+        Signature::Secp256k1Recoverable(Secp256k1RecoverableSignature::from(secp256k1_signature))
+    }
+}
+
+impl From<Secp256k1_RecoverableSignature> for Secp256k1RecoverableSignature {
+    fn from(secp256k1_signature: Secp256k1_RecoverableSignature) -> Self {
+        let (recovery_id, compact) = secp256k1_signature.serialize_compact();
+        let mut signature = compact.to_vec();
+        signature.push(recovery_id.to_i32() as u8);
+
+        Secp256k1RecoverableSignature { signature }
+    }
+}
+
+impl TryInto<Secp256k1_RecoverableSignature> for Secp256k1RecoverableSignature {
+    type Error = failure::Error;
+
+    fn try_into(self) -> Result<Secp256k1_RecoverableSignature, Self::Error> {
+        if self.signature.len() != 65 {
+            Err(Secp256k1ConversionError::FailSignatureConversion)?
+        }
+
+        RecoveryId::from_i32(i32::from(self.signature[64]))
+            .and_then(|recovery_id| {
+                Secp256k1_RecoverableSignature::from_compact(&self.signature[..64], recovery_id)
+            })
+            .map_err(|_| Secp256k1ConversionError::FailSignatureConversion.into())
+    }
+}
+
 impl From<Secp256k1_Signature> for Secp256k1Signature {
     fn from(secp256k1_signature: Secp256k1_Signature) -> Self {
         let der = secp256k1_signature.serialize_der();
@@ -248,8 +598,16 @@ impl TryInto<Secp256k1_Signature> for Secp256k1Signature {
     type Error = failure::Error;
 
     fn try_into(self) -> Result<Secp256k1_Signature, Self::Error> {
-        Secp256k1_Signature::from_der(&self.der)
-            .map_err(|_| Secp256k1ConversionError::FailSignatureConversion.into())
+        let signature = Secp256k1_Signature::from_der(&self.der)
+            .map_err(|_| Secp256k1ConversionError::FailSignatureConversion)?;
+
+        // Only the canonical encoding is accepted, so the bytes of a valid signature cannot be
+        // changed by third parties without invalidating it
+        if signature.serialize_der() != self.der {
+            Err(Secp256k1ConversionError::FailSignatureConversion)?
+        }
+
+        Ok(signature)
     }
 }
 
@@ -306,7 +664,7 @@ impl From<ExtendedSK> for ExtendedSecretKey {
 
 impl Into<ExtendedSK> for ExtendedSecretKey {
     fn into(self) -> ExtendedSK {
-        let secret_key = self.secret_key.into();
+        let secret_key = self.secret_key.clone().into();
 
         ExtendedSK {
             secret_key,
@@ -317,7 +675,7 @@ impl Into<ExtendedSK> for ExtendedSecretKey {
 
 /// Hash
 #[derive(Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Serialize, Hash, ProtobufConvert)]
-#[protobuf_convert(pb = "witnet::Hash")]
+#[protobuf_convert(pb = "witnet::chain::Hash")]
 pub enum Hash {
     /// SHA-256 Hash
     SHA256(SHA256),
@@ -329,6 +687,14 @@ impl Default for Hash {
     }
 }
 
+impl AsRef<[u8]> for Hash {
+    fn as_ref(&self) -> &[u8] {
+        match self {
+            Hash::SHA256(bytes) => bytes,
+        }
+    }
+}
+
 /// Conversion between witnet_crypto::Sha256 and witnet_data_structures::Hash
 impl From<Sha256> for Hash {
     fn from(x: Sha256) -> Self {
@@ -398,12 +764,72 @@ impl<'de> Deserialize<'de> for Hash {
 /// SHA-256 Hash
 pub type SHA256 = [u8; 32];
 
-/// Public Key Hash: slice of the digest of a public key (20 bytes)
-pub type PublicKeyHash = [u8; 20];
+/// Public Key Hash: first 20 bytes of the SHA256 digest of a compressed public key.
+///
+/// Outputs reference the hash instead of the full key: it is shorter and the key is only revealed
+/// when the output is spent.
+#[derive(
+    Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, Serialize, Deserialize,
+)]
+pub struct PublicKeyHash([u8; 20]);
+
+impl PublicKeyHash {
+    /// Create a public key hash from its bytes
+    pub const fn from_bytes(bytes: [u8; 20]) -> Self {
+        PublicKeyHash(bytes)
+    }
+
+    /// Hash a public key
+    pub fn from_public_key(public_key: &PublicKey) -> Self {
+        let Sha256(hash) = calculate_sha256(&public_key.to_bytes());
+        let mut pkh = [0; 20];
+        pkh.copy_from_slice(&hash[..20]);
+
+        PublicKeyHash(pkh)
+    }
+
+    /// Bytes of the public key hash
+    pub fn as_bytes(&self) -> &[u8; 20] {
+        &self.0
+    }
+
+    /// Whether this is the hash of `public_key`, i.e. whether `public_key` can spend the outputs
+    /// locked to this hash
+    pub fn matches(&self, public_key: &PublicKey) -> bool {
+        *self == PublicKeyHash::from_public_key(public_key)
+    }
+}
+
+impl From<&PublicKey> for PublicKeyHash {
+    fn from(public_key: &PublicKey) -> Self {
+        PublicKeyHash::from_public_key(public_key)
+    }
+}
+
+impl fmt::Display for PublicKeyHash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for byte in &self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Maximum weight of a block
+pub const MAX_BLOCK_WEIGHT: u32 = 10_000;
+
+/// Weight of every output on top of the serialized size of the transaction, as it grows the
+/// set of unspent outputs
+pub const OUTPUT_WEIGHT: u32 = 32;
+
+/// Weight of a data request output for every requested witness, as each of them will add a
+/// commit and a reveal transaction to future blocks
+pub const DATA_REQUEST_WITNESS_WEIGHT: u32 = 256;
 
 /// Transaction data structure
 #[derive(Debug, Default, Eq, PartialEq, Clone, Serialize, Deserialize, ProtobufConvert)]
-#[protobuf_convert(pb = "witnet::TransactionBody")]
+#[protobuf_convert(pb = "witnet::chain::TransactionBody")]
 pub struct TransactionBody {
     pub version: u32,
     pub inputs: Vec<Input>,
@@ -414,7 +840,7 @@ pub struct TransactionBody {
 
 /// Signed transaction data structure
 #[derive(Debug, Default, Eq, PartialEq, Clone, Serialize, Deserialize, ProtobufConvert)]
-#[protobuf_convert(pb = "witnet::Transaction")]
+#[protobuf_convert(pb = "witnet::chain::Transaction")]
 pub struct Transaction {
     pub body: TransactionBody,
     pub signatures: Vec<KeyedSignature>,
@@ -446,9 +872,19 @@ impl TransactionBody {
         self.to_pb().write_to_bytes().unwrap().len() as u32
     }
 
-    /// Return the value of the output with index `index`.
-    pub fn get_output_value(&self, index: usize) -> Option<u64> {
-        self.outputs.get(index).map(Output::value)
+    /// Returns the weight of the outputs of the transaction body
+    pub fn outputs_weight(&self) -> u32 {
+        self.outputs
+            .iter()
+            .fold(0, |acc, output| acc.saturating_add(output.weight()))
+    }
+
+    /// Return the value of the output with index `index`, or `None` if there is no such output
+    /// or its value overflows.
+    pub fn get_output_value(&self, index: usize) -> Option<Wit> {
+        self.outputs
+            .get(index)
+            .and_then(|output| output.value().ok())
     }
 
     /// Return the cached hash of the transaction or compute it on-the-fly
@@ -456,13 +892,10 @@ impl TransactionBody {
         match self.hash.get() {
             Some(hash) => hash,
             None => {
-                let hash = self
-                    .to_pb_bytes()
-                    .map(|bytes| calculate_sha256(&*bytes).into())
-                    .ok();
-                self.hash.set(hash);
+                let hash = self.to_pb_hash();
+                self.hash.set(Some(hash));
 
-                hash.unwrap()
+                hash
             }
         }
     }
@@ -487,6 +920,18 @@ impl Transaction {
     pub fn size(&self) -> u32 {
         self.to_pb().write_to_bytes().unwrap().len() as u32
     }
+
+    /// Hash of the whole transaction, including its signatures. Unlike the transaction id
+    /// returned by `hash()`, it changes whenever the signatures change.
+    pub fn full_hash(&self) -> Hash {
+        self.to_pb_hash()
+    }
+
+    /// Returns the weight of the transaction: its size on the wire plus the weight of its
+    /// outputs
+    pub fn weight(&self) -> u32 {
+        self.size().saturating_add(self.body.outputs_weight())
+    }
 }
 
 impl AsRef<Transaction> for Transaction {
@@ -497,7 +942,7 @@ impl AsRef<Transaction> for Transaction {
 
 /// Input data structure
 #[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize, ProtobufConvert)]
-#[protobuf_convert(pb = "witnet::TransactionBody_Input")]
+#[protobuf_convert(pb = "witnet::chain::Input")]
 pub enum Input {
     Commit(CommitInput),
     DataRequest(DataRequestInput),
@@ -532,7 +977,7 @@ impl Input {
 
 /// Value transfer input transaction data structure
 #[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize, ProtobufConvert, Default)]
-#[protobuf_convert(pb = "witnet::TransactionBody_Input_ValueTransferInput")]
+#[protobuf_convert(pb = "witnet::chain::ValueTransferInput")]
 pub struct ValueTransferInput {
     pub transaction_id: Hash,
     pub output_index: u32,
@@ -540,7 +985,7 @@ pub struct ValueTransferInput {
 
 /// Commit input transaction data structure
 #[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize, ProtobufConvert, Default)]
-#[protobuf_convert(pb = "witnet::TransactionBody_Input_CommitInput")]
+#[protobuf_convert(pb = "witnet::chain::CommitInput")]
 pub struct CommitInput {
     pub transaction_id: Hash,
     pub output_index: u32,
@@ -549,7 +994,7 @@ pub struct CommitInput {
 
 /// Commit input transaction data structure
 #[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize, ProtobufConvert, Default)]
-#[protobuf_convert(pb = "witnet::TransactionBody_Input_DataRequestInput")]
+#[protobuf_convert(pb = "witnet::chain::DataRequestInput")]
 pub struct DataRequestInput {
     pub transaction_id: Hash,
     pub output_index: u32,
@@ -558,7 +1003,7 @@ pub struct DataRequestInput {
 
 /// Reveal input transaction data structure
 #[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize, ProtobufConvert, Default)]
-#[protobuf_convert(pb = "witnet::TransactionBody_Input_RevealInput")]
+#[protobuf_convert(pb = "witnet::chain::RevealInput")]
 pub struct RevealInput {
     pub transaction_id: Hash,
     pub output_index: u32,
@@ -566,7 +1011,7 @@ pub struct RevealInput {
 
 /// Output data structure
 #[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize, ProtobufConvert)]
-#[protobuf_convert(pb = "witnet::TransactionBody_Output")]
+#[protobuf_convert(pb = "witnet::chain::Output")]
 pub enum Output {
     ValueTransfer(ValueTransferOutput),
     DataRequest(DataRequestOutput),
@@ -576,76 +1021,161 @@ pub enum Output {
 }
 
 impl Output {
+    /// Return the weight of an output, on top of its size on the wire.
+    pub fn weight(&self) -> u32 {
+        match self {
+            Output::DataRequest(output) => OUTPUT_WEIGHT.saturating_add(
+                DATA_REQUEST_WITNESS_WEIGHT.saturating_mul(u32::from(output.witnesses)),
+            ),
+            _ => OUTPUT_WEIGHT,
+        }
+    }
+
     /// Return the value of an output.
-    pub fn value(&self) -> u64 {
+    ///
+    /// The value of a data request output includes all of its fees, so it can overflow.
+    pub fn value(&self) -> Result<Wit, TransactionError> {
         match self {
-            Output::Commit(output) => output.value,
-            Output::Tally(output) => output.value,
-            Output::DataRequest(output) => {
-                output.value + output.commit_fee + output.reveal_fee + output.tally_fee
-            }
-            Output::Reveal(output) => output.value,
-            Output::ValueTransfer(output) => output.value,
+            Output::Commit(output) => Ok(output.value),
+            Output::Tally(output) => Ok(output.value),
+            Output::DataRequest(output) => Wit::checked_sum(vec![
+                output.value,
+                output.commit_fee,
+                output.reveal_fee,
+                output.tally_fee,
+            ])
+            .ok_or(TransactionError::ValueOverflow),
+            Output::Reveal(output) => Ok(output.value),
+            Output::ValueTransfer(output) => Ok(output.value),
         }
     }
 }
 
 /// Value transfer output transaction data structure
 #[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize, ProtobufConvert, Default)]
-#[protobuf_convert(pb = "witnet::TransactionBody_Output_ValueTransferOutput")]
+#[protobuf_convert(pb = "witnet::chain::ValueTransferOutput")]
 pub struct ValueTransferOutput {
     pub pkh: PublicKeyHash,
-    pub value: u64,
+    pub value: Wit,
 }
 
 /// Data request output transaction data structure
 #[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize, ProtobufConvert, Hash, Default)]
-#[protobuf_convert(pb = "witnet::TransactionBody_Output_DataRequestOutput")]
+#[protobuf_convert(pb = "witnet::chain::DataRequestOutput")]
 pub struct DataRequestOutput {
     pub pkh: PublicKeyHash,
     pub data_request: RADRequest,
-    pub value: u64,
+    pub value: Wit,
     pub witnesses: u16,
     pub backup_witnesses: u16,
-    pub commit_fee: u64,
-    pub reveal_fee: u64,
-    pub tally_fee: u64,
+    pub commit_fee: Wit,
+    pub reveal_fee: Wit,
+    pub tally_fee: Wit,
     pub time_lock: u64,
 }
 
 /// Commit output transaction data structure
 #[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize, ProtobufConvert, Default)]
-#[protobuf_convert(pb = "witnet::TransactionBody_Output_CommitOutput")]
+#[protobuf_convert(pb = "witnet::chain::CommitOutput")]
 pub struct CommitOutput {
     pub commitment: Hash,
-    pub value: u64,
+    pub value: Wit,
 }
 
 /// Reveal output transaction data structure
 #[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize, ProtobufConvert, Default)]
-#[protobuf_convert(pb = "witnet::TransactionBody_Output_RevealOutput")]
+#[protobuf_convert(pb = "witnet::chain::RevealOutput")]
 pub struct RevealOutput {
     pub reveal: Vec<u8>,
     pub pkh: PublicKeyHash,
-    pub value: u64,
+    pub value: Wit,
 }
 
 #[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize, ProtobufConvert, Default)]
-#[protobuf_convert(pb = "witnet::TransactionBody_Output_TallyOutput")]
+#[protobuf_convert(pb = "witnet::chain::TallyOutput")]
 pub struct TallyOutput {
     pub result: Vec<u8>,
     pub pkh: PublicKeyHash,
-    pub value: u64,
+    pub value: Wit,
 }
 
 /// Keyed signature data structure
 #[derive(Debug, Default, Eq, PartialEq, Clone, Serialize, Deserialize, ProtobufConvert)]
-#[protobuf_convert(pb = "witnet::KeyedSignature")]
+#[protobuf_convert(pb = "witnet::chain::KeyedSignature")]
 pub struct KeyedSignature {
     pub signature: Signature,
     pub public_key: PublicKey,
 }
 
+/// Verify that `keyed_signature` signs `hash` within the
+/// [`TRANSACTION_DOMAIN`](witnet_crypto::signature::TRANSACTION_DOMAIN), and that its public key
+/// hashes to `pkh`, i.e. that the signer can spend the outputs locked to `pkh`
+pub fn verify_signatures(
+    keyed_signature: &KeyedSignature,
+    hash: &Hash,
+    pkh: &PublicKeyHash,
+) -> Result<(), KeyedSignatureError> {
+    let found = PublicKeyHash::from_public_key(&keyed_signature.public_key);
+    if found != *pkh {
+        Err(KeyedSignatureError::PublicKeyHashMismatch {
+            expected: *pkh,
+            found,
+        })?
+    }
+
+    let invalid_signature = || KeyedSignatureError::InvalidSignature { hash: *hash };
+    let signature: Secp256k1_Signature = keyed_signature
+        .signature
+        .clone()
+        .try_into()
+        .map_err(|_| invalid_signature())?;
+    let public_key: Secp256k1_PublicKey = keyed_signature
+        .public_key
+        .clone()
+        .try_into()
+        .map_err(|_| invalid_signature())?;
+    let Hash::SHA256(message) = hash;
+
+    verify_tagged(&public_key, TRANSACTION_DOMAIN, message, &signature)
+        .map_err(|_| invalid_signature())
+}
+
+/// Proof of the output of a verifiable random function, used to prove the eligibility to mine
+/// a block or to commit to a data request
+#[derive(Debug, Default, Eq, PartialEq, Clone, Serialize, Deserialize, ProtobufConvert)]
+#[protobuf_convert(pb = "witnet::chain::VrfProof")]
+pub struct VrfProof {
+    /// ECVRF proof, see [`witnet_crypto::vrf`](witnet_crypto::vrf)
+    pub proof: Vec<u8>,
+    /// Public key of the prover
+    pub public_key: PublicKey,
+}
+
+impl VrfProof {
+    /// Create a proof of the evaluation of `message` with the secret key
+    pub fn new(secret_key: &Secp256k1_SecretKey, message: &[u8]) -> Result<Self, failure::Error> {
+        let public_key = Secp256k1_PublicKey::from_secret_key(secp256k1_context(), secret_key);
+        let proof = vrf::prove(secret_key, message)?;
+
+        Ok(VrfProof {
+            proof,
+            public_key: PublicKey::from(public_key),
+        })
+    }
+
+    /// Verify the proof for `message`, returning the output of the VRF
+    pub fn verify(&self, message: &[u8]) -> Result<Hash, failure::Error> {
+        let public_key = self.public_key.clone().try_into()?;
+
+        Ok(vrf::verify(&public_key, &self.proof, message)?.into())
+    }
+
+    /// Output of the VRF, which is only meaningful if the proof is valid
+    pub fn proof_to_hash(&self) -> Result<Hash, failure::Error> {
+        Ok(vrf::proof_to_hash(&self.proof)?.into())
+    }
+}
+
 /// Public Key data structure
 #[derive(Debug, Default, Eq, PartialEq, Clone, Serialize, Deserialize)]
 pub struct PublicKey {
@@ -653,27 +1183,80 @@ pub struct PublicKey {
     pub bytes: [u8; 32],
 }
 
-/// Secret Key data structure
-#[derive(Debug, Default, Eq, PartialEq, Clone, Serialize, Deserialize)]
+impl PublicKey {
+    /// Serialize the key in compressed form: the prefix byte followed by the x coordinate
+    pub fn to_bytes(&self) -> [u8; 33] {
+        let mut bytes = [0; 33];
+        bytes[0] = self.compressed;
+        bytes[1..].copy_from_slice(&self.bytes);
+
+        bytes
+    }
+}
+
+/// Secret Key data structure, zeroed out when dropped and compared in constant time
+#[derive(Default, Clone, Serialize, Deserialize)]
 pub struct SecretKey {
-    // TODO(#560): Use Protected type
     pub bytes: [u8; 32],
 }
 
-/// Extended Secret Key data structure
-#[derive(Debug, Default, Eq, PartialEq, Clone, Serialize, Deserialize)]
+impl PartialEq for SecretKey {
+    fn eq(&self, other: &Self) -> bool {
+        constant_time_eq(&self.bytes, &other.bytes)
+    }
+}
+
+impl Eq for SecretKey {}
+
+impl Drop for SecretKey {
+    fn drop(&mut self) {
+        zero_out(&mut self.bytes);
+    }
+}
+
+impl fmt::Debug for SecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SecretKey(***)")
+    }
+}
+
+/// Extended Secret Key data structure, zeroed out when dropped and compared in constant time
+#[derive(Default, Clone, Serialize, Deserialize)]
 pub struct ExtendedSecretKey {
     /// Secret key
     pub secret_key: SecretKey,
     /// Chain code
-    // TODO(#560): Use Protected type
     pub chain_code: [u8; 32],
 }
 
+impl PartialEq for ExtendedSecretKey {
+    fn eq(&self, other: &Self) -> bool {
+        // Both comparisons are always made, so the time does not reveal which one failed
+        (self.secret_key == other.secret_key)
+            & constant_time_eq(&self.chain_code, &other.chain_code)
+    }
+}
+
+impl Eq for ExtendedSecretKey {}
+
+impl Drop for ExtendedSecretKey {
+    fn drop(&mut self) {
+        zero_out(&mut self.chain_code);
+    }
+}
+
+impl fmt::Debug for ExtendedSecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ExtendedSecretKey(***)")
+    }
+}
+
 #[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize, Hash)]
 pub enum RADType {
     #[serde(rename = "HTTP-GET")]
     HttpGet,
+    #[serde(rename = "HTTP-POST")]
+    HttpPost,
 }
 
 impl Default for RADType {
@@ -684,10 +1267,7 @@ impl Default for RADType {
 
 /// RAD request data structure
 #[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize, ProtobufConvert, Hash, Default)]
-#[protobuf_convert(
-    pb = "witnet::TransactionBody_Output_DataRequestOutput_RADRequest",
-    crate = "crate"
-)]
+#[protobuf_convert(pb = "witnet::rad::RADRequest", crate = "crate")]
 pub struct RADRequest {
     pub not_before: u64,
     pub retrieve: Vec<RADRetrieve>,
@@ -697,46 +1277,52 @@ pub struct RADRequest {
 }
 
 #[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize, ProtobufConvert, Hash, Default)]
-#[protobuf_convert(
-    pb = "witnet::TransactionBody_Output_DataRequestOutput_RADRequest_RADRetrieve",
-    crate = "crate"
-)]
+#[protobuf_convert(pb = "witnet::rad::RADRetrieve", crate = "crate")]
 pub struct RADRetrieve {
     pub kind: RADType,
     pub url: String,
     pub script: Vec<u8>,
+    /// Maximum size of the response in bytes, `0` means no limit
+    #[serde(default)]
+    pub max_response_size: u64,
+    /// Expected media type of the response (e.g. `application/json`), empty means any
+    #[serde(default)]
+    pub content_type: String,
+    /// SHA-256 fingerprint of the TLS certificate expected from the server, empty means no pinning
+    #[serde(default)]
+    pub certificate_fingerprint: Vec<u8>,
+    /// Timeout of every attempt to retrieve the source in milliseconds, `0` means the default
+    /// timeout of the witness
+    #[serde(default)]
+    pub timeout: u64,
+    /// Body of `HTTP-POST` retrievals, empty for `HTTP-GET` ones
+    #[serde(default)]
+    pub body: Vec<u8>,
+    /// Media type of the body of `HTTP-POST` retrievals, empty means `application/json`
+    #[serde(default)]
+    pub body_content_type: String,
 }
 
 #[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize, ProtobufConvert, Hash, Default)]
-#[protobuf_convert(
-    pb = "witnet::TransactionBody_Output_DataRequestOutput_RADRequest_RADAggregate",
-    crate = "crate"
-)]
+#[protobuf_convert(pb = "witnet::rad::RADAggregate", crate = "crate")]
 pub struct RADAggregate {
     pub script: Vec<u8>,
 }
 
 #[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize, ProtobufConvert, Hash, Default)]
-#[protobuf_convert(
-    pb = "witnet::TransactionBody_Output_DataRequestOutput_RADRequest_RADConsensus",
-    crate = "crate"
-)]
+#[protobuf_convert(pb = "witnet::rad::RADConsensus", crate = "crate")]
 pub struct RADConsensus {
     pub script: Vec<u8>,
 }
 
 #[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize, ProtobufConvert, Hash, Default)]
-#[protobuf_convert(
-    pb = "witnet::TransactionBody_Output_DataRequestOutput_RADRequest_RADDeliver",
-    crate = "crate"
-)]
+#[protobuf_convert(pb = "witnet::rad::RADDeliver", crate = "crate")]
 pub struct RADDeliver {
     pub kind: RADType,
     pub url: String,
 }
 
 type WeightedHash = (u64, Hash);
-type WeightedTransaction = (u64, Transaction);
 
 /// Auxiliar methods to get the output pointer from an input
 
@@ -780,10 +1366,76 @@ impl ValueTransferInput {
 /// [`Hash`](Hash) and iteration over the
 /// transactions sorted from by transactions with bigger fees to
 /// transactions with smaller fees.
+///
+/// Transactions are prioritized by their fee per unit of weight, so
+/// that the most profitable transactions are included first in a
+/// block, and the least profitable ones are evicted first when the
+/// pool is full.
 #[derive(Debug, Default, Clone)]
 pub struct TransactionsPool {
-    transactions: HashMap<Hash, WeightedTransaction>,
+    transactions: HashMap<Hash, PoolEntry>,
     sorted_index: BTreeSet<WeightedHash>,
+    memory_usage: usize,
+    total_weight: u64,
+    /// Outputs spent by the value transfer inputs of the transactions,
+    /// with the hash of the transaction spending them
+    spent_outputs: HashMap<OutputPointer, Hash>,
+}
+
+/// Transaction stored in a `TransactionsPool`, with the data used to
+/// prioritize and expire it
+#[derive(Debug, Clone)]
+struct PoolEntry {
+    priority: u64,
+    fee: Wit,
+    weight: u32,
+    epoch: Epoch,
+    transaction: Transaction,
+}
+
+/// Summary of a transaction stored in a `TransactionsPool`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolTransactionInfo {
+    /// Hash of the transaction
+    pub hash: Hash,
+    /// Fee paid by the transaction
+    pub fee: Wit,
+    /// Weight of the transaction
+    pub weight: u32,
+    /// Fee in nanowits per 1000 units of weight
+    pub priority: u64,
+    /// Epoch in which the transaction entered the pool
+    pub epoch: Epoch,
+}
+
+/// Priority of a transaction in a `TransactionsPool`: its fee in
+/// nanowits per 1000 units of weight
+pub fn transaction_priority(fee: Wit, weight: u32) -> u64 {
+    let priority = u128::from(fee.nanowits()) * 1000 / u128::from(weight.max(1));
+
+    priority.min(u128::from(u64::max_value())) as u64
+}
+
+/// Outputs spent by the value transfer inputs of a transaction. Other
+/// inputs, such as the data request output spent by every commitment,
+/// can be shared by several transactions
+fn value_transfer_inputs(transaction: &Transaction) -> impl Iterator<Item = OutputPointer> + '_ {
+    transaction
+        .body
+        .inputs
+        .iter()
+        .filter_map(|input| match input {
+            Input::ValueTransfer(input) => Some(input.output_pointer()),
+            _ => None,
+        })
+}
+
+/// Approximate number of bytes used by a transaction stored in a `TransactionsPool`: its size
+/// on the wire plus the entries in the map and in the sorted index
+fn pool_entry_memory_usage(transaction: &Transaction) -> usize {
+    transaction.size() as usize
+        + std::mem::size_of::<(Hash, PoolEntry)>()
+        + std::mem::size_of::<WeightedHash>()
 }
 
 impl TransactionsPool {
@@ -799,6 +1451,9 @@ impl TransactionsPool {
         TransactionsPool {
             transactions: HashMap::new(),
             sorted_index: BTreeSet::new(),
+            memory_usage: 0,
+            total_weight: 0,
+            spent_outputs: HashMap::new(),
         }
     }
 
@@ -814,6 +1469,9 @@ impl TransactionsPool {
         TransactionsPool {
             transactions: HashMap::with_capacity(capacity),
             sorted_index: BTreeSet::new(),
+            memory_usage: 0,
+            total_weight: 0,
+            spent_outputs: HashMap::new(),
         }
     }
 
@@ -854,14 +1512,14 @@ impl TransactionsPool {
     /// # Examples:
     ///
     /// ```
-    /// # use witnet_data_structures::chain::{TransactionsPool, TransactionBody, Hash, Transaction};
+    /// # use witnet_data_structures::{chain::{TransactionsPool, TransactionBody, Hash, Transaction}, wit::Wit};
     /// let mut pool = TransactionsPool::new();
     ///
     /// let transaction = Transaction::default();
     ///
     /// assert_eq!(pool.len(), 0);
     ///
-    /// pool.insert(Hash::SHA256([0 as u8; 32]), transaction);
+    /// pool.insert(Hash::SHA256([0 as u8; 32]), transaction, Wit::default(), 0);
     ///
     /// assert_eq!(pool.len(), 1);
     /// ```
@@ -876,13 +1534,13 @@ impl TransactionsPool {
     ///
     /// # Examples:
     /// ```
-    /// # use witnet_data_structures::chain::{TransactionsPool, TransactionBody, Hash, Transaction};
+    /// # use witnet_data_structures::{chain::{TransactionsPool, TransactionBody, Hash, Transaction}, wit::Wit};
     /// let mut pool = TransactionsPool::new();
     /// let hash = Hash::SHA256([0 as u8; 32]);
     /// let transaction = Transaction::default();
     /// assert!(!pool.contains(&hash));
     ///
-    /// pool.insert(hash, transaction);
+    /// pool.insert(hash, transaction, Wit::default(), 0);
     ///
     /// assert!(pool.contains(&hash));
     /// ```
@@ -890,6 +1548,25 @@ impl TransactionsPool {
         self.transactions.contains_key(key)
     }
 
+    /// Returns a reference to the transaction for the specified hash, if
+    /// it is in the pool.
+    ///
+    /// # Examples:
+    /// ```
+    /// # use witnet_data_structures::{chain::{TransactionsPool, Hash, Transaction}, wit::Wit};
+    /// let mut pool = TransactionsPool::new();
+    /// let hash = Hash::SHA256([0 as u8; 32]);
+    /// let transaction = Transaction::default();
+    /// assert_eq!(pool.get(&hash), None);
+    ///
+    /// pool.insert(hash, transaction.clone(), Wit::default(), 0);
+    ///
+    /// assert_eq!(pool.get(&hash), Some(&transaction));
+    /// ```
+    pub fn get(&self, key: &Hash) -> Option<&Transaction> {
+        self.transactions.get(key).map(|entry| &entry.transaction)
+    }
+
     /// Returns an `Option` with the transaction for the specified hash or `None` if not exist.
     ///
     /// The `key` may be any borrowed form of the hash, but `Hash` and
@@ -897,11 +1574,11 @@ impl TransactionsPool {
     ///
     /// # Examples:
     /// ```
-    /// # use witnet_data_structures::chain::{TransactionsPool, TransactionBody, Hash, Transaction};
+    /// # use witnet_data_structures::{chain::{TransactionsPool, TransactionBody, Hash, Transaction}, wit::Wit};
     /// let mut pool = TransactionsPool::new();
     /// let hash = Hash::SHA256([0 as u8; 32]);
     /// let transaction = Transaction::default();
-    /// pool.insert(hash, transaction.clone());
+    /// pool.insert(hash, transaction.clone(), Wit::default(), 0);
     ///
     /// assert!(pool.contains(&hash));
     ///
@@ -911,79 +1588,291 @@ impl TransactionsPool {
     /// assert!(!pool.contains(&hash));
     /// ```
     pub fn remove(&mut self, key: &Hash) -> Option<Transaction> {
-        self.transactions.remove(key).map(|(weight, transaction)| {
-            self.sorted_index.remove(&(weight, *key));
-            transaction
-        })
+        self.transactions.remove(key).map(|entry| {
+            self.sorted_index.remove(&(entry.priority, *key));
+            self.memory_usage -= pool_entry_memory_usage(&entry.transaction);
+            self.total_weight -= u64::from(entry.weight);
+            for output in value_transfer_inputs(&entry.transaction) {
+                if self.spent_outputs.get(&output) == Some(key) {
+                    self.spent_outputs.remove(&output);
+                }
+            }
+            entry.transaction
+        })
     }
 
-    /// Insert a transaction identified by `key` into the pool.
+    /// Returns the hash of a transaction in the pool spending any of
+    /// the outputs spent by the value transfer inputs of `transaction`,
+    /// other than `transaction` itself.
     ///
     /// # Examples:
     ///
     /// ```
-    /// # use witnet_data_structures::chain::{TransactionsPool, TransactionBody, Hash, Transaction};
+    /// # use witnet_data_structures::chain::{TransactionsPool, Hash, Input, Transaction, ValueTransferInput};
+    /// # use witnet_data_structures::wit::Wit;
+    /// let mut pool = TransactionsPool::new();
+    /// let mut transaction = Transaction::default();
+    /// transaction.body.inputs = vec![Input::ValueTransfer(ValueTransferInput::default())];
+    /// let mut double_spend = transaction.clone();
+    /// double_spend.body.version = 1;
+    ///
+    /// pool.insert(Hash::SHA256([1; 32]), transaction, Wit::default(), 0);
+    ///
+    /// assert_eq!(pool.conflicting(&Hash::SHA256([2; 32]), &double_spend), Some(Hash::SHA256([1; 32])));
+    /// assert_eq!(pool.conflicting(&Hash::SHA256([2; 32]), &Transaction::default()), None);
+    /// ```
+    pub fn conflicting(&self, key: &Hash, transaction: &Transaction) -> Option<Hash> {
+        value_transfer_inputs(transaction)
+            .filter_map(|output| self.spent_outputs.get(&output))
+            .find(|spender| *spender != key)
+            .cloned()
+    }
+
+    /// Insert a transaction identified by `key` into the pool, paying
+    /// `fee` and received in `epoch`.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use witnet_data_structures::{chain::{TransactionsPool, TransactionBody, Hash, Transaction}, wit::Wit};
     /// let mut pool = TransactionsPool::new();
     /// let transaction = Transaction::default();
-    /// pool.insert(Hash::SHA256([0 as u8; 32]), transaction);
+    /// pool.insert(Hash::SHA256([0 as u8; 32]), transaction, Wit::default(), 0);
     ///
     /// assert!(!pool.is_empty());
     /// ```
-    pub fn insert(&mut self, key: Hash, transaction: Transaction) {
-        let weight = 0; // TODO: weight = transaction-fee / transaction-weight
-        self.transactions.insert(key, (weight, transaction));
-        self.sorted_index.insert((weight, key));
+    pub fn insert(&mut self, key: Hash, transaction: Transaction, fee: Wit, epoch: Epoch) {
+        let weight = transaction.weight();
+        let priority = transaction_priority(fee, weight);
+        self.remove(&key);
+
+        self.memory_usage += pool_entry_memory_usage(&transaction);
+        self.total_weight += u64::from(weight);
+        for output in value_transfer_inputs(&transaction) {
+            self.spent_outputs.insert(output, key);
+        }
+        self.transactions.insert(
+            key,
+            PoolEntry {
+                priority,
+                fee,
+                weight,
+                epoch,
+                transaction,
+            },
+        );
+        self.sorted_index.insert((priority, key));
     }
 
-    /// An iterator visiting all the transactions in the pool in
-    /// descending-fee order, that is, transactions with bigger fees
-    /// come first.
+    /// Returns the approximate number of bytes used by the transactions in the pool.
     ///
-    /// Examples:
+    /// # Examples:
     ///
     /// ```
-    /// # use witnet_data_structures::chain::{TransactionsPool, TransactionBody, Hash, Transaction};
+    /// # use witnet_data_structures::{chain::{TransactionsPool, Hash, Transaction}, wit::Wit};
     /// let mut pool = TransactionsPool::new();
     ///
+    /// assert_eq!(pool.memory_usage(), 0);
+    ///
+    /// pool.insert(Hash::SHA256([0 as u8; 32]), Transaction::default(), Wit::default(), 0);
+    ///
+    /// assert!(pool.memory_usage() > 0);
+    /// ```
+    pub fn memory_usage(&self) -> usize {
+        self.memory_usage
+    }
+
+    /// Removes the transactions with smaller fees until the memory used by the pool is not
+    /// greater than `max_memory` bytes, and returns the removed transactions.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use witnet_data_structures::{chain::{TransactionsPool, Hash, Transaction}, wit::Wit};
+    /// let mut pool = TransactionsPool::new();
+    ///
+    /// pool.insert(Hash::SHA256([0 as u8; 32]), Transaction::default(), Wit::default(), 0);
+    /// pool.insert(Hash::SHA256([1 as u8; 32]), Transaction::default(), Wit::default(), 0);
+    /// let max_memory = pool.memory_usage() - 1;
+    ///
+    /// let evicted = pool.evict(max_memory);
+    ///
+    /// assert_eq!(evicted.len(), 1);
+    /// assert_eq!(pool.len(), 1);
+    /// assert!(pool.memory_usage() <= max_memory);
+    /// ```
+    pub fn evict(&mut self, max_memory: usize) -> Vec<Transaction> {
+        self.evict_while(|pool| pool.memory_usage > max_memory)
+    }
+
+    /// Returns the sum of the weights of the transactions in the pool.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use witnet_data_structures::{chain::{TransactionsPool, Hash, Transaction}, wit::Wit};
+    /// let mut pool = TransactionsPool::new();
     /// let transaction = Transaction::default();
     ///
-    /// pool.insert(Hash::SHA256([0 as u8; 32]), transaction.clone());
-    /// pool.insert(Hash::SHA256([0 as u8; 32]), transaction);
+    /// pool.insert(Hash::SHA256([0 as u8; 32]), transaction.clone(), Wit::default(), 0);
     ///
-    /// let mut iter = pool.iter();
-    /// let tx1 = iter.next();
-    /// let tx2 = iter.next();
+    /// assert_eq!(pool.weight(), u64::from(transaction.weight()));
+    /// ```
+    pub fn weight(&self) -> u64 {
+        self.total_weight
+    }
+
+    /// Removes the transactions with smaller fees per unit of weight until the weight of the
+    /// pool is not greater than `max_weight`, and returns the removed transactions.
+    ///
+    /// # Examples:
     ///
-    /// // TODO: assert!(tx1.weight() >= tx2.weight());
     /// ```
-    pub fn iter(&self) -> impl Iterator<Item = &Transaction> {
+    /// # use witnet_data_structures::{chain::{TransactionsPool, Hash, Transaction}, wit::Wit};
+    /// let mut pool = TransactionsPool::new();
+    /// let transaction = Transaction::default();
+    ///
+    /// pool.insert(Hash::SHA256([0 as u8; 32]), transaction.clone(), Wit::from_nanowits(1), 0);
+    /// pool.insert(Hash::SHA256([1 as u8; 32]), transaction.clone(), Wit::from_nanowits(1000), 0);
+    ///
+    /// let evicted = pool.evict_weight(u64::from(transaction.weight()));
+    ///
+    /// assert_eq!(evicted.len(), 1);
+    /// assert!(pool.contains(&Hash::SHA256([1 as u8; 32])));
+    /// ```
+    pub fn evict_weight(&mut self, max_weight: u64) -> Vec<Transaction> {
+        self.evict_while(|pool| pool.total_weight > max_weight)
+    }
+
+    /// Removes the transactions that entered the pool `expiry_epochs` or more epochs before
+    /// `epoch`, and returns the removed transactions.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use witnet_data_structures::{chain::{TransactionsPool, Hash, Transaction}, wit::Wit};
+    /// let mut pool = TransactionsPool::new();
+    ///
+    /// pool.insert(Hash::SHA256([0 as u8; 32]), Transaction::default(), Wit::default(), 10);
+    ///
+    /// assert!(pool.remove_expired(19, 10).is_empty());
+    /// assert_eq!(pool.remove_expired(20, 10).len(), 1);
+    /// assert!(pool.is_empty());
+    /// ```
+    pub fn remove_expired(&mut self, epoch: Epoch, expiry_epochs: Epoch) -> Vec<Transaction> {
+        let expired: Vec<Hash> = self
+            .transactions
+            .iter()
+            .filter(|(_, entry)| entry.epoch.saturating_add(expiry_epochs) <= epoch)
+            .map(|(hash, _)| *hash)
+            .collect();
+
+        expired
+            .iter()
+            .filter_map(|hash| self.remove(hash))
+            .collect()
+    }
+
+    /// Returns the summary of the transaction for the specified hash, including its fee and
+    /// priority, or `None` if it is not in the pool.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use witnet_data_structures::{chain::{TransactionsPool, Hash, Transaction}, wit::Wit};
+    /// let mut pool = TransactionsPool::new();
+    /// let hash = Hash::SHA256([0 as u8; 32]);
+    ///
+    /// pool.insert(hash, Transaction::default(), Wit::from_nanowits(100), 3);
+    ///
+    /// let info = pool.info(&hash).unwrap();
+    /// assert_eq!(info.fee, Wit::from_nanowits(100));
+    /// assert_eq!(info.epoch, 3);
+    /// ```
+    pub fn info(&self, key: &Hash) -> Option<PoolTransactionInfo> {
+        self.transactions.get(key).map(|entry| PoolTransactionInfo {
+            hash: *key,
+            fee: entry.fee,
+            weight: entry.weight,
+            priority: entry.priority,
+            epoch: entry.epoch,
+        })
+    }
+
+    /// Returns the summaries of the transactions in the pool in
+    /// descending-priority order, that is, the order in which they
+    /// are included in a block.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # use witnet_data_structures::{chain::{TransactionsPool, Hash, Transaction}, wit::Wit};
+    /// let mut pool = TransactionsPool::new();
+    ///
+    /// pool.insert(Hash::SHA256([0 as u8; 32]), Transaction::default(), Wit::from_nanowits(1), 0);
+    /// pool.insert(Hash::SHA256([1 as u8; 32]), Transaction::default(), Wit::from_nanowits(9), 0);
+    ///
+    /// let info = pool.info_by_priority();
+    /// assert_eq!(info[0].hash, Hash::SHA256([1 as u8; 32]));
+    /// assert_eq!(info[1].hash, Hash::SHA256([0 as u8; 32]));
+    /// ```
+    pub fn info_by_priority(&self) -> Vec<PoolTransactionInfo> {
         self.sorted_index
             .iter()
             .rev()
-            .filter_map(move |(_, h)| self.transactions.get(h).map(|(_, t)| t))
+            .filter_map(|(_, hash)| self.info(hash))
+            .collect()
+    }
+
+    /// Removes the transactions with the lowest priority while `condition` holds
+    fn evict_while<F>(&mut self, condition: F) -> Vec<Transaction>
+    where
+        F: Fn(&Self) -> bool,
+    {
+        let mut evicted = vec![];
+        while condition(self) {
+            let (priority, key) = match self.sorted_index.iter().next() {
+                Some(weighted_hash) => *weighted_hash,
+                None => break,
+            };
+            match self.remove(&key) {
+                Some(transaction) => evicted.push(transaction),
+                None => {
+                    self.sorted_index.remove(&(priority, key));
+                }
+            }
+        }
+
+        evicted
     }
 
-    /// Returns a reference to the value corresponding to the key.
+    /// An iterator visiting all the transactions in the pool in
+    /// descending-fee order, that is, transactions with bigger fees
+    /// come first.
     ///
     /// Examples:
     ///
     /// ```
-    /// # use witnet_data_structures::chain::{TransactionsPool, TransactionBody, Hash, Transaction};
+    /// # use witnet_data_structures::{chain::{TransactionsPool, TransactionBody, Hash, Transaction}, wit::Wit};
     /// let mut pool = TransactionsPool::new();
-    /// let hash = Hash::SHA256([0 as u8; 32]);
     ///
     /// let transaction = Transaction::default();
     ///
-    /// assert!(pool.get(&hash).is_none());
+    /// pool.insert(Hash::SHA256([0 as u8; 32]), transaction.clone(), Wit::default(), 0);
+    /// pool.insert(Hash::SHA256([0 as u8; 32]), transaction, Wit::default(), 0);
     ///
-    /// pool.insert(hash, transaction);
+    /// let mut iter = pool.iter();
+    /// let tx1 = iter.next();
+    /// let tx2 = iter.next();
     ///
-    /// assert!(pool.get(&hash).is_some());
+    /// assert!(tx1.is_some());
+    /// assert!(tx2.is_none());
     /// ```
-    pub fn get(&self, key: &Hash) -> Option<&Transaction> {
-        self.transactions
-            .get(key)
-            .map(|(_, transaction)| transaction)
+    pub fn iter(&self) -> impl Iterator<Item = &Transaction> {
+        self.sorted_index
+            .iter()
+            .rev()
+            .filter_map(move |(_, h)| self.transactions.get(h).map(|entry| &entry.transaction))
     }
 
     /// Retains only the elements specified by the predicate.
@@ -994,14 +1883,14 @@ impl TransactionsPool {
     /// # Examples
     ///
     /// ```
-    /// # use witnet_data_structures::chain::{TransactionsPool, TransactionBody, Hash, Transaction};
+    /// # use witnet_data_structures::{chain::{TransactionsPool, TransactionBody, Hash, Transaction}, wit::Wit};
     ///
     /// let mut pool = TransactionsPool::new();
     ///
     /// let transaction = Transaction::default();
     ///
-    /// pool.insert(Hash::SHA256([0 as u8; 32]), transaction.clone());
-    /// pool.insert(Hash::SHA256([1 as u8; 32]), transaction);
+    /// pool.insert(Hash::SHA256([0 as u8; 32]), transaction.clone(), Wit::default(), 0);
+    /// pool.insert(Hash::SHA256([1 as u8; 32]), transaction, Wit::default(), 0);
     /// assert_eq!(pool.len(), 2);
     /// pool.retain(|h, _| match h { Hash::SHA256(n) => n[0]== 0 });
     /// assert_eq!(pool.len(), 1);
@@ -1013,12 +1902,22 @@ impl TransactionsPool {
         let TransactionsPool {
             ref mut transactions,
             ref mut sorted_index,
+            ref mut memory_usage,
+            ref mut total_weight,
+            ref mut spent_outputs,
         } = *self;
 
-        transactions.retain(|hash, (weight, transaction)| {
-            let retain = f(hash, transaction);
+        transactions.retain(|hash, entry| {
+            let retain = f(hash, &entry.transaction);
             if !retain {
-                sorted_index.remove(&(*weight, *hash));
+                sorted_index.remove(&(entry.priority, *hash));
+                *memory_usage -= pool_entry_memory_usage(&entry.transaction);
+                *total_weight -= u64::from(entry.weight);
+                for output in value_transfer_inputs(&entry.transaction) {
+                    if spent_outputs.get(&output) == Some(hash) {
+                        spent_outputs.remove(&output);
+                    }
+                }
             }
 
             retain
@@ -1028,7 +1927,8 @@ impl TransactionsPool {
 
 /// Unspent output data structure (equivalent of Bitcoin's UTXO)
 /// It is used to locate the output by its transaction identifier and its position
-#[derive(Default, Hash, Clone, Eq, PartialEq)]
+#[derive(Default, Hash, Clone, Eq, PartialEq, Ord, PartialOrd, ProtobufConvert)]
+#[protobuf_convert(pb = "witnet::chain::OutputPointer")]
 pub struct OutputPointer {
     pub transaction_id: Hash,
     pub output_index: u32,
@@ -1102,13 +2002,15 @@ impl<'de> Deserialize<'de> for OutputPointer {
 
 /// Inventory entry data structure
 #[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize, ProtobufConvert)]
-#[protobuf_convert(pb = "witnet::InventoryEntry")]
+#[protobuf_convert(pb = "witnet::chain::InventoryEntry")]
 pub enum InventoryEntry {
     Error(Hash),
     Tx(Hash),
     Block(Hash),
+    /// Hash of a transaction containing a data request output
     DataRequest(Hash),
-    DataResult(Hash),
+    /// Hash of a transaction containing a tally output
+    Tally(Hash),
 }
 
 /// Inventory element: block, txns
@@ -1152,8 +2054,10 @@ impl TryFrom<DataRequestInfo> for DataRequestReport {
 #[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
 pub struct DataRequestInfo {
     /// List of commitments to resolve the data request
+    #[serde(serialize_with = "crate::serializers::sorted::set")]
     pub commits: HashSet<OutputPointer>,
     /// List of reveals to the commitments (contains the data request witnet result)
+    #[serde(serialize_with = "crate::serializers::sorted::set")]
     pub reveals: HashSet<OutputPointer>,
     /// Tally of data request (contains final result)
     pub tally: Option<OutputPointer>,
@@ -1171,6 +2075,22 @@ pub struct DataRequestState {
     /// The epoch on which this data request has been or will be unlocked
     // (necessary for removing from the data_requests_by_epoch map)
     pub epoch: Epoch,
+    /// The epoch at the end of which the data request expires if none of its commitments has
+    /// been revealed. Set when the commit stage closes.
+    #[serde(default)]
+    pub reveal_deadline: Option<Epoch>,
+    /// Witnesses of this data request, indexed by the pointer to their commit output
+    #[serde(default)]
+    pub witnesses: HashMap<OutputPointer, DataRequestWitness>,
+}
+
+/// Witness which committed to a data request, used to update its reputation after the tally
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct DataRequestWitness {
+    /// Identity which signed the commitment
+    pub pkh: PublicKeyHash,
+    /// Revealed value, if the commitment has been revealed
+    pub reveal: Option<Vec<u8>>,
 }
 
 impl DataRequestState {
@@ -1184,6 +2104,8 @@ impl DataRequestState {
             info,
             stage,
             epoch,
+            reveal_deadline: None,
+            witnesses: HashMap::new(),
         }
     }
 
@@ -1245,6 +2167,15 @@ impl DataRequestState {
 
         self.stage != old_stage
     }
+
+    /// Check if the reveal stage has timed out at the end of `epoch` without any reveal
+    pub fn reveals_timed_out(&self, epoch: Epoch) -> bool {
+        self.stage == DataRequestStage::REVEAL
+            && self.info.reveals.is_empty()
+            && self
+                .reveal_deadline
+                .map_or(false, |deadline| deadline <= epoch)
+    }
 }
 
 /// Data request current stage
@@ -1258,30 +2189,455 @@ pub enum DataRequestStage {
     TALLY,
 }
 
-pub type UnspentOutputsPool = HashMap<OutputPointer, Output>;
+/// Maximum number of blocks that can be undone from an [`UnspentOutputsPool`](UnspentOutputsPool)
+pub const MAX_UNDO_BLOCKS: usize = 100;
+
+/// Changes made by a block to an [`UnspentOutputsPool`](UnspentOutputsPool), used to undo them
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Clone)]
+pub struct UtxoDiff {
+    /// Hash of the block
+    pub block_hash: Hash,
+    /// Pointers to the outputs created by the block
+    pub inserted: Vec<OutputPointer>,
+    /// Outputs spent by the block
+    pub removed: Vec<(OutputPointer, Output)>,
+}
+
+/// Changes which restore the data requests as they were before a block, used to undo it
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Clone)]
+pub struct DataRequestUndo {
+    /// Hash of the block
+    pub block_hash: Hash,
+    /// Leadership proof of the block, to weigh the branch it belongs to against competing ones
+    #[serde(default)]
+    pub proof: LeadershipProof,
+    /// Changes which turn the data request pool after the block into the one before it
+    pub changes: DataRequestPoolDiff,
+}
+
+/// Pool of unspent outputs, keyed by [`OutputPointer`](OutputPointer).
+///
+/// The diffs of the last applied blocks are kept so they can be undone in reverse order.
+/// These diffs are persisted along with the outputs when using serde, but the protocol buffers
+/// representation only contains the unspent outputs.
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Clone)]
+pub struct UnspentOutputsPool {
+    outputs: HashMap<OutputPointer, Output>,
+    diffs: VecDeque<UtxoDiff>,
+}
+
+impl UnspentOutputsPool {
+    /// Makes a new empty pool of unspent outputs.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the output pointed by `output_pointer`, if it is unspent.
+    pub fn get(&self, output_pointer: &OutputPointer) -> Option<&Output> {
+        self.outputs.get(output_pointer)
+    }
+
+    /// Returns `true` if the output pointed by `output_pointer` is unspent.
+    pub fn contains(&self, output_pointer: &OutputPointer) -> bool {
+        self.outputs.contains_key(output_pointer)
+    }
+
+    /// Inserts an unspent output. This change is not recorded in any diff.
+    pub fn insert(&mut self, output_pointer: OutputPointer, output: Output) -> Option<Output> {
+        self.outputs.insert(output_pointer, output)
+    }
+
+    /// Removes an unspent output. This change is not recorded in any diff.
+    pub fn remove(&mut self, output_pointer: &OutputPointer) -> Option<Output> {
+        self.outputs.remove(output_pointer)
+    }
+
+    /// Returns the number of unspent outputs.
+    pub fn len(&self) -> usize {
+        self.outputs.len()
+    }
+
+    /// Returns `true` if there are no unspent outputs.
+    pub fn is_empty(&self) -> bool {
+        self.outputs.is_empty()
+    }
+
+    /// Forgets the diffs of the applied blocks, which can no longer be undone.
+    pub fn clear_diffs(&mut self) {
+        self.diffs.clear();
+    }
+
+    /// An iterator visiting all the unspent outputs in arbitrary order.
+    pub fn iter(&self) -> impl Iterator<Item = (&OutputPointer, &Output)> {
+        self.outputs.iter()
+    }
+
+    /// Returns the sum of the values of the unspent value transfer outputs that belong to `pkh`.
+    pub fn balance(&self, pkh: &PublicKeyHash) -> Result<Wit, TransactionError> {
+        let values = self.outputs.values().filter_map(|output| match output {
+            Output::ValueTransfer(output) if &output.pkh == pkh => Some(output.value),
+            _ => None,
+        });
+
+        Wit::checked_sum(values).ok_or(TransactionError::ValueOverflow)
+    }
+
+    /// Updates the pool with the outputs spent and created by the transactions of a block, and
+    /// stores the changes so the block can be undone later.
+    ///
+    /// Data request outputs are spent by several commit transactions of the same block, so they
+    /// are removed after processing all the transactions.
+    pub fn apply_block(&mut self, block: &Block) {
+        let mut diff = UtxoDiff {
+            block_hash: block.hash(),
+            ..UtxoDiff::default()
+        };
+        let mut remove_later = vec![];
+
+        for transaction in &block.txns {
+            let txn_hash = transaction.hash();
+            for input in &transaction.body.inputs {
+                let output_pointer = input.output_pointer();
+                match input {
+                    Input::DataRequest(..) => remove_later.push(output_pointer),
+                    _ => self.spend(output_pointer, &mut diff),
+                }
+            }
+
+            for (index, output) in transaction.body.outputs.iter().enumerate() {
+                let output_pointer = OutputPointer {
+                    transaction_id: txn_hash,
+                    output_index: index as u32,
+                };
+                self.create(output_pointer, output.clone(), &mut diff);
+            }
+        }
+
+        for output_pointer in remove_later {
+            self.spend(output_pointer, &mut diff);
+        }
+
+        if self.diffs.len() == MAX_UNDO_BLOCKS {
+            self.diffs.pop_front();
+        }
+        self.diffs.push_back(diff);
+    }
+
+    /// Reverts the changes made by [`apply_block`](UnspentOutputsPool::apply_block).
+    ///
+    /// Blocks can only be undone in reverse order, starting from the last applied block.
+    pub fn undo_block(&mut self, block: &Block) -> Result<(), UtxoError> {
+        self.undo_block_hash(block.hash())
+    }
+
+    /// Reverts the changes made by the block with hash `block_hash`, which must be the last
+    /// applied block. Only the diff of the block is needed, not the block itself.
+    pub fn undo_block_hash(&mut self, block_hash: Hash) -> Result<(), UtxoError> {
+        match self.diffs.back().map(|diff| diff.block_hash) {
+            Some(last_block_hash) if last_block_hash == block_hash => {}
+            Some(last_block_hash) => Err(UtxoError::NotLastBlock {
+                block_hash,
+                last_block_hash,
+            })?,
+            None => Err(UtxoError::NoBlocksToUndo { block_hash })?,
+        }
+
+        let diff = self.diffs.pop_back().unwrap();
+        for output_pointer in &diff.inserted {
+            self.outputs.remove(output_pointer);
+        }
+        for (output_pointer, output) in diff.removed {
+            self.outputs.insert(output_pointer, output);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the changes made by the block with hash `block_hash`, which must be the last
+    /// applied block: the outputs it spent, and the outputs it created that are still unspent.
+    pub fn last_block_changes(
+        &self,
+        block_hash: Hash,
+    ) -> Option<(Vec<OutputPointer>, Vec<(OutputPointer, Output)>)> {
+        let diff = self
+            .diffs
+            .back()
+            .filter(|diff| diff.block_hash == block_hash)?;
+        let spent = diff
+            .removed
+            .iter()
+            .map(|(output_pointer, _)| output_pointer.clone())
+            .collect();
+        let created = diff
+            .inserted
+            .iter()
+            .filter_map(|output_pointer| {
+                self.outputs
+                    .get(output_pointer)
+                    .map(|output| (output_pointer.clone(), output.clone()))
+            })
+            .collect();
+
+        Some((spent, created))
+    }
+
+    /// Applies the changes made by a block, as returned by
+    /// [`last_block_changes`](UnspentOutputsPool::last_block_changes), and stores them so the
+    /// block can be undone later, like [`apply_block`](UnspentOutputsPool::apply_block) does.
+    pub fn apply_block_changes(
+        &mut self,
+        block_hash: Hash,
+        spent: &[OutputPointer],
+        created: &[(OutputPointer, Output)],
+    ) {
+        let mut diff = UtxoDiff {
+            block_hash,
+            ..UtxoDiff::default()
+        };
+        for output_pointer in spent {
+            self.spend(output_pointer.clone(), &mut diff);
+        }
+        for (output_pointer, output) in created {
+            self.create(output_pointer.clone(), output.clone(), &mut diff);
+        }
+
+        if self.diffs.len() == MAX_UNDO_BLOCKS {
+            self.diffs.pop_front();
+        }
+        self.diffs.push_back(diff);
+    }
+
+    /// Insert an output created by the block being applied. Outputs that existed before the
+    /// block are recorded in the diff so they can be restored.
+    fn create(&mut self, output_pointer: OutputPointer, output: Output, diff: &mut UtxoDiff) {
+        if let Some(previous) = self.outputs.insert(output_pointer.clone(), output) {
+            if !diff.inserted.contains(&output_pointer) {
+                diff.removed.push((output_pointer.clone(), previous));
+            }
+        }
+        diff.inserted.push(output_pointer);
+    }
+
+    /// Remove an output spent by the block being applied. Outputs created by the same block do
+    /// not need to be restored when undoing it.
+    fn spend(&mut self, output_pointer: OutputPointer, diff: &mut UtxoDiff) {
+        if let Some(output) = self.outputs.remove(&output_pointer) {
+            if !diff.inserted.contains(&output_pointer) {
+                diff.removed.push((output_pointer, output));
+            }
+        }
+    }
+}
 
 pub type Blockchain = BTreeMap<Epoch, Hash>;
 
 /// Blockchain state (valid at a certain epoch)
+///
+/// The unspent outputs and the data requests are too big to be persisted after every block, so
+/// they are not serialized with the rest of the state. They are persisted periodically as a
+/// [`ChainStateSnapshot`](ChainStateSnapshot), followed by a
+/// [`ChainStateUpdate`](ChainStateUpdate) for every block.
 #[derive(Debug, Default, Serialize, Deserialize, PartialEq, Clone)]
 pub struct ChainState {
     /// Blockchain information data structure
     pub chain_info: Option<ChainInfo>,
     /// Unspent Outputs Pool
+    #[serde(skip)]
     pub unspent_outputs_pool: UnspentOutputsPool,
     /// Collection of state structures for active data requests
+    #[serde(skip)]
     pub data_request_pool: DataRequestPool,
     /// List of consolidated blocks by epoch
     pub block_chain: Blockchain,
+    /// Changes which undo the last consolidated blocks to the data requests, oldest first
+    #[serde(default)]
+    pub data_request_undo: VecDeque<DataRequestUndo>,
+    /// Epoch before which the bodies of the consolidated blocks have been pruned
+    #[serde(default)]
+    pub pruned_until: Epoch,
+}
+
+/// Unspent outputs and data requests after a block
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Clone)]
+pub struct ChainStateSnapshot {
+    /// Beacon of the block
+    pub beacon: CheckpointBeacon,
+    /// Unspent Outputs Pool
+    pub unspent_outputs_pool: UnspentOutputsPool,
+    /// Collection of state structures for active data requests
+    pub data_request_pool: DataRequestPool,
+}
+
+impl ChainStateSnapshot {
+    /// Method to remove the state which only concerns the node that took the snapshot: the
+    /// reveals of its own commitments, the reports not persisted yet and the diffs to undo the
+    /// last blocks. The cache of data request pointers is removed too, as it can be rebuilt from
+    /// the data requests with
+    /// [`DataRequestPool::rebuild_pointer_cache`](DataRequestPool::rebuild_pointer_cache)
+    pub fn strip_local_state(&mut self) {
+        self.unspent_outputs_pool.clear_diffs();
+        self.data_request_pool.waiting_for_reveal.clear();
+        self.data_request_pool.to_be_stored.clear();
+        self.data_request_pool.dr_pointer_cache.clear();
+    }
+}
+
+/// Changes made by a block to the unspent outputs and to the data requests
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Clone)]
+pub struct ChainStateUpdate {
+    /// Beacon of the block
+    pub beacon: CheckpointBeacon,
+    /// Outputs spent by the block
+    pub spent: Vec<OutputPointer>,
+    /// Outputs created by the block, and still unspent after it
+    pub created: Vec<(OutputPointer, Output)>,
+    /// Changes made by the block to the data requests
+    pub data_requests: DataRequestPoolDiff,
 }
 
 impl ChainState {
+    /// Method to take a snapshot of the unspent outputs and the data requests at the tip of the
+    /// chain
+    pub fn snapshot(&self) -> Option<ChainStateSnapshot> {
+        let chain_info = self.chain_info.as_ref()?;
+
+        Some(ChainStateSnapshot {
+            beacon: chain_info.highest_block_checkpoint,
+            unspent_outputs_pool: self.unspent_outputs_pool.clone(),
+            data_request_pool: self.data_request_pool.clone(),
+        })
+    }
+    /// Method to get the changes made by the block at the tip of the chain
+    pub fn last_update(&self) -> Option<ChainStateUpdate> {
+        let beacon = self.chain_info.as_ref()?.highest_block_checkpoint;
+        let (spent, created) = self
+            .unspent_outputs_pool
+            .last_block_changes(beacon.hash_prev_block)?;
+        let undo = self
+            .data_request_undo
+            .back()
+            .filter(|undo| undo.block_hash == beacon.hash_prev_block)?;
+
+        Some(ChainStateUpdate {
+            beacon,
+            spent,
+            created,
+            data_requests: self.data_request_pool.inverse_diff(&undo.changes),
+        })
+    }
+    /// Method to restore the unspent outputs and the data requests from a snapshot and the
+    /// updates of the blocks consolidated after it, which must be the blocks of `block_chain`
+    pub fn restore(
+        &mut self,
+        snapshot: ChainStateSnapshot,
+        updates: Vec<ChainStateUpdate>,
+    ) -> Result<(), SnapshotError> {
+        let snapshot_epoch = snapshot.beacon.checkpoint;
+        if self.block_chain.get(&snapshot_epoch) != Some(&snapshot.beacon.hash_prev_block) {
+            Err(SnapshotError::NotInChain {
+                epoch: snapshot_epoch,
+                hash: snapshot.beacon.hash_prev_block,
+            })?
+        }
+
+        let mut blocks = self
+            .block_chain
+            .range((Bound::Excluded(snapshot_epoch), Bound::Unbounded));
+        let mut unspent_outputs_pool = snapshot.unspent_outputs_pool;
+        let mut data_request_pool = snapshot.data_request_pool;
+        for update in updates {
+            match blocks.next() {
+                Some((epoch, hash))
+                    if *epoch == update.beacon.checkpoint
+                        && *hash == update.beacon.hash_prev_block => {}
+                _ => Err(SnapshotError::UpdateMismatch {
+                    epoch: update.beacon.checkpoint,
+                })?,
+            }
+            unspent_outputs_pool.apply_block_changes(
+                update.beacon.hash_prev_block,
+                &update.spent,
+                &update.created,
+            );
+            data_request_pool.apply_diff(update.data_requests);
+        }
+        if let Some((epoch, _)) = blocks.next() {
+            Err(SnapshotError::UpdateMismatch { epoch: *epoch })?
+        }
+
+        self.unspent_outputs_pool = unspent_outputs_pool;
+        self.data_request_pool = data_request_pool;
+
+        Ok(())
+    }
+    /// Method to replace the chain with an imported snapshot: the block of its beacon becomes the
+    /// tip of the chain, and the blocks before it are not known
+    pub fn import(&mut self, snapshot: ChainStateSnapshot) {
+        if let Some(chain_info) = self.chain_info.as_mut() {
+            chain_info.highest_block_checkpoint = snapshot.beacon;
+        }
+        self.block_chain = Blockchain::new();
+        self.block_chain
+            .insert(snapshot.beacon.checkpoint, snapshot.beacon.hash_prev_block);
+        self.unspent_outputs_pool = snapshot.unspent_outputs_pool;
+        self.data_request_pool = snapshot.data_request_pool;
+        self.data_request_pool.rebuild_pointer_cache();
+        self.data_request_undo.clear();
+    }
+    /// Method to apply a block, validated on top of the tip of the chain, as the new tip. The
+    /// `data_request_pool` is the result of processing the transactions of the block. Returns
+    /// the reveals of the own commitments whose reveal stage starts, and the reports of the
+    /// data requests finished by the block
+    pub fn apply_block(
+        &mut self,
+        block: &Block,
+        data_request_pool: DataRequestPool,
+    ) -> (Vec<Transaction>, Vec<(OutputPointer, DataRequestReport)>) {
+        let block_hash = block.hash();
+        let block_epoch = block.block_header.beacon.checkpoint;
+
+        if let Some(chain_info) = self.chain_info.as_mut() {
+            chain_info.highest_block_checkpoint = CheckpointBeacon {
+                checkpoint: block_epoch,
+                hash_prev_block: block_hash,
+            };
+        }
+        self.unspent_outputs_pool.apply_block(block);
+
+        // Only the changes which undo the block are kept, instead of the previous data requests
+        let previous_data_request_pool =
+            std::mem::replace(&mut self.data_request_pool, data_request_pool);
+        let reveals = self
+            .data_request_pool
+            .update_data_request_stages(block_epoch);
+        let finished = self.data_request_pool.finished_data_requests();
+        let changes = self.data_request_pool.diff(&previous_data_request_pool);
+        self.push_data_request_undo(block_hash, block.proof.clone(), changes);
+        self.block_chain.insert(block_epoch, block_hash);
+
+        (reveals, finished)
+    }
+    /// Method to apply a consolidated block to the unspent outputs and the data requests again,
+    /// to rebuild them from the blocks of the chain when there is no snapshot
+    pub fn replay_block(&mut self, block: &Block) {
+        let block_hash = block.hash();
+        let block_epoch = block.block_header.beacon.checkpoint;
+
+        let mut data_request_pool = self.data_request_pool.clone();
+        // The mint transaction is not processed, as in the validation of the block
+        for transaction in block.txns.iter().skip(1) {
+            data_request_pool.process_transaction(transaction, block_epoch, &block_hash);
+        }
+        // The reveals were sent and the finished data requests were persisted when the block
+        // was consolidated
+        self.apply_block(block, data_request_pool);
+    }
     /// Method to check that all inputs point to unspent outputs
     pub fn find_unspent_outputs(&self, inputs: &[Input]) -> bool {
         inputs.iter().all(|tx_input| {
             let output_pointer = tx_input.output_pointer();
 
-            self.unspent_outputs_pool.contains_key(&output_pointer)
+            self.unspent_outputs_pool.contains(&output_pointer)
         })
     }
     /// Retrieve the output pointed by the output pointer in an input
@@ -1306,30 +2662,70 @@ impl ChainState {
     pub fn generate_unspent_outputs_pool(&self, block: &Block) -> UnspentOutputsPool {
         // Create a copy of the state "unspent_outputs_pool"
         let mut unspent_outputs = self.unspent_outputs_pool.clone();
+        unspent_outputs.apply_block(block);
 
-        let transactions = &block.txns;
-
-        for transaction in transactions {
-            let txn_hash = transaction.hash();
-            for input in &transaction.body.inputs {
-                // Obtain the OuputPointer of each input and remove it from the utxo_set
-                let output_pointer = input.output_pointer();
-
-                unspent_outputs.remove(&output_pointer);
-            }
+        unspent_outputs
+    }
+    /// Method to keep the changes which undo the block `block_hash` to the data requests, along
+    /// with its leadership proof, so that it can be undone. Only the changes of the last
+    /// [`MAX_UNDO_BLOCKS`](MAX_UNDO_BLOCKS) blocks are kept.
+    pub fn push_data_request_undo(
+        &mut self,
+        block_hash: Hash,
+        proof: LeadershipProof,
+        changes: DataRequestPoolDiff,
+    ) {
+        if self.data_request_undo.len() == MAX_UNDO_BLOCKS {
+            self.data_request_undo.pop_front();
+        }
+        self.data_request_undo.push_back(DataRequestUndo {
+            block_hash,
+            proof,
+            changes,
+        });
+    }
+    /// Method to select the consolidated blocks whose bodies can be pruned, advancing
+    /// `pruned_until`. The blocks of the last `keep_epochs` epochs (never less than
+    /// [`MAX_UNDO_BLOCKS`](MAX_UNDO_BLOCKS), so that they can be undone) and the blocks since the
+    /// oldest active data request are kept.
+    pub fn blocks_to_prune(&mut self, keep_epochs: Epoch) -> Vec<Hash> {
+        let tip_epoch = match self.block_chain.keys().next_back() {
+            Some(tip_epoch) => *tip_epoch,
+            None => return vec![],
+        };
+        let keep_epochs = std::cmp::max(keep_epochs, MAX_UNDO_BLOCKS as Epoch);
+        let mut boundary = tip_epoch.saturating_sub(keep_epochs);
+        if let Some(oldest_data_request) =
+            self.data_request_pool.data_requests_by_epoch.keys().next()
+        {
+            boundary = std::cmp::min(boundary, *oldest_data_request);
+        }
+        if boundary <= self.pruned_until {
+            return vec![];
+        }
 
-            for (index, output) in transaction.body.outputs.iter().enumerate() {
-                // Add the new outputs to the utxo_set
-                let output_pointer = OutputPointer {
-                    transaction_id: txn_hash,
-                    output_index: index as u32,
-                };
+        let hashes = self
+            .block_chain
+            .range(self.pruned_until..boundary)
+            .map(|(_epoch, hash)| *hash)
+            .collect();
+        self.pruned_until = boundary;
 
-                unspent_outputs.insert(output_pointer, output.clone());
+        hashes
+    }
+    /// Compare a trusted checkpoint with the consolidated blocks
+    pub fn check_checkpoint(&self, beacon: &CheckpointBeacon) -> CheckpointStatus {
+        let tip_epoch = self.block_chain.keys().next_back();
+        match self.block_chain.get(&beacon.checkpoint) {
+            Some(hash) if *hash == beacon.hash_prev_block => CheckpointStatus::Matching,
+            Some(hash) => CheckpointStatus::Mismatching {
+                local_hash: Some(*hash),
+            },
+            None if tip_epoch.map_or(false, |tip_epoch| *tip_epoch > beacon.checkpoint) => {
+                CheckpointStatus::Mismatching { local_hash: None }
             }
+            None => CheckpointStatus::NotReached,
         }
-
-        unspent_outputs
     }
 }
 
@@ -1398,6 +2794,387 @@ pub fn block_example() -> Block {
 mod tests {
     use super::*;
 
+    fn value_transfer_output(pkh: u8, nanowits: u64) -> Output {
+        Output::ValueTransfer(ValueTransferOutput {
+            pkh: PublicKeyHash::from_bytes([pkh; 20]),
+            value: Wit::from_nanowits(nanowits),
+        })
+    }
+
+    #[test]
+    fn unspent_outputs_pool_apply_and_undo_block() {
+        let spent_pointer = OutputPointer {
+            transaction_id: Hash::SHA256([1; 32]),
+            output_index: 0,
+        };
+        let mut pool = UnspentOutputsPool::new();
+        pool.insert(spent_pointer.clone(), value_transfer_output(1, 10));
+        let original_pool = pool.clone();
+
+        let mint = Transaction::new(
+            TransactionBody::new(0, vec![], vec![value_transfer_output(2, 5)]),
+            vec![],
+        );
+        let value_transfer = Transaction::new(
+            TransactionBody::new(
+                0,
+                vec![Input::ValueTransfer(ValueTransferInput {
+                    transaction_id: spent_pointer.transaction_id,
+                    output_index: spent_pointer.output_index,
+                })],
+                vec![value_transfer_output(2, 4), value_transfer_output(1, 6)],
+            ),
+            vec![],
+        );
+        let block = Block {
+            txns: vec![mint, value_transfer],
+            ..block_example()
+        };
+
+        pool.apply_block(&block);
+        assert!(!pool.contains(&spent_pointer));
+        assert_eq!(pool.len(), 3);
+        assert_eq!(
+            pool.balance(&PublicKeyHash::from_bytes([1; 20])),
+            Ok(Wit::from_nanowits(6))
+        );
+        assert_eq!(
+            pool.balance(&PublicKeyHash::from_bytes([2; 20])),
+            Ok(Wit::from_nanowits(9))
+        );
+
+        pool.undo_block(&block).unwrap();
+        assert_eq!(pool, original_pool);
+        assert_eq!(
+            pool.balance(&PublicKeyHash::from_bytes([1; 20])),
+            Ok(Wit::from_nanowits(10))
+        );
+        assert_eq!(
+            pool.balance(&PublicKeyHash::from_bytes([2; 20])),
+            Ok(Wit::default())
+        );
+    }
+
+    #[test]
+    fn unspent_outputs_pool_undo_only_last_block() {
+        let mut pool = UnspentOutputsPool::new();
+        let first_block = block_example();
+        let second_block = Block {
+            block_header: BlockHeader {
+                version: 1,
+                ..BlockHeader::default()
+            },
+            ..block_example()
+        };
+
+        assert_eq!(
+            pool.undo_block(&first_block),
+            Err(UtxoError::NoBlocksToUndo {
+                block_hash: first_block.hash()
+            })
+        );
+
+        pool.apply_block(&first_block);
+        pool.apply_block(&second_block);
+        assert_eq!(
+            pool.undo_block(&first_block),
+            Err(UtxoError::NotLastBlock {
+                block_hash: first_block.hash(),
+                last_block_hash: second_block.hash(),
+            })
+        );
+        assert_eq!(pool.undo_block(&second_block), Ok(()));
+        assert_eq!(pool.undo_block(&first_block), Ok(()));
+        assert!(pool.is_empty());
+    }
+
+    #[test]
+    fn unspent_outputs_pool_apply_block_changes() {
+        let spent_pointer = OutputPointer {
+            transaction_id: Hash::SHA256([1; 32]),
+            output_index: 0,
+        };
+        let mut original_pool = UnspentOutputsPool::new();
+        original_pool.insert(spent_pointer.clone(), value_transfer_output(1, 10));
+        let value_transfer = Transaction::new(
+            TransactionBody::new(
+                0,
+                vec![Input::ValueTransfer(ValueTransferInput {
+                    transaction_id: spent_pointer.transaction_id,
+                    output_index: spent_pointer.output_index,
+                })],
+                vec![value_transfer_output(2, 10)],
+            ),
+            vec![],
+        );
+        let block = Block {
+            txns: vec![value_transfer],
+            ..block_example()
+        };
+        let mut pool = original_pool.clone();
+        pool.apply_block(&block);
+
+        assert_eq!(pool.last_block_changes(Hash::default()), None);
+        let (spent, created) = pool.last_block_changes(block.hash()).unwrap();
+        assert_eq!(spent, vec![spent_pointer]);
+        assert_eq!(created.len(), 1);
+
+        // Applying the changes has the same effect as applying the block, and can be undone
+        let mut restored_pool = original_pool.clone();
+        restored_pool.apply_block_changes(block.hash(), &spent, &created);
+        assert_eq!(restored_pool, pool);
+        restored_pool.undo_block(&block).unwrap();
+        assert_eq!(restored_pool, original_pool);
+    }
+
+    #[test]
+    fn chain_state_restore_from_snapshot() {
+        let blocks: Vec<Block> = (1..=3)
+            .map(|epoch| {
+                let mint = Transaction::new(
+                    TransactionBody::new(0, vec![], vec![value_transfer_output(epoch, 5)]),
+                    vec![],
+                );
+                Block {
+                    block_header: BlockHeader {
+                        beacon: CheckpointBeacon {
+                            checkpoint: Epoch::from(epoch),
+                            hash_prev_block: Hash::default(),
+                        },
+                        ..BlockHeader::default()
+                    },
+                    txns: vec![mint],
+                    ..block_example()
+                }
+            })
+            .collect();
+        let beacon = |block: &Block| CheckpointBeacon {
+            checkpoint: block.block_header.beacon.checkpoint,
+            hash_prev_block: block.hash(),
+        };
+
+        let mut chain_state = ChainState::default();
+        let mut snapshot = None;
+        let mut updates = vec![];
+        for block in &blocks {
+            chain_state.replay_block(block);
+            chain_state
+                .block_chain
+                .insert(block.block_header.beacon.checkpoint, block.hash());
+            if snapshot.is_none() {
+                snapshot = Some(ChainStateSnapshot {
+                    beacon: beacon(block),
+                    unspent_outputs_pool: chain_state.unspent_outputs_pool.clone(),
+                    data_request_pool: chain_state.data_request_pool.clone(),
+                });
+            } else {
+                let (spent, created) = chain_state
+                    .unspent_outputs_pool
+                    .last_block_changes(block.hash())
+                    .unwrap();
+                let undo = chain_state.data_request_undo.back().unwrap();
+                updates.push(ChainStateUpdate {
+                    beacon: beacon(block),
+                    spent,
+                    created,
+                    data_requests: chain_state.data_request_pool.inverse_diff(&undo.changes),
+                });
+            }
+        }
+        let snapshot = snapshot.unwrap();
+        let empty_chain_state = ChainState {
+            block_chain: chain_state.block_chain.clone(),
+            ..ChainState::default()
+        };
+
+        let mut restored = empty_chain_state.clone();
+        restored.restore(snapshot.clone(), updates.clone()).unwrap();
+        assert_eq!(
+            restored.unspent_outputs_pool,
+            chain_state.unspent_outputs_pool
+        );
+        assert_eq!(restored.data_request_pool, chain_state.data_request_pool);
+
+        // Every block after the snapshot needs its update
+        let mut restored = empty_chain_state.clone();
+        assert_eq!(
+            restored.restore(snapshot.clone(), updates[..1].to_vec()),
+            Err(SnapshotError::UpdateMismatch { epoch: 3 })
+        );
+        assert_eq!(
+            restored.restore(snapshot.clone(), updates[1..].to_vec()),
+            Err(SnapshotError::UpdateMismatch { epoch: 3 })
+        );
+        assert!(restored.unspent_outputs_pool.is_empty());
+
+        // The snapshot must be of a block of the chain
+        let mut other_snapshot = snapshot;
+        other_snapshot.beacon.hash_prev_block = Hash::SHA256([7; 32]);
+        assert_eq!(
+            restored.restore(other_snapshot, updates),
+            Err(SnapshotError::NotInChain {
+                epoch: 1,
+                hash: Hash::SHA256([7; 32]),
+            })
+        );
+    }
+
+    #[test]
+    fn chain_state_import() {
+        let beacon = CheckpointBeacon {
+            checkpoint: 20,
+            hash_prev_block: Hash::SHA256([2; 32]),
+        };
+        let mut unspent_outputs_pool = UnspentOutputsPool::default();
+        unspent_outputs_pool.insert(
+            OutputPointer {
+                transaction_id: Hash::SHA256([1; 32]),
+                output_index: 0,
+            },
+            Output::ValueTransfer(ValueTransferOutput::default()),
+        );
+        let snapshot = ChainStateSnapshot {
+            beacon,
+            unspent_outputs_pool,
+            data_request_pool: DataRequestPool::default(),
+        };
+        let mut chain_state = ChainState::default();
+
+        chain_state.import(snapshot.clone());
+        assert_eq!(
+            chain_state.block_chain.iter().collect::<Vec<_>>(),
+            vec![(&beacon.checkpoint, &beacon.hash_prev_block)]
+        );
+        assert_eq!(
+            chain_state.unspent_outputs_pool,
+            snapshot.unspent_outputs_pool
+        );
+
+        // The imported chain can be restored from the same snapshot after a restart
+        let mut restored = ChainState {
+            block_chain: chain_state.block_chain.clone(),
+            ..ChainState::default()
+        };
+        restored.restore(snapshot, vec![]).unwrap();
+        assert_eq!(
+            restored.unspent_outputs_pool,
+            chain_state.unspent_outputs_pool
+        );
+    }
+
+    #[test]
+    fn chain_state_blocks_to_prune() {
+        let mut chain_state = ChainState::default();
+        for epoch in 0..300 {
+            chain_state
+                .block_chain
+                .insert(epoch, Hash::SHA256([epoch as u8; 32]));
+        }
+
+        // The last `MAX_UNDO_BLOCKS` blocks are always kept
+        let pruned = chain_state.blocks_to_prune(10);
+        assert_eq!(pruned.len(), 300 - 1 - MAX_UNDO_BLOCKS);
+        assert_eq!(chain_state.pruned_until, 299 - MAX_UNDO_BLOCKS as Epoch);
+        assert!(chain_state.blocks_to_prune(10).is_empty());
+
+        // The blocks since the oldest active data request are kept
+        let mut chain_state = ChainState::default();
+        for epoch in 0..300 {
+            chain_state
+                .block_chain
+                .insert(epoch, Hash::SHA256([epoch as u8; 32]));
+        }
+        chain_state
+            .data_request_pool
+            .data_requests_by_epoch
+            .insert(50, HashSet::new());
+        let pruned = chain_state.blocks_to_prune(200);
+        assert_eq!(pruned.len(), 50);
+        assert_eq!(pruned.last(), Some(&Hash::SHA256([49; 32])));
+        assert_eq!(chain_state.pruned_until, 50);
+    }
+
+    #[test]
+    fn chain_state_data_request_undo_capped() {
+        let mut chain_state = ChainState::default();
+        for i in 0..=MAX_UNDO_BLOCKS {
+            chain_state.push_data_request_undo(
+                Hash::SHA256([i as u8; 32]),
+                LeadershipProof::default(),
+                DataRequestPoolDiff::default(),
+            );
+        }
+
+        assert_eq!(chain_state.data_request_undo.len(), MAX_UNDO_BLOCKS);
+        assert_eq!(
+            chain_state.data_request_undo.front().unwrap().block_hash,
+            Hash::SHA256([1; 32])
+        );
+        assert_eq!(
+            chain_state.data_request_undo.back().unwrap().block_hash,
+            Hash::SHA256([MAX_UNDO_BLOCKS as u8; 32])
+        );
+    }
+
+    #[test]
+    fn transactions_pool_prioritized_by_fee_per_weight() {
+        let small = Transaction::default();
+        let mut big = Transaction::default();
+        big.body.outputs = (0..10).map(|i| value_transfer_output(i, 1)).collect();
+        assert!(big.weight() > small.weight());
+
+        // The big transaction pays a higher fee, but a lower fee per unit of weight
+        let fee_small = Wit::from_nanowits(u64::from(small.weight()) * 10);
+        let fee_big = Wit::from_nanowits(u64::from(big.weight()) * 5);
+        let mut pool = TransactionsPool::new();
+        pool.insert(Hash::SHA256([1; 32]), big.clone(), fee_big, 0);
+        pool.insert(Hash::SHA256([2; 32]), small.clone(), fee_small, 0);
+        assert_eq!(pool.iter().collect::<Vec<_>>(), vec![&small, &big]);
+        assert_eq!(
+            pool.weight(),
+            u64::from(small.weight()) + u64::from(big.weight())
+        );
+
+        // Replacing a transaction updates the weight
+        pool.insert(Hash::SHA256([2; 32]), small.clone(), fee_small, 1);
+        assert_eq!(pool.len(), 2);
+        assert_eq!(
+            pool.weight(),
+            u64::from(small.weight()) + u64::from(big.weight())
+        );
+
+        let evicted = pool.evict_weight(u64::from(small.weight()));
+        assert_eq!(evicted, vec![big]);
+        assert_eq!(pool.weight(), u64::from(small.weight()));
+        assert_eq!(pool.info(&Hash::SHA256([2; 32])).unwrap().priority, 10_000);
+    }
+
+    #[test]
+    fn transactions_pool_conflicts() {
+        let spend = |output_index| {
+            let mut transaction = Transaction::default();
+            transaction.body.inputs = vec![Input::ValueTransfer(ValueTransferInput {
+                transaction_id: Hash::SHA256([9; 32]),
+                output_index,
+            })];
+            transaction
+        };
+        let (first, second) = (Hash::SHA256([1; 32]), Hash::SHA256([2; 32]));
+        let mut pool = TransactionsPool::new();
+        pool.insert(first, spend(0), Wit::default(), 0);
+
+        assert_eq!(pool.conflicting(&second, &spend(0)), Some(first));
+        assert_eq!(pool.conflicting(&first, &spend(0)), None);
+        assert_eq!(pool.conflicting(&second, &spend(1)), None);
+
+        // Once removed, its outputs can be spent again
+        pool.retain(|_, _| false);
+        assert_eq!(pool.conflicting(&second, &spend(0)), None);
+        pool.insert(second, spend(0), Wit::default(), 0);
+        pool.remove(&second);
+        assert_eq!(pool.conflicting(&first, &spend(0)), None);
+    }
+
     #[test]
     fn test_block_hashable_trait() {
         let block = block_example();
@@ -1415,6 +3192,53 @@ mod tests {
         assert_eq!(transaction.hash().to_string(), expected);
     }
 
+    #[test]
+    fn test_verify_signatures() {
+        let secret_key = Secp256k1_SecretKey::from_slice(&[0xcd; 32]).unwrap();
+        let public_key = PublicKey::from(Secp256k1_PublicKey::from_secret_key(
+            secp256k1_context(),
+            &secret_key,
+        ));
+        let pkh = PublicKeyHash::from_public_key(&public_key);
+        let hash = Hash::SHA256([1; 32]);
+        let Hash::SHA256(message) = hash;
+        let keyed_signature = KeyedSignature {
+            signature: witnet_crypto::signature::sign_tagged(
+                secret_key,
+                TRANSACTION_DOMAIN,
+                &message,
+            )
+            .into(),
+            public_key,
+        };
+
+        assert_eq!(verify_signatures(&keyed_signature, &hash, &pkh), Ok(()));
+
+        let other_pkh = PublicKeyHash::from_bytes([2; 20]);
+        assert_eq!(
+            verify_signatures(&keyed_signature, &hash, &other_pkh),
+            Err(KeyedSignatureError::PublicKeyHashMismatch {
+                expected: other_pkh,
+                found: pkh,
+            })
+        );
+
+        let other_hash = Hash::SHA256([2; 32]);
+        assert_eq!(
+            verify_signatures(&keyed_signature, &other_hash, &pkh),
+            Err(KeyedSignatureError::InvalidSignature { hash: other_hash })
+        );
+
+        let malformed = KeyedSignature {
+            signature: Signature::default(),
+            ..keyed_signature
+        };
+        assert_eq!(
+            verify_signatures(&malformed, &hash, &pkh),
+            Err(KeyedSignatureError::InvalidSignature { hash })
+        );
+    }
+
     #[test]
     fn test_output_pointer_from_str() {
         let result_success = OutputPointer::from_str(
@@ -1473,6 +3297,43 @@ mod tests {
         assert_eq!(signature.to_string(), signature_into.to_string());
     }
 
+    #[test]
+    fn secp256k1_non_canonical_signatures() {
+        use crate::chain::Secp256k1Signature;
+        use secp256k1::{
+            Message as Secp256k1_Message, Secp256k1, SecretKey as Secp256k1_SecretKey,
+            Signature as Secp256k1_Signature,
+        };
+
+        let secp = Secp256k1::new();
+        let secret_key =
+            Secp256k1_SecretKey::from_slice(&[0xcd; 32]).expect("32 bytes, within curve order");
+        let msg = Secp256k1_Message::from_slice(&[0xab; 32]).unwrap();
+        let der = Secp256k1Signature::from(secp.sign(&msg, &secret_key)).der;
+
+        // Same signature, with a superfluous leading zero in `r`
+        let r_len = usize::from(der[3]);
+        let mut padded = vec![0x30, der[1] + 1, 0x02, der[3] + 1, 0x00];
+        padded.extend_from_slice(&der[4..]);
+        assert_eq!(padded.len(), der.len() + 1);
+        assert_eq!(padded[5..5 + r_len], der[4..4 + r_len]);
+
+        let result: Result<Secp256k1_Signature, _> = Secp256k1Signature { der: padded }.try_into();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn transaction_id_excludes_signatures() {
+        let transaction = transaction_example();
+        let mut resigned = transaction.clone();
+        resigned.signatures[0].signature = Signature::Secp256k1(Secp256k1Signature {
+            der: vec![0x30, 0x06, 0x02, 0x01, 0x01, 0x02, 0x01, 0x01],
+        });
+
+        assert_eq!(transaction.hash(), resigned.hash());
+        assert_ne!(transaction.full_hash(), resigned.full_hash());
+    }
+
     #[test]
     fn secp256k1_from_into_signatures() {
         use crate::chain::Signature;
@@ -1494,6 +3355,100 @@ mod tests {
         assert_eq!(signature.to_string(), signature_into.to_string());
     }
 
+    #[test]
+    fn secp256k1_from_into_recoverable_signatures() {
+        use crate::chain::{Secp256k1RecoverableSignature, Signature};
+        use secp256k1::{
+            Message as Secp256k1_Message, RecoverableSignature as Secp256k1_RecoverableSignature,
+            Secp256k1, SecretKey as Secp256k1_SecretKey, Signature as Secp256k1_Signature,
+        };
+
+        let data = [0xab; 32];
+        let secp = Secp256k1::new();
+        let secret_key =
+            Secp256k1_SecretKey::from_slice(&[0xcd; 32]).expect("32 bytes, within curve order");
+        let msg = Secp256k1_Message::from_slice(&data).unwrap();
+        let signature = secp.sign_recoverable(&msg, &secret_key);
+
+        let witnet_signature = Secp256k1RecoverableSignature::from(signature);
+        assert_eq!(witnet_signature.signature.len(), 65);
+        let signature_into: Secp256k1_RecoverableSignature =
+            witnet_signature.clone().try_into().unwrap();
+        assert_eq!(signature, signature_into);
+
+        // Recoverable signatures can also be used as standard signatures
+        let standard_into: Secp256k1_Signature = Signature::Secp256k1Recoverable(witnet_signature)
+            .try_into()
+            .unwrap();
+        assert_eq!(signature.to_standard(), standard_into);
+
+        let invalid_recovery_id = Secp256k1RecoverableSignature {
+            signature: [[1; 64].to_vec(), vec![4]].concat(),
+        };
+        let invalid_into: Result<Secp256k1_RecoverableSignature, _> =
+            invalid_recovery_id.try_into();
+        assert!(invalid_into.is_err());
+    }
+
+    #[test]
+    fn secp256k1_from_into_multi_signatures() {
+        use crate::chain::{Secp256k1MultiSignature, Signature};
+        use secp256k1::{
+            PublicKey as Secp256k1_PublicKey, SecretKey as Secp256k1_SecretKey,
+            Signature as Secp256k1_Signature,
+        };
+        use witnet_crypto::multisig::{self, AggregatedKey, Signer};
+
+        let secret_key = Secp256k1_SecretKey::from_slice(&[0xcd; 32]).unwrap();
+        let public_key = Secp256k1_PublicKey::from_secret_key(secp256k1_context(), &secret_key);
+        let aggregated_key = AggregatedKey::new(&[public_key]).unwrap();
+        let signer = Signer::new(aggregated_key, secret_key, b"witnet").unwrap();
+        let (_, combiner) = signer.partial_signature().unwrap();
+        let signature = combiner.signature().unwrap();
+
+        let witnet_signature = Secp256k1MultiSignature::from(signature);
+        assert_eq!(witnet_signature.signature.len(), multisig::SIGNATURE_LENGTH);
+        let signature_into: multisig::Signature = witnet_signature.clone().try_into().unwrap();
+        assert_eq!(signature, signature_into);
+
+        // Multi-signatures cannot be used as ECDSA signatures
+        let standard_into: Result<Secp256k1_Signature, _> =
+            Signature::Secp256k1Multisig(witnet_signature).try_into();
+        assert!(standard_into.is_err());
+
+        let malformed = Secp256k1MultiSignature {
+            signature: vec![1; multisig::SIGNATURE_LENGTH],
+        };
+        let malformed_into: Result<multisig::Signature, _> = malformed.try_into();
+        assert!(malformed_into.is_err());
+    }
+
+    #[test]
+    fn bls_from_into_signatures() {
+        use crate::chain::{BlsSignature, Signature};
+        use secp256k1::Signature as Secp256k1_Signature;
+        use witnet_crypto::bls;
+
+        let secret_key = bls::SecretKey::generate();
+        let signature = secret_key.sign(b"witnet");
+
+        let witnet_signature = BlsSignature::from(signature);
+        assert_eq!(witnet_signature.signature.len(), bls::SIGNATURE_LENGTH);
+        let signature_into: bls::Signature = witnet_signature.clone().try_into().unwrap();
+        assert_eq!(signature, signature_into);
+
+        // BLS signatures cannot be used as ECDSA signatures
+        let standard_into: Result<Secp256k1_Signature, _> =
+            Signature::Bls(witnet_signature).try_into();
+        assert!(standard_into.is_err());
+
+        let malformed = BlsSignature {
+            signature: vec![1; bls::SIGNATURE_LENGTH],
+        };
+        let malformed_into: Result<bls::Signature, _> = malformed.try_into();
+        assert!(malformed_into.is_err());
+    }
+
     #[test]
     fn secp256k1_from_into_public_keys() {
         use crate::chain::PublicKey;
@@ -1512,6 +3467,19 @@ mod tests {
         assert_eq!(public_key, pk_into);
     }
 
+    #[test]
+    fn vrf_proof_new_and_verify() {
+        use secp256k1::SecretKey as Secp256k1_SecretKey;
+
+        let secret_key =
+            Secp256k1_SecretKey::from_slice(&[0xcd; 32]).expect("32 bytes, within curve order");
+        let vrf_proof = VrfProof::new(&secret_key, b"epoch 1").unwrap();
+
+        let output = vrf_proof.verify(b"epoch 1").unwrap();
+        assert_eq!(output, vrf_proof.proof_to_hash().unwrap());
+        assert!(vrf_proof.verify(b"epoch 2").is_err());
+    }
+
     #[test]
     fn secp256k1_from_into_secret_keys() {
         use crate::chain::SecretKey;
@@ -1545,4 +3513,146 @@ mod tests {
         assert_eq!(extended_sk, extended_sk_into);
     }
 
+    #[test]
+    fn transaction_weight() {
+        let vt_output = Output::ValueTransfer(ValueTransferOutput::default());
+        let dr_output = Output::DataRequest(DataRequestOutput {
+            witnesses: 3,
+            ..DataRequestOutput::default()
+        });
+        let transaction = Transaction::new(
+            TransactionBody::new(0, vec![], vec![vt_output, dr_output]),
+            vec![],
+        );
+
+        assert_eq!(
+            transaction.weight(),
+            transaction.size() + 2 * OUTPUT_WEIGHT + 3 * DATA_REQUEST_WITNESS_WEIGHT
+        );
+    }
+
+    #[test]
+    fn block_weight() {
+        let block = block_example();
+        let txns_weight: u32 = block.txns.iter().map(Transaction::weight).sum();
+
+        assert_eq!(block.weight(), txns_weight);
+        assert!(block.weight() > 0);
+    }
+
+    #[test]
+    fn epoch_constants_conversions() {
+        let constants = EpochConstants {
+            checkpoint_zero_timestamp: 1000,
+            checkpoints_period: 90,
+        };
+
+        assert_eq!(constants.epoch_at(1000), Ok(0));
+        assert_eq!(constants.epoch_at(1089), Ok(0));
+        assert_eq!(constants.epoch_at(1090), Ok(1));
+        assert_eq!(
+            constants.epoch_at(999),
+            Err(EpochCalculationError::CheckpointZeroInTheFuture(1000))
+        );
+        assert_eq!(constants.timestamp_of(0), Ok(1000));
+        assert_eq!(constants.timestamp_of(1), Ok(1090));
+        assert_eq!(
+            constants.epoch_at(constants.timestamp_of(7).unwrap()),
+            Ok(7)
+        );
+    }
+
+    #[test]
+    fn epoch_constants_seconds_to_next_checkpoint() {
+        let constants = EpochConstants {
+            checkpoint_zero_timestamp: 1000,
+            checkpoints_period: 90,
+        };
+
+        assert_eq!(constants.seconds_to_next_checkpoint(1000), Ok(90));
+        assert_eq!(constants.seconds_to_next_checkpoint(1089), Ok(1));
+        assert_eq!(
+            EpochConstants::default().seconds_to_next_checkpoint(1000),
+            Err(EpochCalculationError::ZeroCheckpointsPeriod)
+        );
+    }
+
+    #[test]
+    fn chain_state_check_checkpoint() {
+        let hash = |byte| Hash::SHA256([byte; 32]);
+        let beacon = |checkpoint, hash_prev_block| CheckpointBeacon {
+            checkpoint,
+            hash_prev_block,
+        };
+        let mut chain_state = ChainState::default();
+        chain_state.block_chain.insert(3, hash(3));
+        chain_state.block_chain.insert(5, hash(5));
+
+        assert_eq!(
+            chain_state.check_checkpoint(&beacon(3, hash(3))),
+            CheckpointStatus::Matching
+        );
+        assert_eq!(
+            chain_state.check_checkpoint(&beacon(5, hash(1))),
+            CheckpointStatus::Mismatching {
+                local_hash: Some(hash(5))
+            }
+        );
+        // There is no local block in epoch 4, but the local chain is already past it
+        assert_eq!(
+            chain_state.check_checkpoint(&beacon(4, hash(4))),
+            CheckpointStatus::Mismatching { local_hash: None }
+        );
+        assert_eq!(
+            chain_state.check_checkpoint(&beacon(8, hash(8))),
+            CheckpointStatus::NotReached
+        );
+    }
+
+    #[test]
+    fn public_key_hash() {
+        // Compressed public key of the secret key 1:
+        // 0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798
+        let mut secret_key = [0; 32];
+        secret_key[31] = 1;
+        let secret_key = Secp256k1_SecretKey::from_slice(&secret_key).unwrap();
+        let public_key = PublicKey::from(Secp256k1_PublicKey::from_secret_key(
+            secp256k1_context(),
+            &secret_key,
+        ));
+        let pkh = PublicKeyHash::from_public_key(&public_key);
+
+        assert_eq!(
+            pkh,
+            PublicKeyHash::from_bytes([
+                0x0f, 0x71, 0x5b, 0xaf, 0x5d, 0x4c, 0x2e, 0xd3, 0x29, 0x78, 0x5c, 0xef, 0x29, 0xe5,
+                0x62, 0xf7, 0x34, 0x88, 0xc8, 0xa2,
+            ])
+        );
+        assert!(pkh.matches(&public_key));
+        assert!(!PublicKeyHash::default().matches(&public_key));
+        assert_eq!(PublicKeyHash::from(&public_key), pkh);
+    }
+
+    #[test]
+    fn rad_request_hash_covers_post_body() {
+        let rad_request = |body: &[u8]| RADRequest {
+            retrieve: vec![RADRetrieve {
+                kind: RADType::HttpPost,
+                url: "https://example.com/prices".to_string(),
+                body: body.to_vec(),
+                ..RADRetrieve::default()
+            }],
+            ..RADRequest::default()
+        };
+
+        assert_eq!(
+            rad_request(b"{}").to_pb_hash(),
+            rad_request(b"{}").to_pb_hash()
+        );
+        assert_ne!(
+            rad_request(br#"{"symbol":"BTCUSD"}"#).to_pb_hash(),
+            rad_request(br#"{"symbol":"ETHUSD"}"#).to_pb_hash()
+        );
+    }
 }
@@ -1,17 +1,24 @@
 use log::{debug, info};
 use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::Hash as StdHash;
 
-use witnet_crypto::hash::calculate_sha256;
+use witnet_crypto::hash::{calculate_sha256, Sha256};
 
 use super::chain::{
     CommitInput, CommitOutput, DataRequestInput, DataRequestOutput, DataRequestReport,
-    DataRequestStage, DataRequestState, Epoch, Hash, Hashable, Input, Output, OutputPointer,
-    RevealInput, RevealOutput, TallyOutput, Transaction, TransactionBody, UnspentOutputsPool,
-    ValueTransferOutput,
+    DataRequestStage, DataRequestState, DataRequestWitness, Epoch, Hash, Hashable, Input, Output,
+    OutputPointer, PublicKeyHash, RevealInput, RevealOutput, TallyOutput, Transaction,
+    TransactionBody, UnspentOutputsPool, ValueTransferOutput,
 };
+use super::reputation::{ReputationEngine, ReputationEngineDiff};
+use super::wit::Wit;
 
 use serde::{Deserialize, Serialize};
 
+/// Epochs to wait for the first reveals of a data request after its commit stage closes. If none
+/// of its commitments has been revealed by then, the data request expires.
+pub const REVEAL_TIMEOUT_EPOCHS: Epoch = 4;
+
 type DataRequestsWithReveals = Vec<(
     (OutputPointer, DataRequestOutput),
     Vec<(OutputPointer, RevealOutput)>,
@@ -24,14 +31,23 @@ pub struct DataRequestPool {
     /// Key: Data Request Pointer, Value: Reveal Transaction
     pub waiting_for_reveal: HashMap<OutputPointer, Transaction>,
     /// List of active data request output pointers ordered by epoch (for mining purposes)
+    #[serde(serialize_with = "crate::serializers::sorted::sets")]
     pub data_requests_by_epoch: BTreeMap<Epoch, HashSet<OutputPointer>>,
     /// List of active data requests indexed by output pointer
     pub data_request_pool: HashMap<OutputPointer, DataRequestState>,
+    /// Active data requests whose stage can advance, sharded by the epoch in which their current
+    /// stage closes. Updating the stages for an epoch only scans the shards up to that epoch,
+    /// instead of the whole pool.
+    #[serde(serialize_with = "crate::serializers::sorted::sets")]
+    pub data_requests_by_deadline: BTreeMap<Epoch, HashSet<OutputPointer>>,
     /// List of data requests that should be persisted into storage
     pub to_be_stored: Vec<(OutputPointer, DataRequestReport)>,
     /// Cache which maps commit_pointer to data_request_pointer
     /// and reveal_pointer to data_request_pointer
     pub dr_pointer_cache: HashMap<OutputPointer, OutputPointer>,
+    /// Reputation of the witnesses, updated when their data requests are tallied or expire
+    #[serde(default)]
+    pub reputation_engine: ReputationEngine,
 }
 
 impl DataRequestPool {
@@ -51,6 +67,23 @@ impl DataRequestPool {
             .map(|dr_state| dr_state.data_request.clone())
     }
 
+    /// Rebuild the cache which maps the commit and reveal pointers to their data request pointer,
+    /// e.g. after importing a snapshot without it
+    pub fn rebuild_pointer_cache(&mut self) {
+        self.dr_pointer_cache = self
+            .data_request_pool
+            .iter()
+            .flat_map(|(dr_pointer, dr_state)| {
+                dr_state
+                    .info
+                    .commits
+                    .iter()
+                    .chain(dr_state.info.reveals.iter())
+                    .map(move |pointer| (pointer.clone(), dr_pointer.clone()))
+            })
+            .collect();
+    }
+
     /// Insert a reveal transaction into the pool
     pub fn insert_reveal(&mut self, data_request_pointer: OutputPointer, reveal: Transaction) {
         self.waiting_for_reveal.insert(data_request_pointer, reveal);
@@ -128,8 +161,23 @@ impl DataRequestPool {
         }
     }
 
-    /// Add a commit to the corresponding data request
-    fn add_commit(&mut self, z: &Input, pointer: OutputPointer, block_hash: &Hash) {
+    /// Schedule a stage update of a data request at the end of `epoch`
+    fn schedule_stage_update(&mut self, epoch: Epoch, dr_pointer: OutputPointer) {
+        self.data_requests_by_deadline
+            .entry(epoch)
+            .or_insert_with(HashSet::new)
+            .insert(dr_pointer);
+    }
+
+    /// Add a commit to the corresponding data request, signed by `witness` if known
+    fn add_commit(
+        &mut self,
+        z: &Input,
+        pointer: OutputPointer,
+        witness: Option<PublicKeyHash>,
+        epoch: Epoch,
+        block_hash: &Hash,
+    ) {
         let transaction_id = pointer.transaction_id;
         // For a commit output, we need to get the corresponding data request input
         if let Input::DataRequest(dri) = z {
@@ -139,10 +187,17 @@ impl DataRequestPool {
             // This is not checked here, as it should have made the block invalid.
             if let Some(dr) = self.data_request_pool.get_mut(&dr_pointer) {
                 dr.add_commit(pointer.clone());
+                if let Some(pkh) = witness {
+                    dr.witnesses
+                        .insert(pointer.clone(), DataRequestWitness { pkh, reveal: None });
+                    self.reputation_engine.record_activity(pkh, epoch);
+                }
                 // Save the commit output pointer into a cache, to be able to
                 // retrieve data requests when we have the commit output pointer
                 // but no data request output pointer
-                self.dr_pointer_cache.insert(pointer, dr_pointer);
+                self.dr_pointer_cache.insert(pointer, dr_pointer.clone());
+                // The commit stage closes at the end of the epoch of the first commits
+                self.schedule_stage_update(epoch, dr_pointer);
             } else {
                 // This can happen when a data request was not stored into the dr_pool.
                 // For example, a very old data request that just now got a commitment.
@@ -176,16 +231,28 @@ impl DataRequestPool {
     }
 
     /// Add a reveal transaction
-    fn add_reveal(&mut self, z: &Input, pointer: OutputPointer, block_hash: &Hash) {
+    fn add_reveal(
+        &mut self,
+        z: &Input,
+        pointer: OutputPointer,
+        reveal: &RevealOutput,
+        epoch: Epoch,
+        block_hash: &Hash,
+    ) {
         let transaction_id = pointer.transaction_id;
         // For a reveal output, we need to get the corresponding commit input
         if let Input::Commit(commit_input) = z {
             let commit_pointer = commit_input.output_pointer();
-            if let Some(dr_pointer) = self.dr_pointer_cache.get(&commit_pointer) {
+            if let Some(dr_pointer) = self.dr_pointer_cache.get(&commit_pointer).cloned() {
                 if let Some(dr) = self.data_request_pool.get_mut(&dr_pointer) {
                     dr.add_reveal(pointer.clone());
+                    if let Some(witness) = dr.witnesses.get_mut(&commit_pointer) {
+                        witness.reveal = Some(reveal.reveal.clone());
+                    }
                     // Save the reveal output pointer into a cache
                     self.dr_pointer_cache.insert(pointer, dr_pointer.clone());
+                    // The reveal stage closes at the end of the epoch of the first reveals
+                    self.schedule_stage_update(epoch, dr_pointer);
                 } else {
                     panic!(
                         "Block contains a reveal for an unknown commitment:\n\
@@ -237,7 +304,14 @@ impl DataRequestPool {
 
     /// Add a tally transaction
     #[allow(clippy::needless_pass_by_value)]
-    fn add_tally(&mut self, reveal: &RevealInput, pointer: OutputPointer, block_hash: &Hash) {
+    fn add_tally(
+        &mut self,
+        reveal: &RevealInput,
+        tally: &TallyOutput,
+        pointer: OutputPointer,
+        epoch: Epoch,
+        block_hash: &Hash,
+    ) {
         let transaction_id = pointer.transaction_id;
         // For a tally output, we need to get the corresponding reveal input
         // Which is the previous transaction in the list
@@ -245,6 +319,11 @@ impl DataRequestPool {
         let reveal_pointer = reveal.output_pointer();
 
         if let Some(dr_pointer) = self.dr_pointer_cache.get(&reveal_pointer).cloned() {
+            let witnesses = self
+                .data_request_pool
+                .get_mut(&dr_pointer)
+                .map(|dr| std::mem::replace(&mut dr.witnesses, HashMap::new()))
+                .unwrap_or_default();
             if let Ok((_dr, dr_info)) = Self::resolve_data_request(
                 &mut self.data_request_pool,
                 &dr_pointer,
@@ -257,6 +336,7 @@ impl DataRequestPool {
                 for p in dr_info.commits.iter().chain(dr_info.reveals.iter()) {
                     self.dr_pointer_cache.remove(p);
                 }
+                self.update_reputation(epoch, witnesses.values(), Some(tally.result.as_slice()));
             } else {
                 panic!(
                     "Block contains a tally for an unknown data request:\n\
@@ -297,68 +377,134 @@ impl DataRequestPool {
         Ok((dr, dr_info))
     }
 
+    /// Update the reputation of the witnesses of a data request resolved in `epoch`. Those which
+    /// revealed the `result` of the tally are rewarded, and the rest are penalized. Without a
+    /// result, all of them are penalized.
+    fn update_reputation<'a, I>(&mut self, epoch: Epoch, witnesses: I, result: Option<&[u8]>)
+    where
+        I: IntoIterator<Item = &'a DataRequestWitness>,
+    {
+        let (truthful, penalized): (Vec<_>, Vec<_>) = witnesses.into_iter().partition(|witness| {
+            result.is_some() && witness.reveal.as_ref().map(Vec::as_slice) == result
+        });
+        let truthful: Vec<PublicKeyHash> =
+            truthful.into_iter().map(|witness| witness.pkh).collect();
+        let penalized: Vec<PublicKeyHash> =
+            penalized.into_iter().map(|witness| witness.pkh).collect();
+
+        self.reputation_engine
+            .apply_tally(epoch, &truthful, &penalized);
+    }
+
+    /// Removes a data request whose commitments have not been revealed in time. Its commitments
+    /// can not be revealed anymore, and their witnesses are penalized.
+    fn expire_data_request(&mut self, dr_pointer: &OutputPointer, epoch: Epoch) {
+        if let Some(dr_state) = self.data_request_pool.remove(dr_pointer) {
+            info!(
+                "Data request {} expired: none of its {} commitments was revealed",
+                dr_pointer,
+                dr_state.info.commits.len()
+            );
+            for commit_pointer in &dr_state.info.commits {
+                self.dr_pointer_cache.remove(commit_pointer);
+            }
+            self.update_reputation(epoch, dr_state.witnesses.values(), None);
+        }
+        self.waiting_for_reveal.remove(dr_pointer);
+    }
+
     /// Return the list of data requests in which this node has participated and are ready
     /// for reveal (the node should send a reveal transaction).
-    /// This function must be called after `add_data_requests_from_block`, in order to update
-    /// the stage of all the data requests.
-    pub fn update_data_request_stages(&mut self) -> Vec<Transaction> {
-        let waiting_for_reveal = &mut self.waiting_for_reveal;
-        let data_requests_by_epoch = &mut self.data_requests_by_epoch;
-        // Update the stage of the active data requests
-        self.data_request_pool
-            .iter_mut()
-            .filter_map(|(dr_pointer, dr_state)| {
-                // We can notify the user that a data request from "my_claims" is available
-                // for reveal.
-                if dr_state.update_stage() {
-                    if let DataRequestStage::REVEAL = dr_state.stage {
-                        // When a data request changes from commit stage to reveal stage, it should
-                        // be removed from the "data_requests_by_epoch" map, which stores the data
-                        // requests potentially available for commitment
-                        if let Some(hs) = data_requests_by_epoch.get_mut(&dr_state.epoch) {
-                            let present = hs.remove(dr_pointer);
-                            if hs.is_empty() {
-                                data_requests_by_epoch.remove(&dr_state.epoch);
-                            }
-                            if !present {
-                                // FIXME: This could be a warn! or a debug! instead of a panic
-                                panic!(
-                                    "Data request {:?} was not present in the \
-                                     data_requests_by_epoch map (epoch #{})",
-                                    dr_pointer, dr_state.epoch
-                                );
-                            }
+    /// This function must be called after processing the transactions of the block of `epoch`,
+    /// in order to update the stage of the data requests whose current stage has closed, and to
+    /// expire the data requests whose reveal stage has timed out.
+    pub fn update_data_request_stages(&mut self, epoch: Epoch) -> Vec<Transaction> {
+        let due_epochs: Vec<Epoch> = self
+            .data_requests_by_deadline
+            .range(..=epoch)
+            .map(|(epoch, _)| *epoch)
+            .collect();
+        let due_pointers: Vec<OutputPointer> = due_epochs
+            .iter()
+            .filter_map(|epoch| self.data_requests_by_deadline.remove(epoch))
+            .flatten()
+            .collect();
+
+        let mut reveals = vec![];
+        let mut expired = vec![];
+        let mut reveal_deadlines = vec![];
+        for dr_pointer in due_pointers {
+            // Data requests which have already been tallied are not in the pool anymore
+            let dr_state = match self.data_request_pool.get_mut(&dr_pointer) {
+                Some(dr_state) => dr_state,
+                None => continue,
+            };
+            if dr_state.reveals_timed_out(epoch) {
+                expired.push(dr_pointer);
+                continue;
+            }
+            // We can notify the user that a data request from "my_claims" is available
+            // for reveal.
+            if dr_state.update_stage() {
+                if let DataRequestStage::REVEAL = dr_state.stage {
+                    let reveal_deadline = epoch + REVEAL_TIMEOUT_EPOCHS;
+                    dr_state.reveal_deadline = Some(reveal_deadline);
+                    reveal_deadlines.push((reveal_deadline, dr_pointer.clone()));
+
+                    // When a data request changes from commit stage to reveal stage, it should
+                    // be removed from the "data_requests_by_epoch" map, which stores the data
+                    // requests potentially available for commitment
+                    if let Some(hs) = self.data_requests_by_epoch.get_mut(&dr_state.epoch) {
+                        let present = hs.remove(&dr_pointer);
+                        if hs.is_empty() {
+                            self.data_requests_by_epoch.remove(&dr_state.epoch);
                         }
+                        if !present {
+                            // FIXME: This could be a warn! or a debug! instead of a panic
+                            panic!(
+                                "Data request {:?} was not present in the \
+                                 data_requests_by_epoch map (epoch #{})",
+                                dr_pointer, dr_state.epoch
+                            );
+                        }
+                    }
 
-                        if let Some(transaction) = waiting_for_reveal.remove(dr_pointer) {
-                            // We submitted a commit for this data request!
-                            // But has it been included into the block?
-                            let commit_pointer = match &transaction.body.inputs[0] {
-                                Input::Commit(commit) => commit.output_pointer(),
-                                _ => panic!("Invalid format for reveal transaction"),
-                            };
-                            if dr_state.info.commits.contains(&commit_pointer) {
-                                // We found our commit, return the reveal transaction to be sent
-                                return Some(transaction);
-                            } else {
-                                info!(
-                                    "The sent commit transaction has not been \
-                                     selected to be part of the data request {:?}",
-                                    dr_pointer
-                                );
-                                debug!(
-                                    "Commit {:?} removed from the list of commits waiting \
-                                     for reveal",
-                                    commit_pointer
-                                );
-                            }
+                    if let Some(transaction) = self.waiting_for_reveal.remove(&dr_pointer) {
+                        // We submitted a commit for this data request!
+                        // But has it been included into the block?
+                        let commit_pointer = match &transaction.body.inputs[0] {
+                            Input::Commit(commit) => commit.output_pointer(),
+                            _ => panic!("Invalid format for reveal transaction"),
+                        };
+                        if dr_state.info.commits.contains(&commit_pointer) {
+                            // We found our commit, return the reveal transaction to be sent
+                            reveals.push(transaction);
+                        } else {
+                            info!(
+                                "The sent commit transaction has not been \
+                                 selected to be part of the data request {:?}",
+                                dr_pointer
+                            );
+                            debug!(
+                                "Commit {:?} removed from the list of commits waiting \
+                                 for reveal",
+                                commit_pointer
+                            );
                         }
                     }
                 }
+            }
+        }
 
-                None
-            })
-            .collect()
+        for dr_pointer in expired {
+            self.expire_data_request(&dr_pointer, epoch);
+        }
+        self.reputation_engine.expire(epoch);
+        for (reveal_deadline, dr_pointer) in reveal_deadlines {
+            self.schedule_stage_update(reveal_deadline, dr_pointer);
+        }
+
+        reveals
     }
 
     /// Process a transaction from a block and update the data request pool accordingly:
@@ -390,10 +536,14 @@ impl DataRequestPool {
                     self.add_data_request(dr_epoch, pointer.clone(), dr.clone());
                 }
                 Output::Commit(_commit) => {
-                    self.add_commit(z, pointer, block_hash);
+                    let witness = t
+                        .signatures
+                        .get(i)
+                        .map(|signature| PublicKeyHash::from_public_key(&signature.public_key));
+                    self.add_commit(z, pointer, witness, epoch, block_hash);
                 }
-                Output::Reveal(_reveal) => {
-                    self.add_reveal(z, pointer, block_hash);
+                Output::Reveal(reveal) => {
+                    self.add_reveal(z, pointer, reveal, epoch, block_hash);
                 }
                 Output::Tally(tally) => {
                     // It is impossible to have a tally in this iterator, because we are
@@ -428,7 +578,7 @@ impl DataRequestPool {
                 &t.body.inputs[tally_index - 1],
                 &t.body.outputs[tally_index],
             ) {
-                (Input::Reveal(reveal), Output::Tally(_tally)) => {
+                (Input::Reveal(reveal), Output::Tally(tally)) => {
                     // Assume that all the reveal inputs point to the same data request
                     // (as that should have been already validated)
                     // And assume that the tally transaction contains as many reveal inputs
@@ -437,7 +587,7 @@ impl DataRequestPool {
                         transaction_id,
                         output_index: tally_index as u32,
                     };
-                    self.add_tally(reveal, pointer, block_hash);
+                    self.add_tally(reveal, tally, pointer, epoch, block_hash);
                 }
                 (_, Output::Tally(_)) => {
                     // This panic implies a logic error in the block validation
@@ -462,7 +612,6 @@ impl DataRequestPool {
     }
 
     /// Get the detailed state of a data request.
-    #[allow(unused)]
     pub fn data_request_state(
         &self,
         data_request_pointer: &OutputPointer,
@@ -470,45 +619,239 @@ impl DataRequestPool {
         self.data_request_pool.get(data_request_pointer)
     }
 
+    /// Get the active data requests created by `requester`, oldest first
+    pub fn data_requests_by_requester(
+        &self,
+        requester: &PublicKeyHash,
+    ) -> Vec<(&OutputPointer, &DataRequestState)> {
+        let mut data_requests: Vec<_> = self
+            .data_request_pool
+            .iter()
+            .filter(|(_, dr_state)| dr_state.data_request.pkh == *requester)
+            .collect();
+        data_requests.sort_by_key(|(dr_pointer, dr_state)| {
+            (
+                dr_state.epoch,
+                dr_pointer.transaction_id,
+                dr_pointer.output_index,
+            )
+        });
+
+        data_requests
+    }
+
     /// Get the data request info of the finished data requests, to be persisted to the storage
     #[allow(unused)]
     pub fn finished_data_requests(&mut self) -> Vec<(OutputPointer, DataRequestReport)> {
         std::mem::replace(&mut self.to_be_stored, vec![])
     }
+
+    /// Get the changes which turn this pool into `other`
+    pub fn diff(&self, other: &DataRequestPool) -> DataRequestPoolDiff {
+        DataRequestPoolDiff {
+            waiting_for_reveal: self.waiting_for_reveal.changes(&other.waiting_for_reveal),
+            data_requests_by_epoch: self
+                .data_requests_by_epoch
+                .changes(&other.data_requests_by_epoch),
+            data_request_pool: self.data_request_pool.changes(&other.data_request_pool),
+            data_requests_by_deadline: self
+                .data_requests_by_deadline
+                .changes(&other.data_requests_by_deadline),
+            to_be_stored: if self.to_be_stored == other.to_be_stored {
+                None
+            } else {
+                Some(other.to_be_stored.clone())
+            },
+            dr_pointer_cache: self.dr_pointer_cache.changes(&other.dr_pointer_cache),
+            reputation_engine: self.reputation_engine.diff(&other.reputation_engine),
+        }
+    }
+
+    /// Get the changes which turn the result of applying `diff` to this pool back into this pool
+    pub fn inverse_diff(&self, diff: &DataRequestPoolDiff) -> DataRequestPoolDiff {
+        DataRequestPoolDiff {
+            waiting_for_reveal: self
+                .waiting_for_reveal
+                .inverse_changes(&diff.waiting_for_reveal),
+            data_requests_by_epoch: self
+                .data_requests_by_epoch
+                .inverse_changes(&diff.data_requests_by_epoch),
+            data_request_pool: self
+                .data_request_pool
+                .inverse_changes(&diff.data_request_pool),
+            data_requests_by_deadline: self
+                .data_requests_by_deadline
+                .inverse_changes(&diff.data_requests_by_deadline),
+            to_be_stored: diff
+                .to_be_stored
+                .as_ref()
+                .map(|_| self.to_be_stored.clone()),
+            dr_pointer_cache: self
+                .dr_pointer_cache
+                .inverse_changes(&diff.dr_pointer_cache),
+            reputation_engine: self.reputation_engine.inverse_diff(&diff.reputation_engine),
+        }
+    }
+
+    /// Apply the changes of a [`DataRequestPoolDiff`](DataRequestPoolDiff)
+    pub fn apply_diff(&mut self, diff: DataRequestPoolDiff) {
+        self.waiting_for_reveal
+            .apply_changes(diff.waiting_for_reveal);
+        self.data_requests_by_epoch
+            .apply_changes(diff.data_requests_by_epoch);
+        self.data_request_pool.apply_changes(diff.data_request_pool);
+        self.data_requests_by_deadline
+            .apply_changes(diff.data_requests_by_deadline);
+        if let Some(to_be_stored) = diff.to_be_stored {
+            self.to_be_stored = to_be_stored;
+        }
+        self.dr_pointer_cache.apply_changes(diff.dr_pointer_cache);
+        self.reputation_engine.apply_diff(diff.reputation_engine);
+    }
+}
+
+/// Changes between two states of a [`DataRequestPool`](DataRequestPool), such as the ones made by
+/// a block or the ones that undo them. Only the entries which differ are kept, so they are much
+/// smaller than the pool itself.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DataRequestPoolDiff {
+    /// Changes of the reveals waiting for the reveal stage
+    pub waiting_for_reveal: MapChanges<OutputPointer, Transaction>,
+    /// Changes of the data requests by epoch
+    pub data_requests_by_epoch: MapChanges<Epoch, HashSet<OutputPointer>>,
+    /// Changes of the active data requests
+    pub data_request_pool: MapChanges<OutputPointer, DataRequestState>,
+    /// Changes of the data requests by the epoch their current stage closes
+    pub data_requests_by_deadline: MapChanges<Epoch, HashSet<OutputPointer>>,
+    /// Data requests to be persisted, if they changed
+    pub to_be_stored: Option<Vec<(OutputPointer, DataRequestReport)>>,
+    /// Changes of the cache of commit and reveal pointers
+    pub dr_pointer_cache: MapChanges<OutputPointer, OutputPointer>,
+    /// Changes of the reputation of the witnesses
+    pub reputation_engine: ReputationEngineDiff,
 }
 
-/// Function to calculate the commit reward
-pub fn calculate_commit_reward(dr_output: &DataRequestOutput) -> u64 {
-    dr_output.value / u64::from(dr_output.witnesses) - dr_output.commit_fee
+/// Entries which differ between two maps: the new value of the inserted or modified entries, and
+/// `None` for the removed ones
+pub type MapChanges<K, V> = Vec<(K, Option<V>)>;
+
+/// Maps whose [`MapChanges`](MapChanges) can be computed and applied
+pub(crate) trait ChangesMap<K, V> {
+    /// Get the changes which turn this map into `other`
+    fn changes(&self, other: &Self) -> MapChanges<K, V>;
+    /// Get the changes which turn the result of applying `changes` to this map back into this
+    /// map
+    fn inverse_changes(&self, changes: &[(K, Option<V>)]) -> MapChanges<K, V>;
+    /// Apply the changes got with [`changes`](ChangesMap::changes)
+    fn apply_changes(&mut self, changes: MapChanges<K, V>);
+}
+
+macro_rules! impl_changes_map {
+    ($map:ident, $($key_bound:path),+) => {
+        impl<K, V> ChangesMap<K, V> for $map<K, V>
+        where
+            K: Clone $(+ $key_bound)+,
+            V: Clone + PartialEq,
+        {
+            fn changes(&self, other: &Self) -> MapChanges<K, V> {
+                let changed = other
+                    .iter()
+                    .filter(|(key, value)| self.get(key) != Some(value))
+                    .map(|(key, value)| (key.clone(), Some(value.clone())));
+                let removed = self
+                    .keys()
+                    .filter(|key| !other.contains_key(key))
+                    .map(|key| (key.clone(), None));
+
+                changed.chain(removed).collect()
+            }
+
+            fn inverse_changes(&self, changes: &[(K, Option<V>)]) -> MapChanges<K, V> {
+                changes
+                    .iter()
+                    .map(|(key, _)| (key.clone(), self.get(key).cloned()))
+                    .collect()
+            }
+
+            fn apply_changes(&mut self, changes: MapChanges<K, V>) {
+                for (key, value) in changes {
+                    match value {
+                        Some(value) => {
+                            self.insert(key, value);
+                        }
+                        None => {
+                            self.remove(&key);
+                        }
+                    }
+                }
+            }
+        }
+    };
+}
+
+impl_changes_map!(HashMap, Eq, StdHash);
+impl_changes_map!(BTreeMap, Ord);
+
+/// Function to calculate the commit reward.
+///
+/// Data requests whose fees exceed their value do not pass validation, so the rewards of the
+/// following functions saturate at zero instead of failing.
+pub fn calculate_commit_reward(dr_output: &DataRequestOutput) -> Wit {
+    dr_output
+        .value
+        .checked_div(u64::from(dr_output.witnesses))
+        .unwrap_or_default()
+        .saturating_sub(dr_output.commit_fee)
 }
 
 /// Function to calculate the reveal reward
-pub fn calculate_reveal_reward(dr_output: &DataRequestOutput) -> u64 {
-    calculate_commit_reward(dr_output) - dr_output.reveal_fee
+pub fn calculate_reveal_reward(dr_output: &DataRequestOutput) -> Wit {
+    calculate_commit_reward(dr_output).saturating_sub(dr_output.reveal_fee)
 }
 
 /// Function to calculate the value transfer reward
-pub fn calculate_dr_vt_reward(dr_output: &DataRequestOutput) -> u64 {
-    calculate_reveal_reward(dr_output) - dr_output.tally_fee
+pub fn calculate_dr_vt_reward(dr_output: &DataRequestOutput) -> Wit {
+    calculate_reveal_reward(dr_output).saturating_sub(dr_output.tally_fee)
 }
 
 /// Function to calculate the tally change
-pub fn calculate_tally_change(dr_output: &DataRequestOutput, n_reveals: u64) -> u64 {
-    calculate_reveal_reward(dr_output) * (u64::from(dr_output.witnesses) - n_reveals)
+pub fn calculate_tally_change(dr_output: &DataRequestOutput, n_reveals: u64) -> Wit {
+    let missing_reveals = u64::from(dr_output.witnesses).saturating_sub(n_reveals);
+
+    // The reveal reward is at most `value / witnesses`, so this is at most the value of the
+    // data request
+    calculate_reveal_reward(dr_output)
+        .checked_mul(missing_reveals)
+        .expect("The tally change cannot exceed the value of the data request")
+}
+
+/// Function to calculate the proof of eligibility of the identity `pkh` to commit to the data
+/// request of `dr_pointer`: the hash of the pointer and the public key hash. Only the owner of the
+/// key can sign a commitment with it, so it is bound to the signer of the commit transaction.
+pub fn calculate_data_request_poe(dr_pointer: &OutputPointer, pkh: &PublicKeyHash) -> [u8; 32] {
+    let Hash::SHA256(transaction_id) = dr_pointer.transaction_id;
+    let mut bytes = Vec::with_capacity(32 + 4 + 20);
+    bytes.extend_from_slice(&transaction_id);
+    bytes.extend_from_slice(&dr_pointer.output_index.to_be_bytes());
+    bytes.extend_from_slice(pkh.as_bytes());
+
+    let Sha256(poe) = calculate_sha256(&bytes);
+
+    poe
 }
 
-/// Create data request commitment
+/// Create data request commitment of the identity `pkh`, which must sign it
 pub fn create_commit_body(
     dr_output_pointer: &OutputPointer,
     dr_output: &DataRequestOutput,
     reveal: Vec<u8>,
+    pkh: &PublicKeyHash,
 ) -> TransactionBody {
     // Create input
     let dr_input = Input::DataRequest(DataRequestInput {
         transaction_id: dr_output_pointer.transaction_id,
         output_index: dr_output_pointer.output_index,
-        // TODO: create a proper poe
-        poe: [0; 32],
+        poe: calculate_data_request_poe(dr_output_pointer, pkh),
     });
 
     // Calculate reveal_value
@@ -545,7 +888,7 @@ pub fn create_reveal_body(
     let reveal_output = Output::Reveal(RevealOutput {
         reveal,
         // TODO: use a proper pkh
-        pkh: [0; 20],
+        pkh: PublicKeyHash::default(),
         value: reveal_value,
     });
 
@@ -601,7 +944,12 @@ pub fn create_tally_body(
 
 #[cfg(test)]
 mod tests {
-    use crate::{chain::*, data_request::DataRequestPool};
+    use crate::{
+        chain::*,
+        data_request::{DataRequestPool, DataRequestPoolDiff, REVEAL_TIMEOUT_EPOCHS},
+        reputation::REPUTATION_ISSUANCE,
+        wit::Wit,
+    };
 
     fn empty_data_request() -> DataRequestOutput {
         let data_request = RADRequest {
@@ -614,37 +962,37 @@ mod tests {
 
         DataRequestOutput {
             data_request,
-            value: 0,
+            value: Wit::default(),
             witnesses: 0,
             backup_witnesses: 0,
-            commit_fee: 0,
-            reveal_fee: 0,
-            tally_fee: 0,
+            commit_fee: Wit::default(),
+            reveal_fee: Wit::default(),
+            tally_fee: Wit::default(),
             time_lock: 0,
-            pkh: [45; 20],
+            pkh: PublicKeyHash::from_bytes([45; 20]),
         }
     }
 
     fn empty_commit_output() -> CommitOutput {
         CommitOutput {
             commitment: Hash::SHA256([50; 32]),
-            value: 4,
+            value: Wit::from_nanowits(4),
         }
     }
 
     fn empty_reveal_output() -> RevealOutput {
         RevealOutput {
             reveal: vec![],
-            pkh: [78; 20],
-            value: 5,
+            pkh: PublicKeyHash::from_bytes([78; 20]),
+            value: Wit::from_nanowits(5),
         }
     }
 
     fn empty_tally_output() -> TallyOutput {
         TallyOutput {
             result: vec![],
-            pkh: [23; 20],
-            value: 6,
+            pkh: PublicKeyHash::from_bytes([23; 20]),
+            value: Wit::from_nanowits(6),
         }
     }
 
@@ -657,8 +1005,8 @@ mod tests {
 
     fn empty_value_transfer_output() -> ValueTransferOutput {
         ValueTransferOutput {
-            pkh: [25; 20],
-            value: 7,
+            pkh: PublicKeyHash::from_bytes([25; 20]),
+            value: Wit::from_nanowits(7),
         }
     }
 
@@ -706,7 +1054,47 @@ mod tests {
         assert!(p.to_be_stored.is_empty());
         assert!(p.dr_pointer_cache.is_empty());
 
-        assert!(p.update_data_request_stages().is_empty());
+        assert!(p.update_data_request_stages(epoch).is_empty());
+    }
+
+    #[test]
+    fn data_requests_by_requester() {
+        let fake_block_hash = Hash::SHA256([1; 32]);
+        let requester = PublicKeyHash::from_bytes([45; 20]);
+        let other_requester = PublicKeyHash::from_bytes([46; 20]);
+
+        let mut p = DataRequestPool::default();
+        let dr_pointers: Vec<OutputPointer> =
+            [(2, requester), (1, other_requester), (0, requester)]
+                .iter()
+                .map(|(epoch, pkh)| {
+                    let transaction = fake_transaction_zip(vec![(
+                        Input::ValueTransfer(empty_value_transfer_input()),
+                        Output::DataRequest(DataRequestOutput {
+                            pkh: *pkh,
+                            time_lock: *epoch,
+                            ..empty_data_request()
+                        }),
+                    )]);
+                    p.process_transaction(&transaction, *epoch as Epoch, &fake_block_hash);
+
+                    OutputPointer {
+                        transaction_id: transaction.hash(),
+                        output_index: 0,
+                    }
+                })
+                .collect();
+
+        let data_requests: Vec<&OutputPointer> = p
+            .data_requests_by_requester(&requester)
+            .into_iter()
+            .map(|(dr_pointer, _)| dr_pointer)
+            .collect();
+        assert_eq!(data_requests, vec![&dr_pointers[2], &dr_pointers[0]]);
+        assert_eq!(p.data_requests_by_requester(&other_requester).len(), 1);
+        assert!(p
+            .data_requests_by_requester(&PublicKeyHash::default())
+            .is_empty());
     }
 
     #[test]
@@ -733,7 +1121,7 @@ mod tests {
             DataRequestStage::COMMIT
         );
         // Since there are no commitments to the data request, it should stay in commit stage
-        assert!(p.update_data_request_stages().is_empty());
+        assert!(p.update_data_request_stages(epoch).is_empty());
 
         assert_eq!(
             p.data_request_pool[&dr_pointer].stage,
@@ -759,6 +1147,12 @@ mod tests {
         // Now we can get the data request pointer from the commit output pointer
         assert_eq!(p.dr_pointer_cache.get(&commit_pointer), Some(&dr_pointer));
 
+        // The cache can be rebuilt from the data requests
+        let mut rebuilt = p.clone();
+        rebuilt.dr_pointer_cache.clear();
+        rebuilt.rebuild_pointer_cache();
+        assert_eq!(rebuilt.dr_pointer_cache, p.dr_pointer_cache);
+
         // And we can also get all the commit pointers from the data request
         assert_eq!(
             p.data_request_pool[&dr_pointer]
@@ -778,7 +1172,7 @@ mod tests {
         assert!(p.data_requests_by_epoch[&epoch].contains(&dr_pointer));
 
         // Update stages
-        assert!(p.update_data_request_stages().is_empty());
+        assert!(p.update_data_request_stages(epoch + 1).is_empty());
 
         // Now in reveal stage
         assert_eq!(
@@ -816,7 +1210,7 @@ mod tests {
             DataRequestStage::COMMIT
         );
         // Since there are no commitments to the data request, it should stay in commit stage
-        assert!(p.update_data_request_stages().is_empty());
+        assert!(p.update_data_request_stages(epoch).is_empty());
 
         assert_eq!(
             p.data_request_pool[&dr_pointer].stage,
@@ -850,7 +1244,7 @@ mod tests {
         );
 
         // Update stages
-        assert!(p.update_data_request_stages().is_empty());
+        assert!(p.update_data_request_stages(epoch + 1).is_empty());
 
         // Now in reveal stage
         assert_eq!(
@@ -904,13 +1298,69 @@ mod tests {
         );
 
         // Update stages
-        assert!(p.update_data_request_stages().is_empty());
+        assert!(p.update_data_request_stages(epoch + 2).is_empty());
 
         // Now in tally stage
         assert_eq!(
             p.data_request_pool[&dr_pointer].stage,
             DataRequestStage::TALLY
         );
+
+        // The reveal timeout does not affect data requests with reveals
+        p.update_data_request_stages(epoch + 1 + REVEAL_TIMEOUT_EPOCHS);
+        assert_eq!(
+            p.data_request_pool[&dr_pointer].stage,
+            DataRequestStage::TALLY
+        );
+    }
+
+    #[test]
+    fn reveal_stage_times_out() {
+        let fake_block_hash = Hash::SHA256([1; 32]);
+        let epoch = 0;
+        let transaction = fake_transaction_zip(vec![(
+            Input::ValueTransfer(empty_value_transfer_input()),
+            Output::DataRequest(empty_data_request()),
+        )]);
+        let dr_pointer = OutputPointer {
+            transaction_id: transaction.hash(),
+            output_index: 0,
+        };
+
+        let mut p = DataRequestPool::default();
+        p.process_transaction(&transaction, epoch, &fake_block_hash);
+
+        let commit_transaction = fake_transaction_zip(vec![(
+            Input::DataRequest(DataRequestInput {
+                transaction_id: dr_pointer.transaction_id,
+                output_index: dr_pointer.output_index,
+                poe: [77; 32],
+            }),
+            Output::Commit(empty_commit_output()),
+        )]);
+        let commit_pointer = OutputPointer {
+            transaction_id: commit_transaction.hash(),
+            output_index: 0,
+        };
+        p.process_transaction(&commit_transaction, epoch + 1, &fake_block_hash);
+        p.update_data_request_stages(epoch + 1);
+        assert_eq!(
+            p.data_request_pool[&dr_pointer].reveal_deadline,
+            Some(epoch + 1 + REVEAL_TIMEOUT_EPOCHS)
+        );
+
+        // Without reveals, the data request waits in the reveal stage until the deadline
+        p.update_data_request_stages(epoch + REVEAL_TIMEOUT_EPOCHS);
+        assert_eq!(
+            p.data_request_pool[&dr_pointer].stage,
+            DataRequestStage::REVEAL
+        );
+
+        // And then it expires, so its commitment can not be revealed anymore
+        p.update_data_request_stages(epoch + 1 + REVEAL_TIMEOUT_EPOCHS);
+        assert!(!p.data_request_pool.contains_key(&dr_pointer));
+        assert_eq!(p.dr_pointer_cache.get(&commit_pointer), None);
+        assert!(p.data_requests_by_deadline.is_empty());
     }
 
     #[test]
@@ -935,7 +1385,7 @@ mod tests {
             DataRequestStage::COMMIT
         );
         // Since there are no commitments to the data request, it should stay in commit stage
-        assert!(p.update_data_request_stages().is_empty());
+        assert!(p.update_data_request_stages(epoch).is_empty());
 
         assert_eq!(
             p.data_request_pool[&dr_pointer].stage,
@@ -969,7 +1419,7 @@ mod tests {
         );
 
         // Update stages
-        assert!(p.update_data_request_stages().is_empty());
+        assert!(p.update_data_request_stages(epoch + 1).is_empty());
 
         // Now in reveal stage
         assert_eq!(
@@ -1001,7 +1451,7 @@ mod tests {
         );
 
         // Update stages
-        assert!(p.update_data_request_stages().is_empty());
+        assert!(p.update_data_request_stages(epoch + 2).is_empty());
 
         // Now in tally stage
         assert_eq!(
@@ -1058,12 +1508,149 @@ mod tests {
         assert_eq!(p.data_request_pool.get(&dr_pointer), None);
 
         // Update stages
-        assert!(p.update_data_request_stages().is_empty());
+        assert!(p.update_data_request_stages(epoch + 2).is_empty());
 
         assert_eq!(p.to_be_stored.len(), 1);
         assert_eq!(p.to_be_stored[0].0, dr_pointer);
     }
 
+    #[test]
+    fn tally_updates_reputation() {
+        let fake_block_hash = Hash::SHA256([1; 32]);
+        let epoch = 0;
+        let transaction = fake_transaction_zip(vec![(
+            Input::ValueTransfer(empty_value_transfer_input()),
+            Output::DataRequest(empty_data_request()),
+        )]);
+        let dr_pointer = OutputPointer {
+            transaction_id: transaction.hash(),
+            output_index: 0,
+        };
+
+        let mut p = DataRequestPool::default();
+        p.process_transaction(&transaction, epoch, &fake_block_hash);
+
+        // Three witnesses commit to the data request
+        let witnesses: Vec<(PublicKeyHash, OutputPointer)> = (1..=3)
+            .map(|i| {
+                let mut commit_transaction = fake_transaction_zip(vec![(
+                    Input::DataRequest(DataRequestInput {
+                        transaction_id: dr_pointer.transaction_id,
+                        output_index: dr_pointer.output_index,
+                        poe: [i; 32],
+                    }),
+                    Output::Commit(empty_commit_output()),
+                )]);
+                commit_transaction.signatures[0].public_key.bytes = [i; 32];
+                p.process_transaction(&commit_transaction, epoch + 1, &fake_block_hash);
+
+                (
+                    PublicKeyHash::from_public_key(&commit_transaction.signatures[0].public_key),
+                    OutputPointer {
+                        transaction_id: commit_transaction.hash(),
+                        output_index: 0,
+                    },
+                )
+            })
+            .collect();
+        p.update_data_request_stages(epoch + 1);
+
+        // The first one reveals the result of the tally, the second one lies and the third one
+        // does not reveal
+        let reveal_pointers: Vec<OutputPointer> = witnesses[..2]
+            .iter()
+            .zip(vec![vec![], vec![1]])
+            .map(|((_, commit_pointer), value)| {
+                let reveal_transaction = fake_transaction_zip(vec![(
+                    Input::Commit(CommitInput {
+                        transaction_id: commit_pointer.transaction_id,
+                        output_index: commit_pointer.output_index,
+                        nonce: 444,
+                    }),
+                    Output::Reveal(RevealOutput {
+                        reveal: value,
+                        ..empty_reveal_output()
+                    }),
+                )]);
+                p.process_transaction(&reveal_transaction, epoch + 2, &fake_block_hash);
+
+                OutputPointer {
+                    transaction_id: reveal_transaction.hash(),
+                    output_index: 0,
+                }
+            })
+            .collect();
+        p.update_data_request_stages(epoch + 2);
+
+        for (pkh, _) in &witnesses {
+            assert!(p.reputation_engine.is_active(pkh));
+        }
+
+        let mut tally_transaction = fake_transaction_zip(vec![(
+            Input::Reveal(RevealInput {
+                transaction_id: reveal_pointers[0].transaction_id,
+                output_index: reveal_pointers[0].output_index,
+            }),
+            Output::ValueTransfer(empty_value_transfer_output()),
+        )]);
+        tally_transaction
+            .body
+            .outputs
+            .push(Output::Tally(empty_tally_output()));
+        p.process_transaction(&tally_transaction, epoch + 3, &fake_block_hash);
+
+        assert_eq!(
+            p.reputation_engine.reputation(&witnesses[0].0),
+            REPUTATION_ISSUANCE
+        );
+        assert_eq!(p.reputation_engine.reputation(&witnesses[1].0), 0);
+        assert_eq!(p.reputation_engine.reputation(&witnesses[2].0), 0);
+        assert_eq!(
+            p.reputation_engine.total_active_reputation(),
+            REPUTATION_ISSUANCE
+        );
+    }
+
+    #[test]
+    fn diff_between_pools() {
+        let fake_block_hash = Hash::SHA256([1; 32]);
+        let epoch = 0;
+        let transaction = fake_transaction_zip(vec![(
+            Input::ValueTransfer(empty_value_transfer_input()),
+            Output::DataRequest(empty_data_request()),
+        )]);
+        let commit_transaction = fake_transaction_zip(vec![(
+            Input::DataRequest(DataRequestInput {
+                transaction_id: transaction.hash(),
+                output_index: 0,
+                poe: [1; 32],
+            }),
+            Output::Commit(empty_commit_output()),
+        )]);
+
+        let mut pools = vec![DataRequestPool::default()];
+        let mut p = DataRequestPool::default();
+        p.process_transaction(&transaction, epoch, &fake_block_hash);
+        pools.push(p.clone());
+        p.process_transaction(&commit_transaction, epoch + 1, &fake_block_hash);
+        pools.push(p.clone());
+        p.update_data_request_stages(epoch + 1);
+        pools.push(p);
+
+        for from in &pools {
+            assert_eq!(from.diff(from), DataRequestPoolDiff::default());
+            for to in &pools {
+                let diff = from.diff(to);
+                let inverse = from.inverse_diff(&diff);
+                let mut pool = from.clone();
+                pool.apply_diff(diff);
+                assert_eq!(&pool, to);
+                pool.apply_diff(inverse);
+                assert_eq!(&pool, from);
+            }
+        }
+    }
+
     #[test]
     fn my_claims() {
         // Test the `add_own_reveal` function
@@ -1087,7 +1674,7 @@ mod tests {
             DataRequestStage::COMMIT
         );
         // Since there are no commitments to the data request, it should stay in commit stage
-        assert!(p.update_data_request_stages().is_empty());
+        assert!(p.update_data_request_stages(epoch).is_empty());
 
         assert_eq!(
             p.data_request_pool[&dr_pointer].stage,
@@ -1146,7 +1733,7 @@ mod tests {
         );
 
         // Update stages. This will return our reveal transaction
-        let my_reveals = p.update_data_request_stages();
+        let my_reveals = p.update_data_request_stages(epoch + 1);
         assert_eq!(my_reveals.len(), 1);
         let my_reveal = &my_reveals[0];
         assert_eq!(my_reveal, &reveal_transaction);
@@ -1190,7 +1777,7 @@ mod tests {
         );
 
         // Update stages
-        assert!(p.update_data_request_stages().is_empty());
+        assert!(p.update_data_request_stages(epoch + 2).is_empty());
 
         // Now in tally stage
         assert_eq!(
@@ -1222,14 +1809,14 @@ mod tests {
             DataRequestStage::COMMIT
         );
         // Since there are no commitments to the data request, it should stay in commit stage
-        assert!(p.update_data_request_stages().is_empty());
+        assert!(p.update_data_request_stages(epoch).is_empty());
 
         assert_eq!(
             p.data_request_pool[&dr_pointer].stage,
             DataRequestStage::COMMIT
         );
 
-        assert!(p.update_data_request_stages().is_empty());
+        assert!(p.update_data_request_stages(epoch).is_empty());
 
         assert_eq!(
             p.data_request_pool[&dr_pointer].stage,
@@ -1263,7 +1850,7 @@ mod tests {
         );
 
         // Update stages
-        assert!(p.update_data_request_stages().is_empty());
+        assert!(p.update_data_request_stages(epoch + 1).is_empty());
 
         // Now in reveal stage
         assert_eq!(
@@ -1272,7 +1859,7 @@ mod tests {
         );
 
         // Update stages
-        assert!(p.update_data_request_stages().is_empty());
+        assert!(p.update_data_request_stages(epoch + 1).is_empty());
 
         // Now in reveal stage
         assert_eq!(
@@ -1304,7 +1891,7 @@ mod tests {
         );
 
         // Update stages
-        assert!(p.update_data_request_stages().is_empty());
+        assert!(p.update_data_request_stages(epoch + 2).is_empty());
 
         // Now in tally stage
         assert_eq!(
@@ -1313,7 +1900,7 @@ mod tests {
         );
 
         // Update stages
-        assert!(p.update_data_request_stages().is_empty());
+        assert!(p.update_data_request_stages(epoch + 2).is_empty());
 
         // Now in tally stage
         assert_eq!(
@@ -1370,14 +1957,133 @@ mod tests {
         assert_eq!(p.data_request_pool.get(&dr_pointer), None);
 
         // Update stages
-        assert!(p.update_data_request_stages().is_empty());
+        assert!(p.update_data_request_stages(epoch + 2).is_empty());
 
         assert_eq!(p.to_be_stored.len(), 1);
         assert_eq!(p.to_be_stored[0].0, dr_pointer);
 
-        assert!(p.update_data_request_stages().is_empty());
+        assert!(p.update_data_request_stages(epoch + 2).is_empty());
 
         assert_eq!(p.to_be_stored.len(), 1);
         assert_eq!(p.to_be_stored[0].0, dr_pointer);
     }
+
+    #[test]
+    fn stage_updates_sharded_by_deadline() {
+        let fake_block_hash = Hash::SHA256([1; 32]);
+        let epoch = 3;
+        let transaction = fake_transaction_zip(vec![(
+            Input::ValueTransfer(empty_value_transfer_input()),
+            Output::DataRequest(empty_data_request()),
+        )]);
+        let dr_pointer = OutputPointer {
+            transaction_id: transaction.hash(),
+            output_index: 0,
+        };
+
+        let mut p = DataRequestPool::default();
+        p.process_transaction(&transaction, epoch, &fake_block_hash);
+
+        // Without commitments the stage cannot advance, so it is not scheduled
+        assert!(p.data_requests_by_deadline.is_empty());
+
+        let commit_transaction = fake_transaction_zip(vec![(
+            Input::DataRequest(DataRequestInput {
+                transaction_id: dr_pointer.transaction_id,
+                output_index: dr_pointer.output_index,
+                poe: [77; 32],
+            }),
+            Output::Commit(empty_commit_output()),
+        )]);
+        p.process_transaction(&commit_transaction, epoch + 2, &fake_block_hash);
+
+        assert!(p.data_requests_by_deadline[&(epoch + 2)].contains(&dr_pointer));
+
+        // The commit stage has not closed yet in previous epochs
+        assert!(p.update_data_request_stages(epoch + 1).is_empty());
+        assert_eq!(
+            p.data_request_pool[&dr_pointer].stage,
+            DataRequestStage::COMMIT
+        );
+        assert!(p.data_requests_by_deadline.contains_key(&(epoch + 2)));
+
+        assert!(p.update_data_request_stages(epoch + 2).is_empty());
+        assert_eq!(
+            p.data_request_pool[&dr_pointer].stage,
+            DataRequestStage::REVEAL
+        );
+
+        // The commit stage shard is gone, and the data request is scheduled again for the end of
+        // its reveal stage
+        let reveal_deadline = epoch + 2 + REVEAL_TIMEOUT_EPOCHS;
+        assert_eq!(
+            p.data_requests_by_deadline.keys().collect::<Vec<_>>(),
+            vec![&reveal_deadline]
+        );
+        assert!(p.data_requests_by_deadline[&reveal_deadline].contains(&dr_pointer));
+
+        assert!(p.update_data_request_stages(reveal_deadline).is_empty());
+        assert!(p.data_requests_by_deadline.is_empty());
+    }
+
+    #[test]
+    fn expired_data_request_penalizes_witnesses() {
+        let fake_block_hash = Hash::SHA256([1; 32]);
+        let epoch = 0;
+        let transaction = fake_transaction_zip(vec![(
+            Input::ValueTransfer(empty_value_transfer_input()),
+            Output::DataRequest(empty_data_request()),
+        )]);
+        let dr_pointer = OutputPointer {
+            transaction_id: transaction.hash(),
+            output_index: 0,
+        };
+
+        let mut p = DataRequestPool::default();
+        p.process_transaction(&transaction, epoch, &fake_block_hash);
+
+        // Two witnesses with a reputation of 10 commit to the data request
+        let witnesses: Vec<PublicKeyHash> = (1..=2)
+            .map(|i| {
+                let mut commit_transaction = fake_transaction_zip(vec![(
+                    Input::DataRequest(DataRequestInput {
+                        transaction_id: dr_pointer.transaction_id,
+                        output_index: dr_pointer.output_index,
+                        poe: [i; 32],
+                    }),
+                    Output::Commit(empty_commit_output()),
+                )]);
+                commit_transaction.signatures[0].public_key.bytes = [i; 32];
+                let pkh =
+                    PublicKeyHash::from_public_key(&commit_transaction.signatures[0].public_key);
+                for reputation_epoch in 0..10 {
+                    p.reputation_engine
+                        .apply_tally(reputation_epoch, &[pkh], &[]);
+                }
+                p.process_transaction(&commit_transaction, epoch + 1, &fake_block_hash);
+
+                pkh
+            })
+            .collect();
+        p.update_data_request_stages(epoch + 1);
+        for pkh in &witnesses {
+            assert_eq!(p.reputation_engine.reputation(pkh), 10);
+        }
+
+        // None of them reveals before the deadline
+        p.update_data_request_stages(epoch + REVEAL_TIMEOUT_EPOCHS);
+        for pkh in &witnesses {
+            assert_eq!(p.reputation_engine.reputation(pkh), 10);
+        }
+
+        // So the data request expires and both are penalized
+        p.update_data_request_stages(epoch + 1 + REVEAL_TIMEOUT_EPOCHS);
+        assert!(!p.data_request_pool.contains_key(&dr_pointer));
+        for pkh in &witnesses {
+            assert_eq!(p.reputation_engine.reputation(pkh), 5);
+            assert!(p.reputation_engine.is_active(pkh));
+        }
+        // Without truthful witnesses, the lost reputation is not issued again
+        assert_eq!(p.reputation_engine.total_reputation(), 10);
+    }
 }
@@ -3,7 +3,8 @@
 use failure::Fail;
 use std::num::ParseIntError;
 
-use super::chain::{Epoch, Hash, OutputPointer};
+use super::chain::{Environment, Epoch, Hash, OutputPointer, PublicKeyHash};
+use super::wit::Wit;
 
 /// The error type for operations on a [`ChainInfo`](ChainInfo)
 #[derive(Debug, PartialEq, Fail)]
@@ -22,6 +23,30 @@ pub enum BuildersError {
     /// No inventory vectors available to create a Inventory Request message
     #[fail(display = "No inventory vectors available to create a Inventory Request message")]
     NoInvVectorsRequest,
+    /// No locator hashes available to create a GetBlocks message
+    #[fail(display = "No locator hashes available to create a GetBlocks message")]
+    NoLocatorHashes,
+    /// Too many locator hashes to create a GetBlocks message
+    #[fail(
+        display = "Too many locator hashes to create a GetBlocks message ({} > {})",
+        count, limit
+    )]
+    TooManyLocatorHashes { count: usize, limit: usize },
+    /// Too many headers to create a Headers message
+    #[fail(
+        display = "Too many headers to create a Headers message ({} > {})",
+        count, limit
+    )]
+    TooManyHeaders { count: usize, limit: usize },
+    /// The magic number of a message does not belong to the expected environment
+    #[fail(
+        display = "Magic number {:#06x} does not belong to the {:?} environment",
+        magic, environment
+    )]
+    WrongMagic {
+        magic: u16,
+        environment: Environment,
+    },
 }
 
 /// The error type for operations on a [`Transaction`](Transaction)
@@ -32,6 +57,9 @@ pub enum TransactionError {
     /// The transaction creates value
     #[fail(display = "Transaction creates value (its fee is negative)")]
     NegativeFee,
+    /// The sum of some values of the transaction does not fit in a `Wit`
+    #[fail(display = "Transaction value overflow")]
+    ValueOverflow,
     /// A transaction with the given hash wasn't found in a pool.
     #[fail(display = "A hash is missing in the pool (\"{}\")", hash)]
     PoolMiss { hash: Hash },
@@ -59,18 +87,30 @@ pub enum TransactionError {
     #[fail(display = "Commit transaction has a invalid Proof of Eligibility")]
     InvalidDataRequestPoe,
     #[fail(display = "Invalid fee found: {}. Expected fee: {}", fee, expected_fee)]
-    InvalidFee { fee: u64, expected_fee: u64 },
-    #[fail(display = "Invalid Data Request reward: {}", reward)]
-    InvalidDataRequestReward { reward: i64 },
+    InvalidFee { fee: Wit, expected_fee: Wit },
+    #[fail(
+        display = "Invalid Data Request reward per witness ({}), it must be greater than the commit and reveal fees ({})",
+        reward, fees
+    )]
+    InvalidDataRequestReward { reward: Wit, fees: Wit },
     #[fail(
         display = "Invalid Data Request reward ({}) for this number of witnesses ({})",
         dr_value, witnesses
     )]
-    InvalidDataRequestValue { dr_value: i64, witnesses: i64 },
+    InvalidDataRequestValue { dr_value: Wit, witnesses: u16 },
     #[fail(display = "Data Request witnesses number is not enough")]
     InsufficientWitnesses,
     #[fail(display = "Reveals from different Data Requests")]
     RevealsFromDifferentDataRequest,
+    /// Only HTTP-POST retrievals can have a body
+    #[fail(display = "HTTP-GET retrieval of {} has a body", url)]
+    BodyInGetRetrieval { url: String },
+    /// Pinned certificates are identified by their SHA-256 fingerprint
+    #[fail(
+        display = "Certificate fingerprint of {} has {} bytes instead of 32",
+        url, length
+    )]
+    InvalidCertificateFingerprint { url: String, length: usize },
     #[fail(
         display = "Mismatching between local tally ({:?}) and miner tally ({:?})",
         local_tally, miner_tally
@@ -97,6 +137,77 @@ pub enum TransactionError {
     SignatureNotFound,
 }
 
+/// The error type for [`verify_signatures`](crate::chain::verify_signatures)
+#[derive(Debug, PartialEq, Fail)]
+pub enum KeyedSignatureError {
+    /// The public key of the signature does not hash to the expected public key hash
+    #[fail(
+        display = "Public key hash mismatch: expected {}, found {}",
+        expected, found
+    )]
+    PublicKeyHashMismatch {
+        expected: PublicKeyHash,
+        found: PublicKeyHash,
+    },
+    /// The signature is malformed or it is not valid for the hash and the public key
+    #[fail(display = "Invalid signature of {}", hash)]
+    InvalidSignature { hash: Hash },
+}
+
+/// The error type for conversions between epochs and timestamps
+#[derive(Debug, PartialEq, Fail)]
+pub enum EpochCalculationError {
+    /// Checkpoint zero is in the future
+    #[fail(display = "Checkpoint zero is in the future (timestamp: {})", _0)]
+    CheckpointZeroInTheFuture(i64),
+    /// The period between checkpoints is zero
+    #[fail(display = "The checkpoints period cannot be zero")]
+    ZeroCheckpointsPeriod,
+    /// Overflow when calculating an epoch or a timestamp
+    #[fail(display = "Overflow when calculating the epoch or its timestamp")]
+    Overflow,
+}
+
+/// The error type for operations on an [`UnspentOutputsPool`](UnspentOutputsPool)
+#[derive(Debug, PartialEq, Fail)]
+pub enum UtxoError {
+    /// There are no stored diffs to undo a block
+    #[fail(
+        display = "Cannot undo block {}: there are no blocks to undo",
+        block_hash
+    )]
+    NoBlocksToUndo { block_hash: Hash },
+    /// Only the last applied block can be undone
+    #[fail(
+        display = "Cannot undo block {}: the last applied block is {}",
+        block_hash, last_block_hash
+    )]
+    NotLastBlock {
+        block_hash: Hash,
+        last_block_hash: Hash,
+    },
+}
+
+/// The error type for restoring a [`ChainState`](ChainState) from a snapshot
+#[derive(Debug, PartialEq, Fail)]
+pub enum SnapshotError {
+    /// There is no snapshot in the storage
+    #[fail(display = "There is no snapshot of the chain state")]
+    Missing,
+    /// The snapshot was not taken at a block of the chain
+    #[fail(
+        display = "The snapshot of block {} at epoch {} is not in the chain",
+        hash, epoch
+    )]
+    NotInChain { epoch: Epoch, hash: Hash },
+    /// The update of the block of the chain at this epoch is missing or does not match the block
+    #[fail(
+        display = "Missing or mismatching update for the block at epoch {}",
+        epoch
+    )]
+    UpdateMismatch { epoch: Epoch },
+}
+
 /// The error type for operations on a [`Block`](Block)
 #[derive(Debug, PartialEq, Fail)]
 pub enum BlockError {
@@ -114,12 +225,14 @@ pub enum BlockError {
         mint_value, fees_value, reward_value
     )]
     MismatchedMintValue {
-        mint_value: u64,
-        fees_value: u64,
-        reward_value: u64,
+        mint_value: Wit,
+        fees_value: Wit,
+        reward_value: Wit,
     },
     #[fail(display = "The block has an invalid PoE")]
     NotValidPoe,
+    #[fail(display = "The leadership proof of the block does not make its miner eligible")]
+    NotEligible,
     #[fail(display = "The block has an invalid Merkle Tree")]
     NotValidMerkleTree,
     #[fail(
@@ -131,7 +244,7 @@ pub enum BlockError {
         block_epoch: Epoch,
     },
     #[fail(
-        display = "Ignoring block because its epoch ({}) is older than highest block checkpoint ({})",
+        display = "Ignoring block because its epoch ({}) is not later than highest block checkpoint ({})",
         block_epoch, chain_epoch
     )]
     BlockOlderThanTip {
@@ -161,6 +274,79 @@ pub enum BlockError {
     VerifySignatureFail { hash: Hash },
 }
 
+/// The error type for the validation of a [`SignedCheckpoint`](SignedCheckpoint)
+#[derive(Debug, PartialEq, Fail)]
+pub enum CheckpointError {
+    /// The checkpoint is not signed by any of the trusted public keys
+    #[fail(
+        display = "Checkpoint for epoch {} is not signed by a trusted public key",
+        checkpoint
+    )]
+    UntrustedSigner { checkpoint: Epoch },
+    /// Checkpoint verification signature process failed.
+    #[fail(
+        display = "Failed to verify the signature of the checkpoint for epoch {}",
+        checkpoint
+    )]
+    VerifySignatureFail { checkpoint: Epoch },
+}
+
+/// The error type for the validation of a block, telling at which stage of the validation it
+/// failed
+#[derive(Debug, Fail)]
+pub enum BlockValidationError {
+    /// The beacon of the block does not extend the tip of the chain in the current epoch
+    #[fail(display = "Invalid beacon: {}", _0)]
+    Beacon(BlockError),
+    /// The leadership proof of the block is not valid
+    #[fail(display = "Invalid leadership proof: {}", _0)]
+    LeadershipProof(failure::Error),
+    /// The leadership proof does not make the miner eligible, given the state of the chain
+    #[fail(display = "Not eligible: {}", _0)]
+    Eligibility(BlockError),
+    /// The merkle root of the header does not commit to the transactions of the block
+    #[fail(
+        display = "Merkle root {} does not match the transactions of the block ({})",
+        found, expected
+    )]
+    MerkleRoot {
+        /// Merkle root of the transactions of the block
+        expected: Hash,
+        /// Merkle root of the header
+        found: Hash,
+    },
+    /// A transaction of the block is not valid
+    #[fail(display = "Invalid transaction #{} ({}): {}", index, hash, error)]
+    Transaction {
+        /// Position of the transaction in the block
+        index: usize,
+        /// Hash of the transaction
+        hash: Hash,
+        /// Reason why the transaction is not valid
+        error: failure::Error,
+    },
+    /// The block has no mint transaction, or it does not create the block reward plus the fees
+    #[fail(display = "Invalid mint transaction: {}", _0)]
+    Mint(failure::Error),
+    /// The block does not have the commits required by its data requests
+    #[fail(display = "Invalid commits: {}", _0)]
+    Commits(BlockError),
+}
+
+impl BlockValidationError {
+    /// Whether the block is malformed, no matter the state of the chain: such blocks can only be
+    /// sent by faulty or malicious peers. Other errors may just mean that the peer is on another
+    /// chain
+    pub fn is_malformed(&self) -> bool {
+        match self {
+            BlockValidationError::LeadershipProof(_) | BlockValidationError::MerkleRoot { .. } => {
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
 #[derive(Debug, Fail)]
 pub enum OutputPointerParseError {
     #[fail(display = "output pointer has an invalid length")]
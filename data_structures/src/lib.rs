@@ -28,5 +28,11 @@ pub mod error;
 /// Module containing data_request structures
 pub mod data_request;
 
+/// Module containing the type used for amounts of value
+pub mod wit;
+
+/// Module containing the reputation of the identities which resolve data requests
+pub mod reputation;
+
 #[cfg(test)]
 pub mod tests;
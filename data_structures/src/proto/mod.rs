@@ -1,9 +1,10 @@
 use self::schema::witnet;
 use crate::types::IpAddress;
-use crate::{chain, types};
+use crate::{chain, types, wit::Wit};
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use failure::{ensure, format_err, Error};
 use protobuf::Message;
+use witnet_crypto::hash::calculate_sha256;
 
 pub mod schema;
 
@@ -38,37 +39,54 @@ pub trait ProtobufConvert: Sized {
         a.merge_from_bytes(bytes)?;
         Self::from_pb(a)
     }
+
+    /// Struct -> ProtoStruct -> Bytes -> Hash
+    ///
+    /// Canonical hash of every type with a protobuf representation: hashes are always calculated
+    /// over the same serialization used in the wire format. `Hashable` implementations of chain
+    /// types must use this method.
+    fn to_pb_hash(&self) -> chain::Hash
+    where
+        Self::ProtoStruct: Message,
+    {
+        calculate_sha256(&self.to_pb_bytes().unwrap()).into()
+    }
 }
 
 impl ProtobufConvert for chain::RADType {
-    type ProtoStruct = witnet::TransactionBody_Output_DataRequestOutput_RADRequest_RADType;
+    type ProtoStruct = witnet::rad::RADType;
 
     fn to_pb(&self) -> Self::ProtoStruct {
         match self {
-            chain::RADType::HttpGet => {
-                witnet::TransactionBody_Output_DataRequestOutput_RADRequest_RADType::HttpGet
-            }
+            chain::RADType::HttpGet => witnet::rad::RADType::HttpGet,
+            chain::RADType::HttpPost => witnet::rad::RADType::HttpPost,
         }
     }
 
     fn from_pb(pb: Self::ProtoStruct) -> Result<Self, Error> {
         Ok(match pb {
-            witnet::TransactionBody_Output_DataRequestOutput_RADRequest_RADType::HttpGet => {
-                chain::RADType::HttpGet
-            }
+            witnet::rad::RADType::HttpGet => chain::RADType::HttpGet,
+            witnet::rad::RADType::HttpPost => chain::RADType::HttpPost,
         })
     }
 }
 
+impl ProtobufConvert for chain::PublicKeyHash {
+    type ProtoStruct = Vec<u8>;
+    fn to_pb(&self) -> Self::ProtoStruct {
+        self.as_bytes().to_pb()
+    }
+    fn from_pb(pb: Self::ProtoStruct) -> Result<Self, Error> {
+        <[u8; 20]>::from_pb(pb).map(chain::PublicKeyHash::from_bytes)
+    }
+}
+
 impl ProtobufConvert for chain::PublicKey {
-    type ProtoStruct = witnet::PublicKey;
+    type ProtoStruct = witnet::chain::PublicKey;
 
     fn to_pb(&self) -> Self::ProtoStruct {
-        let mut m = witnet::PublicKey::new();
-        let mut v = vec![];
-        v.extend(&[self.compressed]);
-        v.extend(&self.bytes);
-        m.set_public_key(v);
+        let mut m = witnet::chain::PublicKey::new();
+        m.set_public_key(self.to_bytes().to_vec());
 
         m
     }
@@ -88,10 +106,10 @@ impl ProtobufConvert for chain::PublicKey {
 }
 
 impl ProtobufConvert for types::Address {
-    type ProtoStruct = witnet::Address;
+    type ProtoStruct = witnet::messages::Address;
 
     fn to_pb(&self) -> Self::ProtoStruct {
-        let mut address = witnet::Address::new();
+        let mut address = witnet::messages::Address::new();
         let mut bytes = vec![];
         match self.ip {
             IpAddress::Ipv4 { ip } => {
@@ -137,6 +155,52 @@ impl ProtobufConvert for types::Address {
     }
 }
 
+impl ProtobufConvert for chain::UnspentOutputsPool {
+    type ProtoStruct = witnet::chain::UnspentOutputsPool;
+
+    fn to_pb(&self) -> Self::ProtoStruct {
+        // Sort the entries so the serialization is deterministic
+        let mut outputs: Vec<_> = self.iter().collect();
+        outputs.sort_by_key(|(pointer, _)| (pointer.transaction_id, pointer.output_index));
+
+        let entries: Vec<_> = outputs
+            .into_iter()
+            .map(|(output_pointer, output)| {
+                let mut entry = witnet::chain::UnspentOutputsPool_Entry::new();
+                entry.set_output_pointer(output_pointer.to_pb());
+                entry.set_output(output.to_pb());
+
+                entry
+            })
+            .collect();
+        let mut m = witnet::chain::UnspentOutputsPool::new();
+        m.set_entries(entries.into());
+
+        m
+    }
+
+    fn from_pb(mut pb: Self::ProtoStruct) -> Result<Self, Error> {
+        let mut pool = chain::UnspentOutputsPool::new();
+        for mut entry in pb.take_entries().into_iter() {
+            let output_pointer = chain::OutputPointer::from_pb(entry.take_output_pointer())?;
+            let output = chain::Output::from_pb(entry.take_output())?;
+            pool.insert(output_pointer, output);
+        }
+
+        Ok(pool)
+    }
+}
+
+impl ProtobufConvert for Wit {
+    type ProtoStruct = u64;
+    fn to_pb(&self) -> Self::ProtoStruct {
+        self.nanowits()
+    }
+    fn from_pb(pb: Self::ProtoStruct) -> Result<Self, Error> {
+        Ok(Wit::from_nanowits(pb))
+    }
+}
+
 impl ProtobufConvert for String {
     type ProtoStruct = Self;
     fn to_pb(&self) -> Self::ProtoStruct {
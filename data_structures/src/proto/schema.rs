@@ -2,4 +2,11 @@
 #![allow(bare_trait_objects)]
 #![allow(renamed_and_removed_lints)]
 
+// One module is generated for every file of the schema: `chain`, `messages` and `rad`
 include!(concat!(env!("OUT_DIR"), "/witnet_proto_mod.rs"));
+
+/// Generated types of the `witnet.*` protobuf packages, e.g. `witnet::chain::Block` for
+/// `witnet.chain.Block`
+pub mod witnet {
+    pub use super::{chain, messages, rad};
+}
@@ -0,0 +1,362 @@
+//! # Reputation
+//!
+//! The witnesses of the data requests gain reputation by revealing values which match the result
+//! of their tallies, and lose it by lying or by not revealing their commitments:
+//!
+//! * Every truthful witness of a tallied data request is issued `REPUTATION_ISSUANCE` points,
+//!   plus an equal share of the reputation lost by the other witnesses of the data request.
+//! * Witnesses revealing a value different from the result, and witnesses which committed
+//!   without revealing, lose `PENALIZATION_PERCENTAGE` percent of their reputation, starting
+//!   with the most recently issued.
+//! * Reputation expires `REPUTATION_EXPIRATION_EPOCHS` epochs after being issued.
+//!
+//! The identities which committed to a data request in the last `ACTIVITY_PERIOD_EPOCHS` epochs
+//! are active. The share of the total active reputation held by an identity is the base of the
+//! eligibility calculations.
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    chain::{Epoch, PublicKeyHash},
+    data_request::{ChangesMap, MapChanges},
+};
+
+/// Reputation issued to every truthful witness of a tallied data request
+pub const REPUTATION_ISSUANCE: u64 = 1;
+
+/// Percentage of their reputation lost by liars and by witnesses which do not reveal
+pub const PENALIZATION_PERCENTAGE: u64 = 50;
+
+/// Epochs after which issued reputation expires
+pub const REPUTATION_EXPIRATION_EPOCHS: Epoch = 20_000;
+
+/// Epochs after its last commitment during which an identity is active
+pub const ACTIVITY_PERIOD_EPOCHS: Epoch = 2_000;
+
+/// Reputation of the identities which resolve data requests
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReputationEngine {
+    /// Reputation of every identity, as the amounts issued to it by the epoch they expire at
+    #[serde(with = "pkh_map")]
+    reputation: HashMap<PublicKeyHash, BTreeMap<Epoch, u64>>,
+    /// Identities with reputation expiring at every epoch
+    #[serde(serialize_with = "crate::serializers::sorted::sets")]
+    expirations: BTreeMap<Epoch, HashSet<PublicKeyHash>>,
+    /// Sum of the reputation of all the identities
+    total_reputation: u64,
+    /// Epoch of the last commitment of every active identity
+    #[serde(with = "pkh_map")]
+    last_activity: HashMap<PublicKeyHash, Epoch>,
+}
+
+impl ReputationEngine {
+    /// Reputation of an identity
+    pub fn reputation(&self, pkh: &PublicKeyHash) -> u64 {
+        self.reputation
+            .get(pkh)
+            .map_or(0, |amounts| amounts.values().sum())
+    }
+
+    /// Sum of the reputation of all the identities
+    pub fn total_reputation(&self) -> u64 {
+        self.total_reputation
+    }
+
+    /// Whether an identity has committed to a data request in the last `ACTIVITY_PERIOD_EPOCHS`
+    /// epochs
+    pub fn is_active(&self, pkh: &PublicKeyHash) -> bool {
+        self.last_activity.contains_key(pkh)
+    }
+
+    /// Number of active identities
+    pub fn active_identities(&self) -> usize {
+        self.last_activity.len()
+    }
+
+    /// Sum of the reputation of the active identities
+    pub fn total_active_reputation(&self) -> u64 {
+        self.last_activity
+            .keys()
+            .map(|pkh| self.reputation(pkh))
+            .sum()
+    }
+
+    /// Method to record a commitment of an identity in `epoch`
+    pub fn record_activity(&mut self, pkh: PublicKeyHash, epoch: Epoch) {
+        let last_activity = self.last_activity.entry(pkh).or_insert(epoch);
+        *last_activity = (*last_activity).max(epoch);
+    }
+
+    /// Method to update the reputation after the tally of a data request in `epoch`. The
+    /// `penalized` witnesses lose part of their reputation, which is shared among the
+    /// `truthful` witnesses along with the new reputation.
+    pub fn apply_tally(
+        &mut self,
+        epoch: Epoch,
+        truthful: &[PublicKeyHash],
+        penalized: &[PublicKeyHash],
+    ) {
+        let lost: u64 = penalized.iter().map(|pkh| self.penalize(pkh)).sum();
+        if truthful.is_empty() {
+            return;
+        }
+
+        // The remainder of the division of the lost reputation is not issued again
+        let share = lost / truthful.len() as u64;
+        for pkh in truthful {
+            self.issue(*pkh, REPUTATION_ISSUANCE + share, epoch);
+        }
+    }
+
+    /// Method to expire the reputation issued until `epoch - REPUTATION_EXPIRATION_EPOCHS`, and
+    /// the activity of the identities without commitments since
+    /// `epoch - ACTIVITY_PERIOD_EPOCHS`
+    pub fn expire(&mut self, epoch: Epoch) {
+        let due_epochs: Vec<Epoch> = self
+            .expirations
+            .range(..=epoch)
+            .map(|(epoch, _)| *epoch)
+            .collect();
+        for due_epoch in due_epochs {
+            for pkh in self.expirations.remove(&due_epoch).unwrap_or_default() {
+                let amounts = match self.reputation.get_mut(&pkh) {
+                    Some(amounts) => amounts,
+                    None => continue,
+                };
+                if let Some(amount) = amounts.remove(&due_epoch) {
+                    self.total_reputation -= amount;
+                }
+                if amounts.is_empty() {
+                    self.reputation.remove(&pkh);
+                }
+            }
+        }
+
+        self.last_activity
+            .retain(|_, last_activity| *last_activity + ACTIVITY_PERIOD_EPOCHS > epoch);
+    }
+
+    /// Get the changes which turn this reputation engine into `other`
+    pub fn diff(&self, other: &ReputationEngine) -> ReputationEngineDiff {
+        ReputationEngineDiff {
+            reputation: self.reputation.changes(&other.reputation),
+            expirations: self.expirations.changes(&other.expirations),
+            total_reputation: if self.total_reputation == other.total_reputation {
+                None
+            } else {
+                Some(other.total_reputation)
+            },
+            last_activity: self.last_activity.changes(&other.last_activity),
+        }
+    }
+
+    /// Get the changes which turn the result of applying `diff` to this reputation engine back
+    /// into this reputation engine
+    pub fn inverse_diff(&self, diff: &ReputationEngineDiff) -> ReputationEngineDiff {
+        ReputationEngineDiff {
+            reputation: self.reputation.inverse_changes(&diff.reputation),
+            expirations: self.expirations.inverse_changes(&diff.expirations),
+            total_reputation: diff.total_reputation.map(|_| self.total_reputation),
+            last_activity: self.last_activity.inverse_changes(&diff.last_activity),
+        }
+    }
+
+    /// Apply the changes of a [`ReputationEngineDiff`](ReputationEngineDiff)
+    pub fn apply_diff(&mut self, diff: ReputationEngineDiff) {
+        self.reputation.apply_changes(diff.reputation);
+        self.expirations.apply_changes(diff.expirations);
+        if let Some(total_reputation) = diff.total_reputation {
+            self.total_reputation = total_reputation;
+        }
+        self.last_activity.apply_changes(diff.last_activity);
+    }
+
+    /// Issue reputation to an identity in `epoch`
+    fn issue(&mut self, pkh: PublicKeyHash, amount: u64, epoch: Epoch) {
+        let expiration = epoch + REPUTATION_EXPIRATION_EPOCHS;
+        *self
+            .reputation
+            .entry(pkh)
+            .or_default()
+            .entry(expiration)
+            .or_insert(0) += amount;
+        self.expirations.entry(expiration).or_default().insert(pkh);
+        self.total_reputation += amount;
+    }
+
+    /// Remove `PENALIZATION_PERCENTAGE` percent of the reputation of an identity, rounded up,
+    /// starting with the most recently issued. Returns the reputation removed.
+    fn penalize(&mut self, pkh: &PublicKeyHash) -> u64 {
+        let amounts = match self.reputation.get_mut(pkh) {
+            Some(amounts) => amounts,
+            None => return 0,
+        };
+        let reputation: u64 = amounts.values().sum();
+        let penalty = (reputation * PENALIZATION_PERCENTAGE + 99) / 100;
+
+        let mut left = penalty;
+        while left > 0 {
+            let (expiration, amount) = match amounts.iter_mut().next_back() {
+                Some((expiration, amount)) => (*expiration, amount),
+                None => break,
+            };
+            let taken = left.min(*amount);
+            *amount -= taken;
+            left -= taken;
+            if *amount == 0 {
+                amounts.remove(&expiration);
+                if let Some(identities) = self.expirations.get_mut(&expiration) {
+                    identities.remove(pkh);
+                    if identities.is_empty() {
+                        self.expirations.remove(&expiration);
+                    }
+                }
+            }
+        }
+        if amounts.is_empty() {
+            self.reputation.remove(pkh);
+        }
+        self.total_reputation -= penalty;
+
+        penalty
+    }
+}
+
+/// Changes between two states of a [`ReputationEngine`](ReputationEngine)
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReputationEngineDiff {
+    /// Changes of the reputation of the identities
+    reputation: MapChanges<PublicKeyHash, BTreeMap<Epoch, u64>>,
+    /// Changes of the identities with reputation expiring at every epoch
+    expirations: MapChanges<Epoch, HashSet<PublicKeyHash>>,
+    /// Sum of the reputation of all the identities, if it changed
+    total_reputation: Option<u64>,
+    /// Changes of the epoch of the last commitment of the active identities
+    last_activity: MapChanges<PublicKeyHash, Epoch>,
+}
+
+/// Serialization of the maps with public key hashes as keys as lists of pairs, because JSON
+/// only supports strings as keys. The pairs are sorted by key, so that the serialization does not
+/// depend on the iteration order of the map
+mod pkh_map {
+    use std::collections::HashMap;
+
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::chain::PublicKeyHash;
+
+    pub fn serialize<S, V>(
+        map: &HashMap<PublicKeyHash, V>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        V: Serialize,
+    {
+        let mut pairs: Vec<(&PublicKeyHash, &V)> = map.iter().collect();
+        pairs.sort_by_key(|(pkh, _)| *pkh);
+
+        serializer.collect_seq(pairs)
+    }
+
+    pub fn deserialize<'de, D, V>(deserializer: D) -> Result<HashMap<PublicKeyHash, V>, D::Error>
+    where
+        D: Deserializer<'de>,
+        V: Deserialize<'de>,
+    {
+        let pairs = Vec::<(PublicKeyHash, V)>::deserialize(deserializer)?;
+
+        Ok(pairs.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pkh(byte: u8) -> PublicKeyHash {
+        PublicKeyHash::from_bytes([byte; 20])
+    }
+
+    #[test]
+    fn truthful_witnesses_gain_reputation() {
+        let mut engine = ReputationEngine::default();
+        engine.apply_tally(10, &[pkh(1), pkh(2)], &[]);
+        engine.apply_tally(11, &[pkh(1)], &[]);
+
+        assert_eq!(engine.reputation(&pkh(1)), 2 * REPUTATION_ISSUANCE);
+        assert_eq!(engine.reputation(&pkh(2)), REPUTATION_ISSUANCE);
+        assert_eq!(engine.reputation(&pkh(3)), 0);
+        assert_eq!(engine.total_reputation(), 3 * REPUTATION_ISSUANCE);
+    }
+
+    #[test]
+    fn penalized_reputation_shared_among_truthful_witnesses() {
+        let mut engine = ReputationEngine::default();
+        for epoch in 0..10 {
+            engine.apply_tally(epoch, &[pkh(1)], &[]);
+        }
+        assert_eq!(engine.reputation(&pkh(1)), 10);
+
+        engine.apply_tally(10, &[pkh(2), pkh(3)], &[pkh(1)]);
+
+        assert_eq!(engine.reputation(&pkh(1)), 5);
+        assert_eq!(engine.reputation(&pkh(2)), REPUTATION_ISSUANCE + 2);
+        assert_eq!(engine.reputation(&pkh(3)), REPUTATION_ISSUANCE + 2);
+        // The remainder of the lost reputation is burnt
+        assert_eq!(engine.total_reputation(), 5 + 2 * (REPUTATION_ISSUANCE + 2));
+
+        // The most recent reputation was lost, so the oldest one is the first to expire
+        engine.expire(REPUTATION_EXPIRATION_EPOCHS + 4);
+        assert_eq!(engine.reputation(&pkh(1)), 0);
+    }
+
+    #[test]
+    fn reputation_expires() {
+        let mut engine = ReputationEngine::default();
+        engine.apply_tally(10, &[pkh(1)], &[]);
+        engine.apply_tally(20, &[pkh(1)], &[]);
+
+        engine.expire(REPUTATION_EXPIRATION_EPOCHS + 9);
+        assert_eq!(engine.reputation(&pkh(1)), 2 * REPUTATION_ISSUANCE);
+        engine.expire(REPUTATION_EXPIRATION_EPOCHS + 10);
+        assert_eq!(engine.reputation(&pkh(1)), REPUTATION_ISSUANCE);
+        engine.expire(REPUTATION_EXPIRATION_EPOCHS + 20);
+        assert_eq!(engine.reputation(&pkh(1)), 0);
+        assert_eq!(engine.total_reputation(), 0);
+        assert_eq!(engine, ReputationEngine::default());
+    }
+
+    #[test]
+    fn active_reputation() {
+        let mut engine = ReputationEngine::default();
+        engine.apply_tally(10, &[pkh(1), pkh(2)], &[]);
+        engine.record_activity(pkh(1), 10);
+        engine.record_activity(pkh(3), 15);
+
+        assert!(engine.is_active(&pkh(1)));
+        assert!(!engine.is_active(&pkh(2)));
+        assert_eq!(engine.active_identities(), 2);
+        assert_eq!(engine.total_active_reputation(), REPUTATION_ISSUANCE);
+
+        engine.expire(10 + ACTIVITY_PERIOD_EPOCHS);
+        assert!(!engine.is_active(&pkh(1)));
+        assert!(engine.is_active(&pkh(3)));
+        assert_eq!(engine.total_active_reputation(), 0);
+    }
+
+    #[test]
+    fn serialization_roundtrip() {
+        let mut engine = ReputationEngine::default();
+        engine.apply_tally(10, &[pkh(1), pkh(2)], &[]);
+        engine.record_activity(pkh(1), 10);
+
+        let json = serde_json::to_string(&engine).unwrap();
+
+        assert_eq!(
+            serde_json::from_str::<ReputationEngine>(&json).unwrap(),
+            engine
+        );
+    }
+}
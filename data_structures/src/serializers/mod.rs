@@ -1,2 +1,4 @@
 /// Tools to convert from flatbuffers into rust structures
 pub mod decoders;
+/// Deterministic serialization of hash sets
+pub mod sorted;
@@ -0,0 +1,43 @@
+//! Serialization of hash sets in ascending order, because the iteration order of a `HashSet`
+//! differs between nodes and the serialized chain state has to be hashed
+use std::collections::{BTreeMap, HashSet};
+use std::hash::Hash;
+
+use serde::{Serialize, Serializer};
+
+/// Items of a set, serialized in ascending order
+struct SortedSet<'a, T>(&'a HashSet<T>);
+
+impl<'a, T> Serialize for SortedSet<'a, T>
+where
+    T: Serialize + Ord + Hash,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut items: Vec<&T> = self.0.iter().collect();
+        items.sort();
+
+        serializer.collect_seq(items)
+    }
+}
+
+/// Serialize a set as a list in ascending order
+pub fn set<S, T>(set: &HashSet<T>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    T: Serialize + Ord + Hash,
+{
+    SortedSet(set).serialize(serializer)
+}
+
+/// Serialize a map of sets, with every set as a list in ascending order
+pub fn sets<S, K, T>(map: &BTreeMap<K, HashSet<T>>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    K: Serialize,
+    T: Serialize + Ord + Hash,
+{
+    serializer.collect_map(map.iter().map(|(key, set)| (key, SortedSet(set))))
+}
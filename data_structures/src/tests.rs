@@ -1,4 +1,5 @@
 use super::chain::*;
+use super::wit::Wit;
 
 #[test]
 fn test_block_hashable_trait() {
@@ -7,6 +8,29 @@ fn test_block_hashable_trait() {
     assert_eq!(block.hash().to_string(), expected);
 }
 
+#[test]
+fn test_hashable_uses_protobuf_serialization() {
+    use crate::proto::ProtobufConvert;
+    use witnet_crypto::hash::calculate_sha256;
+
+    let block = block_example();
+    let beacon = block.block_header.beacon;
+    let expected: Hash = calculate_sha256(&beacon.to_pb_bytes().unwrap()).into();
+
+    assert_eq!(beacon.to_pb_hash(), expected);
+    assert_eq!(beacon.hash(), expected);
+    assert_eq!(block.hash(), block.block_header.to_pb_hash());
+    assert_eq!(block.txns[0].hash(), block.txns[0].body.to_pb_hash());
+
+    // Inventory items have the same ids as the items they contain
+    let transaction = block.txns[0].clone();
+    assert_eq!(InventoryItem::Block(block.clone()).hash(), block.hash());
+    assert_eq!(
+        InventoryItem::Transaction(transaction.clone()).hash(),
+        transaction.hash()
+    );
+}
+
 #[test]
 fn test_transaction_hashable_trait() {
     let transaction = transaction_example();
@@ -309,8 +333,8 @@ fn test_input_output_pointer() {
 fn test_output_value() {
     let output = Output::Commit(CommitOutput {
         commitment: Hash::default(),
-        value: 123,
+        value: Wit::from_nanowits(123),
     });
 
-    assert_eq!(output.value(), 123);
+    assert_eq!(output.value(), Ok(Wit::from_nanowits(123)));
 }
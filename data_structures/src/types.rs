@@ -1,11 +1,13 @@
 use std::fmt;
 
-use crate::chain::{Block, CheckpointBeacon, Hashable, InventoryEntry, Transaction};
+use crate::chain::{
+    Block, BlockHeader, CheckpointBeacon, Hash, Hashable, InventoryEntry, Transaction,
+};
 use crate::proto::{schema::witnet, ProtobufConvert};
 
 /// Witnet's protocol messages
 #[derive(Debug, Eq, PartialEq, Clone, ProtobufConvert)]
-#[protobuf_convert(pb = "witnet::Message")]
+#[protobuf_convert(pb = "witnet::messages::Message")]
 pub struct Message {
     pub kind: Command,
     pub magic: u16,
@@ -13,7 +15,7 @@ pub struct Message {
 
 /// Commands for the Witnet's protocol messages
 #[derive(Debug, Eq, PartialEq, Clone, ProtobufConvert)]
-#[protobuf_convert(pb = "witnet::Message_Command")]
+#[protobuf_convert(pb = "witnet::messages::Message_Command")]
 pub enum Command {
     // Peer discovery messages
     GetPeers(GetPeers),
@@ -26,6 +28,7 @@ pub enum Command {
     // Handshake messages
     Verack(Verack),
     Version(Version),
+    Reject(Reject),
 
     // Inventory messages
     Block(Block),
@@ -33,6 +36,10 @@ pub enum Command {
     InventoryAnnouncement(InventoryAnnouncement),
     InventoryRequest(InventoryRequest),
     LastBeacon(LastBeacon),
+
+    // Synchronization messages
+    GetBlocks(GetBlocks),
+    Headers(Headers),
 }
 
 impl fmt::Display for Command {
@@ -44,11 +51,36 @@ impl fmt::Display for Command {
             Command::Pong(_) => f.write_str(&"PONG".to_string()),
             Command::Verack(_) => f.write_str(&"VERACK".to_string()),
             Command::Version(_) => f.write_str(&"VERSION".to_string()),
+            Command::Reject(_) => f.write_str(&"REJECT".to_string()),
             Command::Block(block) => f.write_str(&format!("BLOCK: {}", block.hash())),
             Command::InventoryAnnouncement(_) => f.write_str(&"INVENTORY_ANNOUNCEMENT".to_string()),
             Command::InventoryRequest(_) => f.write_str(&"INVENTORY_REQUEST".to_string()),
             Command::LastBeacon(_) => f.write_str(&"LAST_BEACON".to_string()),
             Command::Transaction(_) => f.write_str(&"TRANSACTION".to_string()),
+            Command::GetBlocks(_) => f.write_str(&"GET_BLOCKS".to_string()),
+            Command::Headers(_) => f.write_str(&"HEADERS".to_string()),
+        }
+    }
+}
+
+impl Command {
+    /// Name of the command, without any details of its payload
+    pub fn name(&self) -> &'static str {
+        match self {
+            Command::GetPeers(_) => "GET_PEERS",
+            Command::Peers(_) => "PEERS",
+            Command::Ping(_) => "PING",
+            Command::Pong(_) => "PONG",
+            Command::Verack(_) => "VERACK",
+            Command::Version(_) => "VERSION",
+            Command::Reject(_) => "REJECT",
+            Command::Block(_) => "BLOCK",
+            Command::InventoryAnnouncement(_) => "INVENTORY_ANNOUNCEMENT",
+            Command::InventoryRequest(_) => "INVENTORY_REQUEST",
+            Command::LastBeacon(_) => "LAST_BEACON",
+            Command::Transaction(_) => "TRANSACTION",
+            Command::GetBlocks(_) => "GET_BLOCKS",
+            Command::Headers(_) => "HEADERS",
         }
     }
 }
@@ -57,11 +89,11 @@ impl fmt::Display for Command {
 // PEER DISCOVERY MESSAGES
 ///////////////////////////////////////////////////////////
 #[derive(Debug, Eq, PartialEq, Clone, ProtobufConvert)]
-#[protobuf_convert(pb = "witnet::GetPeers")]
+#[protobuf_convert(pb = "witnet::messages::GetPeers")]
 pub struct GetPeers;
 
 #[derive(Debug, Eq, PartialEq, Clone, ProtobufConvert)]
-#[protobuf_convert(pb = "witnet::Peers")]
+#[protobuf_convert(pb = "witnet::messages::Peers")]
 pub struct Peers {
     pub peers: Vec<Address>,
 }
@@ -70,13 +102,13 @@ pub struct Peers {
 // HEARTBEAT MESSAGES
 ///////////////////////////////////////////////////////////
 #[derive(Debug, Eq, PartialEq, Clone, ProtobufConvert)]
-#[protobuf_convert(pb = "witnet::Ping")]
+#[protobuf_convert(pb = "witnet::messages::Ping")]
 pub struct Ping {
     pub nonce: u64,
 }
 
 #[derive(Debug, Eq, PartialEq, Clone, ProtobufConvert)]
-#[protobuf_convert(pb = "witnet::Pong")]
+#[protobuf_convert(pb = "witnet::messages::Pong")]
 pub struct Pong {
     pub nonce: u64,
 }
@@ -85,11 +117,11 @@ pub struct Pong {
 // HANDSHAKE MESSAGES
 ///////////////////////////////////////////////////////////
 #[derive(Debug, Eq, PartialEq, Clone, ProtobufConvert)]
-#[protobuf_convert(pb = "witnet::Verack")]
+#[protobuf_convert(pb = "witnet::messages::Verack")]
 pub struct Verack;
 
 #[derive(Debug, Eq, PartialEq, Clone, ProtobufConvert)]
-#[protobuf_convert(pb = "witnet::Version")]
+#[protobuf_convert(pb = "witnet::messages::Version")]
 pub struct Version {
     pub version: u32,
     pub timestamp: i64,
@@ -99,29 +131,58 @@ pub struct Version {
     pub user_agent: String,
     pub last_epoch: u32,
     pub nonce: u64,
+    /// Ephemeral public key of the encrypted transport, empty if it is not supported
+    pub ephemeral_key: Vec<u8>,
+    /// Hash of the consensus constants of the sender, which must match the ones of the receiver
+    pub consensus_constants: Vec<u8>,
+}
+
+/// Last message sent to a peer whose handshake is refused, before disconnecting it
+#[derive(Debug, Eq, PartialEq, Clone, ProtobufConvert)]
+#[protobuf_convert(pb = "witnet::messages::Reject")]
+pub struct Reject {
+    /// Human readable reason of the rejection
+    pub reason: String,
+    /// Minimum protocol version accepted by the sender
+    pub min_version: u32,
 }
 
 ///////////////////////////////////////////////////////////
 // INVENTORY MESSAGES
 ///////////////////////////////////////////////////////////
 #[derive(Debug, Eq, PartialEq, Clone, ProtobufConvert)]
-#[protobuf_convert(pb = "witnet::InventoryAnnouncement")]
+#[protobuf_convert(pb = "witnet::messages::InventoryAnnouncement")]
 pub struct InventoryAnnouncement {
     pub inventory: Vec<InventoryEntry>,
 }
 
 #[derive(Debug, Eq, PartialEq, Clone, ProtobufConvert)]
-#[protobuf_convert(pb = "witnet::InventoryRequest")]
+#[protobuf_convert(pb = "witnet::messages::InventoryRequest")]
 pub struct InventoryRequest {
     pub inventory: Vec<InventoryEntry>,
 }
 
 #[derive(Debug, Eq, PartialEq, Clone, ProtobufConvert)]
-#[protobuf_convert(pb = "witnet::LastBeacon")]
+#[protobuf_convert(pb = "witnet::messages::LastBeacon")]
 pub struct LastBeacon {
     pub highest_block_checkpoint: CheckpointBeacon,
 }
 
+///////////////////////////////////////////////////////////
+// SYNCHRONIZATION MESSAGES
+///////////////////////////////////////////////////////////
+#[derive(Debug, Eq, PartialEq, Clone, ProtobufConvert)]
+#[protobuf_convert(pb = "witnet::messages::GetBlocks")]
+pub struct GetBlocks {
+    pub locator_hashes: Vec<Hash>,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, ProtobufConvert)]
+#[protobuf_convert(pb = "witnet::messages::Headers")]
+pub struct Headers {
+    pub headers: Vec<BlockHeader>,
+}
+
 ///////////////////////////////////////////////////////////
 // AUX TYPES
 ///////////////////////////////////////////////////////////
@@ -0,0 +1,128 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// 1 nanowit is the minimal unit of value
+/// 1 wit = 1_000_000_000 nanowits
+pub const NANOWITS_PER_WIT: u64 = 1_000_000_000;
+
+/// Amount of value, stored as a number of nanowits.
+///
+/// Arithmetic is only available through checked (or explicitly saturating) operations, so
+/// overflows in fee and reward calculations cannot go unnoticed.
+#[derive(
+    Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, Serialize, Deserialize,
+)]
+pub struct Wit(u64);
+
+impl Wit {
+    /// Create an amount from a number of nanowits
+    pub const fn from_nanowits(nanowits: u64) -> Self {
+        Wit(nanowits)
+    }
+
+    /// Create an amount from a number of wits, or `None` if it does not fit in a `u64` of
+    /// nanowits
+    pub fn from_wits(wits: u64) -> Option<Self> {
+        wits.checked_mul(NANOWITS_PER_WIT).map(Wit)
+    }
+
+    /// Return the amount as a number of nanowits
+    pub fn nanowits(self) -> u64 {
+        self.0
+    }
+
+    /// Return the amount split into its integer part in wits and the remaining nanowits
+    pub fn wits_and_nanowits(self) -> (u64, u64) {
+        (self.0 / NANOWITS_PER_WIT, self.0 % NANOWITS_PER_WIT)
+    }
+
+    /// Checked addition. Returns `None` if overflow occurred.
+    pub fn checked_add(self, rhs: Self) -> Option<Self> {
+        self.0.checked_add(rhs.0).map(Wit)
+    }
+
+    /// Checked subtraction. Returns `None` if the result would be negative.
+    pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+        self.0.checked_sub(rhs.0).map(Wit)
+    }
+
+    /// Checked multiplication by a scalar. Returns `None` if overflow occurred.
+    pub fn checked_mul(self, rhs: u64) -> Option<Self> {
+        self.0.checked_mul(rhs).map(Wit)
+    }
+
+    /// Checked division by a scalar. Returns `None` if `rhs == 0`.
+    pub fn checked_div(self, rhs: u64) -> Option<Self> {
+        self.0.checked_div(rhs).map(Wit)
+    }
+
+    /// Saturating subtraction. Returns zero if the result would be negative.
+    pub fn saturating_sub(self, rhs: Self) -> Self {
+        Wit(self.0.saturating_sub(rhs.0))
+    }
+
+    /// Sum all the amounts of an iterator. Returns `None` if overflow occurred.
+    pub fn checked_sum<I>(iter: I) -> Option<Self>
+    where
+        I: IntoIterator<Item = Self>,
+    {
+        iter.into_iter()
+            .try_fold(Wit::default(), |acc, amount| acc.checked_add(amount))
+    }
+}
+
+/// Format the amount in wits, with as many decimals as needed to represent it exactly
+impl fmt::Display for Wit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (wits, nanowits) = self.wits_and_nanowits();
+        if nanowits == 0 {
+            write!(f, "{}", wits)
+        } else {
+            let decimals = format!("{:09}", nanowits);
+            write!(f, "{}.{}", wits, decimals.trim_end_matches('0'))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wit_conversions() {
+        assert_eq!(Wit::from_wits(2), Some(Wit::from_nanowits(2_000_000_000)));
+        assert_eq!(Wit::from_wits(u64::max_value()), None);
+        assert_eq!(
+            Wit::from_nanowits(2_000_000_003).wits_and_nanowits(),
+            (2, 3)
+        );
+    }
+
+    #[test]
+    fn wit_checked_arithmetic() {
+        let one = Wit::from_nanowits(1);
+        let max = Wit::from_nanowits(u64::max_value());
+
+        assert_eq!(one.checked_add(one), Some(Wit::from_nanowits(2)));
+        assert_eq!(max.checked_add(one), None);
+        assert_eq!(one.checked_sub(one), Some(Wit::default()));
+        assert_eq!(Wit::default().checked_sub(one), None);
+        assert_eq!(max.checked_mul(2), None);
+        assert_eq!(one.checked_div(0), None);
+        assert_eq!(Wit::default().saturating_sub(one), Wit::default());
+        assert_eq!(
+            Wit::checked_sum(vec![one, one, one]),
+            Some(Wit::from_nanowits(3))
+        );
+        assert_eq!(Wit::checked_sum(vec![max, one]), None);
+    }
+
+    #[test]
+    fn wit_display() {
+        assert_eq!(Wit::from_nanowits(0).to_string(), "0");
+        assert_eq!(Wit::from_nanowits(1).to_string(), "0.000000001");
+        assert_eq!(Wit::from_nanowits(1_500_000_000).to_string(), "1.5");
+        assert_eq!(Wit::from_wits(500).unwrap().to_string(), "500");
+    }
+}
@@ -1,6 +1,7 @@
 use std::net::SocketAddr;
 
 use witnet_data_structures::builders::*;
+use witnet_data_structures::proto::ProtobufConvert;
 use witnet_data_structures::{chain::*, types::*};
 
 #[test]
@@ -91,6 +92,25 @@ fn builders_build_peers() {
     assert_eq!(msg, Message::build_peers(0xABCD, &sock_addresses));
 }
 
+#[test]
+fn builders_build_peers_ipv6() {
+    let sock_addresses: Vec<SocketAddr> = vec![
+        "[2001:db8::1]:21337".parse().unwrap(),
+        "192.168.1.1:8000".parse().unwrap(),
+    ];
+    let msg = Message::build_peers(0xABCD, &sock_addresses);
+
+    // The addresses survive the encoding of the message
+    let decoded = Message::from_pb_bytes(&msg.to_pb_bytes().unwrap()).unwrap();
+    match decoded.kind {
+        Command::Peers(Peers { peers }) => {
+            let decoded_addresses: Vec<SocketAddr> = peers.iter().map(from_address).collect();
+            assert_eq!(decoded_addresses, sock_addresses);
+        }
+        _ => panic!("Expected a Peers message"),
+    }
+}
+
 #[test]
 fn builders_build_ping() {
     // Expected message (except nonce which is random)
@@ -110,6 +130,18 @@ fn builders_build_ping() {
     };
 }
 
+#[test]
+fn builders_build_ping_with_nonce() {
+    // Expected message
+    let msg = Message {
+        kind: Command::Ping(Ping { nonce: 1234 }),
+        magic: 0xABCD,
+    };
+
+    // Check that the build_ping_with_nonce function builds the expected message
+    assert_eq!(msg, Message::build_ping_with_nonce(0xABCD, 1234));
+}
+
 #[test]
 fn builders_build_pong() {
     // Expected message
@@ -144,6 +176,8 @@ fn builders_build_version() {
         user_agent: USER_AGENT.to_string(),
         last_epoch: hardcoded_last_epoch,
         nonce: 1234,
+        ephemeral_key: vec![],
+        consensus_constants: vec![],
     });
     let msg = Message {
         kind: version_cmd,
@@ -157,6 +191,7 @@ fn builders_build_version() {
         0xABCD,
         sender_sock_addr,
         receiver_sock_addr,
+        USER_AGENT.to_string(),
         hardcoded_last_epoch,
     );
 
@@ -172,12 +207,16 @@ fn builders_build_version() {
             user_agent,
             last_epoch,
             nonce: _,
+            ephemeral_key,
+            consensus_constants,
         }) if *version == PROTOCOL_VERSION
             && *capabilities == CAPABILITIES
             && *sender_address == sender_addr
             && *receiver_address == receiver_addr
             && user_agent == USER_AGENT
-            && *last_epoch == hardcoded_last_epoch =>
+            && *last_epoch == hardcoded_last_epoch
+            && ephemeral_key.is_empty()
+            && consensus_constants.is_empty() =>
         {
             assert!(true)
         }
@@ -185,6 +224,100 @@ fn builders_build_version() {
     };
 }
 
+#[test]
+fn builders_build_version_ipv6() {
+    let sender_sock_addr: SocketAddr = "[2001:db8::1]:21337".parse().unwrap();
+    let receiver_sock_addr: SocketAddr = "[2001:db8::2]:21337".parse().unwrap();
+    let msg = Message::build_version(
+        0xABCD,
+        sender_sock_addr,
+        receiver_sock_addr,
+        USER_AGENT.to_string(),
+        1234,
+    );
+
+    // The addresses survive the encoding of the message
+    let decoded = Message::from_pb_bytes(&msg.to_pb_bytes().unwrap()).unwrap();
+    assert_eq!(decoded, msg);
+    match decoded.kind {
+        Command::Version(Version {
+            sender_address,
+            receiver_address,
+            ..
+        }) => {
+            assert_eq!(SocketAddr::from(sender_address), sender_sock_addr);
+            assert_eq!(SocketAddr::from(receiver_address), receiver_sock_addr);
+        }
+        _ => panic!("Expected a Version message"),
+    }
+}
+
+#[test]
+fn builders_build_encrypted_version() {
+    let sender_sock_addr = "192.168.1.1:8000".parse().unwrap();
+    let receiver_sock_addr = "192.168.1.2:8001".parse().unwrap();
+    let built_msg = Message::build_encrypted_version(
+        0xABCD,
+        sender_sock_addr,
+        receiver_sock_addr,
+        USER_AGENT.to_string(),
+        1234,
+        vec![0x02; 33],
+    );
+
+    // The encrypted transport capability is announced along with the ephemeral key
+    match built_msg.kind {
+        Command::Version(Version {
+            capabilities,
+            ephemeral_key,
+            ..
+        }) => {
+            assert_eq!(capabilities, CAPABILITIES | ENCRYPTED_TRANSPORT_CAPABILITY);
+            assert_eq!(ephemeral_key, vec![0x02; 33]);
+        }
+        _ => panic!("Expected version, found another type"),
+    };
+}
+
+#[test]
+fn builders_build_version_with_nonce() {
+    let sender_sock_addr = "192.168.1.1:8000".parse().unwrap();
+    let receiver_sock_addr = "192.168.1.2:8001".parse().unwrap();
+    let build = |nonce| {
+        Message::build_version_with_nonce(
+            0xABCD,
+            sender_sock_addr,
+            receiver_sock_addr,
+            USER_AGENT.to_string(),
+            1234,
+            nonce,
+        )
+    };
+
+    // Check that the nonce is the given one
+    match build(5678).kind {
+        Command::Version(Version { nonce, .. }) => assert_eq!(nonce, 5678),
+        _ => panic!("Expected version, found another type"),
+    };
+
+    // Messages built with the same nonce only differ in the timestamp
+    let without_timestamp = |msg: Message| match msg.kind {
+        Command::Version(version) => Version {
+            timestamp: 0,
+            ..version
+        },
+        _ => panic!("Expected version, found another type"),
+    };
+    assert_eq!(
+        without_timestamp(build(5678)),
+        without_timestamp(build(5678))
+    );
+    assert_ne!(
+        without_timestamp(build(5678)),
+        without_timestamp(build(5679))
+    );
+}
+
 #[test]
 fn builders_build_verack() {
     // Expected message
@@ -197,6 +330,28 @@ fn builders_build_verack() {
     assert_eq!(msg, Message::build_verack(0xABCD));
 }
 
+#[test]
+fn builders_build_reject() {
+    // Expected message
+    let msg = Message {
+        kind: Command::Reject(Reject {
+            reason: "Protocol version 1 is no longer supported".to_string(),
+            min_version: 2,
+        }),
+        magic: 0xABCD,
+    };
+
+    // Check that the build_reject function builds the expected message
+    assert_eq!(
+        msg,
+        Message::build_reject(
+            0xABCD,
+            "Protocol version 1 is no longer supported".to_string(),
+            2
+        )
+    );
+}
+
 #[test]
 fn builders_build_inventory_announcement() {
     // Inventory elements
@@ -246,3 +401,134 @@ fn builders_build_inventory_request() {
         Message::build_inventory_request(0xABCD, inventory).unwrap()
     );
 }
+
+#[test]
+fn builders_build_get_blocks() {
+    let locator_hashes = vec![Hash::SHA256([1; 32]), Hash::SHA256([2; 32])];
+
+    // Expected message
+    let msg = Message {
+        kind: Command::GetBlocks(GetBlocks {
+            locator_hashes: locator_hashes.clone(),
+        }),
+        magic: 0xABCD,
+    };
+
+    // Check that the build_get_blocks function builds the expected message
+    assert_eq!(
+        msg,
+        Message::build_get_blocks(0xABCD, locator_hashes).unwrap()
+    );
+
+    // Empty or oversized locators are rejected
+    assert!(Message::build_get_blocks(0xABCD, vec![]).is_err());
+    assert!(
+        Message::build_get_blocks(0xABCD, vec![Hash::default(); MAX_LOCATOR_HASHES + 1]).is_err()
+    );
+}
+
+#[test]
+fn builders_build_headers() {
+    let headers = vec![BlockHeader::default(); 2];
+
+    // Expected message
+    let msg = Message {
+        kind: Command::Headers(Headers {
+            headers: headers.clone(),
+        }),
+        magic: 0xABCD,
+    };
+
+    // Check that the build_headers function builds the expected message
+    assert_eq!(msg, Message::build_headers(0xABCD, headers).unwrap());
+
+    // An empty list of headers is allowed, an oversized one is not
+    assert!(Message::build_headers(0xABCD, vec![]).is_ok());
+    assert!(Message::build_headers(0xABCD, vec![BlockHeader::default(); MAX_HEADERS + 1]).is_err());
+}
+
+#[test]
+fn builders_build_superblock() {
+    let block = block_example();
+    let superblock = SuperBlock::build(3, &[block.clone(), block.clone()]);
+
+    assert_eq!(superblock.index, 3);
+    assert_eq!(superblock.last_block, block.hash());
+    assert_ne!(superblock.blocks_merkle_root, Hash::default());
+    // The example transaction has a tally output
+    assert_ne!(superblock.tallies_merkle_root, Hash::default());
+    assert!(superblock.signatures.is_empty());
+
+    // Signatures do not change the hash of a superblock
+    let signed = superblock.clone().with_signature(KeyedSignature::default());
+    assert_eq!(signed.signatures.len(), 1);
+    assert_eq!(signed.hash(), superblock.hash());
+}
+
+#[test]
+fn builders_build_empty_superblock() {
+    let superblock = SuperBlock::build(0, &[]);
+
+    assert_eq!(superblock.last_block, Hash::default());
+    assert_eq!(
+        superblock.blocks_merkle_root,
+        superblock.tallies_merkle_root
+    );
+}
+
+#[test]
+fn builders_build_inventory_data_request_and_tally() {
+    let inventory = vec![
+        InventoryEntry::DataRequest(Hash::SHA256([3; 32])),
+        InventoryEntry::Tally(Hash::SHA256([4; 32])),
+    ];
+
+    // Expected messages
+    let announcement = Message {
+        kind: Command::InventoryAnnouncement(InventoryAnnouncement {
+            inventory: inventory.clone(),
+        }),
+        magic: 0xABCD,
+    };
+    let request = Message {
+        kind: Command::InventoryRequest(InventoryRequest {
+            inventory: inventory.clone(),
+        }),
+        magic: 0xABCD,
+    };
+
+    // Check that data request and tally entries are accepted by both builders
+    assert_eq!(
+        announcement,
+        Message::build_inventory_announcement(0xABCD, inventory.clone()).unwrap()
+    );
+    assert_eq!(
+        request,
+        Message::build_inventory_request(0xABCD, inventory).unwrap()
+    );
+}
+
+#[test]
+fn builders_check_magic() {
+    let msg = Message::build_get_peers(Environment::Testnet1.magic());
+
+    assert_eq!(msg.check_magic(&Environment::Testnet1), Ok(()));
+    assert_eq!(
+        msg.check_magic(&Environment::Mainnet),
+        Err(witnet_data_structures::error::BuildersError::WrongMagic {
+            magic: Environment::Testnet1.magic(),
+            environment: Environment::Mainnet,
+        })
+    );
+}
+
+#[test]
+fn environment_magic_numbers_are_unique() {
+    for environment in Environment::ALL.iter() {
+        assert_eq!(
+            Environment::from_magic(environment.magic()).as_ref(),
+            Some(environment)
+        );
+    }
+    assert_eq!(Environment::from_magic(0xABCD), None);
+}
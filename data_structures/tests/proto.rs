@@ -1,4 +1,6 @@
-use witnet_data_structures::chain::PublicKey;
+use witnet_data_structures::chain::{
+    block_example, Hash, InventoryEntry, KeyedSignature, PublicKey, SuperBlock,
+};
 use witnet_data_structures::{proto::ProtobufConvert, types, types::IpAddress};
 
 #[test]
@@ -45,3 +47,30 @@ fn public_key_proto() {
 
     assert_eq!(test_public_key, deserialize_public_key);
 }
+
+#[test]
+fn superblock_proto() {
+    let superblock =
+        SuperBlock::build(7, &[block_example()]).with_signature(KeyedSignature::default());
+    let superblock_bytes = superblock.to_pb_bytes().unwrap();
+
+    let superblock2 = SuperBlock::from_pb_bytes(&superblock_bytes).unwrap();
+
+    assert_eq!(superblock, superblock2);
+}
+
+#[test]
+fn inventory_entry_proto() {
+    let entries = vec![
+        InventoryEntry::Tx(Hash::SHA256([1; 32])),
+        InventoryEntry::Block(Hash::SHA256([2; 32])),
+        InventoryEntry::DataRequest(Hash::SHA256([3; 32])),
+        InventoryEntry::Tally(Hash::SHA256([4; 32])),
+    ];
+
+    for entry in entries {
+        let entry_bytes = entry.to_pb_bytes().unwrap();
+
+        assert_eq!(InventoryEntry::from_pb_bytes(&entry_bytes).unwrap(), entry);
+    }
+}
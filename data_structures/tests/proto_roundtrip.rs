@@ -0,0 +1,502 @@
+//! Property tests asserting that every `ProtobufConvert` implementation round-trips:
+//! `from_pb(to_pb(x)) == x` and `from_pb_bytes(to_pb_bytes(x)) == x`
+use std::fmt::Debug;
+
+use proptest::{collection::vec, prelude::*, test_runner::TestCaseError};
+
+use witnet_data_structures::{chain::*, proto::ProtobufConvert, types::*, wit::Wit};
+
+/// Check both the `ProtoStruct` and the bytes round trips of a value
+fn assert_roundtrip<T>(x: &T) -> Result<(), TestCaseError>
+where
+    T: ProtobufConvert + PartialEq + Debug,
+    T::ProtoStruct: protobuf::Message,
+{
+    prop_assert_eq!(&T::from_pb(x.to_pb()).unwrap(), x);
+    prop_assert_eq!(&T::from_pb_bytes(&x.to_pb_bytes().unwrap()).unwrap(), x);
+
+    Ok(())
+}
+
+////////////////////////////////////////////////////////////////////////////////////////
+// CHAIN STRATEGIES
+////////////////////////////////////////////////////////////////////////////////////////
+prop_compose! {
+    fn arb_hash()(bytes in any::<[u8; 32]>()) -> Hash {
+        Hash::SHA256(bytes)
+    }
+}
+
+prop_compose! {
+    fn arb_checkpoint_beacon()(checkpoint in any::<u32>(), hash_prev_block in arb_hash())
+        -> CheckpointBeacon {
+        CheckpointBeacon { checkpoint, hash_prev_block }
+    }
+}
+
+prop_compose! {
+    fn arb_signature()(
+        der in vec(any::<u8>(), 0..80),
+        recoverable in vec(any::<u8>(), 0..80),
+        multisig in vec(any::<u8>(), 0..80),
+        bls in vec(any::<u8>(), 0..100),
+        kind in 0..4u8
+    ) -> Signature {
+        match kind {
+            0 => Signature::Secp256k1(Secp256k1Signature { der }),
+            1 => Signature::Secp256k1Recoverable(
+                Secp256k1RecoverableSignature { signature: recoverable }
+            ),
+            2 => Signature::Secp256k1Multisig(Secp256k1MultiSignature { signature: multisig }),
+            _ => Signature::Bls(BlsSignature { signature: bls }),
+        }
+    }
+}
+
+prop_compose! {
+    fn arb_public_key()(compressed in any::<u8>(), bytes in any::<[u8; 32]>()) -> PublicKey {
+        PublicKey { compressed, bytes }
+    }
+}
+
+prop_compose! {
+    fn arb_keyed_signature()(signature in arb_signature(), public_key in arb_public_key())
+        -> KeyedSignature {
+        KeyedSignature { signature, public_key }
+    }
+}
+
+prop_compose! {
+    fn arb_vrf_proof()(proof in vec(any::<u8>(), 0..100), public_key in arb_public_key())
+        -> VrfProof {
+        VrfProof { proof, public_key }
+    }
+}
+
+prop_compose! {
+    fn arb_block_header()(
+        version in any::<u32>(),
+        beacon in arb_checkpoint_beacon(),
+        hash_merkle_root in arb_hash()
+    ) -> BlockHeader {
+        BlockHeader { version, beacon, hash_merkle_root }
+    }
+}
+
+fn arb_input() -> impl Strategy<Value = Input> {
+    prop_oneof![
+        (arb_hash(), any::<u32>(), any::<u64>()).prop_map(
+            |(transaction_id, output_index, nonce)| {
+                Input::Commit(CommitInput {
+                    transaction_id,
+                    output_index,
+                    nonce,
+                })
+            }
+        ),
+        (arb_hash(), any::<u32>(), any::<[u8; 32]>()).prop_map(
+            |(transaction_id, output_index, poe)| {
+                Input::DataRequest(DataRequestInput {
+                    transaction_id,
+                    output_index,
+                    poe,
+                })
+            }
+        ),
+        (arb_hash(), any::<u32>()).prop_map(|(transaction_id, output_index)| {
+            Input::Reveal(RevealInput {
+                transaction_id,
+                output_index,
+            })
+        }),
+        (arb_hash(), any::<u32>()).prop_map(|(transaction_id, output_index)| {
+            Input::ValueTransfer(ValueTransferInput {
+                transaction_id,
+                output_index,
+            })
+        }),
+    ]
+}
+
+prop_compose! {
+    fn arb_rad_retrieve()(
+        post in any::<bool>(),
+        url in "\\PC*",
+        script in vec(any::<u8>(), 0..16),
+        max_response_size in any::<u64>(),
+        content_type in "\\PC*",
+        certificate_fingerprint in vec(any::<u8>(), 0..32),
+        timeout in any::<u64>(),
+        body in vec(any::<u8>(), 0..32),
+        body_content_type in "\\PC*"
+    ) -> RADRetrieve {
+        RADRetrieve {
+            kind: if post { RADType::HttpPost } else { RADType::HttpGet },
+            url,
+            script,
+            max_response_size,
+            content_type,
+            certificate_fingerprint,
+            timeout,
+            body,
+            body_content_type,
+        }
+    }
+}
+
+prop_compose! {
+    fn arb_rad_request()(
+        not_before in any::<u64>(),
+        retrieve in vec(arb_rad_retrieve(), 0..3),
+        aggregate in vec(any::<u8>(), 0..16),
+        consensus in vec(any::<u8>(), 0..16),
+        deliver in vec("\\PC*", 0..3)
+    ) -> RADRequest {
+        RADRequest {
+            not_before,
+            retrieve,
+            aggregate: RADAggregate { script: aggregate },
+            consensus: RADConsensus { script: consensus },
+            deliver: deliver
+                .into_iter()
+                .map(|url| RADDeliver { kind: RADType::HttpGet, url })
+                .collect(),
+        }
+    }
+}
+
+fn arb_wit() -> impl Strategy<Value = Wit> {
+    any::<u64>().prop_map(Wit::from_nanowits)
+}
+
+fn arb_pkh() -> impl Strategy<Value = PublicKeyHash> {
+    any::<[u8; 20]>().prop_map(PublicKeyHash::from_bytes)
+}
+
+prop_compose! {
+    fn arb_data_request_output()(
+        pkh in arb_pkh(),
+        data_request in arb_rad_request(),
+        value in arb_wit(),
+        witnesses in any::<u16>(),
+        backup_witnesses in any::<u16>(),
+        fees in (arb_wit(), arb_wit(), arb_wit()),
+        time_lock in any::<u64>()
+    ) -> DataRequestOutput {
+        DataRequestOutput {
+            pkh,
+            data_request,
+            value,
+            witnesses,
+            backup_witnesses,
+            commit_fee: fees.0,
+            reveal_fee: fees.1,
+            tally_fee: fees.2,
+            time_lock,
+        }
+    }
+}
+
+fn arb_output() -> impl Strategy<Value = Output> {
+    prop_oneof![
+        (arb_pkh(), arb_wit())
+            .prop_map(|(pkh, value)| Output::ValueTransfer(ValueTransferOutput { pkh, value })),
+        arb_data_request_output().prop_map(Output::DataRequest),
+        (arb_hash(), arb_wit())
+            .prop_map(|(commitment, value)| Output::Commit(CommitOutput { commitment, value })),
+        (vec(any::<u8>(), 0..32), arb_pkh(), arb_wit())
+            .prop_map(|(reveal, pkh, value)| Output::Reveal(RevealOutput { reveal, pkh, value })),
+        (vec(any::<u8>(), 0..32), arb_pkh(), arb_wit())
+            .prop_map(|(result, pkh, value)| Output::Tally(TallyOutput { result, pkh, value })),
+    ]
+}
+
+prop_compose! {
+    fn arb_transaction_body()(
+        version in any::<u32>(),
+        inputs in vec(arb_input(), 0..4),
+        outputs in vec(arb_output(), 0..4)
+    ) -> TransactionBody {
+        TransactionBody::new(version, inputs, outputs)
+    }
+}
+
+prop_compose! {
+    fn arb_transaction()(
+        body in arb_transaction_body(),
+        signatures in vec(arb_keyed_signature(), 0..4)
+    ) -> Transaction {
+        Transaction::new(body, signatures)
+    }
+}
+
+prop_compose! {
+    fn arb_block()(
+        block_header in arb_block_header(),
+        block_sig in arb_keyed_signature(),
+        proof in arb_vrf_proof(),
+        txns in vec(arb_transaction(), 0..4)
+    ) -> Block {
+        Block { block_header, proof: LeadershipProof { block_sig, proof }, txns }
+    }
+}
+
+prop_compose! {
+    fn arb_super_block()(
+        index in any::<u32>(),
+        hashes in (arb_hash(), arb_hash(), arb_hash()),
+        signatures in vec(arb_keyed_signature(), 0..4)
+    ) -> SuperBlock {
+        SuperBlock {
+            index,
+            last_block: hashes.0,
+            blocks_merkle_root: hashes.1,
+            tallies_merkle_root: hashes.2,
+            signatures,
+        }
+    }
+}
+
+prop_compose! {
+    fn arb_consensus_constants()(
+        checkpoint_zero_timestamp in any::<i64>(),
+        checkpoints_period in any::<u16>(),
+        genesis_hash in arb_hash(),
+        // NaN is not equal to itself, so only finite values are generated
+        reputation_demurrage in -1e9f64..1e9,
+        reputation_punishment in -1e9f64..1e9,
+        max_block_weight in any::<u32>(),
+        min_protocol_version in any::<u32>()
+    ) -> ConsensusConstants {
+        ConsensusConstants {
+            checkpoint_zero_timestamp,
+            checkpoints_period,
+            genesis_hash,
+            reputation_demurrage,
+            reputation_punishment,
+            max_block_weight,
+            min_protocol_version,
+        }
+    }
+}
+
+prop_compose! {
+    fn arb_output_pointer()(transaction_id in arb_hash(), output_index in any::<u32>())
+        -> OutputPointer {
+        OutputPointer { transaction_id, output_index }
+    }
+}
+
+prop_compose! {
+    fn arb_unspent_outputs_pool()(
+        outputs in vec((arb_output_pointer(), arb_output()), 0..4)
+    ) -> UnspentOutputsPool {
+        let mut pool = UnspentOutputsPool::new();
+        for (output_pointer, output) in outputs {
+            pool.insert(output_pointer, output);
+        }
+
+        pool
+    }
+}
+
+fn arb_inventory_entry() -> impl Strategy<Value = InventoryEntry> {
+    prop_oneof![
+        arb_hash().prop_map(InventoryEntry::Error),
+        arb_hash().prop_map(InventoryEntry::Tx),
+        arb_hash().prop_map(InventoryEntry::Block),
+        arb_hash().prop_map(InventoryEntry::DataRequest),
+        arb_hash().prop_map(InventoryEntry::Tally),
+    ]
+}
+
+////////////////////////////////////////////////////////////////////////////////////////
+// PROTOCOL MESSAGES STRATEGIES
+////////////////////////////////////////////////////////////////////////////////////////
+fn arb_address() -> impl Strategy<Value = Address> {
+    let ip = prop_oneof![
+        any::<u32>().prop_map(|ip| IpAddress::Ipv4 { ip }),
+        any::<(u32, u32, u32, u32)>().prop_map(|(ip0, ip1, ip2, ip3)| IpAddress::Ipv6 {
+            ip0,
+            ip1,
+            ip2,
+            ip3
+        }),
+    ];
+
+    (ip, any::<u16>()).prop_map(|(ip, port)| Address { ip, port })
+}
+
+prop_compose! {
+    fn arb_version()(
+        numbers in any::<(u32, i64, u64)>(),
+        sender_address in arb_address(),
+        receiver_address in arb_address(),
+        user_agent in "\\PC*",
+        last_epoch in any::<u32>(),
+        nonce in any::<u64>(),
+        ephemeral_key in vec(any::<u8>(), 0..34),
+        consensus_constants in vec(any::<u8>(), 0..33)
+    ) -> Version {
+        Version {
+            version: numbers.0,
+            timestamp: numbers.1,
+            capabilities: numbers.2,
+            sender_address,
+            receiver_address,
+            user_agent,
+            last_epoch,
+            nonce,
+            ephemeral_key,
+            consensus_constants,
+        }
+    }
+}
+
+fn arb_command() -> impl Strategy<Value = Command> {
+    prop_oneof![
+        Just(Command::GetPeers(GetPeers)),
+        vec(arb_address(), 0..8).prop_map(|peers| Command::Peers(Peers { peers })),
+        any::<u64>().prop_map(|nonce| Command::Ping(Ping { nonce })),
+        any::<u64>().prop_map(|nonce| Command::Pong(Pong { nonce })),
+        Just(Command::Verack(Verack)),
+        arb_version().prop_map(Command::Version),
+        ("\\PC*", any::<u32>()).prop_map(|(reason, min_version)| {
+            Command::Reject(Reject {
+                reason,
+                min_version,
+            })
+        }),
+        arb_block().prop_map(Command::Block),
+        arb_transaction().prop_map(Command::Transaction),
+        vec(arb_inventory_entry(), 0..8).prop_map(|inventory| {
+            Command::InventoryAnnouncement(InventoryAnnouncement { inventory })
+        }),
+        vec(arb_inventory_entry(), 0..8)
+            .prop_map(|inventory| Command::InventoryRequest(InventoryRequest { inventory })),
+        arb_checkpoint_beacon().prop_map(|highest_block_checkpoint| {
+            Command::LastBeacon(LastBeacon {
+                highest_block_checkpoint,
+            })
+        }),
+        vec(arb_hash(), 0..8)
+            .prop_map(|locator_hashes| Command::GetBlocks(GetBlocks { locator_hashes })),
+        vec(arb_block_header(), 0..8).prop_map(|headers| Command::Headers(Headers { headers })),
+    ]
+}
+
+prop_compose! {
+    fn arb_message()(kind in arb_command(), magic in any::<u16>()) -> Message {
+        Message { kind, magic }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////
+// ROUND TRIP PROPERTIES
+////////////////////////////////////////////////////////////////////////////////////////
+proptest! {
+    #[test]
+    fn hash_roundtrip(x in arb_hash()) {
+        assert_roundtrip(&x)?;
+    }
+
+    #[test]
+    fn checkpoint_beacon_roundtrip(x in arb_checkpoint_beacon()) {
+        assert_roundtrip(&x)?;
+    }
+
+    #[test]
+    fn signature_roundtrip(x in arb_signature()) {
+        assert_roundtrip(&x)?;
+    }
+
+    #[test]
+    fn public_key_roundtrip(x in arb_public_key()) {
+        assert_roundtrip(&x)?;
+    }
+
+    #[test]
+    fn keyed_signature_roundtrip(x in arb_keyed_signature()) {
+        assert_roundtrip(&x)?;
+    }
+
+    #[test]
+    fn vrf_proof_roundtrip(x in arb_vrf_proof()) {
+        assert_roundtrip(&x)?;
+    }
+
+    #[test]
+    fn block_header_roundtrip(x in arb_block_header()) {
+        assert_roundtrip(&x)?;
+    }
+
+    #[test]
+    fn input_roundtrip(x in arb_input()) {
+        assert_roundtrip(&x)?;
+    }
+
+    #[test]
+    fn output_roundtrip(x in arb_output()) {
+        assert_roundtrip(&x)?;
+    }
+
+    #[test]
+    fn rad_request_roundtrip(x in arb_rad_request()) {
+        assert_roundtrip(&x)?;
+    }
+
+    #[test]
+    fn transaction_body_roundtrip(x in arb_transaction_body()) {
+        assert_roundtrip(&x)?;
+    }
+
+    #[test]
+    fn transaction_roundtrip(x in arb_transaction()) {
+        assert_roundtrip(&x)?;
+    }
+
+    #[test]
+    fn block_roundtrip(x in arb_block()) {
+        assert_roundtrip(&x)?;
+    }
+
+    #[test]
+    fn super_block_roundtrip(x in arb_super_block()) {
+        assert_roundtrip(&x)?;
+    }
+
+    #[test]
+    fn consensus_constants_roundtrip(x in arb_consensus_constants()) {
+        assert_roundtrip(&x)?;
+    }
+
+    #[test]
+    fn output_pointer_roundtrip(x in arb_output_pointer()) {
+        assert_roundtrip(&x)?;
+    }
+
+    #[test]
+    fn unspent_outputs_pool_roundtrip(x in arb_unspent_outputs_pool()) {
+        assert_roundtrip(&x)?;
+    }
+
+    #[test]
+    fn inventory_entry_roundtrip(x in arb_inventory_entry()) {
+        assert_roundtrip(&x)?;
+    }
+
+    #[test]
+    fn address_roundtrip(x in arb_address()) {
+        assert_roundtrip(&x)?;
+    }
+
+    #[test]
+    fn version_roundtrip(x in arb_version()) {
+        assert_roundtrip(&x)?;
+    }
+
+    #[test]
+    fn message_roundtrip(x in arb_message()) {
+        assert_roundtrip(&x)?;
+    }
+}
@@ -0,0 +1,134 @@
+//! Embed build metadata into the node so that deployments can be identified precisely.
+//!
+//! Only inputs that are stable across rebuilds of the same source tree are used, so the
+//! resulting metadata is deterministic.
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Short hash of the git commit the node is built from, or "unknown" if it cannot be read.
+/// Builds made from a source tarball can provide it through the `WITNET_GIT_HASH` variable.
+fn git_hash() -> String {
+    if let Ok(hash) = env::var("WITNET_GIT_HASH") {
+        return hash;
+    }
+
+    Command::new("git")
+        .args(&["rev-parse", "--short=12", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|hash| hash.trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Path of the git directory of the repository, if the node is built from one
+fn git_dir(manifest_dir: &Path) -> Option<PathBuf> {
+    Command::new("git")
+        .args(&["rev-parse", "--git-dir"])
+        .current_dir(manifest_dir)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|dir| manifest_dir.join(dir.trim()))
+}
+
+/// Files that change whenever the commit checked out does: `HEAD` itself, the branch it points
+/// to and the packed refs, where the branch is moved to by `git gc` or `git pack-refs`. The
+/// directory of the branch is watched as well, because a packed branch gets written back to it
+/// as a loose ref on the next commit.
+fn git_watched_files(git_dir: &Path) -> Vec<PathBuf> {
+    let head = git_dir.join("HEAD");
+    let mut files = vec![git_dir.join("packed-refs")];
+    if let Ok(content) = fs::read_to_string(&head) {
+        if content.starts_with("ref: ") {
+            let branch = git_dir.join(content["ref: ".len()..].trim());
+            files.extend(branch.parent().map(Path::to_path_buf));
+            files.push(branch);
+        }
+    }
+    files.push(head);
+
+    // Watching a file that does not exist would make the build script run on every build
+    files.into_iter().filter(|file| file.exists()).collect()
+}
+
+/// Comma-separated, sorted list of the cargo features this crate is built with
+fn features() -> String {
+    let mut features: Vec<String> = env::vars()
+        .filter_map(|(key, _)| {
+            if key.starts_with("CARGO_FEATURE_") {
+                Some(
+                    key["CARGO_FEATURE_".len()..]
+                        .to_lowercase()
+                        .replace('_', "-"),
+                )
+            } else {
+                None
+            }
+        })
+        .collect();
+    features.sort();
+
+    features.join(",")
+}
+
+/// Parsed manifest of a crate, or `None` if it cannot be read
+fn read_manifest(path: &Path) -> Option<toml::Value> {
+    fs::read_to_string(path).ok()?.parse().ok()
+}
+
+/// Value of a string field of the `[package]` section of a manifest
+fn package_field<'a>(manifest: &'a toml::Value, key: &str) -> Option<&'a str> {
+    manifest.get("package")?.get(key)?.as_str()
+}
+
+/// Comma-separated, sorted list of `name=version` pairs of the workspace crates the node
+/// depends on, read from the manifests of the dependencies that have a `path` in this one
+fn crate_versions(manifest_dir: &Path) -> String {
+    let manifest = read_manifest(&manifest_dir.join("Cargo.toml"));
+    let dependencies = manifest
+        .as_ref()
+        .and_then(|manifest| manifest.get("dependencies"))
+        .and_then(toml::Value::as_table);
+    let mut versions: Vec<String> = dependencies
+        .into_iter()
+        .flat_map(|dependencies| dependencies.values())
+        .filter_map(|dependency| {
+            let path = dependency.get("path")?.as_str()?;
+            let dependency_manifest = manifest_dir.join(path).join("Cargo.toml");
+            println!("cargo:rerun-if-changed={}", dependency_manifest.display());
+            let dependency = read_manifest(&dependency_manifest)?;
+
+            Some(format!(
+                "{}={}",
+                package_field(&dependency, "name")?,
+                package_field(&dependency, "version")?
+            ))
+        })
+        .collect();
+    versions.sort();
+
+    versions.join(",")
+}
+
+fn main() {
+    let manifest_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+
+    println!("cargo:rustc-env=WITNET_GIT_HASH={}", git_hash());
+    println!("cargo:rustc-env=WITNET_FEATURES={}", features());
+    println!(
+        "cargo:rustc-env=WITNET_CRATE_VERSIONS={}",
+        crate_versions(&manifest_dir)
+    );
+    println!("cargo:rerun-if-env-changed=WITNET_GIT_HASH");
+    println!("cargo:rerun-if-changed=Cargo.toml");
+    if let Some(git_dir) = git_dir(&manifest_dir) {
+        for file in git_watched_files(&git_dir) {
+            println!("cargo:rerun-if-changed={}", file.display());
+        }
+    }
+}
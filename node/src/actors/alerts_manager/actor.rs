@@ -0,0 +1,17 @@
+use actix::{Actor, Context};
+use log::debug;
+
+use super::AlertsManager;
+
+/// Make actor from AlertsManager
+impl Actor for AlertsManager {
+    /// Every actor has to provide execution `Context` in which it can run.
+    type Context = Context<Self>;
+
+    /// Method to be executed when the actor is started
+    fn started(&mut self, ctx: &mut Self::Context) {
+        debug!("Alerts Manager actor has been started!");
+
+        self.start_checks(ctx);
+    }
+}
@@ -0,0 +1,192 @@
+//! # Alerts
+//!
+//! Alerts notify the operator about conditions that need attention. Every kind of alert is
+//! notified when its condition is detected, repeated while it persists (at most once every
+//! `repeat_period`), and notified again with `Info` severity when the condition is resolved.
+use std::{collections::HashMap, fmt, time::Duration};
+
+use serde::Serialize;
+
+/// Severity of an alert
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Informative notification, e.g. a resolved condition
+    Info,
+    /// Condition that may need the attention of the operator
+    Warning,
+    /// Condition that prevents the node from working properly
+    Critical,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Critical => "critical",
+        };
+
+        write!(f, "{}", severity)
+    }
+}
+
+/// Conditions monitored by the `AlertsManager`
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertKind {
+    /// No connected peers
+    NoPeers,
+    /// The chain tip has not changed for a long time
+    StalledChain,
+    /// The disk of the database is almost full
+    LowDiskSpace,
+    /// The local clock differs from the clocks of the peers
+    ClockDrift,
+}
+
+impl fmt::Display for AlertKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self {
+            AlertKind::NoPeers => "no_peers",
+            AlertKind::StalledChain => "stalled_chain",
+            AlertKind::LowDiskSpace => "low_disk_space",
+            AlertKind::ClockDrift => "clock_drift",
+        };
+
+        write!(f, "{}", kind)
+    }
+}
+
+/// Notification of an alert
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct Alert {
+    /// Monitored condition
+    pub kind: AlertKind,
+    /// Severity of the alert
+    pub severity: Severity,
+    /// Human readable description
+    pub message: String,
+    /// Timestamp of the notification
+    pub timestamp: i64,
+}
+
+/// Deduplication of the alerts of every monitored condition
+#[derive(Debug, Default)]
+pub struct AlertsState {
+    /// Minimum time between two notifications of the same firing alert, in seconds
+    repeat_period: i64,
+    /// Timestamp of the last notification of every firing alert
+    firing: HashMap<AlertKind, i64>,
+}
+
+impl AlertsState {
+    /// Create an empty state that repeats firing alerts every `repeat_period`
+    pub fn new(repeat_period: Duration) -> Self {
+        AlertsState {
+            repeat_period: repeat_period.as_secs() as i64,
+            firing: HashMap::new(),
+        }
+    }
+
+    /// Update the state of a condition: `Some` with the severity and the description of the
+    /// alert if the condition is detected, `None` otherwise. Returns the alert to notify, if any.
+    pub fn update(
+        &mut self,
+        kind: AlertKind,
+        condition: Option<(Severity, String)>,
+        now: i64,
+    ) -> Option<Alert> {
+        match condition {
+            Some((severity, message)) => {
+                if let Some(last_notification) = self.firing.get(&kind) {
+                    if now - last_notification < self.repeat_period {
+                        return None;
+                    }
+                }
+                self.firing.insert(kind, now);
+
+                Some(Alert {
+                    kind,
+                    severity,
+                    message,
+                    timestamp: now,
+                })
+            }
+            None => self.firing.remove(&kind).map(|_| Alert {
+                kind,
+                severity: Severity::Info,
+                message: format!("Resolved: {}", kind),
+                timestamp: now,
+            }),
+        }
+    }
+}
+
+/// Median of a list of values, `None` if it is empty
+pub fn median(values: &[i64]) -> Option<i64> {
+    let mut values = values.to_vec();
+    values.sort_unstable();
+    let len = values.len();
+
+    match len {
+        0 => None,
+        len if len % 2 == 0 => Some((values[len / 2 - 1] + values[len / 2]) / 2),
+        len => Some(values[len / 2]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn no_peers() -> Option<(Severity, String)> {
+        Some((Severity::Critical, "No peers".to_string()))
+    }
+
+    #[test]
+    fn alerts_are_deduplicated() {
+        let mut state = AlertsState::new(Duration::from_secs(100));
+
+        let alert = state.update(AlertKind::NoPeers, no_peers(), 1000).unwrap();
+        assert_eq!(alert.severity, Severity::Critical);
+        assert_eq!(alert.timestamp, 1000);
+
+        // The condition persists, but it was already notified
+        assert_eq!(state.update(AlertKind::NoPeers, no_peers(), 1050), None);
+        // Other conditions are notified independently
+        assert!(state
+            .update(
+                AlertKind::ClockDrift,
+                Some((Severity::Warning, String::new())),
+                1050
+            )
+            .is_some());
+        // Repeated after the repeat period
+        assert!(state.update(AlertKind::NoPeers, no_peers(), 1100).is_some());
+    }
+
+    #[test]
+    fn alerts_are_resolved() {
+        let mut state = AlertsState::new(Duration::from_secs(100));
+
+        assert_eq!(state.update(AlertKind::StalledChain, None, 1000), None);
+        assert!(state.update(AlertKind::NoPeers, no_peers(), 1000).is_some());
+
+        let resolved = state.update(AlertKind::NoPeers, None, 1010).unwrap();
+        assert_eq!(resolved.severity, Severity::Info);
+        assert_eq!(resolved.message, "Resolved: no_peers");
+        assert_eq!(state.update(AlertKind::NoPeers, None, 1020), None);
+
+        // Detected again right after being resolved
+        assert!(state.update(AlertKind::NoPeers, no_peers(), 1030).is_some());
+    }
+
+    #[test]
+    fn median_of_offsets() {
+        assert_eq!(median(&[]), None);
+        assert_eq!(median(&[5]), Some(5));
+        assert_eq!(median(&[30, -2, 1]), Some(1));
+        assert_eq!(median(&[4, 1, 2, 100]), Some(3));
+    }
+}
@@ -0,0 +1,18 @@
+use actix::Handler;
+
+use super::{AlertsManager, CLOCK_OFFSET_SAMPLES};
+use crate::actors::messages::PeerClockOffset;
+
+/// Handler for PeerClockOffset message
+impl Handler<PeerClockOffset> for AlertsManager {
+    type Result = ();
+
+    fn handle(&mut self, msg: PeerClockOffset, _ctx: &mut Self::Context) {
+        log::trace!("Clock offset with peer {}: {}s", msg.address, msg.offset);
+
+        if self.clock_offsets.len() == CLOCK_OFFSET_SAMPLES {
+            self.clock_offsets.pop_front();
+        }
+        self.clock_offsets.push_back(msg.offset);
+    }
+}
@@ -0,0 +1,289 @@
+use std::{
+    collections::VecDeque,
+    path::PathBuf,
+    process::{Command, Stdio},
+    thread,
+    time::Duration,
+};
+
+use actix::prelude::*;
+use futures::Future;
+use log;
+use reqwest::r#async::Client;
+
+use crate::actors::{
+    chain_manager::ChainManager,
+    messages::{GetConsolidatedSessionsCount, GetHighestCheckpointBeacon},
+    sessions_manager::SessionsManager,
+};
+use crate::config_mngr;
+
+use witnet_config::config::Alerts;
+use witnet_data_structures::chain::CheckpointBeacon;
+use witnet_util::{disk::available_space, timestamp::get_timestamp};
+
+use self::alerts::{median, Alert, AlertKind, AlertsState, Severity};
+
+pub mod alerts;
+
+mod actor;
+mod handlers;
+
+/// Number of peer clock offsets used to detect a drift of the local clock
+pub const CLOCK_OFFSET_SAMPLES: usize = 16;
+
+/// Minimum number of peer clock offsets needed to detect a drift of the local clock
+pub const MIN_CLOCK_OFFSET_SAMPLES: usize = 3;
+
+/// Alerts manager actor: periodically checks conditions that need the attention of the operator
+/// and notifies them through the configured channels.
+///
+/// The monitored conditions are:
+///   + No connected peers for longer than `alerts.no_peers_timeout`
+///   + No changes in the chain tip for longer than `alerts.stalled_chain_timeout`
+///   + Less than `alerts.min_free_disk_bytes` available in the disk of the database
+///   + Local clock differing more than `alerts.max_clock_drift` from the clocks of the peers
+///
+/// Alerts are always logged, and optionally posted to a webhook or passed to a shell command.
+#[derive(Default)]
+pub struct AlertsManager {
+    /// Alerts configuration
+    config: Option<Alerts>,
+    /// Directory of the database, used to check the available disk space
+    db_path: PathBuf,
+    /// Deduplication of the notified alerts
+    state: AlertsState,
+    /// Timestamp of the last check with connected peers
+    last_peers_timestamp: i64,
+    /// Last known chain tip and the timestamp when it was first seen
+    last_beacon: Option<(CheckpointBeacon, i64)>,
+    /// Latest offsets between the local clock and the clocks of the peers, in seconds
+    clock_offsets: VecDeque<i64>,
+}
+
+/// Required trait for being able to retrieve alerts manager address from system registry
+impl actix::Supervised for AlertsManager {}
+
+/// Required trait for being able to retrieve alerts manager address from system registry
+impl SystemService for AlertsManager {}
+
+/// Auxiliary methods for AlertsManager actor
+impl AlertsManager {
+    fn start_checks(&mut self, ctx: &mut <Self as Actor>::Context) {
+        config_mngr::get()
+            .into_actor(self)
+            .and_then(|config, act, ctx| {
+                if !config.alerts.enabled {
+                    log::debug!("AlertsManager explicitly disabled by configuration.");
+                    return fut::ok(());
+                }
+
+                let check_period = config.alerts.check_period;
+                act.state = AlertsState::new(config.alerts.repeat_period);
+                act.db_path = config.storage.db_path.clone();
+                act.last_peers_timestamp = get_timestamp();
+                act.config = Some(config.alerts.clone());
+
+                act.check_alerts(ctx, check_period);
+
+                fut::ok(())
+            })
+            .map_err(|err, _, _| log::error!("AlertsManager failed to start: {}", err))
+            .wait(ctx);
+    }
+
+    /// Method to periodically check the alert conditions
+    fn check_alerts(&self, ctx: &mut Context<Self>, check_period: Duration) {
+        ctx.run_later(check_period, move |act, ctx| {
+            act.check_disk_space();
+            act.check_clock_drift();
+            act.check_peers(ctx);
+            act.check_chain_tip(ctx);
+
+            act.check_alerts(ctx, check_period);
+        });
+    }
+
+    fn check_peers(&mut self, ctx: &mut Context<Self>) {
+        System::current()
+            .registry()
+            .get::<SessionsManager>()
+            .send(GetConsolidatedSessionsCount)
+            .into_actor(self)
+            .map_err(|err, _, _| {
+                log::error!("Unsuccessful communication with sessions manager: {}", err)
+            })
+            .map(|num_sessions, act, _ctx| {
+                let now = get_timestamp();
+                if num_sessions > 0 {
+                    act.last_peers_timestamp = now;
+                }
+                let timeout = act.config().no_peers_timeout.as_secs() as i64;
+                let elapsed = now - act.last_peers_timestamp;
+
+                let condition = if elapsed > timeout {
+                    Some((
+                        Severity::Critical,
+                        format!("No connected peers for {} seconds", elapsed),
+                    ))
+                } else {
+                    None
+                };
+                act.update(AlertKind::NoPeers, condition);
+            })
+            .spawn(ctx);
+    }
+
+    fn check_chain_tip(&mut self, ctx: &mut Context<Self>) {
+        System::current()
+            .registry()
+            .get::<ChainManager>()
+            .send(GetHighestCheckpointBeacon)
+            .into_actor(self)
+            .map_err(|err, _, _| {
+                log::error!("Unsuccessful communication with chain manager: {}", err)
+            })
+            .map(|res, act, _ctx| {
+                // The chain tip is not available before the chain state is initialized
+                let beacon = match res {
+                    Ok(beacon) => beacon,
+                    Err(_) => return,
+                };
+                let now = get_timestamp();
+                let since = match act.last_beacon {
+                    Some((last_beacon, since)) if last_beacon == beacon => since,
+                    _ => now,
+                };
+                act.last_beacon = Some((beacon, since));
+                let timeout = act.config().stalled_chain_timeout.as_secs() as i64;
+                let elapsed = now - since;
+
+                let condition = if elapsed > timeout {
+                    Some((
+                        Severity::Critical,
+                        format!(
+                            "The chain tip has been stuck at epoch {} for {} seconds",
+                            beacon.checkpoint, elapsed
+                        ),
+                    ))
+                } else {
+                    None
+                };
+                act.update(AlertKind::StalledChain, condition);
+            })
+            .spawn(ctx);
+    }
+
+    fn check_disk_space(&mut self) {
+        let min_free_disk_bytes = self.config().min_free_disk_bytes;
+        if min_free_disk_bytes == 0 {
+            return;
+        }
+
+        let condition = match available_space(&self.db_path) {
+            Ok(available) if available < min_free_disk_bytes => Some((
+                Severity::Warning,
+                format!(
+                    "Only {} bytes available in the disk of the database ({})",
+                    available,
+                    self.db_path.display()
+                ),
+            )),
+            Ok(_) => None,
+            Err(err) => {
+                log::debug!("Cannot check the available disk space: {}", err);
+                return;
+            }
+        };
+        self.update(AlertKind::LowDiskSpace, condition);
+    }
+
+    fn check_clock_drift(&mut self) {
+        if self.clock_offsets.len() < MIN_CLOCK_OFFSET_SAMPLES {
+            return;
+        }
+        let offsets: Vec<i64> = self.clock_offsets.iter().cloned().collect();
+        let offset = median(&offsets).unwrap_or_default();
+        let max_clock_drift = self.config().max_clock_drift.as_secs() as i64;
+
+        let condition = if offset.abs() > max_clock_drift {
+            Some((
+                Severity::Warning,
+                format!(
+                    "The local clock differs by {} seconds from the clocks of the peers",
+                    offset
+                ),
+            ))
+        } else {
+            None
+        };
+        self.update(AlertKind::ClockDrift, condition);
+    }
+
+    fn config(&self) -> &Alerts {
+        // Checks are only scheduled after the configuration is set
+        self.config.as_ref().unwrap()
+    }
+
+    /// Method to update the state of a condition and notify the resulting alert
+    fn update(&mut self, kind: AlertKind, condition: Option<(Severity, String)>) {
+        if let Some(alert) = self.state.update(kind, condition, get_timestamp()) {
+            self.notify(alert);
+        }
+    }
+
+    /// Method to dispatch an alert to all the configured channels
+    fn notify(&self, alert: Alert) {
+        match alert.severity {
+            Severity::Info => log::info!("[ALERT] {}: {}", alert.kind, alert.message),
+            Severity::Warning => log::warn!("[ALERT] {}: {}", alert.kind, alert.message),
+            Severity::Critical => log::error!("[ALERT] {}: {}", alert.kind, alert.message),
+        }
+
+        if let Some(webhook_url) = &self.config().webhook_url {
+            let webhook_url = webhook_url.clone();
+            Arbiter::spawn(
+                Client::new()
+                    .post(webhook_url.as_str())
+                    .json(&alert)
+                    .send()
+                    .and_then(|response| response.error_for_status())
+                    .map(|_| ())
+                    .map_err(move |err| {
+                        log::warn!("Failed to post alert to {}: {}", webhook_url, err)
+                    }),
+            );
+        }
+
+        if let Some(command) = &self.config().command {
+            run_command(command, &alert);
+        }
+    }
+}
+
+/// Run the alert command in a shell, without waiting for it to finish
+fn run_command(command: &str, alert: &Alert) {
+    let child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .env("WITNET_ALERT_KIND", alert.kind.to_string())
+        .env("WITNET_ALERT_SEVERITY", alert.severity.to_string())
+        .env("WITNET_ALERT_MESSAGE", &alert.message)
+        .env("WITNET_ALERT_TIMESTAMP", alert.timestamp.to_string())
+        .stdin(Stdio::null())
+        .spawn();
+
+    match child {
+        // Reap the child process when it finishes
+        Ok(mut child) => {
+            thread::spawn(move || match child.wait() {
+                Ok(status) if !status.success() => {
+                    log::warn!("Alert command finished with {}", status)
+                }
+                Ok(_) => {}
+                Err(err) => log::warn!("Failed to wait for the alert command: {}", err),
+            });
+        }
+        Err(err) => log::warn!("Failed to run the alert command: {}", err),
+    }
+}
@@ -1,25 +1,38 @@
 // use actix::{Actor, ActorFuture, AsyncContext, Context, ContextFutureSpawner, System, WrapFuture};
+use std::{collections::VecDeque, fs, time::Duration};
+
 use actix::prelude::*;
 
+use futures::{future, stream, Future, Stream};
+
 use super::{
+    fast_sync::import_snapshot,
     handlers::{EpochPayload, EveryEpochPayload},
-    ChainManager,
+    relay::{RelayPolicy, MAX_SEEN_TRANSACTIONS, RELAY_ANNOUNCEMENT_PERIOD, SEEN_TRANSACTIONS_TTL},
+    sync::SYNC_TIMEOUTS_CHECK_PERIOD,
+    ChainManager, ChainManagerError,
 };
 use crate::actors::{
     epoch_manager::{EpochManager, EpochManagerError::CheckpointZeroInTheFuture},
-    messages::{GetEpoch, Subscribe},
-    storage_keys::CHAIN_STATE_KEY,
+    inventory_manager::InventoryManager,
+    messages::{GetEpoch, GetItem, Subscribe},
+    storage_keys::{chain_update_key, CHAIN_SNAPSHOT_KEY, CHAIN_STATE_KEY, MEMPOOL_KEY},
 };
 use crate::config_mngr;
 use crate::storage_mngr;
+use witnet_crypto::signature_cache::SignatureCache;
 use witnet_data_structures::{
-    chain::{Blockchain, ChainInfo, ChainState, CheckpointBeacon, UnspentOutputsPool},
+    chain::{
+        Block, Blockchain, ChainInfo, ChainState, ChainStateSnapshot, ChainStateUpdate,
+        CheckpointBeacon, EpochConstants, InventoryItem, Transaction, UnspentOutputsPool,
+    },
     data_request::DataRequestPool,
+    error::SnapshotError,
 };
 
 use witnet_util::timestamp::pretty_print;
 
-use log::{debug, error, warn};
+use log::{debug, error, info, warn};
 
 /// Implement Actor trait for `ChainManager`
 impl Actor for ChainManager {
@@ -33,7 +46,19 @@ impl Actor for ChainManager {
 
         self.initialize_from_storage(ctx);
 
+        self.restore_mempool(ctx);
+
         self.subscribe_to_epoch_manager(ctx);
+
+        ctx.run_interval(
+            Duration::from_secs(SYNC_TIMEOUTS_CHECK_PERIOD),
+            |act, _ctx| act.check_sync_timeouts(),
+        );
+
+        ctx.run_interval(
+            Duration::from_secs(RELAY_ANNOUNCEMENT_PERIOD),
+            |act, _ctx| act.announce_transactions(),
+        );
     }
 }
 
@@ -47,11 +72,29 @@ impl ChainManager {
             let consensus_constants = (&config.consensus_constants).clone();
 
             act.max_block_weight = consensus_constants.max_block_weight;
+            act.epoch_constants = Some(EpochConstants::from(&consensus_constants));
+            act.candidates_max_memory = config.memory.candidates_max_bytes;
+            act.sync_max_memory = config.memory.sync_max_bytes;
+            act.transactions_pool_max_memory = config.memory.mempool_max_bytes;
+            act.transactions_pool_max_weight = config.mempool.max_weight;
+            act.transactions_pool_expiry_epochs = config.mempool.expiry_epochs;
+            act.relay_policy = RelayPolicy::new(
+                config.mempool.min_relay_fee,
+                SEEN_TRANSACTIONS_TTL,
+                MAX_SEEN_TRANSACTIONS,
+            );
+            act.consensus_percentage = config.connections.consensus_percentage;
+            act.signature_cache = SignatureCache::new(config.memory.signature_cache_max_bytes);
+            act.snapshots_export_path = config.snapshots.export_path.clone();
+            if config.pruning.enabled {
+                act.pruning_keep_epochs = Some(config.pruning.keep_epochs);
+            }
+            let snapshots = config.snapshots.clone();
 
             storage_mngr::get::<_, ChainState>(&CHAIN_STATE_KEY)
                 .into_actor(act)
                 .map_err(|e, _, _| error!("Error while getting chain state from storage: {}", e))
-                .and_then(move |chain_state_from_storage, act, _ctx| {
+                .and_then(move |chain_state_from_storage, act, ctx| {
                     // chain_info_from_storage can be None if the storage does not contain that key
                     if chain_state_from_storage.is_some()
                         && chain_state_from_storage
@@ -69,6 +112,7 @@ impl ChainManager {
                                 // Update Chain Info from storage
                                 act.chain_state = chain_state_from_storage;
                                 debug!("ChainInfo successfully obtained from storage");
+                                act.restore_chain_state(ctx);
                             } else {
                                 // Mismatching consensus constants between config and storage
                                 panic!(
@@ -108,7 +152,38 @@ impl ChainManager {
                             unspent_outputs_pool: UnspentOutputsPool::default(),
                             data_request_pool: DataRequestPool::default(),
                             block_chain: Blockchain::default(),
+                            data_request_undo: VecDeque::default(),
+                            pruned_until: 0,
                         };
+
+                        if let Some(import_path) = snapshots.import_path {
+                            let trusted_hash = snapshots.trusted_hash.unwrap_or_else(|| {
+                                panic!(
+                                    "Cannot import the chain state snapshot {} without the \
+                                     trusted hash of the snapshot (snapshots.trusted_hash)",
+                                    import_path.display()
+                                )
+                            });
+                            let snapshot = fs::read(&import_path)
+                                .map_err(failure::Error::from)
+                                .and_then(|bytes| Ok(import_snapshot(&bytes, trusted_hash)?))
+                                .unwrap_or_else(|e| {
+                                    panic!(
+                                        "Failed to import the chain state snapshot {}: {}",
+                                        import_path.display(),
+                                        e
+                                    )
+                                });
+
+                            info!(
+                                "Chain state imported from {}. Synchronizing from epoch {}",
+                                import_path.display(),
+                                snapshot.beacon.checkpoint
+                            );
+                            act.unverified_snapshot = Some(snapshot.beacon);
+                            act.chain_state.import(snapshot);
+                            act.persist_chain_state(ctx);
+                        }
                     }
 
                     fut::ok(())
@@ -133,6 +208,147 @@ impl ChainManager {
         }).wait(ctx);
     }
 
+    /// Get the transactions of the mempool persisted on the last shutdown. They are validated
+    /// again when the node is synced, because the chain may have changed in the meantime
+    fn restore_mempool(&mut self, ctx: &mut Context<ChainManager>) {
+        storage_mngr::get::<_, Vec<Transaction>>(&MEMPOOL_KEY)
+            .and_then(|transactions| {
+                storage_mngr::delete(&MEMPOOL_KEY).map(move |()| transactions.unwrap_or_default())
+            })
+            .into_actor(self)
+            .map_err(|e, _, _| error!("Failed to restore the mempool from storage: {}", e))
+            .and_then(|transactions, act, _ctx| {
+                if !transactions.is_empty() {
+                    info!(
+                        "{} transactions of the mempool restored from storage",
+                        transactions.len()
+                    );
+                }
+                act.restored_transactions = transactions;
+
+                fut::ok(())
+            })
+            .spawn(ctx);
+    }
+
+    /// Restore the unspent outputs and the data requests from the last snapshot and the updates
+    /// persisted after it. If they are missing or do not match the chain, they are rebuilt by
+    /// replaying the blocks of the chain
+    fn restore_chain_state(&mut self, ctx: &mut Context<ChainManager>) {
+        let epochs: Vec<_> = self.chain_state.block_chain.keys().cloned().collect();
+
+        storage_mngr::get::<_, ChainStateSnapshot>(&CHAIN_SNAPSHOT_KEY)
+            .and_then(|snapshot| snapshot.ok_or_else(|| SnapshotError::Missing.into()))
+            .and_then(move |snapshot| {
+                let snapshot_epoch = snapshot.beacon.checkpoint;
+                let updates: Vec<_> = epochs
+                    .into_iter()
+                    .filter(|epoch| *epoch > snapshot_epoch)
+                    .map(|epoch| {
+                        let key = chain_update_key(epoch);
+                        storage_mngr::get::<_, ChainStateUpdate>(&key).and_then(move |update| {
+                            update.ok_or_else(|| SnapshotError::UpdateMismatch { epoch }.into())
+                        })
+                    })
+                    .collect();
+
+                future::join_all(updates).map(|updates| (snapshot, updates))
+            })
+            .into_actor(self)
+            .then(|res, act, ctx| {
+                let res = res.and_then(|(snapshot, updates)| {
+                    let beacon = updates
+                        .last()
+                        .map_or(snapshot.beacon, |update| update.beacon);
+                    let chain_updates = updates
+                        .iter()
+                        .map(|update| update.beacon.checkpoint)
+                        .collect();
+                    act.chain_state.restore(snapshot, updates)?;
+
+                    Ok((beacon, chain_updates))
+                });
+
+                match res {
+                    Ok((beacon, chain_updates)) => {
+                        debug!("Chain state restored from snapshot");
+                        act.persisted_beacon = Some(beacon);
+                        act.chain_updates = chain_updates;
+                    }
+                    Err(e) => {
+                        warn!(
+                            "Failed to restore chain state from snapshot: {}. Replaying the chain",
+                            e
+                        );
+                        act.replay_chain(ctx);
+                    }
+                }
+
+                fut::ok(())
+            })
+            .wait(ctx);
+    }
+
+    /// Rebuild the unspent outputs and the data requests by applying again the blocks of the
+    /// chain, and persist a snapshot of them. If a block is missing, the chain is reset to the
+    /// genesis block and synchronized again
+    fn replay_chain(&mut self, ctx: &mut Context<ChainManager>) {
+        let mut chain_state = self.chain_state.clone();
+        chain_state.unspent_outputs_pool = UnspentOutputsPool::default();
+        chain_state.data_request_pool = DataRequestPool::default();
+        chain_state.data_request_undo.clear();
+        let hashes: Vec<_> = chain_state.block_chain.values().cloned().collect();
+        let inventory_manager_addr = System::current().registry().get::<InventoryManager>();
+
+        stream::iter_ok(hashes)
+            .and_then(move |hash| {
+                inventory_manager_addr
+                    .send(GetItem { hash })
+                    .then(|res| match res {
+                        Ok(Ok(InventoryItem::Block(block))) => Ok(block),
+                        _ => Err(ChainManagerError::BlockDoesNotExist),
+                    })
+            })
+            .fold(chain_state, |mut chain_state, block: Block| {
+                chain_state.replay_block(&block);
+
+                Ok::<_, ChainManagerError>(chain_state)
+            })
+            .into_actor(self)
+            .then(|res, act, ctx| {
+                match res {
+                    Ok(chain_state) => {
+                        info!(
+                            "Chain state rebuilt from {} blocks",
+                            chain_state.block_chain.len()
+                        );
+                        act.chain_state = chain_state;
+                        act.persist_chain_snapshot(ctx);
+                    }
+                    Err(e) => {
+                        error!(
+                            "Failed to replay the chain: {}. Resetting the chain to genesis",
+                            e
+                        );
+                        let genesis_hash = act.genesis_block_hash;
+                        if let Some(chain_info) = act.chain_state.chain_info.as_mut() {
+                            chain_info.highest_block_checkpoint = CheckpointBeacon {
+                                checkpoint: 0,
+                                hash_prev_block: genesis_hash,
+                            };
+                        }
+                        act.chain_state.unspent_outputs_pool = UnspentOutputsPool::default();
+                        act.chain_state.data_request_pool = DataRequestPool::default();
+                        act.chain_state.block_chain = Blockchain::default();
+                        act.chain_state.data_request_undo.clear();
+                    }
+                }
+
+                fut::ok(())
+            })
+            .wait(ctx);
+    }
+
     /// Get epoch from EpochManager and subscribe to future epochs
     fn subscribe_to_epoch_manager(&mut self, ctx: &mut Context<ChainManager>) {
         // Get EpochManager address from registry
@@ -0,0 +1,258 @@
+//! # Fast synchronization
+//!
+//! Synchronizing a new node from the genesis block means downloading and validating every block
+//! of the chain. Instead, nodes can export a snapshot of their chain state at every superblock
+//! boundary: the unspent outputs, the data requests and the reputation of the witnesses after the
+//! last block of a superblock period. A new node can import one of those snapshots and
+//! synchronize from its beacon, as long as its integrity hash matches the one configured as
+//! trusted. The snapshot is only verified against the consensus of the peers once the headers of
+//! their chain are found to extend its block: until then, the synchronization is aborted if they
+//! do not.
+//!
+//! The exported state does not include what only concerns the node that took it, such as the
+//! reveals of its own commitments or the diffs to undo the last blocks, so every node exports the
+//! same snapshot after the same block. The integrity hash is the SHA-256 of the compact JSON
+//! serialization of the snapshot, which is canonical: the keys of maps and the items of sets are
+//! serialized in ascending order. It is recomputed from the exported file before importing it.
+use failure::Fail;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use witnet_crypto::hash::calculate_sha256;
+use witnet_data_structures::chain::{ChainStateSnapshot, Epoch, Hash, SUPERBLOCK_PERIOD};
+
+/// Errors that prevent a snapshot of the chain state from being imported
+#[derive(Debug, PartialEq, Fail)]
+pub enum FastSyncError {
+    /// The exported file cannot be deserialized
+    #[fail(display = "Malformed snapshot: {}", reason)]
+    Malformed {
+        /// Deserialization error
+        reason: String,
+    },
+    /// The contents of the snapshot do not match its integrity hash
+    #[fail(
+        display = "The snapshot declares the integrity hash {} but its contents hash to {}",
+        declared, found
+    )]
+    Corrupted {
+        /// Integrity hash declared in the exported file
+        declared: String,
+        /// Integrity hash of the contents of the exported file
+        found: Hash,
+    },
+    /// The integrity hash of the snapshot is not the trusted one
+    #[fail(
+        display = "The integrity hash of the snapshot {} does not match the trusted hash {}",
+        hash, trusted
+    )]
+    Untrusted {
+        /// Integrity hash of the snapshot
+        hash: Hash,
+        /// Trusted integrity hash
+        trusted: Hash,
+    },
+}
+
+/// Format of the exported snapshots
+#[derive(Serialize, Deserialize)]
+struct ExportedSnapshot {
+    /// Integrity hash of `snapshot`, hex-encoded
+    hash: String,
+    /// Unspent outputs and data requests after the block of the beacon of the snapshot
+    snapshot: Value,
+}
+
+/// Whether a block consolidated on a tip of `tip_epoch` is the first one of a new superblock
+/// period
+pub fn crosses_superblock_boundary(tip_epoch: Epoch, block_epoch: Epoch) -> bool {
+    tip_epoch / SUPERBLOCK_PERIOD < block_epoch / SUPERBLOCK_PERIOD
+}
+
+/// Serialize a snapshot to be exported, returning its integrity hash and the contents of the file
+pub fn export_snapshot(snapshot: &ChainStateSnapshot) -> Result<(Hash, Vec<u8>), FastSyncError> {
+    let mut snapshot = snapshot.clone();
+    snapshot.strip_local_state();
+    // The maps of a `Value` are sorted by key, and the sets of the chain state are serialized
+    // sorted, so the serialization of the `Value` is canonical
+    let snapshot = serde_json::to_value(snapshot).map_err(malformed)?;
+    let (hash, _) = integrity_hash(&snapshot)?;
+    let exported = ExportedSnapshot {
+        hash: hash.to_string(),
+        snapshot,
+    };
+
+    Ok((hash, serde_json::to_vec(&exported).map_err(malformed)?))
+}
+
+/// Deserialize the contents of an exported snapshot, checking its integrity hash against the
+/// `trusted_hash`
+pub fn import_snapshot(
+    bytes: &[u8],
+    trusted_hash: Hash,
+) -> Result<ChainStateSnapshot, FastSyncError> {
+    let exported: ExportedSnapshot = serde_json::from_slice(bytes).map_err(malformed)?;
+    let (hash, contents) = integrity_hash(&exported.snapshot)?;
+    if hash.to_string() != exported.hash {
+        Err(FastSyncError::Corrupted {
+            declared: exported.hash,
+            found: hash,
+        })?
+    }
+    if hash != trusted_hash {
+        Err(FastSyncError::Untrusted {
+            hash,
+            trusted: trusted_hash,
+        })?
+    }
+
+    serde_json::from_slice(&contents).map_err(malformed)
+}
+
+/// Integrity hash of a serialized snapshot, along with the serialization it was calculated from
+fn integrity_hash(snapshot: &Value) -> Result<(Hash, Vec<u8>), FastSyncError> {
+    let contents = serde_json::to_vec(snapshot).map_err(malformed)?;
+
+    Ok((calculate_sha256(&contents).into(), contents))
+}
+
+fn malformed(error: serde_json::Error) -> FastSyncError {
+    FastSyncError::Malformed {
+        reason: error.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use witnet_data_structures::{
+        chain::{
+            CheckpointBeacon, Output, OutputPointer, Transaction, UnspentOutputsPool,
+            ValueTransferOutput,
+        },
+        data_request::DataRequestPool,
+        wit::Wit,
+    };
+
+    fn snapshot() -> ChainStateSnapshot {
+        let mut unspent_outputs_pool = UnspentOutputsPool::default();
+        unspent_outputs_pool.insert(
+            OutputPointer {
+                transaction_id: Hash::SHA256([1; 32]),
+                output_index: 0,
+            },
+            Output::ValueTransfer(ValueTransferOutput {
+                pkh: Default::default(),
+                value: Wit::from_nanowits(1000),
+            }),
+        );
+
+        ChainStateSnapshot {
+            beacon: CheckpointBeacon {
+                checkpoint: 19,
+                hash_prev_block: Hash::SHA256([2; 32]),
+            },
+            unspent_outputs_pool,
+            data_request_pool: DataRequestPool::default(),
+        }
+    }
+
+    #[test]
+    fn superblock_boundaries() {
+        assert!(!crosses_superblock_boundary(0, SUPERBLOCK_PERIOD - 1));
+        assert!(crosses_superblock_boundary(0, SUPERBLOCK_PERIOD));
+        assert!(crosses_superblock_boundary(
+            SUPERBLOCK_PERIOD - 1,
+            3 * SUPERBLOCK_PERIOD + 1
+        ));
+        assert!(!crosses_superblock_boundary(
+            SUPERBLOCK_PERIOD,
+            2 * SUPERBLOCK_PERIOD - 1
+        ));
+    }
+
+    fn snapshot_with_data_requests(pointers: &[OutputPointer]) -> ChainStateSnapshot {
+        let mut snapshot = snapshot();
+        let data_requests: HashSet<OutputPointer> = pointers.iter().cloned().collect();
+        snapshot
+            .data_request_pool
+            .data_requests_by_epoch
+            .insert(19, data_requests);
+
+        snapshot
+    }
+
+    #[test]
+    fn export_snapshot_canonical() {
+        let pointers: Vec<OutputPointer> = (0..32)
+            .map(|i| OutputPointer {
+                transaction_id: Hash::SHA256([i; 32]),
+                output_index: 0,
+            })
+            .collect();
+        let reversed: Vec<OutputPointer> = pointers.iter().rev().cloned().collect();
+
+        let (hash, bytes) = export_snapshot(&snapshot_with_data_requests(&pointers)).unwrap();
+        let (hash_reversed, bytes_reversed) =
+            export_snapshot(&snapshot_with_data_requests(&reversed)).unwrap();
+
+        assert_eq!(hash, hash_reversed);
+        assert_eq!(bytes, bytes_reversed);
+    }
+
+    #[test]
+    fn export_snapshot_without_local_state() {
+        let mut snapshot = snapshot();
+        let pointer = OutputPointer {
+            transaction_id: Hash::SHA256([4; 32]),
+            output_index: 0,
+        };
+        snapshot
+            .data_request_pool
+            .insert_reveal(pointer.clone(), Transaction::default());
+        snapshot
+            .data_request_pool
+            .dr_pointer_cache
+            .insert(pointer.clone(), pointer);
+
+        let (hash, bytes) = export_snapshot(&snapshot).unwrap();
+
+        assert_eq!(hash, export_snapshot(&self::snapshot()).unwrap().0);
+        assert_eq!(import_snapshot(&bytes, hash), Ok(self::snapshot()));
+    }
+
+    #[test]
+    fn import_exported_snapshot() {
+        let (hash, bytes) = export_snapshot(&snapshot()).unwrap();
+
+        assert_eq!(import_snapshot(&bytes, hash), Ok(snapshot()));
+    }
+
+    #[test]
+    fn import_untrusted_snapshot() {
+        let (hash, bytes) = export_snapshot(&snapshot()).unwrap();
+        let trusted = Hash::SHA256([3; 32]);
+
+        assert_eq!(
+            import_snapshot(&bytes, trusted),
+            Err(FastSyncError::Untrusted { hash, trusted })
+        );
+    }
+
+    #[test]
+    fn import_corrupted_snapshot() {
+        let (hash, bytes) = export_snapshot(&snapshot()).unwrap();
+        let bytes = String::from_utf8(bytes)
+            .unwrap()
+            .replace("1000", "9000")
+            .into_bytes();
+
+        match import_snapshot(&bytes, hash) {
+            Err(FastSyncError::Corrupted { declared, .. }) => {
+                assert_eq!(declared, hash.to_string())
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
+    }
+}
@@ -0,0 +1,248 @@
+//! # Fork choice rule
+//!
+//! Chains are compared by their `ChainWeight`, in this order:
+//!
+//! 1. The chain with more blocks wins, as every block is an epoch with an elected leader.
+//! 2. The chain with the higher accumulated proof quality wins. The quality of a block is derived
+//!    from the output of the VRF of its leadership proof: the lower the output, the higher the
+//!    quality. The signature of the block is not taken into account, as ECDSA signatures are
+//!    malleable and the miner could grind them.
+//! 3. The chain whose tip is at an earlier epoch wins, as it reached the same weight first.
+//! 4. The chain whose tip has the lower hash wins, so that every node makes the same choice.
+//!
+//! Block candidates for the same epoch extend the same tip, so they are compared by the quality
+//! of their proof, with the hash of the block as a tie-break.
+use std::cmp::Ordering;
+
+use witnet_data_structures::chain::{
+    Block, CheckpointBeacon, Epoch, Hash, Hashable, LeadershipProof,
+};
+
+/// Quality of a leadership proof: the first 8 bytes of the output of its VRF, inverted, so that
+/// proofs with lower outputs have higher quality. Malformed proofs have no quality at all
+pub fn proof_quality(proof: &LeadershipProof) -> u64 {
+    match proof.proof.proof_to_hash() {
+        Ok(Hash::SHA256(output)) => {
+            let mut prefix = [0; 8];
+            prefix.copy_from_slice(&output[..8]);
+
+            !u64::from_be_bytes(prefix)
+        }
+        Err(_) => 0,
+    }
+}
+
+/// Weight of a chain, to compare it to competing chains
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ChainWeight {
+    /// Number of blocks
+    pub blocks: u32,
+    /// Sum of the quality of the leadership proofs of the blocks
+    pub quality: u128,
+    /// Beacon of the last block
+    pub tip: CheckpointBeacon,
+}
+
+impl ChainWeight {
+    /// Weight of the chain after appending `block`
+    pub fn extend(self, block: &Block) -> Self {
+        self.extend_with(
+            block.block_header.beacon.checkpoint,
+            block.hash(),
+            &block.proof,
+        )
+    }
+
+    /// Weight of the chain after appending the block with hash `block_hash` at `checkpoint`,
+    /// whose leadership proof is `proof`
+    pub fn extend_with(self, checkpoint: Epoch, block_hash: Hash, proof: &LeadershipProof) -> Self {
+        ChainWeight {
+            blocks: self.blocks + 1,
+            quality: self.quality + u128::from(proof_quality(proof)),
+            tip: CheckpointBeacon {
+                checkpoint,
+                hash_prev_block: block_hash,
+            },
+        }
+    }
+
+    /// Weight of the `blocks` in chain order, on top of a chain of weight `base`
+    pub fn of_blocks<'a, I>(base: Self, blocks: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Block>,
+    {
+        blocks.into_iter().fold(base, ChainWeight::extend)
+    }
+}
+
+impl Ord for ChainWeight {
+    /// The heavier chain is the greater one
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.blocks
+            .cmp(&other.blocks)
+            .then(self.quality.cmp(&other.quality))
+            .then(other.tip.checkpoint.cmp(&self.tip.checkpoint))
+            .then(other.tip.hash_prev_block.cmp(&self.tip.hash_prev_block))
+    }
+}
+
+impl PartialOrd for ChainWeight {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Compare two block candidates extending the same tip. The better candidate is the greater one
+pub fn compare_candidates(a: &Block, b: &Block) -> Ordering {
+    ChainWeight::default()
+        .extend(a)
+        .cmp(&ChainWeight::default().extend(b))
+}
+
+/// Sort block candidates from best to worst according to the fork choice rule
+pub fn sort_candidates(candidates: &mut [Block]) {
+    candidates.sort_by(|a, b| compare_candidates(b, a));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secp256k1::SecretKey as Secp256k1_SecretKey;
+    use witnet_data_structures::chain::{
+        BlockHeader, KeyedSignature, Secp256k1Signature, Signature, VrfProof,
+    };
+
+    /// Block whose leadership proof is made with the secret key `[miner; 32]`
+    fn block(checkpoint: u32, hash_prev_block: Hash, miner: u8) -> Block {
+        let secret_key = Secp256k1_SecretKey::from_slice(&[miner; 32]).unwrap();
+
+        Block {
+            block_header: BlockHeader {
+                beacon: CheckpointBeacon {
+                    checkpoint,
+                    hash_prev_block,
+                },
+                ..BlockHeader::default()
+            },
+            proof: LeadershipProof {
+                proof: VrfProof::new(&secret_key, b"witnet").unwrap(),
+                ..LeadershipProof::default()
+            },
+            txns: vec![],
+        }
+    }
+
+    /// Chain of `len` blocks on top of `parent`, mined by `miner`
+    fn chain(parent: Hash, first_checkpoint: u32, len: u32, miner: u8) -> Vec<Block> {
+        let mut parent = parent;
+        (first_checkpoint..first_checkpoint + len)
+            .map(|checkpoint| {
+                let block = block(checkpoint, parent, miner);
+                parent = block.hash();
+
+                block
+            })
+            .collect()
+    }
+
+    fn weight(blocks: &[Block]) -> ChainWeight {
+        ChainWeight::of_blocks(ChainWeight::default(), blocks)
+    }
+
+    /// Miners of two blocks, the first one with the better proof
+    fn better_and_worse_miners() -> (u8, u8) {
+        let quality = |miner| proof_quality(&block(1, Hash::default(), miner).proof);
+        if quality(1) > quality(2) {
+            (1, 2)
+        } else {
+            (2, 1)
+        }
+    }
+
+    #[test]
+    fn quality_ignores_block_signature() {
+        let a = block(1, Hash::default(), 1);
+        let mut b = a.clone();
+        b.proof.block_sig = KeyedSignature {
+            signature: Signature::Secp256k1(Secp256k1Signature { der: vec![0xff] }),
+            ..KeyedSignature::default()
+        };
+
+        assert_ne!(proof_quality(&a.proof), 0);
+        assert_eq!(proof_quality(&a.proof), proof_quality(&b.proof));
+        // Malformed VRF proofs have no quality
+        assert_eq!(proof_quality(&LeadershipProof::default()), 0);
+    }
+
+    #[test]
+    fn longer_chain_wins() {
+        let (better, worse) = better_and_worse_miners();
+        let short = chain(Hash::default(), 1, 2, better);
+        let long = chain(Hash::default(), 1, 3, worse);
+
+        assert!(weight(&long) > weight(&short));
+        // Even if the short chain has several times the quality of the long one
+        let mut short = weight(&short);
+        short.quality = u128::max_value();
+        assert!(weight(&long) > short);
+    }
+
+    #[test]
+    fn better_proofs_win_between_chains_of_same_length() {
+        let (better, worse) = better_and_worse_miners();
+        let a = chain(Hash::default(), 1, 3, better);
+        let b = chain(Hash::default(), 1, 3, worse);
+
+        assert!(weight(&a) > weight(&b));
+        assert_eq!(weight(&a).blocks, 3);
+        assert_eq!(weight(&a).tip.hash_prev_block, a[2].hash());
+    }
+
+    #[test]
+    fn earlier_tip_wins_with_same_blocks_and_quality() {
+        // Same proofs, but one of the chains skipped an epoch
+        let a = chain(Hash::default(), 1, 2, 1);
+        let mut b = chain(Hash::default(), 1, 1, 1);
+        b.push(block(3, b[0].hash(), 1));
+
+        assert_eq!(weight(&a).quality, weight(&b).quality);
+        assert!(weight(&a) > weight(&b));
+    }
+
+    #[test]
+    fn ties_broken_by_lower_hash() {
+        // Same epoch and proof, different contents
+        let a = block(1, Hash::default(), 1);
+        let mut b = a.clone();
+        b.block_header.version = 1;
+        let (lower, higher) = if a.hash() < b.hash() { (a, b) } else { (b, a) };
+
+        assert_eq!(compare_candidates(&lower, &higher), Ordering::Greater);
+        assert_eq!(compare_candidates(&higher, &lower), Ordering::Less);
+        assert_eq!(compare_candidates(&lower, &lower), Ordering::Equal);
+    }
+
+    #[test]
+    fn candidates_sorted_deterministically() {
+        let (better, worse) = better_and_worse_miners();
+        let best = block(1, Hash::default(), better);
+        let mut tied = best.clone();
+        tied.block_header.version = 1;
+        let worst = block(1, Hash::default(), worse);
+
+        // The order in which the candidates arrived does not matter
+        let mut candidates = vec![worst.clone(), tied.clone(), best.clone()];
+        let mut reversed: Vec<Block> = candidates.iter().rev().cloned().collect();
+        sort_candidates(&mut candidates);
+        sort_candidates(&mut reversed);
+
+        assert_eq!(candidates, reversed);
+        assert_eq!(candidates[2], worst);
+        let lower_hash = if best.hash() < tied.hash() {
+            best
+        } else {
+            tied
+        };
+        assert_eq!(candidates[0], lower_hash);
+    }
+}
@@ -1,25 +1,45 @@
-use actix::{Actor, Context, Handler, Message, SystemService};
-use log::{debug, error, warn};
+use actix::{
+    fut, Actor, Context, Handler, Message, MessageResult, ResponseActFuture, SystemService,
+    WrapFuture,
+};
+use futures::Future;
+use log::{debug, error, info, warn};
 
 use witnet_data_structures::{
-    chain::{CheckpointBeacon, Epoch, Hashable, InventoryEntry, InventoryItem},
+    chain::{
+        Block, CheckpointBeacon, DataRequestReport, Epoch, Hash, Hashable, InventoryEntry,
+        OutputPointer, Transaction,
+    },
     error::ChainInfoError,
 };
-use witnet_validations::validations::validate_block;
+use witnet_validations::validations::{validate_block, validate_header_chain};
 
-use super::{ChainManager, ChainManagerError, StateMachine};
+use super::{
+    fork_choice::sort_candidates, relay::check_conflicts, reorg::check_competing_headers,
+    sync::HeadersFirstSync, BlockTemplate, ChainManager, ChainManagerError, StateMachine,
+};
 use crate::{
     actors::{
         messages::{
-            AddBlocks, AddCandidates, AddTransaction, Anycast, Broadcast, EpochNotification,
-            GetBlocksEpochRange, GetHighestCheckpointBeacon, PeersBeacons, SendLastBeacon,
-            SessionUnitResult,
+            AddBlocks, AddCandidates, AddHeaders, AddTransaction, Broadcast, ChainStatus,
+            CheckCheckpoints, DataRequestStatus, EpochNotification, FilterAnnouncedItems,
+            GetBlockTemplate, GetBlocksEpochRange, GetChainStatus, GetDataRequest,
+            GetDataRequestsByRequester, GetHighestCheckpointBeacon, GetMemoryInfo, GetMempool,
+            GetMempoolTransactions, GetReputation, GetSignatureCacheStats, LocateBlocks,
+            MemoryInfo, MempoolInfo, PeersBeacons, ReputationInfo, SendLastBeacon,
+            SessionUnitResult, Shutdown, SubmitBlock,
         },
         sessions_manager::SessionsManager,
+        storage_keys::MEMPOOL_KEY,
     },
+    storage_mngr,
     utils::mode_consensus,
 };
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Bound,
+};
+use witnet_util::timestamp::get_timestamp;
 use witnet_validations::validations::validate_transaction;
 
 ////////////////////////////////////////////////////////////////////////////////////////
@@ -67,6 +87,7 @@ impl Handler<EpochNotification<EveryEpochPayload>> for ChainManager {
     fn handle(&mut self, msg: EpochNotification<EveryEpochPayload>, ctx: &mut Context<Self>) {
         debug!("Periodic epoch notification received {:?}", msg.checkpoint);
         self.current_epoch = Some(msg.checkpoint);
+        self.expire_transactions(msg.checkpoint);
 
         debug!(
             "EpochNotification received while StateMachine is in state {:?}",
@@ -89,18 +110,13 @@ impl Handler<EpochNotification<EveryEpochPayload>> for ChainManager {
                 if let (Some(current_epoch), Some(chain_info)) =
                     (self.current_epoch, self.chain_state.chain_info.as_ref())
                 {
-                    let candidates = self.candidates.clone();
+                    let mut candidates: Vec<Block> = self.candidates.values().cloned().collect();
 
-                    // Decide the best candidate
-                    // TODO: replace for loop with a try_fold
+                    // Decide the best candidate: the first valid one according to the fork
+                    // choice rule
+                    sort_candidates(&mut candidates);
                     let mut chosen_candidate = None;
-                    for (key, block_candidate) in candidates {
-                        if let Some((chosen_key, _)) = chosen_candidate.clone() {
-                            if chosen_key < key {
-                                // Ignore candidates with bigger hashes
-                                continue;
-                            }
-                        }
+                    for block_candidate in candidates {
                         match validate_block(
                             &block_candidate,
                             current_epoch,
@@ -109,19 +125,22 @@ impl Handler<EpochNotification<EveryEpochPayload>> for ChainManager {
                             &self.chain_state.unspent_outputs_pool,
                             &self.transactions_pool,
                             &self.chain_state.data_request_pool,
+                            &mut self.signature_cache,
                         ) {
-                            Ok(block_in_chain) => chosen_candidate = Some((key, block_in_chain)),
+                            Ok(block_in_chain) => {
+                                chosen_candidate = Some(block_in_chain);
+                                break;
+                            }
                             Err(e) => debug!("{}", e),
                         }
                     }
 
                     // Consolidate the best candidate
-                    if let Some((_, block_in_chain)) = chosen_candidate {
+                    if let Some(block_in_chain) = chosen_candidate {
                         // Persist block and update ChainState
                         self.consolidate_block(
                             ctx,
                             block_in_chain.block,
-                            block_in_chain.utxo_set,
                             block_in_chain.data_request_pool,
                             true,
                         );
@@ -158,7 +177,7 @@ impl Handler<EpochNotification<EveryEpochPayload>> for ChainManager {
                     }
 
                     // Clear candidates
-                    self.candidates.clear();
+                    self.clear_candidates();
                 } else {
                     warn!("ChainManager doesn't have current epoch");
                 }
@@ -197,37 +216,196 @@ impl Handler<AddBlocks> for ChainManager {
         match self.sm_state {
             StateMachine::WaitingConsensus => {}
             StateMachine::Synchronizing => {
-                let old_chain_state = self.chain_state.clone();
-                if let Some(target_beacon) = self.target_beacon {
-                    for block in msg.blocks {
-                        if let Err(e) = self.process_requested_block(ctx, block) {
-                            warn!("{}", e);
-                            self.chain_state = old_chain_state;
-                            break;
-                        }
+                let sync = match self.sync.as_mut() {
+                    Some(sync) => sync,
+                    None => {
+                        warn!("Synchronizing without a synchronization in progress");
+                        return;
                     }
-                    let our_beacon = self
-                        .chain_state
-                        .chain_info
-                        .as_ref()
-                        .unwrap()
-                        .highest_block_checkpoint;
-                    if our_beacon == target_beacon {
-                        // Target achived, go back to state 1
-                        self.sm_state = StateMachine::WaitingConsensus;
-                    } else {
-                        // Try again, send Anycast<SendLastBeacon> to a "safu" peer, i.e. their last beacon matches our target beacon.
-                        SessionsManager::from_registry().do_send(Anycast {
-                            command: SendLastBeacon { beacon: our_beacon },
-                            safu: true,
-                        });
+                };
+                for block in msg.blocks {
+                    let hash = block.hash();
+                    if !sync.block_received(block) {
+                        debug!("Ignoring block {} not requested", hash);
                     }
+                }
+
+                if let Some(fork_point) = sync.fork_point() {
+                    // The competing branch replaces ours at once, when all its blocks arrived
+                    if !sync.all_blocks_received() {
+                        self.request_blocks();
+                        return;
+                    }
+                    let mut blocks = vec![];
+                    while let Some(block) = sync.next_block() {
+                        blocks.push(block);
+                    }
+                    if let Err(e) = self.reorganize(ctx, fork_point, blocks) {
+                        self.abort_sync(e);
+                        return;
+                    }
+                }
+
+                // Consolidate the blocks in chain order, each one once all the previous ones are
+                loop {
+                    let block = match self.sync.as_mut().and_then(HeadersFirstSync::next_block) {
+                        Some(block) => block,
+                        None => break,
+                    };
+                    if let Err(e) = self.process_requested_block(ctx, block) {
+                        self.abort_sync(e);
+                        return;
+                    }
+                }
+
+                let target = match self.sync.as_ref() {
+                    Some(sync) if sync.is_finished() => sync.target(),
+                    _ => {
+                        // The consolidated blocks may have made room for more headers
+                        if self
+                            .sync
+                            .as_ref()
+                            .map_or(false, HeadersFirstSync::needs_headers)
+                        {
+                            self.request_headers();
+                        }
+                        self.request_blocks();
+                        return;
+                    }
+                };
+                let our_beacon = self
+                    .chain_state
+                    .chain_info
+                    .as_ref()
+                    .unwrap()
+                    .highest_block_checkpoint;
+                if our_beacon == target {
+                    // Target achived, go back to state 1
+                    self.sync = None;
+                    self.update_state_machine(StateMachine::WaitingConsensus);
                 } else {
-                    warn!("Target Beacon is None");
+                    self.abort_sync(format!(
+                        "the chain is on {:?} instead of the target {:?}",
+                        our_beacon, target
+                    ));
                 }
             }
-            StateMachine::Synced => {}
+            StateMachine::Synced => {
+                // Blocks requested out of a synchronization, such as the missing ancestors of
+                // orphan blocks
+                for block in msg.blocks {
+                    let hash = block.hash();
+                    if let Err(e) = self.process_requested_block(ctx, block) {
+                        debug!("Failed to consolidate block {}: {}", hash, e);
+                    }
+                }
+            }
+        };
+    }
+}
+
+/// Handler for AddHeaders message
+impl Handler<AddHeaders> for ChainManager {
+    type Result = SessionUnitResult;
+
+    fn handle(&mut self, AddHeaders { headers }: AddHeaders, _ctx: &mut Context<Self>) {
+        debug!("AddHeaders received with {} headers", headers.len());
+
+        let current_epoch = match self.current_epoch {
+            Some(current_epoch) => current_epoch,
+            None => {
+                warn!("ChainManager doesn't have current epoch");
+                return;
+            }
+        };
+        let sync = match &mut self.sync {
+            Some(sync) if sync.is_waiting_headers() => sync,
+            _ => {
+                debug!("Ignoring headers not requested");
+                return;
+            }
         };
+
+        // An imported snapshot is only trusted once the chain of the target agreed by the peers
+        // is found to contain its block, i.e. once the headers towards the target extend it
+        if let (Some(snapshot_beacon), Some(header)) = (self.unverified_snapshot, headers.first()) {
+            if sync.headers_tip() == snapshot_beacon
+                && header.beacon.hash_prev_block != snapshot_beacon.hash_prev_block
+            {
+                self.abort_sync(format!(
+                    "the headers of the consensus chain do not extend the imported chain state \
+                     snapshot at epoch {}",
+                    snapshot_beacon.checkpoint
+                ));
+                return;
+            }
+        }
+
+        // The first headers may extend an older block of our chain: the target is on a
+        // competing chain, and the headers start at the fork point
+        if let Some(header) = headers.first() {
+            let parent = header.beacon.hash_prev_block;
+            if sync.pending_blocks() == 0 && parent != sync.headers_tip().hash_prev_block {
+                let fork_point = if parent == self.genesis_block_hash {
+                    Some(CheckpointBeacon {
+                        checkpoint: 0,
+                        hash_prev_block: parent,
+                    })
+                } else {
+                    self.chain_state
+                        .block_chain
+                        .iter()
+                        .find(|(_, hash)| **hash == parent)
+                        .map(|(epoch, hash)| CheckpointBeacon {
+                            checkpoint: *epoch,
+                            hash_prev_block: *hash,
+                        })
+                };
+                if let Some(fork_point) = fork_point {
+                    debug!("Competing chain forked at epoch #{}", fork_point.checkpoint);
+                    sync.rebase(fork_point);
+                }
+            }
+        }
+
+        let result = validate_header_chain(
+            &headers,
+            current_epoch,
+            sync.headers_tip(),
+            self.genesis_block_hash,
+        )
+        .and_then(|_| Ok(sync.add_headers(headers)?))
+        .map(|()| sync.headers_complete());
+
+        match result {
+            Ok(headers_complete) => {
+                if headers_complete {
+                    self.snapshot_verified();
+                }
+                let competing_branch = match self.sync.as_ref() {
+                    Some(sync) if headers_complete => sync
+                        .fork_point()
+                        .map(|fork_point| (fork_point.checkpoint, sync.pending_blocks())),
+                    _ => None,
+                };
+                if let Some((fork_epoch, theirs)) = competing_branch {
+                    if let Err(e) = check_competing_headers(&self.chain_state, fork_epoch, theirs) {
+                        self.abort_sync(e);
+                        return;
+                    }
+                }
+
+                if self
+                    .sync
+                    .as_ref()
+                    .map_or(false, HeadersFirstSync::needs_headers)
+                {
+                    self.request_headers();
+                }
+                self.request_blocks();
+            }
+            Err(e) => self.abort_sync(e),
+        }
     }
 }
 
@@ -238,7 +416,9 @@ impl Handler<AddCandidates> for ChainManager {
     fn handle(&mut self, msg: AddCandidates, _ctx: &mut Context<Self>) {
         // AddCandidates is needed in all states
         for block in msg.blocks {
-            self.process_candidate(block)
+            if let Err(e) = self.process_candidate(block) {
+                warn!("{}", e);
+            }
         }
     }
 }
@@ -263,30 +443,356 @@ impl Handler<AddTransaction> for ChainManager {
             StateMachine::Synced => {}
         };
 
-        let transaction_hash = &msg.transaction.hash();
-        if self.transactions_pool.contains(transaction_hash) {
+        let transaction_hash = msg.transaction.hash();
+        if self.transactions_pool.contains(&transaction_hash) {
             debug!("Transaction is already in the pool: {}", transaction_hash);
             return;
-        } else {
-            match validate_transaction(
-                &msg.transaction,
-                &self.chain_state.unspent_outputs_pool,
-                &self.chain_state.data_request_pool,
-                &mut HashMap::new(),
-            ) {
-                Ok(_) => {
-                    debug!("Transaction added successfully");
-                    // Broadcast valid transaction
-                    self.broadcast_item(InventoryItem::Transaction(msg.transaction.clone()));
-
-                    // Add valid transaction to transactions_pool
-                    self.transactions_pool
-                        .insert(*transaction_hash, msg.transaction);
-                }
+        }
+        // Every transaction is processed only once, even if it is not valid
+        if let Err(e) = self.relay_policy.see(transaction_hash, get_timestamp()) {
+            debug!("{}", e);
+            return;
+        }
 
-                Err(e) => warn!("{}", e),
+        let fee = match validate_transaction(
+            &msg.transaction,
+            &self.chain_state.unspent_outputs_pool,
+            &self.chain_state.data_request_pool,
+            &mut HashMap::new(),
+            &mut self.signature_cache,
+        ) {
+            Ok(fee) => fee,
+            Err(e) => {
+                warn!("{}", e);
+                return;
             }
+        };
+        let relayable = self
+            .relay_policy
+            .check_fee(transaction_hash, &msg.transaction, fee)
+            .and_then(|()| {
+                check_conflicts(transaction_hash, &msg.transaction, &self.transactions_pool)
+            });
+        if let Err(e) = relayable {
+            debug!("Transaction not accepted: {}", e);
+            return;
+        }
+
+        debug!("Transaction added successfully");
+        // Add valid transaction to transactions_pool
+        self.transactions_pool.insert(
+            transaction_hash,
+            msg.transaction,
+            fee,
+            self.current_epoch.unwrap_or_default(),
+        );
+        self.evict_transactions();
+
+        // Announce valid transaction, unless it was evicted right away
+        if self.transactions_pool.contains(&transaction_hash) {
+            self.relay_policy.announce(transaction_hash);
+        }
+    }
+}
+
+/// Handler for GetBlockTemplate message
+impl Handler<GetBlockTemplate> for ChainManager {
+    type Result = ResponseActFuture<Self, BlockTemplate, failure::Error>;
+
+    fn handle(&mut self, _msg: GetBlockTemplate, ctx: &mut Context<Self>) -> Self::Result {
+        self.build_block_template(ctx)
+    }
+}
+
+/// Handler for GetMemoryInfo message
+impl Handler<GetMemoryInfo> for ChainManager {
+    type Result = MessageResult<GetMemoryInfo>;
+
+    fn handle(&mut self, _msg: GetMemoryInfo, _ctx: &mut Context<Self>) -> Self::Result {
+        MessageResult(vec![
+            MemoryInfo {
+                component: "transactions_pool".to_string(),
+                usage: self.transactions_pool.memory_usage(),
+                cap: self.transactions_pool_max_memory,
+            },
+            MemoryInfo {
+                component: "block_candidates".to_string(),
+                usage: self.candidates_memory_usage,
+                cap: self.candidates_max_memory,
+            },
+            MemoryInfo {
+                component: "sync".to_string(),
+                usage: self
+                    .sync
+                    .as_ref()
+                    .map(HeadersFirstSync::memory_usage)
+                    .unwrap_or(0),
+                cap: self.sync_max_memory,
+            },
+            MemoryInfo {
+                component: "signature_cache".to_string(),
+                usage: self.signature_cache.memory_usage(),
+                cap: self.signature_cache.max_bytes(),
+            },
+        ])
+    }
+}
+
+/// Handler for GetMempool message
+impl Handler<GetMempool> for ChainManager {
+    type Result = MessageResult<GetMempool>;
+
+    fn handle(&mut self, _msg: GetMempool, _ctx: &mut Context<Self>) -> Self::Result {
+        MessageResult(MempoolInfo {
+            weight: self.transactions_pool.weight(),
+            max_weight: self.transactions_pool_max_weight,
+            transactions: self.transactions_pool.info_by_priority(),
+        })
+    }
+}
+
+/// Handler for GetSignatureCacheStats message
+impl Handler<GetSignatureCacheStats> for ChainManager {
+    type Result = MessageResult<GetSignatureCacheStats>;
+
+    fn handle(&mut self, _msg: GetSignatureCacheStats, _ctx: &mut Context<Self>) -> Self::Result {
+        MessageResult(self.signature_cache.stats())
+    }
+}
+
+/// Handler for GetReputation message
+impl Handler<GetReputation> for ChainManager {
+    type Result = MessageResult<GetReputation>;
+
+    fn handle(
+        &mut self,
+        GetReputation { pkh }: GetReputation,
+        _ctx: &mut Context<Self>,
+    ) -> Self::Result {
+        let reputation_engine = &self.chain_state.data_request_pool.reputation_engine;
+
+        MessageResult(ReputationInfo {
+            reputation: reputation_engine.reputation(&pkh),
+            active: reputation_engine.is_active(&pkh),
+            total_active_reputation: reputation_engine.total_active_reputation(),
+            active_identities: reputation_engine.active_identities(),
+        })
+    }
+}
+
+/// Handler for GetChainStatus message
+impl Handler<GetChainStatus> for ChainManager {
+    type Result = MessageResult<GetChainStatus>;
+
+    fn handle(&mut self, _msg: GetChainStatus, _ctx: &mut Context<Self>) -> Self::Result {
+        MessageResult(ChainStatus {
+            state: self.sm_state,
+            current_epoch: self.current_epoch,
+            tip: self
+                .chain_state
+                .chain_info
+                .as_ref()
+                .map(|chain_info| chain_info.highest_block_checkpoint),
+            last_block_timestamp: self.last_block_timestamp,
+        })
+    }
+}
+
+/// Handler for GetDataRequest message
+impl Handler<GetDataRequest> for ChainManager {
+    type Result = ResponseActFuture<Self, Option<DataRequestStatus>, failure::Error>;
+
+    fn handle(
+        &mut self,
+        GetDataRequest { hash }: GetDataRequest,
+        _ctx: &mut Context<Self>,
+    ) -> Self::Result {
+        // Data request transactions have a single output
+        let dr_pointer = OutputPointer {
+            transaction_id: hash,
+            output_index: 0,
+        };
+        if let Some(dr_state) = self
+            .chain_state
+            .data_request_pool
+            .data_request_state(&dr_pointer)
+        {
+            return Box::new(fut::ok(Some(DataRequestStatus::Active(dr_state.clone()))));
+        }
+
+        // Resolved data requests are removed from the pool, and their reports persisted
+        let fut = storage_mngr::get::<_, DataRequestReport>(&dr_pointer)
+            .map(|report| report.map(DataRequestStatus::Resolved))
+            .into_actor(self);
+
+        Box::new(fut)
+    }
+}
+
+/// Handler for GetDataRequestsByRequester message
+impl Handler<GetDataRequestsByRequester> for ChainManager {
+    type Result = MessageResult<GetDataRequestsByRequester>;
+
+    fn handle(
+        &mut self,
+        GetDataRequestsByRequester { pkh }: GetDataRequestsByRequester,
+        _ctx: &mut Context<Self>,
+    ) -> Self::Result {
+        MessageResult(
+            self.chain_state
+                .data_request_pool
+                .data_requests_by_requester(&pkh)
+                .into_iter()
+                .map(|(dr_pointer, dr_state)| (dr_pointer.clone(), dr_state.clone()))
+                .collect(),
+        )
+    }
+}
+
+/// Handler for Shutdown message
+impl Handler<Shutdown> for ChainManager {
+    type Result = ResponseActFuture<Self, (), failure::Error>;
+
+    fn handle(&mut self, _msg: Shutdown, _ctx: &mut Context<Self>) -> Self::Result {
+        // Messages are handled one at a time, so no block is being processed now. The chain state,
+        // which is not persisted while synchronizing, is written along with a snapshot of the
+        // unspent outputs and the data requests and with the mempool, so that the node resumes
+        // from where it stopped on restart
+        let transactions: Vec<Transaction> = self.transactions_pool.iter().cloned().collect();
+        let num_transactions = transactions.len();
+        let mut batch = storage_mngr::WriteBatch::default();
+        let res = batch
+            .put(&MEMPOOL_KEY, &transactions)
+            .and_then(|()| self.add_chain_state_snapshot(&mut batch));
+        if let Err(e) = res {
+            return Box::new(fut::err(e));
         }
+
+        let fut = storage_mngr::write(batch)
+            .map(move |()| {
+                info!(
+                    "Chain state and {} transactions of the mempool persisted",
+                    num_transactions
+                )
+            })
+            .into_actor(self);
+
+        Box::new(fut)
+    }
+}
+
+/// Handler for CheckCheckpoints message
+impl Handler<CheckCheckpoints> for ChainManager {
+    type Result = MessageResult<CheckCheckpoints>;
+
+    fn handle(&mut self, msg: CheckCheckpoints, _ctx: &mut Context<Self>) -> Self::Result {
+        MessageResult(
+            msg.checkpoints
+                .into_iter()
+                .map(|beacon| (beacon, self.chain_state.check_checkpoint(&beacon)))
+                .collect(),
+        )
+    }
+}
+
+/// Handler for SubmitBlock message
+impl Handler<SubmitBlock> for ChainManager {
+    type Result = Result<(), failure::Error>;
+
+    fn handle(&mut self, msg: SubmitBlock, ctx: &mut Context<Self>) -> Self::Result {
+        let current_epoch = self.current_epoch.ok_or(ChainManagerError::ChainNotReady)?;
+        let chain_beacon = self.handle(GetHighestCheckpointBeacon, ctx)?;
+
+        validate_block(
+            &msg.block,
+            current_epoch,
+            chain_beacon,
+            self.genesis_block_hash,
+            &self.chain_state.unspent_outputs_pool,
+            &self.transactions_pool,
+            &self.chain_state.data_request_pool,
+            &mut self.signature_cache,
+        )?;
+
+        // Valid blocks are processed as any other candidate
+        self.process_candidate(msg.block)
+    }
+}
+
+/// Handler for LocateBlocks
+impl Handler<LocateBlocks> for ChainManager {
+    type Result = Result<Vec<Hash>, ChainManagerError>;
+
+    fn handle(
+        &mut self,
+        LocateBlocks {
+            locator_hashes,
+            limit,
+        }: LocateBlocks,
+        _ctx: &mut Context<Self>,
+    ) -> Self::Result {
+        debug!("LocateBlocks received with {} hashes", locator_hashes.len());
+
+        // The blocks follow the most recent block of our chain known to the peer, or the genesis
+        // block if there is none
+        let locator_hashes: HashSet<Hash> = locator_hashes.into_iter().collect();
+        let block_chain = &self.chain_state.block_chain;
+        let start = block_chain
+            .iter()
+            .rev()
+            .find(|(_, hash)| locator_hashes.contains(hash))
+            .map(|(epoch, _)| Bound::Excluded(*epoch))
+            .unwrap_or(Bound::Unbounded);
+
+        Ok(block_chain
+            .range((start, Bound::Unbounded))
+            .map(|(_, hash)| *hash)
+            .take(limit)
+            .collect())
+    }
+}
+
+/// Handler for FilterAnnouncedItems message
+impl Handler<FilterAnnouncedItems> for ChainManager {
+    type Result = MessageResult<FilterAnnouncedItems>;
+
+    fn handle(
+        &mut self,
+        FilterAnnouncedItems { items }: FilterAnnouncedItems,
+        _ctx: &mut Context<Self>,
+    ) -> Self::Result {
+        let now = get_timestamp();
+
+        MessageResult(
+            items
+                .into_iter()
+                .filter(|item| match item {
+                    InventoryEntry::Tx(hash) => {
+                        !self.relay_policy.is_seen(hash, now)
+                            && !self.transactions_pool.contains(hash)
+                    }
+                    _ => true,
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Handler for GetMempoolTransactions message
+impl Handler<GetMempoolTransactions> for ChainManager {
+    type Result = MessageResult<GetMempoolTransactions>;
+
+    fn handle(
+        &mut self,
+        GetMempoolTransactions { hashes }: GetMempoolTransactions,
+        _ctx: &mut Context<Self>,
+    ) -> Self::Result {
+        MessageResult(
+            hashes
+                .iter()
+                .filter_map(|hash| self.transactions_pool.get(hash).cloned())
+                .collect(),
+        )
     }
 }
 
@@ -339,8 +845,10 @@ impl Handler<PeersBeacons> for ChainManager {
                 // and set the state to Synchronizing
 
                 // Run the consensus on the beacons, will return the most common beacon
-                // In case of tie returns None
-                if let Some(beacon) = mode_consensus(pb.iter().map(|(_p, b)| b)).cloned() {
+                // In case of tie, or if it is not shared by enough peers, returns None
+                if let Some(beacon) =
+                    mode_consensus(pb.iter().map(|(_p, b)| b), self.consensus_percentage).cloned()
+                {
                     // Consensus: unregister peers which have a different beacon
                     let peers_out_of_consensus = pb
                         .into_iter()
@@ -356,7 +864,8 @@ impl Handler<PeersBeacons> for ChainManager {
                         .highest_block_checkpoint;
 
                     // Check if we are already synchronized
-                    self.sm_state = if our_beacon == beacon {
+                    let next_state = if our_beacon == beacon {
+                        self.snapshot_verified();
                         StateMachine::Synced
                     } else {
                         // Review candidates
@@ -372,28 +881,30 @@ impl Handler<PeersBeacons> for ChainManager {
                                 Err(e) => {
                                     debug!("Failed to consolidate consensus candidate: {}", e);
 
-                                    // Send Anycast<SendLastBeacon> to a safu peer in order to begin the synchronization
-                                    SessionsManager::from_registry().do_send(Anycast {
-                                        command: SendLastBeacon { beacon: our_beacon },
-                                        safu: true,
-                                    });
+                                    // Ask the headers to a safu peer in order to begin the synchronization
+                                    self.start_sync(beacon);
 
                                     StateMachine::Synchronizing
                                 }
                             }
                         } else {
-                            // Send Anycast<SendLastBeacon> to a safu peer in order to begin the synchronization
-                            SessionsManager::from_registry().do_send(Anycast {
-                                command: SendLastBeacon { beacon: our_beacon },
-                                safu: true,
-                            });
+                            // Ask the headers to a safu peer in order to begin the synchronization
+                            self.start_sync(beacon);
 
                             StateMachine::Synchronizing
                         }
                     };
+                    self.update_state_machine(next_state);
 
                     // Clear candidates
-                    self.candidates.clear();
+                    self.clear_candidates();
+
+                    // The transactions restored from storage can be validated once synced
+                    if let StateMachine::Synced = self.sm_state {
+                        for transaction in self.restored_transactions.drain(..) {
+                            ctx.notify(AddTransaction { transaction });
+                        }
+                    }
 
                     Ok(peers_out_of_consensus)
                 } else {
@@ -425,10 +936,10 @@ impl Handler<PeersBeacons> for ChainManager {
                     .unwrap()
                     .highest_block_checkpoint;
 
-                // We also take into account our beacon to calculate the consensus
-                // TODO: should we count our own beacon when deciding consensus?
+                // Only the beacons of the outbound peers count: our own beacon is what is being
+                // checked against the consensus
                 let consensus_beacon =
-                    mode_consensus(pb.iter().map(|(_p, b)| b).chain(&[our_beacon])).cloned();
+                    mode_consensus(pb.iter().map(|(_p, b)| b), self.consensus_percentage).cloned();
 
                 match consensus_beacon {
                     Some(a) if a == our_beacon => {
@@ -444,21 +955,27 @@ impl Handler<PeersBeacons> for ChainManager {
                         //self.target_beacon = Some(beacon);
                         Ok(peers_out_of_consensus)
                     }
-                    Some(_a) => {
-                        // We are out of consensus!
-                        // TODO: We should probably rewind(1) to avoid a fork, but for simplicity
-                        // (rewind is not implemented yet) we just print a message and carry on
+                    Some(a) => {
+                        // We are out of consensus! Synchronize towards the consensus beacon,
+                        // which reorganizes the chain if it is on a heavier competing branch
                         warn!(
                             "[CONSENSUS]: We are on {:?} but the network is on {:?}",
-                            our_beacon, consensus_beacon
+                            our_beacon, a
                         );
+                        let peers_out_of_consensus = pb
+                            .into_iter()
+                            .filter_map(|(p, b)| if b != a { Some(p) } else { None })
+                            .collect();
+                        self.target_beacon = Some(a);
+                        self.start_sync(a);
+                        self.update_state_machine(StateMachine::Synchronizing);
+                        self.clear_candidates();
 
-                        // Return an empty vector indicating that we do not want to unregister any peer
-                        Ok(vec![])
+                        Ok(peers_out_of_consensus)
                     }
                     None => {
-                        // There is no consensus because of a tie, do not rewind?
-                        // For example this could happen when each peer reports a different beacon...
+                        // There is no consensus because of a tie, or because no beacon is shared
+                        // by enough peers. Keep our chain until the network agrees on one
                         warn!(
                             "[CONSENSUS]: We are on {:?} but the network has no consensus",
                             our_beacon
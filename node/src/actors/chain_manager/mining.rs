@@ -6,9 +6,12 @@ use ansi_term::Color::{White, Yellow};
 use log::{debug, error, info, warn};
 
 use futures::future::{join_all, Future};
-use std::{collections::HashMap, time::Duration};
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
 
-use super::ChainManager;
+use super::{scheduler::RetrievalTask, BlockTemplate, ChainManager, ChainManagerError};
 use crate::actors::{
     messages::{
         AddCandidates, AddTransaction, GetHighestCheckpointBeacon, ResolveRA, RunConsensus,
@@ -17,21 +20,30 @@ use crate::actors::{
 };
 
 use crate::signature_mngr;
-use rand::Rng;
+use witnet_crypto::signature::{BLOCK_DOMAIN, TRANSACTION_DOMAIN};
 use witnet_data_structures::{
     chain::{
-        Block, BlockHeader, CheckpointBeacon, Hashable, Input, LeadershipProof, Output,
+        Block, BlockHeader, CheckpointBeacon, Epoch, Hashable, Input, LeadershipProof, Output,
         OutputPointer, PublicKeyHash, Transaction, TransactionsPool, UnspentOutputsPool,
-        ValueTransferOutput,
+        ValueTransferOutput, VrfProof,
+    },
+    data_request::{
+        calculate_data_request_poe, create_commit_body, create_reveal_body, create_tally_body,
+        create_vt_tally,
     },
-    data_request::{create_commit_body, create_reveal_body, create_tally_body, create_vt_tally},
     serializers::decoders::TryFrom,
 };
 use witnet_rad::types::RadonTypes;
+use witnet_util::timestamp::get_timestamp;
 use witnet_validations::validations::{
-    block_reward, merkle_tree_root, transaction_fee, validate_block, verify_poe_data_request,
+    block_reward, calculate_block_target, is_block_eligible, merkle_tree_root, transaction_fee,
+    validate_block, verify_poe_data_request,
 };
 
+/// Delay between the start of an epoch and the mining of its block. Commitments must be
+/// broadcast before it in order to be included in that block.
+pub const BLOCK_MINING_DELAY: Duration = Duration::from_secs(5);
+
 impl ChainManager {
     /// Try to mine a block
     pub fn try_mine_block(&mut self, ctx: &mut Context<Self>) {
@@ -45,8 +57,6 @@ impl ChainManager {
 
         debug!("Periodic epoch notification received {:?}", current_epoch);
 
-        // Check eligibility
-        // S(H(beacon))
         let mut beacon = match self.handle(GetHighestCheckpointBeacon, ctx) {
             Ok(b) => b,
             _ => return,
@@ -67,70 +77,170 @@ impl ChainManager {
         // The highest checkpoint beacon should contain the current epoch
         beacon.checkpoint = current_epoch;
 
-        // TODO: Use a real PoE
-        let poe = true;
-        if poe {
-            // FIXME (tmpolaczyk): block creation must happen after data request mining
-            // (we must wait for all the potential nodes to sent their transactions)
-            // The best way would be to start mining a few seconds _before_ the epoch
-            // checkpoint, but for simplicity we just wait for 5 seconds after the checkpoint
-            ctx.run_later(Duration::from_secs(5), move |act, ctx| {
-                info!(
-                    "{} Discovered eligibility for mining a block for epoch #{}",
-                    Yellow.bold().paint("[Mining]"),
-                    Yellow.bold().paint(beacon.checkpoint.to_string())
+        // Evaluate the VRF on the beacon with the key of the node to check the eligibility
+        signature_mngr::vrf_prove(&beacon)
+            .into_actor(self)
+            .map_err(|e, _, _| error!("Couldn't create VRF proof: {}", e))
+            .and_then(move |vrf_proof, act, ctx| {
+                let own_pkh = PublicKeyHash::from_public_key(&vrf_proof.public_key);
+                let target = calculate_block_target(
+                    &act.chain_state.data_request_pool.reputation_engine,
+                    &own_pkh,
+                );
+                let eligible = vrf_proof
+                    .proof_to_hash()
+                    .map(|vrf_output| is_block_eligible(vrf_output, target))
+                    .unwrap_or(false);
+                if !eligible {
+                    debug!(
+                        "Not eligible for mining a block for epoch #{}",
+                        beacon.checkpoint
+                    );
+                    return actix::fut::ok(());
+                }
+
+                // FIXME (tmpolaczyk): block creation must happen after data request mining
+                // (we must wait for all the potential nodes to sent their transactions)
+                // The best way would be to start mining a few seconds _before_ the epoch
+                // checkpoint, but for simplicity we just wait for 5 seconds after the checkpoint
+                ctx.run_later(BLOCK_MINING_DELAY, move |act, ctx| {
+                    act.mine_block(ctx, beacon, vrf_proof);
+                });
+
+                actix::fut::ok(())
+            })
+            .wait(ctx);
+    }
+
+    /// Build, sign and validate the block of the epoch of `beacon`, which the node is eligible
+    /// to mine according to `vrf_proof`, and add it to the candidates, which are broadcast
+    fn mine_block(
+        &mut self,
+        ctx: &mut Context<Self>,
+        beacon: CheckpointBeacon,
+        vrf_proof: VrfProof,
+    ) {
+        let current_epoch = beacon.checkpoint;
+        info!(
+            "{} Discovered eligibility for mining a block for epoch #{}",
+            Yellow.bold().paint("[Mining]"),
+            Yellow.bold().paint(beacon.checkpoint.to_string())
+        );
+
+        self.create_tally_transactions()
+            .join(
+                signature_mngr::sign(BLOCK_DOMAIN, &beacon)
+                    .map_err(|e| error!("Couldn't sign beacon: {}", e)),
+            )
+            .into_actor(self)
+            .and_then(move |(tally_transactions, keyed_signature), act, ctx| {
+                let leadership_proof = LeadershipProof {
+                    block_sig: keyed_signature,
+                    proof: vrf_proof,
+                };
+
+                // Build the block using the supplied beacon and eligibility proof
+                let block = build_block(
+                    &act.transactions_pool,
+                    &act.chain_state.unspent_outputs_pool,
+                    act.max_block_weight,
+                    beacon,
+                    leadership_proof,
+                    &tally_transactions,
                 );
-                // Send proof of eligibility to chain manager,
-                // which will construct and broadcast the block
-
-                act.create_tally_transactions()
-                    .join(
-                        signature_mngr::sign(&beacon)
-                            .map_err(|e| error!("Couldn't sign beacon: {}", e)),
-                    )
-                    .into_actor(act)
-                    .and_then(move |(tally_transactions, keyed_signature), act, ctx| {
-                        let leadership_proof = LeadershipProof {
-                            block_sig: keyed_signature,
-                        };
-
-                        // Build the block using the supplied beacon and eligibility proof
-                        let block = build_block(
-                            &act.transactions_pool,
-                            &act.chain_state.unspent_outputs_pool,
-                            act.max_block_weight,
-                            beacon,
-                            leadership_proof,
-                            &tally_transactions,
+
+                match validate_block(
+                    &block,
+                    current_epoch,
+                    beacon,
+                    act.genesis_block_hash,
+                    &act.chain_state.unspent_outputs_pool,
+                    &act.transactions_pool,
+                    &act.chain_state.data_request_pool,
+                    &mut act.signature_cache,
+                ) {
+                    Ok(_) => {
+                        // Send AddCandidates message to self
+                        // This will run all the validations again
+                        act.handle(
+                            AddCandidates {
+                                blocks: vec![block],
+                            },
+                            ctx,
                         );
+                    }
 
-                        match validate_block(
-                            &block,
-                            current_epoch,
-                            beacon,
-                            act.genesis_block_hash,
-                            &act.chain_state.unspent_outputs_pool,
-                            &act.transactions_pool,
-                            &act.chain_state.data_request_pool,
-                        ) {
-                            Ok(_) => {
-                                // Send AddCandidates message to self
-                                // This will run all the validations again
-                                act.handle(
-                                    AddCandidates {
-                                        blocks: vec![block],
-                                    },
-                                    ctx,
-                                );
-                            }
-
-                            Err(e) => error!("Error trying to mine a block: {}", e),
-                        }
+                    Err(e) => error!("Error trying to mine a block: {}", e),
+                }
 
-                        actix::fut::ok(())
-                    })
-                    .wait(ctx);
+                actix::fut::ok(())
+            })
+            .wait(ctx);
+    }
+
+    /// Build a template of the block to be mined in the current epoch, so that block
+    /// construction can be performed or audited by external software
+    pub fn build_block_template(
+        &mut self,
+        ctx: &mut Context<Self>,
+    ) -> ResponseActFuture<Self, BlockTemplate, failure::Error> {
+        let current_epoch = match self.current_epoch {
+            Some(epoch) => epoch,
+            None => return Box::new(actix::fut::err(ChainManagerError::ChainNotReady.into())),
+        };
+
+        let mut beacon = match self.handle(GetHighestCheckpointBeacon, ctx) {
+            Ok(b) => b,
+            Err(e) => return Box::new(actix::fut::err(e)),
+        };
+        // The beacon of the new block contains the current epoch
+        beacon.checkpoint = current_epoch;
+
+        let fut = self
+            .create_tally_transactions()
+            .into_actor(self)
+            .map_err(|(), _, _| ChainManagerError::TallyCreationFailed.into())
+            .map(move |tally_transactions, act, _| {
+                // The leadership proof will be provided by the external miner
+                let block = build_block(
+                    &act.transactions_pool,
+                    &act.chain_state.unspent_outputs_pool,
+                    act.max_block_weight,
+                    beacon,
+                    LeadershipProof::default(),
+                    &tally_transactions,
+                );
+
+                BlockTemplate {
+                    version: block.block_header.version,
+                    beacon: block.block_header.beacon,
+                    hash_merkle_root: block.block_header.hash_merkle_root,
+                    txns: block.txns,
+                }
             });
+
+        Box::new(fut)
+    }
+
+    /// Time left until the block of `epoch` is mined, which is the deadline for broadcasting the
+    /// commitments of that epoch
+    fn commitments_deadline(&self, epoch: Epoch) -> Duration {
+        let block_mining_timestamp = self
+            .epoch_constants
+            .and_then(|constants| constants.timestamp_of(epoch).ok())
+            .and_then(|timestamp| timestamp.checked_add(BLOCK_MINING_DELAY.as_secs() as i64));
+
+        match block_mining_timestamp {
+            Some(timestamp) => {
+                let now = get_timestamp();
+                if timestamp > now {
+                    Duration::from_secs((timestamp - now) as u64)
+                } else {
+                    Duration::from_secs(0)
+                }
+            }
+            // Assume that the epoch has just started
+            None => BLOCK_MINING_DELAY,
         }
     }
 
@@ -145,30 +255,90 @@ impl ChainManager {
 
         let current_epoch = self.current_epoch.unwrap();
 
+        // The eligibility for every data request depends on the identity of the node
+        signature_mngr::public_key()
+            .into_actor(self)
+            .map_err(|e, _, _| error!("Couldn't get the public key of the node: {}", e))
+            .map(move |public_key, act, ctx| {
+                let own_pkh = PublicKeyHash::from_public_key(&public_key);
+                act.mine_data_requests(ctx, current_epoch, own_pkh);
+            })
+            .wait(ctx);
+    }
+
+    /// Resolve and commit to the data requests of `current_epoch` which the node, whose identity
+    /// is `own_pkh`, is eligible for
+    fn mine_data_requests(
+        &mut self,
+        ctx: &mut Context<Self>,
+        current_epoch: Epoch,
+        own_pkh: PublicKeyHash,
+    ) {
         // Data Request mining
         let dr_output_pointers = self
             .chain_state
             .data_request_pool
             .get_dr_output_pointers_by_epoch(current_epoch);
 
-        for dr_output_pointer in dr_output_pointers {
-            let data_request_output = self
-                .chain_state
-                .data_request_pool
-                .get_dr_output(&dr_output_pointer);
-
-            if data_request_output.is_some() && verify_poe_data_request() {
-                let data_request_output = data_request_output.unwrap();
-                let rad_request = data_request_output.data_request.clone();
-
-                // Send ResolveRA message to RADManager
-                let rad_manager_addr = System::current().registry().get::<RadManager>();
-                rad_manager_addr
-                    .send(ResolveRA {
-                        rad_request,
-                    })
-                    .into_actor(self)
-                    .then(|result, _, _| match result {
+        // Commitments are due before the block of this epoch is mined
+        let deadline = self.commitments_deadline(current_epoch);
+        let tasks = dr_output_pointers
+            .into_iter()
+            .filter_map(|dr_output_pointer| {
+                let data_request_output = self
+                    .chain_state
+                    .data_request_pool
+                    .get_dr_output(&dr_output_pointer)?;
+
+                let poe = calculate_data_request_poe(&dr_output_pointer, &own_pkh);
+                if verify_poe_data_request(
+                    &self.chain_state.data_request_pool.reputation_engine,
+                    &dr_output_pointer,
+                    data_request_output.witnesses,
+                    &own_pkh,
+                    poe,
+                ) {
+                    Some(RetrievalTask::new(
+                        dr_output_pointer,
+                        data_request_output,
+                        deadline,
+                    ))
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        let schedule = self.witness_scheduler.schedule(tasks);
+        for task in schedule.preempted {
+            debug!(
+                "Postponing the retrieval of data request {} to meet the commitment deadlines \
+                 of more valuable data requests",
+                task.dr_pointer
+            );
+        }
+
+        for task in schedule.scheduled {
+            let RetrievalTask {
+                dr_pointer: dr_output_pointer,
+                dr_output: data_request_output,
+                rad_hash,
+                ..
+            } = task;
+            let rad_request = data_request_output.data_request.clone();
+
+            // Send ResolveRA message to RADManager
+            let rad_manager_addr = System::current().registry().get::<RadManager>();
+            let sent_at = Instant::now();
+            rad_manager_addr
+                .send(ResolveRA {
+                    rad_request,
+                })
+                .into_actor(self)
+                .then(move |result, act, _| {
+                    act.witness_scheduler.record_completion(rad_hash, sent_at, Instant::now());
+
+                    match result {
                         Ok(Ok(value)) => fut::ok(value),
                         Ok(Err(e)) => {
                             log::error!("Couldn't resolve rad request: {}", e);
@@ -178,52 +348,52 @@ impl ChainManager {
                             log::error!("Couldn't resolve rad request: {}", e);
                             fut::err(())
                         }
-                    })
-                    .and_then(move |reveal_value, act, _ctx| {
-                        // Create commitment transaction
-                        let commit_body = create_commit_body(&dr_output_pointer, &data_request_output, reveal_value.clone());
-                        signature_mngr::sign(&commit_body)
-                            .map_err(|e| log::error!("Couldn't sign commit body: {}", e))
-                            .into_actor(act)
-                            .and_then(move |sig, act, _ctx| {
-                                let commit_transaction = Transaction::new(commit_body, vec![sig]);
-                                let commit_pointer = OutputPointer {
-                                    transaction_id: commit_transaction.hash(),
-                                    output_index: 0,
-                                };
-                                let reveal_body = create_reveal_body(commit_pointer,  &data_request_output, reveal_value);
-
-                                signature_mngr::sign(&reveal_body)
-                                    .map_err(|e| log::error!("Couldn't sign reveal body: {}", e))
-                                    .into_actor(act)
-                                    .and_then(move |sig, act, ctx| {
-                                        let reveal_transaction = Transaction::new(reveal_body, vec![sig]);
-
-                                        // Hold reveal transaction under "waiting_for_reveal" field of data requests pool
-                                        act.chain_state.data_request_pool.insert_reveal(dr_output_pointer.clone(), reveal_transaction);
-
-                                        info!(
-                                            "{} Discovered eligibility for mining a data request {} for epoch #{}",
-                                            Yellow.bold().paint("[Mining]"),
-                                            Yellow.bold().paint(dr_output_pointer.to_string()),
-                                            Yellow.bold().paint(current_epoch.to_string())
-                                        );
-
-                                        // Send AddTransaction message to self
-                                        // And broadcast it to all of peers
-                                        act.handle(
-                                            AddTransaction {
-                                                transaction: commit_transaction,
-                                            },
-                                            ctx,
-                                        );
-
-                                        actix::fut::ok(())
-                                    })
-                            })
-                    })
-                    .wait(ctx)
-            }
+                    }
+                })
+                .and_then(move |reveal_value, act, _ctx| {
+                    // Create commitment transaction
+                    let commit_body = create_commit_body(&dr_output_pointer, &data_request_output, reveal_value.clone(), &own_pkh);
+                    signature_mngr::sign(TRANSACTION_DOMAIN, &commit_body)
+                        .map_err(|e| log::error!("Couldn't sign commit body: {}", e))
+                        .into_actor(act)
+                        .and_then(move |sig, act, _ctx| {
+                            let commit_transaction = Transaction::new(commit_body, vec![sig]);
+                            let commit_pointer = OutputPointer {
+                                transaction_id: commit_transaction.hash(),
+                                output_index: 0,
+                            };
+                            let reveal_body = create_reveal_body(commit_pointer,  &data_request_output, reveal_value);
+
+                            signature_mngr::sign(TRANSACTION_DOMAIN, &reveal_body)
+                                .map_err(|e| log::error!("Couldn't sign reveal body: {}", e))
+                                .into_actor(act)
+                                .and_then(move |sig, act, ctx| {
+                                    let reveal_transaction = Transaction::new(reveal_body, vec![sig]);
+
+                                    // Hold reveal transaction under "waiting_for_reveal" field of data requests pool
+                                    act.chain_state.data_request_pool.insert_reveal(dr_output_pointer.clone(), reveal_transaction);
+
+                                    info!(
+                                        "{} Discovered eligibility for mining a data request {} for epoch #{}",
+                                        Yellow.bold().paint("[Mining]"),
+                                        Yellow.bold().paint(dr_output_pointer.to_string()),
+                                        Yellow.bold().paint(current_epoch.to_string())
+                                    );
+
+                                    // Send AddTransaction message to self
+                                    // And broadcast it to all of peers
+                                    act.handle(
+                                        AddTransaction {
+                                            transaction: commit_transaction,
+                                        },
+                                        ctx,
+                                    );
+
+                                    actix::fut::ok(())
+                                })
+                        })
+                })
+                .wait(ctx)
         }
     }
 
@@ -260,7 +430,7 @@ impl ChainManager {
                     let tally_body =
                         create_tally_body(&dr_output, inputs, outputs, consensus.clone());
 
-                    signature_mngr::sign(&tally_body)
+                    signature_mngr::sign(TRANSACTION_DOMAIN, &tally_body)
                         .map_err(|e| log::error!("Couldn't sign tally body: {}", e))
                         .and_then(move |sig| {
                             let tally_transaction = Transaction::new(tally_body, vec![sig]);
@@ -311,8 +481,9 @@ fn build_block(
     proof: LeadershipProof,
     tally_transactions: &[Transaction],
 ) -> Block {
-    // Get all the unspent transactions and calculate the sum of their fees
-    let mut transaction_fees = 0;
+    // Get all the unspent transactions and add their fees to the block reward
+    let epoch = beacon.checkpoint;
+    let mut reward = block_reward(epoch);
     let mut block_weight = 0;
     let mut transactions = Vec::new();
 
@@ -326,8 +497,7 @@ fn build_block(
     // TODO: refactor this statement into a functional `try_fold`
     for transaction in tally_transactions.iter().chain(transactions_pool.iter()) {
         debug!("Pushing transaction into block: {:?}", transaction);
-        // Currently, 1 weight unit is equivalent to 1 byte
-        let transaction_weight = transaction.size();
+        let transaction_weight = transaction.weight();
         let transaction_fee = match transaction_fee(&transaction.body, unspent_outputs_pool) {
             Ok(x) => x,
             Err(e) => {
@@ -338,6 +508,13 @@ fn build_block(
                 continue;
             }
         };
+        let new_reward = match reward.checked_add(transaction_fee) {
+            Some(x) => x,
+            None => {
+                warn!("Block reward overflow when including transaction fee");
+                continue;
+            }
+        };
         let new_block_weight = block_weight + transaction_weight;
 
         if new_block_weight <= max_block_weight {
@@ -351,14 +528,14 @@ fn build_block(
                             // Ok, push commitment
                             *new_w += 1;
                             transactions.push(transaction.clone());
-                            transaction_fees += transaction_fee;
+                            reward = new_reward;
                             block_weight += transaction_weight;
                         }
                     }
                 }
             } else {
                 transactions.push(transaction.clone());
-                transaction_fees += transaction_fee;
+                reward = new_reward;
                 block_weight += transaction_weight;
             }
 
@@ -368,13 +545,9 @@ fn build_block(
         }
     }
 
-    // Include Mint Transaction by miner
-    // TODO: Include Witnet's node PKH (keyed signature is not needed as there is no input)
-    let mut pkh = PublicKeyHash::default();
-    pkh[0] = rand::thread_rng().gen();
-
-    let epoch = beacon.checkpoint;
-    let reward = block_reward(epoch) + transaction_fees;
+    // Include Mint Transaction by miner, paying the reward to the key of the leadership proof
+    // (keyed signature is not needed as there is no input)
+    let pkh = PublicKeyHash::from_public_key(&proof.block_sig.public_key);
 
     // Build Mint Transaction
     transactions[0]
@@ -406,9 +579,9 @@ mod tests {
     use secp256k1::{
         PublicKey as Secp256k1_PublicKey, Secp256k1, SecretKey as Secp256k1_SecretKey,
     };
-    use witnet_crypto::signature::{sign, verify};
-    use witnet_data_structures::chain::*;
-    use witnet_validations::validations::validate_block_signature;
+    use witnet_crypto::signature::{sign_tagged, verify_tagged};
+    use witnet_data_structures::{chain::*, reputation::ReputationEngine, wit::Wit};
+    use witnet_validations::validations::{validate_block_signature, verify_poe_block};
 
     #[test]
     fn build_empty_block() {
@@ -418,7 +591,7 @@ mod tests {
         // transaction size is 0 bytes (since missing fields are initialized with the default
         // values). Therefore version cannot be 0.
         let transaction = Transaction::default();
-        transaction_pool.insert(transaction.hash(), transaction.clone());
+        transaction_pool.insert(transaction.hash(), transaction.clone(), Wit::default(), 0);
 
         let unspent_outputs_pool = UnspentOutputsPool::default();
 
@@ -454,7 +627,7 @@ mod tests {
         // Initialize transaction_pool with 1 transaction
         let mut transaction_pool = TransactionsPool::default();
         let transaction = Transaction::default();
-        transaction_pool.insert(transaction.hash(), transaction.clone());
+        transaction_pool.insert(transaction.hash(), transaction.clone(), Wit::default(), 0);
 
         let unspent_outputs_pool = UnspentOutputsPool::default();
 
@@ -470,10 +643,10 @@ mod tests {
         let secret_key =
             Secp256k1_SecretKey::from_slice(&[0xcd; 32]).expect("32 bytes, within curve order");
         let public_key = Secp256k1_PublicKey::from_secret_key(&secp, &secret_key);
-        let signature = sign(secret_key, &data);
+        let signature = sign_tagged(secret_key, BLOCK_DOMAIN, &data);
 
         // Check Signature
-        assert!(verify(&public_key, &data, &signature).is_ok());
+        assert!(verify_tagged(&public_key, BLOCK_DOMAIN, &data, &signature).is_ok());
 
         let witnet_signature: Signature = Signature::from(signature);
         let witnet_pk: PublicKey = PublicKey::from(public_key);
@@ -481,8 +654,9 @@ mod tests {
         let block_proof = LeadershipProof {
             block_sig: KeyedSignature {
                 signature: witnet_signature,
-                public_key: witnet_pk,
+                public_key: witnet_pk.clone(),
             },
+            proof: VrfProof::new(&secret_key, &data).unwrap(),
         };
 
         // Build empty block (because max weight is zero)
@@ -504,8 +678,17 @@ mod tests {
         // Check that transaction in block is not the transaction in `transactions_pool`
         assert_ne!(block.txns[0], transaction);
 
-        // Validate block signature
+        // Validate block signature and proof of eligibility
         assert!(validate_block_signature(&block).is_ok());
+        assert!(verify_poe_block(&block, &ReputationEngine::default()).is_ok());
+
+        // The reward is paid to the miner
+        match &block.txns[0].body.outputs[0] {
+            Output::ValueTransfer(output) => {
+                assert_eq!(output.pkh, PublicKeyHash::from_public_key(&witnet_pk))
+            }
+            output => panic!("Unexpected mint output: {:?}", output),
+        }
     }
 
     #[test]
@@ -521,7 +704,7 @@ mod tests {
                 })],
                 vec![Output::ValueTransfer(ValueTransferOutput {
                     pkh: PublicKeyHash::default(),
-                    value: 1,
+                    value: Wit::from_nanowits(1),
                 })],
             ),
             vec![],
@@ -542,11 +725,11 @@ mod tests {
                 vec![
                     Output::ValueTransfer(ValueTransferOutput {
                         pkh: PublicKeyHash::default(),
-                        value: 2,
+                        value: Wit::from_nanowits(2),
                     }),
                     Output::ValueTransfer(ValueTransferOutput {
                         pkh: PublicKeyHash::default(),
-                        value: 3,
+                        value: Wit::from_nanowits(3),
                     }),
                 ],
             ),
@@ -568,11 +751,11 @@ mod tests {
                 vec![
                     Output::ValueTransfer(ValueTransferOutput {
                         pkh: PublicKeyHash::default(),
-                        value: 4,
+                        value: Wit::from_nanowits(4),
                     }),
                     Output::ValueTransfer(ValueTransferOutput {
                         pkh: PublicKeyHash::default(),
-                        value: 5,
+                        value: Wit::from_nanowits(5),
                     }),
                 ],
             ),
@@ -580,22 +763,34 @@ mod tests {
         );
 
         // Insert transactions into `transactions_pool`
-        // TODO: Currently the insert function does not take into account the fees to compute the transaction's weight
         let mut transaction_pool = TransactionsPool::default();
-        transaction_pool.insert(transaction_1.hash(), transaction_1.clone());
-        transaction_pool.insert(transaction_2.hash(), transaction_2.clone());
-        transaction_pool.insert(transaction_3.hash(), transaction_3.clone());
+        transaction_pool.insert(
+            transaction_1.hash(),
+            transaction_1.clone(),
+            Wit::default(),
+            0,
+        );
+        transaction_pool.insert(
+            transaction_2.hash(),
+            transaction_2.clone(),
+            Wit::default(),
+            0,
+        );
+        transaction_pool.insert(
+            transaction_3.hash(),
+            transaction_3.clone(),
+            Wit::default(),
+            0,
+        );
 
         let unspent_outputs_pool = UnspentOutputsPool::default();
 
-        // Set `max_block_weight` to fit only `transaction_1` size
-        let max_block_weight = transaction_1.size();
+        // Set `max_block_weight` to fit only `transaction_1` weight
+        let max_block_weight = transaction_1.weight();
 
         // Fields required to mine a block
         let block_beacon = CheckpointBeacon::default();
-        let block_proof = LeadershipProof {
-            block_sig: KeyedSignature::default(),
-        };
+        let block_proof = LeadershipProof::default();
 
         // Build block with
         let block = build_block(
@@ -647,7 +842,7 @@ mod tests {
             0x84, 0x9e, 0xc6, 0xb9,
         ];
 
-        let signature = sign(sk, &data);
+        let signature = sign(sk, &data).unwrap();
         assert!(verify(&public_key, &data, &signature).is_ok());
 
         // Conversion step
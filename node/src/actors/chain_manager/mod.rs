@@ -25,7 +25,11 @@
 //! * Updating the UTXO set with valid transactions that have already been anchored into a valid block. This includes:
 //!     - Removing the UTXOs that the transaction spends as inputs.
 //!     - Adding a new UTXO for every output in the transaction.
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, fs,
+    path::PathBuf,
+};
 
 use actix::prelude::*;
 use actix::{
@@ -34,34 +38,62 @@ use actix::{
 };
 use ansi_term::Color::{Purple, White, Yellow};
 use failure::Fail;
+use futures::{future, Future};
 use log::{debug, error, info, warn};
-
+use serde::{Deserialize, Serialize};
+
+use self::{
+    fast_sync::{crosses_superblock_boundary, export_snapshot},
+    relay::RelayPolicy,
+    reorg::{check_competing_branch, unwind_chain},
+    scheduler::WitnessScheduler,
+    sync::{locator_hashes, HeadersFirstSync},
+};
 use crate::actors::{
     inventory_manager::InventoryManager,
-    json_rpc::JsonRpcServer,
-    messages::{AddItem, AddTransaction, Broadcast, NewBlock, SendInventoryItem},
+    messages::{
+        AddItem, AddOrphanBlock, AddTransaction, Anycast, Broadcast, GetItem, PruneBlocks,
+        SendGetBlocks, SendInventoryAnnouncement, SendInventoryItem, SendInventoryRequest,
+        TakeOrphanBlocks,
+    },
     sessions_manager::SessionsManager,
-    storage_keys::CHAIN_STATE_KEY,
+    storage_keys::{chain_update_key, CHAIN_SNAPSHOT_KEY, CHAIN_STATE_KEY},
 };
+#[cfg(feature = "jsonrpc")]
+use crate::actors::{json_rpc::JsonRpcServer, messages::NewBlock};
 use crate::storage_mngr;
+use witnet_crypto::signature_cache::SignatureCache;
 use witnet_data_structures::{
     chain::{
-        Block, ChainState, CheckpointBeacon, DataRequestReport, Epoch, Hash, Hashable,
-        InventoryItem, Output, OutputPointer, Transaction, TransactionsPool, UnspentOutputsPool,
+        Block, ChainState, CheckpointBeacon, DataRequestReport, Epoch, EpochConstants, Hash,
+        Hashable, InventoryEntry, InventoryItem, Output, OutputPointer, Transaction,
+        TransactionsPool, UnspentOutputsPool,
     },
     data_request::DataRequestPool,
+    error::{BlockError, BlockValidationError},
     serializers::decoders::TryFrom,
 };
 use witnet_rad::types::RadonTypes;
+use witnet_util::timestamp::get_timestamp;
 use witnet_validations::validations::{validate_block, validate_candidate};
 
 mod actor;
+mod fast_sync;
+mod fork_choice;
 mod handlers;
 mod mining;
+mod relay;
+mod reorg;
+mod scheduler;
+mod sync;
 
 /// Maximum blocks number to be sent during synchronization process
 pub const MAX_BLOCKS_SYNC: usize = 500;
 
+/// Maximum number of blocks persisted as updates of the chain state after a snapshot, before
+/// persisting a new snapshot
+pub const CHAIN_SNAPSHOT_PERIOD: usize = 100;
+
 /// Possible errors when interacting with ChainManager
 #[derive(Debug, PartialEq, Fail)]
 pub enum ChainManagerError {
@@ -74,10 +106,39 @@ pub enum ChainManagerError {
     /// StorageError
     #[fail(display = "ChainManager is not ready yet")]
     ChainNotReady,
+    /// The tally transactions of a block could not be created
+    #[fail(display = "Failed to create the tally transactions")]
+    TallyCreationFailed,
+    /// The memory cap for block candidates was reached
+    #[fail(
+        display = "Memory cap for block candidates reached, discarding candidate {}",
+        hash
+    )]
+    CandidatesMemoryCapReached {
+        /// Hash of the discarded candidate
+        hash: Hash,
+    },
+}
+
+/// Block template for external miners
+///
+/// The leadership proof of the block is the signature of `beacon`. Miners can replace the
+/// outputs of the mint transaction (the first one in `txns`), as long as they recompute the
+/// `hash_merkle_root` accordingly.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockTemplate {
+    /// Block header version
+    pub version: u32,
+    /// Beacon of the block: current epoch and hash of the previous block
+    pub beacon: CheckpointBeacon,
+    /// Merkle root of the selected transactions
+    pub hash_merkle_root: Hash,
+    /// Selected transactions, starting with the mint transaction
+    pub txns: Vec<Transaction>,
 }
 
 /// State Machine
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum StateMachine {
     /// First state, ChainManager is waiting to consensus between its peers
     WaitingConsensus,
@@ -103,6 +164,8 @@ pub struct ChainManager {
     chain_state: ChainState,
     /// Current Epoch
     current_epoch: Option<Epoch>,
+    /// Constants to convert between epochs and timestamps
+    epoch_constants: Option<EpochConstants>,
     /// Transactions Pool (_mempool_)
     transactions_pool: TransactionsPool,
     /// Maximum weight each block can have
@@ -113,10 +176,49 @@ pub struct ChainManager {
     genesis_block_hash: Hash,
     /// state of the state machine
     sm_state: StateMachine,
+    /// Timestamp of the consolidation of the last block
+    last_block_timestamp: Option<i64>,
     /// The best beacon known to this node—to which it will try to catch up
     target_beacon: Option<CheckpointBeacon>,
+    /// Percentage of the outbound peers that must agree on a beacon for it to be the consensus
+    consensus_percentage: u32,
+    /// State of the synchronization towards the target beacon, if any
+    sync: Option<HeadersFirstSync>,
+    /// Memory cap for the headers and blocks of a synchronization, `0` means no cap
+    sync_max_memory: usize,
     /// Map that stores candidate blocks for further validation and consolidation as tip of the blockchain
     candidates: HashMap<Hash, Block>,
+    /// Approximate number of bytes used by the candidate blocks
+    candidates_memory_usage: usize,
+    /// Memory cap for the candidate blocks, `0` means no cap
+    candidates_max_memory: usize,
+    /// Memory cap for the transactions pool, `0` means no cap
+    transactions_pool_max_memory: usize,
+    /// Weight cap for the transactions pool, `0` means no cap
+    transactions_pool_max_weight: u64,
+    /// Epochs a transaction is kept in the transactions pool, `0` means forever
+    transactions_pool_expiry_epochs: Epoch,
+    /// Policy deciding which transactions are relayed to the peers, and when
+    relay_policy: RelayPolicy,
+    /// Scheduler for the retrievals of the data requests this node is eligible for
+    witness_scheduler: WitnessScheduler,
+    /// Successful signature verifications, shared between the transactions pool admission and
+    /// the validation of blocks
+    signature_cache: SignatureCache,
+    /// Beacon of the last block whose unspent outputs and data requests were persisted
+    persisted_beacon: Option<CheckpointBeacon>,
+    /// Epochs of the blocks persisted as updates of the chain state since the last snapshot
+    chain_updates: Vec<Epoch>,
+    /// Transactions of the mempool persisted on the last shutdown, added again to the mempool
+    /// once the node is synced
+    restored_transactions: Vec<Transaction>,
+    /// Directory where the chain state is exported at every superblock boundary, if any
+    snapshots_export_path: Option<PathBuf>,
+    /// Beacon of the imported chain state snapshot, until the consensus chain of the peers is
+    /// found to contain its block
+    unverified_snapshot: Option<CheckpointBeacon>,
+    /// Epochs of blocks whose bodies are kept when pruning is enabled
+    pruning_keep_epochs: Option<Epoch>,
 }
 
 /// Required trait for being able to retrieve ChainManager address from registry
@@ -127,8 +229,9 @@ impl SystemService for ChainManager {}
 
 /// Auxiliary methods for ChainManager actor
 impl ChainManager {
-    /// Method to persist chain_info into storage
-    fn persist_chain_state(&self, ctx: &mut Context<Self>) {
+    /// Method to persist chain_info into storage, along with the changes made by the last
+    /// consolidated block to the unspent outputs and the data requests, in a single write
+    fn persist_chain_state(&mut self, ctx: &mut Context<Self>) {
         match self.chain_state.chain_info.as_ref() {
             Some(x) => x,
             None => {
@@ -137,7 +240,16 @@ impl ChainManager {
             }
         };
 
-        storage_mngr::put(&CHAIN_STATE_KEY, &self.chain_state)
+        let mut batch = storage_mngr::WriteBatch::default();
+        let res = batch
+            .put(&CHAIN_STATE_KEY, &self.chain_state)
+            .and_then(|()| self.add_chain_update(&mut batch));
+        if let Err(e) = res {
+            error!("Failed to serialize chain state: {}", e);
+            return;
+        }
+
+        storage_mngr::write(batch)
             .into_actor(self)
             .and_then(|_, _, _| {
                 debug!("Successfully persisted chain_info into storage");
@@ -147,6 +259,138 @@ impl ChainManager {
             .wait(ctx);
     }
 
+    /// Method to add to `batch` the changes made by the last consolidated block to the unspent
+    /// outputs and the data requests. A snapshot of them is added instead every
+    /// `CHAIN_SNAPSHOT_PERIOD` blocks, or if the previous block was not persisted
+    fn add_chain_update(
+        &mut self,
+        batch: &mut storage_mngr::WriteBatch,
+    ) -> Result<(), failure::Error> {
+        let tip = match self.chain_state.chain_info.as_ref() {
+            Some(chain_info) => chain_info.highest_block_checkpoint,
+            None => return Ok(()),
+        };
+        let previous_block = self
+            .chain_state
+            .block_chain
+            .range(..tip.checkpoint)
+            .next_back()
+            .map(|(epoch, hash)| CheckpointBeacon {
+                checkpoint: *epoch,
+                hash_prev_block: *hash,
+            });
+
+        let update = if self.persisted_beacon.is_some()
+            && self.persisted_beacon == previous_block
+            && self.chain_updates.len() < CHAIN_SNAPSHOT_PERIOD
+        {
+            self.chain_state.last_update()
+        } else {
+            None
+        };
+
+        match update {
+            Some(update) => {
+                batch.put(&chain_update_key(tip.checkpoint), &update)?;
+                self.chain_updates.push(tip.checkpoint);
+                self.persisted_beacon = Some(tip);
+            }
+            None => self.add_chain_snapshot(batch)?,
+        }
+
+        Ok(())
+    }
+
+    /// Method to add to `batch` a snapshot of the unspent outputs and the data requests,
+    /// replacing the previous snapshot and its updates
+    fn add_chain_snapshot(
+        &mut self,
+        batch: &mut storage_mngr::WriteBatch,
+    ) -> Result<(), failure::Error> {
+        let snapshot = match self.chain_state.snapshot() {
+            Some(snapshot) => snapshot,
+            None => return Ok(()),
+        };
+
+        // The updates before the snapshot are not needed anymore, so they are deleted along with
+        // the previous snapshot
+        batch.put(&CHAIN_SNAPSHOT_KEY, &snapshot)?;
+        self.chain_updates
+            .drain(..)
+            .try_for_each(|epoch| batch.delete(&chain_update_key(epoch)))?;
+        self.persisted_beacon = Some(snapshot.beacon);
+
+        Ok(())
+    }
+
+    /// Method to add to `batch` the chain state along with a snapshot of the unspent outputs and
+    /// the data requests, replacing the previous snapshot and its updates, so that the node is
+    /// restored from them without replaying any block. Nothing is added if the chain state is
+    /// empty
+    fn add_chain_state_snapshot(
+        &mut self,
+        batch: &mut storage_mngr::WriteBatch,
+    ) -> Result<(), failure::Error> {
+        if self.chain_state.chain_info.is_none() {
+            return Ok(());
+        }
+
+        batch.put(&CHAIN_STATE_KEY, &self.chain_state)?;
+        self.add_chain_snapshot(batch)
+    }
+
+    /// Method to export the unspent outputs and the data requests at the tip of the chain to a
+    /// file of `snapshots_export_path`, so that other nodes can import them to synchronize from
+    /// the tip instead of from the genesis block
+    fn export_chain_state(&self) {
+        let export_path = match self.snapshots_export_path.as_ref() {
+            Some(export_path) => export_path,
+            None => return,
+        };
+        let snapshot = match self.chain_state.snapshot() {
+            Some(snapshot) => snapshot,
+            None => return,
+        };
+
+        let epoch = snapshot.beacon.checkpoint;
+        let path = export_path.join(format!("chain_state_{}.json", epoch));
+        let res = export_snapshot(&snapshot)
+            .map_err(failure::Error::from)
+            .and_then(|(hash, bytes)| {
+                fs::write(&path, bytes)?;
+
+                Ok(hash)
+            });
+        match res {
+            Ok(hash) => info!(
+                "Exported the chain state at epoch {} with integrity hash {} to {}",
+                epoch,
+                hash,
+                path.display()
+            ),
+            Err(e) => error!("Failed to export the chain state at epoch {}: {}", epoch, e),
+        }
+    }
+
+    /// Method to prune the bodies of the blocks older than `pruning_keep_epochs`, if pruning is
+    /// enabled. Their headers are kept so the chain can still be served to the peers.
+    fn prune_blocks(&mut self) {
+        let keep_epochs = match self.pruning_keep_epochs {
+            Some(keep_epochs) => keep_epochs,
+            None => return,
+        };
+
+        let hashes = self.chain_state.blocks_to_prune(keep_epochs);
+        if !hashes.is_empty() {
+            debug!(
+                "Pruning {} blocks before epoch {}",
+                hashes.len(),
+                self.chain_state.pruned_until
+            );
+            InventoryManager::from_registry().do_send(PruneBlocks { hashes });
+        }
+    }
+
     /// Method to Send an Item to Inventory Manager
     fn persist_item(&self, ctx: &mut Context<Self>, item: InventoryItem) {
         // Get InventoryManager address
@@ -203,6 +447,242 @@ impl ChainManager {
         });
     }
 
+    /// Method to announce the next batch of accepted transactions to the peers, and to forget
+    /// the transactions seen too long ago
+    fn announce_transactions(&mut self) {
+        self.relay_policy.expire(get_timestamp());
+
+        let items = self.relay_policy.take_announcement();
+        if items.is_empty() {
+            return;
+        }
+        debug!("Announcing {} transactions to the peers", items.len());
+
+        SessionsManager::from_registry().do_send(Broadcast {
+            command: SendInventoryAnnouncement { items },
+            only_inbound: false,
+        });
+    }
+
+    /// Method to start synchronizing the chain up to `target`, beginning with the headers
+    fn start_sync(&mut self, target: CheckpointBeacon) {
+        let chain_beacon = match self.chain_state.chain_info.as_ref() {
+            Some(chain_info) => chain_info.highest_block_checkpoint,
+            None => {
+                error!("No ChainInfo loaded in ChainManager");
+                return;
+            }
+        };
+
+        info!(
+            "Synchronizing from epoch #{} to epoch #{}",
+            chain_beacon.checkpoint, target.checkpoint
+        );
+        self.sync = Some(HeadersFirstSync::new(
+            chain_beacon,
+            target,
+            self.sync_max_memory,
+        ));
+        self.request_headers();
+    }
+
+    /// Method to request the headers following the headers tip to a peer in consensus
+    fn request_headers(&mut self) {
+        let now = get_timestamp();
+        let block_chain = &self.chain_state.block_chain;
+        let genesis_block_hash = self.genesis_block_hash;
+        let result = match self.sync.as_mut() {
+            Some(sync) => sync.headers_requested(now).map(|()| {
+                locator_hashes(
+                    sync.headers_tip().hash_prev_block,
+                    block_chain.values().rev(),
+                    genesis_block_hash,
+                )
+            }),
+            None => return,
+        };
+
+        match result {
+            Ok(locator_hashes) => {
+                SessionsManager::from_registry().do_send(Anycast {
+                    command: SendGetBlocks { locator_hashes },
+                    safu: true,
+                });
+            }
+            Err(e) => self.abort_sync(e),
+        }
+    }
+
+    /// Method to request the windows of blocks that are due, each one to a peer in consensus
+    fn request_blocks(&mut self) {
+        let now = get_timestamp();
+        let result = match self.sync.as_mut() {
+            Some(sync) => sync.next_windows(now),
+            None => return,
+        };
+
+        match result {
+            Ok(windows) => {
+                for window in windows {
+                    SessionsManager::from_registry().do_send(Anycast {
+                        command: SendInventoryRequest {
+                            items: window.into_iter().map(InventoryEntry::Block).collect(),
+                        },
+                        safu: true,
+                    });
+                }
+            }
+            Err(e) => self.abort_sync(e),
+        }
+    }
+
+    /// Method to request again the headers and blocks not delivered in time
+    fn check_sync_timeouts(&mut self) {
+        let now = get_timestamp();
+        let headers_expired = self
+            .sync
+            .as_ref()
+            .map(|sync| sync.headers_expired(now))
+            .unwrap_or(false);
+        if headers_expired {
+            self.request_headers();
+        }
+
+        self.request_blocks();
+    }
+
+    /// Method to give up the synchronization and wait for a new consensus
+    fn abort_sync<E: fmt::Display>(&mut self, reason: E) {
+        warn!("Synchronization aborted: {}", reason);
+        self.sync = None;
+        self.update_state_machine(StateMachine::WaitingConsensus);
+    }
+
+    /// Method to record that the block of the imported snapshot, if any, is on the chain agreed
+    /// by the peers
+    fn snapshot_verified(&mut self) {
+        if let Some(beacon) = self.unverified_snapshot.take() {
+            info!(
+                "The imported chain state snapshot at epoch {} is on the consensus chain",
+                beacon.checkpoint
+            );
+        }
+    }
+
+    /// Method to move the state machine to the `next` state, logging the transitions
+    fn update_state_machine(&mut self, next: StateMachine) {
+        if self.sm_state != next {
+            info!("State machine: {:?} -> {:?}", self.sm_state, next);
+            self.sm_state = next;
+        }
+    }
+
+    /// Method to replace the blocks after `fork_point` with the `blocks` of a heavier competing
+    /// branch. The branch is validated on a copy of the chain state first, so if any of its
+    /// blocks is not valid, the chain, the transactions pool and the storage are left as they were
+    fn reorganize(
+        &mut self,
+        ctx: &mut Context<Self>,
+        fork_point: CheckpointBeacon,
+        blocks: Vec<Block>,
+    ) -> Result<(), failure::Error> {
+        let current_epoch = self.current_epoch.ok_or(ChainManagerError::ChainNotReady)?;
+        check_competing_branch(&self.chain_state, fork_point.checkpoint, &blocks)?;
+
+        let mut chain_state = self.chain_state.clone();
+        let unwound = unwind_chain(&mut chain_state, fork_point)?;
+        let mut consolidated = Vec::with_capacity(blocks.len());
+        for block in blocks {
+            let chain_beacon = chain_state
+                .chain_info
+                .as_ref()
+                .ok_or(ChainManagerError::ChainNotReady)?
+                .highest_block_checkpoint;
+            let block_in_chain = validate_block(
+                &block,
+                current_epoch,
+                chain_beacon,
+                self.genesis_block_hash,
+                &chain_state.unspent_outputs_pool,
+                &self.transactions_pool,
+                &chain_state.data_request_pool,
+                &mut self.signature_cache,
+            )?;
+            let (reveals, finished) =
+                chain_state.apply_block(&block_in_chain.block, block_in_chain.data_request_pool);
+            consolidated.push((block_in_chain.block, reveals, finished));
+        }
+
+        // The whole branch is valid, so its side effects can take place
+        info!(
+            "Chain reorganized at epoch #{}: {} blocks replaced",
+            fork_point.checkpoint,
+            unwound.len()
+        );
+        self.chain_state = chain_state;
+        self.last_block_timestamp = Some(get_timestamp());
+        let included = consolidated
+            .iter()
+            .flat_map(|(block, _, _)| block.txns.iter().map(Hashable::hash))
+            .collect();
+        self.restore_unwound_transactions(ctx, unwound, included);
+        let tip = consolidated
+            .last()
+            .map_or(fork_point.hash_prev_block, |(block, _, _)| block.hash());
+        for (block, reveals, finished) in consolidated {
+            self.block_consolidated(ctx, block, reveals, finished, false);
+        }
+        if let StateMachine::Synced = self.sm_state {
+            self.prune_blocks();
+            self.persist_chain_state(ctx);
+            self.process_orphans(ctx, tip);
+        }
+
+        Ok(())
+    }
+
+    /// Method to add the transactions of the `unwound` blocks to the transactions pool again,
+    /// except the mint transactions and the ones `included` in the blocks which replaced them.
+    /// They are validated again against the new chain once the node is synced
+    fn restore_unwound_transactions(
+        &self,
+        ctx: &mut Context<Self>,
+        unwound: Vec<Hash>,
+        included: HashSet<Hash>,
+    ) {
+        let inventory_manager_addr = System::current().registry().get::<InventoryManager>();
+        let blocks = unwound.into_iter().map(move |hash| {
+            inventory_manager_addr
+                .send(GetItem { hash })
+                .then(move |res| match res {
+                    Ok(Ok(InventoryItem::Block(block))) => Ok::<_, ()>(Some(block)),
+                    _ => {
+                        warn!("Unwound block {} not found in storage", hash);
+                        Ok(None)
+                    }
+                })
+        });
+
+        future::join_all(blocks)
+            .into_actor(self)
+            .map(move |blocks: Vec<Option<Block>>, act, ctx| {
+                let transactions = blocks
+                    .into_iter()
+                    .flatten()
+                    .flat_map(|block| block.txns.into_iter().skip(1))
+                    .filter(|transaction| !included.contains(&transaction.hash()));
+                for transaction in transactions {
+                    act.relay_policy.forget(&transaction.hash());
+                    if let StateMachine::Synced = act.sm_state {
+                        ctx.notify(AddTransaction { transaction });
+                    } else {
+                        act.restored_transactions.push(transaction);
+                    }
+                }
+            })
+            .spawn(ctx);
+    }
+
     fn process_requested_block(
         &mut self,
         ctx: &mut Context<Self>,
@@ -221,41 +701,139 @@ impl ChainManager {
                 &self.chain_state.unspent_outputs_pool,
                 &self.transactions_pool,
                 &self.chain_state.data_request_pool,
+                &mut self.signature_cache,
             ) {
                 Ok(block_in_chain) => {
                     // Persist block and update ChainState
                     self.consolidate_block(
                         ctx,
                         block_in_chain.block,
-                        block_in_chain.utxo_set,
                         block_in_chain.data_request_pool,
                         false,
                     );
 
                     Ok(())
                 }
-                Err(e) => Err(e),
+                Err(e) => {
+                    if let BlockValidationError::Beacon(BlockError::PreviousHashNotKnown {
+                        ..
+                    }) = e
+                    {
+                        // Keep the block until its parent arrives
+                        let inventory_manager_addr =
+                            System::current().registry().get::<InventoryManager>();
+                        inventory_manager_addr.do_send(AddOrphanBlock { block });
+                    }
+
+                    Err(e.into())
+                }
             }
         } else {
             Err(ChainManagerError::ChainNotReady)?
         }
     }
 
-    fn process_candidate(&mut self, block: Block) {
-        if let Some(current_epoch) = self.current_epoch {
-            let hash_block = block.hash();
+    /// Method to process the orphan blocks waiting for the block `parent`, which has just been
+    /// consolidated
+    fn process_orphans(&self, ctx: &mut Context<Self>, parent: Hash) {
+        let inventory_manager_addr = System::current().registry().get::<InventoryManager>();
 
-            if !self.candidates.contains_key(&hash_block) {
-                match validate_candidate(&block, current_epoch) {
-                    Ok(()) => {
-                        self.candidates.insert(hash_block, block.clone());
-                        self.broadcast_item(InventoryItem::Block(block));
+        inventory_manager_addr
+            .send(TakeOrphanBlocks { parent })
+            .into_actor(self)
+            .map_err(|e, _, _| error!("Unsuccessful communication with InventoryManager: {}", e))
+            .and_then(|orphans, act, ctx| {
+                // Blocks are only consolidated out of a synchronization when the chain is synced
+                if let StateMachine::Synced = act.sm_state {
+                    for orphan in orphans {
+                        let hash = orphan.hash();
+                        match act.process_requested_block(ctx, orphan) {
+                            Ok(()) => debug!("Orphan block {} consolidated", hash),
+                            Err(e) => debug!("Failed to consolidate orphan block {}: {}", hash, e),
+                        }
                     }
-                    Err(e) => warn!("{}", e),
                 }
+
+                fut::ok(())
+            })
+            .spawn(ctx);
+    }
+
+    fn process_candidate(&mut self, block: Block) -> Result<(), failure::Error> {
+        let current_epoch = self.current_epoch.ok_or(ChainManagerError::ChainNotReady)?;
+        let hash_block = block.hash();
+
+        if self.candidates.contains_key(&hash_block) {
+            return Ok(());
+        }
+
+        validate_candidate(
+            &block,
+            current_epoch,
+            &self.chain_state.data_request_pool.reputation_engine,
+        )?;
+
+        let block_memory_usage = block.size() as usize;
+        if self.candidates_max_memory != 0
+            && self.candidates_memory_usage + block_memory_usage > self.candidates_max_memory
+        {
+            Err(ChainManagerError::CandidatesMemoryCapReached { hash: hash_block })?
+        }
+
+        self.candidates_memory_usage += block_memory_usage;
+        self.candidates.insert(hash_block, block.clone());
+        self.broadcast_item(InventoryItem::Block(block));
+
+        Ok(())
+    }
+
+    fn clear_candidates(&mut self) {
+        self.candidates.clear();
+        self.candidates_memory_usage = 0;
+    }
+
+    /// Evict the transactions with smaller fees when the transactions pool exceeds its memory
+    /// cap or its weight cap
+    fn evict_transactions(&mut self) {
+        if self.transactions_pool_max_memory != 0 {
+            let evicted = self
+                .transactions_pool
+                .evict(self.transactions_pool_max_memory);
+            if !evicted.is_empty() {
+                info!(
+                    "Memory cap for the transactions pool reached, evicted {} transactions",
+                    evicted.len()
+                );
             }
-        } else {
-            warn!("ChainManager doesn't have current epoch");
+        }
+
+        if self.transactions_pool_max_weight != 0 {
+            let evicted = self
+                .transactions_pool
+                .evict_weight(self.transactions_pool_max_weight);
+            if !evicted.is_empty() {
+                info!(
+                    "Weight cap for the transactions pool reached, evicted {} transactions",
+                    evicted.len()
+                );
+            }
+        }
+    }
+
+    /// Remove the transactions which have been in the transactions pool for too many epochs
+    fn expire_transactions(&mut self, epoch: Epoch) {
+        if self.transactions_pool_expiry_epochs == 0 {
+            return;
+        }
+
+        let expired = self
+            .transactions_pool
+            .remove_expired(epoch, self.transactions_pool_expiry_epochs);
+        if !expired.is_empty() {
+            debug!(
+                "Removed {} expired transactions from the transactions pool",
+                expired.len()
+            );
         }
     }
 
@@ -263,74 +841,84 @@ impl ChainManager {
         &mut self,
         ctx: &mut Context<Self>,
         block: Block,
-        utxo_set: UnspentOutputsPool,
         dr_pool: DataRequestPool,
         info_flag: bool,
     ) {
-        // Update chain_info
-        match self.chain_state.chain_info.as_mut() {
-            Some(chain_info) => {
-                let block_hash = block.hash();
-                let block_epoch = block.block_header.beacon.checkpoint;
-
-                // Update `highest_block_checkpoint`
-                let beacon = CheckpointBeacon {
-                    checkpoint: block_epoch,
-                    hash_prev_block: block_hash,
-                };
-                chain_info.highest_block_checkpoint = beacon;
-
-                // Update UnspentOutputsPool
-                self.chain_state.unspent_outputs_pool = utxo_set;
-
-                // Update TransactionPool
-                update_transaction_pool(&mut self.transactions_pool, block.txns.as_ref());
-
-                // Update DataRequestPool
-                self.chain_state.data_request_pool = dr_pool;
-                let reveals = self
-                    .chain_state
-                    .data_request_pool
-                    .update_data_request_stages();
-                for reveal in reveals {
-                    // Send AddTransaction message to self
-                    // And broadcast it to all of peers
-                    ctx.address().do_send(AddTransaction {
-                        transaction: reveal,
-                    })
-                }
-                // Persist finished data requests into storage
-                let to_be_stored = self.chain_state.data_request_pool.finished_data_requests();
-                to_be_stored.into_iter().for_each(|dr| {
-                    self.persist_data_request(ctx, &dr);
-                    if info_flag {
-                        show_info_tally(&self.chain_state.unspent_outputs_pool, dr, block_epoch);
-                    }
-                });
+        // The state after the last block of a superblock period is exported before consolidating
+        // the first block of the next one
+        if let (StateMachine::Synced, Some(chain_info)) =
+            (self.sm_state, self.chain_state.chain_info.as_ref())
+        {
+            let tip_epoch = chain_info.highest_block_checkpoint.checkpoint;
+            if tip_epoch > 0
+                && crosses_superblock_boundary(tip_epoch, block.block_header.beacon.checkpoint)
+            {
+                self.export_chain_state();
+            }
+        }
 
-                if info_flag {
-                    show_info_dr(&self.chain_state.data_request_pool, &block);
+        if self.chain_state.chain_info.is_none() {
+            error!("No ChainInfo loaded in ChainManager");
+            return;
+        }
 
-                    debug!("{:?}", block);
-                    debug!("Mint transaction hash: {:?}", block.txns[0].hash());
-                }
+        let block_hash = block.hash();
+        let (reveals, finished) = self.chain_state.apply_block(&block, dr_pool);
+        self.last_block_timestamp = Some(get_timestamp());
+        self.block_consolidated(ctx, block, reveals, finished, info_flag);
 
-                // Insert candidate block into `block_chain` and persist it
-                self.chain_state.block_chain.insert(block_epoch, block_hash);
-                self.persist_item(ctx, InventoryItem::Block(block.clone()));
+        // Persist chain_info into storage, and process the blocks waiting for this one
+        if let StateMachine::Synced = self.sm_state {
+            self.prune_blocks();
+            self.persist_chain_state(ctx);
+            self.process_orphans(ctx, block_hash);
+        }
+    }
 
-                // Persist chain_info into storage
-                if let StateMachine::Synced = self.sm_state {
-                    self.persist_chain_state(ctx);
-                }
+    /// Method to carry out the side effects of a block just applied to the chain state: removing
+    /// its transactions from the transactions pool, sending the `reveals` of our commitments,
+    /// persisting the `finished` data requests and the block itself, and notifying it
+    fn block_consolidated(
+        &mut self,
+        ctx: &mut Context<Self>,
+        block: Block,
+        reveals: Vec<Transaction>,
+        finished: Vec<(OutputPointer, DataRequestReport)>,
+        info_flag: bool,
+    ) {
+        let block_epoch = block.block_header.beacon.checkpoint;
 
-                // Send notification to JsonRpcServer
-                JsonRpcServer::from_registry().do_send(NewBlock { block })
-            }
-            None => {
-                error!("No ChainInfo loaded in ChainManager");
+        // Update TransactionPool
+        update_transaction_pool(&mut self.transactions_pool, block.txns.as_ref());
+
+        for reveal in reveals {
+            // Send AddTransaction message to self
+            // And broadcast it to all of peers
+            ctx.address().do_send(AddTransaction {
+                transaction: reveal,
+            })
+        }
+        // Persist finished data requests into storage
+        finished.into_iter().for_each(|dr| {
+            self.persist_data_request(ctx, &dr);
+            if info_flag {
+                show_info_tally(&self.chain_state.unspent_outputs_pool, dr, block_epoch);
             }
+        });
+
+        if info_flag {
+            show_info_dr(&self.chain_state.data_request_pool, &block);
+
+            debug!("{:?}", block);
+            debug!("Mint transaction hash: {:?}", block.txns[0].hash());
         }
+
+        // Persist the block
+        self.persist_item(ctx, InventoryItem::Block(block.clone()));
+
+        // Send notification to JsonRpcServer
+        #[cfg(feature = "jsonrpc")]
+        JsonRpcServer::from_registry().do_send(NewBlock { block });
     }
 }
 
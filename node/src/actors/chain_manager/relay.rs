@@ -0,0 +1,316 @@
+//! # Transaction relay policy
+//!
+//! Not every valid transaction is accepted into the pool and relayed to the peers:
+//!
+//! * A transaction is only processed the first time it is seen. Its hash is remembered for
+//!   `SEEN_TRANSACTIONS_TTL` seconds, valid or not, and announcements of seen transactions are
+//!   not requested again.
+//! * Value transfers and data requests must pay at least the minimum relay fee per unit of
+//!   weight. Commits, reveals and tallies pay the fees set by their data request.
+//! * A transaction spending an output already spent by a transaction of the pool is a double
+//!   spend, so the first one seen is kept.
+//!
+//! Accepted transactions are not sent to the peers one by one. Their hashes are queued and
+//! announced to every peer in batches of up to `MAX_ANNOUNCEMENT_ITEMS` every
+//! `RELAY_ANNOUNCEMENT_PERIOD` seconds, and the peers request the ones they have not seen.
+use std::collections::{HashMap, VecDeque};
+
+use failure::Fail;
+
+use witnet_data_structures::{
+    chain::{
+        transaction_priority, Hash, InventoryEntry, Transaction, TransactionType, TransactionsPool,
+    },
+    wit::Wit,
+};
+use witnet_validations::validations::transaction_tag;
+
+/// Seconds the hash of a seen transaction is remembered
+pub const SEEN_TRANSACTIONS_TTL: i64 = 600;
+
+/// Maximum number of hashes of seen transactions remembered
+pub const MAX_SEEN_TRANSACTIONS: usize = 100_000;
+
+/// Period of the announcement of the accepted transactions to the peers (in seconds)
+pub const RELAY_ANNOUNCEMENT_PERIOD: u64 = 2;
+
+/// Maximum number of transactions announced in a single `InventoryAnnouncement`
+pub const MAX_ANNOUNCEMENT_ITEMS: usize = 1000;
+
+/// Reasons for a transaction not to be accepted and relayed
+#[derive(Debug, PartialEq, Fail)]
+pub enum RelayError {
+    /// The transaction was already seen
+    #[fail(display = "Transaction {} was already seen", hash)]
+    AlreadySeen {
+        /// Hash of the transaction
+        hash: Hash,
+    },
+    /// The transaction pays less than the minimum relay fee
+    #[fail(
+        display = "Transaction {} pays {} nanowits per 1000 units of weight, the minimum relay fee is {}",
+        hash, priority, min_relay_fee
+    )]
+    FeeTooLow {
+        /// Hash of the transaction
+        hash: Hash,
+        /// Fee paid, in nanowits per 1000 units of weight
+        priority: u64,
+        /// Minimum relay fee, in nanowits per 1000 units of weight
+        min_relay_fee: u64,
+    },
+    /// The transaction spends an output already spent by a transaction of the pool
+    #[fail(
+        display = "Transaction {} conflicts with transaction {} of the pool",
+        hash, conflict
+    )]
+    ConflictsWithPool {
+        /// Hash of the transaction
+        hash: Hash,
+        /// Hash of the transaction of the pool spending the same output
+        conflict: Hash,
+    },
+}
+
+/// State of the relay policy. Timestamps are in seconds.
+#[derive(Debug)]
+pub struct RelayPolicy {
+    /// Minimum fee of value transfers and data requests, in nanowits per 1000 units of weight
+    min_relay_fee: u64,
+    /// Seconds each seen transaction is remembered
+    seen_ttl: i64,
+    /// Maximum number of seen transactions remembered
+    max_seen: usize,
+    /// Hashes of the seen transactions, with the timestamp they are forgotten at
+    seen: HashMap<Hash, i64>,
+    /// Hashes of the accepted transactions waiting to be announced
+    announcements: VecDeque<Hash>,
+}
+
+impl Default for RelayPolicy {
+    fn default() -> Self {
+        Self::new(0, SEEN_TRANSACTIONS_TTL, MAX_SEEN_TRANSACTIONS)
+    }
+}
+
+impl RelayPolicy {
+    /// Create a relay policy with a minimum relay fee of `min_relay_fee` nanowits per 1000 units
+    /// of weight, remembering up to `max_seen` transactions for `seen_ttl` seconds
+    pub fn new(min_relay_fee: u64, seen_ttl: i64, max_seen: usize) -> Self {
+        RelayPolicy {
+            min_relay_fee,
+            seen_ttl,
+            max_seen,
+            seen: HashMap::new(),
+            announcements: VecDeque::new(),
+        }
+    }
+    /// Method to check if a transaction was seen and is still remembered at `now`
+    pub fn is_seen(&self, hash: &Hash, now: i64) -> bool {
+        self.seen
+            .get(hash)
+            .map_or(false, |expiration| *expiration > now)
+    }
+    /// Method to remember a transaction as seen at `now`, forgetting the oldest one if the cache
+    /// is full. Fails if it was already seen
+    pub fn see(&mut self, hash: Hash, now: i64) -> Result<(), RelayError> {
+        if self.is_seen(&hash, now) {
+            Err(RelayError::AlreadySeen { hash })?
+        }
+        if self.max_seen == 0 {
+            return Ok(());
+        }
+        if self.seen.len() >= self.max_seen {
+            self.expire(now);
+        }
+        if self.seen.len() >= self.max_seen {
+            let oldest = self
+                .seen
+                .iter()
+                .min_by_key(|(_, expiration)| **expiration)
+                .map(|(hash, _)| *hash);
+            if let Some(oldest) = oldest {
+                self.seen.remove(&oldest);
+            }
+        }
+        self.seen.insert(hash, now + self.seen_ttl);
+
+        Ok(())
+    }
+    /// Method to forget a seen transaction, so that it can be accepted again
+    pub fn forget(&mut self, hash: &Hash) {
+        self.seen.remove(hash);
+    }
+    /// Method to forget the transactions seen before `now - seen_ttl`
+    pub fn expire(&mut self, now: i64) {
+        self.seen.retain(|_, expiration| *expiration > now);
+    }
+    /// Method to check that a transaction paying `fee` pays the minimum relay fee, if it has to
+    pub fn check_fee(
+        &self,
+        hash: Hash,
+        transaction: &Transaction,
+        fee: Wit,
+    ) -> Result<(), RelayError> {
+        match transaction_tag(&transaction.body) {
+            TransactionType::ValueTransfer | TransactionType::DataRequest => {
+                let priority = transaction_priority(fee, transaction.weight());
+                if priority < self.min_relay_fee {
+                    Err(RelayError::FeeTooLow {
+                        hash,
+                        priority,
+                        min_relay_fee: self.min_relay_fee,
+                    })?
+                }
+
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+    /// Method to queue an accepted transaction to be announced to the peers
+    pub fn announce(&mut self, hash: Hash) {
+        self.announcements.push_back(hash);
+    }
+    /// Method to take the next batch of transactions to be announced, oldest first
+    pub fn take_announcement(&mut self) -> Vec<InventoryEntry> {
+        let len = self.announcements.len().min(MAX_ANNOUNCEMENT_ITEMS);
+
+        self.announcements
+            .drain(..len)
+            .map(InventoryEntry::Tx)
+            .collect()
+    }
+}
+
+/// Check that a transaction does not spend the outputs spent by another transaction of the pool
+pub fn check_conflicts(
+    hash: Hash,
+    transaction: &Transaction,
+    transactions_pool: &TransactionsPool,
+) -> Result<(), RelayError> {
+    match transactions_pool.conflicting(&hash, transaction) {
+        Some(conflict) => Err(RelayError::ConflictsWithPool { hash, conflict }),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use witnet_data_structures::chain::{
+        Hashable, Input, Output, ValueTransferInput, ValueTransferOutput,
+    };
+
+    fn value_transfer(output_index: u32) -> Transaction {
+        let mut transaction = Transaction::default();
+        transaction.body.inputs = vec![Input::ValueTransfer(ValueTransferInput {
+            transaction_id: Hash::SHA256([9; 32]),
+            output_index,
+        })];
+        transaction.body.outputs = vec![Output::ValueTransfer(ValueTransferOutput::default())];
+
+        transaction
+    }
+
+    #[test]
+    fn seen_transactions_are_remembered_until_expired() {
+        let mut policy = RelayPolicy::new(0, 100, 10);
+        let hash = Hash::SHA256([1; 32]);
+
+        assert_eq!(policy.see(hash, 0), Ok(()));
+        assert!(policy.is_seen(&hash, 99));
+        assert_eq!(policy.see(hash, 99), Err(RelayError::AlreadySeen { hash }));
+        assert!(!policy.is_seen(&hash, 100));
+        assert_eq!(policy.see(hash, 100), Ok(()));
+    }
+
+    #[test]
+    fn forgotten_transaction_seen_again() {
+        let mut policy = RelayPolicy::new(0, 100, 10);
+        let hash = Hash::SHA256([1; 32]);
+        policy.see(hash, 0).unwrap();
+        policy.forget(&hash);
+
+        assert!(!policy.is_seen(&hash, 1));
+        assert_eq!(policy.see(hash, 1), Ok(()));
+    }
+
+    #[test]
+    fn oldest_seen_transaction_forgotten() {
+        let mut policy = RelayPolicy::new(0, 100, 2);
+        let hashes: Vec<Hash> = (1..=3).map(|i| Hash::SHA256([i; 32])).collect();
+        for (now, hash) in hashes.iter().enumerate() {
+            policy.see(*hash, now as i64).unwrap();
+        }
+
+        assert!(!policy.is_seen(&hashes[0], 3));
+        assert!(policy.is_seen(&hashes[1], 3));
+        assert!(policy.is_seen(&hashes[2], 3));
+    }
+
+    #[test]
+    fn min_relay_fee() {
+        let policy = RelayPolicy::new(1000, 100, 10);
+        let transaction = value_transfer(0);
+        let hash = transaction.hash();
+        let weight = u64::from(transaction.weight());
+
+        assert_eq!(
+            policy.check_fee(hash, &transaction, Wit::from_nanowits(weight)),
+            Ok(())
+        );
+        assert_eq!(
+            policy.check_fee(hash, &transaction, Wit::from_nanowits(weight - 1)),
+            Err(RelayError::FeeTooLow {
+                hash,
+                priority: transaction_priority(Wit::from_nanowits(weight - 1), weight as u32),
+                min_relay_fee: 1000,
+            })
+        );
+        // Transactions without a fee of their own are not checked
+        assert_eq!(
+            policy.check_fee(hash, &Transaction::default(), Wit::default()),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn conflicting_transactions_not_relayed() {
+        let mut pool = TransactionsPool::new();
+        let first = value_transfer(0);
+        pool.insert(first.hash(), first.clone(), Wit::default(), 0);
+
+        let mut double_spend = value_transfer(0);
+        double_spend.body.version = 1;
+        assert_eq!(
+            check_conflicts(double_spend.hash(), &double_spend, &pool),
+            Err(RelayError::ConflictsWithPool {
+                hash: double_spend.hash(),
+                conflict: first.hash(),
+            })
+        );
+        assert_eq!(check_conflicts(first.hash(), &first, &pool), Ok(()));
+        assert_eq!(
+            check_conflicts(Hash::default(), &value_transfer(1), &pool),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn announcements_batched() {
+        let mut policy = RelayPolicy::default();
+        for i in 0..MAX_ANNOUNCEMENT_ITEMS + 1 {
+            policy.announce(Hash::SHA256([(i % 256) as u8; 32]));
+        }
+
+        assert_eq!(policy.take_announcement().len(), MAX_ANNOUNCEMENT_ITEMS);
+        assert_eq!(
+            policy.take_announcement(),
+            vec![InventoryEntry::Tx(Hash::SHA256(
+                [(MAX_ANNOUNCEMENT_ITEMS % 256) as u8; 32]
+            ))]
+        );
+        assert_eq!(policy.take_announcement(), vec![]);
+    }
+}
@@ -0,0 +1,317 @@
+//! # Chain reorganization
+//!
+//! When the consensus of the peers is on a competing chain, the headers-first synchronization
+//! finds the fork point: the last block both chains have in common. If the competing branch is
+//! heavier than ours after the fork point, according to the [fork choice rule], our branch is
+//! unwound, undoing the changes of its blocks to the unspent outputs and to the data requests,
+//! and the competing branch is applied on top of the fork point. Blocks older than the last
+//! `MAX_UNDO_BLOCKS` ones cannot be undone, so they are final.
+//!
+//! The headers of the competing branch only tell how many blocks it has, so branches with fewer
+//! blocks than ours are rejected before downloading them, and the weight of the branch is
+//! checked once all its blocks, with their leadership proofs, have arrived.
+//!
+//! [fork choice rule]: super::fork_choice
+use failure::Fail;
+
+use witnet_data_structures::{
+    chain::{Block, ChainState, CheckpointBeacon, Epoch, Hash, MAX_UNDO_BLOCKS},
+    error::UtxoError,
+};
+
+use super::fork_choice::ChainWeight;
+
+/// Errors while reorganizing the chain
+#[derive(Debug, PartialEq, Fail)]
+pub enum ReorgError {
+    /// The competing branch has fewer blocks than ours, so it cannot be heavier
+    #[fail(
+        display = "The competing branch has {} blocks after the fork, and ours has {}",
+        theirs, ours
+    )]
+    Shorter {
+        /// Blocks of our branch after the fork point
+        ours: usize,
+        /// Blocks of the competing branch after the fork point
+        theirs: usize,
+    },
+    /// The competing branch is not heavier than ours
+    #[fail(
+        display = "The competing branch ({:?}) is not heavier than ours ({:?})",
+        theirs, ours
+    )]
+    NotHeavier {
+        /// Weight of our branch after the fork point
+        ours: ChainWeight,
+        /// Weight of the competing branch after the fork point
+        theirs: ChainWeight,
+    },
+    /// The fork point is older than the blocks which can be undone
+    #[fail(
+        display = "Cannot unwind {} blocks to the fork at epoch {}, only {} can be undone",
+        depth, fork_epoch, max_depth
+    )]
+    BeyondFinality {
+        /// Epoch of the fork point
+        fork_epoch: Epoch,
+        /// Blocks of our branch after the fork point
+        depth: usize,
+        /// Blocks which can be undone
+        max_depth: usize,
+    },
+    /// The data requests state before a block is not available
+    #[fail(display = "No data requests state to undo block {}", block_hash)]
+    MissingDataRequestUndo {
+        /// Hash of the block
+        block_hash: Hash,
+    },
+    /// The unspent outputs changes of a block cannot be undone
+    #[fail(display = "{}", _0)]
+    Utxo(UtxoError),
+}
+
+impl From<UtxoError> for ReorgError {
+    fn from(err: UtxoError) -> Self {
+        ReorgError::Utxo(err)
+    }
+}
+
+/// Number of blocks of the chain after the fork at `fork_epoch`
+pub fn branch_len(chain_state: &ChainState, fork_epoch: Epoch) -> usize {
+    chain_state
+        .block_chain
+        .range(fork_epoch.saturating_add(1)..)
+        .count()
+}
+
+/// Weight of our branch after the fork at `fork_epoch`, from the leadership proofs kept to undo
+/// its blocks
+pub fn branch_weight(
+    chain_state: &ChainState,
+    fork_epoch: Epoch,
+) -> Result<ChainWeight, ReorgError> {
+    chain_state
+        .block_chain
+        .range(fork_epoch.saturating_add(1)..)
+        .try_fold(ChainWeight::default(), |weight, (epoch, block_hash)| {
+            chain_state
+                .data_request_undo
+                .iter()
+                .find(|undo| undo.block_hash == *block_hash)
+                .map(|undo| weight.extend_with(*epoch, *block_hash, &undo.proof))
+                .ok_or_else(|| ReorgError::MissingDataRequestUndo {
+                    block_hash: *block_hash,
+                })
+        })
+}
+
+/// Check, once its headers are known, that the competing branch with `theirs` blocks after the
+/// fork at `fork_epoch` may be heavier than ours, i.e. it does not have fewer blocks, and that
+/// our branch can be unwound
+pub fn check_competing_headers(
+    chain_state: &ChainState,
+    fork_epoch: Epoch,
+    theirs: usize,
+) -> Result<(), ReorgError> {
+    let ours = branch_len(chain_state, fork_epoch);
+    let max_depth = chain_state.data_request_undo.len().min(MAX_UNDO_BLOCKS);
+    if ours > max_depth {
+        Err(ReorgError::BeyondFinality {
+            fork_epoch,
+            depth: ours,
+            max_depth,
+        })?
+    }
+    if theirs < ours {
+        Err(ReorgError::Shorter { ours, theirs })?
+    }
+
+    Ok(())
+}
+
+/// Check that the competing `blocks` after the fork at `fork_epoch`, in chain order, are heavier
+/// than our branch according to the fork choice rule, and that our branch can be unwound
+pub fn check_competing_branch(
+    chain_state: &ChainState,
+    fork_epoch: Epoch,
+    blocks: &[Block],
+) -> Result<(), ReorgError> {
+    check_competing_headers(chain_state, fork_epoch, blocks.len())?;
+    let ours = branch_weight(chain_state, fork_epoch)?;
+    let theirs = ChainWeight::of_blocks(ChainWeight::default(), blocks);
+    if theirs <= ours {
+        Err(ReorgError::NotHeavier { ours, theirs })?
+    }
+
+    Ok(())
+}
+
+/// Undo the blocks consolidated after `fork_point`, newest first, and return their hashes.
+/// If an error is returned, `chain_state` is left half-unwound and must be discarded
+pub fn unwind_chain(
+    chain_state: &mut ChainState,
+    fork_point: CheckpointBeacon,
+) -> Result<Vec<Hash>, ReorgError> {
+    let branch: Vec<(Epoch, Hash)> = chain_state
+        .block_chain
+        .range(fork_point.checkpoint.saturating_add(1)..)
+        .rev()
+        .map(|(epoch, hash)| (*epoch, *hash))
+        .collect();
+
+    for (epoch, block_hash) in &branch {
+        chain_state
+            .unspent_outputs_pool
+            .undo_block_hash(*block_hash)?;
+        match chain_state.data_request_undo.pop_back() {
+            Some(undo) if undo.block_hash == *block_hash => {
+                chain_state.data_request_pool.apply_diff(undo.changes);
+            }
+            _ => Err(ReorgError::MissingDataRequestUndo {
+                block_hash: *block_hash,
+            })?,
+        }
+        chain_state.block_chain.remove(epoch);
+    }
+    if let Some(chain_info) = chain_state.chain_info.as_mut() {
+        chain_info.highest_block_checkpoint = fork_point;
+    }
+
+    Ok(branch.into_iter().map(|(_, hash)| hash).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secp256k1::SecretKey as Secp256k1_SecretKey;
+    use witnet_data_structures::{
+        chain::{block_example, Block, Hashable, VrfProof},
+        data_request::DataRequestPool,
+    };
+
+    /// Blocks at epochs `first` to `last`, whose leadership proofs are made with the secret key
+    /// `[miner; 32]`
+    fn blocks(first: Epoch, last: Epoch, miner: u8) -> Vec<Block> {
+        let secret_key = Secp256k1_SecretKey::from_slice(&[miner; 32]).unwrap();
+
+        (first..=last)
+            .map(|epoch| {
+                let mut block = block_example();
+                block.block_header.beacon.checkpoint = epoch;
+                block.proof.proof = VrfProof::new(&secret_key, b"witnet").unwrap();
+
+                block
+            })
+            .collect()
+    }
+
+    /// Chain state with blocks at epochs 1 to `len`, and the data requests state before each
+    /// block tagged with the epoch of the previous block
+    fn chain_state(len: u32) -> (ChainState, Vec<Block>) {
+        let mut chain_state = ChainState::default();
+        let blocks = blocks(1, len, 1);
+        for block in &blocks {
+            let epoch = block.block_header.beacon.checkpoint;
+            let block_hash = block.hash();
+
+            chain_state.unspent_outputs_pool.apply_block(block);
+            let pool = data_request_pool(epoch);
+            let changes = pool.diff(&chain_state.data_request_pool);
+            chain_state.data_request_pool = pool;
+            chain_state.push_data_request_undo(block_hash, block.proof.clone(), changes);
+            chain_state.block_chain.insert(epoch, block_hash);
+        }
+
+        (chain_state, blocks)
+    }
+
+    fn data_request_pool(epoch: Epoch) -> DataRequestPool {
+        let mut pool = DataRequestPool::default();
+        pool.data_requests_by_epoch
+            .insert(epoch, Default::default());
+
+        pool
+    }
+
+    #[test]
+    fn unwind_to_fork_point() {
+        let (mut state, blocks) = chain_state(4);
+        let (expected, _) = chain_state(2);
+        let fork_point = CheckpointBeacon {
+            checkpoint: 2,
+            hash_prev_block: blocks[1].hash(),
+        };
+
+        assert_eq!(
+            unwind_chain(&mut state, fork_point),
+            Ok(vec![blocks[3].hash(), blocks[2].hash()])
+        );
+        assert_eq!(state.block_chain, expected.block_chain);
+        assert_eq!(state.unspent_outputs_pool, expected.unspent_outputs_pool);
+        assert_eq!(state.data_request_pool, data_request_pool(2));
+        assert_eq!(state.data_request_undo, expected.data_request_undo);
+    }
+
+    #[test]
+    fn unwind_without_undo_data() {
+        let (mut state, blocks) = chain_state(2);
+        state.data_request_undo.clear();
+
+        assert_eq!(
+            unwind_chain(&mut state, CheckpointBeacon::default()),
+            Err(ReorgError::MissingDataRequestUndo {
+                block_hash: blocks[1].hash()
+            })
+        );
+    }
+
+    #[test]
+    fn competing_branch_must_be_heavier() {
+        let (state, our_blocks) = chain_state(4);
+        let ours = ChainWeight::of_blocks(ChainWeight::default(), &our_blocks[1..]);
+
+        assert_eq!(branch_len(&state, 1), 3);
+        assert_eq!(branch_weight(&state, 1), Ok(ours));
+
+        // Shorter branches are rejected from their headers
+        assert_eq!(
+            check_competing_headers(&state, 1, 2),
+            Err(ReorgError::Shorter { ours: 3, theirs: 2 })
+        );
+        assert_eq!(check_competing_headers(&state, 1, 3), Ok(()));
+
+        // Branches with as many blocks are compared by the quality of their proofs
+        assert_eq!(
+            check_competing_branch(&state, 1, &our_blocks[1..]),
+            Err(ReorgError::NotHeavier { ours, theirs: ours })
+        );
+        let theirs = blocks(2, 4, 2);
+        let theirs_weight = ChainWeight::of_blocks(ChainWeight::default(), &theirs);
+        assert_ne!(theirs_weight.quality, ours.quality);
+        if theirs_weight > ours {
+            assert_eq!(check_competing_branch(&state, 1, &theirs), Ok(()));
+        } else {
+            assert_eq!(
+                check_competing_branch(&state, 1, &theirs),
+                Err(ReorgError::NotHeavier {
+                    ours,
+                    theirs: theirs_weight
+                })
+            );
+        }
+        // Longer branches are heavier whatever their proofs
+        assert_eq!(check_competing_branch(&state, 1, &blocks(2, 5, 2)), Ok(()));
+
+        // Blocks without undo data are final
+        let mut state = state;
+        state.data_request_undo.pop_front();
+        assert_eq!(
+            check_competing_headers(&state, 0, 10),
+            Err(ReorgError::BeyondFinality {
+                fork_epoch: 0,
+                depth: 4,
+                max_depth: 3,
+            })
+        );
+    }
+}
@@ -0,0 +1,252 @@
+//! # Witness scheduler
+//!
+//! Retrievals of the data requests the node is eligible for are executed one after another by
+//! the `RadManager`, so under load not all of them can be completed before their commit
+//! deadline. The `WitnessScheduler` orders them earliest-deadline-first, using estimates of the
+//! execution time learned from previous executions, and preempts the least valuable ones when
+//! the deadlines cannot be met.
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use witnet_data_structures::{
+    chain::{DataRequestOutput, Hash, OutputPointer},
+    proto::ProtobufConvert,
+    wit::Wit,
+};
+
+/// Estimated execution time for retrievals without execution history
+pub const DEFAULT_EXECUTION_ESTIMATE: Duration = Duration::from_millis(1000);
+
+/// Maximum number of RAD requests whose execution time is remembered
+pub const MAX_HISTORY_ENTRIES: usize = 10_000;
+
+/// Retrieval of a data request which the node is eligible to commit to
+#[derive(Clone, Debug, PartialEq)]
+pub struct RetrievalTask {
+    /// Pointer to the data request output
+    pub dr_pointer: OutputPointer,
+    /// Data request output
+    pub dr_output: DataRequestOutput,
+    /// Hash of the RAD request, used to look up its execution history
+    pub rad_hash: Hash,
+    /// Time left until the commitment must be broadcast
+    pub deadline: Duration,
+    /// Reward for every witness of the data request
+    pub value: Wit,
+}
+
+impl RetrievalTask {
+    /// Create a new task for a data request whose commitment is due within `deadline`
+    pub fn new(
+        dr_pointer: OutputPointer,
+        dr_output: DataRequestOutput,
+        deadline: Duration,
+    ) -> Self {
+        let rad_hash = dr_output.data_request.to_pb_hash();
+        let value = dr_output
+            .value
+            .checked_div(u64::from(dr_output.witnesses.max(1)))
+            .unwrap_or_default();
+
+        RetrievalTask {
+            dr_pointer,
+            dr_output,
+            rad_hash,
+            deadline,
+            value,
+        }
+    }
+}
+
+/// Result of scheduling a batch of retrieval tasks
+#[derive(Debug, Default, PartialEq)]
+pub struct Schedule {
+    /// Tasks to be executed, in execution order
+    pub scheduled: Vec<RetrievalTask>,
+    /// Tasks that would make more valuable tasks miss their deadlines
+    pub preempted: Vec<RetrievalTask>,
+}
+
+/// Earliest-deadline-first scheduler for the retrievals of data requests
+#[derive(Debug, Default)]
+pub struct WitnessScheduler {
+    /// Estimated execution time of every known RAD request
+    history: HashMap<Hash, Duration>,
+    /// Instant when the last retrieval finished
+    last_completion: Option<Instant>,
+}
+
+impl WitnessScheduler {
+    /// Estimated execution time of the retrieval of a RAD request
+    pub fn estimate(&self, rad_hash: &Hash) -> Duration {
+        self.history
+            .get(rad_hash)
+            .cloned()
+            .unwrap_or(DEFAULT_EXECUTION_ESTIMATE)
+    }
+
+    /// Update the execution history of a RAD request with a new sample, using an exponential
+    /// moving average which gives a weight of 1/4 to the new sample
+    pub fn record_execution(&mut self, rad_hash: Hash, elapsed: Duration) {
+        if self.history.len() >= MAX_HISTORY_ENTRIES && !self.history.contains_key(&rad_hash) {
+            // Evict an arbitrary entry to keep the history bounded
+            if let Some(evicted) = self.history.keys().next().cloned() {
+                self.history.remove(&evicted);
+            }
+        }
+
+        let estimate = match self.history.get(&rad_hash) {
+            Some(previous) => (*previous * 3 + elapsed) / 4,
+            None => elapsed,
+        };
+        self.history.insert(rad_hash, estimate);
+    }
+
+    /// Record the completion of a retrieval that was sent for execution at `sent_at`.
+    ///
+    /// Retrievals are executed one after another, so the execution of a retrieval starts when
+    /// it is sent or when the previous one finishes, whatever happens last.
+    pub fn record_completion(&mut self, rad_hash: Hash, sent_at: Instant, now: Instant) {
+        let started_at = match self.last_completion {
+            Some(last_completion) if last_completion > sent_at => last_completion,
+            _ => sent_at,
+        };
+        self.last_completion = Some(now);
+
+        if now > started_at {
+            self.record_execution(rad_hash, now - started_at);
+        }
+    }
+
+    /// Schedule a batch of retrieval tasks.
+    ///
+    /// Tasks are sorted by deadline (and by estimated execution time for equal deadlines).
+    /// Whenever a task would finish after its deadline, the least valuable task scheduled so far
+    /// is preempted, which maximizes the number of successful commitments while favoring the
+    /// most valuable data requests.
+    pub fn schedule(&self, mut tasks: Vec<RetrievalTask>) -> Schedule {
+        tasks.sort_by_key(|task| (task.deadline, self.estimate(&task.rad_hash)));
+
+        let mut schedule = Schedule::default();
+        let mut elapsed = Duration::from_secs(0);
+        for task in tasks {
+            elapsed += self.estimate(&task.rad_hash);
+            let deadline = task.deadline;
+            schedule.scheduled.push(task);
+
+            while elapsed > deadline {
+                // Preempt the least valuable task, or the slowest one in case of a tie
+                let (index, _) = schedule
+                    .scheduled
+                    .iter()
+                    .enumerate()
+                    .min_by_key(|(_, task)| {
+                        (task.value, std::cmp::Reverse(self.estimate(&task.rad_hash)))
+                    })
+                    .unwrap();
+                let preempted = schedule.scheduled.remove(index);
+                elapsed -= self.estimate(&preempted.rad_hash);
+                schedule.preempted.push(preempted);
+            }
+        }
+
+        schedule
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use witnet_data_structures::chain::{RADRequest, RADRetrieve};
+
+    fn task(id: u32, value: u64, deadline_secs: u64) -> RetrievalTask {
+        let dr_output = DataRequestOutput {
+            value: Wit::from_nanowits(value),
+            witnesses: 1,
+            data_request: RADRequest {
+                retrieve: vec![RADRetrieve {
+                    url: format!("https://example.com/{}", id),
+                    ..RADRetrieve::default()
+                }],
+                ..RADRequest::default()
+            },
+            ..DataRequestOutput::default()
+        };
+        let dr_pointer = OutputPointer {
+            transaction_id: Hash::SHA256([id as u8; 32]),
+            output_index: id,
+        };
+
+        RetrievalTask::new(dr_pointer, dr_output, Duration::from_secs(deadline_secs))
+    }
+
+    fn ids(tasks: &[RetrievalTask]) -> Vec<u32> {
+        tasks
+            .iter()
+            .map(|task| task.dr_pointer.output_index)
+            .collect()
+    }
+
+    #[test]
+    fn schedule_earliest_deadline_first() {
+        let scheduler = WitnessScheduler::default();
+        let schedule = scheduler.schedule(vec![task(0, 10, 9), task(1, 10, 3), task(2, 10, 6)]);
+
+        assert_eq!(ids(&schedule.scheduled), vec![1, 2, 0]);
+        assert!(schedule.preempted.is_empty());
+    }
+
+    #[test]
+    fn schedule_preempts_least_valuable() {
+        let scheduler = WitnessScheduler::default();
+        // Only two retrievals fit before the deadline
+        let schedule = scheduler.schedule(vec![task(0, 30, 2), task(1, 10, 2), task(2, 20, 2)]);
+
+        assert_eq!(ids(&schedule.scheduled), vec![0, 2]);
+        assert_eq!(ids(&schedule.preempted), vec![1]);
+    }
+
+    #[test]
+    fn schedule_uses_execution_history() {
+        let mut scheduler = WitnessScheduler::default();
+        let slow = task(0, 10, 3);
+        let fast = task(1, 10, 3);
+        scheduler.record_execution(slow.rad_hash, Duration::from_secs(3));
+        scheduler.record_execution(fast.rad_hash, Duration::from_millis(500));
+
+        let schedule = scheduler.schedule(vec![slow, fast, task(2, 10, 3)]);
+
+        // The slow task is the one preempted, as it takes the time of the other two together
+        assert_eq!(ids(&schedule.scheduled), vec![1, 2]);
+        assert_eq!(ids(&schedule.preempted), vec![0]);
+    }
+
+    #[test]
+    fn record_execution_moving_average() {
+        let mut scheduler = WitnessScheduler::default();
+        let rad_hash = Hash::SHA256([1; 32]);
+
+        assert_eq!(scheduler.estimate(&rad_hash), DEFAULT_EXECUTION_ESTIMATE);
+        scheduler.record_execution(rad_hash, Duration::from_secs(4));
+        assert_eq!(scheduler.estimate(&rad_hash), Duration::from_secs(4));
+        scheduler.record_execution(rad_hash, Duration::from_secs(8));
+        assert_eq!(scheduler.estimate(&rad_hash), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn record_completion_of_queued_retrievals() {
+        let mut scheduler = WitnessScheduler::default();
+        let first = Hash::SHA256([1; 32]);
+        let second = Hash::SHA256([2; 32]);
+        let sent_at = Instant::now();
+
+        // Both retrievals are sent at the same time, but the second one waits for the first one
+        scheduler.record_completion(first, sent_at, sent_at + Duration::from_secs(2));
+        scheduler.record_completion(second, sent_at, sent_at + Duration::from_secs(3));
+
+        assert_eq!(scheduler.estimate(&first), Duration::from_secs(2));
+        assert_eq!(scheduler.estimate(&second), Duration::from_secs(1));
+    }
+}
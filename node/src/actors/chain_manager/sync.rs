@@ -0,0 +1,611 @@
+//! # Headers-first synchronization
+//!
+//! When the node is behind the consensus of its peers, it first downloads the headers of the
+//! missing blocks with `GetBlocks` messages, checking that they form a chain from its tip to the
+//! target beacon. Then the blocks themselves are requested in windows of consecutive blocks,
+//! several windows at a time, each one to a random peer in consensus. Blocks may arrive out of
+//! order, so they are buffered until all the previous ones are consolidated, and the blocks not
+//! delivered in time are requested again, most likely to another peer.
+//!
+//! If the first headers do not extend the tip of the chain but an older block, the target is on a
+//! competing chain: the synchronization is rebased on that fork point, and the blocks are only
+//! applied once all of them were delivered, replacing our branch in a single reorganization.
+//!
+//! The headers and blocks kept by the synchronization are capped in memory: once the cap is
+//! reached, no more headers are requested until the pending blocks are consolidated. A competing
+//! chain that does not fit in the cap cannot be applied, so its synchronization is given up.
+use std::{
+    collections::{HashMap, VecDeque},
+    mem,
+};
+
+use failure::Fail;
+
+use witnet_data_structures::chain::{Block, BlockHeader, CheckpointBeacon, Epoch, Hash, Hashable};
+
+/// Number of consecutive blocks requested to the same peer
+pub const SYNC_WINDOW_SIZE: usize = 16;
+
+/// Maximum number of windows of blocks requested and not delivered yet
+pub const MAX_WINDOWS_IN_FLIGHT: usize = 8;
+
+/// Seconds a peer has to deliver the headers or the blocks requested to it
+pub const SYNC_REQUEST_TIMEOUT: i64 = 30;
+
+/// Period of the checks of the requests not delivered in time, in seconds
+pub const SYNC_TIMEOUTS_CHECK_PERIOD: u64 = 5;
+
+/// Number of times headers or a block are requested before giving up the synchronization
+pub const MAX_SYNC_REQUEST_ATTEMPTS: u32 = 3;
+
+/// Number of consecutive hashes at the beginning of a locator, before they get sparser
+const DENSE_LOCATOR_HASHES: usize = 10;
+
+/// Errors that make the node give up a synchronization
+#[derive(Debug, PartialEq, Fail)]
+pub enum SyncError {
+    /// Headers were requested too many times without an answer
+    #[fail(
+        display = "Headers were requested {} times without an answer",
+        attempts
+    )]
+    HeadersNotDelivered {
+        /// Number of requests
+        attempts: u32,
+    },
+    /// A block was requested too many times without being delivered
+    #[fail(
+        display = "Block {} was requested {} times without being delivered",
+        hash, attempts
+    )]
+    BlockNotDelivered {
+        /// Hash of the block
+        hash: Hash,
+        /// Number of requests
+        attempts: u32,
+    },
+    /// The peer has no more headers, but the target beacon was not reached
+    #[fail(
+        display = "No more headers after epoch {}, before reaching the target epoch {}",
+        checkpoint, target
+    )]
+    HeadersExhausted {
+        /// Epoch of the last header
+        checkpoint: Epoch,
+        /// Epoch of the target beacon
+        target: Epoch,
+    },
+    /// The headers skip or replace the block of the target beacon
+    #[fail(
+        display = "The headers do not lead to the target block at epoch {}",
+        target
+    )]
+    NotTargetChain {
+        /// Epoch of the target beacon
+        target: Epoch,
+    },
+    /// The competing chain does not fit in the memory cap of the synchronization
+    #[fail(
+        display = "The competing chain does not fit in the memory cap of {} bytes",
+        max_memory
+    )]
+    MemoryCapReached {
+        /// Memory cap in bytes
+        max_memory: usize,
+    },
+}
+
+/// State of a headers-first synchronization towards a target beacon. Timestamps are in seconds.
+#[derive(Debug)]
+pub struct HeadersFirstSync {
+    /// Beacon of the consensus of the peers, which the chain has to reach
+    target: CheckpointBeacon,
+    /// Beacon of the last header validated, which the next headers must extend
+    headers_tip: CheckpointBeacon,
+    /// Deadline of the headers requested and not delivered yet, if any
+    headers_deadline: Option<i64>,
+    /// Number of consecutive requests of headers without an answer
+    headers_attempts: u32,
+    /// Hashes of the blocks not consolidated yet, in chain order
+    pending: VecDeque<Hash>,
+    /// Hashes of the blocks not requested yet, in chain order
+    unrequested: VecDeque<Hash>,
+    /// Deadline of the blocks requested and not delivered yet
+    requested: HashMap<Hash, i64>,
+    /// Number of times each block was requested
+    attempts: HashMap<Hash, u32>,
+    /// Blocks delivered before some of the previous ones
+    buffer: HashMap<Hash, Block>,
+    /// Approximate number of bytes used by the buffered blocks
+    buffer_memory_usage: usize,
+    /// Memory cap for the headers and the buffered blocks, `0` means no cap
+    max_memory: usize,
+    /// Last block in common with the competing chain, if the target is not on our chain
+    fork_point: Option<CheckpointBeacon>,
+}
+
+impl HeadersFirstSync {
+    /// Create a synchronization from the beacon of the chain to the target beacon, using up to
+    /// `max_memory` bytes
+    pub fn new(
+        chain_beacon: CheckpointBeacon,
+        target: CheckpointBeacon,
+        max_memory: usize,
+    ) -> Self {
+        HeadersFirstSync {
+            target,
+            headers_tip: chain_beacon,
+            headers_deadline: None,
+            headers_attempts: 0,
+            pending: VecDeque::new(),
+            unrequested: VecDeque::new(),
+            requested: HashMap::new(),
+            attempts: HashMap::new(),
+            buffer: HashMap::new(),
+            buffer_memory_usage: 0,
+            max_memory,
+            fork_point: None,
+        }
+    }
+    /// Beacon the synchronization has to reach
+    pub fn target(&self) -> CheckpointBeacon {
+        self.target
+    }
+    /// Beacon of the last header validated, which the next headers must extend
+    pub fn headers_tip(&self) -> CheckpointBeacon {
+        self.headers_tip
+    }
+    /// Last block in common with the competing chain, if the target is not on our chain
+    pub fn fork_point(&self) -> Option<CheckpointBeacon> {
+        self.fork_point
+    }
+    /// Method to start the headers at `fork_point`, an older block of our chain, because the
+    /// target is on a competing chain. Returns `false` if some headers were already added
+    pub fn rebase(&mut self, fork_point: CheckpointBeacon) -> bool {
+        if !self.pending.is_empty() {
+            return false;
+        }
+        self.headers_tip = fork_point;
+        self.fork_point = Some(fork_point);
+
+        true
+    }
+    /// Number of blocks not consolidated yet
+    pub fn pending_blocks(&self) -> usize {
+        self.pending.len()
+    }
+    /// Approximate number of bytes used by the hashes of the headers and the buffered blocks
+    pub fn memory_usage(&self) -> usize {
+        (self.pending.len() + self.unrequested.len()) * mem::size_of::<Hash>()
+            + self.buffer_memory_usage
+    }
+    /// Method to check if the memory cap is reached
+    pub fn is_full(&self) -> bool {
+        self.max_memory != 0 && self.memory_usage() >= self.max_memory
+    }
+    /// Method to check if more headers have to be requested: they are missing, none were
+    /// requested already, and there is room for them
+    pub fn needs_headers(&self) -> bool {
+        !self.headers_complete() && !self.is_waiting_headers() && !self.is_full()
+    }
+    /// Method to check if all the headers up to the target beacon were validated
+    pub fn headers_complete(&self) -> bool {
+        self.headers_tip == self.target
+    }
+    /// Method to check if headers were requested and not delivered yet
+    pub fn is_waiting_headers(&self) -> bool {
+        self.headers_deadline.is_some()
+    }
+    /// Method to check if the headers requested were not delivered in time
+    pub fn headers_expired(&self, now: i64) -> bool {
+        self.headers_deadline
+            .map(|deadline| deadline <= now)
+            .unwrap_or(false)
+    }
+    /// Method to record a request of headers
+    pub fn headers_requested(&mut self, now: i64) -> Result<(), SyncError> {
+        if self.headers_attempts >= MAX_SYNC_REQUEST_ATTEMPTS {
+            Err(SyncError::HeadersNotDelivered {
+                attempts: self.headers_attempts,
+            })?
+        }
+        self.headers_attempts += 1;
+        self.headers_deadline = Some(now + SYNC_REQUEST_TIMEOUT);
+
+        Ok(())
+    }
+    /// Method to add headers already validated to extend the headers tip. The headers after the
+    /// target beacon, and the ones that do not fit in the memory cap, are ignored
+    pub fn add_headers(&mut self, headers: Vec<BlockHeader>) -> Result<(), SyncError> {
+        let target = self.target;
+        if headers.is_empty() && !self.headers_complete() {
+            Err(SyncError::HeadersExhausted {
+                checkpoint: self.headers_tip.checkpoint,
+                target: target.checkpoint,
+            })?
+        }
+
+        for header in headers {
+            // At least one header is kept, so that the synchronization can always progress
+            if self.headers_complete() || (self.is_full() && !self.pending.is_empty()) {
+                break;
+            }
+            let beacon = CheckpointBeacon {
+                checkpoint: header.beacon.checkpoint,
+                hash_prev_block: header.hash(),
+            };
+            if beacon.checkpoint > target.checkpoint
+                || (beacon.checkpoint == target.checkpoint && beacon != target)
+            {
+                Err(SyncError::NotTargetChain {
+                    target: target.checkpoint,
+                })?
+            }
+
+            self.pending.push_back(beacon.hash_prev_block);
+            self.unrequested.push_back(beacon.hash_prev_block);
+            self.headers_tip = beacon;
+        }
+        self.headers_deadline = None;
+        self.headers_attempts = 0;
+
+        Ok(())
+    }
+    /// Method to get the windows of blocks to request: the ones whose deadline expired, and new
+    /// ones while there is room for them
+    pub fn next_windows(&mut self, now: i64) -> Result<Vec<Vec<Hash>>, SyncError> {
+        // The blocks of a competing chain are only consolidated once all of them arrived, so the
+        // memory cannot be freed until then
+        if self.fork_point.is_some() && self.is_full() && !self.all_blocks_received() {
+            Err(SyncError::MemoryCapReached {
+                max_memory: self.max_memory,
+            })?
+        }
+
+        let mut expired: Vec<Hash> = self
+            .requested
+            .iter()
+            .filter(|(_, deadline)| **deadline <= now)
+            .map(|(hash, _)| *hash)
+            .collect();
+        // Request the blocks in chain order, so that windows hold consecutive blocks
+        let position = |hash: &Hash| self.pending.iter().position(|pending| pending == hash);
+        expired.sort_by_key(position);
+
+        let max_in_flight = SYNC_WINDOW_SIZE * MAX_WINDOWS_IN_FLIGHT;
+        let num_new = max_in_flight.saturating_sub(self.requested.len());
+        let num_new = num_new.min(self.unrequested.len());
+        let new: Vec<Hash> = self.unrequested.drain(..num_new).collect();
+
+        let mut windows = vec![];
+        for blocks in &[expired, new] {
+            for window in blocks.chunks(SYNC_WINDOW_SIZE) {
+                for hash in window {
+                    let attempts = self.attempts.entry(*hash).or_insert(0);
+                    if *attempts >= MAX_SYNC_REQUEST_ATTEMPTS {
+                        Err(SyncError::BlockNotDelivered {
+                            hash: *hash,
+                            attempts: *attempts,
+                        })?
+                    }
+                    *attempts += 1;
+                    self.requested.insert(*hash, now + SYNC_REQUEST_TIMEOUT);
+                }
+                windows.push(window.to_vec());
+            }
+        }
+
+        Ok(windows)
+    }
+    /// Method to record a block delivered by a peer. Returns `false` if the block was not
+    /// requested
+    pub fn block_received(&mut self, block: Block) -> bool {
+        let hash = block.hash();
+        if self.requested.remove(&hash).is_none() {
+            return false;
+        }
+        self.buffer_memory_usage += block.size() as usize;
+        self.buffer.insert(hash, block);
+
+        true
+    }
+    /// Method to take the next block to consolidate, if it was already delivered
+    pub fn next_block(&mut self) -> Option<Block> {
+        let block = self.buffer.remove(self.pending.front()?)?;
+        self.buffer_memory_usage -= block.size() as usize;
+        if let Some(hash) = self.pending.pop_front() {
+            self.attempts.remove(&hash);
+        }
+
+        Some(block)
+    }
+    /// Method to check if all the blocks up to the target beacon were delivered
+    pub fn all_blocks_received(&self) -> bool {
+        self.headers_complete() && self.unrequested.is_empty() && self.requested.is_empty()
+    }
+    /// Method to check if all the blocks up to the target beacon were consolidated
+    pub fn is_finished(&self) -> bool {
+        self.headers_complete() && self.pending.is_empty()
+    }
+}
+
+/// Build the locator hashes of a `GetBlocks` message: `tip`, followed by the hashes of the
+/// consolidated `chain` from newest to oldest, one every block at first and then with an
+/// exponentially growing step, and finally the hash of the genesis block
+pub fn locator_hashes<'a, I>(tip: Hash, chain: I, genesis_hash: Hash) -> Vec<Hash>
+where
+    I: IntoIterator<Item = &'a Hash>,
+{
+    let mut locator = vec![tip];
+    let mut step = 1;
+    let mut next = 0;
+    for (index, hash) in chain.into_iter().enumerate() {
+        if index != next {
+            continue;
+        }
+        if *hash != tip {
+            locator.push(*hash);
+        }
+        if locator.len() > DENSE_LOCATOR_HASHES {
+            step *= 2;
+        }
+        next += step;
+    }
+    if !locator.contains(&genesis_hash) {
+        locator.push(genesis_hash);
+    }
+
+    locator
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use witnet_data_structures::chain::LeadershipProof;
+
+    fn genesis_beacon() -> CheckpointBeacon {
+        CheckpointBeacon {
+            checkpoint: 0,
+            hash_prev_block: Hash::SHA256([0; 32]),
+        }
+    }
+
+    fn chain(len: u32) -> Vec<Block> {
+        let mut prev_beacon = genesis_beacon();
+        (1..=len)
+            .map(|checkpoint| {
+                let block = Block {
+                    block_header: BlockHeader {
+                        beacon: CheckpointBeacon {
+                            checkpoint,
+                            hash_prev_block: prev_beacon.hash_prev_block,
+                        },
+                        ..BlockHeader::default()
+                    },
+                    proof: LeadershipProof::default(),
+                    txns: vec![],
+                };
+                prev_beacon.hash_prev_block = block.hash();
+
+                block
+            })
+            .collect()
+    }
+
+    fn beacon(block: &Block) -> CheckpointBeacon {
+        CheckpointBeacon {
+            checkpoint: block.block_header.beacon.checkpoint,
+            hash_prev_block: block.hash(),
+        }
+    }
+
+    fn headers(blocks: &[Block]) -> Vec<BlockHeader> {
+        blocks
+            .iter()
+            .map(|block| block.block_header.clone())
+            .collect()
+    }
+
+    #[test]
+    fn add_headers_until_target() {
+        let blocks = chain(5);
+        let mut sync = HeadersFirstSync::new(genesis_beacon(), beacon(&blocks[3]), 0);
+
+        sync.headers_requested(0).unwrap();
+        assert!(sync.is_waiting_headers());
+        sync.add_headers(headers(&blocks[..2])).unwrap();
+        assert!(!sync.is_waiting_headers());
+        assert!(!sync.headers_complete());
+        assert_eq!(sync.headers_tip(), beacon(&blocks[1]));
+
+        // The headers after the target are ignored
+        sync.add_headers(headers(&blocks[2..])).unwrap();
+        assert!(sync.headers_complete());
+        assert_eq!(sync.pending.len(), 4);
+    }
+
+    #[test]
+    fn add_headers_not_leading_to_target() {
+        let blocks = chain(3);
+        let mut sync = HeadersFirstSync::new(genesis_beacon(), beacon(&blocks[2]), 0);
+        assert_eq!(
+            sync.add_headers(vec![]),
+            Err(SyncError::HeadersExhausted {
+                checkpoint: 0,
+                target: 3
+            })
+        );
+
+        // A different block at the epoch of the target
+        let mut sync = HeadersFirstSync::new(genesis_beacon(), beacon(&blocks[1]), 0);
+        let mut other_block = blocks[1].clone();
+        other_block.block_header.version = 1;
+        let mut other_headers = headers(&blocks[..1]);
+        other_headers.push(other_block.block_header);
+        assert_eq!(
+            sync.add_headers(other_headers),
+            Err(SyncError::NotTargetChain { target: 2 })
+        );
+    }
+
+    #[test]
+    fn headers_requested_too_many_times() {
+        let blocks = chain(1);
+        let mut sync = HeadersFirstSync::new(genesis_beacon(), beacon(&blocks[0]), 0);
+
+        for now in 0..MAX_SYNC_REQUEST_ATTEMPTS {
+            sync.headers_requested(i64::from(now)).unwrap();
+        }
+        assert!(!sync.headers_expired(SYNC_REQUEST_TIMEOUT));
+        assert!(sync.headers_expired(SYNC_REQUEST_TIMEOUT + 2));
+        assert_eq!(
+            sync.headers_requested(SYNC_REQUEST_TIMEOUT + 2),
+            Err(SyncError::HeadersNotDelivered {
+                attempts: MAX_SYNC_REQUEST_ATTEMPTS
+            })
+        );
+    }
+
+    #[test]
+    fn blocks_consolidated_in_order() {
+        let blocks = chain(40);
+        let mut sync = HeadersFirstSync::new(genesis_beacon(), beacon(&blocks[39]), 0);
+        sync.add_headers(headers(&blocks)).unwrap();
+
+        let windows = sync.next_windows(0).unwrap();
+        assert_eq!(windows.len(), 3);
+        assert_eq!(windows[0].len(), SYNC_WINDOW_SIZE);
+        assert_eq!(windows[2].len(), 40 - 2 * SYNC_WINDOW_SIZE);
+        assert_eq!(windows[1][0], blocks[SYNC_WINDOW_SIZE].hash());
+        // Everything was requested already
+        assert!(sync.next_windows(0).unwrap().is_empty());
+
+        // The second block is buffered until the first one arrives
+        assert!(sync.block_received(blocks[1].clone()));
+        assert_eq!(sync.next_block(), None);
+        assert!(sync.block_received(blocks[0].clone()));
+        assert!(!sync.block_received(blocks[0].clone()));
+        assert_eq!(sync.next_block(), Some(blocks[0].clone()));
+        assert_eq!(sync.next_block(), Some(blocks[1].clone()));
+        assert_eq!(sync.next_block(), None);
+
+        for block in &blocks[2..] {
+            assert!(sync.block_received(block.clone()));
+        }
+        while sync.next_block().is_some() {}
+        assert!(sync.is_finished());
+    }
+
+    #[test]
+    fn expired_blocks_requested_again() {
+        let blocks = chain(3);
+        let mut sync = HeadersFirstSync::new(genesis_beacon(), beacon(&blocks[2]), 0);
+        sync.add_headers(headers(&blocks)).unwrap();
+        sync.next_windows(0).unwrap();
+        assert!(sync.block_received(blocks[1].clone()));
+
+        // Only the blocks not delivered are requested again, in chain order
+        assert!(sync
+            .next_windows(SYNC_REQUEST_TIMEOUT - 1)
+            .unwrap()
+            .is_empty());
+        let windows = sync.next_windows(SYNC_REQUEST_TIMEOUT).unwrap();
+        assert_eq!(windows, vec![vec![blocks[0].hash(), blocks[2].hash()]]);
+
+        sync.next_windows(2 * SYNC_REQUEST_TIMEOUT).unwrap();
+        assert_eq!(
+            sync.next_windows(3 * SYNC_REQUEST_TIMEOUT),
+            Err(SyncError::BlockNotDelivered {
+                hash: blocks[0].hash(),
+                attempts: MAX_SYNC_REQUEST_ATTEMPTS
+            })
+        );
+    }
+
+    #[test]
+    fn rebase_on_fork_point() {
+        let blocks = chain(4);
+        let mut fork = blocks[1].clone();
+        fork.block_header.version = 1;
+        let competing = vec![fork];
+        let fork_point = beacon(&blocks[0]);
+
+        let mut sync = HeadersFirstSync::new(beacon(&blocks[3]), beacon(&competing[0]), 0);
+        assert_eq!(sync.fork_point(), None);
+        assert!(sync.rebase(fork_point));
+        assert_eq!(sync.fork_point(), Some(fork_point));
+        assert_eq!(sync.headers_tip(), fork_point);
+
+        sync.add_headers(headers(&competing)).unwrap();
+        assert!(!sync.rebase(fork_point));
+        assert_eq!(sync.pending_blocks(), 1);
+        assert!(!sync.all_blocks_received());
+
+        sync.next_windows(0).unwrap();
+        assert!(!sync.all_blocks_received());
+        assert!(sync.block_received(competing[0].clone()));
+        assert!(sync.all_blocks_received());
+        assert!(!sync.is_finished());
+    }
+
+    #[test]
+    fn headers_capped_by_memory() {
+        let blocks = chain(10);
+        let hash_size = mem::size_of::<Hash>();
+        let mut sync = HeadersFirstSync::new(genesis_beacon(), beacon(&blocks[9]), 4 * hash_size);
+
+        sync.headers_requested(0).unwrap();
+        sync.add_headers(headers(&blocks)).unwrap();
+        // Each header is kept both as pending and as not requested yet
+        assert_eq!(sync.pending_blocks(), 2);
+        assert!(sync.is_full());
+        assert!(!sync.needs_headers());
+
+        // Once their blocks are consolidated, there is room for the next headers
+        sync.next_windows(0).unwrap();
+        for block in &blocks[..2] {
+            assert!(sync.block_received(block.clone()));
+        }
+        while sync.next_block().is_some() {}
+        assert_eq!(sync.memory_usage(), 0);
+        assert!(sync.needs_headers());
+        assert_eq!(sync.headers_tip(), beacon(&blocks[1]));
+    }
+
+    #[test]
+    fn competing_chain_over_memory_cap() {
+        let blocks = chain(4);
+        let mut fork = blocks[1].clone();
+        fork.block_header.version = 1;
+        let competing = vec![fork];
+
+        let mut sync = HeadersFirstSync::new(beacon(&blocks[3]), beacon(&competing[0]), 1);
+        assert!(sync.rebase(beacon(&blocks[0])));
+        sync.add_headers(headers(&competing)).unwrap();
+        assert_eq!(
+            sync.next_windows(0),
+            Err(SyncError::MemoryCapReached { max_memory: 1 })
+        );
+    }
+
+    #[test]
+    fn locator_hashes_sparse() {
+        let hashes: Vec<Hash> = (1..=30).map(|i| Hash::SHA256([i; 32])).collect();
+        let genesis_hash = genesis_beacon().hash_prev_block;
+        let tip = hashes[29];
+
+        let locator = locator_hashes(tip, hashes.iter().rev(), genesis_hash);
+        let expected: Vec<Hash> = [30, 29, 28, 27, 26, 25, 24, 23, 22, 21, 20, 18, 14, 6]
+            .iter()
+            .map(|i| Hash::SHA256([*i; 32]))
+            .chain(vec![genesis_hash])
+            .collect();
+        assert_eq!(locator, expected);
+
+        // Without blocks, the locator starts at the genesis block
+        assert_eq!(
+            locator_hashes(genesis_hash, vec![], genesis_hash),
+            vec![genesis_hash]
+        );
+    }
+}
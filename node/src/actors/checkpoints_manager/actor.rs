@@ -0,0 +1,17 @@
+use actix::{Actor, Context};
+use log::debug;
+
+use super::CheckpointsManager;
+
+/// Make actor from CheckpointsManager
+impl Actor for CheckpointsManager {
+    /// Every actor has to provide execution `Context` in which it can run.
+    type Context = Context<Self>;
+
+    /// Method to be executed when the actor is started
+    fn started(&mut self, ctx: &mut Self::Context) {
+        debug!("Checkpoints Manager actor has been started!");
+
+        self.start_polling(ctx);
+    }
+}
@@ -0,0 +1,175 @@
+use std::time::Duration;
+
+use actix::prelude::*;
+use futures::Future;
+use log;
+use reqwest::r#async::Client;
+
+use crate::actors::{chain_manager::ChainManager, messages::CheckCheckpoints};
+use crate::config_mngr;
+
+use witnet_data_structures::{
+    chain::{CheckpointBeacon, CheckpointStatus, PublicKey, SignedCheckpoint},
+    serializers::decoders::TryInto,
+};
+use witnet_util::parser::parse_hex;
+use witnet_validations::validations::validate_checkpoint_signature;
+
+mod actor;
+
+/// Checkpoints manager actor: periodically fetches checkpoints signed by a trusted source and
+/// compares them with the local chain, to alert when the node is following a minority fork.
+///
+/// The feed is a JSON array of `SignedCheckpoint`s served at `checkpoints.feed_url`. Checkpoints
+/// not signed by one of the `checkpoints.trusted_keys` are ignored.
+#[derive(Default)]
+pub struct CheckpointsManager {
+    /// Public keys trusted to sign checkpoints
+    trusted_keys: Vec<PublicKey>,
+    /// Whether the last comparison found a mismatching checkpoint
+    fork_detected: bool,
+}
+
+/// Required trait for being able to retrieve checkpoints manager address from system registry
+impl actix::Supervised for CheckpointsManager {}
+
+/// Required trait for being able to retrieve checkpoints manager address from system registry
+impl SystemService for CheckpointsManager {}
+
+/// Auxiliary methods for CheckpointsManager actor
+impl CheckpointsManager {
+    fn start_polling(&mut self, ctx: &mut <Self as Actor>::Context) {
+        config_mngr::get()
+            .into_actor(self)
+            .and_then(|config, act, ctx| {
+                let feed_url = match config.checkpoints.feed_url.clone() {
+                    Some(feed_url) => feed_url,
+                    None => {
+                        log::debug!("No checkpoints feed configured");
+                        return fut::ok(());
+                    }
+                };
+
+                act.trusted_keys = config
+                    .checkpoints
+                    .trusted_keys
+                    .iter()
+                    .filter_map(|key| {
+                        let public_key = parse_public_key(key);
+                        if public_key.is_none() {
+                            log::error!("Ignoring invalid trusted checkpoints key: {}", key);
+                        }
+
+                        public_key
+                    })
+                    .collect();
+                if act.trusted_keys.is_empty() {
+                    log::error!(
+                        "Checkpoints feed {} configured without trusted keys, ignoring it",
+                        feed_url
+                    );
+                    return fut::ok(());
+                }
+
+                log::info!("Checking the chain against the checkpoints of {}", feed_url);
+                act.fetch_checkpoints(ctx, feed_url.clone());
+                act.poll_checkpoints(ctx, feed_url, config.checkpoints.poll_period);
+
+                fut::ok(())
+            })
+            .map_err(|err, _, _| log::error!("Checkpoints manager failed to start: {}", err))
+            .wait(ctx);
+    }
+
+    /// Method to periodically fetch the checkpoints feed
+    fn poll_checkpoints(&self, ctx: &mut Context<Self>, feed_url: String, poll_period: Duration) {
+        ctx.run_later(poll_period, move |act, ctx| {
+            act.fetch_checkpoints(ctx, feed_url.clone());
+
+            act.poll_checkpoints(ctx, feed_url, poll_period);
+        });
+    }
+
+    /// Method to fetch the checkpoints feed and check the checkpoints with valid signatures
+    fn fetch_checkpoints(&self, ctx: &mut Context<Self>, feed_url: String) {
+        Client::new()
+            .get(feed_url.as_str())
+            .send()
+            .and_then(|response| response.error_for_status())
+            .and_then(|mut response| response.json::<Vec<SignedCheckpoint>>())
+            .into_actor(self)
+            .map_err(move |err, _, _| {
+                log::warn!("Failed to fetch checkpoints from {}: {}", feed_url, err)
+            })
+            .and_then(|signed_checkpoints, act, _ctx| {
+                let checkpoints: Vec<CheckpointBeacon> = signed_checkpoints
+                    .into_iter()
+                    .filter_map(|signed_checkpoint| {
+                        match validate_checkpoint_signature(&signed_checkpoint, &act.trusted_keys) {
+                            Ok(()) => Some(signed_checkpoint.beacon),
+                            Err(err) => {
+                                log::warn!("Ignoring checkpoint: {}", err);
+                                None
+                            }
+                        }
+                    })
+                    .collect();
+
+                System::current()
+                    .registry()
+                    .get::<ChainManager>()
+                    .send(CheckCheckpoints { checkpoints })
+                    .into_actor(act)
+                    .map_err(|err, _, _| {
+                        log::error!("Unsuccessful communication with chain manager: {}", err)
+                    })
+            })
+            .map(|statuses, act, _ctx| act.process_statuses(statuses))
+            .spawn(ctx);
+    }
+
+    /// Method to alert about the checkpoints that do not match the local chain
+    fn process_statuses(&mut self, statuses: Vec<(CheckpointBeacon, CheckpointStatus)>) {
+        let mut fork_detected = false;
+        for (beacon, status) in statuses {
+            if let CheckpointStatus::Mismatching { local_hash } = status {
+                fork_detected = true;
+                let local_hash = local_hash
+                    .map(|hash| hash.to_string())
+                    .unwrap_or_else(|| "none".to_string());
+                log::error!(
+                    "!!! FORK ALERT !!! The local chain does not match the trusted checkpoint \
+                     for epoch {} (expected block {}, found {}). This node is probably following \
+                     a minority fork",
+                    beacon.checkpoint,
+                    beacon.hash_prev_block,
+                    local_hash
+                );
+            }
+        }
+
+        if self.fork_detected && !fork_detected {
+            log::info!("The local chain matches the trusted checkpoints again");
+        }
+        self.fork_detected = fork_detected;
+    }
+}
+
+/// Parse a hex-encoded public key in compressed format
+fn parse_public_key(key: &str) -> Option<PublicKey> {
+    let bytes = parse_hex(key);
+    if bytes.len() != 33 || key.len() != 66 {
+        return None;
+    }
+
+    let mut public_key = PublicKey {
+        compressed: bytes[0],
+        bytes: [0; 32],
+    };
+    public_key.bytes.copy_from_slice(&bytes[1..]);
+
+    // Check that it is a valid point of the curve
+    let secp256k1_public_key: Result<secp256k1::PublicKey, _> = public_key.clone().try_into();
+
+    secp256k1_public_key.ok().map(|_| public_key)
+}
@@ -1,12 +1,20 @@
 use std::io;
-use std::io::Cursor;
+use std::io::{Cursor, Read, Write};
+use std::time::Instant;
 
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 
 use actix::Message;
 use bytes;
+use futures::{Async, Future, Poll};
 use log;
-use tokio::codec::{Decoder, Encoder};
+use tokio::{
+    codec::{Decoder, Encoder},
+    io::{AsyncRead, AsyncWrite},
+    timer::Delay,
+};
+
+use witnet_p2p::bandwidth::BandwidthLimiter;
 
 const HEADER_SIZE: usize = 4; // bytes
 
@@ -89,3 +97,89 @@ impl Encoder for P2PCodec {
         Ok(())
     }
 }
+
+/// Connection throttled by the bandwidth limiters of a session. It sits under the codec, so the
+/// bytes are limited as they are read from and written to the connection, whatever the size of
+/// the messages.
+pub struct ThrottledStream<S> {
+    stream: S,
+    upload: BandwidthLimiter,
+    download: BandwidthLimiter,
+    /// Timers to wake up the task once the throttled direction can go on
+    upload_delay: Option<Delay>,
+    download_delay: Option<Delay>,
+}
+
+impl<S> ThrottledStream<S> {
+    /// Throttle `stream` with the upload and download limiters of a session
+    pub fn new(stream: S, upload: BandwidthLimiter, download: BandwidthLimiter) -> Self {
+        ThrottledStream {
+            stream,
+            upload,
+            download,
+            upload_delay: None,
+            download_delay: None,
+        }
+    }
+}
+
+/// Get the number of bytes the limiter allows to transfer. If none, schedule a wake-up of the
+/// current task for when some are allowed and return a `WouldBlock` error
+fn poll_allowance(limiter: &mut BandwidthLimiter, delay: &mut Option<Delay>) -> io::Result<usize> {
+    loop {
+        let now = Instant::now();
+        match limiter.allowance(now) {
+            Ok(allowance) => {
+                *delay = None;
+
+                return Ok(allowance.min(usize::max_value() as u64) as usize);
+            }
+            Err(wait_time) => {
+                let mut timer = Delay::new(now + wait_time);
+                match timer.poll() {
+                    Ok(Async::NotReady) => {
+                        *delay = Some(timer);
+
+                        return Err(io::ErrorKind::WouldBlock.into());
+                    }
+                    Ok(Async::Ready(())) => {}
+                    Err(e) => return Err(io::Error::new(io::ErrorKind::Other, e)),
+                }
+            }
+        }
+    }
+}
+
+impl<S: Read> Read for ThrottledStream<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let allowance = poll_allowance(&mut self.download, &mut self.download_delay)?;
+        let len = buf.len().min(allowance);
+        let read = self.stream.read(&mut buf[..len])?;
+        self.download.consume(read as u64);
+
+        Ok(read)
+    }
+}
+
+impl<S: AsyncRead> AsyncRead for ThrottledStream<S> {}
+
+impl<S: Write> Write for ThrottledStream<S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let allowance = poll_allowance(&mut self.upload, &mut self.upload_delay)?;
+        let len = buf.len().min(allowance);
+        let written = self.stream.write(&buf[..len])?;
+        self.upload.consume(written as u64);
+
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+impl<S: AsyncWrite> AsyncWrite for ThrottledStream<S> {
+    fn shutdown(&mut self) -> Poll<(), io::Error> {
+        self.stream.shutdown()
+    }
+}
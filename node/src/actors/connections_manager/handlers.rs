@@ -1,12 +1,31 @@
+use std::{
+    io,
+    net::{Ipv4Addr, SocketAddr},
+};
+
 use actix::{
-    actors::resolver::{ConnectAddr, Resolver},
-    ActorFuture, ContextFutureSpawner, Handler, SystemService, WrapFuture,
+    actors::resolver::{ConnectAddr, Resolve, Resolver},
+    ActorFuture, ContextFutureSpawner, Handler, MessageResult, SystemService, WrapFuture,
 };
+use futures::Future;
 
-use witnet_p2p::sessions::SessionType;
+use log::{debug, error, info, warn};
+
+use witnet_p2p::{
+    sessions::SessionType,
+    socks5::{is_onion_host, Target},
+    user_agents::UserAgentMatch,
+};
 
 use super::ConnectionsManager;
-use crate::actors::messages::{InboundTcpConnect, OutboundTcpConnect};
+use crate::actors::{
+    messages::{
+        AddTriedPeers, CheckUserAgent, FeelerConnect, FeelerFailed, GetNetworkTotals,
+        InboundTcpConnect, OutboundHostConnect, OutboundTcpConnect, RecordProtocolMessage,
+        RecordRejectedVersion,
+    },
+    peers_manager::PeersManager,
+};
 
 /// Handler for InboundTcpConnect messages (built from inbound connections)
 impl Handler<InboundTcpConnect> for ConnectionsManager {
@@ -27,6 +46,34 @@ impl Handler<OutboundTcpConnect> for ConnectionsManager {
 
     /// Method to handle the OutboundTcpConnect message
     fn handle(&mut self, msg: OutboundTcpConnect, ctx: &mut Self::Context) {
+        let address = msg.address;
+
+        // Connect through the proxy if there is one. This may take a while, so it does not block
+        // the actor
+        if let Some(proxy) = self.proxy {
+            ConnectionsManager::proxy_connect(proxy, Target::Address(address))
+                .map(move |stream| {
+                    debug!("Connected to peer {} through proxy {}", address, proxy);
+
+                    // Request the creation of a new session actor from connection
+                    ConnectionsManager::request_session_creation(
+                        stream,
+                        SessionType::Outbound,
+                        Some(address),
+                    );
+                })
+                .map_err(move |err| {
+                    warn!(
+                        "Failed to connect to peer {} through proxy: {}",
+                        address, err
+                    )
+                })
+                .into_actor(self)
+                .spawn(ctx);
+
+            return;
+        }
+
         // Get resolver from registry and send a ConnectAddr message to it
         Resolver::from_registry()
             .send(ConnectAddr(msg.address))
@@ -35,3 +82,184 @@ impl Handler<OutboundTcpConnect> for ConnectionsManager {
             .wait(ctx);
     }
 }
+
+/// Handler for FeelerConnect messages (requested for checking the peers of the new bucket)
+impl Handler<FeelerConnect> for ConnectionsManager {
+    type Result = ();
+
+    fn handle(&mut self, msg: FeelerConnect, ctx: &mut Self::Context) {
+        let address = msg.address;
+
+        // The connection is closed as soon as it is established, no session is created
+        self.feeler_connect(address)
+            .then(move |res| -> Result<(), ()> {
+                let peers_manager_addr = PeersManager::from_registry();
+                match res {
+                    Ok(_stream) => {
+                        debug!("Feeler connection to peer {} succeeded", address);
+                        peers_manager_addr.do_send(AddTriedPeers {
+                            addresses: vec![address],
+                        });
+                    }
+                    Err(err) => {
+                        debug!("Feeler connection to peer {} failed: {}", address, err);
+                        peers_manager_addr.do_send(FeelerFailed { address });
+                    }
+                }
+
+                Ok(())
+            })
+            .into_actor(self)
+            .spawn(ctx);
+    }
+}
+
+/// Handler for OutboundHostConnect messages (requested for connecting to seed hosts)
+impl Handler<OutboundHostConnect> for ConnectionsManager {
+    type Result = ();
+
+    fn handle(&mut self, msg: OutboundHostConnect, ctx: &mut Self::Context) {
+        let host = msg.host;
+        let host_err = host.clone();
+
+        // Connect through the proxy if there is one, which resolves the host so that it is not
+        // leaked in the local DNS queries
+        if let Some(proxy) = self.proxy {
+            let target = match Target::parse_host(&host) {
+                Ok(target) => target,
+                Err(err) => {
+                    warn!("Failed to connect to host through proxy: {}", err);
+                    return;
+                }
+            };
+            let target_address = match target {
+                Target::Address(address) => Some(address),
+                Target::Host(..) => None,
+            };
+
+            ConnectionsManager::proxy_connect(proxy, target)
+                .and_then(move |stream| -> io::Result<()> {
+                    // The address of a host resolved by the proxy is unknown, so the session is
+                    // identified by the local port of the connection to the proxy. The IP address
+                    // is left unspecified, so that it is never taken for the address of a peer
+                    let remote_addr = match target_address {
+                        Some(address) => address,
+                        None => SocketAddr::new(
+                            Ipv4Addr::UNSPECIFIED.into(),
+                            stream.local_addr()?.port(),
+                        ),
+                    };
+                    debug!("Connected to host {} through proxy {}", host, proxy);
+
+                    // Request the creation of a new session actor from connection
+                    ConnectionsManager::request_session_creation(
+                        stream,
+                        SessionType::Outbound,
+                        Some(remote_addr),
+                    );
+
+                    Ok(())
+                })
+                .map_err(move |err| {
+                    warn!(
+                        "Failed to connect to host {} through proxy: {}",
+                        host_err, err
+                    )
+                })
+                .into_actor(self)
+                .spawn(ctx);
+
+            return;
+        }
+
+        // Onion services cannot be resolved without a proxy
+        if is_onion_host(&host) {
+            warn!(
+                "Cannot connect to onion host {} without a proxy, set connections.proxy",
+                host
+            );
+            return;
+        }
+
+        // Resolve the host and race the connections to its addresses. Unlike single address
+        // connections, this may take several connection attempt delays, so it does not block
+        // the actor
+        Resolver::from_registry()
+            .send(Resolve::host(host.clone()))
+            .map_err(|err| error!("Unsuccessful communication with resolver: {}", err))
+            .and_then(move |res| {
+                res.map_err(move |err| warn!("Failed to resolve host {}: {}", host, err))
+            })
+            .and_then(move |addresses| {
+                ConnectionsManager::happy_eyeballs_connect(addresses)
+                    .map_err(move |err| warn!("Failed to connect to host {}: {}", host_err, err))
+            })
+            .map(|stream| {
+                stream
+                    .peer_addr()
+                    .map(|ip| debug!("Connected to peer {:?}", ip))
+                    .unwrap_or_else(|err| error!("Peer address error in stream: {}", err));
+
+                // Request the creation of a new session actor from connection
+                ConnectionsManager::request_session_creation(stream, SessionType::Outbound, None);
+            })
+            .into_actor(self)
+            .spawn(ctx);
+    }
+}
+
+/// Handler for CheckUserAgent messages (sent by sessions upon receiving a version message)
+impl Handler<CheckUserAgent> for ConnectionsManager {
+    type Result = MessageResult<CheckUserAgent>;
+
+    fn handle(&mut self, msg: CheckUserAgent, _ctx: &mut Self::Context) -> Self::Result {
+        let user_agent_match = self.user_agent_filter.check(&msg.user_agent);
+        let metrics = self.user_agent_filter.metrics();
+
+        match user_agent_match {
+            UserAgentMatch::Avoided => info!(
+                "Dropping peer {} with avoided user agent {:?} ({} avoided so far)",
+                msg.address, msg.user_agent, metrics.avoided
+            ),
+            UserAgentMatch::Preferred => debug!(
+                "Peer {} has preferred user agent {:?} ({} preferred so far)",
+                msg.address, msg.user_agent, metrics.preferred
+            ),
+            UserAgentMatch::Neutral => {
+                debug!("Peer {} has user agent {:?}", msg.address, msg.user_agent)
+            }
+        }
+
+        MessageResult(user_agent_match)
+    }
+}
+
+/// Handler for RecordProtocolMessage messages (sent by sessions for every message they receive
+/// or send)
+impl Handler<RecordProtocolMessage> for ConnectionsManager {
+    type Result = ();
+
+    fn handle(&mut self, msg: RecordProtocolMessage, _ctx: &mut Self::Context) {
+        self.network_totals
+            .record(msg.direction, msg.command, msg.size);
+    }
+}
+
+/// Handler for RecordRejectedVersion messages (sent by sessions when they reject a peer because
+/// of its protocol version)
+impl Handler<RecordRejectedVersion> for ConnectionsManager {
+    type Result = ();
+
+    fn handle(&mut self, msg: RecordRejectedVersion, _ctx: &mut Self::Context) {
+        self.network_totals.record_rejected_version(msg.version);
+    }
+}
+
+/// Handler for GetNetworkTotals messages
+impl Handler<GetNetworkTotals> for ConnectionsManager {
+    type Result = MessageResult<GetNetworkTotals>;
+
+    fn handle(&mut self, _msg: GetNetworkTotals, _ctx: &mut Self::Context) -> Self::Result {
+        MessageResult(self.network_totals.clone())
+    }
+}
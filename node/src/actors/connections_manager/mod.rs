@@ -1,23 +1,63 @@
+use std::{
+    cmp,
+    collections::VecDeque,
+    fs, io,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    time::{Duration, Instant},
+};
+
 use actix::prelude::*;
-use futures::Stream;
+use futures::{future, Future, Stream};
 use log;
-use tokio::net::{TcpListener, TcpStream};
+use net2::TcpBuilder;
+use tokio::{
+    io::{read_exact, write_all},
+    net::{TcpListener, TcpStream, UdpSocket},
+    reactor::Handle,
+    timer::{Delay, Timeout},
+};
 
 use crate::actors::{
-    messages::{Create, InboundTcpConnect, ResolverResult},
+    messages::{Create, InboundTcpConnect, ResolverResult, SetExternalAddress},
     sessions_manager::SessionsManager,
 };
 
 use crate::config_mngr;
 
-use witnet_p2p::sessions::SessionType;
+use witnet_p2p::{
+    happy_eyeballs::{attempt_delay, interleave_addresses},
+    metrics::NetworkTotals,
+    nat_pmp,
+    sessions::SessionType,
+    socks5::{self, Target},
+    user_agents::UserAgentFilter,
+};
 
 mod actor;
 mod handlers;
 
+/// Time to wait for the response of the NAT-PMP gateway
+const NAT_PMP_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Period to retry the port mapping after a failure
+const PORT_MAPPING_RETRY_PERIOD: Duration = Duration::from_secs(300);
+
+/// Time to wait for the proxy to connect to a peer. Tor may need to build a circuit first
+const PROXY_CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Time to wait for a feeler connection to be established
+const FEELER_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
 /// Connections manager actor
 #[derive(Default)]
-pub struct ConnectionsManager;
+pub struct ConnectionsManager {
+    /// Filter for the user agents announced by peers
+    user_agent_filter: UserAgentFilter,
+    /// Totals of the protocol messages exchanged with all the peers
+    network_totals: NetworkTotals,
+    /// SOCKS5 proxy for the outbound connections
+    proxy: Option<SocketAddr>,
+}
 
 /// Required trait for being able to retrieve connections manager address from system registry
 impl actix::Supervised for ConnectionsManager {}
@@ -30,24 +70,55 @@ impl ConnectionsManager {
     fn start_server(&mut self, ctx: &mut <Self as Actor>::Context) {
         config_mngr::get()
             .into_actor(self)
-            .and_then(|config, _, ctx| {
-                // Bind TCP listener to this address
-                // FIXME(#72): decide what to do with actor when server cannot be started
-                let listener = TcpListener::bind(&config.connections.server_addr).unwrap();
-
-                ctx.add_message_stream(
-                    listener
-                        .incoming()
-                        .map_err(|err| {
-                            log::error!("Error incoming listener: {}", err);
-                        })
-                        .map(InboundTcpConnect::new),
+            .and_then(|config, act, ctx| {
+                // Set the user agent patterns of peers to avoid or prefer
+                act.user_agent_filter = UserAgentFilter::new(
+                    config.connections.avoid_user_agents.clone(),
+                    config.connections.prefer_user_agents.clone(),
                 );
 
-                log::info!(
-                    "P2P server has been started at {:?}",
-                    &config.connections.server_addr
-                );
+                // Bind a TCP listener to the server address and to the address of every
+                // additional listener
+                let dual_stack = config.connections.dual_stack;
+                for address in config.connections.listen_addresses() {
+                    // FIXME(#72): decide what to do with actor when server cannot be started
+                    let listener = bind_listener(address, dual_stack).unwrap();
+
+                    ctx.add_message_stream(
+                        listener
+                            .incoming()
+                            .map_err(|err| {
+                                log::error!("Error incoming listener: {}", err);
+                            })
+                            .map(InboundTcpConnect::new),
+                    );
+
+                    log::info!("P2P server has been started at {:?}", address);
+                }
+
+                // Connect to peers through the proxy. The address of the node is not advertised,
+                // so the port is not mapped either
+                act.proxy = config.connections.proxy;
+                if let Some(proxy) = act.proxy {
+                    log::info!("Outbound connections go through SOCKS5 proxy {}", proxy);
+                }
+
+                // Map the server port in the NAT gateway so that peers outside the local network
+                // can connect to this node
+                if config.connections.port_mapping && act.proxy.is_none() {
+                    match config
+                        .connections
+                        .port_mapping_gateway
+                        .or_else(default_gateway)
+                    {
+                        Some(gateway) => {
+                            act.map_port(ctx, gateway, config.connections.server_addr.port())
+                        }
+                        None => log::warn!(
+                            "Port mapping failed to start: the default gateway was not found"
+                        ),
+                    }
+                }
 
                 fut::ok(())
             })
@@ -55,8 +126,74 @@ impl ConnectionsManager {
             .wait(ctx);
     }
 
-    /// Method to request the creation of a session actor from a TCP stream
-    fn request_session_creation(stream: TcpStream, session_type: SessionType) {
+    /// Method to map the server port in the NAT gateway using NAT-PMP, and to renew the mapping
+    /// before it expires. The external address is advertised to peers while the port is mapped.
+    fn map_port(&self, ctx: &mut <Self as Actor>::Context, gateway: Ipv4Addr, port: u16) {
+        let gateway_addr = SocketAddr::new(IpAddr::V4(gateway), nat_pmp::NAT_PMP_PORT);
+
+        nat_pmp_request(gateway_addr, nat_pmp::external_address_request())
+            .and_then(|response| {
+                nat_pmp::parse_external_address_response(&response).map_err(failure::Error::from)
+            })
+            .and_then(move |external| {
+                let request =
+                    nat_pmp::tcp_mapping_request(port, port, nat_pmp::DEFAULT_MAPPING_LIFETIME);
+
+                nat_pmp_request(gateway_addr, request)
+                    .and_then(|response| {
+                        nat_pmp::parse_mapping_response(&response).map_err(failure::Error::from)
+                    })
+                    .map(move |mapping| (external.address, mapping))
+            })
+            .into_actor(self)
+            .then(move |res, _act, ctx| {
+                let sessions_manager_addr = System::current().registry().get::<SessionsManager>();
+
+                let renew_period = match res {
+                    Ok((address, mapping)) => {
+                        let external_address =
+                            SocketAddr::new(IpAddr::V4(address), mapping.external_port);
+                        log::debug!(
+                            "P2P server port {} mapped to {} for {} seconds",
+                            port,
+                            external_address,
+                            mapping.lifetime
+                        );
+                        sessions_manager_addr.do_send(SetExternalAddress {
+                            address: Some(external_address),
+                        });
+
+                        // Renew the mapping halfway through its lifetime
+                        Duration::from_secs(u64::from(cmp::max(mapping.lifetime / 2, 1)))
+                    }
+                    Err(err) => {
+                        log::warn!(
+                            "Failed to map P2P server port {} in gateway {}: {}",
+                            port,
+                            gateway,
+                            err
+                        );
+                        sessions_manager_addr.do_send(SetExternalAddress { address: None });
+
+                        PORT_MAPPING_RETRY_PERIOD
+                    }
+                };
+                ctx.run_later(renew_period, move |act, ctx| {
+                    act.map_port(ctx, gateway, port)
+                });
+
+                fut::ok(())
+            })
+            .spawn(ctx);
+    }
+
+    /// Method to request the creation of a session actor from a TCP stream. The `remote_addr` of
+    /// streams connected through a proxy is the address of the peer, not the one of the proxy
+    fn request_session_creation(
+        stream: TcpStream,
+        session_type: SessionType,
+        remote_addr: Option<SocketAddr>,
+    ) {
         // Get sessions manager address
         let sessions_manager_addr = System::current().registry().get::<SessionsManager>();
 
@@ -64,9 +201,111 @@ impl ConnectionsManager {
         sessions_manager_addr.do_send(Create {
             stream,
             session_type,
+            remote_addr,
         });
     }
 
+    /// Connect to `target` through a SOCKS5 proxy. Host names are resolved by the proxy.
+    fn proxy_connect(
+        proxy: SocketAddr,
+        target: Target,
+    ) -> Box<dyn Future<Item = TcpStream, Error = io::Error>> {
+        let invalid_data =
+            |err: socks5::Socks5Error| io::Error::new(io::ErrorKind::InvalidData, err.to_string());
+
+        let handshake = TcpStream::connect(&proxy)
+            .and_then(|stream| write_all(stream, socks5::greeting()))
+            .and_then(|(stream, _)| read_exact(stream, [0; socks5::METHOD_SELECTION_LENGTH]))
+            .and_then(move |(stream, response)| {
+                socks5::parse_method_selection(&response)
+                    .map(|()| stream)
+                    .map_err(invalid_data)
+            })
+            .and_then(move |stream| write_all(stream, socks5::connect_request(&target)))
+            .and_then(|(stream, _)| read_exact(stream, [0; socks5::REPLY_HEADER_LENGTH]))
+            .and_then(move |(stream, header)| {
+                socks5::parse_reply_header(&header)
+                    .map(|reply_length| (stream, reply_length))
+                    .map_err(invalid_data)
+            })
+            // Skip the address bound by the proxy, which is not needed
+            .and_then(|(stream, reply_length)| {
+                read_exact(stream, vec![0; reply_length - socks5::REPLY_HEADER_LENGTH])
+            })
+            .map(|(stream, _)| stream);
+
+        Box::new(
+            Timeout::new(handshake, PROXY_CONNECT_TIMEOUT).map_err(move |err| {
+                if err.is_elapsed() {
+                    io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        format!("SOCKS5 proxy {} did not connect in time", proxy),
+                    )
+                } else {
+                    err.into_inner().unwrap_or_else(|| {
+                        io::Error::new(io::ErrorKind::Other, "SOCKS5 proxy timer failed")
+                    })
+                }
+            }),
+        )
+    }
+
+    /// Connect to `address` to check whether the peer is reachable, through the proxy if there
+    /// is one
+    fn feeler_connect(
+        &self,
+        address: SocketAddr,
+    ) -> Box<dyn Future<Item = TcpStream, Error = io::Error>> {
+        match self.proxy {
+            Some(proxy) => ConnectionsManager::proxy_connect(proxy, Target::Address(address)),
+            None => Box::new(
+                Timeout::new(TcpStream::connect(&address), FEELER_CONNECT_TIMEOUT).map_err(
+                    move |err| {
+                        if err.is_elapsed() {
+                            io::Error::new(
+                                io::ErrorKind::TimedOut,
+                                format!("peer {} did not accept the connection in time", address),
+                            )
+                        } else {
+                            err.into_inner().unwrap_or_else(|| {
+                                io::Error::new(io::ErrorKind::Other, "feeler timer failed")
+                            })
+                        }
+                    },
+                ),
+            ),
+        }
+    }
+
+    /// Connect to the addresses a host resolves to with Happy Eyeballs: the attempts are
+    /// staggered, IPv6 and IPv4 addresses alternate, and the first connection to succeed wins.
+    /// The pending attempts are cancelled by dropping them.
+    fn happy_eyeballs_connect(
+        addresses: VecDeque<SocketAddr>,
+    ) -> Box<dyn Future<Item = TcpStream, Error = io::Error>> {
+        let attempts: Vec<_> = interleave_addresses(addresses)
+            .into_iter()
+            .enumerate()
+            .map(|(index, address)| {
+                Delay::new(Instant::now() + attempt_delay(index))
+                    .map_err(|err| io::Error::new(io::ErrorKind::Other, err))
+                    .and_then(move |()| {
+                        log::debug!("Trying to connect to {}", address);
+                        TcpStream::connect(&address)
+                    })
+            })
+            .collect();
+
+        if attempts.is_empty() {
+            return Box::new(future::err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "the host has no addresses",
+            )));
+        }
+
+        Box::new(future::select_ok(attempts).map(|(stream, _pending)| stream))
+    }
+
     /// Method to process resolver ConnectAddr response
     fn process_connect_addr_response(
         response: Result<ResolverResult, MailboxError>,
@@ -93,7 +332,11 @@ impl ConnectionsManager {
                             });
 
                         // Request the creation of a new session actor from connection
-                        ConnectionsManager::request_session_creation(stream, SessionType::Outbound);
+                        ConnectionsManager::request_session_creation(
+                            stream,
+                            SessionType::Outbound,
+                            None,
+                        );
 
                         actix::fut::ok(())
                     }
@@ -102,3 +345,61 @@ impl ConnectionsManager {
         }
     }
 }
+
+/// Bind a TCP listener to `address`. IPv6 listeners accept IPv4 connections too if `dual_stack` is
+/// enabled, whatever the default of the operating system is
+fn bind_listener(address: SocketAddr, dual_stack: bool) -> io::Result<TcpListener> {
+    if address.is_ipv4() {
+        return TcpListener::bind(&address);
+    }
+
+    let builder = TcpBuilder::new_v6()?;
+    builder.only_v6(!dual_stack)?;
+    // Like the standard library, allow binding again right after a restart, except on Windows
+    // where this would allow other processes to bind to the same port
+    if cfg!(unix) {
+        builder.reuse_address(true)?;
+    }
+    let listener = builder.bind(address)?.listen(1024)?;
+
+    TcpListener::from_std(listener, &Handle::default())
+}
+
+/// Send a NAT-PMP request to the gateway and wait for its response
+fn nat_pmp_request(
+    gateway: SocketAddr,
+    request: Vec<u8>,
+) -> Box<dyn Future<Item = Vec<u8>, Error = failure::Error>> {
+    let local_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0);
+
+    let exchange = future::result(UdpSocket::bind(&local_addr))
+        .and_then(move |socket| socket.send_dgram(request, &gateway))
+        .and_then(|(socket, _)| socket.recv_dgram(vec![0; 16]))
+        .and_then(move |(_, mut response, length, source)| {
+            // Responses from other hosts may be forged
+            if source == gateway {
+                response.truncate(length);
+                Ok(response)
+            } else {
+                Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("NAT-PMP response from unexpected address {}", source),
+                ))
+            }
+        });
+
+    Box::new(Timeout::new(exchange, NAT_PMP_TIMEOUT).map_err(move |err| {
+        if err.is_elapsed() {
+            failure::format_err!("NAT-PMP gateway {} did not respond", gateway)
+        } else {
+            err.into()
+        }
+    }))
+}
+
+/// Find the default gateway of the host in its routing table. Only supported in Linux.
+fn default_gateway() -> Option<Ipv4Addr> {
+    fs::read_to_string("/proc/net/route")
+        .ok()
+        .and_then(|route_table| nat_pmp::parse_default_gateway(&route_table))
+}
@@ -5,7 +5,9 @@ use log::{debug, error};
 use witnet_data_structures::chain::Epoch;
 
 use super::EpochManager;
-use crate::actors::messages::{EpochResult, GetEpoch, SubscribeAll, SubscribeEpoch};
+use crate::actors::messages::{
+    EpochResult, GetEpoch, SubscribeAll, SubscribeEpoch, UpdateClockOffset,
+};
 
 ////////////////////////////////////////////////////////////////////////////////////////
 // ACTOR MESSAGE HANDLERS
@@ -56,3 +58,12 @@ impl Handler<SubscribeAll> for EpochManager {
         self.subscriptions_all.push(msg.notification);
     }
 }
+
+impl Handler<UpdateClockOffset> for EpochManager {
+    type Result = ();
+
+    /// Method to handle UpdateClockOffset messages
+    fn handle(&mut self, msg: UpdateClockOffset, _ctx: &mut Self::Context) {
+        self.set_clock_offset(&msg.samples);
+    }
+}
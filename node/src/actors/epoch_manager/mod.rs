@@ -5,17 +5,35 @@ use ansi_term::Color::Purple;
 
 use log::{debug, error, info, warn};
 
-use std::{collections::BTreeMap, time::Duration};
-
-use witnet_data_structures::chain::Epoch;
-use witnet_util::timestamp::{get_timestamp, get_timestamp_nanos};
-
-use crate::actors::messages::{EpochNotification, EpochResult};
+use std::{
+    collections::BTreeMap,
+    net::{ToSocketAddrs, UdpSocket},
+    thread,
+    time::Duration,
+};
+
+use witnet_config::config::Ntp;
+use witnet_data_structures::{
+    chain::{Epoch, EpochConstants},
+    error::EpochCalculationError,
+};
+use witnet_util::{
+    ntp::{self, NtpSample},
+    timestamp::{
+        clock_offset_millis, get_local_timestamp_millis, get_timestamp, get_timestamp_nanos,
+        set_clock_offset_millis,
+    },
+};
+
+use crate::actors::messages::{EpochNotification, EpochResult, UpdateClockOffset};
 use crate::config_mngr;
 
 mod actor;
 mod handlers;
 
+/// Time to wait for the response of each NTP server
+const NTP_TIMEOUT: Duration = Duration::from_secs(5);
+
 /// Possible errors when getting the current epoch
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum EpochManagerError {
@@ -32,6 +50,20 @@ pub enum EpochManagerError {
     Overflow,
 }
 
+impl From<EpochCalculationError> for EpochManagerError {
+    fn from(error: EpochCalculationError) -> Self {
+        match error {
+            EpochCalculationError::CheckpointZeroInTheFuture(zero) => {
+                EpochManagerError::CheckpointZeroInTheFuture(zero)
+            }
+            EpochCalculationError::ZeroCheckpointsPeriod => {
+                EpochManagerError::UnknownCheckpointPeriod
+            }
+            EpochCalculationError::Overflow => EpochManagerError::Overflow,
+        }
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////////////
 // ACTOR BASIC STRUCTURE
 ////////////////////////////////////////////////////////////////////////////////////////
@@ -52,6 +84,9 @@ pub struct EpochManager {
 
     /// Last epoch that was checked by the epoch monitor process
     last_checked_epoch: Option<Epoch>,
+
+    /// NTP configuration, if the clock is corrected with the NTP servers
+    ntp: Option<Ntp>,
 }
 
 /// Required trait for being able to retrieve EpochManager address from system registry
@@ -74,22 +109,21 @@ impl EpochManager {
         }
         self.checkpoints_period = Some(period);
     }
-    /// Calculate the last checkpoint (current epoch) at the supplied timestamp
-    pub fn epoch_at(&self, timestamp: i64) -> EpochResult<Epoch> {
+    /// Get the constants needed to convert between epochs and timestamps
+    pub fn epoch_constants(&self) -> EpochResult<EpochConstants> {
         match (self.checkpoint_zero_timestamp, self.checkpoints_period) {
-            (Some(zero), Some(period)) => {
-                let elapsed = timestamp - zero;
-                if elapsed < 0 {
-                    Err(EpochManagerError::CheckpointZeroInTheFuture(zero))
-                } else {
-                    let epoch = elapsed as Epoch / Epoch::from(period);
-                    Ok(epoch)
-                }
-            }
+            (Some(checkpoint_zero_timestamp), Some(checkpoints_period)) => Ok(EpochConstants {
+                checkpoint_zero_timestamp,
+                checkpoints_period,
+            }),
             (None, _) => Err(EpochManagerError::UnknownEpochZero),
             (_, None) => Err(EpochManagerError::UnknownCheckpointPeriod),
         }
     }
+    /// Calculate the last checkpoint (current epoch) at the supplied timestamp
+    pub fn epoch_at(&self, timestamp: i64) -> EpochResult<Epoch> {
+        Ok(self.epoch_constants()?.epoch_at(timestamp)?)
+    }
     /// Calculate the last checkpoint (current epoch)
     pub fn current_epoch(&self) -> EpochResult<Epoch> {
         let now = get_timestamp();
@@ -97,17 +131,7 @@ impl EpochManager {
     }
     /// Calculate the timestamp for a checkpoint (the start of an epoch)
     pub fn epoch_timestamp(&self, epoch: Epoch) -> EpochResult<i64> {
-        match (self.checkpoint_zero_timestamp, self.checkpoints_period) {
-            // Calculate (period * epoch + zero) with overflow checks
-            (Some(zero), Some(period)) => Epoch::from(period)
-                .checked_mul(epoch)
-                .filter(|&x| x <= Epoch::max_value() as Epoch)
-                .map(i64::from)
-                .and_then(|x| x.checked_add(zero))
-                .ok_or(EpochManagerError::Overflow),
-            (None, _) => Err(EpochManagerError::UnknownEpochZero),
-            (_, None) => Err(EpochManagerError::UnknownCheckpointPeriod),
-        }
+        Ok(self.epoch_constants()?.timestamp_of(epoch)?)
     }
     /// Method to process the configuration received from the config manager
     fn process_config(&mut self, ctx: &mut <Self as Actor>::Context) {
@@ -125,6 +149,16 @@ impl EpochManager {
                 // Start checkpoint monitoring process
                 actor.checkpoint_monitor(ctx);
 
+                // Start clock offset update process
+                if config.ntp.enabled && !config.ntp.servers.is_empty() {
+                    let update_period = config.ntp.update_period;
+                    actor.ntp = Some(config.ntp.clone());
+                    actor.update_clock_offset(ctx);
+                    if update_period.as_secs() > 0 {
+                        ctx.run_interval(update_period, |act, ctx| act.update_clock_offset(ctx));
+                    }
+                }
+
                 fut::ok(())
             })
             .map_err(|err, _, _| {
@@ -134,27 +168,13 @@ impl EpochManager {
     }
     /// Method to compute time remaining to next checkpoint
     fn time_to_next_checkpoint(&self) -> EpochResult<Duration> {
-        // Get current timestamp and epoch
+        // Get current timestamp and number of seconds remaining to the next checkpoint
         let (now_secs, now_nanos) = get_timestamp_nanos();
-        let current_epoch = self.epoch_at(now_secs)?;
-
-        // Get timestamp for the start of next checkpoint
-        let next_checkpoint = self.epoch_timestamp(
-            current_epoch
-                .checked_add(1)
-                .ok_or(EpochManagerError::Overflow)?,
-        )?;
-
-        // Get number of nanoseconds remaining to the next checkpoint
-        let secs = next_checkpoint - now_secs;
-
-        // Check if number of seconds to next checkpoint is valid
-        // This number should never be negative with current implementation
-        if secs < 0 {
-            Err(EpochManagerError::Overflow)
-        } else {
-            Ok(Duration::new(secs as u64, 1_000_000_000 - now_nanos))
-        }
+        let secs = self
+            .epoch_constants()?
+            .seconds_to_next_checkpoint(now_secs)?;
+
+        Ok(Duration::new(secs, 1_000_000_000 - now_nanos))
     }
     /// Method to monitor checkpoints and execute some actions on each
     fn checkpoint_monitor(&self, ctx: &mut Context<Self>) {
@@ -170,6 +190,15 @@ impl EpochManager {
                     Err(_) => return,
                 };
 
+                // The clock offset may have been corrected backwards since the process was
+                // scheduled, so the epoch may not have started yet
+                if act.last_checked_epoch.map_or(false, |last_checked_epoch| {
+                    current_epoch <= last_checked_epoch
+                }) {
+                    act.checkpoint_monitor(ctx);
+                    return;
+                }
+
                 // Send message to actors which subscribed to all epochs
                 for subscription in &mut act.subscriptions_all {
                     subscription.send_notification(current_epoch);
@@ -215,6 +244,88 @@ impl EpochManager {
             },
         );
     }
+    /// Method to measure the offset of the clock with the NTP servers. The requests block, so
+    /// they are sent from another thread, which reports the samples back to the actor
+    fn update_clock_offset(&self, ctx: &mut Context<Self>) {
+        let servers = match &self.ntp {
+            Some(ntp) => ntp.servers.clone(),
+            None => return,
+        };
+        let epoch_manager_addr = ctx.address();
+
+        thread::spawn(move || {
+            let samples = servers
+                .iter()
+                .filter_map(|server| match query_ntp_server(server) {
+                    Ok(sample) => Some(sample),
+                    Err(e) => {
+                        warn!("Failed to query NTP server {}: {}", server, e);
+                        None
+                    }
+                })
+                .collect();
+
+            epoch_manager_addr.do_send(UpdateClockOffset { samples });
+        });
+    }
+    /// Method to update the clock offset with the samples of the NTP servers
+    fn set_clock_offset(&self, samples: &[NtpSample]) {
+        let offset = match ntp::median_offset(samples) {
+            Some(offset) => offset,
+            None => {
+                warn!("No NTP server answered, the clock offset was not updated");
+                return;
+            }
+        };
+        debug!(
+            "Clock offset updated from {} ms to {} ms with {} NTP servers",
+            clock_offset_millis(),
+            offset,
+            samples.len()
+        );
+        set_clock_offset_millis(offset);
+
+        let max_offset = self
+            .ntp
+            .as_ref()
+            .map_or(0, |ntp| ntp.max_offset.as_millis());
+        if u128::from(offset.abs() as u64) > max_offset {
+            warn!(
+                "The local clock is {} ms {} the NTP servers. Epochs are still computed with the \
+                 corrected time, but the clock of the system should be synchronized",
+                offset.abs(),
+                if offset > 0 { "behind" } else { "ahead of" }
+            );
+        }
+    }
+}
+
+/// Measure the offset of the local clock with the NTP server at `server`, as `host:port`
+fn query_ntp_server(server: &str) -> Result<NtpSample, failure::Error> {
+    let address = server
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| failure::format_err!("Cannot resolve {}", server))?;
+    let local_address = if address.is_ipv4() {
+        "0.0.0.0:0"
+    } else {
+        "[::]:0"
+    };
+    let socket = UdpSocket::bind(local_address)?;
+    socket.set_read_timeout(Some(NTP_TIMEOUT))?;
+    socket.connect(address)?;
+
+    let origin = get_local_timestamp_millis();
+    socket.send(&ntp::request(origin))?;
+    let mut response = [0; 2 * ntp::NTP_PACKET_LEN];
+    let length = socket.recv(&mut response)?;
+    let destination = get_local_timestamp_millis();
+
+    Ok(ntp::parse_response(
+        &response[..length],
+        origin,
+        destination,
+    )?)
 }
 
 /// Trait that must follow all notifications that will be sent back to subscriber actors
@@ -1,10 +1,33 @@
-use actix::{Actor, Context, Supervised, SystemService};
+use actix::prelude::*;
+use log;
 
-use super::InventoryManager;
+use super::{
+    orphans::{OrphanPool, MAX_ORPHAN_BLOCKS, ORPHAN_BLOCK_EXPIRY},
+    InventoryManager,
+};
+use crate::config_mngr;
 
 /// Make actor from `InventoryManager`
 impl Actor for InventoryManager {
     type Context = Context<Self>;
+
+    /// Method to be executed when the actor is started
+    fn started(&mut self, ctx: &mut Self::Context) {
+        // Get the memory cap for the orphan blocks
+        config_mngr::get()
+            .into_actor(self)
+            .and_then(|config, act, _ctx| {
+                act.orphans = OrphanPool::new(
+                    MAX_ORPHAN_BLOCKS,
+                    config.memory.orphans_max_bytes,
+                    ORPHAN_BLOCK_EXPIRY,
+                );
+
+                fut::ok(())
+            })
+            .map_err(|err, _, _| log::error!("Inventory manager startup error: {}", err))
+            .wait(ctx);
+    }
 }
 
 /// Required trait to be able to be managed by a Supervisor
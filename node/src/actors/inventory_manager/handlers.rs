@@ -3,9 +3,21 @@ use actix::{ActorFuture, Context, Handler, ResponseActFuture, WrapFuture};
 use log;
 
 use super::{InventoryManager, InventoryManagerError};
-use crate::actors::messages::{AddItem, GetItem};
+use futures::{future, stream, Future, Stream};
+
+use crate::actors::{
+    messages::{
+        AddItem, AddOrphanBlock, Anycast, GetBlockHeader, GetItem, GetMemoryInfo, MemoryInfo,
+        PruneBlocks, SendInventoryRequest, TakeOrphanBlocks,
+    },
+    sessions_manager::SessionsManager,
+    storage_keys::block_header_key,
+};
 use crate::storage_mngr;
-use witnet_data_structures::chain::{Hash, Hashable, InventoryItem};
+use witnet_data_structures::chain::{
+    Block, BlockHeader, Hash, Hashable, InventoryEntry, InventoryItem,
+};
+use witnet_util::timestamp::get_timestamp;
 
 ////////////////////////////////////////////////////////////////////////////////////////
 // ACTOR MESSAGE HANDLERS
@@ -16,12 +28,7 @@ impl Handler<AddItem> for InventoryManager {
     type Result = ResponseActFuture<Self, (), InventoryManagerError>;
 
     fn handle(&mut self, msg: AddItem, _ctx: &mut Context<Self>) -> Self::Result {
-        let hash = match &msg.item {
-            InventoryItem::Block(item) => item.hash(),
-            InventoryItem::Transaction(item) => item.hash(),
-        };
-
-        let key = match hash {
+        let key = match msg.item.hash() {
             Hash::SHA256(h) => h.to_vec(),
         };
         let fut = storage_mngr::put(&key, &msg.item)
@@ -48,17 +55,167 @@ impl Handler<GetItem> for InventoryManager {
             Hash::SHA256(x) => x.to_vec(),
         };
 
+        let header_key = block_header_key(msg.hash);
         let fut = storage_mngr::get::<_, InventoryItem>(&key)
+            .and_then(move |opt| match opt {
+                Some(item) => future::Either::A(future::ok(Ok(item))),
+                // Pruned blocks are not served, but they are told apart from unknown items
+                None => future::Either::B(storage_mngr::get::<_, BlockHeader>(&header_key).map(
+                    |header| match header {
+                        Some(_) => Err(InventoryManagerError::ItemPruned),
+                        None => Err(InventoryManagerError::ItemDoesNotExist),
+                    },
+                )),
+            })
             .into_actor(self)
             .map_err(|e, _, _| {
                 log::error!("Couldn't get item from storage: {}", e);
                 InventoryManagerError::MailBoxError
             })
+            .and_then(|res, _, _| fut::result(res));
+
+        Box::new(fut)
+    }
+}
+
+/// Handler for GetBlockHeader message
+impl Handler<GetBlockHeader> for InventoryManager {
+    type Result = ResponseActFuture<Self, BlockHeader, InventoryManagerError>;
+
+    fn handle(&mut self, msg: GetBlockHeader, _ctx: &mut Context<Self>) -> Self::Result {
+        let key = match msg.hash {
+            Hash::SHA256(x) => x.to_vec(),
+        };
+
+        let header_key = block_header_key(msg.hash);
+        let fut = storage_mngr::get::<_, InventoryItem>(&key)
+            .and_then(move |opt| match opt {
+                Some(InventoryItem::Block(block)) => {
+                    future::Either::A(future::ok(Some(block.block_header)))
+                }
+                _ => future::Either::B(storage_mngr::get::<_, BlockHeader>(&header_key)),
+            })
+            .into_actor(self)
+            .map_err(|e, _, _| {
+                log::error!("Couldn't get block header from storage: {}", e);
+                InventoryManagerError::MailBoxError
+            })
             .and_then(|opt, _, _| match opt {
                 None => fut::err(InventoryManagerError::ItemDoesNotExist),
-                Some(item) => fut::ok(item),
+                Some(header) => fut::ok(header),
             });
 
         Box::new(fut)
     }
 }
+
+/// Handler for PruneBlocks message
+impl Handler<PruneBlocks> for InventoryManager {
+    type Result = ();
+
+    fn handle(&mut self, PruneBlocks { hashes }: PruneBlocks, ctx: &mut Context<Self>) {
+        let num_blocks = hashes.len();
+
+        // The header of every block is persisted along with the deletion of the block, so it is
+        // never lost
+        stream::iter_ok(hashes)
+            .and_then(|hash| {
+                let key = match hash {
+                    Hash::SHA256(x) => x.to_vec(),
+                };
+
+                storage_mngr::get::<_, InventoryItem>(&key).and_then(move |opt| match opt {
+                    Some(InventoryItem::Block(block)) => {
+                        let mut batch = storage_mngr::WriteBatch::default();
+                        future::Either::A(
+                            future::result(
+                                batch
+                                    .put(&block_header_key(hash), &block.block_header)
+                                    .and_then(|()| batch.delete(&key)),
+                            )
+                            .and_then(move |()| storage_mngr::write(batch)),
+                        )
+                    }
+                    // Already pruned
+                    _ => future::Either::B(future::ok(())),
+                })
+            })
+            .for_each(|()| Ok(()))
+            .into_actor(self)
+            .map_err(|e, _, _| log::error!("Failed to prune blocks: {}", e))
+            .and_then(move |(), _, _| {
+                log::debug!("Pruned the bodies of {} blocks", num_blocks);
+
+                fut::ok(())
+            })
+            .wait(ctx);
+    }
+}
+
+/// Handler for AddOrphanBlock message
+impl Handler<AddOrphanBlock> for InventoryManager {
+    type Result = ();
+
+    fn handle(&mut self, AddOrphanBlock { block }: AddOrphanBlock, ctx: &mut Context<Self>) {
+        let block_hash = block.hash();
+        let ancestor = match self.orphans.insert(block, get_timestamp()) {
+            Some(ancestor) => ancestor,
+            None => return,
+        };
+        log::debug!(
+            "Orphan block {} added to the pool, waiting for block {}",
+            block_hash,
+            ancestor
+        );
+
+        // The missing ancestor is only requested if it is not in the inventory: otherwise the
+        // orphan belongs to a fork, and it will expire
+        let key = match ancestor {
+            Hash::SHA256(x) => x.to_vec(),
+        };
+        storage_mngr::get::<_, InventoryItem>(&key)
+            .into_actor(self)
+            .map_err(|e, _, _| log::error!("Couldn't get item from storage: {}", e))
+            .and_then(move |opt, _, _| {
+                if opt.is_none() {
+                    SessionsManager::from_registry().do_send(Anycast {
+                        command: SendInventoryRequest {
+                            items: vec![InventoryEntry::Block(ancestor)],
+                        },
+                        safu: true,
+                    });
+                }
+
+                fut::ok(())
+            })
+            .wait(ctx);
+    }
+}
+
+/// Handler for TakeOrphanBlocks message
+impl Handler<TakeOrphanBlocks> for InventoryManager {
+    type Result = MessageResult<TakeOrphanBlocks>;
+
+    fn handle(
+        &mut self,
+        TakeOrphanBlocks { parent }: TakeOrphanBlocks,
+        _ctx: &mut Context<Self>,
+    ) -> Self::Result {
+        let orphans: Vec<Block> = self.orphans.take_children(&parent, get_timestamp());
+
+        MessageResult(orphans)
+    }
+}
+
+/// Handler for GetMemoryInfo message
+impl Handler<GetMemoryInfo> for InventoryManager {
+    type Result = MessageResult<GetMemoryInfo>;
+
+    fn handle(&mut self, _msg: GetMemoryInfo, _ctx: &mut Context<Self>) -> Self::Result {
+        MessageResult(vec![MemoryInfo {
+            component: "orphan_blocks".to_string(),
+            usage: self.orphans.memory_usage(),
+            cap: self.orphans.max_memory(),
+        }])
+    }
+}
@@ -4,12 +4,18 @@
 
 use std::fmt;
 
+use self::orphans::OrphanPool;
+
 mod actor;
 mod handlers;
+mod orphans;
 
 /// InventoryManager actor
 #[derive(Default)]
-pub struct InventoryManager;
+pub struct InventoryManager {
+    /// Blocks received before their parent
+    orphans: OrphanPool,
+}
 
 /// Possible errors when interacting with InventoryManager
 #[derive(Debug)]
@@ -18,6 +24,8 @@ pub enum InventoryManagerError {
     ItemAlreadyExists,
     /// An item does not exist
     ItemDoesNotExist,
+    /// The body of a block has been pruned, only its header is kept
+    ItemPruned,
     /// MailBoxError
     MailBoxError,
 }
@@ -0,0 +1,282 @@
+//! # Orphan block pool
+//!
+//! Blocks whose parent is not known yet are not dropped: they are kept in the `OrphanPool`,
+//! indexed by the hash of their parent, while the missing ancestor is requested. When the parent
+//! is accepted, its orphan children are taken from the pool to be processed again. The pool is
+//! capped both in number of blocks and in memory, evicting the oldest orphans first, and orphans
+//! whose parent never arrives expire.
+use std::collections::HashMap;
+
+use witnet_data_structures::chain::{Block, Hash, Hashable};
+
+/// Maximum number of orphan blocks kept in the pool
+pub const MAX_ORPHAN_BLOCKS: usize = 100;
+
+/// Seconds an orphan block is kept in the pool waiting for its parent
+pub const ORPHAN_BLOCK_EXPIRY: i64 = 600;
+
+/// Blocks waiting for their parent. Timestamps are in seconds.
+#[derive(Debug)]
+pub struct OrphanPool {
+    /// Maximum number of orphans
+    max_blocks: usize,
+    /// Memory cap for the orphans, `0` means no cap
+    max_memory: usize,
+    /// Seconds each orphan is kept
+    expiry: i64,
+    /// Orphans by hash, with the timestamp they expire at
+    blocks: HashMap<Hash, (Block, i64)>,
+    /// Hashes of the orphans by hash of their parent
+    children: HashMap<Hash, Vec<Hash>>,
+    /// Approximate number of bytes used by the orphans
+    memory_usage: usize,
+}
+
+impl Default for OrphanPool {
+    fn default() -> Self {
+        Self::new(MAX_ORPHAN_BLOCKS, 0, ORPHAN_BLOCK_EXPIRY)
+    }
+}
+
+impl OrphanPool {
+    /// Create a pool of up to `max_blocks` orphans using up to `max_memory` bytes, each kept for
+    /// `expiry` seconds
+    pub fn new(max_blocks: usize, max_memory: usize, expiry: i64) -> Self {
+        OrphanPool {
+            max_blocks,
+            max_memory,
+            expiry,
+            blocks: HashMap::new(),
+            children: HashMap::new(),
+            memory_usage: 0,
+        }
+    }
+    /// Number of orphans in the pool
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+    /// Method to check if the pool is empty
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+    /// Approximate number of bytes used by the orphans
+    pub fn memory_usage(&self) -> usize {
+        self.memory_usage
+    }
+    /// Memory cap for the orphans, `0` means no cap
+    pub fn max_memory(&self) -> usize {
+        self.max_memory
+    }
+    /// Method to check if a block is in the pool
+    pub fn contains(&self, hash: &Hash) -> bool {
+        self.blocks.contains_key(hash)
+    }
+    /// Method to add an orphan block to the pool, evicting the oldest orphans until it fits.
+    /// Returns the hash of the missing ancestor to request, or `None` if the block was already in
+    /// the pool or the ancestor is already awaited by other orphans
+    pub fn insert(&mut self, block: Block, now: i64) -> Option<Hash> {
+        self.expire(now);
+
+        let hash = block.hash();
+        let block_memory_usage = block.size() as usize;
+        let max_memory = self.max_memory;
+        let exceeds_memory = |memory_usage: usize| max_memory != 0 && memory_usage > max_memory;
+        if self.max_blocks == 0 || exceeds_memory(block_memory_usage) || self.contains(&hash) {
+            return None;
+        }
+        while self.blocks.len() >= self.max_blocks
+            || exceeds_memory(self.memory_usage + block_memory_usage)
+        {
+            let oldest = self
+                .blocks
+                .iter()
+                .min_by_key(|(_, (_, expiration))| *expiration)
+                .map(|(hash, _)| *hash);
+            match oldest {
+                Some(oldest) => self.remove(&oldest),
+                None => break,
+            }
+        }
+
+        let parent = block.block_header.beacon.hash_prev_block;
+        let ancestor = self.missing_ancestor(parent);
+        let awaited = self.children.contains_key(&ancestor);
+
+        self.memory_usage += block_memory_usage;
+        self.blocks.insert(hash, (block, now + self.expiry));
+        self.children.entry(parent).or_default().push(hash);
+
+        if awaited {
+            None
+        } else {
+            Some(ancestor)
+        }
+    }
+    /// Method to take the orphans whose parent is `parent`, which can be processed now
+    pub fn take_children(&mut self, parent: &Hash, now: i64) -> Vec<Block> {
+        self.expire(now);
+
+        self.children
+            .remove(parent)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|hash| self.take(&hash))
+            .collect()
+    }
+    /// Method to remove the orphans that expired at `now`
+    pub fn expire(&mut self, now: i64) {
+        let expired: Vec<Hash> = self
+            .blocks
+            .iter()
+            .filter(|(_, (_, expiration))| *expiration <= now)
+            .map(|(hash, _)| *hash)
+            .collect();
+        for hash in expired {
+            self.remove(&hash);
+        }
+    }
+
+    /// First ancestor of `parent` that is not an orphan, including `parent` itself
+    fn missing_ancestor(&self, parent: Hash) -> Hash {
+        let mut ancestor = parent;
+        // Bounded by the number of orphans, in case the blocks form a cycle
+        for _ in 0..=self.blocks.len() {
+            match self.blocks.get(&ancestor) {
+                Some((block, _)) => ancestor = block.block_header.beacon.hash_prev_block,
+                None => break,
+            }
+        }
+
+        ancestor
+    }
+
+    fn take(&mut self, hash: &Hash) -> Option<Block> {
+        let (block, _) = self.blocks.remove(hash)?;
+        self.memory_usage -= block.size() as usize;
+
+        Some(block)
+    }
+
+    fn remove(&mut self, hash: &Hash) {
+        if let Some(block) = self.take(hash) {
+            let parent = block.block_header.beacon.hash_prev_block;
+            let is_empty = match self.children.get_mut(&parent) {
+                Some(siblings) => {
+                    siblings.retain(|sibling| sibling != hash);
+                    siblings.is_empty()
+                }
+                None => false,
+            };
+            if is_empty {
+                self.children.remove(&parent);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use witnet_data_structures::chain::{BlockHeader, CheckpointBeacon, LeadershipProof};
+
+    fn block(checkpoint: u32, hash_prev_block: Hash) -> Block {
+        Block {
+            block_header: BlockHeader {
+                beacon: CheckpointBeacon {
+                    checkpoint,
+                    hash_prev_block,
+                },
+                ..BlockHeader::default()
+            },
+            proof: LeadershipProof::default(),
+            txns: vec![],
+        }
+    }
+
+    #[test]
+    fn insert_requests_missing_ancestor() {
+        let mut pool = OrphanPool::default();
+        let missing = Hash::SHA256([1; 32]);
+        let parent = block(2, missing);
+        let child = block(3, parent.hash());
+        let sibling = block(4, parent.hash());
+
+        // The child waits for its parent, which waits for the missing block
+        assert_eq!(pool.insert(child.clone(), 0), Some(parent.hash()));
+        assert_eq!(pool.insert(parent.clone(), 0), Some(missing));
+        // The missing block is already awaited
+        assert_eq!(pool.insert(sibling.clone(), 0), None);
+        assert_eq!(pool.insert(child.clone(), 0), None);
+        assert_eq!(pool.len(), 3);
+
+        assert_eq!(pool.take_children(&missing, 1), vec![parent.clone()]);
+        let mut children = pool.take_children(&parent.hash(), 1);
+        children.sort_by_key(|block| block.block_header.beacon.checkpoint);
+        assert_eq!(children, vec![child, sibling]);
+        assert!(pool.is_empty());
+    }
+
+    #[test]
+    fn orphans_expire() {
+        let mut pool = OrphanPool::new(10, 0, 100);
+        let parent = Hash::SHA256([1; 32]);
+        let orphan = block(2, parent);
+        pool.insert(orphan.clone(), 0);
+
+        assert_eq!(pool.take_children(&parent, 100), vec![]);
+        assert!(pool.is_empty());
+
+        // Once expired, the parent is requested again
+        assert_eq!(pool.insert(orphan.clone(), 100), Some(parent));
+        pool.expire(200);
+        assert!(!pool.contains(&orphan.hash()));
+    }
+
+    #[test]
+    fn oldest_orphan_evicted() {
+        let mut pool = OrphanPool::new(2, 0, 100);
+        let orphans: Vec<Block> = (1..=3)
+            .map(|i| block(i, Hash::SHA256([i as u8; 32])))
+            .collect();
+        for (now, orphan) in orphans.iter().enumerate() {
+            pool.insert(orphan.clone(), now as i64);
+        }
+
+        assert_eq!(pool.len(), 2);
+        assert!(!pool.contains(&orphans[0].hash()));
+        assert!(pool.contains(&orphans[1].hash()));
+        assert!(pool.contains(&orphans[2].hash()));
+        assert_eq!(pool.take_children(&Hash::SHA256([1; 32]), 3), vec![]);
+
+        // A pool without room keeps nothing
+        let mut pool = OrphanPool::new(0, 0, 100);
+        assert_eq!(pool.insert(orphans[0].clone(), 0), None);
+        assert!(pool.is_empty());
+    }
+
+    #[test]
+    fn orphans_evicted_by_memory() {
+        let orphans: Vec<Block> = (1..=3)
+            .map(|i| block(i, Hash::SHA256([i as u8; 32])))
+            .collect();
+        let block_memory_usage = orphans[0].size() as usize;
+        let mut pool = OrphanPool::new(10, 2 * block_memory_usage, 100);
+        for (now, orphan) in orphans.iter().enumerate() {
+            pool.insert(orphan.clone(), now as i64);
+        }
+
+        assert_eq!(pool.len(), 2);
+        assert_eq!(pool.memory_usage(), 2 * block_memory_usage);
+        assert!(!pool.contains(&orphans[0].hash()));
+        assert_eq!(
+            pool.take_children(&Hash::SHA256([2; 32]), 3),
+            vec![orphans[1].clone()]
+        );
+        assert_eq!(pool.memory_usage(), block_memory_usage);
+
+        // A block larger than the cap is not kept
+        let mut pool = OrphanPool::new(10, block_memory_usage - 1, 100);
+        assert_eq!(pool.insert(orphans[0].clone(), 0), None);
+        assert!(pool.is_empty());
+    }
+}
@@ -12,13 +12,29 @@ use jsonrpc_pubsub::{PubSubHandler, Session, Subscriber, SubscriptionId};
 use log::{debug, error, info};
 use serde::{Deserialize, Serialize};
 
-use witnet_data_structures::chain::{self, Block, Hash, InventoryEntry, Transaction};
-
-use crate::actors::{
-    chain_manager::{ChainManager, ChainManagerError},
-    epoch_manager::EpochManager,
-    inventory_manager::InventoryManager,
-    messages::{AddCandidates, AddTransaction, GetBlocksEpochRange, GetEpoch, GetItem},
+use witnet_data_structures::chain::{
+    self, Block, Hash, InventoryEntry, PublicKeyHash, Transaction,
+};
+use witnet_p2p::sessions::access::AccessList;
+
+use crate::{
+    actors::{
+        chain_manager::{ChainManager, ChainManagerError},
+        connections_manager::ConnectionsManager,
+        epoch_manager::EpochManager,
+        inventory_manager::InventoryManager,
+        messages::{
+            AddCandidates, AddTransaction, GetAccessList, GetBlockTemplate, GetBlocksEpochRange,
+            GetChainStatus, GetDataRequest, GetDataRequestsByRequester, GetEpoch, GetItem,
+            GetMemoryInfo, GetMempool, GetNetworkTotals, GetPeersLatency, GetReputation,
+            GetSessionsCount, GetSignatureCacheStats, SetAccessList, SubmitBlock,
+        },
+        node_status::NodeStatus,
+        peers_manager::PeersManager,
+        sessions_manager::SessionsManager,
+    },
+    build_info::BuildInfo,
+    config_mngr,
 };
 
 //use std::str::FromStr;
@@ -40,6 +56,30 @@ pub fn jsonrpc_io_handler(subscriptions: Subscriptions) -> PubSubHandler<Arc<Ses
         get_block_chain(params.parse())
     });
     io.add_method("getBlock", |params: Params| get_block(params.parse()));
+    io.add_method("getVersion", |_params: Params| get_version());
+    io.add_method("getBlockTemplate", |_params: Params| get_block_template());
+    io.add_method("submitBlock", |params: Params| submit_block(params.parse()));
+    io.add_method("getMemoryInfo", |_params: Params| get_memory_info());
+    io.add_method("getNodeStatus", |_params: Params| get_node_status());
+    io.add_method("getMempool", |_params: Params| get_mempool());
+    io.add_method("getNetworkTotals", |_params: Params| get_network_totals());
+    io.add_method("getSignatureCacheStats", |_params: Params| {
+        get_signature_cache_stats()
+    });
+    io.add_method("getReputation", |params: Params| {
+        get_reputation(params.parse())
+    });
+    io.add_method("getDataRequest", |params: Params| {
+        get_data_request(params.parse())
+    });
+    io.add_method("getDataRequestsByRequester", |params: Params| {
+        get_data_requests_by_requester(params.parse())
+    });
+    io.add_method("getPeersLatency", |_params: Params| get_peers_latency());
+    io.add_method("getAccessList", |_params: Params| get_access_list());
+    io.add_method("setAccessList", |params: Params| {
+        set_access_list(params.parse())
+    });
     //io.add_method("getOutput", |params: Params| get_output(params.parse()));
 
     // We need two Arcs, one for subscribe and one for unsuscribe
@@ -364,6 +404,321 @@ pub fn get_block(hash: Result<(Hash,), jsonrpc_core::Error>) -> JsonRpcResultAsy
     )
 }
 
+/// Get the version and build information of the node
+/* test
+{"jsonrpc":"2.0","id":1,"method":"getVersion"}
+*/
+pub fn get_version() -> JsonRpcResultAsync {
+    Box::new(config_mngr::get().then(|res| match res {
+        Ok(config) => {
+            let build_info = BuildInfo::new(
+                &config.consensus_constants,
+                &config.connections.user_agent_suffix,
+            );
+            match serde_json::to_value(build_info) {
+                Ok(value) => futures::finished(value),
+                Err(e) => futures::failed(internal_error(e)),
+            }
+        }
+        Err(e) => futures::failed(internal_error(e)),
+    }))
+}
+
+/// Get a template of the block to be mined in the current epoch, for external miners
+/* test
+{"jsonrpc":"2.0","id":1,"method":"getBlockTemplate"}
+*/
+pub fn get_block_template() -> JsonRpcResultAsync {
+    let chain_manager_addr = ChainManager::from_registry();
+    Box::new(
+        chain_manager_addr
+            .send(GetBlockTemplate)
+            .then(|res| match res {
+                Ok(Ok(block_template)) => match serde_json::to_value(block_template) {
+                    Ok(value) => futures::finished(value),
+                    Err(e) => futures::failed(internal_error(e)),
+                },
+                Ok(Err(e)) => futures::failed(internal_error(e)),
+                Err(e) => futures::failed(internal_error(e)),
+            }),
+    )
+}
+
+/// Get the approximate memory usage of the pools and caches of the node, and their caps
+/* test
+{"jsonrpc":"2.0","id":1,"method":"getMemoryInfo"}
+*/
+pub fn get_memory_info() -> JsonRpcResultAsync {
+    let chain_manager_addr = ChainManager::from_registry();
+    let inventory_manager_addr = InventoryManager::from_registry();
+    let peers_manager_addr = PeersManager::from_registry();
+    Box::new(
+        chain_manager_addr
+            .send(GetMemoryInfo)
+            .join3(
+                inventory_manager_addr.send(GetMemoryInfo),
+                peers_manager_addr.send(GetMemoryInfo),
+            )
+            .then(|res| match res {
+                Ok((mut memory_info, inventory_memory_info, peers_memory_info)) => {
+                    memory_info.extend(inventory_memory_info);
+                    memory_info.extend(peers_memory_info);
+                    match serde_json::to_value(memory_info) {
+                        Ok(value) => futures::finished(value),
+                        Err(e) => futures::failed(internal_error(e)),
+                    }
+                }
+                Err(e) => futures::failed(internal_error(e)),
+            }),
+    )
+}
+
+/// Get the status of the node: its health, the synchronization state of the chain and the
+/// number of consolidated sessions
+/* test
+{"jsonrpc":"2.0","id":1,"method":"getNodeStatus"}
+*/
+pub fn get_node_status() -> JsonRpcResultAsync {
+    let chain_manager_addr = ChainManager::from_registry();
+    let sessions_manager_addr = SessionsManager::from_registry();
+    Box::new(
+        chain_manager_addr
+            .send(GetChainStatus)
+            .join(sessions_manager_addr.send(GetSessionsCount))
+            .then(|res| match res {
+                Ok((chain_status, sessions_count)) => {
+                    let node_status = NodeStatus::new(chain_status, sessions_count);
+                    match serde_json::to_value(node_status) {
+                        Ok(value) => futures::finished(value),
+                        Err(e) => futures::failed(internal_error(e)),
+                    }
+                }
+                Err(e) => futures::failed(internal_error(e)),
+            }),
+    )
+}
+
+/// Get the unconfirmed transactions of the transactions pool, from the highest fee per unit of
+/// weight to the lowest, and the weight of the pool
+/* test
+{"jsonrpc":"2.0","id":1,"method":"getMempool"}
+*/
+pub fn get_mempool() -> JsonRpcResultAsync {
+    let chain_manager_addr = ChainManager::from_registry();
+    Box::new(chain_manager_addr.send(GetMempool).then(|res| match res {
+        Ok(mempool) => match serde_json::to_value(mempool) {
+            Ok(value) => futures::finished(value),
+            Err(e) => futures::failed(internal_error(e)),
+        },
+        Err(e) => futures::failed(internal_error(e)),
+    }))
+}
+
+/// Get the reputation of an identity, given its public key hash
+/* test
+{"jsonrpc":"2.0","id":1,"method":"getReputation","params":[[92,113,79,236,128,183,243,12,54,238,99,233,99,89,168,90,227,233,40,73]]}
+*/
+pub fn get_reputation(pkh: Result<(PublicKeyHash,), jsonrpc_core::Error>) -> JsonRpcResultAsync {
+    let pkh = match pkh {
+        Ok(x) => x.0,
+        Err(e) => return Box::new(futures::failed(e)),
+    };
+
+    let chain_manager_addr = ChainManager::from_registry();
+    Box::new(
+        chain_manager_addr
+            .send(GetReputation { pkh })
+            .then(|res| match res {
+                Ok(reputation) => match serde_json::to_value(reputation) {
+                    Ok(value) => futures::finished(value),
+                    Err(e) => futures::failed(internal_error(e)),
+                },
+                Err(e) => futures::failed(internal_error(e)),
+            }),
+    )
+}
+
+/// Get the state of the data request created by the transaction with the given hash: its stage,
+/// commits and reveals while it is active, or its commits, reveals and tally once resolved
+/* test
+{"jsonrpc":"2.0","id":1,"method":"getDataRequest","params":["c0002c6b25615c0f71069f159dffddf8a0b3e529efb054402f0649e969715bdb"]}
+*/
+pub fn get_data_request(hash: Result<(Hash,), jsonrpc_core::Error>) -> JsonRpcResultAsync {
+    let hash = match hash {
+        Ok(x) => x.0,
+        Err(e) => return Box::new(futures::failed(e)),
+    };
+
+    let chain_manager_addr = ChainManager::from_registry();
+    Box::new(
+        chain_manager_addr
+            .send(GetDataRequest { hash })
+            .then(move |res| match res {
+                Ok(Ok(Some(dr_status))) => match serde_json::to_value(dr_status) {
+                    Ok(value) => futures::finished(value),
+                    Err(e) => futures::failed(internal_error(e)),
+                },
+                Ok(Ok(None)) => {
+                    futures::failed(internal_error(format!("Data request {} not found", hash)))
+                }
+                Ok(Err(e)) => futures::failed(internal_error(e)),
+                Err(e) => futures::failed(internal_error(e)),
+            }),
+    )
+}
+
+/// Get the active data requests created by an identity, given its public key hash
+/* test
+{"jsonrpc":"2.0","id":1,"method":"getDataRequestsByRequester","params":[[92,113,79,236,128,183,243,12,54,238,99,233,99,89,168,90,227,233,40,73]]}
+*/
+pub fn get_data_requests_by_requester(
+    pkh: Result<(PublicKeyHash,), jsonrpc_core::Error>,
+) -> JsonRpcResultAsync {
+    let pkh = match pkh {
+        Ok(x) => x.0,
+        Err(e) => return Box::new(futures::failed(e)),
+    };
+
+    let chain_manager_addr = ChainManager::from_registry();
+    Box::new(
+        chain_manager_addr
+            .send(GetDataRequestsByRequester { pkh })
+            .then(|res| match res {
+                Ok(data_requests) => match serde_json::to_value(data_requests) {
+                    Ok(value) => futures::finished(value),
+                    Err(e) => futures::failed(internal_error(e)),
+                },
+                Err(e) => futures::failed(internal_error(e)),
+            }),
+    )
+}
+
+/// Get the number of messages, bytes and message size histograms of the protocol messages
+/// exchanged with all the peers, by command and direction
+/* test
+{"jsonrpc":"2.0","id":1,"method":"getNetworkTotals"}
+*/
+pub fn get_network_totals() -> JsonRpcResultAsync {
+    let connections_manager_addr = ConnectionsManager::from_registry();
+    Box::new(
+        connections_manager_addr
+            .send(GetNetworkTotals)
+            .then(|res| match res {
+                Ok(network_totals) => match serde_json::to_value(network_totals) {
+                    Ok(value) => futures::finished(value),
+                    Err(e) => futures::failed(internal_error(e)),
+                },
+                Err(e) => futures::failed(internal_error(e)),
+            }),
+    )
+}
+
+/// Get the usage counters of the cache of successful signature verifications, including its
+/// hit rate
+/* test
+{"jsonrpc":"2.0","id":1,"method":"getSignatureCacheStats"}
+*/
+pub fn get_signature_cache_stats() -> JsonRpcResultAsync {
+    let chain_manager_addr = ChainManager::from_registry();
+    Box::new(
+        chain_manager_addr
+            .send(GetSignatureCacheStats)
+            .then(|res| match res {
+                Ok(stats) => match serde_json::to_value(stats) {
+                    Ok(mut value) => {
+                        value["hit_rate"] = stats.hit_rate().into();
+                        futures::finished(value)
+                    }
+                    Err(e) => futures::failed(internal_error(e)),
+                },
+                Err(e) => futures::failed(internal_error(e)),
+            }),
+    )
+}
+
+/// Get the latency measured with ping and pong messages for every peer with a consolidated session
+/* test
+{"jsonrpc":"2.0","id":1,"method":"getPeersLatency"}
+*/
+pub fn get_peers_latency() -> JsonRpcResultAsync {
+    let sessions_manager_addr = SessionsManager::from_registry();
+    Box::new(
+        sessions_manager_addr
+            .send(GetPeersLatency)
+            .then(|res| match res {
+                Ok(peers_latency) => match serde_json::to_value(peers_latency) {
+                    Ok(value) => futures::finished(value),
+                    Err(e) => futures::failed(internal_error(e)),
+                },
+                Err(e) => futures::failed(internal_error(e)),
+            }),
+    )
+}
+
+/// Get the IP ranges the sessions are allowed or forbidden to connect to
+/* test
+{"jsonrpc":"2.0","id":1,"method":"getAccessList"}
+*/
+pub fn get_access_list() -> JsonRpcResultAsync {
+    let sessions_manager_addr = SessionsManager::from_registry();
+    Box::new(
+        sessions_manager_addr
+            .send(GetAccessList)
+            .then(|res| match res {
+                Ok(access_list) => match serde_json::to_value(access_list) {
+                    Ok(value) => futures::finished(value),
+                    Err(e) => futures::failed(internal_error(e)),
+                },
+                Err(e) => futures::failed(internal_error(e)),
+            }),
+    )
+}
+
+/// Replace the IP ranges the sessions are allowed or forbidden to connect to, closing the
+/// sessions with peers that are no longer allowed.
+///
+/// Returns a boolean indicating success.
+/* test
+{"jsonrpc":"2.0","id":1,"method":"setAccessList","params":{"whitelist":["10.0.0.0/8"],"blacklist":["10.0.0.13"]}}
+*/
+pub fn set_access_list(access_list: Result<AccessList, jsonrpc_core::Error>) -> JsonRpcResultAsync {
+    let access_list = match access_list {
+        Ok(x) => x,
+        Err(e) => return Box::new(futures::failed(e)),
+    };
+
+    let sessions_manager_addr = SessionsManager::from_registry();
+    Box::new(
+        sessions_manager_addr
+            .send(SetAccessList { access_list })
+            .then(|res| match res {
+                Ok(()) => futures::finished(Value::Bool(true)),
+                Err(e) => futures::failed(internal_error(e)),
+            }),
+    )
+}
+
+/// Submit a block built by an external miner.
+///
+/// Returns a boolean indicating success.
+pub fn submit_block(block: Result<(Block,), jsonrpc_core::Error>) -> JsonRpcResultAsync {
+    let block = match block {
+        Ok(x) => x.0,
+        Err(e) => return Box::new(futures::failed(e)),
+    };
+
+    let chain_manager_addr = ChainManager::from_registry();
+    Box::new(
+        chain_manager_addr
+            .send(SubmitBlock { block })
+            .then(|res| match res {
+                Ok(Ok(())) => futures::finished(Value::Bool(true)),
+                Ok(Err(e)) => futures::failed(internal_error(e)),
+                Err(e) => futures::failed(internal_error(e)),
+            }),
+    )
+}
+
 /*
 /// get output
 pub fn get_output(output_pointer: Result<(String,), jsonrpc_core::Error>) -> JsonRpcResultAsync {
@@ -440,7 +795,7 @@ mod tests {
     use futures::sync::mpsc;
 
     #[cfg(test)]
-    use witnet_data_structures::chain::RADRequest;
+    use witnet_data_structures::{chain::RADRequest, wit::Wit};
 
     use super::*;
 
@@ -491,12 +846,24 @@ mod tests {
             kind: RADType::HttpGet,
             url: "https://openweathermap.org/data/2.5/weather?id=2950159&appid=b6907d289e10d714a6e88b30761fae22".to_string(),
             script: vec![0],
+            max_response_size: 0,
+            content_type: String::new(),
+            certificate_fingerprint: vec![],
+            timeout: 0,
+            body: vec![],
+            body_content_type: String::new(),
         };
 
         let rad_retrieve_2 = RADRetrieve {
             kind: RADType::HttpGet,
             url: "https://openweathermap.org/data/2.5/weather?id=2950159&appid=b6907d289e10d714a6e88b30761fae22".to_string(),
             script: vec![0],
+            max_response_size: 0,
+            content_type: String::new(),
+            certificate_fingerprint: vec![],
+            timeout: 0,
+            body: vec![],
+            body_content_type: String::new(),
         };
 
         let rad_consensus = RADConsensus { script: vec![0] };
@@ -520,32 +887,32 @@ mod tests {
         };
         let data_request_output = Output::DataRequest(DataRequestOutput {
             backup_witnesses: 0,
-            commit_fee: 0,
+            commit_fee: Wit::default(),
             data_request: rad_request,
-            pkh: [0; 20],
-            reveal_fee: 0,
-            tally_fee: 0,
+            pkh: PublicKeyHash::default(),
+            reveal_fee: Wit::default(),
+            tally_fee: Wit::default(),
             time_lock: 0,
-            value: 0,
+            value: Wit::default(),
             witnesses: 0,
         });
         let commit_output = Output::Commit(CommitOutput {
             commitment: Hash::default(),
-            value: 0,
+            value: Wit::default(),
         });
         let reveal_output = Output::Reveal(RevealOutput {
-            pkh: [0; 20],
+            pkh: PublicKeyHash::default(),
             reveal: [0; 32].to_vec(),
-            value: 0,
+            value: Wit::default(),
         });
         let consensus_output = Output::Tally(TallyOutput {
-            pkh: [0; 20],
+            pkh: PublicKeyHash::default(),
             result: [0; 32].to_vec(),
-            value: 0,
+            value: Wit::default(),
         });
         let value_transfer_output = Output::ValueTransfer(ValueTransferOutput {
-            pkh: [0; 20],
-            value: 0,
+            pkh: PublicKeyHash::default(),
+            value: Wit::default(),
         });
         let inputs = vec![reveal_input, data_request_input, commit_input];
         let outputs = vec![
@@ -568,9 +935,7 @@ mod tests {
                 },
                 hash_merkle_root: Hash::SHA256([3; 32]),
             },
-            proof: LeadershipProof {
-                block_sig: KeyedSignature::default(),
-            },
+            proof: LeadershipProof::default(),
             txns,
         };
 
@@ -622,6 +987,40 @@ mod tests {
         assert_eq!(response, Some(expected));
     }
 
+    #[test]
+    fn submit_block_invalid_params() {
+        // What happens when the submitBlock method is called without a block?
+        let msg = r#"{"jsonrpc":"2.0","method":"submitBlock","params":[0],"id":1}"#;
+        let expected =
+            r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid params"#.to_string();
+        let subscriptions = Subscriptions::default();
+        let (transport_sender, _transport_receiver) = mpsc::channel(0);
+        let meta = Arc::new(Session::new(transport_sender));
+        let io = jsonrpc_io_handler(subscriptions);
+        let response = io.handle_request_sync(&msg, meta);
+        // Compare only the first N characters
+        let response =
+            response.map(|s| s.chars().take(expected.chars().count()).collect::<String>());
+        assert_eq!(response, Some(expected));
+    }
+
+    #[test]
+    fn set_access_list_invalid_params() {
+        // What happens when the setAccessList method is called with an invalid IP range?
+        let msg = r#"{"jsonrpc":"2.0","method":"setAccessList","params":{"whitelist":["10.0.0.0/33"]},"id":1}"#;
+        let expected =
+            r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid params"#.to_string();
+        let subscriptions = Subscriptions::default();
+        let (transport_sender, _transport_receiver) = mpsc::channel(0);
+        let meta = Arc::new(Session::new(transport_sender));
+        let io = jsonrpc_io_handler(subscriptions);
+        let response = io.handle_request_sync(&msg, meta);
+        // Compare only the first N characters
+        let response =
+            response.map(|s| s.chars().take(expected.chars().count()).collect::<String>());
+        assert_eq!(response, Some(expected));
+    }
+
     #[test]
     fn subscribe_invalid_method() {
         // Try to subscribe to a non-existent subscription?
@@ -703,8 +1102,8 @@ mod tests {
             transaction_id: Hash::default(),
         });
         let value_transfer_output = Output::ValueTransfer(ValueTransferOutput {
-            pkh: [0; 20],
-            value: 0,
+            pkh: PublicKeyHash::default(),
+            value: Wit::default(),
         });
 
         let rad_aggregate = RADAggregate { script: vec![0] };
@@ -713,12 +1112,24 @@ mod tests {
             kind: RADType::HttpGet,
             url: "https://openweathermap.org/data/2.5/weather?id=2950159&appid=b6907d289e10d714a6e88b30761fae22".to_string(),
             script: vec![0],
+            max_response_size: 0,
+            content_type: String::new(),
+            certificate_fingerprint: vec![],
+            timeout: 0,
+            body: vec![],
+            body_content_type: String::new(),
         };
 
         let rad_retrieve_2 = RADRetrieve {
             kind: RADType::HttpGet,
             url: "https://openweathermap.org/data/2.5/weather?id=2950159&appid=b6907d289e10d714a6e88b30761fae22".to_string(),
             script: vec![0],
+            max_response_size: 0,
+            content_type: String::new(),
+            certificate_fingerprint: vec![],
+            timeout: 0,
+            body: vec![],
+            body_content_type: String::new(),
         };
 
         let rad_consensus = RADConsensus { script: vec![0] };
@@ -742,28 +1153,28 @@ mod tests {
         };
         let data_request_output = Output::DataRequest(DataRequestOutput {
             backup_witnesses: 0,
-            commit_fee: 0,
+            commit_fee: Wit::default(),
             data_request: rad_request,
-            pkh: [0; 20],
-            reveal_fee: 0,
-            tally_fee: 0,
+            pkh: PublicKeyHash::default(),
+            reveal_fee: Wit::default(),
+            tally_fee: Wit::default(),
             time_lock: 0,
-            value: 0,
+            value: Wit::default(),
             witnesses: 0,
         });
         let commit_output = Output::Commit(CommitOutput {
             commitment: Hash::default(),
-            value: 0,
+            value: Wit::default(),
         });
         let reveal_output = Output::Reveal(RevealOutput {
-            pkh: [0; 20],
+            pkh: PublicKeyHash::default(),
             reveal: [0; 32].to_vec(),
-            value: 0,
+            value: Wit::default(),
         });
         let consensus_output = Output::Tally(TallyOutput {
-            pkh: [0; 20],
+            pkh: PublicKeyHash::default(),
             result: [0; 32].to_vec(),
-            value: 0,
+            value: Wit::default(),
         });
 
         let inputs = vec![commit_input, data_request_input, reveal_input];
@@ -787,14 +1198,12 @@ mod tests {
                 },
                 hash_merkle_root: Hash::SHA256([3; 32]),
             },
-            proof: LeadershipProof {
-                block_sig: KeyedSignature::default(),
-            },
+            proof: LeadershipProof::default(),
             txns,
         };
         let inv_elem = InventoryItem::Block(block);
         let s = serde_json::to_string(&inv_elem);
-        let expected = r#"{"block":{"block_header":{"version":1,"beacon":{"checkpoint":2,"hash_prev_block":{"SHA256":[4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4]}},"hash_merkle_root":{"SHA256":[3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3]}},"proof":{"block_sig":{"signature":{"Secp256k1":{"der":[]}},"public_key":{"compressed":0,"bytes":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0]}}},"txns":[{"body":{"version":0,"inputs":[{"Commit":{"transaction_id":{"SHA256":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0]},"output_index":0,"nonce":0}},{"DataRequest":{"transaction_id":{"SHA256":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0]},"output_index":0,"poe":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0]}},{"Reveal":{"transaction_id":{"SHA256":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0]},"output_index":0}}],"outputs":[{"ValueTransfer":{"pkh":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0],"value":0}},{"DataRequest":{"pkh":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0],"data_request":{"not_before":0,"retrieve":[{"kind":"HTTP-GET","url":"https://openweathermap.org/data/2.5/weather?id=2950159&appid=b6907d289e10d714a6e88b30761fae22","script":[0]},{"kind":"HTTP-GET","url":"https://openweathermap.org/data/2.5/weather?id=2950159&appid=b6907d289e10d714a6e88b30761fae22","script":[0]}],"aggregate":{"script":[0]},"consensus":{"script":[0]},"deliver":[{"kind":"HTTP-GET","url":"https://hooks.zapier.com/hooks/catch/3860543/l2awcd/"},{"kind":"HTTP-GET","url":"https://hooks.zapier.com/hooks/catch/3860543/l1awcw/"}]},"value":0,"witnesses":0,"backup_witnesses":0,"commit_fee":0,"reveal_fee":0,"tally_fee":0,"time_lock":0}},{"Commit":{"commitment":{"SHA256":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0]},"value":0}},{"Reveal":{"reveal":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0],"pkh":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0],"value":0}},{"Tally":{"result":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0],"pkh":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0],"value":0}}],"hash":null},"signatures":[{"signature":{"Secp256k1":{"der":[]}},"public_key":{"compressed":0,"bytes":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0]}}]}]}}"#;
+        let expected = r#"{"block":{"block_header":{"version":1,"beacon":{"checkpoint":2,"hash_prev_block":{"SHA256":[4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4,4]}},"hash_merkle_root":{"SHA256":[3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3,3]}},"proof":{"block_sig":{"signature":{"Secp256k1":{"der":[]}},"public_key":{"compressed":0,"bytes":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0]}},"proof":{"proof":[],"public_key":{"compressed":0,"bytes":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0]}}},"txns":[{"body":{"version":0,"inputs":[{"Commit":{"transaction_id":{"SHA256":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0]},"output_index":0,"nonce":0}},{"DataRequest":{"transaction_id":{"SHA256":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0]},"output_index":0,"poe":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0]}},{"Reveal":{"transaction_id":{"SHA256":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0]},"output_index":0}}],"outputs":[{"ValueTransfer":{"pkh":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0],"value":0}},{"DataRequest":{"pkh":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0],"data_request":{"not_before":0,"retrieve":[{"kind":"HTTP-GET","url":"https://openweathermap.org/data/2.5/weather?id=2950159&appid=b6907d289e10d714a6e88b30761fae22","script":[0],"max_response_size":0,"content_type":"","certificate_fingerprint":[],"timeout":0,"body":[],"body_content_type":""},{"kind":"HTTP-GET","url":"https://openweathermap.org/data/2.5/weather?id=2950159&appid=b6907d289e10d714a6e88b30761fae22","script":[0],"max_response_size":0,"content_type":"","certificate_fingerprint":[],"timeout":0,"body":[],"body_content_type":""}],"aggregate":{"script":[0]},"consensus":{"script":[0]},"deliver":[{"kind":"HTTP-GET","url":"https://hooks.zapier.com/hooks/catch/3860543/l2awcd/"},{"kind":"HTTP-GET","url":"https://hooks.zapier.com/hooks/catch/3860543/l1awcw/"}]},"value":0,"witnesses":0,"backup_witnesses":0,"commit_fee":0,"reveal_fee":0,"tally_fee":0,"time_lock":0}},{"Commit":{"commitment":{"SHA256":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0]},"value":0}},{"Reveal":{"reveal":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0],"pkh":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0],"value":0}},{"Tally":{"result":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0],"pkh":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0],"value":0}}],"hash":null},"signatures":[{"signature":{"Secp256k1":{"der":[]}},"public_key":{"compressed":0,"bytes":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0]}}]}]}}"#;
         assert_eq!(s.unwrap(), expected);
     }
 
@@ -807,12 +1216,24 @@ mod tests {
             kind: RADType::HttpGet,
             url: "https://openweathermap.org/data/2.5/weather?id=2950159&appid=b6907d289e10d714a6e88b30761fae22".to_string(),
             script: vec![0],
+            max_response_size: 0,
+            content_type: String::new(),
+            certificate_fingerprint: vec![],
+            timeout: 0,
+            body: vec![],
+            body_content_type: String::new(),
         };
 
         let rad_retrieve_2 = RADRetrieve {
             kind: RADType::HttpGet,
             url: "https://openweathermap.org/data/2.5/weather?id=2950159&appid=b6907d289e10d714a6e88b30761fae22".to_string(),
             script: vec![0],
+            max_response_size: 0,
+            content_type: String::new(),
+            certificate_fingerprint: vec![],
+            timeout: 0,
+            body: vec![],
+            body_content_type: String::new(),
         };
 
         let rad_consensus = RADConsensus { script: vec![0] };
@@ -839,7 +1260,7 @@ mod tests {
 
         let inv_elem = InventoryItem::Transaction(transaction);
         let s = serde_json::to_string(&inv_elem);
-        let expected = r#"{"transaction":{"body":{"version":0,"inputs":[{"ValueTransfer":{"transaction_id":{"SHA256":[9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9]},"output_index":0}}],"outputs":[{"DataRequest":{"pkh":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0],"data_request":{"not_before":0,"retrieve":[{"kind":"HTTP-GET","url":"https://openweathermap.org/data/2.5/weather?id=2950159&appid=b6907d289e10d714a6e88b30761fae22","script":[0]},{"kind":"HTTP-GET","url":"https://openweathermap.org/data/2.5/weather?id=2950159&appid=b6907d289e10d714a6e88b30761fae22","script":[0]}],"aggregate":{"script":[0]},"consensus":{"script":[0]},"deliver":[{"kind":"HTTP-GET","url":"https://hooks.zapier.com/hooks/catch/3860543/l2awcd/"},{"kind":"HTTP-GET","url":"https://hooks.zapier.com/hooks/catch/3860543/l1awcw/"}]},"value":0,"witnesses":0,"backup_witnesses":0,"commit_fee":0,"reveal_fee":0,"tally_fee":0,"time_lock":0}}],"hash":null},"signatures":[{"signature":{"Secp256k1":{"der":[]}},"public_key":{"compressed":0,"bytes":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0]}}]}}"#;
+        let expected = r#"{"transaction":{"body":{"version":0,"inputs":[{"ValueTransfer":{"transaction_id":{"SHA256":[9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9,9]},"output_index":0}}],"outputs":[{"DataRequest":{"pkh":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0],"data_request":{"not_before":0,"retrieve":[{"kind":"HTTP-GET","url":"https://openweathermap.org/data/2.5/weather?id=2950159&appid=b6907d289e10d714a6e88b30761fae22","script":[0],"max_response_size":0,"content_type":"","certificate_fingerprint":[],"timeout":0,"body":[],"body_content_type":""},{"kind":"HTTP-GET","url":"https://openweathermap.org/data/2.5/weather?id=2950159&appid=b6907d289e10d714a6e88b30761fae22","script":[0],"max_response_size":0,"content_type":"","certificate_fingerprint":[],"timeout":0,"body":[],"body_content_type":""}],"aggregate":{"script":[0]},"consensus":{"script":[0]},"deliver":[{"kind":"HTTP-GET","url":"https://hooks.zapier.com/hooks/catch/3860543/l2awcd/"},{"kind":"HTTP-GET","url":"https://hooks.zapier.com/hooks/catch/3860543/l1awcw/"}]},"value":0,"witnesses":0,"backup_witnesses":0,"commit_fee":0,"reveal_fee":0,"tally_fee":0,"time_lock":0}}],"hash":null},"signatures":[{"signature":{"Secp256k1":{"der":[]}},"public_key":{"compressed":0,"bytes":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0]}}]}}"#;
         assert_eq!(s.unwrap(), expected);
     }
 
@@ -858,13 +1279,13 @@ mod tests {
 
         let data_request_output = Output::DataRequest(DataRequestOutput {
             backup_witnesses: 0,
-            commit_fee: 0,
+            commit_fee: Wit::default(),
             data_request,
-            pkh: [0; 20],
-            reveal_fee: 0,
-            tally_fee: 0,
+            pkh: PublicKeyHash::default(),
+            reveal_fee: Wit::default(),
+            tally_fee: Wit::default(),
             time_lock: 0,
-            value: 0,
+            value: Wit::default(),
             witnesses: 0,
         });
 
@@ -0,0 +1,17 @@
+use actix::{Actor, Context};
+use log::debug;
+
+use super::LanDiscoveryManager;
+
+/// Make actor from LanDiscoveryManager
+impl Actor for LanDiscoveryManager {
+    /// Every actor has to provide execution `Context` in which it can run.
+    type Context = Context<Self>;
+
+    /// Method to be executed when the actor is started
+    fn started(&mut self, ctx: &mut Self::Context) {
+        debug!("LAN Discovery Manager actor has been started!");
+
+        self.start_discovery(ctx);
+    }
+}
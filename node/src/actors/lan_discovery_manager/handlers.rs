@@ -0,0 +1,49 @@
+use actix::{Handler, System};
+use log::{debug, info};
+
+use witnet_p2p::mdns;
+
+use super::{LanDiscoveryManager, SERVICE_NAME};
+use crate::actors::{
+    messages::{AddPeers, MdnsPacket},
+    peers_manager::PeersManager,
+};
+
+/// Handler for MdnsPacket messages (received from the mDNS multicast group)
+impl Handler<MdnsPacket> for LanDiscoveryManager {
+    /// Response for message, which is defined by `ResponseType` trait
+    type Result = ();
+
+    /// Method to handle the MdnsPacket message
+    fn handle(&mut self, msg: MdnsPacket, _ctx: &mut Self::Context) {
+        if mdns::is_query(&msg.data, SERVICE_NAME) {
+            self.announce();
+            return;
+        }
+
+        let announcement = match mdns::parse_announcement(&msg.data, SERVICE_NAME) {
+            Some(announcement) => announcement,
+            None => return,
+        };
+        if self.is_own_instance(&announcement.instance) {
+            return;
+        }
+
+        let address = announcement.peer_address(msg.source);
+        if !mdns::is_local_address(&address.ip()) {
+            debug!(
+                "Ignoring peer {} announced by {}: not a local network address",
+                address, msg.source
+            );
+            return;
+        }
+
+        info!("Discovered peer {} in the local network", address);
+        System::current()
+            .registry()
+            .get::<PeersManager>()
+            .do_send(AddPeers {
+                addresses: vec![address],
+            });
+    }
+}
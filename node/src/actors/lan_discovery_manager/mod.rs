@@ -0,0 +1,161 @@
+use std::{
+    io,
+    net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket},
+    time::Duration,
+};
+
+use actix::prelude::*;
+use futures::Stream;
+use log;
+use rand::{thread_rng, Rng};
+use tokio::{
+    codec::BytesCodec,
+    net::{UdpFramed, UdpSocket as TokioUdpSocket},
+    reactor::Handle,
+};
+
+use crate::actors::messages::MdnsPacket;
+use crate::config_mngr;
+
+use witnet_data_structures::chain::Environment;
+use witnet_p2p::mdns;
+
+mod actor;
+mod handlers;
+
+/// Name of the DNS-SD service announced by Witnet nodes
+pub const SERVICE_NAME: &str = "_witnet._tcp.local";
+
+/// LAN discovery manager actor: finds other nodes of the local network using mDNS.
+///
+/// The node announces its P2P server as an instance of the `_witnet._tcp.local` service and
+/// periodically queries for other instances, which are sent to the `PeersManager`. It is only
+/// enabled with the `connections.lan_discovery` setting in the development environment.
+#[derive(Default)]
+pub struct LanDiscoveryManager {
+    /// Socket used to send mDNS packets, `None` while LAN discovery is disabled
+    socket: Option<UdpSocket>,
+    /// Name of the service instance announced by this node
+    instance: String,
+    /// Address of the P2P server announced to other nodes, if reachable from the network
+    server_addr: Option<SocketAddr>,
+}
+
+/// Required trait for being able to retrieve LAN discovery manager address from system registry
+impl actix::Supervised for LanDiscoveryManager {}
+
+/// Required trait for being able to retrieve LAN discovery manager address from system registry
+impl SystemService for LanDiscoveryManager {}
+
+/// Auxiliary methods for LanDiscoveryManager actor
+impl LanDiscoveryManager {
+    fn start_discovery(&mut self, ctx: &mut <Self as Actor>::Context) {
+        config_mngr::get()
+            .into_actor(self)
+            .and_then(|config, act, ctx| {
+                if !config.connections.lan_discovery {
+                    log::debug!("LAN discovery is disabled");
+                    return fut::ok(());
+                }
+                if config.connections.proxy.is_some() {
+                    log::info!("LAN discovery is disabled because the node uses a proxy");
+                    return fut::ok(());
+                }
+                if config.environment != Environment::Development {
+                    log::warn!(
+                        "LAN discovery is only available in the development environment, \
+                         ignoring it in {:?}",
+                        config.environment
+                    );
+                    return fut::ok(());
+                }
+
+                let server_addr = config.connections.server_addr;
+                if server_addr.ip().is_loopback() {
+                    log::warn!(
+                        "P2P server address {} is not reachable from the local network, \
+                         it will not be announced",
+                        server_addr
+                    );
+                } else {
+                    act.server_addr = Some(server_addr);
+                }
+
+                if let Err(err) = act.bind(ctx) {
+                    log::error!("LAN discovery failed to start: {}", err);
+                    return fut::ok(());
+                }
+                act.instance = format!("witnet-{:016x}", thread_rng().gen::<u64>());
+                log::info!("LAN discovery has been started as {}", act.instance);
+
+                act.announce();
+                act.query();
+                act.discovery_peers(ctx, config.connections.discovery_peers_period);
+
+                fut::ok(())
+            })
+            .map_err(|err, _, _| log::error!("LAN discovery failed to start: {}", err))
+            .wait(ctx);
+    }
+
+    /// Method to join the mDNS multicast group and receive its packets as `MdnsPacket` messages
+    fn bind(&mut self, ctx: &mut <Self as Actor>::Context) -> io::Result<()> {
+        // Other mDNS responders of the host may be bound to the same port
+        let socket = net2::UdpBuilder::new_v4()?
+            .reuse_address(true)?
+            .bind((Ipv4Addr::UNSPECIFIED, mdns::MDNS_PORT))?;
+        socket.join_multicast_v4(&mdns::MDNS_IPV4, &Ipv4Addr::UNSPECIFIED)?;
+        // Nodes running in the same host must see each other
+        socket.set_multicast_loop_v4(true)?;
+
+        let receiver = TokioUdpSocket::from_std(socket.try_clone()?, &Handle::default())?;
+        ctx.add_message_stream(
+            UdpFramed::new(receiver, BytesCodec::new())
+                .map_err(|err| log::error!("Error receiving mDNS packet: {}", err))
+                .map(|(data, source)| MdnsPacket { data, source }),
+        );
+        self.socket = Some(socket);
+
+        Ok(())
+    }
+
+    /// Method to periodically query for other nodes of the local network
+    fn discovery_peers(&self, ctx: &mut Context<Self>, discovery_peers_period: Duration) {
+        ctx.run_later(discovery_peers_period, move |act, ctx| {
+            act.query();
+
+            act.discovery_peers(ctx, discovery_peers_period);
+        });
+    }
+
+    /// Method to announce the P2P server of this node
+    fn announce(&self) {
+        if let Some(server_addr) = self.server_addr {
+            self.send(&mdns::build_announcement(
+                SERVICE_NAME,
+                &self.instance,
+                server_addr,
+            ));
+        }
+    }
+
+    /// Method to query for the P2P servers of other nodes
+    fn query(&self) {
+        self.send(&mdns::build_query(SERVICE_NAME));
+    }
+
+    /// Method to send a packet to the mDNS multicast group
+    fn send(&self, packet: &[u8]) {
+        if let Some(socket) = &self.socket {
+            let multicast_addr = SocketAddr::new(IpAddr::V4(mdns::MDNS_IPV4), mdns::MDNS_PORT);
+            if let Err(err) = socket.send_to(packet, multicast_addr) {
+                log::warn!("Error sending mDNS packet: {}", err);
+            }
+        }
+    }
+
+    /// Returns `true` if the announced instance is the one of this node
+    fn is_own_instance(&self, instance: &str) -> bool {
+        instance.eq_ignore_ascii_case(&format!("{}.{}", self.instance, SERVICE_NAME))
+    }
+}
@@ -7,17 +7,26 @@ use std::{
 };
 
 use actix::{actors::resolver::ResolverError, dev::ToEnvelope, Actor, Addr, Handler, Message};
+use bytes::BytesMut;
+use serde::{Deserialize, Serialize};
 use tokio::net::TcpStream;
 
+use witnet_crypto::signature_cache::SignatureCacheStats;
 use witnet_data_structures::chain::{
-    Block, CheckpointBeacon, Epoch, Hash, InventoryEntry, InventoryItem, RADConsensus, RADRequest,
-    Transaction,
+    Block, BlockHeader, CheckpointBeacon, CheckpointStatus, DataRequestReport, DataRequestState,
+    Epoch, Hash, InventoryEntry, InventoryItem, OutputPointer, PoolTransactionInfo, PublicKeyHash,
+    RADConsensus, RADRequest, RADRetrieve, Transaction,
 };
-use witnet_p2p::sessions::{SessionStatus, SessionType};
-use witnet_rad::error::RadError;
+use witnet_p2p::{
+    metrics::{Direction, NetworkTotals},
+    sessions::{access::AccessList, latency::LatencyStats, SessionStatus, SessionType},
+    user_agents::UserAgentMatch,
+};
+use witnet_rad::{error::RadError, report::RadonReport};
+use witnet_util::ntp::NtpSample;
 
 use super::{
-    chain_manager::{ChainManagerError, MAX_BLOCKS_SYNC},
+    chain_manager::{BlockTemplate, ChainManagerError, StateMachine, MAX_BLOCKS_SYNC},
     epoch_manager::{
         AllEpochSubscription, EpochManagerError, SendableNotification, SingleEpochSubscription,
     },
@@ -25,6 +34,20 @@ use super::{
     session::Session,
 };
 
+////////////////////////////////////////////////////////////////////////////////////////
+// MESSAGES FROM ALERTS MANAGER
+////////////////////////////////////////////////////////////////////////////////////////
+
+/// Message with the difference between the local clock and the clock of a peer, as announced in
+/// its `Version` message
+#[derive(Message)]
+pub struct PeerClockOffset {
+    /// Socket address of the peer
+    pub address: SocketAddr,
+    /// Local timestamp minus the timestamp of the peer, in seconds
+    pub offset: i64,
+}
+
 ////////////////////////////////////////////////////////////////////////////////////////
 // MESSAGES FROM CHAIN MANAGER
 ////////////////////////////////////////////////////////////////////////////////////////
@@ -50,6 +73,16 @@ impl Message for AddBlocks {
     type Result = SessionUnitResult;
 }
 
+/// Add the headers requested during a synchronization
+pub struct AddHeaders {
+    /// Headers, in chain order
+    pub headers: Vec<BlockHeader>,
+}
+
+impl Message for AddHeaders {
+    type Result = SessionUnitResult;
+}
+
 /// Add a new candidate
 pub struct AddCandidates {
     /// Candidates
@@ -126,6 +159,58 @@ impl Message for GetBlocksEpochRange {
     type Result = Result<Vec<(Epoch, InventoryEntry)>, ChainManagerError>;
 }
 
+/// Message to obtain the hashes of the consolidated blocks following the most recent block
+/// known to a peer, given the locator hashes of its chain
+pub struct LocateBlocks {
+    /// Hashes of the chain of the peer, from newest to oldest
+    pub locator_hashes: Vec<Hash>,
+    /// Maximum number of hashes
+    pub limit: usize,
+}
+
+impl Message for LocateBlocks {
+    type Result = Result<Vec<Hash>, ChainManagerError>;
+}
+
+/// Message to select the items announced by a peer which are worth requesting, leaving out the
+/// transactions already seen
+pub struct FilterAnnouncedItems {
+    /// Items announced by the peer
+    pub items: Vec<InventoryEntry>,
+}
+
+impl Message for FilterAnnouncedItems {
+    type Result = Vec<InventoryEntry>;
+}
+
+/// Message to obtain the transactions of the transactions pool with the given hashes. The
+/// hashes not found in the pool are skipped
+pub struct GetMempoolTransactions {
+    /// Hashes of the transactions
+    pub hashes: Vec<Hash>,
+}
+
+impl Message for GetMempoolTransactions {
+    type Result = Vec<Transaction>;
+}
+
+/// Message to obtain a template of the block to be mined in the current epoch
+pub struct GetBlockTemplate;
+
+impl Message for GetBlockTemplate {
+    type Result = Result<BlockTemplate, failure::Error>;
+}
+
+/// Submit a block built by an external miner
+pub struct SubmitBlock {
+    /// Block
+    pub block: Block,
+}
+
+impl Message for SubmitBlock {
+    type Result = Result<(), failure::Error>;
+}
+
 /// A list of peers and their respective last beacon, used to establish consensus
 pub struct PeersBeacons {
     /// A list of peers and their respective last beacon
@@ -137,6 +222,17 @@ impl Message for PeersBeacons {
     type Result = Result<Vec<SocketAddr>, ()>;
 }
 
+/// Message to compare trusted checkpoints with the consolidated blocks
+pub struct CheckCheckpoints {
+    /// Checkpoint beacons with verified signatures
+    pub checkpoints: Vec<CheckpointBeacon>,
+}
+
+impl Message for CheckCheckpoints {
+    /// Result: status of every checkpoint
+    type Result = Vec<(CheckpointBeacon, CheckpointStatus)>;
+}
+
 ////////////////////////////////////////////////////////////////////////////////////////
 // MESSAGES FROM CONNECTIONS MANAGER
 ////////////////////////////////////////////////////////////////////////////////////////
@@ -162,9 +258,75 @@ pub struct OutboundTcpConnect {
     pub address: SocketAddr,
 }
 
+/// Actor message to request a feeler connection to a peer: a short-lived connection that is closed
+/// as soon as it is established, just to check whether the peer is reachable
+#[derive(Message)]
+pub struct FeelerConnect {
+    /// Address of the peer
+    pub address: SocketAddr,
+}
+
+/// Actor message to request an outbound TCP connection to a host, trying all the addresses it
+/// resolves to with Happy Eyeballs
+#[derive(Message)]
+pub struct OutboundHostConnect {
+    /// Host of the outbound connection, as `hostname:port`
+    pub host: String,
+}
+
+/// Message to check the user agent announced by a peer against the configured patterns
+pub struct CheckUserAgent {
+    /// Socket address of the peer
+    pub address: SocketAddr,
+    /// User agent announced by the peer in its `Version` message
+    pub user_agent: String,
+}
+
+impl Message for CheckUserAgent {
+    type Result = UserAgentMatch;
+}
+
+/// Message to account a protocol message exchanged with a peer
+#[derive(Message)]
+pub struct RecordProtocolMessage {
+    /// Whether the message was received or sent
+    pub direction: Direction,
+    /// Name of the command of the message
+    pub command: &'static str,
+    /// Size of the encoded message in bytes
+    pub size: usize,
+}
+
+/// Message to account a handshake rejected because of the protocol version of the peer
+#[derive(Message)]
+pub struct RecordRejectedVersion {
+    /// Protocol version announced by the peer
+    pub version: u32,
+}
+
+/// Message to get the totals of the protocol messages exchanged with all the peers
+pub struct GetNetworkTotals;
+
+impl Message for GetNetworkTotals {
+    type Result = NetworkTotals;
+}
+
 /// Returned type by the Resolver actor for the ConnectAddr message
 pub type ResolverResult = Result<TcpStream, ResolverError>;
 
+////////////////////////////////////////////////////////////////////////////////////////
+// MESSAGES FROM LAN DISCOVERY MANAGER
+////////////////////////////////////////////////////////////////////////////////////////
+
+/// Actor message that holds a packet received from the mDNS multicast group
+#[derive(Message)]
+pub struct MdnsPacket {
+    /// Contents of the packet
+    pub data: BytesMut,
+    /// Address of the sender
+    pub source: SocketAddr,
+}
+
 ////////////////////////////////////////////////////////////////////////////////////////
 // MESSAGES FROM EPOCH MANAGER
 ////////////////////////////////////////////////////////////////////////////////////////
@@ -179,6 +341,13 @@ impl Message for GetEpoch {
     type Result = EpochResult<Epoch>;
 }
 
+/// Update the offset of the clock with the samples measured with the NTP servers
+#[derive(Message)]
+pub struct UpdateClockOffset {
+    /// Samples of the servers which answered
+    pub samples: Vec<NtpSample>,
+}
+
 /// Subscribe
 pub struct Subscribe;
 
@@ -274,6 +443,40 @@ impl Message for GetItem {
     type Result = Result<InventoryItem, InventoryManagerError>;
 }
 
+/// Ask for the header of a block identified by its hash, even if its body has been pruned
+pub struct GetBlockHeader {
+    /// Block hash
+    pub hash: Hash,
+}
+
+impl Message for GetBlockHeader {
+    type Result = Result<BlockHeader, InventoryManagerError>;
+}
+
+/// Delete the bodies of the blocks identified by their hashes, keeping their headers
+#[derive(Message)]
+pub struct PruneBlocks {
+    /// Hashes of the blocks
+    pub hashes: Vec<Hash>,
+}
+
+/// Keep a block whose parent is not known yet, requesting its missing ancestor
+#[derive(Message)]
+pub struct AddOrphanBlock {
+    /// Orphan block
+    pub block: Block,
+}
+
+/// Take the orphan blocks whose parent has just been accepted
+pub struct TakeOrphanBlocks {
+    /// Hash of the parent
+    pub parent: Hash,
+}
+
+impl Message for TakeOrphanBlocks {
+    type Result = Vec<Block>;
+}
+
 ////////////////////////////////////////////////////////////////////////////////////////
 // MESSAGES FROM PEERS MANAGER
 ////////////////////////////////////////////////////////////////////////////////////////
@@ -293,6 +496,28 @@ impl Message for AddPeers {
     type Result = PeersSocketAddrsResult;
 }
 
+/// Message to move one or more peer addresses to the tried bucket, after establishing a
+/// connection to them
+pub struct AddTriedPeers {
+    /// Addresses of the peers
+    pub addresses: Vec<SocketAddr>,
+}
+
+impl Message for AddTriedPeers {
+    type Result = PeersSocketAddrsResult;
+}
+
+/// Message to add the peer addresses received in a `Peers` message from another node. Addresses
+/// seen recently are skipped, and the processing time of every message is capped.
+pub struct AddGossipedPeers {
+    /// Addresses received from the peer
+    pub addresses: Vec<SocketAddr>,
+}
+
+impl Message for AddGossipedPeers {
+    type Result = PeersSocketAddrsResult;
+}
+
 /// Message to remove one or more peer addresses from the list
 pub struct RemovePeers {
     /// Address of the peer
@@ -303,6 +528,14 @@ impl Message for RemovePeers {
     type Result = PeersSocketAddrsResult;
 }
 
+/// Message to record a failed feeler connection to a peer of the new bucket. Peers that fail too
+/// many times in a row are removed from the list
+#[derive(Message)]
+pub struct FeelerFailed {
+    /// Address of the peer
+    pub address: SocketAddr,
+}
+
 /// Message to get a (random) peer address from the list
 pub struct GetRandomPeer;
 
@@ -310,7 +543,8 @@ impl Message for GetRandomPeer {
     type Result = PeersSocketAddrResult;
 }
 
-/// Message to get all the peer addresses from the list
+/// Message to get a random sample of the peer addresses from the list, to be sent in a `Peers`
+/// message
 pub struct RequestPeers;
 
 impl Message for RequestPeers {
@@ -329,6 +563,13 @@ pub struct ResolveRA {
     pub rad_request: RADRequest,
 }
 
+/// Message for retrieving a single source of a data request in a `RadWorker`.
+#[derive(Debug)]
+pub struct RunRetrieval {
+    /// RAD retrieval to be executed
+    pub retrieve: RADRetrieve,
+}
+
 /// Message for running the consensus step of a data request.
 #[derive(Debug)]
 pub struct RunConsensus {
@@ -342,6 +583,10 @@ impl Message for ResolveRA {
     type Result = Result<Vec<u8>, RadError>;
 }
 
+impl Message for RunRetrieval {
+    type Result = RadonReport;
+}
+
 impl Message for RunConsensus {
     type Result = Result<Vec<u8>, RadError>;
 }
@@ -390,6 +635,32 @@ impl fmt::Display for SendInventoryItem {
     }
 }
 
+/// Message to request inventory items to a peer, which has to deliver them before a deadline
+#[derive(Clone, Debug, Message)]
+pub struct SendInventoryRequest {
+    /// Inventory entries
+    pub items: Vec<InventoryEntry>,
+}
+
+impl fmt::Display for SendInventoryRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SendInventoryRequest")
+    }
+}
+
+/// Message to request the headers of the blocks following the locator hashes to a peer
+#[derive(Clone, Debug, Message)]
+pub struct SendGetBlocks {
+    /// Hashes of our chain, from newest to oldest
+    pub locator_hashes: Vec<Hash>,
+}
+
+impl fmt::Display for SendGetBlocks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SendGetBlocks")
+    }
+}
+
 /// Message to send beacon through the network
 #[derive(Clone, Debug, Message)]
 pub struct SendLastBeacon {
@@ -414,6 +685,62 @@ pub struct CloseSession;
 /// Message result of unit
 pub type SessionsUnitResult = Result<(), failure::Error>;
 
+/// Message to obtain the number of consolidated sessions, both inbound and outbound
+pub struct GetConsolidatedSessionsCount;
+
+impl Message for GetConsolidatedSessionsCount {
+    type Result = usize;
+}
+
+/// Number of consolidated sessions of every kind
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SessionsCount {
+    /// Consolidated inbound sessions
+    pub inbound: usize,
+    /// Consolidated outbound sessions
+    pub outbound: usize,
+    /// Outbound sessions whose peers agree with the consensus beacon
+    pub outbound_consensus: usize,
+}
+
+/// Message to obtain the number of consolidated sessions of every kind
+pub struct GetSessionsCount;
+
+impl Message for GetSessionsCount {
+    type Result = SessionsCount;
+}
+
+/// Latency of a peer with a consolidated session
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PeerLatency {
+    /// Socket address which identifies the peer
+    pub address: SocketAddr,
+    /// Latency of the peer, in milliseconds
+    pub latency: LatencyStats,
+}
+
+/// Message to obtain the latency of the peers with a consolidated session
+pub struct GetPeersLatency;
+
+impl Message for GetPeersLatency {
+    type Result = Vec<PeerLatency>;
+}
+
+/// Message to obtain the IP ranges the sessions are allowed or forbidden to connect to
+pub struct GetAccessList;
+
+impl Message for GetAccessList {
+    type Result = AccessList;
+}
+
+/// Message to replace the access list of the sessions. The sessions with peers that are no
+/// longer allowed are closed
+#[derive(Clone, Debug, Message)]
+pub struct SetAccessList {
+    /// New access list
+    pub access_list: AccessList,
+}
+
 /// Message indicating a new session needs to be created
 pub struct Create {
     /// TCP stream
@@ -421,6 +748,10 @@ pub struct Create {
 
     /// Session type
     pub session_type: SessionType,
+
+    /// Address of the peer if the stream is connected through a proxy, whose address is the one
+    /// of the stream
+    pub remote_addr: Option<SocketAddr>,
 }
 
 impl Message for Create {
@@ -520,6 +851,186 @@ pub struct PeerBeacon {
     pub beacon: CheckpointBeacon,
 }
 
+/// Message indicating that a peer did not deliver the inventory items requested to it in time
+#[derive(Clone, Debug, Message)]
+pub struct ReportSlowPeer {
+    /// Socket address which identifies the peer
+    pub address: SocketAddr,
+}
+
+/// Message indicating that a peer sent a message over the rate limits
+#[derive(Clone, Debug, Message)]
+pub struct ReportFloodingPeer {
+    /// Socket address which identifies the peer
+    pub address: SocketAddr,
+}
+
+/// Message indicating that a peer sent a malformed block
+#[derive(Clone, Debug, Message)]
+pub struct ReportInvalidBlock {
+    /// Socket address which identifies the peer
+    pub address: SocketAddr,
+}
+
+/// Message indicating that a peer sent a message with more items than the protocol allows
+#[derive(Clone, Debug, Message)]
+pub struct ReportOversizedMessage {
+    /// Socket address which identifies the peer
+    pub address: SocketAddr,
+}
+
+/// Message indicating that a peer announced a user agent matching a preferred pattern
+#[derive(Clone, Debug, Message)]
+pub struct ReportPreferredPeer {
+    /// Socket address which identifies the peer
+    pub address: SocketAddr,
+    /// Address the peer listens on, as announced in its `Version` message
+    pub listening_address: Option<SocketAddr>,
+}
+
+/// Message indicating the latency measured for a peer from its pongs
+#[derive(Clone, Debug, Message)]
+pub struct ReportPeerLatency {
+    /// Socket address which identifies the peer
+    pub address: SocketAddr,
+    /// Latency of the peer
+    pub latency: LatencyStats,
+}
+
+/// Message indicating the external address of the server mapped in the NAT gateway, which is
+/// advertised to peers instead of the server address
+#[derive(Clone, Debug, Message)]
+pub struct SetExternalAddress {
+    /// External address, `None` if the port mapping was lost
+    pub address: Option<SocketAddr>,
+}
+
+/// Approximate memory usage of a pool or cache
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MemoryInfo {
+    /// Name of the pool or cache
+    pub component: String,
+    /// Approximate number of bytes in use
+    pub usage: usize,
+    /// Memory cap in bytes, `0` if the component is not limited
+    pub cap: usize,
+}
+
+/// Message to obtain the memory usage of the pools and caches of an actor
+pub struct GetMemoryInfo;
+
+impl Message for GetMemoryInfo {
+    type Result = Vec<MemoryInfo>;
+}
+
+/// Contents of the pool of unconfirmed transactions
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MempoolInfo {
+    /// Sum of the weights of the transactions
+    pub weight: u64,
+    /// Weight cap, `0` if the pool is not limited
+    pub max_weight: u64,
+    /// Transactions in the order they are included in blocks, from the highest fee per unit of
+    /// weight to the lowest
+    pub transactions: Vec<PoolTransactionInfo>,
+}
+
+/// Message to obtain the contents of the pool of unconfirmed transactions
+pub struct GetMempool;
+
+impl Message for GetMempool {
+    type Result = MempoolInfo;
+}
+
+/// Message to obtain the usage counters of the cache of signature verifications
+pub struct GetSignatureCacheStats;
+
+impl Message for GetSignatureCacheStats {
+    type Result = SignatureCacheStats;
+}
+
+/// Reputation of an identity
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ReputationInfo {
+    /// Reputation of the identity
+    pub reputation: u64,
+    /// Whether the identity has committed to a data request recently
+    pub active: bool,
+    /// Sum of the reputation of the active identities
+    pub total_active_reputation: u64,
+    /// Number of active identities
+    pub active_identities: usize,
+}
+
+/// Message to obtain the reputation of an identity
+pub struct GetReputation {
+    /// Public key hash of the identity
+    pub pkh: PublicKeyHash,
+}
+
+impl Message for GetReputation {
+    type Result = ReputationInfo;
+}
+
+/// State of a data request
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum DataRequestStatus {
+    /// The data request is in the pool of active data requests, with its current stage and its
+    /// commits and reveals so far
+    Active(DataRequestState),
+    /// The data request has been resolved, with its commits, reveals and tally
+    Resolved(DataRequestReport),
+}
+
+/// Message to obtain the state of the data request created by the transaction with the given
+/// hash
+pub struct GetDataRequest {
+    /// Hash of the data request transaction
+    pub hash: Hash,
+}
+
+impl Message for GetDataRequest {
+    type Result = Result<Option<DataRequestStatus>, failure::Error>;
+}
+
+/// Message to obtain the active data requests created by an identity, oldest first
+pub struct GetDataRequestsByRequester {
+    /// Public key hash of the requester
+    pub pkh: PublicKeyHash,
+}
+
+impl Message for GetDataRequestsByRequester {
+    type Result = Vec<(OutputPointer, DataRequestState)>;
+}
+
+/// Synchronization state of the chain
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ChainStatus {
+    /// State of the state machine of the `ChainManager`
+    pub state: StateMachine,
+    /// Current epoch, if the epoch manager has already notified it
+    pub current_epoch: Option<Epoch>,
+    /// Beacon of the last consolidated block, if the chain state is initialized
+    pub tip: Option<CheckpointBeacon>,
+    /// Timestamp of the consolidation of the last block since the node started
+    pub last_block_timestamp: Option<i64>,
+}
+
+/// Message to obtain the synchronization state of the chain
+pub struct GetChainStatus;
+
+impl Message for GetChainStatus {
+    type Result = ChainStatus;
+}
+
+/// Message to prepare an actor for the shutdown of the node. The response is sent once the
+/// actor has flushed its state to storage
+pub struct Shutdown;
+
+impl Message for Shutdown {
+    type Result = Result<(), failure::Error>;
+}
+
 // JsonRpcServer messages (notifications)
 
 /// New block notification
@@ -19,20 +19,33 @@ pub mod session;
 /// Connections manager actor module
 pub mod connections_manager;
 
+/// LAN discovery manager actor module
+pub mod lan_discovery_manager;
+
 /// Storage keys constants
 pub mod storage_keys;
 
 /// EpochManager actor module
 pub mod epoch_manager;
 
+/// AlertsManager actor module
+pub mod alerts_manager;
+
 /// ChainManager actor module
 pub mod chain_manager;
 
+/// CheckpointsManager actor module
+pub mod checkpoints_manager;
+
 /// InventoryManager actor module
 pub mod inventory_manager;
 
 /// RadManager actor module
 pub mod rad_manager;
 
+/// Node status module
+pub mod node_status;
+
 /// JSON RPC server
+#[cfg(feature = "jsonrpc")]
 pub mod json_rpc;
@@ -1,30 +1,65 @@
 use std::{path::PathBuf, process::exit, result::Result};
 
-use actix::{Actor, System};
+use actix::{Actor, Arbiter, System};
 use failure;
-use futures::future::Future;
-use log::info;
+use futures::{
+    future::{self, Either, Future},
+    sync::mpsc,
+    Stream,
+};
+use log::{error, info, warn};
 
+#[cfg(feature = "jsonrpc")]
+use crate::actors::json_rpc::JsonRpcServer;
 use crate::actors::{
-    chain_manager::ChainManager, connections_manager::ConnectionsManager,
-    epoch_manager::EpochManager, inventory_manager::InventoryManager, json_rpc::JsonRpcServer,
-    peers_manager::PeersManager, rad_manager::RadManager, sessions_manager::SessionsManager,
+    alerts_manager::AlertsManager, chain_manager::ChainManager,
+    checkpoints_manager::CheckpointsManager, connections_manager::ConnectionsManager,
+    epoch_manager::EpochManager, inventory_manager::InventoryManager,
+    lan_discovery_manager::LanDiscoveryManager, messages::Shutdown, peers_manager::PeersManager,
+    rad_manager::RadManager, sessions_manager::SessionsManager,
 };
 use crate::config_mngr;
 use crate::signature_mngr;
 use crate::storage_mngr;
 
+/// Handle to request the shutdown of the node, which can be sent to other threads (e.g. to the
+/// handler of the interrupt signals)
+#[derive(Clone)]
+pub struct ShutdownHandle(mpsc::UnboundedSender<()>);
+
+impl ShutdownHandle {
+    /// Request the shutdown of the node. If it was already requested, the process exits
+    /// immediately without waiting for the state to be flushed to storage
+    pub fn close(&self) {
+        if self.0.unbounded_send(()).is_err() {
+            warn!("Shutdown requested again, exiting without waiting");
+            exit(1);
+        }
+    }
+}
+
 /// Function to run the main system
 pub fn run(
     config: Option<PathBuf>,
     fallback_config: Option<PathBuf>,
-    callback: fn(),
+    callback: fn(ShutdownHandle),
 ) -> Result<(), failure::Error> {
     // Init system
     let system = System::new("node");
 
     // Call cb function (register interrupt handlers)
-    callback();
+    let (shutdown_sender, shutdown_receiver) = mpsc::unbounded();
+    callback(ShutdownHandle(shutdown_sender));
+    Arbiter::spawn(
+        shutdown_receiver
+            .into_future()
+            .map_err(|_| ())
+            .and_then(|(request, _)| match request {
+                Some(()) => Either::A(close()),
+                // The handle was dropped without requesting the shutdown
+                None => Either::B(future::ok(())),
+            }),
+    );
 
     // Start ConfigManager actor
     config_mngr::start();
@@ -49,6 +84,10 @@ pub fn run(
     let connections_manager_addr = ConnectionsManager::default().start();
     System::current().registry().set(connections_manager_addr);
 
+    // Start LanDiscoveryManager actor
+    let lan_discovery_manager_addr = LanDiscoveryManager::default().start();
+    System::current().registry().set(lan_discovery_manager_addr);
+
     // Start SessionManager actor
     let sessions_manager_addr = SessionsManager::default().start();
     System::current().registry().set(sessions_manager_addr);
@@ -61,6 +100,10 @@ pub fn run(
     let chain_manager_addr = ChainManager::default().start();
     System::current().registry().set(chain_manager_addr);
 
+    // Start CheckpointsManager actor
+    let checkpoints_manager_addr = CheckpointsManager::default().start();
+    System::current().registry().set(checkpoints_manager_addr);
+
     // Start InventoryManager actor
     let inventory_manager_addr = InventoryManager::default().start();
     System::current().registry().set(inventory_manager_addr);
@@ -69,23 +112,61 @@ pub fn run(
     let rad_manager_addr = RadManager::default().start();
     System::current().registry().set(rad_manager_addr);
 
+    // Start AlertsManager actor
+    let alerts_manager_addr = AlertsManager::default().start();
+    System::current().registry().set(alerts_manager_addr);
+
     // Start JSON RPC server
-    let json_rpc_server_addr = JsonRpcServer::default().start();
-    System::current().registry().set(json_rpc_server_addr);
+    #[cfg(feature = "jsonrpc")]
+    {
+        let json_rpc_server_addr = JsonRpcServer::default().start();
+        System::current().registry().set(json_rpc_server_addr);
+    }
 
     // Run system
-    system.run();
-
-    Ok(())
+    match system.run() {
+        0 => Ok(()),
+        code => exit(code),
+    }
 }
 
-/// Function to close the main system
-pub fn close() {
+/// Function to close the main system gracefully. New sessions are not accepted anymore, and the
+/// state of the chain, the mempool and the peers is flushed to storage before the system stops.
+/// Since the actors handle their messages one at a time, the chain manager flushes its state
+/// once the block being processed, if any, has been consolidated.
+fn close() -> impl Future<Item = (), Error = ()> {
     info!("Closing node");
 
-    // FIXME(#72): find out how to gracefully stop the system
-    // System::current().stop();
-
-    // Process exit
-    exit(0);
+    let sessions_manager_addr = System::current().registry().get::<SessionsManager>();
+    let chain_manager_addr = System::current().registry().get::<ChainManager>();
+    let peers_manager_addr = System::current().registry().get::<PeersManager>();
+
+    sessions_manager_addr
+        .send(Shutdown)
+        .then(move |_| {
+            chain_manager_addr
+                .send(Shutdown)
+                .join(peers_manager_addr.send(Shutdown))
+        })
+        .then(|res| {
+            let code = match res {
+                Ok((Ok(()), Ok(()))) => {
+                    info!("Node state flushed to storage");
+                    0
+                }
+                Ok((chain_res, peers_res)) => {
+                    for e in chain_res.err().into_iter().chain(peers_res.err()) {
+                        error!("Failed to flush the node state to storage: {}", e);
+                    }
+                    1
+                }
+                Err(e) => {
+                    error!("Failed to flush the node state to storage: {}", e);
+                    1
+                }
+            };
+            System::current().stop_with_code(code);
+
+            Ok(())
+        })
 }
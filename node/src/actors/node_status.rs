@@ -0,0 +1,141 @@
+//! # Node status
+//!
+//! The status of the node aggregates the synchronization state of the `ChainManager` and the
+//! sessions of the `SessionsManager` into a single health indicator, so operators can tell at a
+//! glance whether the node is synced, stalled or isolated.
+use serde::{Deserialize, Serialize};
+
+use witnet_data_structures::chain::Epoch;
+
+use super::{
+    chain_manager::StateMachine,
+    messages::{ChainStatus, SessionsCount},
+};
+
+/// Epochs the chain tip can lag behind the current epoch before a synced node is stalled
+pub const MAX_TIP_LAG_EPOCHS: Epoch = 10;
+
+/// Health of the node
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum NodeHealth {
+    /// The node has no consolidated outbound sessions, so it cannot follow the network
+    Isolated,
+    /// The node is waiting for its outbound peers to agree on a beacon
+    WaitingConsensus,
+    /// The node is catching up with the consensus beacon
+    Synchronizing,
+    /// The node is synced, but its chain tip has not advanced for more than
+    /// `MAX_TIP_LAG_EPOCHS` epochs
+    Stalled,
+    /// The node is synced with the network
+    Synced,
+}
+
+/// Status of the node
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NodeStatus {
+    /// Health derived from the state of the chain and the sessions
+    pub health: NodeHealth,
+    /// Synchronization state of the chain
+    pub chain: ChainStatus,
+    /// Number of consolidated sessions
+    pub sessions: SessionsCount,
+}
+
+impl NodeStatus {
+    /// Aggregate the state of the chain and the sessions into the status of the node
+    pub fn new(chain: ChainStatus, sessions: SessionsCount) -> Self {
+        let tip_lag = match (chain.current_epoch, chain.tip) {
+            (Some(current_epoch), Some(tip)) => current_epoch.saturating_sub(tip.checkpoint),
+            _ => 0,
+        };
+
+        let health = if sessions.outbound == 0 {
+            NodeHealth::Isolated
+        } else {
+            match chain.state {
+                StateMachine::WaitingConsensus => NodeHealth::WaitingConsensus,
+                StateMachine::Synchronizing => NodeHealth::Synchronizing,
+                StateMachine::Synced if tip_lag > MAX_TIP_LAG_EPOCHS => NodeHealth::Stalled,
+                StateMachine::Synced => NodeHealth::Synced,
+            }
+        };
+
+        NodeStatus {
+            health,
+            chain,
+            sessions,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use witnet_data_structures::chain::{CheckpointBeacon, Hash};
+
+    fn chain_status(state: StateMachine, current_epoch: Epoch, tip_epoch: Epoch) -> ChainStatus {
+        ChainStatus {
+            state,
+            current_epoch: Some(current_epoch),
+            tip: Some(CheckpointBeacon {
+                checkpoint: tip_epoch,
+                hash_prev_block: Hash::default(),
+            }),
+            last_block_timestamp: None,
+        }
+    }
+
+    fn sessions_count(outbound: usize) -> SessionsCount {
+        SessionsCount {
+            inbound: 0,
+            outbound,
+            outbound_consensus: outbound,
+        }
+    }
+
+    #[test]
+    fn health_follows_state_machine() {
+        let status = NodeStatus::new(
+            chain_status(StateMachine::WaitingConsensus, 100, 50),
+            sessions_count(4),
+        );
+        assert_eq!(status.health, NodeHealth::WaitingConsensus);
+
+        let status = NodeStatus::new(
+            chain_status(StateMachine::Synchronizing, 100, 50),
+            sessions_count(4),
+        );
+        assert_eq!(status.health, NodeHealth::Synchronizing);
+
+        let status = NodeStatus::new(
+            chain_status(StateMachine::Synced, 100, 99),
+            sessions_count(4),
+        );
+        assert_eq!(status.health, NodeHealth::Synced);
+    }
+
+    #[test]
+    fn synced_node_without_new_blocks_is_stalled() {
+        let status = NodeStatus::new(
+            chain_status(StateMachine::Synced, 100 + MAX_TIP_LAG_EPOCHS, 100),
+            sessions_count(4),
+        );
+        assert_eq!(status.health, NodeHealth::Synced);
+
+        let status = NodeStatus::new(
+            chain_status(StateMachine::Synced, 101 + MAX_TIP_LAG_EPOCHS, 100),
+            sessions_count(4),
+        );
+        assert_eq!(status.health, NodeHealth::Stalled);
+    }
+
+    #[test]
+    fn node_without_outbound_sessions_is_isolated() {
+        let status = NodeStatus::new(
+            chain_status(StateMachine::Synced, 100, 99),
+            sessions_count(0),
+        );
+        assert_eq!(status.health, NodeHealth::Isolated);
+    }
+}
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use actix::prelude::*;
 use log::{debug, error, info};
 
@@ -5,7 +7,7 @@ use super::PeersManager;
 use crate::actors::storage_keys::PEERS_KEY;
 use crate::config_mngr;
 use crate::storage_mngr;
-use witnet_p2p::peers::Peers;
+use witnet_p2p::{peers::Peers, sessions::is_advertisable_address};
 
 /// Make actor from PeersManager
 impl Actor for PeersManager {
@@ -25,6 +27,41 @@ impl Actor for PeersManager {
                 // Get storage peers period
                 let storage_peers_period = config.connections.storage_peers_period;
 
+                // Get memory cap for the known peers
+                act.max_memory = config.memory.peers_max_bytes;
+
+                // Get the DNS seeds and the fallback seeds, used when no peer is known. Behind a
+                // proxy, the DNS seeds are not queried, as the queries would not go through it
+                let dns_seeds = if config.connections.proxy.is_some() {
+                    vec![]
+                } else {
+                    config.connections.dns_seeds.clone()
+                };
+                let fallback_seeds = config.connections.fallback_seeds.clone();
+                let default_port = config.environment.default_port();
+
+                // Get the listening addresses that can be advertised to other peers, none behind a
+                // proxy
+                act.advertised_addresses = if config.connections.proxy.is_some() {
+                    vec![]
+                } else {
+                    config
+                        .connections
+                        .listen_addresses()
+                        .into_iter()
+                        .filter(is_advertisable_address)
+                        .collect()
+                };
+
+                // Get the limits of the addresses sent to other peers
+                act.gossip_max_addresses = config.connections.gossip_max_addresses;
+                act.gossip_max_address_age =
+                    config.connections.gossip_max_address_age.as_secs() as i64;
+
+                // Get the period of the feeler connections and the failures a peer can have
+                let feeler_period = config.connections.feeler_period;
+                act.feeler_max_failures = config.connections.feeler_max_failures;
+
                 // Add all peers
                 info!(
                     "Adding the following peer addresses from config: {:?}",
@@ -34,26 +71,34 @@ impl Actor for PeersManager {
                     Ok(_duplicated_peers) => {}
                     Err(e) => error!("Error when adding peer addresses from config: {}", e),
                 }
+                act.dirty = true;
 
                 storage_mngr::get::<_, Peers>(&PEERS_KEY)
                     .into_actor(act)
                     .map_err(|e, _, _| error!("Couldn't get peers from storage: {}", e))
-                    .and_then(|peers_from_storage, act, _| {
+                    .and_then(move |peers_from_storage, act, ctx| {
                         // peers_from_storage can be None if the storage does not contain that key
                         if let Some(peers_from_storage) = peers_from_storage {
-                            // Add all the peers from storage
-                            // The add method handles duplicates by overwriting the old values
-                            let peers = peers_from_storage.get_all().unwrap();
+                            // Merge the address book from storage, keeping the tried peers and
+                            // the last-seen timestamps
                             info!(
-                                "Adding the following peer addresses from storage: {:?}",
-                                peers
+                                "Adding {} peer addresses from storage",
+                                peers_from_storage.len()
+                            );
+                            act.peers.merge(peers_from_storage);
+                            act.evict_peers();
+                        }
+
+                        // A new node has no peers yet, discover them from the DNS seeds
+                        if act.peers.is_empty()
+                            && !(dns_seeds.is_empty() && fallback_seeds.is_empty())
+                        {
+                            act.bootstrap_from_dns_seeds(
+                                ctx,
+                                dns_seeds,
+                                default_port,
+                                fallback_seeds,
                             );
-                            match act.peers.add(peers) {
-                                Ok(_duplicated_peers) => {}
-                                Err(e) => {
-                                    error!("Error when adding peer addresses from storage: {}", e);
-                                }
-                            }
                         }
 
                         fut::ok(())
@@ -63,6 +108,11 @@ impl Actor for PeersManager {
                 // Start the storage peers process on SessionsManager start
                 act.persist_peers(ctx, storage_peers_period);
 
+                // Start checking the peers of the new bucket with feeler connections
+                if feeler_period != Duration::from_secs(0) {
+                    act.feeler_peers(ctx, feeler_period);
+                }
+
                 fut::ok(())
             })
             .map_err(|err, _, _| log::error!("Peer discovery failed: {}", err))
@@ -1,11 +1,22 @@
-use actix::{Context, Handler};
-use log::{debug, error, warn};
+use std::time::Instant;
 
-use super::PeersManager;
-use crate::actors::messages::{
-    AddPeers, GetRandomPeer, PeersSocketAddrResult, PeersSocketAddrsResult, RemovePeers,
-    RequestPeers,
+use actix::{Context, Handler, MessageResult, ResponseActFuture, WrapFuture};
+use futures::Future;
+use log::{debug, error, info, warn};
+use rand::{seq::SliceRandom, thread_rng};
+
+use super::{PeersManager, MAX_GOSSIP_PROCESSING_TIME};
+use crate::actors::{
+    messages::{
+        AddGossipedPeers, AddPeers, AddTriedPeers, FeelerFailed, GetMemoryInfo, GetRandomPeer,
+        MemoryInfo, PeersSocketAddrResult, PeersSocketAddrsResult, RemovePeers, RequestPeers,
+        Shutdown,
+    },
+    storage_keys::PEERS_KEY,
 };
+use crate::storage_mngr;
+use witnet_p2p::peers::PEERS_SAMPLE_SIZE;
+use witnet_util::timestamp::get_timestamp;
 
 /// Handler for AddPeers message
 impl Handler<AddPeers> for PeersManager {
@@ -14,7 +25,85 @@ impl Handler<AddPeers> for PeersManager {
     fn handle(&mut self, msg: AddPeers, _: &mut Context<Self>) -> Self::Result {
         // Insert address
         debug!("Adding the following peer addresses: {:?}", msg.addresses);
-        self.peers.add(msg.addresses)
+        let result = self.peers.add(msg.addresses);
+        self.evict_peers();
+        self.dirty = true;
+
+        result
+    }
+}
+
+/// Handler for AddTriedPeers message
+impl Handler<AddTriedPeers> for PeersManager {
+    type Result = PeersSocketAddrsResult;
+
+    fn handle(&mut self, msg: AddTriedPeers, _: &mut Context<Self>) -> Self::Result {
+        debug!(
+            "Marking the following peer addresses as tried: {:?}",
+            msg.addresses
+        );
+        let result = self.peers.add_tried(msg.addresses);
+        self.evict_peers();
+        self.dirty = true;
+
+        result
+    }
+}
+
+/// Handler for AddGossipedPeers message
+impl Handler<AddGossipedPeers> for PeersManager {
+    type Result = PeersSocketAddrsResult;
+
+    fn handle(&mut self, msg: AddGossipedPeers, _: &mut Context<Self>) -> Self::Result {
+        let started = Instant::now();
+        let received = msg.addresses.len();
+        let mut addresses = Vec::new();
+        for (i, address) in msg.addresses.into_iter().enumerate() {
+            if started.elapsed() > MAX_GOSSIP_PROCESSING_TIME {
+                warn!(
+                    "Processing time exceeded, ignoring {} of {} gossiped peer addresses",
+                    received - i,
+                    received
+                );
+                break;
+            }
+            if self.seen_addresses.insert(&address) {
+                addresses.push(address);
+            }
+        }
+
+        if addresses.is_empty() {
+            return Ok(vec![]);
+        }
+
+        debug!(
+            "Adding {} new gossiped peer addresses: {:?}",
+            addresses.len(),
+            addresses
+        );
+        let result = self.peers.add(addresses);
+        self.evict_peers();
+        self.dirty = true;
+
+        result
+    }
+}
+
+/// Handler for FeelerFailed message
+impl Handler<FeelerFailed> for PeersManager {
+    type Result = ();
+
+    fn handle(&mut self, msg: FeelerFailed, _: &mut Context<Self>) {
+        if self
+            .peers
+            .feeler_failed(&msg.address, self.feeler_max_failures)
+        {
+            info!(
+                "Forgetting peer {} after {} failed feeler connections",
+                msg.address, self.feeler_max_failures
+            );
+        }
+        self.dirty = true;
     }
 }
 
@@ -25,6 +114,7 @@ impl Handler<RemovePeers> for PeersManager {
     fn handle(&mut self, msg: RemovePeers, _: &mut Context<Self>) -> Self::Result {
         // // Find index of element with address
         debug!("Removing the following addresses: {:?}", msg.addresses);
+        self.dirty = true;
         self.peers.remove(&msg.addresses)
     }
 }
@@ -58,7 +148,57 @@ impl Handler<RequestPeers> for PeersManager {
     type Result = PeersSocketAddrsResult;
 
     fn handle(&mut self, _msg: RequestPeers, _: &mut Context<Self>) -> Self::Result {
-        debug!("Get all peers");
-        self.peers.get_all()
+        debug!("Get a sample of the recently seen peers");
+        let max_addresses = self.gossip_max_addresses.min(PEERS_SAMPLE_SIZE);
+        let since = if self.gossip_max_address_age == 0 {
+            i64::min_value()
+        } else {
+            get_timestamp() - self.gossip_max_address_age
+        };
+
+        // Advertise the public addresses of the server along with the known peers, in random
+        // order
+        self.peers
+            .get_recent_sample(max_addresses, since)
+            .map(|addresses| {
+                let mut advertised_addresses = self.advertised_addresses.clone();
+                advertised_addresses.extend(
+                    addresses
+                        .into_iter()
+                        .filter(|address| !self.advertised_addresses.contains(address)),
+                );
+                advertised_addresses.truncate(max_addresses);
+                advertised_addresses.shuffle(&mut thread_rng());
+
+                advertised_addresses
+            })
+    }
+}
+
+/// Handler for Shutdown message
+impl Handler<Shutdown> for PeersManager {
+    type Result = ResponseActFuture<Self, (), failure::Error>;
+
+    fn handle(&mut self, _msg: Shutdown, _: &mut Context<Self>) -> Self::Result {
+        // The changes since the last periodic persistence would be lost otherwise
+        self.dirty = false;
+        let fut = storage_mngr::put(&PEERS_KEY, &self.peers)
+            .map(|()| info!("Peers persisted to storage"))
+            .into_actor(self);
+
+        Box::new(fut)
+    }
+}
+
+/// Handler for GetMemoryInfo message
+impl Handler<GetMemoryInfo> for PeersManager {
+    type Result = MessageResult<GetMemoryInfo>;
+
+    fn handle(&mut self, _msg: GetMemoryInfo, _: &mut Context<Self>) -> Self::Result {
+        MessageResult(vec![MemoryInfo {
+            component: "peers".to_string(),
+            usage: self.peers.memory_usage(),
+            cap: self.max_memory,
+        }])
     }
 }
@@ -1,24 +1,39 @@
-use std::time::Duration;
+use std::{net::SocketAddr, time::Duration};
 
 use actix::prelude::*;
 use actix::{
-    ActorFuture, AsyncContext, Context, ContextFutureSpawner, Supervised, SystemService, WrapFuture,
+    actors::resolver::{Resolve, Resolver},
+    ActorFuture, AsyncContext, Context, ContextFutureSpawner, Supervised, SystemService,
+    WrapFuture,
 };
+use futures::future::{join_all, Future};
 
-use log::{debug, error};
+use log::{debug, error, info, warn};
 
-use crate::actors::storage_keys::PEERS_KEY;
+use crate::actors::{
+    connections_manager::ConnectionsManager, messages::FeelerConnect, storage_keys::PEERS_KEY,
+};
 use crate::storage_mngr;
-use witnet_p2p::peers::Peers;
+use witnet_data_structures::{
+    builders::{from_address, to_address},
+    types::Address,
+};
+use witnet_p2p::{
+    dns_seeds::seed_host_port,
+    peers::{Peers, SeenAddresses},
+};
 
 // Internal Actor implementation for PeersManager
 mod actor;
 
 /// Handlers to manage the previous messages using the `peers` library:
 /// * Add peers
+/// * Add peers gossiped by other nodes
 /// * Remove peers
+/// * Record failed feeler connections
 /// * Get random peer
 /// * Get all peers
+/// * Get memory usage
 mod handlers;
 
 /// Peers manager actor: manages a list of available peers to connect
@@ -32,27 +47,141 @@ mod handlers;
 pub struct PeersManager {
     /// Known peers
     peers: Peers,
+    /// Memory cap for the known peers, `0` means no cap
+    max_memory: usize,
+    /// Addresses recently gossiped by other nodes
+    seen_addresses: SeenAddresses,
+    /// Whether the known peers changed since they were last persisted
+    dirty: bool,
+    /// Public addresses of the server, advertised to other peers along with the known peers
+    advertised_addresses: Vec<SocketAddr>,
+    /// Maximum number of addresses sent in answer to a `GetPeers` message
+    gossip_max_addresses: usize,
+    /// Maximum age of the addresses sent to other peers, in seconds, `0` means no limit
+    gossip_max_address_age: i64,
+    /// Consecutive failed feeler connections before forgetting a peer, `0` means never
+    feeler_max_failures: u32,
 }
 
+/// Maximum time spent processing the addresses of a single `Peers` message. The rest of the
+/// addresses are ignored, so that huge messages do not block the actor.
+pub const MAX_GOSSIP_PROCESSING_TIME: Duration = Duration::from_millis(10);
+
 impl PeersManager {
-    /// Method to periodically persist peers into storage
+    /// Method to periodically persist peers into storage. All the changes made during a period
+    /// are written at once, and nothing is written if the peers did not change.
     fn persist_peers(&self, ctx: &mut Context<Self>, storage_peers_period: Duration) {
         // Schedule the discovery_peers with a given period
         ctx.run_later(storage_peers_period, move |act, ctx| {
-            storage_mngr::put(&PEERS_KEY, &act.peers)
-                .into_actor(act)
-                .and_then(|_, _, _| {
-                    debug!("PeersManager successfully persisted peers to storage");
-                    fut::ok(())
-                })
-                .map_err(|err, _, _| {
-                    error!("Peers manager persist peers to storage failed: {}", err)
-                })
-                .spawn(ctx);
+            if act.dirty {
+                act.dirty = false;
+                storage_mngr::put(&PEERS_KEY, &act.peers)
+                    .into_actor(act)
+                    .and_then(|_, _, _| {
+                        debug!("PeersManager successfully persisted peers to storage");
+                        fut::ok(())
+                    })
+                    .map_err(|err, act, _| {
+                        error!("Peers manager persist peers to storage failed: {}", err);
+                        // Try again in the next period
+                        act.dirty = true;
+                    })
+                    .spawn(ctx);
+            }
 
             act.persist_peers(ctx, storage_peers_period);
         });
     }
+
+    /// Method to periodically check a random peer of the new bucket with a feeler connection.
+    /// Reachable peers are moved to the tried bucket and peers that fail too many times in a row
+    /// are forgotten, so that the dial attempts are not wasted on stale addresses.
+    fn feeler_peers(&self, ctx: &mut Context<Self>, feeler_period: Duration) {
+        ctx.run_later(feeler_period, move |act, ctx| {
+            if let Some(address) = act.peers.get_random_new() {
+                debug!("Checking peer {} with a feeler connection", address);
+                ConnectionsManager::from_registry().do_send(FeelerConnect { address });
+            }
+
+            act.feeler_peers(ctx, feeler_period);
+        });
+    }
+
+    /// Method to discover peers by resolving the DNS seeds, used when the address book is empty.
+    /// The resolved addresses are converted into Witnet addresses and added to the known peers,
+    /// like the ones received in a `Peers` message. If the DNS seeds give no address, the
+    /// hardcoded fallback seeds are added instead.
+    fn bootstrap_from_dns_seeds(
+        &self,
+        ctx: &mut Context<Self>,
+        dns_seeds: Vec<String>,
+        default_port: u16,
+        fallback_seeds: Vec<SocketAddr>,
+    ) {
+        let lookups = dns_seeds.into_iter().map(move |seed| {
+            let (host, port) = seed_host_port(&seed, default_port);
+            let request =
+                Resolver::from_registry().send(Resolve::host_port(host.to_string(), port));
+
+            request.then(move |res| -> Result<Vec<Address>, ()> {
+                match res {
+                    Ok(Ok(addresses)) => {
+                        debug!("DNS seed {} resolved to {:?}", seed, addresses);
+                        Ok(addresses.into_iter().map(to_address).collect())
+                    }
+                    Ok(Err(e)) => {
+                        warn!("Failed to resolve DNS seed {}: {}", seed, e);
+                        Ok(vec![])
+                    }
+                    Err(e) => {
+                        error!("Unsuccessful communication with resolver: {}", e);
+                        Ok(vec![])
+                    }
+                }
+            })
+        });
+
+        join_all(lookups)
+            .into_actor(self)
+            .map(move |results, act, _ctx| {
+                let addresses: Vec<SocketAddr> =
+                    results.iter().flatten().map(from_address).collect();
+                let addresses = if addresses.is_empty() {
+                    warn!(
+                        "No peer addresses obtained from the DNS seeds, adding {} fallback seeds",
+                        fallback_seeds.len()
+                    );
+                    fallback_seeds
+                } else {
+                    info!("Adding {} peer addresses from DNS seeds", addresses.len());
+                    addresses
+                };
+
+                match act.peers.add(addresses) {
+                    Ok(_duplicated_peers) => {}
+                    Err(e) => error!("Error when adding peer addresses from DNS seeds: {}", e),
+                }
+                act.evict_peers();
+                act.dirty = true;
+            })
+            .spawn(ctx);
+    }
+
+    /// Method to forget the oldest peers when the known peers exceed their memory cap
+    fn evict_peers(&mut self) {
+        if self.max_memory == 0 {
+            return;
+        }
+
+        match self.peers.evict(self.max_memory) {
+            Ok(ref evicted) if evicted.is_empty() => {}
+            Ok(evicted) => info!(
+                "Memory cap for known peers reached, forgetting {} peers",
+                evicted.len()
+            ),
+            Err(e) => error!("Error when evicting peer addresses: {}", e),
+        }
+    }
 }
 
 /// Required traits for being able to retrieve SessionsManager address from registry
@@ -1,6 +1,9 @@
-use super::RadManager;
-use actix::{Actor, Context, Supervised, SystemService};
+use super::{RadManager, RadWorker};
+use crate::{build_info, config_mngr};
+use actix::prelude::*;
 use log;
+use std::cmp;
+use witnet_rad::http::HttpOptions;
 
 /// Implement Actor trait for `RadManager`
 impl Actor for RadManager {
@@ -8,8 +11,35 @@ impl Actor for RadManager {
     type Context = Context<Self>;
 
     /// Method to be executed when the actor is started
-    fn started(&mut self, _ctx: &mut Self::Context) {
+    fn started(&mut self, ctx: &mut Self::Context) {
         log::debug!("RadManager actor has been started!");
+
+        config_mngr::get()
+            .into_actor(self)
+            .and_then(|config, act, _ctx| {
+                let user_agent = config.rad.user_agent.clone().unwrap_or_else(|| {
+                    build_info::user_agent(&config.connections.user_agent_suffix)
+                });
+                act.http_options = HttpOptions {
+                    timeout: cmp::min(config.rad.timeout, config.rad.max_timeout),
+                    max_timeout: config.rad.max_timeout,
+                    max_retries: config.rad.max_retries,
+                    retry_backoff: config.rad.retry_backoff,
+                    max_redirects: config.rad.max_redirects,
+                    user_agent,
+                };
+
+                let http_options = act.http_options.clone();
+                let workers = cmp::max(config.rad.workers, 1);
+                act.workers = Some(SyncArbiter::start(workers, move || {
+                    RadWorker::new(http_options.clone())
+                }));
+                log::debug!("Started {} RadWorker threads", workers);
+
+                fut::ok(())
+            })
+            .map_err(|err, _, _| log::error!("RadManager startup error: {}", err))
+            .wait(ctx);
     }
 }
 
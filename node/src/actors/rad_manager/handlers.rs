@@ -1,25 +1,60 @@
 //! Message handlers for `RadManager`
-use actix::{Handler, Message};
+use actix::{Handler, MailboxError, Message, ResponseFuture};
+use futures::future::{self, Future};
+use log::warn;
 use witnet_data_structures::serializers::decoders::TryFrom;
 use witnet_rad as rad;
-use witnet_rad::types::RadonTypes;
+use witnet_rad::{error::RadError, report::RadonReport, types::RadonTypes};
 
 use super::RadManager;
-use crate::actors::messages::{ResolveRA, RunConsensus};
+use crate::actors::messages::{ResolveRA, RunConsensus, RunRetrieval};
 
 impl Handler<ResolveRA> for RadManager {
-    type Result = <ResolveRA as Message>::Result;
+    type Result = ResponseFuture<Vec<u8>, RadError>;
 
     fn handle(&mut self, msg: ResolveRA, _ctx: &mut Self::Context) -> Self::Result {
         let retrieve_scripts = msg.rad_request.retrieve;
         let aggregate_script = msg.rad_request.aggregate.script;
 
-        let retrieve_responses = retrieve_scripts
-            .into_iter()
-            .filter_map(|retrieve| rad::run_retrieval(retrieve).ok())
-            .collect();
+        // The sources are retrieved in parallel by the workers
+        let workers = self.workers.clone();
+        let retrievals = retrieve_scripts.into_iter().map(move |retrieve| {
+            let url = retrieve.url.clone();
+            let report: Box<dyn Future<Item = RadonReport, Error = MailboxError>> = match &workers {
+                Some(workers) => Box::new(workers.send(RunRetrieval { retrieve })),
+                None => Box::new(future::err(MailboxError::Closed)),
+            };
+
+            report.then(move |report| -> Result<Option<RadonTypes>, RadError> {
+                let result = match report {
+                    Ok(report) => match report.result {
+                        Ok(result) => Some(result),
+                        Err(ref e) => {
+                            match report.failed_call() {
+                                Some(call) => warn!(
+                                    "Retrieval of {} failed at operator {} applied to {}: {}",
+                                    url, call.operator, call.input_type, e
+                                ),
+                                None => warn!("Retrieval of {} failed: {}", url, e),
+                            }
+                            None
+                        }
+                    },
+                    Err(e) => {
+                        warn!("Retrieval of {} failed: {}", url, e);
+                        None
+                    }
+                };
+
+                Ok(result)
+            })
+        });
+
+        Box::new(future::join_all(retrievals).and_then(move |results| {
+            let retrieve_responses = results.into_iter().flatten().collect();
 
-        rad::run_aggregation(retrieve_responses, aggregate_script)
+            rad::run_aggregation(retrieve_responses, aggregate_script)
+        }))
     }
 }
 
@@ -35,6 +70,6 @@ impl Handler<RunConsensus> for RadManager {
             .filter_map(|input| RadonTypes::try_from(input.as_slice()).ok())
             .collect();
 
-        rad::run_consensus(radon_types_vec, packed_script)
+        rad::run_tally(radon_types_vec, packed_script)
     }
 }
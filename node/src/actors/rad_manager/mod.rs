@@ -3,12 +3,28 @@
 //! This module contains the `RadManager` actor which is in charge of
 //! receiving and executing [Data Requests] using the [RAD Engine].
 //!
+//! The HTTP client of the retrievals blocks, so the sources are retrieved
+//! by a pool of `RadWorker` actors running in their own threads, keeping
+//! the arbiter of the node responsive.
+//!
 //! [Data Requests]: https://docs.witnet.io/protocol/data-requests/overview/
 //! [RAD Engine]: https://docs.witnet.io/protocol/data-requests/overview/#the-rad-engine
 
+use actix::Addr;
+use witnet_rad::http::HttpOptions;
+
 mod actor;
 mod handlers;
+mod worker;
+
+use self::worker::RadWorker;
 
 /// RadManager actor
 #[derive(Default)]
-pub struct RadManager;
+pub struct RadManager {
+    /// Options of the HTTP client used by the retrievals
+    http_options: HttpOptions,
+    /// Pool of actors retrieving the sources, started once the
+    /// configuration is loaded
+    workers: Option<Addr<RadWorker>>,
+}
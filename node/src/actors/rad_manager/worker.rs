@@ -0,0 +1,31 @@
+use actix::{Actor, Handler, MessageResult, SyncContext};
+use witnet_rad as rad;
+use witnet_rad::http::HttpOptions;
+
+use crate::actors::messages::RunRetrieval;
+
+/// Actor retrieving the sources of data requests in a thread of a
+/// `SyncArbiter`, as the HTTP client blocks until the source answers
+pub struct RadWorker {
+    /// Options of the HTTP client used by the retrievals
+    http_options: HttpOptions,
+}
+
+impl RadWorker {
+    /// Create a worker retrieving sources with the given options
+    pub fn new(http_options: HttpOptions) -> Self {
+        RadWorker { http_options }
+    }
+}
+
+impl Actor for RadWorker {
+    type Context = SyncContext<Self>;
+}
+
+impl Handler<RunRetrieval> for RadWorker {
+    type Result = MessageResult<RunRetrieval>;
+
+    fn handle(&mut self, msg: RunRetrieval, _ctx: &mut Self::Context) -> Self::Result {
+        MessageResult(rad::run_retrieval_report(msg.retrieve, &self.http_options))
+    }
+}
@@ -4,14 +4,13 @@ use actix::{
 };
 use log::{debug, error, info, warn};
 
-use witnet_data_structures::types::Message as WitnetMessage;
 use witnet_p2p::sessions::{SessionStatus, SessionType};
 
 use super::{handlers::EveryEpochPayload, Session};
 use crate::actors::{
     chain_manager::ChainManager,
     epoch_manager::{EpochManager, EpochManagerError::CheckpointZeroInTheFuture},
-    messages::{AddBlocks, GetEpoch, Register, Subscribe, Unregister},
+    messages::{GetEpoch, Register, Subscribe, Unregister},
     sessions_manager::SessionsManager,
 };
 use witnet_util::timestamp::pretty_print;
@@ -27,7 +26,8 @@ impl Actor for Session {
         ctx.run_later(self.handshake_timeout, |act, ctx| {
             if act.status != SessionStatus::Consolidated {
                 info!(
-                    "Handshake timeout expired, disconnecting session with peer {:?}",
+                    "Handshake timeout expired in state {:?}, disconnecting session with peer {:?}",
+                    act.handshake.state(),
                     act.remote_addr
                 );
                 if let SessionStatus::Unconsolidated = act.status {
@@ -74,16 +74,10 @@ impl Actor for Session {
             .and_then(|_, act, _ctx| {
                 // Send version if outbound session
                 if let SessionType::Outbound = act.session_type {
-                    // FIXME(#142): include the checkpoint of the current tip of the local blockchain
-                    let version_msg = WitnetMessage::build_version(
-                        act.magic_number,
-                        act.server_addr,
-                        act.remote_addr,
-                        0,
-                    );
+                    let version_msg = act.build_version();
                     act.send_message(version_msg);
-                    // Set HandshakeFlag of sent version message
-                    act.handshake_flags.version_tx = true;
+                    // Set handshake flag of sent version message
+                    act.handshake.version_tx = true;
                 }
 
                 actix::fut::ok(())
@@ -103,15 +97,6 @@ impl Actor for Session {
             status: self.status,
         });
 
-        // When session unregisters, notify ChainManager to stop waiting for new blocks
-        if self.blocks_timestamp != 0 {
-            // Get ChainManager address
-            let chain_manager_addr = System::current().registry().get::<ChainManager>();
-
-            chain_manager_addr.do_send(AddBlocks { blocks: vec![] });
-            warn!("Session disconnected during block exchange");
-        }
-
         Running::Stop
     }
 }
@@ -1,40 +1,57 @@
-use std::io::Error;
+use std::{collections::HashSet, io::Error, time::Duration};
 
 use actix::io::WriteHandler;
 use actix::{
-    ActorContext, ActorFuture, Context, ContextFutureSpawner, Handler, StreamHandler, System,
-    SystemService, WrapFuture,
+    ActorContext, ActorFuture, AsyncContext, Context, ContextFutureSpawner, Handler, StreamHandler,
+    System, SystemService, WrapFuture,
 };
 use ansi_term::Color::Green;
 use futures::future;
 use log::{debug, error, info, trace, warn};
+use rand::{thread_rng, Rng};
 
 use witnet_data_structures::{
-    builders::from_address,
-    chain::{Block, CheckpointBeacon, Hashable, InventoryEntry, InventoryItem, Transaction},
+    builders::{
+        from_address, MAX_HEADERS, MAX_LOCATOR_HASHES, PROTOCOL_VERSION, PRUNED_CAPABILITY,
+    },
+    chain::{
+        Block, BlockHeader, CheckpointBeacon, Environment, Hash, Hashable, InventoryEntry,
+        InventoryItem, Transaction,
+    },
     proto::ProtobufConvert,
     types::{
-        Address, Command, InventoryAnnouncement, InventoryRequest, LastBeacon,
-        Message as WitnetMessage, Peers, Version,
+        Address, Command, GetBlocks, Headers, InventoryAnnouncement, InventoryRequest, LastBeacon,
+        Message as WitnetMessage, Peers, Ping, Pong, Reject, Version,
     },
 };
-use witnet_p2p::sessions::{SessionStatus, SessionType};
+use witnet_p2p::{
+    metrics::Direction,
+    sessions::{SessionStatus, SessionType},
+    user_agents::UserAgentMatch,
+};
 
-use super::Session;
+use super::{record_protocol_message, Session};
 use crate::actors::{
+    alerts_manager::AlertsManager,
     chain_manager::ChainManager,
     codec::BytesMut,
-    inventory_manager::InventoryManager,
+    connections_manager::ConnectionsManager,
+    inventory_manager::{InventoryManager, InventoryManagerError},
     messages::{
-        AddBlocks, AddCandidates, AddPeers, AddTransaction, CloseSession, Consolidate,
-        EpochNotification, GetBlocksEpochRange, GetHighestCheckpointBeacon, GetItem, PeerBeacon,
-        RequestPeers, SendGetPeers, SendInventoryAnnouncement, SendInventoryItem, SendLastBeacon,
-        SessionUnitResult,
+        AddBlocks, AddCandidates, AddGossipedPeers, AddHeaders, AddTransaction, Anycast,
+        CheckUserAgent, CloseSession, Consolidate, EpochNotification, FilterAnnouncedItems,
+        GetBlockHeader, GetBlocksEpochRange, GetHighestCheckpointBeacon, GetItem,
+        GetMempoolTransactions, LocateBlocks, PeerBeacon, PeerClockOffset, RecordRejectedVersion,
+        RemovePeers, ReportFloodingPeer, ReportInvalidBlock, ReportOversizedMessage,
+        ReportPeerLatency, ReportPreferredPeer, ReportSlowPeer, RequestPeers, SendGetBlocks,
+        SendGetPeers, SendInventoryAnnouncement, SendInventoryItem, SendInventoryRequest,
+        SendLastBeacon, SessionUnitResult,
     },
     peers_manager::PeersManager,
     sessions_manager::SessionsManager,
 };
-use witnet_util::timestamp::get_timestamp;
+use witnet_util::timestamp::{get_timestamp, get_timestamp_millis};
+use witnet_validations::validations::validate_block_integrity;
 
 /// Implement WriteHandler for Session
 impl WriteHandler<Error> for Session {}
@@ -60,30 +77,45 @@ impl Handler<EpochNotification<EpochPayload>> for ChainManager {
 impl Handler<EpochNotification<EveryEpochPayload>> for Session {
     type Result = ();
 
-    fn handle(&mut self, msg: EpochNotification<EveryEpochPayload>, ctx: &mut Context<Self>) {
+    fn handle(&mut self, msg: EpochNotification<EveryEpochPayload>, _ctx: &mut Context<Self>) {
         debug!("Periodic epoch notification received {:?}", msg.checkpoint);
         self.current_epoch = Some(msg.checkpoint);
-
-        let now = get_timestamp();
-        if self.blocks_timestamp != 0 && now - self.blocks_timestamp > self.blocks_timeout {
-            // Get ChainManager address
-            let chain_manager_addr = System::current().registry().get::<ChainManager>();
-
-            chain_manager_addr.do_send(AddBlocks { blocks: vec![] });
-            warn!("Timeout for waiting blocks achieved");
-            ctx.stop();
-        }
     }
 }
 
+/// Command name used to account the received messages that cannot be decoded
+const INVALID_COMMAND: &str = "INVALID";
+
+/// Messages over the rate limits a peer can send before it is disconnected
+const MAX_FLOOD_VIOLATIONS: u32 = 20;
+
 /// Implement `StreamHandler` trait in order to use `Framed` with an actor
 impl StreamHandler<BytesMut, Error> for Session {
     /// This is main event loop for client requests
     fn handle(&mut self, bytes: BytesMut, ctx: &mut Self::Context) {
+        // Decrypt the message if the encrypted transport is in use
+        let bytes = match self.transport.decrypt(&bytes) {
+            Ok(Some(plaintext)) => BytesMut::from(plaintext),
+            Ok(None) => bytes,
+            Err(e) => {
+                warn!(
+                    "Error decrypting message from peer {}: {}",
+                    self.remote_addr, e
+                );
+
+                // Stop this session
+                ctx.stop();
+                return;
+            }
+        };
         let result = WitnetMessage::from_pb_bytes(&bytes);
         match result {
-            Err(err) => error!("Error decoding message: {:?}", err),
+            Err(err) => {
+                record_protocol_message(Direction::Received, INVALID_COMMAND, bytes.len());
+                error!("Error decoding message: {:?}", err)
+            }
             Ok(msg) => {
+                record_protocol_message(Direction::Received, msg.kind.name(), bytes.len());
                 debug!(
                     "{} Received {} message from session {:?}",
                     Green.bold().paint("[<]"),
@@ -92,18 +124,32 @@ impl StreamHandler<BytesMut, Error> for Session {
                 );
                 trace!("\t{:?}", msg);
 
-                // Consensus constants validation between nodes
+                // Environment validation between nodes
                 if msg.magic != self.magic_number {
                     error!(
-                        "Mismatching consensus constants. \
-                         Magic number received: {}, Ours: {}",
-                        msg.magic, self.magic_number
+                        "Mismatching environments. \
+                         Received: {:?} (magic number {}), Ours: {:?} (magic number {})",
+                        Environment::from_magic(msg.magic),
+                        msg.magic,
+                        Environment::from_magic(self.magic_number),
+                        self.magic_number
                     );
 
                     // Stop this session
                     ctx.stop();
                     return;
                 }
+                self.transport.record_received(&msg, &bytes);
+                self.latency.message_received(get_timestamp_millis());
+
+                // Drop the messages over the rate limits
+                if !self
+                    .flood_protection
+                    .message_received(msg.kind.name(), get_timestamp_millis())
+                {
+                    flood_violation(self, ctx, msg.kind.name());
+                    return;
+                }
 
                 match (self.session_type, self.status, msg.kind) {
                     ////////////////////
@@ -113,29 +159,103 @@ impl StreamHandler<BytesMut, Error> for Session {
                     (
                         _,
                         SessionStatus::Unconsolidated,
-                        Command::Version(Version { sender_address, .. }),
+                        Command::Version(Version {
+                            version,
+                            capabilities,
+                            sender_address,
+                            user_agent,
+                            timestamp,
+                            ephemeral_key,
+                            consensus_constants,
+                            ..
+                        }),
                     ) => {
-                        let msgs = handshake_version(self, &sender_address);
-                        for msg in msgs {
-                            self.send_message(msg);
+                        match self.handshake.negotiate_version(
+                            PROTOCOL_VERSION,
+                            version,
+                            self.min_peer_version,
+                        ) {
+                            Ok(protocol_version) => debug!(
+                                "Negotiated protocol version {} with peer {} (announced {})",
+                                protocol_version, self.remote_addr, version
+                            ),
+                            Err(_) => {
+                                handshake_reject_version(self, ctx, version);
+                                return;
+                            }
                         }
-                        try_consolidate_session(self, ctx);
+                        if consensus_constants.as_slice() != self.consensus_constants.as_ref() {
+                            handshake_reject_consensus_constants(self, ctx, &consensus_constants);
+                            return;
+                        }
+
+                        // Derive the keys of the encrypted transport, if both peers support it
+                        if let Err(e) = self.transport.negotiate(
+                            capabilities,
+                            &ephemeral_key,
+                            self.session_type == SessionType::Outbound,
+                        ) {
+                            warn!(
+                                "Error negotiating the encrypted transport with peer {}: {}",
+                                self.remote_addr, e
+                            );
+                            ctx.stop();
+                            return;
+                        }
+                        if self.transport.is_encrypted() {
+                            debug!(
+                                "Encrypted transport negotiated with peer {}",
+                                self.remote_addr
+                            );
+                        }
+                        if capabilities & PRUNED_CAPABILITY != 0 {
+                            debug!("Peer {} prunes the bodies of old blocks", self.remote_addr);
+                        }
+
+                        // Report the clock of the peer, to detect a drift of the local clock
+                        System::current().registry().get::<AlertsManager>().do_send(
+                            PeerClockOffset {
+                                address: self.remote_addr,
+                                offset: get_timestamp() - timestamp,
+                            },
+                        );
+                        let msgs = handshake_version(self, &sender_address);
+                        handshake_check_user_agent(self, ctx, user_agent, msgs);
                     }
                     // Handler Verack message
                     (_, SessionStatus::Unconsolidated, Command::Verack(_)) => {
                         handshake_verack(self);
                         try_consolidate_session(self, ctx);
                     }
+                    // Handle Reject message: the peer refused the handshake
+                    (_, _, Command::Reject(Reject { reason, .. })) => {
+                        warn!("Peer {} rejected the session: {}", self.remote_addr, reason);
+                        ctx.stop();
+                    }
                     ////////////////////
                     // PEER DISCOVERY //
                     ////////////////////
                     // Handle GetPeers message
                     (_, SessionStatus::Consolidated, Command::GetPeers(_)) => {
-                        peer_discovery_get_peers(self, ctx);
+                        if self.gossip.get_peers_received(get_timestamp()) {
+                            peer_discovery_get_peers(self, ctx);
+                        } else {
+                            debug!(
+                                "Ignoring GetPeers message from peer {}, sent too soon",
+                                self.remote_addr
+                            );
+                        }
                     }
                     // Handle Peers message
                     (_, SessionStatus::Consolidated, Command::Peers(Peers { peers })) => {
-                        peer_discovery_peers(&peers);
+                        if self.gossip.peers_received(get_timestamp()) {
+                            peer_discovery_peers(&peers);
+                        } else {
+                            debug!(
+                                "Ignoring unsolicited Peers message from peer {}, sent too soon",
+                                self.remote_addr
+                            );
+                        }
                     }
                     ///////////////////////
                     // INVENTORY_REQUEST //
@@ -145,31 +265,31 @@ impl StreamHandler<BytesMut, Error> for Session {
                         SessionStatus::Consolidated,
                         Command::InventoryRequest(InventoryRequest { inventory }),
                     ) => {
-                        let inventory_mngr = System::current().registry().get::<InventoryManager>();
-                        let item_requests: Vec<_> = inventory
-                            .iter()
-                            .filter_map(|item| match item {
-                                InventoryEntry::Block(hash) | InventoryEntry::Tx(hash) => {
-                                    Some(inventory_mngr.send(GetItem { hash: *hash }))
-                                }
-                                _ => None,
-                            })
-                            .collect();
-
-                        future::join_all(item_requests)
-                            .into_actor(self)
-                            .map_err(|e, _, _| error!("Inventory request error: {}", e))
-                            .and_then(|item_responses, session, _| {
-                                for item_response in item_responses {
-                                    match item_response {
-                                        Ok(item) => send_inventory_item_msg(session, item),
-                                        Err(e) => warn!("Inventory result is error: {}", e),
-                                    }
-                                }
-
-                                actix::fut::ok(())
-                            })
-                            .wait(ctx);
+                        inventory_process_request(self, ctx, inventory);
+                    }
+                    ////////////////////
+                    //    HEADERS     //
+                    ////////////////////
+                    // Handle GetBlocks message: answer it with the headers following the
+                    // locator hashes
+                    (
+                        _,
+                        SessionStatus::Consolidated,
+                        Command::GetBlocks(GetBlocks { locator_hashes }),
+                    ) => {
+                        if locator_hashes.len() > MAX_LOCATOR_HASHES {
+                            oversized_message(self, ctx, "GetBlocks", locator_hashes.len());
+                            return;
+                        }
+                        inventory_get_blocks(self, ctx, locator_hashes);
+                    }
+                    // Handle Headers message
+                    (_, SessionStatus::Consolidated, Command::Headers(Headers { headers })) => {
+                        if headers.len() > MAX_HEADERS {
+                            oversized_message(self, ctx, "Headers", headers.len());
+                            return;
+                        }
+                        inventory_process_headers(self, headers);
                     }
                     //////////////////////////
                     // TRANSACTION RECEIVED //
@@ -213,7 +333,19 @@ impl StreamHandler<BytesMut, Error> for Session {
                     ////////////////////////////
                     // Handle InventoryAnnouncement message
                     (_, SessionStatus::Consolidated, Command::InventoryAnnouncement(inv)) => {
-                        inventory_process_inv(self, &inv);
+                        inventory_process_inv(self, ctx, &inv);
+                    }
+                    ////////////////////
+                    //    LATENCY     //
+                    ////////////////////
+                    // Handle Ping message: answer it with a Pong message with the same nonce
+                    (_, SessionStatus::Consolidated, Command::Ping(Ping { nonce })) => {
+                        let pong_msg = WitnetMessage::build_pong(self.magic_number, nonce);
+                        self.send_message(pong_msg);
+                    }
+                    // Handle Pong message
+                    (_, SessionStatus::Consolidated, Command::Pong(Pong { nonce })) => {
+                        latency_pong(self, nonce);
                     }
                     /////////////////////
                     // NOT SUPPORTED   //
@@ -241,6 +373,8 @@ impl Handler<SendGetPeers> for Session {
         let get_peers_msg = WitnetMessage::build_get_peers(self.magic_number);
         // Write get peers message in session
         self.send_message(get_peers_msg);
+        // The Peers message answering it is expected
+        self.gossip.get_peers_sent();
     }
 }
 
@@ -276,6 +410,39 @@ impl Handler<SendInventoryItem> for Session {
     }
 }
 
+/// Handler for SendInventoryRequest message (sent by other actors)
+impl Handler<SendInventoryRequest> for Session {
+    type Result = SessionUnitResult;
+
+    fn handle(&mut self, msg: SendInventoryRequest, ctx: &mut Context<Self>) {
+        debug!(
+            "Sending InventoryRequest message to peer at {:?}",
+            self.remote_addr
+        );
+        send_inventory_request(self, ctx, msg.items);
+    }
+}
+
+/// Handler for SendGetBlocks message (sent by other actors)
+impl Handler<SendGetBlocks> for Session {
+    type Result = SessionUnitResult;
+
+    fn handle(&mut self, msg: SendGetBlocks, _ctx: &mut Context<Self>) {
+        debug!(
+            "Sending GetBlocks message to peer at {:?}",
+            self.remote_addr
+        );
+        match WitnetMessage::build_get_blocks(self.magic_number, msg.locator_hashes) {
+            Ok(get_blocks_msg) => {
+                self.send_message(get_blocks_msg);
+                // The Headers message answering it is expected
+                self.headers_requested = true;
+            }
+            Err(e) => error!("Failed to build GetBlocks message: {}", e),
+        }
+    }
+}
+
 impl Handler<SendLastBeacon> for Session {
     type Result = SessionUnitResult;
 
@@ -295,8 +462,8 @@ impl Handler<CloseSession> for Session {
 
 /// Function to try to consolidate session if handshake conditions are met
 fn try_consolidate_session(session: &mut Session, ctx: &mut Context<Session>) {
-    // Check if HandshakeFlags are all set to true
-    if session.handshake_flags.all_true() && session.remote_sender_addr.is_some() {
+    // Check if the handshake messages have all been exchanged
+    if session.handshake.is_completed() && session.remote_sender_addr.is_some() {
         // Update session to consolidate status
         update_consolidate(session, ctx);
     }
@@ -321,11 +488,15 @@ fn update_consolidate(session: &Session, ctx: &mut Context<Session>) {
             match res {
                 Ok(Ok(_)) => {
                     debug!(
-                        "Successfully consolidated session {:?} in SessionManager",
-                        act.remote_addr
+                        "Successfully consolidated session {:?} in SessionManager \
+                         (protocol version {:?})",
+                        act.remote_addr,
+                        act.handshake.protocol_version()
                     );
                     // Set status to consolidate
                     act.status = SessionStatus::Consolidated;
+                    // Start measuring the latency of the peer
+                    latency_start_pinging(act, ctx);
 
                     actix::fut::ok(())
                 }
@@ -345,6 +516,100 @@ fn update_consolidate(session: &Session, ctx: &mut Context<Session>) {
         .wait(ctx);
 }
 
+/// Function to send a Ping message to the peer periodically, disconnecting it if it misses too
+/// many of them in a row
+fn latency_start_pinging(session: &Session, ctx: &mut Context<Session>) {
+    // A zero interval disables the pings
+    if session.ping_interval == Duration::from_secs(0) {
+        return;
+    }
+
+    ctx.run_interval(session.ping_interval, |act, ctx| {
+        let now = get_timestamp_millis();
+
+        // The pings that were not answered before this one are missed
+        let missed_pongs = act.latency.expire_pings(now);
+        if act.max_missed_pongs > 0 && missed_pongs >= act.max_missed_pongs {
+            warn!(
+                "Peer {} missed {} consecutive pings, disconnecting",
+                act.remote_addr, missed_pongs
+            );
+            ctx.stop();
+            return;
+        }
+        if missed_pongs > 0 {
+            report_latency(act);
+        }
+
+        let nonce = thread_rng().gen();
+        act.latency.ping_sent(nonce, now);
+        let ping_msg = WitnetMessage::build_ping_with_nonce(act.magic_number, nonce);
+        act.send_message(ping_msg);
+    });
+}
+
+/// Function called when Pong message is received
+fn latency_pong(session: &mut Session, nonce: u64) {
+    match session.latency.pong_received(nonce, get_timestamp_millis()) {
+        Some(rtt) => {
+            trace!(
+                "Round-trip time to peer {}: {} ms",
+                session.remote_addr,
+                rtt
+            );
+            report_latency(session);
+        }
+        None => debug!(
+            "Ignoring Pong message with unknown nonce {} from peer {}",
+            nonce, session.remote_addr
+        ),
+    }
+}
+
+/// Function called when a message over the rate limits is received. The quality of the peer is
+/// decreased, and the peer is disconnected if it keeps flooding the session
+fn flood_violation(session: &mut Session, ctx: &mut Context<Session>, command: &str) {
+    SessionsManager::from_registry().do_send(ReportFloodingPeer {
+        address: session.remote_addr,
+    });
+
+    let violations = session.flood_protection.violations();
+    if violations >= MAX_FLOOD_VIOLATIONS {
+        warn!(
+            "Disconnecting peer {} after {} messages over the rate limits",
+            session.remote_addr, violations
+        );
+        ctx.stop();
+    } else {
+        debug!(
+            "Dropping {} message from peer {}: rate limit exceeded",
+            command, session.remote_addr
+        );
+    }
+}
+
+/// Function called when a message has more items than the protocol allows. The peer is penalized
+/// and the session is closed, since honest peers never build such messages
+fn oversized_message(session: &Session, ctx: &mut Context<Session>, command: &str, count: usize) {
+    SessionsManager::from_registry().do_send(ReportOversizedMessage {
+        address: session.remote_addr,
+    });
+
+    warn!(
+        "Disconnecting peer {} after receiving a {} message with {} items",
+        session.remote_addr, command, count
+    );
+    ctx.stop();
+}
+
+/// Function to notify the SessionsManager of the latency of the peer
+fn report_latency(session: &Session) {
+    SessionsManager::from_registry().do_send(ReportPeerLatency {
+        address: session.remote_addr,
+        latency: session.latency.stats(),
+    });
+}
+
 /// Function called when GetPeers message is received
 fn peer_discovery_get_peers(session: &mut Session, ctx: &mut Context<Session>) {
     // Get the address of PeersManager actor
@@ -389,11 +654,8 @@ fn peer_discovery_peers(peers: &[Address]) {
     // Convert array of address to vector of socket addresses
     let addresses = peers.iter().map(from_address).collect();
 
-    // Send AddPeers message to the peers manager
-    peers_manager_addr.do_send(AddPeers {
-        // TODO: convert Vec<Address> to Vec<SocketAddr>
-        addresses,
-    });
+    // Send AddGossipedPeers message to the peers manager
+    peers_manager_addr.do_send(AddGossipedPeers { addresses });
 }
 
 /// Function called when Block message is received
@@ -403,6 +665,23 @@ fn inventory_process_block(session: &mut Session, _ctx: &mut Context<Session>, b
 
     let block_epoch = block.block_header.beacon.checkpoint;
     let block_hash = block.hash();
+    let requested = session.requested_items.remove(&block_hash).is_some();
+
+    // Malformed blocks are invalid in any chain, so they are dropped before reaching the
+    // ChainManager and the peer is penalized for sending them
+    if let Err(e) = validate_block_integrity(&block) {
+        warn!(
+            "Dropping block {} from peer {}: {}",
+            block_hash, session.remote_addr, e
+        );
+        if e.is_malformed() {
+            SessionsManager::from_registry().do_send(ReportInvalidBlock {
+                address: session.remote_addr,
+            });
+        }
+
+        return;
+    }
 
     if Some(block_epoch) == session.current_epoch {
         debug!("Send Candidate");
@@ -410,50 +689,25 @@ fn inventory_process_block(session: &mut Session, _ctx: &mut Context<Session>, b
         chain_manager_addr.do_send(AddCandidates {
             blocks: vec![block],
         });
+    } else if requested {
+        // Blocks are delivered to the ChainManager as soon as they are received, it puts them
+        // in order
+        chain_manager_addr.do_send(AddBlocks {
+            blocks: vec![block],
+        });
     } else {
-        // Add block to requested_blocks
-        if session.requested_block_hashes.contains(&block_hash) {
-            session.requested_blocks.insert(block_hash, block);
-        } else {
-            error!("Unexpected not requested block");
-        }
-
-        if session.requested_blocks.len() == session.requested_block_hashes.len() {
-            let mut blocks_vector = vec![];
-            // Iterate over requested block hashes ordered by epoch
-            // TODO: Now we assume that it is sort by epoch,
-            // It would be nice to check it to sort it or discard it
-            for hash in session.requested_block_hashes.clone() {
-                if let Some(block) = session.requested_blocks.remove(&hash) {
-                    blocks_vector.push(block);
-                } else {
-                    // As soon as there is a missing block, stop processing the other
-                    // blocks, send a empty message to the ChainManager and close the session
-                    blocks_vector.clear();
-                    chain_manager_addr.do_send(AddBlocks { blocks: vec![] });
-                    warn!("Unexpected missing block");
-                }
-            }
-
-            // Send a message to the ChainManager to try to add a new block
-            chain_manager_addr.do_send(AddBlocks {
-                blocks: blocks_vector,
-            });
-
-            // Clear requested block structures
-            session.blocks_timestamp = 0;
-            session.requested_blocks.clear();
-            session.requested_block_hashes.clear();
-        }
+        error!("Unexpected not requested block");
     }
 }
 
 /// Function called when Block message is received
 fn inventory_process_transaction(
-    _session: &mut Session,
+    session: &mut Session,
     _ctx: &mut Context<Session>,
     transaction: Transaction,
 ) {
+    session.requested_items.remove(&transaction.hash());
+
     // Get ChainManager address
     let chain_manager_addr = System::current().registry().get::<ChainManager>();
 
@@ -462,35 +716,271 @@ fn inventory_process_transaction(
 }
 
 /// Function to process an InventoryAnnouncement message
-fn inventory_process_inv(session: &mut Session, inv: &InventoryAnnouncement) {
+fn inventory_process_inv(
+    session: &mut Session,
+    ctx: &mut Context<Session>,
+    inv: &InventoryAnnouncement,
+) {
+    // Get ChainManager address
+    let chain_manager_addr = System::current().registry().get::<ChainManager>();
+
     // Check how many of the received inventory vectors need to be requested
-    let inv_entries = &inv.inventory;
+    chain_manager_addr
+        .send(FilterAnnouncedItems {
+            items: inv.inventory.clone(),
+        })
+        .into_actor(session)
+        .then(|res, act, ctx| {
+            match res {
+                Ok(inv_entries) => {
+                    if !inv_entries.is_empty() {
+                        send_inventory_request(act, ctx, inv_entries);
+                    }
+                }
+                Err(e) => error!("Unsuccessful communication with ChainManager: {}", e),
+            }
+
+            actix::fut::ok(())
+        })
+        .wait(ctx);
+}
 
-    session.requested_block_hashes = inv_entries
+/// Function to process an InventoryRequest message. Transactions are served from the
+/// transactions pool, and the rest of the items, as well as the transactions which are no longer
+/// in the pool, from the inventory
+fn inventory_process_request(
+    session: &mut Session,
+    ctx: &mut Context<Session>,
+    inventory: Vec<InventoryEntry>,
+) {
+    // Get ChainManager address
+    let chain_manager_addr = System::current().registry().get::<ChainManager>();
+    let hashes = inventory
         .iter()
-        .map(|inv_entry| match inv_entry.clone() {
-            InventoryEntry::Error(hash)
-            | InventoryEntry::Block(hash)
-            | InventoryEntry::DataRequest(hash)
-            | InventoryEntry::DataResult(hash)
-            | InventoryEntry::Tx(hash) => hash,
+        .filter_map(|item| match item {
+            InventoryEntry::Tx(hash) => Some(*hash),
+            _ => None,
         })
         .collect();
 
-    session.blocks_timestamp = get_timestamp();
+    chain_manager_addr
+        .send(GetMempoolTransactions { hashes })
+        .into_actor(session)
+        .then(move |res, act, ctx| {
+            let transactions = res.unwrap_or_else(|e| {
+                error!("Unsuccessful communication with ChainManager: {}", e);
+                vec![]
+            });
+            let served: HashSet<Hash> = transactions.iter().map(|tx| tx.hash()).collect();
+            for transaction in transactions {
+                send_inventory_item_msg(act, InventoryItem::Transaction(transaction));
+            }
+
+            let inventory_mngr = System::current().registry().get::<InventoryManager>();
+            let item_requests: Vec<_> = inventory
+                .iter()
+                .filter_map(|item| match item {
+                    InventoryEntry::Tx(hash) if served.contains(hash) => None,
+                    // Data requests and tallies are stored as regular transactions
+                    InventoryEntry::Block(hash)
+                    | InventoryEntry::Tx(hash)
+                    | InventoryEntry::DataRequest(hash)
+                    | InventoryEntry::Tally(hash) => {
+                        Some(inventory_mngr.send(GetItem { hash: *hash }))
+                    }
+                    _ => None,
+                })
+                .collect();
+
+            future::join_all(item_requests)
+                .into_actor(act)
+                .map_err(|e, _, _| error!("Inventory request error: {}", e))
+                .and_then(|item_responses, session, _| {
+                    for item_response in item_responses {
+                        match item_response {
+                            Ok(item) => send_inventory_item_msg(session, item),
+                            Err(InventoryManagerError::ItemPruned) => {
+                                debug!("Not serving a pruned block to peer {}", session.remote_addr)
+                            }
+                            Err(e) => warn!("Inventory result is error: {}", e),
+                        }
+                    }
+
+                    actix::fut::ok(())
+                })
+                .wait(ctx);
+
+            actix::fut::ok(())
+        })
+        .wait(ctx);
+}
+
+/// Function called when GetBlocks message is received. The headers of the consolidated blocks
+/// following the most recent block of the locator are sent to the peer, up to `MAX_HEADERS`.
+fn inventory_get_blocks(session: &Session, ctx: &mut Context<Session>, locator_hashes: Vec<Hash>) {
+    // Get ChainManager address from registry
+    let chain_manager_addr = System::current().registry().get::<ChainManager>();
+
+    chain_manager_addr
+        .send(LocateBlocks {
+            locator_hashes,
+            limit: MAX_HEADERS,
+        })
+        .into_actor(session)
+        .then(|res, act, ctx| {
+            match res {
+                Ok(Ok(hashes)) => {
+                    let inventory_mngr = System::current().registry().get::<InventoryManager>();
+                    // The headers of the pruned blocks are kept, so they are served too
+                    let header_requests: Vec<_> = hashes
+                        .into_iter()
+                        .map(|hash| inventory_mngr.send(GetBlockHeader { hash }))
+                        .collect();
+
+                    future::join_all(header_requests)
+                        .into_actor(act)
+                        .map_err(|e, _, _| error!("Inventory request error: {}", e))
+                        .and_then(|header_responses, act, _| {
+                            // The headers must form a chain, so they end at the first block
+                            // missing from the inventory
+                            let mut headers = vec![];
+                            for header_response in header_responses {
+                                match header_response {
+                                    Ok(header) => headers.push(header),
+                                    Err(_) => break,
+                                }
+                            }
+
+                            match WitnetMessage::build_headers(act.magic_number, headers) {
+                                Ok(headers_msg) => act.send_message(headers_msg),
+                                Err(e) => error!("Failed to build Headers message: {}", e),
+                            }
+
+                            actix::fut::ok(())
+                        })
+                        .wait(ctx);
+
+                    actix::fut::ok(())
+                }
+                _ => {
+                    warn!("Failed to locate the blocks requested by the peer in ChainManager");
+
+                    actix::fut::err(())
+                }
+            }
+        })
+        .wait(ctx);
+}
+
+/// Function called when Headers message is received
+fn inventory_process_headers(session: &mut Session, headers: Vec<BlockHeader>) {
+    if !session.headers_requested {
+        debug!(
+            "Ignoring unsolicited Headers message from peer {}",
+            session.remote_addr
+        );
+        return;
+    }
+    session.headers_requested = false;
+
+    // Get ChainManager address
+    let chain_manager_addr = System::current().registry().get::<ChainManager>();
+
+    chain_manager_addr.do_send(AddHeaders { headers });
+}
+
+/// Hash of the item of an inventory entry
+fn inventory_entry_hash(inv_entry: &InventoryEntry) -> Hash {
+    match inv_entry {
+        InventoryEntry::Error(hash)
+        | InventoryEntry::Block(hash)
+        | InventoryEntry::DataRequest(hash)
+        | InventoryEntry::Tally(hash)
+        | InventoryEntry::Tx(hash) => *hash,
+    }
+}
+
+/// Function to request inventory items to the peer. The peer has `blocks_timeout` seconds to
+/// deliver them, otherwise it is reported as slow and the items are requested to another peer.
+fn send_inventory_request(
+    session: &mut Session,
+    ctx: &mut Context<Session>,
+    inv_entries: Vec<InventoryEntry>,
+) {
+    let deadline = get_timestamp() + session.blocks_timeout;
+    for inv_entry in &inv_entries {
+        session.requested_items.insert(
+            inventory_entry_hash(inv_entry),
+            (inv_entry.clone(), deadline),
+        );
+    }
 
     // Try to create InventoryRequest protocol message to request missing inventory vectors
-    if let Ok(inv_req_msg) =
-        WitnetMessage::build_inventory_request(session.magic_number, inv_entries.to_vec())
-    {
-        // Send InventoryRequest message through the session network connection
-        session.send_message(inv_req_msg);
+    match WitnetMessage::build_inventory_request(session.magic_number, inv_entries) {
+        Ok(inv_req_msg) => {
+            // Send InventoryRequest message through the session network connection
+            session.send_message(inv_req_msg);
+
+            let timeout = Duration::from_secs(session.blocks_timeout.max(0) as u64);
+            ctx.run_later(timeout, check_inventory_deadlines);
+        }
+        Err(e) => error!("Failed to build InventoryRequest message: {}", e),
+    }
+}
+
+/// Function called when the deadline of an InventoryRequest is reached. The items which have not
+/// been delivered yet are requested to another peer, and the quality of this peer is decreased.
+fn check_inventory_deadlines(session: &mut Session, ctx: &mut Context<Session>) {
+    let now = get_timestamp();
+    let expired: Vec<Hash> = session
+        .requested_items
+        .iter()
+        .filter(|(_, (_, deadline))| *deadline <= now)
+        .map(|(hash, _)| *hash)
+        .collect();
+    if expired.is_empty() {
+        return;
+    }
+
+    warn!(
+        "Peer {} did not deliver {} requested inventory items in time",
+        session.remote_addr,
+        expired.len()
+    );
+    SessionsManager::from_registry().do_send(ReportSlowPeer {
+        address: session.remote_addr,
+    });
+
+    let mut missing_blocks = false;
+    let mut missing_items = vec![];
+    for hash in expired {
+        match session.requested_items.remove(&hash) {
+            Some((InventoryEntry::Block(_), _)) => missing_blocks = true,
+            Some((inv_entry, _)) => missing_items.push(inv_entry),
+            None => {}
+        }
+    }
+
+    if !missing_items.is_empty() {
+        // The slow peer has just been penalized, so it is only chosen again if there is no other
+        SessionsManager::from_registry().do_send(Anycast {
+            command: SendInventoryRequest {
+                items: missing_items,
+            },
+            safu: false,
+        });
+    }
+
+    if missing_blocks {
+        // Give up the synchronization with this peer: the ChainManager requests the blocks again
+        // to another peer, in consensus with the chain we are synchronizing to
+        ctx.stop();
     }
 }
 
 /// Function called when Verack message is received
 fn handshake_verack(session: &mut Session) {
-    let flags = &mut session.handshake_flags;
+    let flags = &mut session.handshake;
 
     if flags.verack_rx {
         debug!("Verack message already received");
@@ -500,9 +990,113 @@ fn handshake_verack(session: &mut Session) {
     flags.verack_rx = true;
 }
 
+/// Function called when the peer announces a protocol version older than the minimum accepted.
+/// The peer is told why in a final Reject message, and disconnected.
+fn handshake_reject_version(session: &mut Session, ctx: &mut Context<Session>, version: u32) {
+    info!(
+        "Rejecting peer {} with protocol version {} (minimum accepted is {})",
+        session.remote_addr, version, session.min_peer_version
+    );
+    ConnectionsManager::from_registry().do_send(RecordRejectedVersion { version });
+
+    let reason = format!(
+        "Protocol version {} is no longer supported, the minimum is {}. Please upgrade your node.",
+        version, session.min_peer_version
+    );
+    handshake_reject(session, ctx, reason);
+}
+
+/// Function called when the peer announces consensus constants other than ours, which means that
+/// it cannot follow our chain even if it uses the same magic number
+fn handshake_reject_consensus_constants(
+    session: &mut Session,
+    ctx: &mut Context<Session>,
+    consensus_constants: &[u8],
+) {
+    let theirs: String = consensus_constants
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    info!(
+        "Rejecting peer {} with consensus constants {} (ours are {})",
+        session.remote_addr, theirs, session.consensus_constants
+    );
+
+    let reason = format!(
+        "The consensus constants {} are not ours ({}). Please check the environment and the \
+         consensus constants of your node.",
+        theirs, session.consensus_constants
+    );
+    handshake_reject(session, ctx, reason);
+}
+
+/// Function to send a final Reject message to the peer with the `reason` of the rejection, and
+/// disconnect it
+fn handshake_reject(session: &mut Session, ctx: &mut Context<Session>, reason: String) {
+    let reject =
+        WitnetMessage::build_reject(session.magic_number, reason, session.min_peer_version);
+    session.send_message(reject);
+    // Flush the Reject message before the connection is dropped
+    session.framed.close();
+    ctx.stop();
+}
+
+/// Function to check the user agent announced by the peer before going on with the handshake.
+/// Peers matching an avoided user agent pattern are dropped and removed from the peers list,
+/// while the ones matching a preferred pattern are reported to be favored by the sessions manager.
+fn handshake_check_user_agent(
+    session: &mut Session,
+    ctx: &mut Context<Session>,
+    user_agent: String,
+    msgs: Vec<WitnetMessage>,
+) {
+    ConnectionsManager::from_registry()
+        .send(CheckUserAgent {
+            address: session.remote_addr,
+            user_agent,
+        })
+        .into_actor(session)
+        .then(move |res, act, ctx| match res {
+            Ok(UserAgentMatch::Avoided) => {
+                if let Some(address) = act.remote_sender_addr {
+                    PeersManager::from_registry().do_send(RemovePeers {
+                        addresses: vec![address],
+                    });
+                }
+                ctx.stop();
+
+                actix::fut::err(())
+            }
+            Ok(user_agent_match) => {
+                if user_agent_match == UserAgentMatch::Preferred {
+                    SessionsManager::from_registry().do_send(ReportPreferredPeer {
+                        address: act.remote_addr,
+                        listening_address: act.remote_sender_addr,
+                    });
+                }
+                for msg in msgs {
+                    act.send_message(msg);
+                }
+                try_consolidate_session(act, ctx);
+
+                actix::fut::ok(())
+            }
+            Err(e) => {
+                error!(
+                    "Failed to check user agent of peer {}: {}",
+                    act.remote_addr, e
+                );
+                ctx.stop();
+
+                actix::fut::err(())
+            }
+        })
+        .wait(ctx);
+}
+
 /// Function called when Version message is received
 fn handshake_version(session: &mut Session, sender_address: &Address) -> Vec<WitnetMessage> {
-    let flags = &mut session.handshake_flags;
+    let flags = &mut session.handshake;
 
     if flags.version_rx {
         debug!("Version message already received");
@@ -522,13 +1116,7 @@ fn handshake_version(session: &mut Session, sender_address: &Address) -> Vec<Wit
     }
     if !flags.version_tx {
         flags.version_tx = true;
-        let version = WitnetMessage::build_version(
-            session.magic_number,
-            session.server_addr,
-            session.remote_addr,
-            0,
-        );
-        responses.push(version);
+        responses.push(session.build_version());
     }
 
     responses
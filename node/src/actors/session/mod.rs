@@ -1,48 +1,208 @@
 use std::{collections::HashMap, net::SocketAddr, time::Duration};
 
-use actix::io::FramedWrite;
+use actix::{io::FramedWrite, SystemService};
 
 use ansi_term::Color::Green;
 
-use log::{debug, error};
+use log::{debug, error, warn};
 
 use tokio::{io::WriteHalf, net::TcpStream};
 
+use witnet_crypto::transport::{
+    handshake_hash, validate_public_key, EphemeralKey, TransportCiphers, TransportError,
+};
 use witnet_data_structures::{
-    chain::{Block, Hash},
+    builders::{ENCRYPTED_TRANSPORT_CAPABILITY, PRUNED_CAPABILITY},
+    chain::{Hash, InventoryEntry},
     proto::ProtobufConvert,
-    types::Message as WitnetMessage,
+    types::{Command, Message as WitnetMessage},
+};
+use witnet_p2p::{
+    metrics::Direction,
+    peers::gossip::GossipLimiter,
+    sessions::{
+        flood::{FloodProtection, MessageRate},
+        handshake::Handshake,
+        latency::LatencyTracker,
+        SessionStatus, SessionType,
+    },
 };
-use witnet_p2p::sessions::{SessionStatus, SessionType};
 
-use crate::actors::codec::P2PCodec;
+use crate::actors::{
+    codec::{P2PCodec, ThrottledStream},
+    connections_manager::ConnectionsManager,
+    messages::RecordProtocolMessage,
+};
 use witnet_data_structures::chain::Epoch;
+use witnet_util::timestamp::get_timestamp_millis;
 
 mod actor;
 
 mod handlers;
 
-/// HandshakeFlags
+/// Encrypted transport of a session.
+///
+/// Each peer encrypts the messages it sends once it has sent both its `Version` and `Verack`
+/// messages, and decrypts the messages it receives once it has received both of them from the
+/// peer. The transport is only used if both peers announced it in their `Version` message.
+///
+/// The ciphers are derived once both `Version` messages have been exchanged, because the hash of
+/// the serialized messages is bound to the keys of the session.
 #[derive(Default)]
-struct HandshakeFlags {
-    /// Flag to indicate that a version message was sent
-    version_tx: bool,
-    /// Flag to indicate that a version message was received
-    version_rx: bool,
-    /// Flag to indicate that a verack message was sent
-    verack_tx: bool,
-    /// Flag to indicate that a verack message was received
-    verack_rx: bool,
+struct Transport {
+    /// Ephemeral key announced to the peer, `None` if the encrypted transport is disabled
+    ephemeral_key: Option<EphemeralKey>,
+    /// Ephemeral key announced by the peer and whether this peer opened the connection, kept
+    /// until the ciphers are derived
+    peer_ephemeral_key: Option<(Vec<u8>, bool)>,
+    /// Serialized `Version` messages sent to and received from the peer
+    version_sent_bytes: Option<Vec<u8>>,
+    version_received_bytes: Option<Vec<u8>>,
+    /// Ciphers negotiated with the peer, `None` until both `Version` messages are exchanged
+    ciphers: Option<TransportCiphers>,
+    /// Flags to indicate that the version and verack messages were sent
+    version_sent: bool,
+    verack_sent: bool,
+    /// Flags to indicate that the version and verack messages were received
+    version_received: bool,
+    verack_received: bool,
 }
 
-/// HandshakeFlags helper methods
-impl HandshakeFlags {
-    // Auxiliary function to check if all flags are set to true
-    fn all_true(&self) -> bool {
-        self.verack_tx && self.verack_rx && self.version_tx && self.version_rx
+/// Transport helper methods
+impl Transport {
+    /// Create the transport of a session, generating its ephemeral key if enabled
+    fn new(encrypted_transport: bool) -> Self {
+        Transport {
+            ephemeral_key: if encrypted_transport {
+                Some(EphemeralKey::generate())
+            } else {
+                None
+            },
+            ..Transport::default()
+        }
+    }
+    /// Method to negotiate the ciphers with the capabilities and the ephemeral key announced by
+    /// the peer. The `initiator` is the peer that opened the connection
+    fn negotiate(
+        &mut self,
+        capabilities: u64,
+        peer_ephemeral_key: &[u8],
+        initiator: bool,
+    ) -> Result<(), TransportError> {
+        if self.ephemeral_key.is_some() && capabilities & ENCRYPTED_TRANSPORT_CAPABILITY != 0 {
+            validate_public_key(peer_ephemeral_key)?;
+            self.peer_ephemeral_key = Some((peer_ephemeral_key.to_vec(), initiator));
+            self.derive_ciphers()?;
+        }
+
+        Ok(())
+    }
+    /// Method to derive the ciphers once the peer announced its ephemeral key and both `Version`
+    /// messages are known
+    fn derive_ciphers(&mut self) -> Result<(), TransportError> {
+        if let (
+            Some(ephemeral_key),
+            Some((peer_ephemeral_key, initiator)),
+            Some(version_sent),
+            Some(version_received),
+        ) = (
+            &self.ephemeral_key,
+            &self.peer_ephemeral_key,
+            &self.version_sent_bytes,
+            &self.version_received_bytes,
+        ) {
+            // The `Version` message of the initiator goes first for both peers
+            let handshake = if *initiator {
+                handshake_hash(version_sent, version_received)
+            } else {
+                handshake_hash(version_received, version_sent)
+            };
+            self.ciphers = Some(ephemeral_key.agree(peer_ephemeral_key, *initiator, &handshake)?);
+            self.peer_ephemeral_key = None;
+        }
+
+        Ok(())
+    }
+    /// Method to check if the messages of the session are encrypted, or will be once the
+    /// handshake is completed
+    fn is_encrypted(&self) -> bool {
+        self.ciphers.is_some() || self.peer_ephemeral_key.is_some()
+    }
+    /// Method to encrypt a message before sending it, if needed
+    fn encrypt(&mut self, msg: &WitnetMessage, bytes: Vec<u8>) -> Result<Vec<u8>, TransportError> {
+        let sending_encrypted = self.version_sent && self.verack_sent;
+        match &msg.kind {
+            Command::Version(_) => {
+                self.version_sent = true;
+                self.version_sent_bytes = Some(bytes.clone());
+                self.derive_ciphers()?;
+            }
+            Command::Verack(_) => self.verack_sent = true,
+            _ => {}
+        }
+
+        match &mut self.ciphers {
+            Some(ciphers) if sending_encrypted => ciphers.sender.encrypt(&bytes),
+            _ => Ok(bytes),
+        }
+    }
+    /// Method to decrypt a received message, if needed
+    fn decrypt(&mut self, bytes: &[u8]) -> Result<Option<Vec<u8>>, TransportError> {
+        let receiving_encrypted = self.version_received && self.verack_received;
+
+        match &mut self.ciphers {
+            Some(ciphers) if receiving_encrypted => ciphers
+                .receiver
+                .decrypt(bytes)
+                .map(|plaintext| Some(plaintext.as_ref().to_vec())),
+            _ => Ok(None),
+        }
+    }
+    /// Method to record a received message, once decoded from its plaintext `bytes`
+    fn record_received(&mut self, msg: &WitnetMessage, bytes: &[u8]) {
+        match &msg.kind {
+            Command::Version(_) => {
+                self.version_received = true;
+                self.version_received_bytes = Some(bytes.to_vec());
+            }
+            Command::Verack(_) => self.verack_received = true,
+            _ => {}
+        }
     }
 }
 
+/// Rate limits of the messages that are cheap to send but costly to answer, by command name
+const MESSAGE_RATE_LIMITS: &[(&str, MessageRate)] = &[
+    (
+        "GET_BLOCKS",
+        MessageRate {
+            burst: 10,
+            interval: 1_000,
+        },
+    ),
+    (
+        "GET_PEERS",
+        MessageRate {
+            burst: 5,
+            interval: 10_000,
+        },
+    ),
+    (
+        "INVENTORY_REQUEST",
+        MessageRate {
+            burst: 100,
+            interval: 100,
+        },
+    ),
+    (
+        "PING",
+        MessageRate {
+            burst: 10,
+            interval: 1_000,
+        },
+    ),
+];
+
 /// Session representing a TCP connection
 pub struct Session {
     /// Server socket address (local peer)
@@ -55,7 +215,7 @@ pub struct Session {
     session_type: SessionType,
 
     /// Framed wrapper to send messages through the TCP connection
-    framed: FramedWrite<WriteHalf<TcpStream>, P2PCodec>,
+    framed: FramedWrite<WriteHalf<ThrottledStream<TcpStream>>, P2PCodec>,
 
     /// Handshake timeout
     handshake_timeout: Duration,
@@ -63,8 +223,8 @@ pub struct Session {
     /// Session status
     status: SessionStatus,
 
-    /// HandshakeFlags
-    handshake_flags: HandshakeFlags,
+    /// Handshake messages exchanged and protocol version negotiated with the peer
+    handshake: Handshake,
 
     /// Remote sender address
     remote_sender_addr: Option<SocketAddr>,
@@ -72,33 +232,70 @@ pub struct Session {
     /// Magic number
     magic_number: u16,
 
+    /// Hash of the consensus constants, which must match the ones of the remote peer
+    consensus_constants: Hash,
+
     /// Current epoch
     current_epoch: Option<Epoch>,
 
-    /// Requested block hashes vector
-    requested_block_hashes: Vec<Hash>,
-
-    /// HashMap with requested blocks
-    requested_blocks: HashMap<Hash, Block>,
+    /// Headers requested to the peer and not delivered yet
+    headers_requested: bool,
 
     /// Timeout for requested blocks
     blocks_timeout: i64,
 
-    /// Timestamp for requested blocks
-    blocks_timestamp: i64,
+    /// Inventory items requested to the peer and not delivered yet, by hash, with the timestamp
+    /// by which they must be delivered
+    requested_items: HashMap<Hash, (InventoryEntry, i64)>,
+
+    /// User agent announced to the remote peer
+    user_agent: String,
+
+    /// Minimum protocol version accepted from the remote peer
+    min_peer_version: u32,
+
+    /// Encrypted transport of the session
+    transport: Transport,
+
+    /// Announce to the remote peer that the bodies of old blocks are pruned
+    pruned: bool,
+
+    /// Pings sent to the remote peer and latency measured from its pongs
+    latency: LatencyTracker,
+
+    /// Period between the pings sent to the remote peer
+    ping_interval: Duration,
+
+    /// Number of consecutive missed pings before disconnecting the remote peer
+    max_missed_pongs: u32,
+
+    /// Peer addresses exchanged with the remote peer
+    gossip: GossipLimiter,
+
+    /// Rate limits of the messages received from the remote peer
+    flood_protection: FloodProtection,
 }
 
 /// Session helper methods
 impl Session {
     /// Method to create a new session
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         server_addr: SocketAddr,
         remote_addr: SocketAddr,
         session_type: SessionType,
-        framed: FramedWrite<WriteHalf<TcpStream>, P2PCodec>,
+        framed: FramedWrite<WriteHalf<ThrottledStream<TcpStream>>, P2PCodec>,
         handshake_timeout: Duration,
         magic_number: u16,
+        consensus_constants: Hash,
         blocks_timeout: i64,
+        user_agent: String,
+        min_peer_version: u32,
+        encrypted_transport: bool,
+        pruned: bool,
+        ping_interval: Duration,
+        max_missed_pongs: u32,
+        gossip_interval: Duration,
     ) -> Session {
         Session {
             server_addr,
@@ -107,14 +304,23 @@ impl Session {
             framed,
             handshake_timeout,
             status: SessionStatus::Unconsolidated,
-            handshake_flags: HandshakeFlags::default(),
+            handshake: Handshake::default(),
             remote_sender_addr: None,
             magic_number,
+            consensus_constants,
             current_epoch: None,
-            requested_block_hashes: vec![],
-            requested_blocks: HashMap::new(),
+            headers_requested: false,
             blocks_timeout,
-            blocks_timestamp: 0,
+            requested_items: HashMap::new(),
+            user_agent,
+            min_peer_version,
+            transport: Transport::new(encrypted_transport),
+            pruned,
+            latency: LatencyTracker::default(),
+            ping_interval,
+            max_missed_pongs,
+            gossip: GossipLimiter::new(gossip_interval.as_secs() as i64),
+            flood_protection: FloodProtection::new(MESSAGE_RATE_LIMITS, get_timestamp_millis()),
         }
     }
     /// Method to send a Witnet message to the remote peer
@@ -128,11 +334,57 @@ impl Session {
         debug!("\t{:?}", msg);
         // Convert WitnetMessage into a vector of bytes
         match ProtobufConvert::to_pb_bytes(&msg) {
-            Ok(bytes) => self.framed.write(bytes.into()),
+            Ok(bytes) => {
+                record_protocol_message(Direction::Sent, msg.kind.name(), bytes.len());
+                // Convert bytes into BytestMut and send them, encrypted if needed
+                match self.transport.encrypt(&msg, bytes) {
+                    Ok(bytes) => self.framed.write(bytes.into()),
+                    Err(e) => warn!("Error encrypting message: {}", e),
+                }
+            }
             Err(e) => {
                 error!("Error encoding message: {}", e);
             }
         }
-        // Convert bytes into BytestMut and send them
     }
+    /// Method to build the Version message sent to the remote peer, announcing the encrypted
+    /// transport and the pruning of old blocks if enabled
+    fn build_version(&self) -> WitnetMessage {
+        // FIXME(#142): include the checkpoint of the current tip of the local blockchain
+        let mut msg = match &self.transport.ephemeral_key {
+            Some(ephemeral_key) => WitnetMessage::build_encrypted_version(
+                self.magic_number,
+                self.server_addr,
+                self.remote_addr,
+                self.user_agent.clone(),
+                0,
+                ephemeral_key.public_key_bytes(),
+            ),
+            None => WitnetMessage::build_version(
+                self.magic_number,
+                self.server_addr,
+                self.remote_addr,
+                self.user_agent.clone(),
+                0,
+            ),
+        };
+        if let Command::Version(version) = &mut msg.kind {
+            version.consensus_constants = self.consensus_constants.as_ref().to_vec();
+            if self.pruned {
+                version.capabilities |= PRUNED_CAPABILITY;
+            }
+        }
+
+        msg
+    }
+}
+
+/// Account a protocol message exchanged with a peer in the network totals kept by the
+/// `ConnectionsManager`
+fn record_protocol_message(direction: Direction, command: &'static str, size: usize) {
+    ConnectionsManager::from_registry().do_send(RecordProtocolMessage {
+        direction,
+        command,
+        size,
+    });
 }
@@ -1,9 +1,12 @@
 use super::SessionsManager;
-use crate::config_mngr;
+use crate::{build_info, config_mngr};
 use actix::prelude::*;
 use log;
-use witnet_crypto::hash::calculate_sha256;
-use witnet_data_structures::proto::ProtobufConvert;
+use witnet_data_structures::chain::Hashable;
+use witnet_p2p::{
+    bandwidth::BandwidthLimits,
+    sessions::{access::AccessList, Listener},
+};
 
 /// Make actor from `SessionsManager`
 impl Actor for SessionsManager {
@@ -21,11 +24,22 @@ impl Actor for SessionsManager {
                 // Get periods for peers bootstrapping and discovery tasks
                 let bootstrap_peers_period = config.connections.bootstrap_peers_period;
                 let discovery_peers_period = config.connections.discovery_peers_period;
-                let consensus_constants = config.consensus_constants.clone();
 
-                // Set server address, connections limits and handshake timeout
+                // Set server address, listeners, connections limits and handshake timeout
                 act.sessions
                     .set_server_address(config.connections.server_addr);
+                act.sessions.set_listeners(
+                    config
+                        .connections
+                        .listeners
+                        .iter()
+                        .map(|listener| Listener {
+                            address: listener.addr,
+                            inbound_limit: listener.inbound_limit,
+                            whitelist: listener.whitelist.clone(),
+                        })
+                        .collect(),
+                );
                 act.sessions.set_limits(
                     config.connections.inbound_limit,
                     config.connections.outbound_limit,
@@ -34,10 +48,50 @@ impl Actor for SessionsManager {
                     .set_handshake_timeout(config.connections.handshake_timeout);
                 act.sessions
                     .set_blocks_timeout(config.connections.blocks_timeout);
+                act.sessions.set_user_agent(build_info::user_agent(
+                    &config.connections.user_agent_suffix,
+                ));
+                act.sessions
+                    .set_encrypted_transport(config.connections.encrypted_transport);
+                act.sessions.set_pruned(config.pruning.enabled);
+                act.sessions
+                    .set_ping_interval(config.connections.ping_interval);
+                act.sessions
+                    .set_max_missed_pongs(config.connections.max_missed_pongs);
+                act.sessions
+                    .set_gossip_interval(config.connections.gossip_interval);
+                act.sessions.set_bandwidth_limits(BandwidthLimits::new(
+                    config.connections.max_upload_rate,
+                    config.connections.max_download_rate,
+                    config.connections.max_total_upload_rate,
+                    config.connections.max_total_download_rate,
+                ));
+
+                // An invalid access list stops the node instead of being ignored, as it could
+                // let the node connect to peers that should be forbidden
+                match AccessList::parse(
+                    &config.connections.whitelist,
+                    &config.connections.blacklist,
+                ) {
+                    Ok(access_list) => act.sessions.set_access_list(access_list),
+                    Err(err) => {
+                        System::current().stop_with_code(1);
+                        return fut::err(failure::Error::from(err));
+                    }
+                }
+
+                act.seed_hosts = config.connections.seed_hosts.clone();
+                act.hide_server_address = config.connections.proxy.is_some();
 
-                let magic = calculate_sha256(&consensus_constants.to_pb_bytes().unwrap());
-                let magic = u16::from(magic.0[0]) << 8 | (u16::from(magic.0[1]));
-                act.sessions.set_magic_number(magic);
+                act.sessions.set_magic_number(config.environment.magic());
+                // The magic number only identifies the environment, and the consensus constants
+                // can be overridden in testnet and development, so they are checked separately
+                act.consensus_constants = config.consensus_constants.hash();
+                // The configured minimum can only raise the one of the consensus constants
+                act.sessions.set_min_peer_version(std::cmp::max(
+                    config.consensus_constants.min_protocol_version,
+                    config.connections.min_peer_version,
+                ));
 
                 // The peers bootstrapping process begins upon SessionsManager's start
                 act.bootstrap_peers(ctx, bootstrap_peers_period);
@@ -1,22 +1,39 @@
 use std::{
     fmt::{Debug, Display},
     marker::Send,
+    net::{Ipv4Addr, SocketAddr},
 };
+#[cfg(feature = "chaos")]
+use std::{io, time::Instant};
 
 use actix::{
     io::FramedWrite, Actor, ActorFuture, Context, ContextFutureSpawner, Handler, Message,
-    StreamHandler, System, WrapFuture,
+    MessageResult, StreamHandler, System, WrapFuture,
 };
-use log::{debug, error, warn};
+#[cfg(feature = "chaos")]
+use futures::{future, Future, Stream};
+use log::{debug, error, info, warn};
+#[cfg(feature = "chaos")]
+use tokio::timer::Delay;
 use tokio::{codec::FramedRead, io::AsyncRead};
 
+use witnet_p2p::sessions::{
+    canonical_address, SessionType, FLOODING_PEER_PENALTY, INVALID_BLOCK_PENALTY,
+    OVERSIZED_MESSAGE_PENALTY, SLOW_PEER_PENALTY,
+};
+
 use super::SessionsManager;
+#[cfg(feature = "chaos")]
+use crate::actors::codec::BytesMut;
 use crate::actors::messages::EpochNotification;
 use crate::actors::{
-    codec::P2PCodec,
+    codec::{P2PCodec, ThrottledStream},
     messages::{
-        AddPeers, Anycast, Broadcast, Consolidate, Create, PeerBeacon, Register,
-        SessionsUnitResult, Unregister,
+        AddPeers, AddTriedPeers, Anycast, Broadcast, CloseSession, Consolidate, Create,
+        GetAccessList, GetConsolidatedSessionsCount, GetPeersLatency, GetSessionsCount, PeerBeacon,
+        PeerLatency, Register, ReportFloodingPeer, ReportInvalidBlock, ReportOversizedMessage,
+        ReportPeerLatency, ReportPreferredPeer, ReportSlowPeer, SessionsCount, SessionsUnitResult,
+        SetAccessList, SetExternalAddress, Shutdown, Unregister,
     },
     peers_manager::PeersManager,
     session::Session,
@@ -27,31 +44,145 @@ impl Handler<Create> for SessionsManager {
     type Result = ();
 
     fn handle(&mut self, msg: Create, _ctx: &mut Context<Self>) {
+        if self.shutting_down {
+            debug!(
+                "Dropping {:?} connection: the node is shutting down",
+                msg.session_type
+            );
+            return;
+        }
+
+        // Check the policy of the listener that accepted the connection. The stream is dropped
+        // (and the connection closed) if it is not allowed
+        if let SessionType::Inbound = msg.session_type {
+            let addresses = msg.stream.local_addr().and_then(|local_addr| {
+                Ok((local_addr, canonical_address(msg.stream.peer_addr()?)))
+            });
+            match addresses {
+                Ok((local_addr, remote_addr)) => {
+                    // When the inbound limit is reached, the worst inbound peer makes room for
+                    // the new one. Checked before the listener policy, which takes a slot of the
+                    // listener
+                    let evicted = if self.sessions.is_inbound_full() {
+                        match self.sessions.get_inbound_session_to_evict() {
+                            Some(evicted) => Some(evicted),
+                            None => {
+                                debug!(
+                                    "Dropping inbound connection from {} to {}: inbound limit \
+                                     reached",
+                                    remote_addr, local_addr
+                                );
+                                return;
+                            }
+                        }
+                    } else {
+                        None
+                    };
+                    if let Err(e) = self.sessions.accept_inbound(local_addr, remote_addr) {
+                        debug!(
+                            "Dropping inbound connection from {} to {}: {}",
+                            remote_addr, local_addr, e
+                        );
+                        return;
+                    }
+                    if let Some((address, session)) = evicted {
+                        debug!(
+                            "Evicting inbound peer {} with quality {} to accept {}",
+                            address,
+                            self.sessions.get_peer_quality(address),
+                            remote_addr
+                        );
+                        session.do_send(CloseSession);
+                    }
+                }
+                Err(e) => {
+                    warn!("Dropping inbound connection with unknown address: {}", e);
+                    return;
+                }
+            }
+        }
+
+        // Check the access list for the outbound connections too, as the addresses of the seed
+        // hosts are only known once they are resolved
+        if let SessionType::Outbound = msg.session_type {
+            let remote_addr = match msg.remote_addr {
+                Some(remote_addr) => Ok(remote_addr),
+                None => msg.stream.peer_addr().map(canonical_address),
+            };
+            match remote_addr {
+                Ok(remote_addr) if !self.sessions.access_list.is_allowed(remote_addr.ip()) => {
+                    debug!(
+                        "Dropping outbound connection to {}: not allowed by the access list",
+                        remote_addr
+                    );
+                    return;
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    warn!("Dropping outbound connection with unknown address: {}", e);
+                    return;
+                }
+            }
+        }
+
         // Get handshake timeout
         let handshake_timeout = self.sessions.handshake_timeout;
 
-        // Get server address
-        let server_addr = self.sessions.server_address;
+        // Get server address, advertising the external address if the port is mapped in the NAT.
+        // Behind a proxy, an unspecified address is advertised instead, which peers ignore
+        let server_addr = if self.hide_server_address {
+            Some(SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), 0))
+        } else {
+            self.external_address.or(self.sessions.server_address)
+        };
 
         // Get magic number
         let magic_number = self.sessions.magic_number;
 
+        // Get the hash of the consensus constants
+        let consensus_constants = self.consensus_constants;
+
         // Get blocks timeout
         let blocks_timeout = self.sessions.blocks_timeout;
 
+        // Get user agent
+        let user_agent = self.sessions.user_agent.clone();
+
+        // Get minimum protocol version of the peers
+        let min_peer_version = self.sessions.min_peer_version;
+
+        // Get whether the session is encrypted if the peer supports it
+        let encrypted_transport = self.sessions.encrypted_transport;
+
+        // Get whether the node announces that it prunes the bodies of old blocks
+        let pruned = self.sessions.pruned;
+
+        // Get the period of the pings and the number of pings a peer can miss
+        let ping_interval = self.sessions.ping_interval;
+        let max_missed_pongs = self.sessions.max_missed_pongs;
+
+        // Get the minimum time between two peer address messages accepted from the peer
+        let gossip_interval = self.sessions.gossip_interval;
+
+        // Get the bandwidth limiters of the session
+        let upload_limiter = self.sessions.bandwidth_limits.upload_limiter();
+        let download_limiter = self.sessions.bandwidth_limits.download_limiter();
+
         // Create a Session actor
         Session::create(move |ctx| {
             // Get server address (if not present, send local address instead)
             let server_addr = server_addr.unwrap_or_else(|| msg.stream.local_addr().unwrap());
 
-            // Get remote peer address
-            let remote_addr = msg.stream.peer_addr().unwrap();
+            // Get remote peer address, as IPv4 if the peer connected to a dual-stack socket
+            let remote_addr = msg
+                .remote_addr
+                .unwrap_or_else(|| canonical_address(msg.stream.peer_addr().unwrap()));
 
-            // Split TCP stream into read and write parts
-            let (r, w) = msg.stream.split();
+            // Throttle the TCP stream and split it into read and write parts
+            let (r, w) = ThrottledStream::new(msg.stream, upload_limiter, download_limiter).split();
 
             // Add stream in session actor from the read part of the tcp stream
-            Session::add_stream(FramedRead::new(r, P2PCodec), ctx);
+            Session::add_stream(with_chaos(FramedRead::new(r, P2PCodec)), ctx);
 
             // Create the session actor and store in its state the write part of the tcp stream
             Session::new(
@@ -61,12 +192,71 @@ impl Handler<Create> for SessionsManager {
                 FramedWrite::new(w, P2PCodec, ctx),
                 handshake_timeout,
                 magic_number,
+                consensus_constants,
                 blocks_timeout,
+                user_agent,
+                min_peer_version,
+                encrypted_transport,
+                pruned,
+                ping_interval,
+                max_missed_pongs,
+                gossip_interval,
             )
         });
     }
 }
 
+/// Randomly drop and delay the messages received by a session if chaos testing is enabled. The
+/// messages are delayed in order, like a congested connection would.
+#[cfg(feature = "chaos")]
+fn with_chaos<S>(stream: S) -> Box<dyn Stream<Item = BytesMut, Error = io::Error>>
+where
+    S: Stream<Item = BytesMut, Error = io::Error> + 'static,
+{
+    let chaos = match witnet_util::chaos::Chaos::global() {
+        Some(chaos) => chaos,
+        None => return Box::new(stream),
+    };
+    let delay_chaos = chaos.clone();
+
+    Box::new(
+        stream
+            .filter(move |_| {
+                let drop = chaos.drop_message();
+                if drop {
+                    debug!("Chaos testing: dropping a received message");
+                }
+
+                !drop
+            })
+            .and_then(move |bytes| match delay_chaos.message_delay() {
+                Some(delay) => future::Either::A(
+                    Delay::new(Instant::now() + delay)
+                        .map(move |()| bytes)
+                        .map_err(|err| io::Error::new(io::ErrorKind::Other, err)),
+                ),
+                None => future::Either::B(future::ok(bytes)),
+            }),
+    )
+}
+
+#[cfg(not(feature = "chaos"))]
+fn with_chaos<S>(stream: S) -> S {
+    stream
+}
+
+/// Handler for Shutdown message.
+impl Handler<Shutdown> for SessionsManager {
+    type Result = Result<(), failure::Error>;
+
+    fn handle(&mut self, _msg: Shutdown, _: &mut Context<Self>) -> Self::Result {
+        info!("Not accepting new sessions anymore");
+        self.shutting_down = true;
+
+        Ok(())
+    }
+}
+
 /// Handler for Register message.
 impl Handler<Register> for SessionsManager {
     type Result = SessionsUnitResult;
@@ -138,6 +328,14 @@ impl Handler<Consolidate> for SessionsManager {
             addresses: vec![msg.potential_new_peer],
         });
 
+        // A connection to the peer of an outbound session was established, so its address is
+        // moved to the tried bucket
+        if let (Ok(_), SessionType::Outbound) = (&result, msg.session_type) {
+            peers_manager_addr.do_send(AddTriedPeers {
+                addresses: vec![msg.address],
+            });
+        }
+
         match &result {
             Ok(_) => debug!(
                 "Established a consolidated {:?} session with the peer at {}",
@@ -264,3 +462,173 @@ impl Handler<PeerBeacon> for SessionsManager {
         }
     }
 }
+
+/// Handler for ReportSlowPeer message
+impl Handler<ReportSlowPeer> for SessionsManager {
+    type Result = ();
+
+    fn handle(&mut self, msg: ReportSlowPeer, _ctx: &mut Context<Self>) {
+        self.sessions
+            .decrease_peer_quality(msg.address, SLOW_PEER_PENALTY);
+        debug!(
+            "Quality of peer {} decreased to {}",
+            msg.address,
+            self.sessions.get_peer_quality(msg.address)
+        );
+    }
+}
+
+/// Handler for ReportFloodingPeer message
+impl Handler<ReportFloodingPeer> for SessionsManager {
+    type Result = ();
+
+    fn handle(&mut self, msg: ReportFloodingPeer, _ctx: &mut Context<Self>) {
+        self.sessions
+            .decrease_peer_quality(msg.address, FLOODING_PEER_PENALTY);
+        debug!(
+            "Quality of flooding peer {} decreased to {}",
+            msg.address,
+            self.sessions.get_peer_quality(msg.address)
+        );
+    }
+}
+
+/// Handler for ReportInvalidBlock message
+impl Handler<ReportInvalidBlock> for SessionsManager {
+    type Result = ();
+
+    fn handle(&mut self, msg: ReportInvalidBlock, _ctx: &mut Context<Self>) {
+        self.sessions
+            .decrease_peer_quality(msg.address, INVALID_BLOCK_PENALTY);
+        debug!(
+            "Quality of peer {} decreased to {} after sending a malformed block",
+            msg.address,
+            self.sessions.get_peer_quality(msg.address)
+        );
+    }
+}
+
+/// Handler for ReportPreferredPeer message
+impl Handler<ReportPreferredPeer> for SessionsManager {
+    type Result = ();
+
+    fn handle(&mut self, msg: ReportPreferredPeer, _ctx: &mut Context<Self>) {
+        self.sessions
+            .set_preferred_peer(msg.address, msg.listening_address);
+    }
+}
+
+/// Handler for ReportOversizedMessage message
+impl Handler<ReportOversizedMessage> for SessionsManager {
+    type Result = ();
+
+    fn handle(&mut self, msg: ReportOversizedMessage, _ctx: &mut Context<Self>) {
+        self.sessions
+            .decrease_peer_quality(msg.address, OVERSIZED_MESSAGE_PENALTY);
+        debug!(
+            "Quality of peer {} decreased to {} after sending an oversized message",
+            msg.address,
+            self.sessions.get_peer_quality(msg.address)
+        );
+    }
+}
+
+/// Handler for ReportPeerLatency message
+impl Handler<ReportPeerLatency> for SessionsManager {
+    type Result = ();
+
+    fn handle(&mut self, msg: ReportPeerLatency, _ctx: &mut Context<Self>) {
+        self.sessions.set_peer_latency(msg.address, msg.latency);
+    }
+}
+
+/// Handler for SetExternalAddress message
+impl Handler<SetExternalAddress> for SessionsManager {
+    type Result = ();
+
+    fn handle(&mut self, msg: SetExternalAddress, _ctx: &mut Context<Self>) {
+        if self.external_address != msg.address {
+            match msg.address {
+                Some(address) => info!("Advertising external address {} to peers", address),
+                None => info!("No longer advertising an external address to peers"),
+            }
+        }
+        self.external_address = msg.address;
+    }
+}
+
+/// Handler for GetConsolidatedSessionsCount message
+impl Handler<GetConsolidatedSessionsCount> for SessionsManager {
+    type Result = MessageResult<GetConsolidatedSessionsCount>;
+
+    fn handle(
+        &mut self,
+        _msg: GetConsolidatedSessionsCount,
+        _ctx: &mut Context<Self>,
+    ) -> Self::Result {
+        MessageResult(self.sessions.get_all_consolidated_sessions().count())
+    }
+}
+
+/// Handler for GetSessionsCount message
+impl Handler<GetSessionsCount> for SessionsManager {
+    type Result = MessageResult<GetSessionsCount>;
+
+    fn handle(&mut self, _msg: GetSessionsCount, _ctx: &mut Context<Self>) -> Self::Result {
+        MessageResult(SessionsCount {
+            inbound: self.sessions.inbound_consolidated.collection.len(),
+            outbound: self.sessions.outbound_consolidated.collection.len(),
+            outbound_consensus: self
+                .sessions
+                .outbound_consolidated_consensus
+                .collection
+                .len(),
+        })
+    }
+}
+
+/// Handler for GetAccessList message
+impl Handler<GetAccessList> for SessionsManager {
+    type Result = MessageResult<GetAccessList>;
+
+    fn handle(&mut self, _msg: GetAccessList, _ctx: &mut Context<Self>) -> Self::Result {
+        MessageResult(self.sessions.access_list.clone())
+    }
+}
+
+/// Handler for SetAccessList message
+impl Handler<SetAccessList> for SessionsManager {
+    type Result = ();
+
+    fn handle(&mut self, msg: SetAccessList, _ctx: &mut Context<Self>) {
+        info!(
+            "Access list changed: {} whitelisted and {} blacklisted IP ranges",
+            msg.access_list.whitelist.len(),
+            msg.access_list.blacklist.len()
+        );
+        self.sessions.set_access_list(msg.access_list);
+
+        for (address, session) in self.sessions.get_disallowed_sessions() {
+            debug!(
+                "Closing session with {}: not allowed by the access list",
+                address
+            );
+            session.do_send(CloseSession);
+        }
+    }
+}
+
+/// Handler for GetPeersLatency message
+impl Handler<GetPeersLatency> for SessionsManager {
+    type Result = MessageResult<GetPeersLatency>;
+
+    fn handle(&mut self, _msg: GetPeersLatency, _ctx: &mut Context<Self>) -> Self::Result {
+        MessageResult(
+            self.sessions
+                .get_consolidated_peers_latency()
+                .into_iter()
+                .map(|(address, latency)| PeerLatency { address, latency })
+                .collect(),
+        )
+    }
+}
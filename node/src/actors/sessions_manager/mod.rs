@@ -15,14 +15,14 @@ use crate::actors::{
     connections_manager::ConnectionsManager,
     epoch_manager::EpochManager,
     messages::{
-        Anycast, CloseSession, GetRandomPeer, OutboundTcpConnect, PeersBeacons,
-        PeersSocketAddrResult, SendGetPeers, Subscribe,
+        Anycast, CloseSession, GetRandomPeer, OutboundHostConnect, OutboundTcpConnect,
+        PeersBeacons, PeersSocketAddrResult, SendGetPeers, Subscribe,
     },
     peers_manager::PeersManager,
     session::Session,
 };
 use std::collections::{HashMap, HashSet};
-use witnet_data_structures::chain::CheckpointBeacon;
+use witnet_data_structures::chain::{CheckpointBeacon, Hash};
 
 mod actor;
 mod handlers;
@@ -34,6 +34,19 @@ pub struct SessionsManager {
     sessions: Sessions<Addr<Session>>,
     // List of beacons of outbound sessions
     beacons: HashMap<SocketAddr, Option<CheckpointBeacon>>,
+    // Hosts dialed when there is no eligible known peer
+    seed_hosts: Vec<String>,
+    // Index of the next seed host to dial
+    next_seed_host: usize,
+    // External address of the server mapped in the NAT gateway, advertised instead of the server
+    // address
+    external_address: Option<SocketAddr>,
+    // Do not advertise the server address, because the outbound connections go through a proxy
+    hide_server_address: bool,
+    // The node is shutting down, so no new sessions are accepted
+    shutting_down: bool,
+    // Hash of the consensus constants, announced to the peers in the handshake
+    consensus_constants: Hash,
 }
 
 impl SessionsManager {
@@ -41,6 +54,11 @@ impl SessionsManager {
     fn bootstrap_peers(&self, ctx: &mut Context<Self>, bootstrap_peers_period: Duration) {
         // Schedule the bootstrap with a given period
         ctx.run_later(bootstrap_peers_period, move |act, ctx| {
+            // No more peers are dialed once the node is shutting down
+            if act.shutting_down {
+                return;
+            }
+
             info!(
                 "{} Inbound: {} | Outbound: {}",
                 Cyan.bold().paint("[Sessions]"),
@@ -51,11 +69,27 @@ impl SessionsManager {
             );
             trace!("{:#?}", act.sessions.show_ips());
 
-            // Check if bootstrap is needed
-            if act.sessions.is_outbound_bootstrap_needed() {
-                // Get peers manager address
-                let peers_manager_addr = System::current().registry().get::<PeersManager>();
-
+            // Dial as many peers as outbound sessions are missing to reach the outbound limit
+            let num_missing_sessions = act.sessions.get_num_missing_outbound_sessions();
+            if num_missing_sessions > 0 {
+                debug!(
+                    "Dialing {} peers to reach the outbound limit",
+                    num_missing_sessions
+                );
+            }
+            // Peers with a preferred user agent are dialed again before asking for random peers
+            let mut preferred_peers = act.sessions.get_preferred_outbound_candidates();
+            preferred_peers.truncate(num_missing_sessions);
+            for address in preferred_peers.iter().cloned() {
+                debug!(
+                    "Trying to create a new outbound connection to preferred peer {}",
+                    address
+                );
+                ConnectionsManager::from_registry().do_send(OutboundTcpConnect { address });
+            }
+            // Get peers manager address
+            let peers_manager_addr = System::current().registry().get::<PeersManager>();
+            for _ in preferred_peers.len()..num_missing_sessions {
                 // Start chain of actions
                 peers_manager_addr
                     // Send GetPeer message to peers manager actor
@@ -81,6 +115,8 @@ impl SessionsManager {
 
                         actix::fut::ok(())
                     })
+                    // Fall back to the seed hosts if there was no eligible peer
+                    .map_err(|(), act, _ctx| act.dial_next_seed_host())
                     .wait(ctx);
             }
             // Reschedule the bootstrap peers task
@@ -101,6 +137,23 @@ impl SessionsManager {
         });
     }
 
+    /// Dial the next seed host, going through them in a round-robin fashion
+    fn dial_next_seed_host(&mut self) {
+        if self.seed_hosts.is_empty() {
+            return;
+        }
+
+        let host = self.seed_hosts[self.next_seed_host % self.seed_hosts.len()].clone();
+        self.next_seed_host = self.next_seed_host.wrapping_add(1);
+        debug!(
+            "Trying to create a new outbound connection to seed host {}",
+            host
+        );
+
+        let connections_manager_addr = System::current().registry().get::<ConnectionsManager>();
+        connections_manager_addr.do_send(OutboundHostConnect { host });
+    }
+
     /// Method to process peers manager RequestPeer response
     fn process_get_peer_response(
         &mut self,
@@ -1,8 +1,124 @@
+use witnet_data_structures::chain::{Epoch, Hash};
+
 /// Constant to specify the peers key for the storage
 pub static PEERS_KEY: &'static [u8] = b"peers";
 
+/// Constant to specify the key of the transactions of the mempool persisted on shutdown
+pub static MEMPOOL_KEY: &'static [u8] = b"mempool";
+
 /// Constant to specify the chain state key for the storage
 pub static CHAIN_STATE_KEY: &'static [u8] = b"chain";
 
+/// Constant to specify the key of the snapshot of the unspent outputs and the data requests
+pub static CHAIN_SNAPSHOT_KEY: &'static [u8] = b"chain_snapshot_v1";
+
+/// Key of the changes made to the unspent outputs and the data requests by the block of `epoch`
+pub fn chain_update_key(epoch: Epoch) -> String {
+    format!("chain_update_v1_{}", epoch)
+}
+
+/// Key of the header of a block whose body has been pruned
+pub fn block_header_key(hash: Hash) -> String {
+    format!("block_header_v1_{}", hash)
+}
+
 /// Constant to specify the secret key key for the storage
 pub static MASTER_KEY: &'static [u8] = b"master_key";
+
+/// Column family of the blocks, and of the headers of the pruned blocks
+pub const BLOCKS_COLUMN_FAMILY: &str = "blocks";
+
+/// Column family of the reports of the finished data requests, indexed by their output pointer
+pub const TRANSACTIONS_COLUMN_FAMILY: &str = "transactions";
+
+/// Column family of the chain state, its snapshots and updates, and the persisted mempool
+pub const CHAIN_STATE_COLUMN_FAMILY: &str = "chain_state";
+
+/// Column family of the known peers
+pub const PEERS_COLUMN_FAMILY: &str = "peers";
+
+/// Column families of the RocksDB storage backend
+pub const COLUMN_FAMILIES: [&str; 4] = [
+    BLOCKS_COLUMN_FAMILY,
+    TRANSACTIONS_COLUMN_FAMILY,
+    CHAIN_STATE_COLUMN_FAMILY,
+    PEERS_COLUMN_FAMILY,
+];
+
+/// Column family of a key serialized by the `storage_mngr`, `None` for the keys kept in the
+/// default column family (e.g. the master key)
+pub fn column_family(key: &[u8]) -> Option<&'static str> {
+    if let Ok(bytes) = serde_json::from_slice::<Vec<u8>>(key) {
+        if bytes.as_slice() == PEERS_KEY {
+            Some(PEERS_COLUMN_FAMILY)
+        } else if [CHAIN_STATE_KEY, CHAIN_SNAPSHOT_KEY, MEMPOOL_KEY].contains(&bytes.as_slice()) {
+            Some(CHAIN_STATE_COLUMN_FAMILY)
+        } else if bytes.len() == 32 {
+            // Inventory items are keyed by their hash
+            Some(BLOCKS_COLUMN_FAMILY)
+        } else {
+            None
+        }
+    } else if let Ok(string) = serde_json::from_slice::<String>(key) {
+        if string.starts_with("block_header_v1_") {
+            Some(BLOCKS_COLUMN_FAMILY)
+        } else if string.starts_with("chain_update_v1_") {
+            Some(CHAIN_STATE_COLUMN_FAMILY)
+        } else if string.contains(':') {
+            // Data request reports are keyed by the output pointer of the data request
+            Some(TRANSACTIONS_COLUMN_FAMILY)
+        } else {
+            None
+        }
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use witnet_data_structures::chain::OutputPointer;
+
+    fn serialized<K: serde::Serialize>(key: &K) -> Vec<u8> {
+        serde_json::to_vec(key).unwrap()
+    }
+
+    #[test]
+    fn keys_column_families() {
+        let hash = Hash::SHA256([1; 32]);
+        let block_key = match hash {
+            Hash::SHA256(x) => x.to_vec(),
+        };
+        let dr_pointer = OutputPointer {
+            transaction_id: hash,
+            output_index: 0,
+        };
+
+        assert_eq!(
+            column_family(&serialized(&block_key)),
+            Some(BLOCKS_COLUMN_FAMILY)
+        );
+        assert_eq!(
+            column_family(&serialized(&block_header_key(hash))),
+            Some(BLOCKS_COLUMN_FAMILY)
+        );
+        assert_eq!(
+            column_family(&serialized(&dr_pointer)),
+            Some(TRANSACTIONS_COLUMN_FAMILY)
+        );
+        assert_eq!(
+            column_family(&serialized(&CHAIN_STATE_KEY)),
+            Some(CHAIN_STATE_COLUMN_FAMILY)
+        );
+        assert_eq!(
+            column_family(&serialized(&chain_update_key(10))),
+            Some(CHAIN_STATE_COLUMN_FAMILY)
+        );
+        assert_eq!(
+            column_family(&serialized(&PEERS_KEY)),
+            Some(PEERS_COLUMN_FAMILY)
+        );
+        assert_eq!(column_family(&serialized(&MASTER_KEY)), None);
+    }
+}
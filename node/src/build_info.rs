@@ -0,0 +1,125 @@
+//! Build metadata embedded at compile time
+//!
+//! This information is exposed through the `getVersion` JSON-RPC method and
+//! the user agent announced in `Version` messages, so that crawlers and
+//! support can tell deployments apart.
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use witnet_data_structures::{
+    builders::{PROTOCOL_VERSION, USER_AGENT},
+    chain::{ConsensusConstants, Hash, Hashable},
+};
+
+/// Version of the node crate
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Short hash of the git commit the node was built from
+pub const GIT_HASH: &str = env!("WITNET_GIT_HASH");
+
+/// Comma-separated list of the cargo features enabled at build time
+pub const FEATURES: &str = env!("WITNET_FEATURES");
+
+/// Comma-separated list of `name=version` pairs of the workspace crates the node is built with
+pub const CRATE_VERSIONS: &str = env!("WITNET_CRATE_VERSIONS");
+
+/// Build and protocol information of a running node
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BuildInfo {
+    /// Version of the node crate
+    pub version: String,
+    /// Short hash of the git commit the node was built from
+    pub git_hash: String,
+    /// Cargo features enabled at build time
+    pub features: Vec<String>,
+    /// Versions of the workspace crates the node is built with, by crate name
+    pub crate_versions: BTreeMap<String, String>,
+    /// Version of the P2P protocol
+    pub protocol_version: u32,
+    /// User agent announced to other peers
+    pub user_agent: String,
+    /// Hash of the consensus constants in use
+    pub consensus_constants_hash: Hash,
+}
+
+impl BuildInfo {
+    /// Collect the build information along with the hash of the given consensus constants
+    pub fn new(consensus_constants: &ConsensusConstants, user_agent_suffix: &str) -> Self {
+        BuildInfo {
+            version: VERSION.to_string(),
+            git_hash: GIT_HASH.to_string(),
+            features: features(),
+            crate_versions: crate_versions(),
+            protocol_version: PROTOCOL_VERSION,
+            user_agent: user_agent(user_agent_suffix),
+            consensus_constants_hash: consensus_constants.hash(),
+        }
+    }
+}
+
+/// User agent announced in `Version` messages, e.g. `full-node-desktop-edition/0.2.0-1a2b3c4d5e6f`
+///
+/// Operators can append a custom suffix, separated by a slash.
+pub fn user_agent(suffix: &str) -> String {
+    if suffix.is_empty() {
+        format!("{}/{}-{}", USER_AGENT, VERSION, GIT_HASH)
+    } else {
+        format!("{}/{}-{}/{}", USER_AGENT, VERSION, GIT_HASH, suffix)
+    }
+}
+
+/// List of the cargo features enabled at build time
+pub fn features() -> Vec<String> {
+    FEATURES
+        .split(',')
+        .filter(|feature| !feature.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Versions of the workspace crates the node is built with, by crate name
+pub fn crate_versions() -> BTreeMap<String, String> {
+    CRATE_VERSIONS
+        .split(',')
+        .filter_map(|entry| {
+            let mut parts = entry.splitn(2, '=');
+
+            Some((parts.next()?.to_string(), parts.next()?.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn user_agent_includes_version() {
+        let user_agent = user_agent("");
+
+        assert!(user_agent.starts_with(USER_AGENT));
+        assert!(user_agent.contains(VERSION));
+        assert!(user_agent.ends_with(GIT_HASH));
+    }
+
+    #[test]
+    fn user_agent_with_suffix() {
+        let user_agent = user_agent("my-pool");
+
+        assert!(user_agent.starts_with(USER_AGENT));
+        assert!(user_agent.ends_with(&format!("{}/my-pool", GIT_HASH)));
+    }
+
+    #[test]
+    fn features_have_no_empty_entries() {
+        assert!(features().iter().all(|feature| !feature.is_empty()));
+    }
+    #[test]
+    fn crate_versions_include_dependencies() {
+        let versions = crate_versions();
+
+        assert!(versions.contains_key("witnet_data_structures"));
+        assert!(versions.values().all(|version| !version.is_empty()));
+    }
+}
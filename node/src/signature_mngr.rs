@@ -2,7 +2,7 @@
 //!
 //! This module provides a Signature Manager, which, after being
 //! initialized with a key, can be used repeatedly to sign data with
-//! that key.
+//! that key, and to create VRF proofs with it.
 use actix::prelude::*;
 use failure;
 use failure::bail;
@@ -12,12 +12,12 @@ use log;
 use crate::{actors::storage_keys::MASTER_KEY, storage_mngr};
 
 use witnet_crypto::{
-    key::{ExtendedSK, MasterKeyGen, SignContext, PK, SK},
+    key::{ExtendedSK, MasterKeyGen, PK, SK},
     mnemonic::MnemonicGen,
     signature,
 };
 use witnet_data_structures::chain::{
-    ExtendedSecretKey, Hash, Hashable, KeyedSignature, PublicKey, Signature,
+    ExtendedSecretKey, Hash, Hashable, KeyedSignature, PublicKey, Signature, VrfProof,
 };
 
 /// Start the signature manager
@@ -34,10 +34,14 @@ pub fn set_key(key: SK) -> impl Future<Item = (), Error = failure::Error> {
     addr.send(SetKey(key)).flatten()
 }
 
-/// Sign a piece of data with the stored key.
+/// Sign a piece of data with the stored key, within a signing domain such as
+/// `signature::BLOCK_DOMAIN`.
 ///
 /// This might fail if the manager has not been initialized with a key
-pub fn sign<T>(data: &T) -> impl Future<Item = KeyedSignature, Error = failure::Error>
+pub fn sign<T>(
+    domain: &'static str,
+    data: &T,
+) -> impl Future<Item = KeyedSignature, Error = failure::Error>
 where
     T: Hashable,
 {
@@ -46,7 +50,34 @@ where
         .get::<SignatureManager>();
     let Hash::SHA256(data_hash) = data.hash();
 
-    addr.send(Sign(data_hash.to_vec())).flatten()
+    addr.send(Sign(domain, data_hash.to_vec())).flatten()
+}
+
+/// Create a VRF proof of the hash of a piece of data with the stored key, such as the beacon
+/// of the block whose eligibility is being checked.
+///
+/// This might fail if the manager has not been initialized with a key
+pub fn vrf_prove<T>(data: &T) -> impl Future<Item = VrfProof, Error = failure::Error>
+where
+    T: Hashable,
+{
+    let addr = actix::System::current()
+        .registry()
+        .get::<SignatureManager>();
+    let Hash::SHA256(data_hash) = data.hash();
+
+    addr.send(VrfProve(data_hash.to_vec())).flatten()
+}
+
+/// Get the public key of the stored key, which identifies the node, e.g. as a witness.
+///
+/// This might fail if the manager has not been initialized with a key
+pub fn public_key() -> impl Future<Item = PublicKey, Error = failure::Error> {
+    let addr = actix::System::current()
+        .registry()
+        .get::<SignatureManager>();
+
+    addr.send(GetPublicKey).flatten()
 }
 
 #[derive(Debug, Default)]
@@ -56,13 +87,15 @@ struct SignatureManager {
 
 impl SignatureManager {
     fn set_key(&mut self, key: SK) {
-        let public_key = PK::from_secret_key(&SignContext::signing_only(), &key);
+        let public_key = PK::from_secret_key(signature::secp256k1_context(), &key);
         self.keypair = Some((key, public_key));
     }
 }
 
 struct SetKey(SK);
-struct Sign(Vec<u8>);
+struct Sign(&'static str, Vec<u8>);
+struct VrfProve(Vec<u8>);
+struct GetPublicKey;
 
 fn persist_master_key(master_key: ExtendedSK) -> impl Future<Item = (), Error = failure::Error> {
     let master_key = ExtendedSecretKey::from(master_key);
@@ -128,6 +161,14 @@ impl Message for Sign {
     type Result = Result<KeyedSignature, failure::Error>;
 }
 
+impl Message for VrfProve {
+    type Result = Result<VrfProof, failure::Error>;
+}
+
+impl Message for GetPublicKey {
+    type Result = Result<PublicKey, failure::Error>;
+}
+
 impl Handler<SetKey> for SignatureManager {
     type Result = <SetKey as Message>::Result;
 
@@ -143,10 +184,10 @@ impl Handler<SetKey> for SignatureManager {
 impl Handler<Sign> for SignatureManager {
     type Result = <Sign as Message>::Result;
 
-    fn handle(&mut self, Sign(data): Sign, _ctx: &mut Self::Context) -> Self::Result {
+    fn handle(&mut self, Sign(domain, data): Sign, _ctx: &mut Self::Context) -> Self::Result {
         match self.keypair {
             Some((secret, public)) => {
-                let signature = signature::sign(secret, &data);
+                let signature = signature::sign_tagged(secret, domain, &data);
                 let keyed_signature = KeyedSignature {
                     signature: Signature::from(signature),
                     public_key: PublicKey::from(public),
@@ -158,3 +199,27 @@ impl Handler<Sign> for SignatureManager {
         }
     }
 }
+
+impl Handler<VrfProve> for SignatureManager {
+    type Result = <VrfProve as Message>::Result;
+
+    fn handle(&mut self, VrfProve(data): VrfProve, _ctx: &mut Self::Context) -> Self::Result {
+        match self.keypair {
+            Some((secret, _public)) => VrfProof::new(&secret, &data),
+            None => bail!("Signature Manager cannot create VRF proofs because it contains no key"),
+        }
+    }
+}
+
+impl Handler<GetPublicKey> for SignatureManager {
+    type Result = <GetPublicKey as Message>::Result;
+
+    fn handle(&mut self, _msg: GetPublicKey, _ctx: &mut Self::Context) -> Self::Result {
+        match self.keypair {
+            Some((_secret, public)) => Ok(PublicKey::from(public)),
+            None => {
+                bail!("Signature Manager cannot provide a public key because it contains no key")
+            }
+        }
+    }
+}
@@ -7,7 +7,7 @@ use log;
 use serde;
 use serde_json;
 
-use crate::config_mngr;
+use crate::{actors::storage_keys, config_mngr};
 use witnet_config::config;
 use witnet_storage::{backends, storage};
 
@@ -69,6 +69,75 @@ where
         .and_then(move |key_bytes| addr.send(Delete(key_bytes)).flatten())
 }
 
+/// Get the values whose key is a string starting with `prefix`, along with their keys
+pub fn get_by_prefix<T>(
+    prefix: &str,
+) -> impl Future<Item = Vec<(String, T)>, Error = failure::Error>
+where
+    T: serde::de::DeserializeOwned,
+{
+    let addr = actix::System::current().registry().get::<StorageManager>();
+
+    // Keys are serialized as JSON strings, so the closing quote is not part of the prefix
+    futures::future::result(serde_json::to_vec(prefix))
+        .map_err(|e| as_failure!(e))
+        .and_then(move |mut prefix_bytes| {
+            prefix_bytes.pop();
+            addr.send(GetByPrefix(prefix_bytes)).flatten()
+        })
+        .and_then(|pairs| {
+            pairs
+                .into_iter()
+                .map(|(key, value)| {
+                    let key: String = serde_json::from_slice(&key)?;
+                    let value = serde_json::from_slice(&value)?;
+
+                    Ok((key, value))
+                })
+                .collect::<Result<Vec<_>, serde_json::Error>>()
+                .map_err(|e| as_failure!(e))
+        })
+}
+
+/// Batch of writes to be applied atomically with [`write`](write)
+#[derive(Default)]
+pub struct WriteBatch {
+    batch: storage::WriteBatch,
+}
+
+impl WriteBatch {
+    /// Put a value associated to the key into the storage
+    pub fn put<K, V>(&mut self, key: &K, value: &V) -> Result<(), failure::Error>
+    where
+        K: serde::Serialize,
+        V: serde::Serialize,
+    {
+        let key_bytes = serde_json::to_vec(key).map_err(|e| as_failure!(e))?;
+        let value_bytes = serde_json::to_vec(value).map_err(|e| as_failure!(e))?;
+        self.batch.put(key_bytes, value_bytes);
+
+        Ok(())
+    }
+
+    /// Delete value associated to key
+    pub fn delete<K>(&mut self, key: &K) -> Result<(), failure::Error>
+    where
+        K: serde::Serialize,
+    {
+        let key_bytes = serde_json::to_vec(key).map_err(|e| as_failure!(e))?;
+        self.batch.delete(key_bytes);
+
+        Ok(())
+    }
+}
+
+/// Apply all the writes of a batch, or none of them if it fails
+pub fn write(batch: WriteBatch) -> impl Future<Item = (), Error = failure::Error> {
+    let addr = actix::System::current().registry().get::<StorageManager>();
+
+    addr.send(Write(batch.batch)).flatten()
+}
+
 struct StorageManager {
     backend: Box<dyn storage::Storage>,
 }
@@ -92,7 +161,7 @@ impl Actor for StorageManager {
             .and_then(|conf, act, _ctx| {
                 let storage_conf = &conf.storage;
                 fut::result(create_appropriate_backend(storage_conf).map(|backend| {
-                    act.backend = backend;
+                    act.backend = with_chaos(backend);
                     log::info!(
                         "Configured {:#?} as the storage backend",
                         storage_conf.backend
@@ -156,6 +225,38 @@ impl Handler<Delete> for StorageManager {
     }
 }
 
+struct Write(storage::WriteBatch);
+
+impl Message for Write {
+    type Result = Result<(), failure::Error>;
+}
+
+impl Handler<Write> for StorageManager {
+    type Result = <Write as Message>::Result;
+
+    fn handle(&mut self, Write(batch): Write, _ctx: &mut Self::Context) -> Self::Result {
+        self.backend.write_batch(batch)
+    }
+}
+
+struct GetByPrefix(Vec<u8>);
+
+impl Message for GetByPrefix {
+    type Result = Result<Vec<(Vec<u8>, Vec<u8>)>, failure::Error>;
+}
+
+impl Handler<GetByPrefix> for StorageManager {
+    type Result = <GetByPrefix as Message>::Result;
+
+    fn handle(
+        &mut self,
+        GetByPrefix(prefix): GetByPrefix,
+        _ctx: &mut Self::Context,
+    ) -> Self::Result {
+        Ok(self.backend.prefix_iterator(&prefix)?.collect())
+    }
+}
+
 macro_rules! encrypted_backend {
     ($backend:expr, $password_opt:expr) => {
         if let Some(password) = $password_opt {
@@ -167,6 +268,23 @@ macro_rules! encrypted_backend {
     };
 }
 
+/// Make the storage operations fail randomly if chaos testing is enabled
+#[cfg(feature = "chaos")]
+fn with_chaos(backend: Box<dyn storage::Storage>) -> Box<dyn storage::Storage> {
+    match witnet_util::chaos::Chaos::global() {
+        Some(chaos) => {
+            log::warn!("Chaos testing: storage operations will fail randomly");
+            Box::new(backends::chaos::Backend::new(backend, chaos))
+        }
+        None => backend,
+    }
+}
+
+#[cfg(not(feature = "chaos"))]
+fn with_chaos(backend: Box<dyn storage::Storage>) -> Box<dyn storage::Storage> {
+    backend
+}
+
 fn create_appropriate_backend(
     conf: &config::Storage,
 ) -> Result<Box<dyn storage::Storage>, failure::Error> {
@@ -179,10 +297,26 @@ fn create_appropriate_backend(
         )),
         config::StorageBackend::RocksDB => {
             let path = conf.db_path.as_path();
-
-            backends::rocksdb::Backend::open_default(path)
-                .map(|backend| encrypted_backend!(backend, passwd))
-                .map_err(|e| as_failure!(e))
+            let options = backends::rocksdb::Options {
+                block_cache_bytes: conf.block_cache_bytes,
+                write_buffer_bytes: conf.write_buffer_bytes,
+                compaction_style: match conf.compaction_style {
+                    config::CompactionStyle::Level => backends::rocksdb::CompactionStyle::Level,
+                    config::CompactionStyle::Universal => {
+                        backends::rocksdb::CompactionStyle::Universal
+                    }
+                    config::CompactionStyle::Fifo => backends::rocksdb::CompactionStyle::Fifo,
+                },
+                max_background_compactions: conf.max_background_compactions,
+            };
+
+            backends::rocksdb::ColumnFamilyBackend::open(
+                path,
+                &options,
+                &storage_keys::COLUMN_FAMILIES,
+                storage_keys::column_family,
+            )
+            .map(|backend| encrypted_backend!(backend, passwd))
         }
     }
 }
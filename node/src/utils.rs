@@ -74,15 +74,18 @@ pub fn validate_tally_output_uniqueness(outputs: &[Output]) -> bool {
     count_tally_outputs(outputs) == 1
 }
 
-/// Given a list of elements, return the most common one. In case of tie, return `None`.
-pub fn mode_consensus<'a, I, V>(pb: I) -> Option<&'a V>
+/// Given a list of elements, return the most common one if it is at least `threshold` percent of
+/// the elements. In case of tie, return `None`.
+pub fn mode_consensus<'a, I, V>(pb: I, threshold: u32) -> Option<&'a V>
 where
     I: Iterator<Item = &'a V>,
     V: Eq + Hash,
 {
     let mut bp = HashMap::new();
+    let mut total: usize = 0;
     for k in pb {
         *bp.entry(k).or_insert(0) += 1;
+        total += 1;
     }
 
     let mut bpv: Vec<_> = bp.into_iter().collect();
@@ -93,7 +96,11 @@ where
         // In case of tie, no consensus
         None
     } else {
-        // Otherwise, the first element is the most common
-        bpv.into_iter().map(|(k, _count)| k).next()
+        // Otherwise, the first element is the most common, which is the consensus if it has
+        // enough support
+        bpv.into_iter()
+            .next()
+            .filter(|(_k, count)| count * 100 >= threshold as usize * total)
+            .map(|(k, _count)| k)
     }
 }
@@ -4,25 +4,33 @@ use witnet_node::utils::mode_consensus;
 fn test_mode_consensus() {
     // The mode consensus function selects the most common item from a list
     let v = vec![1, 1, 2, 2, 3, 3, 3];
-    let c = mode_consensus(v.iter());
+    let c = mode_consensus(v.iter(), 0);
     assert_eq!(c, Some(&3));
 
     // When there is only one element, that element is the mode
     let v = vec![3, 3, 3];
-    let c = mode_consensus(v.iter());
+    let c = mode_consensus(v.iter(), 0);
     assert_eq!(c, Some(&3));
 
     let v = vec![3];
-    let c = mode_consensus(v.iter());
+    let c = mode_consensus(v.iter(), 0);
     assert_eq!(c, Some(&3));
 
     // But when there is a tie, there is no consensus
     let v = vec![2, 2, 2, 3, 3, 3];
-    let c = mode_consensus(v.iter());
+    let c = mode_consensus(v.iter(), 0);
     assert_eq!(c, None);
 
     // Similarly, when there are no elements, there is no consensus
     let v: Vec<i32> = vec![];
-    let c = mode_consensus(v.iter());
+    let c = mode_consensus(v.iter(), 0);
     assert_eq!(c, None);
+
+    // The most common item must also reach the threshold percentage of the items
+    let v = vec![1, 2, 2, 3, 3, 3];
+    assert_eq!(mode_consensus(v.iter(), 50), Some(&3));
+    assert_eq!(mode_consensus(v.iter(), 51), None);
+
+    let v = vec![3, 3, 3];
+    assert_eq!(mode_consensus(v.iter(), 100), Some(&3));
 }
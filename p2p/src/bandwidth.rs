@@ -0,0 +1,169 @@
+//! Library for limiting the bandwidth used by the sessions
+//!
+//! Every session has a token bucket for each direction, filled at the configured rate of bytes
+//! per second and holding up to one second worth of bytes. Reading from or writing to the
+//! connection takes tokens from the bucket, and waits when it is empty. The buckets for the total
+//! bandwidth are shared by all the sessions, so that many peers cannot saturate the connection of
+//! the node either.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Token bucket limiting a transfer rate, in bytes per second
+#[derive(Clone, Debug)]
+pub struct TokenBucket {
+    /// Bytes added per second, `0` means no limit
+    rate: u64,
+    /// Bytes that can be transferred right now
+    tokens: f64,
+    /// Last time the bucket was filled
+    updated: Instant,
+}
+
+impl TokenBucket {
+    /// Create a full bucket for `rate` bytes per second, `0` means no limit
+    pub fn new(rate: u64, now: Instant) -> Self {
+        TokenBucket {
+            rate,
+            tokens: rate as f64,
+            updated: now,
+        }
+    }
+    /// Method to check if the bucket does not limit the rate
+    pub fn is_unlimited(&self) -> bool {
+        self.rate == 0
+    }
+    /// Method to get the number of bytes that can be transferred right now
+    pub fn available(&mut self, now: Instant) -> u64 {
+        if self.is_unlimited() {
+            return u64::max_value();
+        }
+        self.refill(now);
+
+        self.tokens as u64
+    }
+    /// Method to get the time to wait until at least one byte can be transferred
+    pub fn wait_time(&mut self, now: Instant) -> Duration {
+        if self.available(now) > 0 {
+            return Duration::from_secs(0);
+        }
+        let missing = 1.0 - self.tokens;
+        let nanos = (missing * 1_000_000_000.0 / self.rate as f64).ceil() as u64;
+
+        Duration::from_nanos(nanos)
+    }
+    /// Method to take the tokens of `bytes` transferred from the bucket
+    pub fn consume(&mut self, bytes: u64) {
+        if !self.is_unlimited() {
+            self.tokens = (self.tokens - bytes as f64).max(0.0);
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        if now <= self.updated {
+            return;
+        }
+        let elapsed = now - self.updated;
+        let added = elapsed.as_secs() as f64 * self.rate as f64
+            + f64::from(elapsed.subsec_nanos()) * self.rate as f64 / 1_000_000_000.0;
+        self.tokens = (self.tokens + added).min(self.rate as f64);
+        self.updated = now;
+    }
+}
+
+/// Token bucket shared by all the sessions
+pub type SharedTokenBucket = Arc<Mutex<TokenBucket>>;
+
+/// Bandwidth limiter of one direction of a session: the bytes transferred are limited by the
+/// bucket of the session and by the bucket shared by all the sessions
+#[derive(Clone, Debug)]
+pub struct BandwidthLimiter {
+    session: TokenBucket,
+    total: Option<SharedTokenBucket>,
+}
+
+impl BandwidthLimiter {
+    /// Create a limiter for `rate` bytes per second, sharing the `total` bucket if any
+    pub fn new(rate: u64, total: Option<SharedTokenBucket>, now: Instant) -> Self {
+        BandwidthLimiter {
+            session: TokenBucket::new(rate, now),
+            total,
+        }
+    }
+    /// Method to get the number of bytes that can be transferred right now, or the time to wait
+    /// if none can
+    pub fn allowance(&mut self, now: Instant) -> Result<u64, Duration> {
+        let mut available = self.session.available(now);
+        let mut wait_time = self.session.wait_time(now);
+        if let Some(total) = &self.total {
+            let mut total = total.lock().unwrap();
+            available = available.min(total.available(now));
+            wait_time = wait_time.max(total.wait_time(now));
+        }
+
+        if available > 0 {
+            Ok(available)
+        } else {
+            Err(wait_time)
+        }
+    }
+    /// Method to record `bytes` transferred
+    pub fn consume(&mut self, bytes: u64) {
+        self.session.consume(bytes);
+        if let Some(total) = &self.total {
+            total.lock().unwrap().consume(bytes);
+        }
+    }
+}
+
+/// Bandwidth limits of the sessions, in bytes per second, `0` means no limit
+#[derive(Clone, Debug, Default)]
+pub struct BandwidthLimits {
+    /// Upload limit of each session
+    pub upload_rate: u64,
+    /// Download limit of each session
+    pub download_rate: u64,
+    /// Upload limit of all the sessions together
+    total_upload: Option<SharedTokenBucket>,
+    /// Download limit of all the sessions together
+    total_download: Option<SharedTokenBucket>,
+}
+
+impl BandwidthLimits {
+    /// Create the limits of each session and of all the sessions together
+    pub fn new(
+        upload_rate: u64,
+        download_rate: u64,
+        total_upload_rate: u64,
+        total_download_rate: u64,
+    ) -> Self {
+        let shared = |rate| {
+            if rate == 0 {
+                None
+            } else {
+                Some(Arc::new(Mutex::new(TokenBucket::new(rate, Instant::now()))))
+            }
+        };
+
+        BandwidthLimits {
+            upload_rate,
+            download_rate,
+            total_upload: shared(total_upload_rate),
+            total_download: shared(total_download_rate),
+        }
+    }
+    /// Method to create the upload limiter of a new session
+    pub fn upload_limiter(&self) -> BandwidthLimiter {
+        BandwidthLimiter::new(self.upload_rate, self.total_upload.clone(), Instant::now())
+    }
+    /// Method to create the download limiter of a new session
+    pub fn download_limiter(&self) -> BandwidthLimiter {
+        BandwidthLimiter::new(
+            self.download_rate,
+            self.total_download.clone(),
+            Instant::now(),
+        )
+    }
+}
@@ -0,0 +1,20 @@
+//! Library for bootstrapping the known peers from DNS seeds
+//!
+//! A DNS seed is a hostname which resolves to the addresses of some reachable peers. The port can
+//! be given along with the hostname; otherwise, the default port of the environment is used.
+
+/// Split a DNS seed into its hostname and port, using `default_port` when the seed has no port
+pub fn seed_host_port(seed: &str, default_port: u16) -> (&str, u16) {
+    let seed = seed.trim();
+    if let Some(index) = seed.rfind(':') {
+        let (host, port) = (&seed[..index], &seed[index + 1..]);
+        // A colon in the host means that the seed is an IPv6 address without port
+        if !host.contains(':') {
+            if let Ok(port) = port.parse() {
+                return (host, port);
+            }
+        }
+    }
+
+    (seed, default_port)
+}
@@ -8,6 +8,12 @@ pub enum SessionsError {
     /// Errors when registering sessions. Max number of peers reached
     #[fail(display = "Register failed. Max number of peers reached")]
     MaxPeersReached,
+    /// Errors when accepting inbound connections. Address not whitelisted by the listener
+    #[fail(display = "Connection refused. Address not whitelisted by the listener")]
+    NotWhitelisted,
+    /// Errors when accepting inbound connections. Address not allowed by the access list
+    #[fail(display = "Connection refused. Address not allowed by the access list")]
+    NotAllowed,
     /// Errors when registering sessions. Address already registered in sessions
     #[fail(display = "Register failed. Address already registered in sessions")]
     AddressAlreadyRegistered,
@@ -17,4 +23,15 @@ pub enum SessionsError {
     /// Errors when updating sessions
     #[fail(display = "Is not an outbound consolidated peer")]
     NotOutboundConsolidatedPeer,
+    /// Errors when negotiating the protocol version. The peer is older than the minimum accepted
+    #[fail(
+        display = "Protocol version {} is older than the minimum accepted {}",
+        version, min_version
+    )]
+    UnsupportedVersion {
+        /// Protocol version announced by the peer
+        version: u32,
+        /// Minimum protocol version accepted
+        min_version: u32,
+    },
 }
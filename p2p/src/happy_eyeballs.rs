@@ -0,0 +1,51 @@
+//! Library for connecting to dual-stack hosts with Happy Eyeballs (RFC 8305)
+//!
+//! When a host resolves to both IPv6 and IPv4 addresses, trying them one after another can stall
+//! for a whole TCP timeout on networks where one of the families is broken. Instead, connection
+//! attempts are staggered: a new one starts every [`CONNECTION_ATTEMPT_DELAY`] while the previous
+//! ones are still pending, the first connection to succeed wins and the rest are cancelled.
+//!
+//! The addresses are interleaved by family, IPv6 first, so that the second attempt already uses
+//! the other family.
+use std::{net::SocketAddr, time::Duration};
+
+/// Delay between the start of two consecutive connection attempts
+pub const CONNECTION_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+/// Order the addresses of a host for connection attempts: duplicates are removed and the
+/// families alternate, starting with IPv6. Addresses of the same family keep their order.
+pub fn interleave_addresses<I>(addresses: I) -> Vec<SocketAddr>
+where
+    I: IntoIterator<Item = SocketAddr>,
+{
+    let mut ipv6 = vec![];
+    let mut ipv4 = vec![];
+    for address in addresses {
+        let family = if address.is_ipv6() {
+            &mut ipv6
+        } else {
+            &mut ipv4
+        };
+        if !family.contains(&address) {
+            family.push(address);
+        }
+    }
+
+    let mut interleaved = Vec::with_capacity(ipv6.len() + ipv4.len());
+    let mut ipv6 = ipv6.into_iter();
+    let mut ipv4 = ipv4.into_iter();
+    loop {
+        match (ipv6.next(), ipv4.next()) {
+            (None, None) => break,
+            (first, second) => interleaved.extend(first.into_iter().chain(second)),
+        }
+    }
+
+    interleaved
+}
+
+/// Delay before starting the connection attempt to the address at position `index` of the
+/// interleaved list
+pub fn attempt_delay(index: usize) -> Duration {
+    CONNECTION_ATTEMPT_DELAY * index as u32
+}
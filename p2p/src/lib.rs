@@ -12,3 +12,19 @@ pub mod peers;
 pub mod sessions;
 
 pub mod error;
+
+pub mod user_agents;
+
+pub mod mdns;
+
+pub mod metrics;
+
+pub mod happy_eyeballs;
+
+pub mod dns_seeds;
+
+pub mod nat_pmp;
+
+pub mod socks5;
+
+pub mod bandwidth;
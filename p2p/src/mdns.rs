@@ -0,0 +1,308 @@
+//! Library for discovering peers in the local network using multicast DNS
+//!
+//! Nodes announce their P2P server as an instance of a DNS-SD service: a `PTR`
+//! record pointing to the instance, a `SRV` record with the port of the server
+//! and, when the server is bound to a specific address, an `A` record with it.
+//! Only the subset of RFC 6762 needed to find other nodes is implemented.
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+/// IPv4 multicast address used by mDNS
+pub const MDNS_IPV4: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+
+/// UDP port used by mDNS
+pub const MDNS_PORT: u16 = 5353;
+
+/// Time to live of the announced records, in seconds
+pub const RECORD_TTL: u32 = 120;
+
+const TYPE_A: u16 = 1;
+const TYPE_PTR: u16 = 12;
+const TYPE_SRV: u16 = 33;
+const CLASS_IN: u16 = 1;
+/// Class of unique records, with the cache-flush bit set
+const CLASS_IN_FLUSH: u16 = 0x8001;
+/// Flags of a response: QR and AA bits set
+const FLAGS_RESPONSE: u16 = 0x8400;
+const FLAG_QR: u16 = 0x8000;
+/// Maximum number of compression pointers followed when reading a name
+const MAX_POINTERS: usize = 16;
+
+/// Peer announced by another node
+#[derive(Clone, Debug, PartialEq)]
+pub struct Announcement {
+    /// Full name of the announced service instance
+    pub instance: String,
+    /// Port of the P2P server
+    pub port: u16,
+    /// Address of the P2P server, if announced
+    pub ip: Option<Ipv4Addr>,
+}
+
+impl Announcement {
+    /// Address of the announced P2P server. The source address of the packet is used when the
+    /// announcement does not include an address.
+    pub fn peer_address(&self, source: SocketAddr) -> SocketAddr {
+        let ip = self.ip.map(IpAddr::V4).unwrap_or_else(|| source.ip());
+
+        SocketAddr::new(ip, self.port)
+    }
+}
+
+/// Returns `true` if the address belongs to the local network: loopback, private or link-local
+/// addresses. Peers announced with any other address are ignored.
+pub fn is_local_address(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => ip.is_loopback() || ip.is_private() || ip.is_link_local(),
+        IpAddr::V6(ip) => {
+            let first_segment = ip.segments()[0];
+            // Unique local (fc00::/7) and link-local (fe80::/10) ranges
+            ip.is_loopback() || first_segment & 0xfe00 == 0xfc00 || first_segment & 0xffc0 == 0xfe80
+        }
+    }
+}
+
+/// Build a query for the instances of `service`, e.g. `_witnet._tcp.local`
+pub fn build_query(service: &str) -> Vec<u8> {
+    let mut packet = header(0, 1, 0, 0);
+    write_name(&mut packet, service);
+    write_u16(&mut packet, TYPE_PTR);
+    write_u16(&mut packet, CLASS_IN);
+
+    packet
+}
+
+/// Build an announcement of the instance `instance` of `service`, listening at `server_addr`.
+///
+/// The address is only included if it is a specific IPv4 address.
+pub fn build_announcement(service: &str, instance: &str, server_addr: SocketAddr) -> Vec<u8> {
+    let instance_name = format!("{}.{}", instance, service);
+    let host_name = format!("{}.local", instance);
+    let ip = match server_addr.ip() {
+        IpAddr::V4(ip) if !ip.is_unspecified() => Some(ip),
+        _ => None,
+    };
+
+    let mut packet = header(FLAGS_RESPONSE, 0, 1, if ip.is_some() { 2 } else { 1 });
+
+    // PTR record: service -> instance
+    let mut rdata = vec![];
+    write_name(&mut rdata, &instance_name);
+    write_record(&mut packet, service, TYPE_PTR, CLASS_IN, &rdata);
+
+    // SRV record: instance -> host and port
+    let mut rdata = vec![];
+    write_u16(&mut rdata, 0);
+    write_u16(&mut rdata, 0);
+    write_u16(&mut rdata, server_addr.port());
+    write_name(&mut rdata, &host_name);
+    write_record(
+        &mut packet,
+        &instance_name,
+        TYPE_SRV,
+        CLASS_IN_FLUSH,
+        &rdata,
+    );
+
+    // A record: host -> address
+    if let Some(ip) = ip {
+        write_record(
+            &mut packet,
+            &host_name,
+            TYPE_A,
+            CLASS_IN_FLUSH,
+            &ip.octets(),
+        );
+    }
+
+    packet
+}
+
+/// Returns `true` if the packet is a query asking for the instances of `service`
+pub fn is_query(packet: &[u8], service: &str) -> bool {
+    parse_query(packet, service).unwrap_or(false)
+}
+
+/// Parse an announcement of an instance of `service`. Returns `None` if the packet is not a
+/// valid response announcing an instance of `service`.
+pub fn parse_announcement(packet: &[u8], service: &str) -> Option<Announcement> {
+    let mut reader = Reader::new(packet);
+    let (flags, questions, records) = reader.header()?;
+    if flags & FLAG_QR == 0 {
+        return None;
+    }
+    for _ in 0..questions {
+        reader.name()?;
+        reader.skip(4)?;
+    }
+
+    let mut instance = None;
+    let mut srv = None;
+    let mut addresses = vec![];
+    for _ in 0..records {
+        let name = reader.name()?;
+        let rtype = reader.u16()?;
+        reader.skip(6)?;
+        let rdlength = usize::from(reader.u16()?);
+        let rdata_end = reader.position + rdlength;
+        match rtype {
+            TYPE_PTR if name.eq_ignore_ascii_case(service) => instance = Some(reader.name()?),
+            TYPE_SRV => {
+                reader.skip(4)?;
+                let port = reader.u16()?;
+                srv = Some((name, port, reader.name()?));
+            }
+            TYPE_A if rdlength == 4 => {
+                let octets = reader.bytes(4)?;
+                let ip = Ipv4Addr::new(octets[0], octets[1], octets[2], octets[3]);
+                addresses.push((name, ip));
+            }
+            _ => {}
+        }
+        reader.position = rdata_end;
+    }
+
+    let instance = instance?;
+    let (_, port, host) = srv.filter(|(name, _, _)| name.eq_ignore_ascii_case(&instance))?;
+    let ip = addresses
+        .into_iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(&host))
+        .map(|(_, ip)| ip);
+
+    Some(Announcement { instance, port, ip })
+}
+
+fn parse_query(packet: &[u8], service: &str) -> Option<bool> {
+    let mut reader = Reader::new(packet);
+    let (flags, questions, _) = reader.header()?;
+    if flags & FLAG_QR != 0 {
+        return Some(false);
+    }
+    for _ in 0..questions {
+        let name = reader.name()?;
+        let qtype = reader.u16()?;
+        reader.skip(2)?;
+        if qtype == TYPE_PTR && name.eq_ignore_ascii_case(service) {
+            return Some(true);
+        }
+    }
+
+    Some(false)
+}
+
+fn header(flags: u16, questions: u16, answers: u16, additional: u16) -> Vec<u8> {
+    let mut packet = vec![];
+    // Transaction ID is always zero in mDNS
+    write_u16(&mut packet, 0);
+    write_u16(&mut packet, flags);
+    write_u16(&mut packet, questions);
+    write_u16(&mut packet, answers);
+    write_u16(&mut packet, 0);
+    write_u16(&mut packet, additional);
+
+    packet
+}
+
+fn write_u16(buf: &mut Vec<u8>, value: u16) {
+    buf.extend_from_slice(&value.to_be_bytes());
+}
+
+fn write_name(buf: &mut Vec<u8>, name: &str) {
+    for label in name.split('.').filter(|label| !label.is_empty()) {
+        // Labels are limited to 63 bytes
+        let label = &label.as_bytes()[..label.len().min(63)];
+        buf.push(label.len() as u8);
+        buf.extend_from_slice(label);
+    }
+    buf.push(0);
+}
+
+fn write_record(buf: &mut Vec<u8>, name: &str, rtype: u16, class: u16, rdata: &[u8]) {
+    write_name(buf, name);
+    write_u16(buf, rtype);
+    write_u16(buf, class);
+    buf.extend_from_slice(&RECORD_TTL.to_be_bytes());
+    write_u16(buf, rdata.len() as u16);
+    buf.extend_from_slice(rdata);
+}
+
+/// Cursor over a received packet
+struct Reader<'a> {
+    packet: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(packet: &'a [u8]) -> Self {
+        Reader {
+            packet,
+            position: 0,
+        }
+    }
+
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self
+            .packet
+            .get(self.position..self.position.checked_add(len)?)?;
+        self.position += len;
+
+        Some(bytes)
+    }
+
+    fn skip(&mut self, len: usize) -> Option<()> {
+        self.bytes(len).map(|_| ())
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.bytes(2)
+            .map(|bytes| u16::from(bytes[0]) << 8 | u16::from(bytes[1]))
+    }
+
+    /// Read the header, returning the flags, the number of questions and the total number of
+    /// resource records
+    fn header(&mut self) -> Option<(u16, u16, u32)> {
+        self.skip(2)?;
+        let flags = self.u16()?;
+        let questions = self.u16()?;
+        let records = u32::from(self.u16()?) + u32::from(self.u16()?) + u32::from(self.u16()?);
+
+        Some((flags, questions, records))
+    }
+
+    /// Read a name, following compression pointers
+    fn name(&mut self) -> Option<String> {
+        let mut labels: Vec<String> = vec![];
+        let mut position = self.position;
+        let mut end = None;
+        let mut pointers = 0;
+        loop {
+            let len = *self.packet.get(position)?;
+            match len {
+                0 => {
+                    position += 1;
+                    break;
+                }
+                len if len & 0xC0 == 0xC0 => {
+                    pointers += 1;
+                    if pointers > MAX_POINTERS {
+                        return None;
+                    }
+                    let low = *self.packet.get(position + 1)?;
+                    if end.is_none() {
+                        end = Some(position + 2);
+                    }
+                    position = usize::from(len & 0x3F) << 8 | usize::from(low);
+                }
+                len => {
+                    let start = position + 1;
+                    let label = self.packet.get(start..start + usize::from(len))?;
+                    labels.push(String::from_utf8_lossy(label).into_owned());
+                    position = start + usize::from(len);
+                }
+            }
+        }
+        self.position = end.unwrap_or(position);
+
+        Some(labels.join("."))
+    }
+}
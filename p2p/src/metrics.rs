@@ -0,0 +1,145 @@
+//! Library for accounting the protocol messages exchanged with other peers
+//!
+//! Messages are accounted per command and direction: number of messages,
+//! total bytes and a histogram of message sizes. The sizes are the sizes of
+//! the encoded messages, without the 4-byte length prefix added by the codec.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+/// Upper bounds (inclusive, in bytes) of the buckets of the message size
+/// histograms. Messages bigger than the last bound are counted in an extra
+/// bucket.
+pub const SIZE_BUCKETS: [usize; 8] = [
+    128,
+    512,
+    2 * 1024,
+    8 * 1024,
+    32 * 1024,
+    128 * 1024,
+    512 * 1024,
+    2 * 1024 * 1024,
+];
+
+/// Direction of a protocol message
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Direction {
+    /// Message received from a peer
+    Received,
+    /// Message sent to a peer
+    Sent,
+}
+
+/// Totals of the messages of a command
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct CommandTotals {
+    /// Number of messages
+    pub messages: u64,
+    /// Total size of the messages, in bytes
+    pub bytes: u64,
+    /// Number of messages in each bucket of `SIZE_BUCKETS`, plus one bucket
+    /// for the bigger messages
+    pub size_histogram: Vec<u64>,
+}
+
+impl Default for CommandTotals {
+    fn default() -> Self {
+        CommandTotals {
+            messages: 0,
+            bytes: 0,
+            size_histogram: vec![0; SIZE_BUCKETS.len() + 1],
+        }
+    }
+}
+
+impl CommandTotals {
+    /// Account a message of `size` bytes
+    pub fn record(&mut self, size: usize) {
+        let bucket = SIZE_BUCKETS
+            .iter()
+            .position(|&bound| size <= bound)
+            .unwrap_or_else(|| SIZE_BUCKETS.len());
+
+        self.messages += 1;
+        self.bytes += size as u64;
+        self.size_histogram[bucket] += 1;
+    }
+
+    /// Add the totals of another command
+    pub fn merge(&mut self, other: &CommandTotals) {
+        self.messages += other.messages;
+        self.bytes += other.bytes;
+        for (count, other_count) in self
+            .size_histogram
+            .iter_mut()
+            .zip(other.size_histogram.iter())
+        {
+            *count += other_count;
+        }
+    }
+}
+
+/// Totals of the protocol messages exchanged with all the peers, by command
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct NetworkTotals {
+    /// Upper bounds of the buckets of the size histograms
+    pub size_buckets: Vec<usize>,
+    /// Totals of the received messages
+    pub received: BTreeMap<String, CommandTotals>,
+    /// Totals of the sent messages
+    pub sent: BTreeMap<String, CommandTotals>,
+    /// Number of handshakes rejected because the peer announced an unsupported protocol
+    /// version, by version. Used to decide when old versions can be deprecated.
+    pub rejected_versions: BTreeMap<u32, u64>,
+}
+
+impl Default for NetworkTotals {
+    fn default() -> Self {
+        NetworkTotals {
+            size_buckets: SIZE_BUCKETS.to_vec(),
+            received: BTreeMap::new(),
+            sent: BTreeMap::new(),
+            rejected_versions: BTreeMap::new(),
+        }
+    }
+}
+
+impl NetworkTotals {
+    /// Account a message of `size` bytes with command `command`
+    pub fn record(&mut self, direction: Direction, command: &str, size: usize) {
+        let totals = match direction {
+            Direction::Received => &mut self.received,
+            Direction::Sent => &mut self.sent,
+        };
+
+        match totals.get_mut(command) {
+            Some(command_totals) => command_totals.record(size),
+            None => {
+                let mut command_totals = CommandTotals::default();
+                command_totals.record(size);
+                totals.insert(command.to_string(), command_totals);
+            }
+        }
+    }
+
+    /// Account a handshake rejected because the peer announced protocol version `version`
+    pub fn record_rejected_version(&mut self, version: u32) {
+        *self.rejected_versions.entry(version).or_insert(0) += 1;
+    }
+
+    /// Totals of all the commands in one direction
+    pub fn total(&self, direction: Direction) -> CommandTotals {
+        let totals = match direction {
+            Direction::Received => &self.received,
+            Direction::Sent => &self.sent,
+        };
+
+        totals
+            .values()
+            .fold(CommandTotals::default(), |mut total, command_totals| {
+                total.merge(command_totals);
+                total
+            })
+    }
+}
@@ -0,0 +1,150 @@
+//! Library for mapping the P2P port in the NAT gateway using NAT-PMP
+//!
+//! Nodes behind a NAT cannot accept inbound connections unless the gateway forwards the P2P port
+//! to them. With NAT-PMP (RFC 6886), the node asks the gateway for its external address and for a
+//! mapping of the P2P port, which expires after a lifetime and must be renewed before that.
+//! Only the requests needed to map a TCP port are implemented.
+
+use std::net::Ipv4Addr;
+
+use failure::Fail;
+
+/// UDP port where the gateway listens for NAT-PMP requests
+pub const NAT_PMP_PORT: u16 = 5351;
+
+/// Lifetime requested for the mappings, in seconds, as recommended by RFC 6886
+pub const DEFAULT_MAPPING_LIFETIME: u32 = 7200;
+
+const VERSION: u8 = 0;
+const OPCODE_EXTERNAL_ADDRESS: u8 = 0;
+const OPCODE_MAP_TCP: u8 = 2;
+/// Responses have the opcode of their request plus 128
+const OPCODE_RESPONSE: u8 = 128;
+
+/// The error type for the NAT-PMP responses
+#[derive(Debug, PartialEq, Fail)]
+pub enum NatPmpError {
+    /// The response is too short or has an unknown version
+    #[fail(display = "Malformed NAT-PMP response")]
+    Malformed,
+    /// The response does not belong to the request
+    #[fail(display = "Unexpected NAT-PMP response with opcode {}", opcode)]
+    UnexpectedOpcode {
+        /// Opcode of the response
+        opcode: u8,
+    },
+    /// The gateway refused the request, e.g. because NAT-PMP is disabled
+    #[fail(display = "NAT-PMP request failed with result code {}", code)]
+    ResultCode {
+        /// Result code of the response: 1 unsupported version, 2 not authorized, 3 network
+        /// failure, 4 out of resources, 5 unsupported opcode
+        code: u16,
+    },
+}
+
+/// External address of the gateway
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ExternalAddress {
+    /// Seconds since the gateway started or reset its mappings
+    pub epoch: u32,
+    /// External IPv4 address
+    pub address: Ipv4Addr,
+}
+
+/// Port mapping granted by the gateway
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Mapping {
+    /// Seconds since the gateway started or reset its mappings
+    pub epoch: u32,
+    /// Local port
+    pub internal_port: u16,
+    /// Port of the external address forwarded to the local port, which may differ from the
+    /// requested one
+    pub external_port: u16,
+    /// Seconds until the mapping expires
+    pub lifetime: u32,
+}
+
+/// Build a request for the external address of the gateway
+pub fn external_address_request() -> Vec<u8> {
+    vec![VERSION, OPCODE_EXTERNAL_ADDRESS]
+}
+
+/// Build a request to map `external_port` of the gateway to the local `internal_port` for
+/// `lifetime` seconds. A lifetime of `0` deletes the mapping.
+pub fn tcp_mapping_request(internal_port: u16, external_port: u16, lifetime: u32) -> Vec<u8> {
+    let mut request = vec![VERSION, OPCODE_MAP_TCP, 0, 0];
+    request.extend_from_slice(&internal_port.to_be_bytes());
+    request.extend_from_slice(&external_port.to_be_bytes());
+    request.extend_from_slice(&lifetime.to_be_bytes());
+
+    request
+}
+
+/// Parse the response to an external address request
+pub fn parse_external_address_response(response: &[u8]) -> Result<ExternalAddress, NatPmpError> {
+    let epoch = parse_header(response, OPCODE_EXTERNAL_ADDRESS, 12)?;
+
+    Ok(ExternalAddress {
+        epoch,
+        address: Ipv4Addr::new(response[8], response[9], response[10], response[11]),
+    })
+}
+
+/// Parse the response to a TCP mapping request
+pub fn parse_mapping_response(response: &[u8]) -> Result<Mapping, NatPmpError> {
+    let epoch = parse_header(response, OPCODE_MAP_TCP, 16)?;
+
+    Ok(Mapping {
+        epoch,
+        internal_port: read_u16(&response[8..]),
+        external_port: read_u16(&response[10..]),
+        lifetime: read_u32(&response[12..]),
+    })
+}
+
+/// Find the default gateway in a Linux routing table, as found in `/proc/net/route`: the gateway
+/// of the route whose destination and mask are `0.0.0.0`
+pub fn parse_default_gateway(route_table: &str) -> Option<Ipv4Addr> {
+    route_table.lines().skip(1).find_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        match (fields.get(1), fields.get(2), fields.get(7)) {
+            (Some(&"00000000"), Some(gateway), Some(&"00000000")) => {
+                // The addresses are hex-encoded in host byte order, which is little endian in
+                // every platform this file is read on
+                let gateway = u32::from_str_radix(gateway, 16).ok()?;
+                if gateway == 0 {
+                    None
+                } else {
+                    Some(Ipv4Addr::from(gateway.to_le_bytes()))
+                }
+            }
+            _ => None,
+        }
+    })
+}
+
+/// Check the common header of the responses and return their epoch
+fn parse_header(response: &[u8], request_opcode: u8, length: usize) -> Result<u32, NatPmpError> {
+    if response.len() < length || response[0] != VERSION {
+        Err(NatPmpError::Malformed)?
+    }
+    let opcode = response[1];
+    if opcode != OPCODE_RESPONSE + request_opcode {
+        Err(NatPmpError::UnexpectedOpcode { opcode })?
+    }
+    let code = read_u16(&response[2..]);
+    if code != 0 {
+        Err(NatPmpError::ResultCode { code })?
+    }
+
+    Ok(read_u32(&response[4..]))
+}
+
+fn read_u16(bytes: &[u8]) -> u16 {
+    u16::from(bytes[0]) << 8 | u16::from(bytes[1])
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from(read_u16(bytes)) << 16 | u32::from(read_u16(&bytes[2..]))
+}
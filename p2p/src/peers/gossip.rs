@@ -0,0 +1,64 @@
+//! Library for limiting the gossip of peer addresses
+//!
+//! Answering every `GetPeers` message lets a peer map the topology of the network by asking
+//! again and again, and accepting every `Peers` message lets it flood the address book with
+//! bogus addresses. Each session answers at most one `GetPeers` message per interval, and
+//! accepts at most one unsolicited `Peers` message per interval. The `Peers` message answering
+//! a `GetPeers` message sent to the peer is always accepted.
+
+/// Gossip of peer addresses exchanged with a peer. Timestamps are in seconds.
+#[derive(Clone, Debug, Default)]
+pub struct GossipLimiter {
+    /// Minimum time between two `GetPeers` messages answered or two unsolicited `Peers` messages
+    /// accepted
+    min_interval: i64,
+    /// Flag to indicate that a `GetPeers` message was sent and not answered yet
+    peers_requested: bool,
+    /// Timestamp of the last `GetPeers` message answered
+    last_get_peers: Option<i64>,
+    /// Timestamp of the last unsolicited `Peers` message accepted
+    last_unsolicited_peers: Option<i64>,
+}
+
+impl GossipLimiter {
+    /// Create a limiter allowing one message of each kind every `min_interval` seconds
+    pub fn new(min_interval: i64) -> Self {
+        GossipLimiter {
+            min_interval,
+            ..GossipLimiter::default()
+        }
+    }
+    /// Method to record a `GetPeers` message sent to the peer
+    pub fn get_peers_sent(&mut self) {
+        self.peers_requested = true;
+    }
+    /// Method to check if a `GetPeers` message received from the peer should be answered
+    pub fn get_peers_received(&mut self, timestamp: i64) -> bool {
+        Self::allow(&mut self.last_get_peers, self.min_interval, timestamp)
+    }
+    /// Method to check if a `Peers` message received from the peer should be accepted
+    pub fn peers_received(&mut self, timestamp: i64) -> bool {
+        if self.peers_requested {
+            self.peers_requested = false;
+
+            true
+        } else {
+            Self::allow(
+                &mut self.last_unsolicited_peers,
+                self.min_interval,
+                timestamp,
+            )
+        }
+    }
+
+    fn allow(last: &mut Option<i64>, min_interval: i64, timestamp: i64) -> bool {
+        match *last {
+            Some(last) if timestamp.saturating_sub(last) < min_interval => false,
+            _ => {
+                *last = Some(timestamp);
+
+                true
+            }
+        }
+    }
+}
@@ -1,26 +1,52 @@
 //! Library for managing a list of available peers
 
+/// Gossip module
+pub mod gossip;
+
 use serde::{Deserialize, Serialize};
 
-use std::collections::HashMap;
+use std::collections::hash_map::RandomState;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::{BuildHasher, Hash, Hasher};
 use std::net::SocketAddr;
 
-use rand::{thread_rng, Rng};
+use rand::{seq::IteratorRandom, thread_rng, Rng};
 
 use witnet_util::timestamp::get_timestamp;
 
+use crate::sessions::canonical_address;
+
+/// Maximum number of addresses sent in a `Peers` message
+pub const PEERS_SAMPLE_SIZE: usize = 1000;
+
 /// Peer information being used while listing available Witnet peers
 #[derive(Serialize, Deserialize)]
 struct PeerInfo {
     address: SocketAddr,
-    _timestamp: i64,
+    /// Last time the peer was seen: when its address was last added or, for tried peers, when a
+    /// connection to it was last established
+    #[serde(rename = "_timestamp")]
+    last_seen: i64,
+    /// Number of consecutive feeler connections to the peer that failed
+    #[serde(default)]
+    failed_attempts: u32,
 }
 
-/// Peers TBD
+/// Address book of the known peers.
+///
+/// Addresses are kept in two buckets: the `new` bucket holds the addresses heard of, from the
+/// configuration or from other peers, and the `tried` bucket holds the addresses of the peers
+/// this node has successfully connected to. The address book is persisted to storage, so it
+/// survives restarts.
 #[derive(Default, Serialize, Deserialize)]
 pub struct Peers {
-    /// Server sessions
-    peers: HashMap<SocketAddr, PeerInfo>,
+    /// Addresses not connected to yet. Address books persisted before the buckets existed only
+    /// contain this bucket, under the `peers` name.
+    #[serde(alias = "peers")]
+    new: HashMap<SocketAddr, PeerInfo>,
+    /// Addresses of the peers this node has connected to
+    #[serde(default)]
+    tried: HashMap<SocketAddr, PeerInfo>,
 }
 
 impl Peers {
@@ -28,19 +54,32 @@ impl Peers {
     /// If an address did already exist, it gets overwritten
     /// Returns all the overwritten addresses
     pub fn add(&mut self, addrs: Vec<SocketAddr>) -> Result<Vec<SocketAddr>, failure::Error> {
-        // Insert address
-        // Note: if the peer address exists, the peer info will be overwritten
+        let now = get_timestamp();
+
         Ok(addrs
             .into_iter()
+            .map(canonical_address)
             // Filter out unspecified addresses (aka 0.0.0.0)
             .filter(|address| !address.ip().is_unspecified())
             .filter_map(|address| {
-                self.peers
+                // Tried peers stay in their bucket, only their timestamp is updated
+                if let Some(info) = self.tried.get_mut(&address) {
+                    info.last_seen = now;
+                    return Some(address);
+                }
+
+                // Hearing of an address again does not make up for the failed connections to it
+                let failed_attempts = self
+                    .new
+                    .get(&address)
+                    .map_or(0, |info| info.failed_attempts);
+                self.new
                     .insert(
                         address,
                         PeerInfo {
                             address,
-                            _timestamp: get_timestamp(), //msg.timestamp,
+                            last_seen: now,
+                            failed_attempts,
                         },
                     )
                     .map(|v| v.address)
@@ -48,38 +87,283 @@ impl Peers {
             .collect())
     }
 
+    /// Move multiple peer addresses to the tried bucket, as a connection to them was established,
+    /// and save timestamp
+    /// Returns the addresses which were not known yet
+    pub fn add_tried(&mut self, addrs: Vec<SocketAddr>) -> Result<Vec<SocketAddr>, failure::Error> {
+        let now = get_timestamp();
+
+        Ok(addrs
+            .into_iter()
+            .map(canonical_address)
+            .filter(|address| !address.ip().is_unspecified())
+            .filter(|address| {
+                let known = self.new.remove(address).is_some();
+                let tried = self
+                    .tried
+                    .insert(
+                        *address,
+                        PeerInfo {
+                            address: *address,
+                            last_seen: now,
+                            failed_attempts: 0,
+                        },
+                    )
+                    .is_some();
+
+                !known && !tried
+            })
+            .collect())
+    }
+
+    /// Merge the addresses of another address book into this one. The tried bucket takes
+    /// precedence over the new one, and the most recent timestamp of every address is kept.
+    pub fn merge(&mut self, other: Peers) {
+        for (address, info) in other.tried {
+            let last_seen = self
+                .new
+                .remove(&address)
+                .into_iter()
+                .chain(self.tried.remove(&address))
+                .map(|old| old.last_seen)
+                .fold(info.last_seen, std::cmp::max);
+            self.tried.insert(
+                address,
+                PeerInfo {
+                    address,
+                    last_seen,
+                    failed_attempts: 0,
+                },
+            );
+        }
+        for (address, info) in other.new {
+            let bucket = if self.tried.contains_key(&address) {
+                &mut self.tried
+            } else {
+                &mut self.new
+            };
+            let entry = bucket.entry(address).or_insert(PeerInfo {
+                address,
+                last_seen: info.last_seen,
+                failed_attempts: info.failed_attempts,
+            });
+            entry.last_seen = std::cmp::max(entry.last_seen, info.last_seen);
+        }
+    }
+
     /// Remove a peer given an address
     /// Returns the removed addresses
     pub fn remove(&mut self, addrs: &[SocketAddr]) -> Result<Vec<SocketAddr>, failure::Error> {
         Ok(addrs
             .iter()
-            .filter_map(|address| self.peers.remove(&address).map(|info| info.address))
+            .filter_map(|address| {
+                self.new
+                    .remove(address)
+                    .or_else(|| self.tried.remove(address))
+                    .map(|info| info.address)
+            })
             .collect())
     }
 
-    /// Get a random socket address from the peers list
+    /// Get a random socket address from the peers list. Both buckets are equally likely to be
+    /// chosen, as long as they are not empty.
     pub fn get_random(&mut self) -> Result<Option<SocketAddr>, failure::Error> {
-        // Random index with range [0, len) of the peers vector
-        let index = thread_rng().gen_range(0, std::cmp::max(self.peers.len(), 1));
+        let mut rng = thread_rng();
+        let bucket = if self.tried.is_empty() || (!self.new.is_empty() && rng.gen()) {
+            &self.new
+        } else {
+            &self.tried
+        };
 
-        // Get element at index
-        let random_addr = self
-            .peers
-            // get peer infos
-            .values()
-            // enumerate them -> (indices, peer info)
-            .enumerate()
-            // filter by index and get address -> Iterator<Option<SocketAddr>>
-            .filter_map(|(i, v)| if i == index { Some(v.address) } else { None })
-            // Get first one, because
-            .next()
-            .map(|v| v.to_owned());
+        Ok(bucket.keys().choose(&mut rng).cloned())
+    }
+
+    /// Get a random socket address from the new bucket, to check with a feeler connection
+    /// whether the peer is reachable
+    pub fn get_random_new(&self) -> Option<SocketAddr> {
+        self.new.keys().choose(&mut thread_rng()).cloned()
+    }
+
+    /// Record a failed feeler connection to a peer of the new bucket. Peers are removed after
+    /// `max_failures` consecutive failures, `0` never removes them.
+    /// Returns `true` if the peer was removed
+    pub fn feeler_failed(&mut self, address: &SocketAddr, max_failures: u32) -> bool {
+        let info = match self.new.get_mut(address) {
+            Some(info) => info,
+            None => return false,
+        };
+        info.failed_attempts = info.failed_attempts.saturating_add(1);
+        if max_failures == 0 || info.failed_attempts < max_failures {
+            return false;
+        }
 
-        Ok(random_addr)
+        self.new.remove(address).is_some()
+    }
+
+    /// Get a random sample of up to `amount` socket addresses from the peers list
+    pub fn get_sample(&self, amount: usize) -> Result<Vec<SocketAddr>, failure::Error> {
+        Ok(self
+            .tried
+            .keys()
+            .chain(self.new.keys())
+            .cloned()
+            .choose_multiple(&mut thread_rng(), amount))
+    }
+
+    /// Get a random sample of up to `amount` socket addresses from the peers seen since the
+    /// `since` timestamp
+    pub fn get_recent_sample(
+        &self,
+        amount: usize,
+        since: i64,
+    ) -> Result<Vec<SocketAddr>, failure::Error> {
+        Ok(self
+            .tried
+            .values()
+            .chain(self.new.values())
+            .filter(|info| info.last_seen >= since)
+            .map(|info| info.address)
+            .choose_multiple(&mut thread_rng(), amount))
     }
 
     /// Get all the peers from the list
     pub fn get_all(&self) -> Result<Vec<SocketAddr>, failure::Error> {
-        Ok(self.peers.values().map(|v| v.address).collect())
+        Ok(self
+            .tried
+            .values()
+            .chain(self.new.values())
+            .map(|v| v.address)
+            .collect())
+    }
+
+    /// Whether a connection to the peer at `address` has been established
+    pub fn is_tried(&self, address: &SocketAddr) -> bool {
+        self.tried.contains_key(address)
+    }
+
+    /// Last time the peer at `address` was seen, if known
+    pub fn last_seen(&self, address: &SocketAddr) -> Option<i64> {
+        self.tried
+            .get(address)
+            .or_else(|| self.new.get(address))
+            .map(|info| info.last_seen)
+    }
+
+    /// Number of known peers
+    pub fn len(&self) -> usize {
+        self.new.len() + self.tried.len()
+    }
+
+    /// Returns `true` if no peer is known
+    pub fn is_empty(&self) -> bool {
+        self.new.is_empty() && self.tried.is_empty()
+    }
+
+    /// Approximate number of bytes used by the peers list
+    pub fn memory_usage(&self) -> usize {
+        self.len() * std::mem::size_of::<(SocketAddr, PeerInfo)>()
+    }
+
+    /// Remove the peers that were seen first until the memory used by the peers list is not
+    /// greater than `max_memory` bytes. Peers from the new bucket are removed before tried ones.
+    /// Returns the removed addresses
+    pub fn evict(&mut self, max_memory: usize) -> Result<Vec<SocketAddr>, failure::Error> {
+        let entry_size = std::mem::size_of::<(SocketAddr, PeerInfo)>();
+        let max_peers = max_memory / entry_size;
+        if self.len() <= max_peers {
+            return Ok(vec![]);
+        }
+
+        let by_age = |bucket: &HashMap<SocketAddr, PeerInfo>| {
+            let mut by_age: Vec<(i64, SocketAddr)> = bucket
+                .values()
+                .map(|info| (info.last_seen, info.address))
+                .collect();
+            by_age.sort();
+
+            by_age
+        };
+        let oldest: Vec<SocketAddr> = by_age(&self.new)
+            .into_iter()
+            .chain(by_age(&self.tried))
+            .take(self.len() - max_peers)
+            .map(|(_, address)| address)
+            .collect();
+
+        self.remove(&oldest)
+    }
+}
+
+/// Default number of addresses remembered by `SeenAddresses`
+pub const DEFAULT_SEEN_ADDRESSES_CAPACITY: usize = 65_536;
+
+/// Bounded set of recently seen peer addresses, used to skip addresses which are gossiped again
+/// and again by well-connected peers.
+///
+/// Only a 64-bit SipHash digest of every address is stored. The SipHash key is chosen randomly
+/// for every set, so remote peers cannot craft addresses with colliding digests. When the set is
+/// full, the oldest digests are forgotten first.
+pub struct SeenAddresses {
+    hasher: RandomState,
+    digests: HashSet<u64>,
+    order: VecDeque<u64>,
+    capacity: usize,
+}
+
+impl Default for SeenAddresses {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_SEEN_ADDRESSES_CAPACITY)
+    }
+}
+
+impl SeenAddresses {
+    /// Create a set which remembers up to `capacity` addresses
+    pub fn with_capacity(capacity: usize) -> Self {
+        SeenAddresses {
+            hasher: RandomState::new(),
+            digests: HashSet::with_capacity(capacity),
+            order: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Insert an address into the set
+    /// Returns `true` if the address had not been seen recently
+    pub fn insert(&mut self, address: &SocketAddr) -> bool {
+        if self.capacity == 0 {
+            return true;
+        }
+
+        let mut hasher = self.hasher.build_hasher();
+        address.hash(&mut hasher);
+        let digest = hasher.finish();
+
+        if !self.digests.insert(digest) {
+            return false;
+        }
+        self.order.push_back(digest);
+        if self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.digests.remove(&oldest);
+            }
+        }
+
+        true
+    }
+
+    /// Number of addresses in the set
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    /// Returns `true` if no address has been seen
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+
+    /// Forget all the addresses
+    pub fn clear(&mut self) {
+        self.digests.clear();
+        self.order.clear();
     }
 }
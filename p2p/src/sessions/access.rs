@@ -0,0 +1,167 @@
+//! Library for the access list of the sessions
+//!
+//! The whitelist and the blacklist are lists of IP addresses and CIDR ranges (such as
+//! `10.0.0.0/8` or `2001:db8::/32`). Peers in the blacklist are never connected to, inbound or
+//! outbound. If the whitelist is not empty, only the peers in it are connected to, which lets a
+//! validator behind sentry nodes connect to its sentries and nothing else.
+use std::{fmt, net::IpAddr, str::FromStr};
+
+use failure::Fail;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::canonical_ip;
+
+/// The error type for the access list
+#[derive(Debug, PartialEq, Fail)]
+pub enum AccessError {
+    /// The range is not an IP address or a CIDR range
+    #[fail(
+        display = "Invalid IP range {:?}, expected an IP address or a CIDR range",
+        range
+    )]
+    InvalidIpRange {
+        /// Range given
+        range: String,
+    },
+}
+
+/// Range of IP addresses sharing the first `prefix_len` bits
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IpRange {
+    address: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpRange {
+    /// Method to check if an IP address belongs to the range. IPv4-mapped IPv6 addresses are
+    /// checked as IPv4
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.address, canonical_ip(ip)) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                prefix_matches(&network.octets(), &ip.octets(), self.prefix_len)
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                prefix_matches(&network.octets(), &ip.octets(), self.prefix_len)
+            }
+            _ => false,
+        }
+    }
+}
+
+fn prefix_matches(network: &[u8], ip: &[u8], prefix_len: u8) -> bool {
+    let full_bytes = usize::from(prefix_len / 8);
+    let remaining_bits = prefix_len % 8;
+    if network[..full_bytes] != ip[..full_bytes] {
+        return false;
+    }
+    if remaining_bits == 0 {
+        return true;
+    }
+    let mask = 0xffu8 << (8 - remaining_bits);
+
+    network[full_bytes] & mask == ip[full_bytes] & mask
+}
+
+impl From<IpAddr> for IpRange {
+    fn from(address: IpAddr) -> Self {
+        let address = canonical_ip(address);
+        let prefix_len = if address.is_ipv4() { 32 } else { 128 };
+
+        IpRange {
+            address,
+            prefix_len,
+        }
+    }
+}
+
+impl FromStr for IpRange {
+    type Err = AccessError;
+
+    fn from_str(range: &str) -> Result<Self, Self::Err> {
+        let invalid_range = || AccessError::InvalidIpRange {
+            range: range.to_string(),
+        };
+
+        let mut parts = range.trim().splitn(2, '/');
+        let address: IpAddr = parts
+            .next()
+            .and_then(|address| address.parse().ok())
+            .ok_or_else(invalid_range)?;
+        let mut ip_range = IpRange::from(address);
+        if let Some(prefix_len) = parts.next() {
+            let prefix_len: u8 = prefix_len.parse().map_err(|_| invalid_range())?;
+            if prefix_len > ip_range.prefix_len || address != ip_range.address {
+                Err(invalid_range())?
+            }
+            ip_range.prefix_len = prefix_len;
+        }
+
+        Ok(ip_range)
+    }
+}
+
+impl fmt::Display for IpRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.address {
+            IpAddr::V4(_) if self.prefix_len == 32 => write!(f, "{}", self.address),
+            IpAddr::V6(_) if self.prefix_len == 128 => write!(f, "{}", self.address),
+            _ => write!(f, "{}/{}", self.address, self.prefix_len),
+        }
+    }
+}
+
+impl Serialize for IpRange {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for IpRange {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let range = String::deserialize(deserializer)?;
+
+        range.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// IP ranges the sessions are allowed or forbidden to connect to
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct AccessList {
+    /// If not empty, only the peers in these ranges are allowed
+    #[serde(default)]
+    pub whitelist: Vec<IpRange>,
+    /// Peers in these ranges are never allowed, even if they are whitelisted
+    #[serde(default)]
+    pub blacklist: Vec<IpRange>,
+}
+
+impl AccessList {
+    /// Create an access list from the IP addresses and CIDR ranges of the configuration
+    pub fn parse<S: AsRef<str>>(whitelist: &[S], blacklist: &[S]) -> Result<Self, AccessError> {
+        let parse_ranges = |ranges: &[S]| {
+            ranges
+                .iter()
+                .map(|range| range.as_ref().parse())
+                .collect::<Result<Vec<IpRange>, AccessError>>()
+        };
+
+        Ok(AccessList {
+            whitelist: parse_ranges(whitelist)?,
+            blacklist: parse_ranges(blacklist)?,
+        })
+    }
+    /// Method to check if the sessions are allowed to connect to an IP address
+    pub fn is_allowed(&self, ip: IpAddr) -> bool {
+        let is_blacklisted = self.blacklist.iter().any(|range| range.contains(ip));
+        let is_whitelisted =
+            self.whitelist.is_empty() || self.whitelist.iter().any(|range| range.contains(ip));
+
+        is_whitelisted && !is_blacklisted
+    }
+}
@@ -0,0 +1,105 @@
+//! Library for protecting the sessions against message floods
+//!
+//! Some messages are cheap to send but costly to answer. Each session limits the rate of those
+//! messages with a token bucket per command: a peer can send a small burst of them at once, and
+//! then one more every interval. Messages over the limit are dropped and count as violations, so
+//! that peers which keep flooding the node can be disconnected.
+use std::collections::HashMap;
+
+/// Rate limit of the messages of a command
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MessageRate {
+    /// Number of messages that can be received at once
+    pub burst: u32,
+    /// Milliseconds to wait for each message after the burst, `0` means no limit
+    pub interval: i64,
+}
+
+/// Messages of a command that can be received right now
+#[derive(Clone, Debug)]
+struct MessageBucket {
+    rate: MessageRate,
+    tokens: u32,
+    /// Timestamp of the last refill of the bucket
+    updated: i64,
+}
+
+impl MessageBucket {
+    fn take(&mut self, timestamp: i64) -> bool {
+        if self.rate.interval == 0 {
+            return true;
+        }
+
+        if self.tokens >= self.rate.burst {
+            self.updated = timestamp;
+        } else {
+            let refills = timestamp.saturating_sub(self.updated) / self.rate.interval;
+            if refills > 0 {
+                let refills = refills.min(i64::from(self.rate.burst)) as u32;
+                self.tokens = (self.tokens + refills).min(self.rate.burst);
+                self.updated = if self.tokens == self.rate.burst {
+                    timestamp
+                } else {
+                    self.updated + i64::from(refills) * self.rate.interval
+                };
+            }
+        }
+
+        if self.tokens == 0 {
+            false
+        } else {
+            self.tokens -= 1;
+
+            true
+        }
+    }
+}
+
+/// Rate limits of the messages received from a peer. Timestamps are in milliseconds.
+#[derive(Clone, Debug, Default)]
+pub struct FloodProtection {
+    buckets: HashMap<&'static str, MessageBucket>,
+    violations: u32,
+}
+
+impl FloodProtection {
+    /// Create the rate limits of the given commands, identified by name
+    pub fn new(limits: &[(&'static str, MessageRate)], timestamp: i64) -> Self {
+        let buckets = limits
+            .iter()
+            .map(|&(command, rate)| {
+                let bucket = MessageBucket {
+                    rate,
+                    tokens: rate.burst,
+                    updated: timestamp,
+                };
+
+                (command, bucket)
+            })
+            .collect();
+
+        FloodProtection {
+            buckets,
+            violations: 0,
+        }
+    }
+    /// Method to record a message received from the peer. Returns `false` if the message exceeds
+    /// the rate limit of its command, which counts as a violation. Commands without a limit are
+    /// always accepted
+    pub fn message_received(&mut self, command: &str, timestamp: i64) -> bool {
+        let accepted = self
+            .buckets
+            .get_mut(command)
+            .map(|bucket| bucket.take(timestamp))
+            .unwrap_or(true);
+        if !accepted {
+            self.violations = self.violations.saturating_add(1);
+        }
+
+        accepted
+    }
+    /// Method to get the number of messages received over the rate limits
+    pub fn violations(&self) -> u32 {
+        self.violations
+    }
+}
@@ -0,0 +1,81 @@
+//! Library for the handshake of the sessions
+//!
+//! Both peers send a `Version` message and answer the one they receive with a `Verack` message.
+//! The session is consolidated once the four messages have been exchanged. The protocol version
+//! of the session is the lowest of the ones announced by both peers, so that newer features are
+//! only used with the peers that support them.
+use crate::error::SessionsError;
+
+/// Step of the handshake a session is waiting for
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HandshakeState {
+    /// The `Version` message of the peer has not been received yet
+    AwaitingVersion,
+    /// The `Verack` message of the peer has not been received yet
+    AwaitingVerack,
+    /// The four handshake messages have been exchanged
+    Completed,
+}
+
+/// Handshake of a session: the handshake messages sent and received, and the protocol version
+/// negotiated with the peer
+#[derive(Clone, Debug, Default)]
+pub struct Handshake {
+    /// Flag to indicate that a version message was sent
+    pub version_tx: bool,
+    /// Flag to indicate that a version message was received
+    pub version_rx: bool,
+    /// Flag to indicate that a verack message was sent
+    pub verack_tx: bool,
+    /// Flag to indicate that a verack message was received
+    pub verack_rx: bool,
+    /// Protocol version of the session, once negotiated
+    protocol_version: Option<u32>,
+}
+
+impl Handshake {
+    /// Method to check if the four handshake messages have been exchanged
+    pub fn is_completed(&self) -> bool {
+        self.verack_tx && self.verack_rx && self.version_tx && self.version_rx
+    }
+    /// Method to get the step of the handshake the session is waiting for
+    pub fn state(&self) -> HandshakeState {
+        if self.is_completed() {
+            HandshakeState::Completed
+        } else if !self.version_rx {
+            HandshakeState::AwaitingVersion
+        } else {
+            HandshakeState::AwaitingVerack
+        }
+    }
+    /// Method to negotiate the protocol version of the session with the version announced by
+    /// the peer. Peers older than `min_version` are refused.
+    pub fn negotiate_version(
+        &mut self,
+        local_version: u32,
+        remote_version: u32,
+        min_version: u32,
+    ) -> Result<u32, SessionsError> {
+        if remote_version < min_version {
+            Err(SessionsError::UnsupportedVersion {
+                version: remote_version,
+                min_version,
+            })?
+        }
+        let protocol_version = std::cmp::min(local_version, remote_version);
+        self.protocol_version = Some(protocol_version);
+
+        Ok(protocol_version)
+    }
+    /// Method to get the protocol version negotiated with the peer, if any
+    pub fn protocol_version(&self) -> Option<u32> {
+        self.protocol_version
+    }
+    /// Method to check if the peer supports the features introduced in protocol version
+    /// `version`
+    pub fn supports(&self, version: u32) -> bool {
+        self.protocol_version
+            .map(|protocol_version| protocol_version >= version)
+            .unwrap_or(false)
+    }
+}
@@ -0,0 +1,74 @@
+//! Library for measuring the latency of the sessions
+//!
+//! Every session sends `Ping` messages periodically and the peer answers each of them with a
+//! `Pong` message carrying the same nonce. The round-trip time of each ping is smoothed as in
+//! TCP (RFC 6298), so that a single slow pong does not change the latency of the peer much.
+//! Pings that are not answered before the next one is due count as missed, and peers that miss
+//! too many of them in a row are considered unresponsive.
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Weight of the last round-trip time in the smoothed round-trip time, as `1 / RTT_WEIGHT`
+const RTT_WEIGHT: u64 = 8;
+
+/// Latency of a peer, in milliseconds
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct LatencyStats {
+    /// Smoothed round-trip time, `None` until the first pong is received
+    pub smoothed_rtt: Option<u64>,
+    /// Round-trip time of the last pong, `None` until the first pong is received
+    pub last_rtt: Option<u64>,
+    /// Timestamp of the last message received from the peer, `None` if there was none
+    pub last_seen: Option<i64>,
+    /// Number of consecutive pings not answered in time
+    pub missed_pongs: u32,
+}
+
+/// Pings sent to a peer and latency measured from its pongs. Timestamps are in milliseconds.
+#[derive(Clone, Debug, Default)]
+pub struct LatencyTracker {
+    /// Nonces of the pings not answered yet, with the timestamp they were sent at
+    outstanding_pings: HashMap<u64, i64>,
+    stats: LatencyStats,
+}
+
+impl LatencyTracker {
+    /// Method to record a ping sent to the peer
+    pub fn ping_sent(&mut self, nonce: u64, timestamp: i64) {
+        self.outstanding_pings.insert(nonce, timestamp);
+    }
+    /// Method to record a pong received from the peer. Returns the round-trip time of the ping,
+    /// or `None` if the nonce does not belong to an outstanding ping
+    pub fn pong_received(&mut self, nonce: u64, timestamp: i64) -> Option<u64> {
+        let sent = self.outstanding_pings.remove(&nonce)?;
+        let rtt = timestamp.saturating_sub(sent).max(0) as u64;
+
+        self.stats.smoothed_rtt = Some(match self.stats.smoothed_rtt {
+            Some(smoothed_rtt) => (smoothed_rtt * (RTT_WEIGHT - 1) + rtt) / RTT_WEIGHT,
+            None => rtt,
+        });
+        self.stats.last_rtt = Some(rtt);
+        self.stats.missed_pongs = 0;
+
+        Some(rtt)
+    }
+    /// Method to record any message received from the peer
+    pub fn message_received(&mut self, timestamp: i64) {
+        self.stats.last_seen = Some(timestamp);
+    }
+    /// Method to forget the pings sent before `deadline`, counting them as missed. Returns the
+    /// number of consecutive pings missed by the peer
+    pub fn expire_pings(&mut self, deadline: i64) -> u32 {
+        let num_pings = self.outstanding_pings.len();
+        self.outstanding_pings.retain(|_, sent| *sent >= deadline);
+        let missed = num_pings - self.outstanding_pings.len();
+        self.stats.missed_pongs = self.stats.missed_pongs.saturating_add(missed as u32);
+
+        self.stats.missed_pongs
+    }
+    /// Method to get the latency measured so far
+    pub fn stats(&self) -> LatencyStats {
+        self.stats
+    }
+}
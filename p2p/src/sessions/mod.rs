@@ -1,13 +1,43 @@
 //! Library for managing the node sessions, including outbound and inbound sessions
 
+/// Access list module
+pub mod access;
 /// Bounded sessions module
 pub mod bounded_sessions;
+/// Flood protection module
+pub mod flood;
+/// Handshake module
+pub mod handshake;
+/// Latency module
+pub mod latency;
 
-use std::{net::SocketAddr, time::Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    net::{IpAddr, SocketAddr},
+    time::Duration,
+};
 
-use rand::{thread_rng, Rng};
+use rand::{seq::SliceRandom, thread_rng, Rng};
 
-use super::{error::SessionsError, sessions::bounded_sessions::BoundedSessions};
+use super::{
+    bandwidth::BandwidthLimits,
+    error::SessionsError,
+    sessions::{access::AccessList, bounded_sessions::BoundedSessions, latency::LatencyStats},
+};
+
+/// Quality points lost by a peer each time it does not deliver the inventory items requested to
+/// it in time
+pub const SLOW_PEER_PENALTY: i32 = 1;
+
+/// Quality points lost by a peer each time it sends a message over the rate limits
+pub const FLOODING_PEER_PENALTY: i32 = 1;
+
+/// Quality points lost by a peer each time it sends a malformed block
+pub const INVALID_BLOCK_PENALTY: i32 = 5;
+
+/// Quality points lost by a peer each time it sends a message with more items than the protocol
+/// allows, e.g. a `Headers` message with more than `MAX_HEADERS` headers
+pub const OVERSIZED_MESSAGE_PENALTY: i32 = 5;
 
 /// Session type
 #[derive(Copy, Clone, Debug)]
@@ -27,6 +57,69 @@ pub enum SessionStatus {
     Consolidated,
 }
 
+/// Policy applied to the inbound connections accepted by a listener of the server
+#[derive(Clone, Debug, PartialEq)]
+pub struct Listener {
+    /// Address the listener is bound to
+    pub address: SocketAddr,
+    /// Maximum number of inbound sessions from this listener (`None` means that only the global
+    /// inbound limit applies)
+    pub inbound_limit: Option<u16>,
+    /// IP addresses allowed to connect through this listener (all of them if empty)
+    pub whitelist: Vec<IpAddr>,
+}
+
+impl Listener {
+    /// Method to check if a connection with local address `local_addr` was accepted by this
+    /// listener
+    pub fn matches(&self, local_addr: SocketAddr) -> bool {
+        is_bound_to(self.address, local_addr)
+    }
+    /// Method to check if a remote IP address is allowed to connect through this listener
+    pub fn is_whitelisted(&self, ip: IpAddr) -> bool {
+        let ip = canonical_ip(ip);
+
+        self.whitelist.is_empty() || self.whitelist.contains(&ip)
+    }
+}
+
+/// Check if a listening address can be advertised to other peers, that is, if it is bound to a
+/// specific public IP address
+pub fn is_advertisable_address(address: &SocketAddr) -> bool {
+    !address.ip().is_unspecified() && !crate::mdns::is_local_address(&address.ip())
+}
+
+/// Check if a connection with local address `local_addr` was accepted by a socket bound to
+/// `bind_addr`, which may be an unspecified address (`0.0.0.0` or `::`)
+fn is_bound_to(bind_addr: SocketAddr, local_addr: SocketAddr) -> bool {
+    let ip = bind_addr.ip();
+
+    bind_addr.port() == local_addr.port()
+        && bind_addr.is_ipv4() == local_addr.is_ipv4()
+        && (ip.is_unspecified() || ip == local_addr.ip())
+}
+
+/// IPv4 addresses connecting to IPv6 sockets are seen as IPv4-mapped IPv6 addresses
+/// (`::ffff:a.b.c.d`), convert them back to IPv4
+pub fn canonical_ip(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(ipv6) => match ipv6.segments() {
+            [0, 0, 0, 0, 0, 0xffff, _, _] => IpAddr::V4(ipv6.to_ipv4().unwrap()),
+            _ => ip,
+        },
+        IpAddr::V4(_) => ip,
+    }
+}
+
+/// Convert the IPv4-mapped IPv6 address of a socket address back to IPv4, so that a peer is known
+/// by the same address whether it connected to an IPv4 or to a dual-stack IPv6 socket
+pub fn canonical_address(address: SocketAddr) -> SocketAddr {
+    match canonical_ip(address.ip()) {
+        ip @ IpAddr::V4(_) => SocketAddr::new(ip, address.port()),
+        IpAddr::V6(_) => address,
+    }
+}
+
 /// Sessions struct contains:
 /// - server address used to listen to incoming connections
 /// - list of inbound sessions parametrized with their reference (T)
@@ -38,6 +131,10 @@ where
 {
     /// Server address listening to incoming connections
     pub server_address: Option<SocketAddr>,
+    /// Additional listeners of the server
+    pub listeners: Vec<Listener>,
+    /// Index of the listener that accepted each inbound session, by remote address
+    inbound_listeners: HashMap<SocketAddr, usize>,
     /// Inbound consolidated sessions: __known__ peers sessions that connect to the server
     pub inbound_consolidated: BoundedSessions<T>,
     /// Inbound sessions: __untrusted__ peers that connect to the server
@@ -58,6 +155,36 @@ where
     pub magic_number: u16,
     /// Timeout for requested blocks
     pub blocks_timeout: i64,
+    /// User agent announced to other peers
+    pub user_agent: String,
+    /// Minimum protocol version accepted from peers
+    pub min_peer_version: u32,
+    /// Encrypt the sessions with the peers that support it
+    pub encrypted_transport: bool,
+    /// Announce to the peers that the bodies of old blocks are pruned
+    pub pruned: bool,
+    /// Period between the pings sent to each peer
+    pub ping_interval: Duration,
+    /// Number of consecutive missed pings before disconnecting a peer
+    pub max_missed_pongs: u32,
+    /// Minimum time between two `GetPeers` messages answered or two unsolicited `Peers` messages
+    /// accepted from each peer
+    pub gossip_interval: Duration,
+    /// Bandwidth limits of each session and of all the sessions together
+    pub bandwidth_limits: BandwidthLimits,
+    /// IP ranges the sessions are allowed or forbidden to connect to
+    pub access_list: AccessList,
+    /// Quality score of the peers, by address. Peers start at 0 and lose points when they
+    /// misbehave. The score is kept when a peer disconnects, so it still applies if it comes back
+    peer_quality: HashMap<SocketAddr, i32>,
+    /// Latency of the peers with a consolidated session, by address
+    peer_latency: HashMap<SocketAddr, LatencyStats>,
+    /// Addresses of the sessions with peers whose user agent matches a preferred pattern, which
+    /// are the last ones to be evicted
+    preferred_sessions: HashSet<SocketAddr>,
+    /// Listening addresses of the peers whose user agent matches a preferred pattern, which are
+    /// dialed before any other peer. They are kept when a peer disconnects, so it is dialed again
+    preferred_peers: HashSet<SocketAddr>,
 }
 
 /// Default trait implementation
@@ -68,6 +195,8 @@ where
     fn default() -> Self {
         Self {
             server_address: None,
+            listeners: Vec::new(),
+            inbound_listeners: HashMap::new(),
             inbound_consolidated: BoundedSessions::default(),
             inbound_unconsolidated: BoundedSessions::default(),
             outbound_consolidated: BoundedSessions::default(),
@@ -76,6 +205,19 @@ where
             handshake_timeout: Duration::default(),
             magic_number: 0 as u16,
             blocks_timeout: 0 as i64,
+            user_agent: String::new(),
+            min_peer_version: 0,
+            encrypted_transport: false,
+            pruned: false,
+            ping_interval: Duration::default(),
+            max_missed_pongs: 0,
+            gossip_interval: Duration::default(),
+            bandwidth_limits: BandwidthLimits::default(),
+            access_list: AccessList::default(),
+            peer_quality: HashMap::new(),
+            peer_latency: HashMap::new(),
+            preferred_sessions: HashSet::new(),
+            preferred_peers: HashSet::new(),
         }
     }
 }
@@ -105,6 +247,56 @@ where
     pub fn set_server_address(&mut self, server_address: SocketAddr) {
         self.server_address = Some(server_address);
     }
+    /// Method to set the additional listeners of the server
+    pub fn set_listeners(&mut self, listeners: Vec<Listener>) {
+        self.listeners = listeners;
+    }
+    /// Method to check if an inbound connection is allowed by the access list and by the policy
+    /// of the listener that accepted it. The main server address has no listener policy.
+    pub fn accept_inbound(
+        &mut self,
+        local_addr: SocketAddr,
+        remote_addr: SocketAddr,
+    ) -> Result<(), failure::Error> {
+        if !self.access_list.is_allowed(remote_addr.ip()) {
+            Err(SessionsError::NotAllowed)?
+        }
+
+        // The server address takes precedence over the additional listeners
+        let is_server = self
+            .server_address
+            .map(|address| is_bound_to(address, local_addr))
+            .unwrap_or(false);
+        if is_server {
+            return Ok(());
+        }
+        let index = match self
+            .listeners
+            .iter()
+            .position(|listener| listener.matches(local_addr))
+        {
+            Some(index) => index,
+            None => return Ok(()),
+        };
+        let listener = &self.listeners[index];
+
+        if !listener.is_whitelisted(remote_addr.ip()) {
+            Err(SessionsError::NotWhitelisted)?
+        }
+        if let Some(limit) = listener.inbound_limit {
+            let num_sessions = self
+                .inbound_listeners
+                .values()
+                .filter(|&&listener_index| listener_index == index)
+                .count();
+            if num_sessions >= limit as usize {
+                Err(SessionsError::MaxPeersReached)?
+            }
+        }
+        self.inbound_listeners.insert(remote_addr, index);
+
+        Ok(())
+    }
     /// Method to set the sessions limits
     pub fn set_limits(&mut self, inbound_limit: u16, outbound_consolidated_limit: u16) {
         self.inbound_consolidated.set_limit(inbound_limit);
@@ -121,6 +313,54 @@ where
     pub fn set_magic_number(&mut self, magic_number: u16) {
         self.magic_number = magic_number;
     }
+    /// Method to set the user agent announced to other peers
+    pub fn set_user_agent(&mut self, user_agent: String) {
+        self.user_agent = user_agent;
+    }
+    /// Method to set the minimum protocol version accepted from peers
+    pub fn set_min_peer_version(&mut self, min_peer_version: u32) {
+        self.min_peer_version = min_peer_version;
+    }
+    /// Method to enable the encrypted transport of the sessions
+    pub fn set_encrypted_transport(&mut self, encrypted_transport: bool) {
+        self.encrypted_transport = encrypted_transport;
+    }
+    /// Method to announce to the peers that the bodies of old blocks are pruned
+    pub fn set_pruned(&mut self, pruned: bool) {
+        self.pruned = pruned;
+    }
+    /// Method to set the period between the pings sent to each peer
+    pub fn set_ping_interval(&mut self, ping_interval: Duration) {
+        self.ping_interval = ping_interval;
+    }
+    /// Method to set the number of consecutive missed pings before disconnecting a peer
+    pub fn set_max_missed_pongs(&mut self, max_missed_pongs: u32) {
+        self.max_missed_pongs = max_missed_pongs;
+    }
+    /// Method to set the minimum time between two peer address messages accepted from each peer
+    pub fn set_gossip_interval(&mut self, gossip_interval: Duration) {
+        self.gossip_interval = gossip_interval;
+    }
+    /// Method to set the bandwidth limits of the sessions, in bytes per second
+    pub fn set_bandwidth_limits(&mut self, bandwidth_limits: BandwidthLimits) {
+        self.bandwidth_limits = bandwidth_limits;
+    }
+    /// Method to set the IP ranges the sessions are allowed or forbidden to connect to
+    pub fn set_access_list(&mut self, access_list: AccessList) {
+        self.access_list = access_list;
+    }
+    /// Method to get the sessions, inbound or outbound, with peers not allowed by the access list
+    pub fn get_disallowed_sessions(&self) -> Vec<(SocketAddr, T)> {
+        self.inbound_consolidated
+            .collection
+            .iter()
+            .chain(self.inbound_unconsolidated.collection.iter())
+            .chain(self.outbound_consolidated.collection.iter())
+            .chain(self.outbound_unconsolidated.collection.iter())
+            .filter(|(address, _)| !self.access_list.is_allowed(address.ip()))
+            .map(|(address, info)| (*address, info.reference.clone()))
+            .collect()
+    }
     /// Method to set the timout for waiting requested blocks
     pub fn set_blocks_timeout(&mut self, blocks_timeout: i64) {
         self.blocks_timeout = blocks_timeout;
@@ -137,14 +377,22 @@ where
             .collection
             .contains_key(&candidate_addr);
 
-        // Check if address is the server address
+        // Check if address is the server address or the address of a listener
         let is_server = self
             .server_address
             .map(|address| address == candidate_addr)
-            .unwrap_or(false);
+            .unwrap_or(false)
+            || self
+                .listeners
+                .iter()
+                .any(|listener| listener.address == candidate_addr);
 
-        // Return true if the address has not been used as outbound session or server address
-        !is_outbound_consolidated && !is_outbound_unconsolidated && !is_server
+        // Return true if the address has not been used as outbound session or server address,
+        // and it is allowed by the access list
+        !is_outbound_consolidated
+            && !is_outbound_unconsolidated
+            && !is_server
+            && self.access_list.is_allowed(candidate_addr.ip())
     }
     /// Method to get total number of outbound peers
     pub fn get_num_outbound_sessions(&self) -> usize {
@@ -156,38 +404,155 @@ where
     }
     /// Method to check if outbound bootstrap is needed
     pub fn is_outbound_bootstrap_needed(&self) -> bool {
+        self.get_num_missing_outbound_sessions() > 0
+    }
+    /// Method to get the number of outbound sessions needed to reach the outbound limit. Without
+    /// a limit, one more session is always needed
+    pub fn get_num_missing_outbound_sessions(&self) -> usize {
         let num_outbound_sessions = self.get_num_outbound_sessions();
 
         self.outbound_consolidated
             .limit
-            .map(|limit| num_outbound_sessions < limit as usize)
-            .unwrap_or(true)
+            .map(|limit| (limit as usize).saturating_sub(num_outbound_sessions))
+            .unwrap_or(1)
+    }
+    /// Method to check if the inbound sessions, consolidated or not, reached the inbound limit
+    pub fn is_inbound_full(&self) -> bool {
+        let num_inbound_sessions = self.inbound_consolidated.collection.len()
+            + self.inbound_unconsolidated.collection.len();
+
+        self.inbound_consolidated
+            .limit
+            .map(|limit| num_inbound_sessions >= limit as usize)
+            .unwrap_or(false)
+    }
+    /// Method to choose the consolidated inbound session to evict to make room for a new inbound
+    /// session: one with a peer that is not preferred if possible, then the one with the worst
+    /// quality score and then the highest latency, at random among ties
+    pub fn get_inbound_session_to_evict(&self) -> Option<(SocketAddr, T)> {
+        // Peers whose latency has not been measured yet are not penalized
+        let eviction_key = |address: SocketAddr| {
+            let rtt = self
+                .get_peer_latency(address)
+                .and_then(|latency| latency.smoothed_rtt)
+                .unwrap_or(0);
+
+            (
+                self.is_preferred_session(address),
+                self.get_peer_quality(address),
+                std::cmp::Reverse(rtt),
+            )
+        };
+        let worst_key = self
+            .inbound_consolidated
+            .collection
+            .keys()
+            .map(|address| eviction_key(*address))
+            .min()?;
+        let worst_sessions: Vec<_> = self
+            .inbound_consolidated
+            .collection
+            .iter()
+            .filter(|(address, _)| eviction_key(**address) == worst_key)
+            .collect();
+
+        let (address, info) = worst_sessions[thread_rng().gen_range(0, worst_sessions.len())];
+
+        Some((*address, info.reference.clone()))
+    }
+    /// Method to get the quality score of a peer
+    pub fn get_peer_quality(&self, address: SocketAddr) -> i32 {
+        self.peer_quality.get(&address).cloned().unwrap_or(0)
+    }
+    /// Method to decrease the quality score of a peer
+    pub fn decrease_peer_quality(&mut self, address: SocketAddr, penalty: i32) {
+        let quality = self.peer_quality.entry(address).or_insert(0);
+        *quality = quality.saturating_sub(penalty);
     }
-    /// Method to get a random consolidated outbound session
+    /// Method to mark a session as established with a peer whose user agent matches a preferred
+    /// pattern, along with the address the peer listens on, if known
+    pub fn set_preferred_peer(
+        &mut self,
+        address: SocketAddr,
+        listening_address: Option<SocketAddr>,
+    ) {
+        self.preferred_sessions.insert(address);
+        if let Some(listening_address) = listening_address {
+            self.preferred_peers.insert(listening_address);
+        }
+    }
+    /// Method to check whether a session is established with a peer with a preferred user agent
+    pub fn is_preferred_session(&self, address: SocketAddr) -> bool {
+        self.preferred_sessions.contains(&address)
+    }
+    /// Method to get the listening addresses of the preferred peers that are eligible as outbound
+    /// peers, in random order
+    pub fn get_preferred_outbound_candidates(&self) -> Vec<SocketAddr> {
+        let mut candidates: Vec<SocketAddr> = self
+            .preferred_peers
+            .iter()
+            .cloned()
+            .filter(|address| self.is_outbound_address_eligible(*address))
+            .collect();
+        candidates.shuffle(&mut thread_rng());
+
+        candidates
+    }
+    /// Method to get the latency of a peer with a consolidated session, if it was measured
+    pub fn get_peer_latency(&self, address: SocketAddr) -> Option<LatencyStats> {
+        self.peer_latency.get(&address).cloned()
+    }
+    /// Method to update the latency of a peer with a consolidated session
+    pub fn set_peer_latency(&mut self, address: SocketAddr, latency: LatencyStats) {
+        if self.inbound_consolidated.collection.contains_key(&address)
+            || self.outbound_consolidated.collection.contains_key(&address)
+        {
+            self.peer_latency.insert(address, latency);
+        }
+    }
+    /// Method to get the latency of all the peers with a consolidated session, including the
+    /// ones whose latency was not measured yet
+    pub fn get_consolidated_peers_latency(&self) -> Vec<(SocketAddr, LatencyStats)> {
+        self.outbound_consolidated
+            .collection
+            .keys()
+            .chain(self.inbound_consolidated.collection.keys())
+            .map(|address| {
+                (
+                    *address,
+                    self.get_peer_latency(*address).unwrap_or_default(),
+                )
+            })
+            .collect()
+    }
+    /// Method to get a random consolidated outbound session, among the ones with the best quality
+    /// score
     pub fn get_random_anycast_session(&self, safu: bool) -> Option<T> {
-        // Get iterator over the values of the hashmap
-        let mut outbound_sessions_iter = if safu {
+        // Get the collection of candidate sessions
+        let outbound_sessions = if safu {
             // Safu: use only peers with consensus
-            self.outbound_consolidated_consensus.collection.values()
+            &self.outbound_consolidated_consensus.collection
         } else {
             // Not safu: use all peers
-            self.outbound_consolidated.collection.values()
+            &self.outbound_consolidated.collection
         };
 
-        // Get the number of elements in the collection from the iterator
-        let len = outbound_sessions_iter.len();
+        // Only the peers with the best quality are chosen, so a peer that misbehaved is not used
+        // again while there are better ones
+        let best_quality = outbound_sessions
+            .keys()
+            .map(|address| self.get_peer_quality(*address))
+            .max()?;
+        let best_sessions: Vec<_> = outbound_sessions
+            .iter()
+            .filter(|(address, _)| self.get_peer_quality(**address) == best_quality)
+            .map(|(_, info)| &info.reference)
+            .collect();
 
-        // Get random index
-        let index: usize = if len == 0 {
-            0
-        } else {
-            thread_rng().gen_range(0, len)
-        };
+        // Get session info reference at random index
+        let index = thread_rng().gen_range(0, best_sessions.len());
 
-        // Get session info reference at random index (None if no elements in the collection)
-        outbound_sessions_iter
-            .nth(index)
-            .map(|info| info.reference.clone())
+        Some(best_sessions[index].clone())
     }
     /// Method to get all the consolidated sessions (inbound and outbound)
     pub fn get_all_consolidated_sessions<'a>(&'a self) -> impl Iterator<Item = &T> + 'a {
@@ -228,6 +593,17 @@ where
         status: SessionStatus,
         address: SocketAddr,
     ) -> Result<(), failure::Error> {
+        // Release the slot of the listener that accepted the session
+        if let SessionType::Inbound = session_type {
+            self.inbound_listeners.remove(&address);
+        }
+
+        // The latency is only kept while the session is alive
+        if let SessionStatus::Consolidated = status {
+            self.peer_latency.remove(&address);
+        }
+        self.preferred_sessions.remove(&address);
+
         // If this is an outbound consolidated session, try to remove it from the consensus list
         if let (SessionType::Outbound, SessionStatus::Consolidated) = (session_type, status) {
             match self.unconsensus_session(address) {
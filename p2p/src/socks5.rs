@@ -0,0 +1,170 @@
+//! Library for connecting to peers through a SOCKS5 proxy, such as Tor
+//!
+//! The client greets the proxy offering no authentication, and then asks it to connect to the
+//! target (RFC 1928). Targets can be given by host name, so that they are resolved by the proxy:
+//! this is the only way to reach Tor onion services, and it keeps the names of the peers out of
+//! the local DNS queries.
+
+use std::net::{IpAddr, SocketAddr};
+
+use failure::Fail;
+
+const VERSION: u8 = 5;
+const METHOD_NO_AUTHENTICATION: u8 = 0;
+const COMMAND_CONNECT: u8 = 1;
+const ADDRESS_TYPE_IPV4: u8 = 1;
+const ADDRESS_TYPE_DOMAIN: u8 = 3;
+const ADDRESS_TYPE_IPV6: u8 = 4;
+
+/// Length of the response of the proxy to the greeting
+pub const METHOD_SELECTION_LENGTH: usize = 2;
+
+/// Length of the beginning of the reply to a connect request, which determines the length of
+/// the whole reply
+pub const REPLY_HEADER_LENGTH: usize = 5;
+
+/// The error type for the SOCKS5 handshake
+#[derive(Debug, PartialEq, Fail)]
+pub enum Socks5Error {
+    /// The response is too short or has an unknown version or address type
+    #[fail(display = "Malformed SOCKS5 response")]
+    Malformed,
+    /// The proxy requires an authentication method that is not supported
+    #[fail(display = "SOCKS5 proxy requires an unsupported authentication method")]
+    UnsupportedAuthentication,
+    /// The host is not a `hostname:port` pair
+    #[fail(display = "Invalid host {:?}, expected hostname:port", host)]
+    InvalidHost {
+        /// Host given as target
+        host: String,
+    },
+    /// The proxy could not connect to the target
+    #[fail(display = "SOCKS5 proxy failed to connect: {}", reason)]
+    ConnectFailed {
+        /// Reply code of the proxy
+        code: u8,
+        /// Description of the reply code
+        reason: &'static str,
+    },
+}
+
+/// Target of a connection through the proxy
+#[derive(Clone, Debug, PartialEq)]
+pub enum Target {
+    /// Socket address of the peer
+    Address(SocketAddr),
+    /// Host name and port of the peer, resolved by the proxy
+    Host(String, u16),
+}
+
+impl Target {
+    /// Parse a `hostname:port` host. Hosts that are socket addresses are not resolved.
+    pub fn parse_host(host: &str) -> Result<Self, Socks5Error> {
+        if let Ok(address) = host.parse() {
+            return Ok(Target::Address(address));
+        }
+        let invalid_host = || Socks5Error::InvalidHost {
+            host: host.to_string(),
+        };
+
+        let separator = host.rfind(':').ok_or_else(invalid_host)?;
+        let (name, port) = (&host[..separator], &host[separator + 1..]);
+        let port = port.parse().map_err(|_| invalid_host())?;
+        // The length of the name is encoded in a single byte
+        if name.is_empty() || name.len() > 255 || name.contains(':') {
+            Err(invalid_host())?
+        }
+
+        Ok(Target::Host(name.to_string(), port))
+    }
+}
+
+/// Check if a `hostname:port` host is a Tor onion service, which can only be reached through
+/// a Tor proxy
+pub fn is_onion_host(host: &str) -> bool {
+    match Target::parse_host(host) {
+        Ok(Target::Host(name, _)) => name.to_ascii_lowercase().ends_with(".onion"),
+        _ => false,
+    }
+}
+
+/// Build the greeting sent to the proxy, offering no authentication
+pub fn greeting() -> Vec<u8> {
+    vec![VERSION, 1, METHOD_NO_AUTHENTICATION]
+}
+
+/// Parse the response of the proxy to the greeting
+pub fn parse_method_selection(response: &[u8]) -> Result<(), Socks5Error> {
+    match response {
+        [VERSION, METHOD_NO_AUTHENTICATION] => Ok(()),
+        // Including 0xff, no acceptable method
+        [VERSION, _] => Err(Socks5Error::UnsupportedAuthentication),
+        _ => Err(Socks5Error::Malformed),
+    }
+}
+
+/// Build the request to connect to `target`
+pub fn connect_request(target: &Target) -> Vec<u8> {
+    let mut request = vec![VERSION, COMMAND_CONNECT, 0];
+    let port = match target {
+        Target::Address(address) => {
+            match address.ip() {
+                IpAddr::V4(ip) => {
+                    request.push(ADDRESS_TYPE_IPV4);
+                    request.extend_from_slice(&ip.octets());
+                }
+                IpAddr::V6(ip) => {
+                    request.push(ADDRESS_TYPE_IPV6);
+                    request.extend_from_slice(&ip.octets());
+                }
+            }
+            address.port()
+        }
+        Target::Host(name, port) => {
+            request.push(ADDRESS_TYPE_DOMAIN);
+            request.push(name.len() as u8);
+            request.extend_from_slice(name.as_bytes());
+            *port
+        }
+    };
+    request.extend_from_slice(&port.to_be_bytes());
+
+    request
+}
+
+/// Parse the first `REPLY_HEADER_LENGTH` bytes of the reply to a connect request and return the
+/// length of the whole reply, which ends with the address bound by the proxy
+pub fn parse_reply_header(header: &[u8]) -> Result<usize, Socks5Error> {
+    if header.len() < REPLY_HEADER_LENGTH || header[0] != VERSION {
+        Err(Socks5Error::Malformed)?
+    }
+    let code = header[1];
+    if code != 0 {
+        Err(Socks5Error::ConnectFailed {
+            code,
+            reason: reply_reason(code),
+        })?
+    }
+
+    // Version, reply code, reserved byte and address type, followed by the address and the port
+    match header[3] {
+        ADDRESS_TYPE_IPV4 => Ok(4 + 4 + 2),
+        ADDRESS_TYPE_IPV6 => Ok(4 + 16 + 2),
+        ADDRESS_TYPE_DOMAIN => Ok(4 + 1 + header[4] as usize + 2),
+        _ => Err(Socks5Error::Malformed),
+    }
+}
+
+fn reply_reason(code: u8) -> &'static str {
+    match code {
+        1 => "general failure",
+        2 => "connection not allowed by ruleset",
+        3 => "network unreachable",
+        4 => "host unreachable",
+        5 => "connection refused",
+        6 => "TTL expired",
+        7 => "command not supported",
+        8 => "address type not supported",
+        _ => "unknown error",
+    }
+}
@@ -0,0 +1,114 @@
+//! Library for classifying peers according to the user agent they announce
+//!
+//! Patterns are matched against the whole user agent and may contain any
+//! number of `*` wildcards, e.g. `full-node-desktop-edition/0.1.*`.
+
+/// Result of matching a user agent against the configured patterns
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UserAgentMatch {
+    /// The user agent matches one of the patterns to avoid
+    Avoided,
+    /// The user agent matches one of the preferred patterns
+    Preferred,
+    /// The user agent matches no pattern
+    Neutral,
+}
+
+/// Number of user agents classified by a filter
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct UserAgentMetrics {
+    /// User agents matching a pattern to avoid
+    pub avoided: u64,
+    /// User agents matching a preferred pattern
+    pub preferred: u64,
+    /// User agents matching no pattern
+    pub neutral: u64,
+}
+
+/// Filter of peers by user agent pattern
+///
+/// Preferred patterns take precedence over the patterns to avoid, so that a
+/// broad pattern can be avoided while still allowing specific versions.
+#[derive(Clone, Debug, Default)]
+pub struct UserAgentFilter {
+    /// Patterns of user agents to avoid
+    avoid: Vec<String>,
+    /// Patterns of preferred user agents
+    prefer: Vec<String>,
+    /// Classification counters
+    metrics: UserAgentMetrics,
+}
+
+impl UserAgentFilter {
+    /// Create a new filter from the lists of patterns to avoid and to prefer
+    pub fn new(avoid: Vec<String>, prefer: Vec<String>) -> Self {
+        UserAgentFilter {
+            avoid,
+            prefer,
+            metrics: UserAgentMetrics::default(),
+        }
+    }
+
+    /// Classify a user agent without updating the metrics
+    pub fn classify(&self, user_agent: &str) -> UserAgentMatch {
+        if self
+            .prefer
+            .iter()
+            .any(|pattern| pattern_matches(pattern, user_agent))
+        {
+            UserAgentMatch::Preferred
+        } else if self
+            .avoid
+            .iter()
+            .any(|pattern| pattern_matches(pattern, user_agent))
+        {
+            UserAgentMatch::Avoided
+        } else {
+            UserAgentMatch::Neutral
+        }
+    }
+
+    /// Classify a user agent and account for it in the metrics
+    pub fn check(&mut self, user_agent: &str) -> UserAgentMatch {
+        let result = self.classify(user_agent);
+        match result {
+            UserAgentMatch::Avoided => self.metrics.avoided += 1,
+            UserAgentMatch::Preferred => self.metrics.preferred += 1,
+            UserAgentMatch::Neutral => self.metrics.neutral += 1,
+        }
+
+        result
+    }
+
+    /// Get the classification counters
+    pub fn metrics(&self) -> UserAgentMetrics {
+        self.metrics
+    }
+}
+
+/// Check whether a user agent matches a pattern with `*` wildcards
+pub fn pattern_matches(pattern: &str, user_agent: &str) -> bool {
+    let mut parts = pattern.split('*');
+    // `split` always yields at least one element
+    let first = parts.next().unwrap_or("");
+    if !user_agent.starts_with(first) {
+        return false;
+    }
+    let mut rest = &user_agent[first.len()..];
+
+    let parts: Vec<&str> = parts.collect();
+    match parts.split_last() {
+        // No wildcards: the whole user agent must match
+        None => rest.is_empty(),
+        Some((last, middle)) => {
+            for part in middle {
+                match rest.find(part) {
+                    Some(index) => rest = &rest[index + part.len()..],
+                    None => return false,
+                }
+            }
+
+            rest.ends_with(last)
+        }
+    }
+}
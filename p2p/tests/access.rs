@@ -0,0 +1,76 @@
+use witnet_p2p::sessions::access::*;
+
+/// Check the parsing and formatting of IP addresses and CIDR ranges
+#[test]
+fn p2p_access_parse_ip_range() {
+    let roundtrip = |range: &str| range.parse::<IpRange>().unwrap().to_string();
+
+    assert_eq!(roundtrip("10.0.0.1"), "10.0.0.1");
+    assert_eq!(roundtrip("10.0.0.0/8"), "10.0.0.0/8");
+    assert_eq!(roundtrip("10.0.0.1/32"), "10.0.0.1");
+    assert_eq!(roundtrip("2001:db8::/32"), "2001:db8::/32");
+    assert_eq!(roundtrip("::ffff:10.0.0.1"), "10.0.0.1");
+
+    for invalid in &[
+        "",
+        "10.0.0",
+        "10.0.0.0/33",
+        "2001:db8::/129",
+        "10.0.0.0/",
+        "host/8",
+    ] {
+        assert_eq!(
+            invalid.parse::<IpRange>(),
+            Err(AccessError::InvalidIpRange {
+                range: invalid.to_string()
+            })
+        );
+    }
+}
+
+/// Check which IP addresses belong to a range
+#[test]
+fn p2p_access_ip_range_contains() {
+    let contains = |range: &str, ip: &str| {
+        range
+            .parse::<IpRange>()
+            .unwrap()
+            .contains(ip.parse().unwrap())
+    };
+
+    assert!(contains("10.0.0.1", "10.0.0.1"));
+    assert!(!contains("10.0.0.1", "10.0.0.2"));
+    assert!(contains("10.0.0.0/8", "10.255.0.1"));
+    assert!(!contains("10.0.0.0/8", "11.0.0.1"));
+    assert!(contains("192.168.0.0/23", "192.168.1.200"));
+    assert!(!contains("192.168.0.0/23", "192.168.2.1"));
+    assert!(contains("0.0.0.0/0", "1.2.3.4"));
+    assert!(contains("2001:db8::/32", "2001:db8:ffff::1"));
+    assert!(!contains("2001:db8::/32", "2001:db9::1"));
+
+    // IPv4-mapped addresses are matched as IPv4, and IPv4 ranges never match IPv6 addresses
+    assert!(contains("10.0.0.0/8", "::ffff:10.0.0.1"));
+    assert!(!contains("0.0.0.0/0", "2001:db8::1"));
+}
+
+/// Check that the blacklist takes precedence over the whitelist
+#[test]
+fn p2p_access_list_is_allowed() {
+    let is_allowed =
+        |access_list: &AccessList, ip: &str| access_list.is_allowed(ip.parse().unwrap());
+
+    // Everything is allowed by an empty access list
+    let access_list = AccessList::default();
+    assert!(is_allowed(&access_list, "1.2.3.4"));
+
+    let access_list = AccessList::parse(&["10.0.0.0/8"], &["10.0.0.13"]).unwrap();
+    assert!(is_allowed(&access_list, "10.0.0.1"));
+    assert!(!is_allowed(&access_list, "10.0.0.13"));
+    assert!(!is_allowed(&access_list, "1.2.3.4"));
+
+    let access_list = AccessList::parse(&[], &["2001:db8::/32"]).unwrap();
+    assert!(is_allowed(&access_list, "1.2.3.4"));
+    assert!(!is_allowed(&access_list, "2001:db8::1"));
+
+    assert!(AccessList::parse(&["10.0.0.0/8", "invalid"], &[]).is_err());
+}
@@ -0,0 +1,63 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use witnet_p2p::bandwidth::*;
+
+/// Check that the bucket is filled at the given rate, up to one second worth of bytes
+#[test]
+fn p2p_bandwidth_token_bucket() {
+    let start = Instant::now();
+    let mut bucket = TokenBucket::new(1000, start);
+    assert_eq!(bucket.available(start), 1000);
+
+    bucket.consume(1000);
+    assert_eq!(bucket.available(start), 0);
+    assert_eq!(bucket.wait_time(start), Duration::from_millis(1));
+    assert_eq!(bucket.available(start + Duration::from_millis(250)), 250);
+    assert_eq!(
+        bucket.wait_time(start + Duration::from_millis(250)),
+        Duration::from_secs(0)
+    );
+
+    // The bucket never holds more than one second worth of bytes
+    assert_eq!(bucket.available(start + Duration::from_secs(10)), 1000);
+}
+
+/// Check that a zero rate means no limit
+#[test]
+fn p2p_bandwidth_unlimited() {
+    let now = Instant::now();
+    let mut bucket = TokenBucket::new(0, now);
+    assert!(bucket.is_unlimited());
+
+    bucket.consume(1_000_000);
+    assert_eq!(bucket.available(now), u64::max_value());
+
+    let mut limiter = BandwidthLimiter::new(0, None, now);
+    limiter.consume(1_000_000);
+    assert_eq!(limiter.allowance(now), Ok(u64::max_value()));
+}
+
+/// Check that the sessions sharing the total bucket are limited by it
+#[test]
+fn p2p_bandwidth_shared_total() {
+    let start = Instant::now();
+    let total = Arc::new(Mutex::new(TokenBucket::new(1500, start)));
+    let mut first = BandwidthLimiter::new(1000, Some(total.clone()), start);
+    let mut second = BandwidthLimiter::new(1000, Some(total), start);
+
+    assert_eq!(first.allowance(start), Ok(1000));
+    first.consume(1000);
+    assert_eq!(first.allowance(start), Err(Duration::from_millis(1)));
+
+    // Only what is left of the total bucket can be used by the second session
+    assert_eq!(second.allowance(start), Ok(500));
+    second.consume(500);
+    assert_eq!(second.allowance(start), Err(Duration::from_nanos(666_667)));
+
+    let later = start + Duration::from_millis(100);
+    assert_eq!(first.allowance(later), Ok(100));
+    assert_eq!(second.allowance(later), Ok(150));
+}
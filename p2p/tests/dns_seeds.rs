@@ -0,0 +1,38 @@
+use witnet_p2p::dns_seeds::*;
+
+#[test]
+fn p2p_dns_seeds_default_port() {
+    assert_eq!(
+        seed_host_port("dnsseed.witnet.io", 21337),
+        ("dnsseed.witnet.io", 21337)
+    );
+    assert_eq!(
+        seed_host_port(" dnsseed.witnet.io ", 21337),
+        ("dnsseed.witnet.io", 21337)
+    );
+    assert_eq!(seed_host_port("::1", 21337), ("::1", 21337));
+}
+
+#[test]
+fn p2p_dns_seeds_given_port() {
+    assert_eq!(
+        seed_host_port("dnsseed.witnet.io:11337", 21337),
+        ("dnsseed.witnet.io", 11337)
+    );
+    assert_eq!(
+        seed_host_port("127.0.0.1:11337", 21337),
+        ("127.0.0.1", 11337)
+    );
+}
+
+#[test]
+fn p2p_dns_seeds_invalid_port() {
+    assert_eq!(
+        seed_host_port("dnsseed.witnet.io:port", 21337),
+        ("dnsseed.witnet.io:port", 21337)
+    );
+    assert_eq!(
+        seed_host_port("dnsseed.witnet.io:70000", 21337),
+        ("dnsseed.witnet.io:70000", 21337)
+    );
+}
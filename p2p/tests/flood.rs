@@ -0,0 +1,55 @@
+use witnet_p2p::sessions::flood::*;
+
+const LIMITS: &[(&str, MessageRate)] = &[(
+    "PING",
+    MessageRate {
+        burst: 3,
+        interval: 1_000,
+    },
+)];
+
+/// Check that a burst of messages is accepted, and then one message per interval
+#[test]
+fn p2p_flood_burst_and_interval() {
+    let mut flood = FloodProtection::new(LIMITS, 0);
+
+    assert!(flood.message_received("PING", 0));
+    assert!(flood.message_received("PING", 0));
+    assert!(flood.message_received("PING", 0));
+    assert!(!flood.message_received("PING", 0));
+    assert!(!flood.message_received("PING", 999));
+    assert!(flood.message_received("PING", 1_000));
+    assert!(!flood.message_received("PING", 1_500));
+    assert!(flood.message_received("PING", 2_000));
+    assert_eq!(flood.violations(), 3);
+
+    // The burst is available again after a quiet period, but not more than the burst
+    for _ in 0..3 {
+        assert!(flood.message_received("PING", 60_000));
+    }
+    assert!(!flood.message_received("PING", 60_000));
+    assert_eq!(flood.violations(), 4);
+}
+
+/// Check that the commands without a limit are always accepted
+#[test]
+fn p2p_flood_unlimited_commands() {
+    let mut flood = FloodProtection::new(LIMITS, 0);
+
+    for _ in 0..100 {
+        assert!(flood.message_received("BLOCK", 0));
+    }
+    assert_eq!(flood.violations(), 0);
+
+    let mut flood = FloodProtection::new(
+        &[(
+            "PING",
+            MessageRate {
+                burst: 0,
+                interval: 0,
+            },
+        )],
+        0,
+    );
+    assert!(flood.message_received("PING", 0));
+}
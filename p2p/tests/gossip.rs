@@ -0,0 +1,44 @@
+use witnet_p2p::peers::gossip::*;
+
+/// Check that at most one GetPeers message is answered per interval
+#[test]
+fn p2p_gossip_get_peers_interval() {
+    let mut gossip = GossipLimiter::new(10);
+
+    assert!(gossip.get_peers_received(100));
+    assert!(!gossip.get_peers_received(100));
+    assert!(!gossip.get_peers_received(109));
+    assert!(gossip.get_peers_received(110));
+
+    // Ignored messages do not delay the next one
+    assert!(!gossip.get_peers_received(115));
+    assert!(gossip.get_peers_received(120));
+}
+
+/// Check that solicited Peers messages are always accepted, and unsolicited ones at most once per
+/// interval
+#[test]
+fn p2p_gossip_peers_solicited() {
+    let mut gossip = GossipLimiter::new(10);
+
+    assert!(gossip.peers_received(100));
+    assert!(!gossip.peers_received(105));
+
+    // The answer to a GetPeers message is accepted, but only once
+    gossip.get_peers_sent();
+    assert!(gossip.peers_received(106));
+    assert!(!gossip.peers_received(107));
+
+    assert!(gossip.peers_received(110));
+}
+
+/// Check that no message is limited with a zero interval
+#[test]
+fn p2p_gossip_no_interval() {
+    let mut gossip = GossipLimiter::new(0);
+
+    assert!(gossip.get_peers_received(100));
+    assert!(gossip.get_peers_received(100));
+    assert!(gossip.peers_received(100));
+    assert!(gossip.peers_received(100));
+}
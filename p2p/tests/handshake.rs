@@ -0,0 +1,53 @@
+use witnet_p2p::{error::SessionsError, sessions::handshake::*};
+
+/// Check the steps of the handshake
+#[test]
+fn p2p_handshake_state() {
+    let mut handshake = Handshake::default();
+    assert_eq!(handshake.state(), HandshakeState::AwaitingVersion);
+
+    // Outbound sessions send their version first
+    handshake.version_tx = true;
+    assert_eq!(handshake.state(), HandshakeState::AwaitingVersion);
+
+    handshake.version_rx = true;
+    handshake.verack_tx = true;
+    assert_eq!(handshake.state(), HandshakeState::AwaitingVerack);
+    assert!(!handshake.is_completed());
+
+    handshake.verack_rx = true;
+    assert_eq!(handshake.state(), HandshakeState::Completed);
+    assert!(handshake.is_completed());
+}
+
+/// Check that the lowest protocol version of both peers is used
+#[test]
+fn p2p_handshake_negotiate_version() {
+    let mut handshake = Handshake::default();
+    assert_eq!(handshake.protocol_version(), None);
+    assert!(!handshake.supports(1));
+
+    assert_eq!(handshake.negotiate_version(3, 2, 1), Ok(2));
+    assert_eq!(handshake.protocol_version(), Some(2));
+    assert!(handshake.supports(2));
+    assert!(!handshake.supports(3));
+
+    // Newer peers use the local version
+    assert_eq!(handshake.negotiate_version(3, 5, 1), Ok(3));
+    assert_eq!(handshake.protocol_version(), Some(3));
+}
+
+/// Check that peers older than the minimum version are refused
+#[test]
+fn p2p_handshake_negotiate_unsupported_version() {
+    let mut handshake = Handshake::default();
+
+    assert_eq!(
+        handshake.negotiate_version(3, 1, 2),
+        Err(SessionsError::UnsupportedVersion {
+            version: 1,
+            min_version: 2,
+        })
+    );
+    assert_eq!(handshake.protocol_version(), None);
+}
@@ -0,0 +1,66 @@
+use std::{net::SocketAddr, time::Duration};
+
+use witnet_p2p::happy_eyeballs::*;
+
+fn addresses(addresses: &[&str]) -> Vec<SocketAddr> {
+    addresses
+        .iter()
+        .map(|address| address.parse().unwrap())
+        .collect()
+}
+
+#[test]
+fn p2p_happy_eyeballs_interleave_families() {
+    let resolved = addresses(&[
+        "192.168.1.1:21337",
+        "192.168.1.2:21337",
+        "192.168.1.3:21337",
+        "[2001:db8::1]:21337",
+        "[2001:db8::2]:21337",
+    ]);
+
+    assert_eq!(
+        interleave_addresses(resolved),
+        addresses(&[
+            "[2001:db8::1]:21337",
+            "192.168.1.1:21337",
+            "[2001:db8::2]:21337",
+            "192.168.1.2:21337",
+            "192.168.1.3:21337",
+        ])
+    );
+}
+
+#[test]
+fn p2p_happy_eyeballs_single_family() {
+    let resolved = addresses(&["192.168.1.2:21337", "192.168.1.1:21337"]);
+
+    assert_eq!(interleave_addresses(resolved.clone()), resolved);
+    assert!(interleave_addresses(vec![]).is_empty());
+}
+
+#[test]
+fn p2p_happy_eyeballs_remove_duplicates() {
+    let resolved = addresses(&[
+        "192.168.1.1:21337",
+        "[2001:db8::1]:21337",
+        "192.168.1.1:21337",
+        "192.168.1.1:21338",
+    ]);
+
+    assert_eq!(
+        interleave_addresses(resolved),
+        addresses(&[
+            "[2001:db8::1]:21337",
+            "192.168.1.1:21337",
+            "192.168.1.1:21338",
+        ])
+    );
+}
+
+#[test]
+fn p2p_happy_eyeballs_attempt_delay() {
+    assert_eq!(attempt_delay(0), Duration::from_secs(0));
+    assert_eq!(attempt_delay(1), CONNECTION_ATTEMPT_DELAY);
+    assert_eq!(attempt_delay(4), Duration::from_secs(1));
+}
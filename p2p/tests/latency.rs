@@ -0,0 +1,57 @@
+use witnet_p2p::sessions::latency::*;
+
+/// Check the round-trip times measured from the pongs
+#[test]
+fn p2p_latency_round_trip_time() {
+    let mut latency = LatencyTracker::default();
+    assert_eq!(latency.stats(), LatencyStats::default());
+
+    latency.ping_sent(1, 1_000);
+    assert_eq!(latency.pong_received(1, 1_200), Some(200));
+    assert_eq!(latency.stats().smoothed_rtt, Some(200));
+    assert_eq!(latency.stats().last_rtt, Some(200));
+
+    // The smoothed round-trip time moves 1/8 of the way to each new measure
+    latency.ping_sent(2, 2_000);
+    assert_eq!(latency.pong_received(2, 2_600), Some(600));
+    assert_eq!(latency.stats().smoothed_rtt, Some(250));
+    assert_eq!(latency.stats().last_rtt, Some(600));
+
+    // Pongs answering an unknown or an already answered ping are ignored
+    assert_eq!(latency.pong_received(2, 2_700), None);
+    assert_eq!(latency.pong_received(3, 2_700), None);
+    assert_eq!(latency.stats().smoothed_rtt, Some(250));
+}
+
+/// Check the count of consecutive missed pongs
+#[test]
+fn p2p_latency_missed_pongs() {
+    let mut latency = LatencyTracker::default();
+
+    latency.ping_sent(1, 1_000);
+    assert_eq!(latency.expire_pings(1_000), 0);
+    latency.ping_sent(2, 2_000);
+    assert_eq!(latency.expire_pings(2_000), 1);
+    latency.ping_sent(3, 3_000);
+    assert_eq!(latency.expire_pings(3_000), 2);
+    assert_eq!(latency.stats().missed_pongs, 2);
+
+    // Expired pings cannot be answered anymore
+    assert_eq!(latency.pong_received(1, 3_100), None);
+    assert_eq!(latency.stats().missed_pongs, 2);
+
+    // A pong resets the count
+    assert_eq!(latency.pong_received(3, 3_100), Some(100));
+    assert_eq!(latency.stats().missed_pongs, 0);
+    assert_eq!(latency.expire_pings(4_000), 0);
+}
+
+/// Check the timestamp of the last message received from the peer
+#[test]
+fn p2p_latency_last_seen() {
+    let mut latency = LatencyTracker::default();
+
+    latency.message_received(1_000);
+    latency.message_received(1_500);
+    assert_eq!(latency.stats().last_seen, Some(1_500));
+}
@@ -1,8 +1,47 @@
 /// Peers library tests
 pub mod peers;
 
+/// Gossip library tests
+pub mod gossip;
+
 /// Bounded Sessions library tests
 pub mod bounded_sessions;
 
 /// Sesssions library tests
 pub mod sessions;
+
+/// Handshake library tests
+pub mod handshake;
+
+/// Access list library tests
+pub mod access;
+
+/// Flood protection library tests
+pub mod flood;
+
+/// Latency library tests
+pub mod latency;
+
+/// User agents library tests
+pub mod user_agents;
+
+/// mDNS library tests
+pub mod mdns;
+
+/// Protocol metrics library tests
+pub mod metrics;
+
+/// Happy Eyeballs library tests
+pub mod happy_eyeballs;
+
+/// DNS seeds library tests
+pub mod dns_seeds;
+
+/// NAT-PMP library tests
+pub mod nat_pmp;
+
+/// SOCKS5 library tests
+pub mod socks5;
+
+/// Bandwidth library tests
+pub mod bandwidth;
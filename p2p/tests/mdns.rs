@@ -0,0 +1,114 @@
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+use witnet_p2p::mdns::*;
+
+const SERVICE: &str = "_witnet._tcp.local";
+
+#[test]
+fn p2p_mdns_query() {
+    let query = build_query(SERVICE);
+
+    assert!(is_query(&query, SERVICE));
+    assert!(is_query(&query, "_WITNET._tcp.local"));
+    assert!(!is_query(&query, "_other._tcp.local"));
+    // A query is not an announcement
+    assert_eq!(parse_announcement(&query, SERVICE), None);
+}
+
+#[test]
+fn p2p_mdns_announcement_with_address() {
+    let server_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 10)), 21337);
+    let source = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 20)), MDNS_PORT);
+    let announcement = build_announcement(SERVICE, "node-a", server_addr);
+
+    assert!(!is_query(&announcement, SERVICE));
+    let parsed = parse_announcement(&announcement, SERVICE).unwrap();
+    assert_eq!(
+        parsed,
+        Announcement {
+            instance: "node-a._witnet._tcp.local".to_string(),
+            port: 21337,
+            ip: Some(Ipv4Addr::new(192, 168, 1, 10)),
+        }
+    );
+    assert_eq!(parsed.peer_address(source), server_addr);
+
+    // Announcements of other services are ignored
+    assert_eq!(parse_announcement(&announcement, "_other._tcp.local"), None);
+}
+
+#[test]
+fn p2p_mdns_announcement_without_address() {
+    let server_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 21337);
+    let source = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 20)), MDNS_PORT);
+    let announcement = build_announcement(SERVICE, "node-b", server_addr);
+
+    let parsed = parse_announcement(&announcement, SERVICE).unwrap();
+    assert_eq!(parsed.ip, None);
+    assert_eq!(
+        parsed.peer_address(source),
+        SocketAddr::new(source.ip(), 21337)
+    );
+}
+
+#[test]
+fn p2p_mdns_compressed_names() {
+    // Response with a PTR record and a SRV record using compression pointers
+    let mut packet = vec![0, 0, 0x84, 0, 0, 0, 0, 2, 0, 0, 0, 0];
+    // PTR record, its name (_witnet._tcp.local) starts at offset 12
+    packet.extend_from_slice(b"\x07_witnet\x04_tcp\x05local\x00");
+    packet.extend_from_slice(&[0, 12, 0, 1, 0, 0, 0, 120, 0, 9]);
+    // PTR target starts at offset 42: node-c + pointer to the service name
+    packet.extend_from_slice(b"\x06node-c");
+    packet.extend_from_slice(&[0xC0, 12]);
+    // SRV record named after the PTR target, with port 0x5339 and a pointer as target
+    packet.extend_from_slice(&[0xC0, 42, 0, 33, 0x80, 1, 0, 0, 0, 120, 0, 8]);
+    packet.extend_from_slice(&[0, 0, 0, 0, 0x53, 0x39, 0xC0, 42]);
+
+    let parsed = parse_announcement(&packet, SERVICE).unwrap();
+    assert_eq!(parsed.instance, "node-c._witnet._tcp.local");
+    assert_eq!(parsed.port, 0x5339);
+    assert_eq!(parsed.ip, None);
+}
+
+#[test]
+fn p2p_mdns_malformed_packets() {
+    let announcement = build_announcement(
+        SERVICE,
+        "node-d",
+        SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), 21337),
+    );
+
+    // Truncated packets are rejected without panicking
+    for len in 0..announcement.len() {
+        assert!(!is_query(&announcement[..len], SERVICE));
+        let _ = parse_announcement(&announcement[..len], SERVICE);
+    }
+
+    // Compression pointer loops are rejected
+    let mut packet = vec![0, 0, 0x84, 0, 0, 0, 0, 1, 0, 0, 0, 0];
+    packet.extend_from_slice(&[0xC0, 12]);
+    assert_eq!(parse_announcement(&packet, SERVICE), None);
+}
+
+#[test]
+fn p2p_mdns_local_addresses() {
+    let local = [
+        "127.0.0.1",
+        "10.1.2.3",
+        "172.16.0.1",
+        "192.168.1.10",
+        "169.254.0.1",
+        "::1",
+        "fd00::1",
+        "fe80::1",
+    ];
+    let public = ["8.8.8.8", "172.32.0.1", "2001:db8::1"];
+
+    for ip in local.iter() {
+        assert!(is_local_address(&ip.parse().unwrap()), "{}", ip);
+    }
+    for ip in public.iter() {
+        assert!(!is_local_address(&ip.parse().unwrap()), "{}", ip);
+    }
+}
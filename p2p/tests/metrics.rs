@@ -0,0 +1,54 @@
+use witnet_p2p::metrics::*;
+
+#[test]
+fn p2p_metrics_command_totals() {
+    let mut totals = CommandTotals::default();
+    assert_eq!(totals.size_histogram.len(), SIZE_BUCKETS.len() + 1);
+
+    totals.record(0);
+    totals.record(128);
+    totals.record(129);
+    totals.record(3 * 1024 * 1024);
+
+    assert_eq!(totals.messages, 4);
+    assert_eq!(totals.bytes, 128 + 129 + 3 * 1024 * 1024);
+    assert_eq!(totals.size_histogram[0], 2);
+    assert_eq!(totals.size_histogram[1], 1);
+    assert_eq!(totals.size_histogram[SIZE_BUCKETS.len()], 1);
+}
+
+#[test]
+fn p2p_metrics_network_totals() {
+    let mut totals = NetworkTotals::default();
+
+    totals.record(Direction::Received, "BLOCK", 1000);
+    totals.record(Direction::Received, "BLOCK", 3000);
+    totals.record(Direction::Received, "PING", 10);
+    totals.record(Direction::Sent, "PONG", 10);
+
+    assert_eq!(totals.received.len(), 2);
+    assert_eq!(totals.received["BLOCK"].messages, 2);
+    assert_eq!(totals.received["BLOCK"].bytes, 4000);
+    assert_eq!(totals.sent.len(), 1);
+    assert_eq!(totals.sent["PONG"].messages, 1);
+
+    let received = totals.total(Direction::Received);
+    assert_eq!(received.messages, 3);
+    assert_eq!(received.bytes, 4010);
+    assert_eq!(received.size_histogram.iter().sum::<u64>(), 3);
+    assert_eq!(totals.total(Direction::Sent).bytes, 10);
+}
+
+#[test]
+fn p2p_metrics_rejected_versions() {
+    let mut totals = NetworkTotals::default();
+    assert!(totals.rejected_versions.is_empty());
+
+    totals.record_rejected_version(1);
+    totals.record_rejected_version(1);
+    totals.record_rejected_version(0);
+
+    assert_eq!(totals.rejected_versions.len(), 2);
+    assert_eq!(totals.rejected_versions[&0], 1);
+    assert_eq!(totals.rejected_versions[&1], 2);
+}
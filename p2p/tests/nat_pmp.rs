@@ -0,0 +1,89 @@
+use std::net::Ipv4Addr;
+
+use witnet_p2p::nat_pmp::*;
+
+#[test]
+fn p2p_nat_pmp_requests() {
+    assert_eq!(external_address_request(), vec![0, 0]);
+    assert_eq!(
+        tcp_mapping_request(21337, 21338, DEFAULT_MAPPING_LIFETIME),
+        vec![0, 2, 0, 0, 0x53, 0x59, 0x53, 0x5a, 0, 0, 0x1c, 0x20]
+    );
+}
+
+#[test]
+fn p2p_nat_pmp_external_address_response() {
+    let response = [0, 128, 0, 0, 0, 0, 1, 0, 203, 0, 113, 7];
+
+    assert_eq!(
+        parse_external_address_response(&response),
+        Ok(ExternalAddress {
+            epoch: 256,
+            address: Ipv4Addr::new(203, 0, 113, 7),
+        })
+    );
+    assert_eq!(
+        parse_external_address_response(&response[..11]),
+        Err(NatPmpError::Malformed)
+    );
+}
+
+#[test]
+fn p2p_nat_pmp_mapping_response() {
+    let response = [
+        0, 130, 0, 0, 0, 0, 0, 10, 0x53, 0x59, 0x53, 0x5b, 0, 0, 0x0e, 0x10,
+    ];
+
+    assert_eq!(
+        parse_mapping_response(&response),
+        Ok(Mapping {
+            epoch: 10,
+            internal_port: 21337,
+            external_port: 21339,
+            lifetime: 3600,
+        })
+    );
+
+    // The response to another request is rejected
+    assert_eq!(
+        parse_external_address_response(&response),
+        Err(NatPmpError::UnexpectedOpcode { opcode: 130 })
+    );
+}
+
+#[test]
+fn p2p_nat_pmp_result_code() {
+    // NAT-PMP disabled in the gateway: not authorized
+    let response = [0, 130, 0, 2, 0, 0, 0, 10, 0x53, 0x59, 0, 0, 0, 0, 0, 0];
+
+    assert_eq!(
+        parse_mapping_response(&response),
+        Err(NatPmpError::ResultCode { code: 2 })
+    );
+}
+
+#[test]
+fn p2p_nat_pmp_default_gateway() {
+    let route_table = "\
+Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\t\tMTU\tWindow\tIRTT
+eth0\t0000A8C0\t00000000\t0001\t0\t0\t0\t00FFFFFF\t0\t0\t0
+eth0\t00000000\t0100A8C0\t0003\t0\t0\t0\t00000000\t0\t0\t0
+";
+
+    assert_eq!(
+        parse_default_gateway(route_table),
+        Some(Ipv4Addr::new(192, 168, 0, 1))
+    );
+    assert_eq!(parse_default_gateway(""), None);
+    assert_eq!(
+        parse_default_gateway(
+            route_table
+                .lines()
+                .take(2)
+                .collect::<Vec<_>>()
+                .join("\n")
+                .as_str()
+        ),
+        None
+    );
+}
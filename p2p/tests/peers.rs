@@ -1,6 +1,7 @@
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 
 use witnet_p2p::peers::*;
+use witnet_util::timestamp::get_timestamp;
 
 #[test]
 fn p2p_peers_add() {
@@ -110,3 +111,236 @@ fn p2p_peers_get_all() {
     added_peers.sort_by(sort_by_ip_then_port);
     assert_eq!(many_peers, added_peers);
 }
+
+#[test]
+fn p2p_peers_evict() {
+    // Create peers struct
+    let mut peers = Peers::default();
+    assert_eq!(peers.memory_usage(), 0);
+
+    // Add 10 addresses
+    let many_peers = (0..10)
+        .map(|i| SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 1, i)), 8080))
+        .collect();
+    peers.add(many_peers).unwrap();
+    let peer_size = peers.memory_usage() / 10;
+
+    // Nothing is evicted while the memory usage is below the cap
+    assert_eq!(peers.evict(peer_size * 10).unwrap(), vec![]);
+
+    // Evict until only 4 peers fit
+    assert_eq!(peers.evict(peer_size * 4).unwrap().len(), 6);
+    assert_eq!(peers.get_all().unwrap().len(), 4);
+    assert_eq!(peers.memory_usage(), peer_size * 4);
+
+    // A cap of 0 bytes evicts every peer
+    assert_eq!(peers.evict(0).unwrap().len(), 4);
+    assert_eq!(peers.get_all().unwrap().len(), 0);
+}
+
+#[test]
+fn p2p_seen_addresses() {
+    let mut seen = SeenAddresses::with_capacity(2);
+    let address = |i| SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, i)), 8080);
+
+    assert!(seen.insert(&address(1)));
+    assert!(!seen.insert(&address(1)));
+    assert!(seen.insert(&address(2)));
+    assert_eq!(seen.len(), 2);
+
+    // The oldest address is forgotten when the capacity is exceeded
+    assert!(seen.insert(&address(3)));
+    assert_eq!(seen.len(), 2);
+    assert!(seen.insert(&address(1)));
+    assert!(!seen.insert(&address(3)));
+
+    seen.clear();
+    assert!(seen.is_empty());
+    assert!(seen.insert(&address(3)));
+}
+
+#[test]
+fn p2p_peers_tried_bucket() {
+    let mut peers = Peers::default();
+    let address = |i| SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, i)), 8080);
+
+    peers.add(vec![address(1), address(2)]).unwrap();
+    assert!(!peers.is_tried(&address(1)));
+
+    // Only the addresses that were not known are returned
+    assert_eq!(
+        peers.add_tried(vec![address(1), address(3)]).unwrap(),
+        vec![address(3)]
+    );
+    assert!(peers.is_tried(&address(1)));
+    assert!(peers.is_tried(&address(3)));
+    assert!(!peers.is_tried(&address(2)));
+    assert_eq!(peers.len(), 3);
+
+    // Adding a tried address again keeps it in the tried bucket
+    assert_eq!(peers.add(vec![address(1)]).unwrap(), vec![address(1)]);
+    assert!(peers.is_tried(&address(1)));
+    assert!(peers.last_seen(&address(1)).is_some());
+    assert_eq!(peers.last_seen(&address(4)), None);
+
+    // Tried peers can be removed too
+    assert_eq!(
+        peers.remove(&[address(1), address(2)]).unwrap(),
+        vec![address(1), address(2)]
+    );
+    assert_eq!(peers.get_all().unwrap(), vec![address(3)]);
+    assert_eq!(peers.get_random().unwrap(), Some(address(3)));
+}
+
+#[test]
+fn p2p_peers_get_sample() {
+    let mut peers = Peers::default();
+    assert_eq!(peers.get_sample(10).unwrap(), vec![]);
+
+    let many_peers: Vec<_> = (0..100)
+        .map(|i| SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, i)), 8080))
+        .collect();
+    peers.add(many_peers[..50].to_vec()).unwrap();
+    peers.add_tried(many_peers[50..].to_vec()).unwrap();
+
+    let sample = peers.get_sample(10).unwrap();
+    assert_eq!(sample.len(), 10);
+    assert!(sample.iter().all(|address| many_peers.contains(address)));
+
+    // The sample is capped by the number of known peers
+    assert_eq!(peers.get_sample(1000).unwrap().len(), 100);
+}
+
+#[test]
+fn p2p_peers_get_recent_sample() {
+    let mut peers = Peers::default();
+    let now = get_timestamp();
+    assert_eq!(peers.get_recent_sample(10, now).unwrap(), vec![]);
+
+    let many_peers: Vec<_> = (0..20)
+        .map(|i| SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, i)), 8080))
+        .collect();
+    peers.add(many_peers[..10].to_vec()).unwrap();
+    peers.add_tried(many_peers[10..].to_vec()).unwrap();
+
+    let sample = peers.get_recent_sample(5, now - 60).unwrap();
+    assert_eq!(sample.len(), 5);
+    assert!(sample.iter().all(|address| many_peers.contains(address)));
+    assert_eq!(peers.get_recent_sample(100, now - 60).unwrap().len(), 20);
+
+    // Peers not seen since the given timestamp are left out
+    assert_eq!(peers.get_recent_sample(100, now + 60).unwrap(), vec![]);
+}
+
+#[test]
+fn p2p_peers_evict_new_first() {
+    let mut peers = Peers::default();
+    let address = |i| SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, i)), 8080);
+
+    peers.add_tried(vec![address(1), address(2)]).unwrap();
+    peers.add(vec![address(3), address(4)]).unwrap();
+    let peer_size = peers.memory_usage() / 4;
+
+    let mut evicted = peers.evict(peer_size * 2).unwrap();
+    evicted.sort();
+    assert_eq!(evicted, vec![address(3), address(4)]);
+    assert!(peers.is_tried(&address(1)));
+    assert!(peers.is_tried(&address(2)));
+}
+
+#[test]
+fn p2p_peers_merge() {
+    let address = |i| SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, i)), 8080);
+
+    let mut stored = Peers::default();
+    stored.add(vec![address(1)]).unwrap();
+    stored.add_tried(vec![address(2)]).unwrap();
+
+    let mut peers = Peers::default();
+    peers.add(vec![address(2), address(3)]).unwrap();
+    peers.merge(stored);
+
+    assert_eq!(peers.len(), 3);
+    assert!(!peers.is_tried(&address(1)));
+    assert!(peers.is_tried(&address(2)));
+    assert!(!peers.is_tried(&address(3)));
+}
+
+#[test]
+fn p2p_peers_persistence() {
+    let address = |i| SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, i)), 8080);
+
+    let mut peers = Peers::default();
+    peers.add(vec![address(1)]).unwrap();
+    peers.add_tried(vec![address(2)]).unwrap();
+
+    let restored: Peers = serde_json::from_slice(&serde_json::to_vec(&peers).unwrap()).unwrap();
+    assert_eq!(restored.len(), 2);
+    assert!(!restored.is_tried(&address(1)));
+    assert!(restored.is_tried(&address(2)));
+    assert_eq!(
+        restored.last_seen(&address(1)),
+        peers.last_seen(&address(1))
+    );
+
+    // Peers persisted before the tried bucket existed are loaded into the new bucket
+    let old: Peers = serde_json::from_str(
+        r#"{"peers":{"127.0.0.1:8080":{"address":"127.0.0.1:8080","_timestamp":5}}}"#,
+    )
+    .unwrap();
+    assert_eq!(old.len(), 1);
+    assert!(!old.is_tried(&address(1)));
+    assert_eq!(old.last_seen(&address(1)), Some(5));
+}
+
+#[test]
+fn p2p_peers_ipv6() {
+    let ipv6: SocketAddr = "[2001:db8::1]:21337".parse().unwrap();
+    let mapped: SocketAddr = "[::ffff:1.2.3.4]:21337".parse().unwrap();
+    let ipv4: SocketAddr = "1.2.3.4:21337".parse().unwrap();
+
+    let mut peers = Peers::default();
+    peers.add(vec![ipv6, mapped]).unwrap();
+    let mut all = peers.get_all().unwrap();
+    all.sort();
+    assert_eq!(all, vec![ipv4, ipv6]);
+
+    // IPv4-mapped addresses are the same peer as their IPv4 address
+    assert_eq!(peers.add(vec![ipv4]).unwrap(), vec![ipv4]);
+    assert_eq!(peers.add_tried(vec![mapped]).unwrap(), vec![]);
+    assert!(peers.is_tried(&ipv4));
+    assert_eq!(peers.len(), 2);
+}
+
+#[test]
+fn p2p_peers_feeler() {
+    let address = |i| SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, i)), 8080);
+
+    let mut peers = Peers::default();
+    assert_eq!(peers.get_random_new(), None);
+
+    // Only the peers of the new bucket are checked with feeler connections
+    peers.add(vec![address(1)]).unwrap();
+    peers.add_tried(vec![address(2)]).unwrap();
+    for _ in 0..10 {
+        assert_eq!(peers.get_random_new(), Some(address(1)));
+    }
+
+    // Peers are removed after the maximum number of failures, even if heard of again
+    assert!(!peers.feeler_failed(&address(1), 3));
+    peers.add(vec![address(1)]).unwrap();
+    assert!(!peers.feeler_failed(&address(1), 3));
+    assert!(peers.feeler_failed(&address(1), 3));
+    assert_eq!(peers.get_all().unwrap(), vec![address(2)]);
+
+    // Tried peers are not removed
+    assert!(!peers.feeler_failed(&address(2), 1));
+    assert!(peers.is_tried(&address(2)));
+
+    // Without a maximum, peers are never removed
+    peers.add(vec![address(3)]).unwrap();
+    for _ in 0..10 {
+        assert!(!peers.feeler_failed(&address(3), 0));
+    }
+    assert_eq!(peers.len(), 2);
+}
@@ -1,7 +1,7 @@
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::time::Duration;
 
-use witnet_p2p::sessions::*;
+use witnet_p2p::sessions::{latency::LatencyStats, *};
 
 /// Check if the sessions default initializes with empty state
 #[test]
@@ -129,6 +129,133 @@ fn p2p_sessions_is_outbound_address_eligible() {
     assert!(sessions.is_outbound_address_eligible(valid_address_3));
 }
 
+/// Check the policies of the additional listeners for inbound connections
+#[test]
+fn p2p_sessions_accept_inbound() {
+    // Create sessions struct
+    let mut sessions = Sessions::<String>::default();
+
+    // Set server address and a listener with a whitelist and an inbound limit
+    sessions.set_server_address("0.0.0.0:8000".parse().unwrap());
+    sessions.set_listeners(vec![Listener {
+        address: "[::]:9000".parse().unwrap(),
+        inbound_limit: Some(1),
+        whitelist: vec!["10.0.0.1".parse().unwrap(), "2001:db8::1".parse().unwrap()],
+    }]);
+
+    // Connections accepted by the server address are always allowed
+    let remote_address: SocketAddr = "10.0.0.2:5000".parse().unwrap();
+    assert!(sessions
+        .accept_inbound("192.168.1.1:8000".parse().unwrap(), remote_address)
+        .is_ok());
+
+    // Connections from addresses outside the whitelist are refused
+    let local_address: SocketAddr = "[2001:db8::ff]:9000".parse().unwrap();
+    assert!(sessions
+        .accept_inbound(local_address, "[2001:db8::2]:5000".parse().unwrap())
+        .is_err());
+
+    // IPv4-mapped addresses are matched against the IPv4 addresses of the whitelist
+    let remote_address: SocketAddr = "[::ffff:10.0.0.1]:5000".parse().unwrap();
+    assert!(sessions
+        .accept_inbound(local_address, remote_address)
+        .is_ok());
+
+    // The inbound limit of the listener is reached
+    assert!(sessions
+        .accept_inbound(local_address, "[2001:db8::1]:5000".parse().unwrap())
+        .is_err());
+
+    // Unregistering the session releases its slot
+    assert!(sessions
+        .register_session(
+            SessionType::Inbound,
+            remote_address,
+            "reference".to_string()
+        )
+        .is_ok());
+    assert!(sessions
+        .unregister_session(
+            SessionType::Inbound,
+            SessionStatus::Unconsolidated,
+            remote_address
+        )
+        .is_ok());
+    assert!(sessions
+        .accept_inbound(local_address, "[2001:db8::1]:5000".parse().unwrap())
+        .is_ok());
+
+    // Listener addresses are not eligible as outbound peers
+    assert!(!sessions.is_outbound_address_eligible("[::]:9000".parse().unwrap()));
+}
+
+/// Check that the access list applies to inbound connections and outbound peers
+#[test]
+fn p2p_sessions_access_list() {
+    // Create sessions struct
+    let mut sessions = Sessions::<String>::default();
+    sessions.set_server_address("0.0.0.0:8000".parse().unwrap());
+
+    let allowed_address: SocketAddr = "10.0.0.1:5000".parse().unwrap();
+    let blacklisted_address: SocketAddr = "10.0.0.13:5000".parse().unwrap();
+    assert!(sessions
+        .register_session(
+            SessionType::Inbound,
+            blacklisted_address,
+            "reference".to_string()
+        )
+        .is_ok());
+    sessions.set_access_list(access::AccessList::parse(&["10.0.0.0/8"], &["10.0.0.13"]).unwrap());
+
+    // Inbound connections are checked even if accepted by the server address
+    let local_address: SocketAddr = "192.168.1.1:8000".parse().unwrap();
+    assert!(sessions
+        .accept_inbound(local_address, allowed_address)
+        .is_ok());
+    assert!(sessions
+        .accept_inbound(local_address, blacklisted_address)
+        .is_err());
+    assert!(sessions
+        .accept_inbound(local_address, "1.2.3.4:5000".parse().unwrap())
+        .is_err());
+
+    // Outbound peers are checked too
+    assert!(sessions.is_outbound_address_eligible(allowed_address));
+    assert!(!sessions.is_outbound_address_eligible(blacklisted_address));
+    assert!(!sessions.is_outbound_address_eligible("1.2.3.4:5000".parse().unwrap()));
+
+    // Sessions registered before the access list changed are reported
+    assert_eq!(
+        sessions.get_disallowed_sessions(),
+        vec![(blacklisted_address, "reference".to_string())]
+    );
+}
+
+/// Check that only addresses bound to specific public IPs are advertised
+#[test]
+fn p2p_sessions_is_advertisable_address() {
+    let advertisable = |address: &str| is_advertisable_address(&address.parse().unwrap());
+
+    assert!(advertisable("1.2.3.4:8000"));
+    assert!(advertisable("[2001:db8::1]:8000"));
+    assert!(!advertisable("0.0.0.0:8000"));
+    assert!(!advertisable("[::]:8000"));
+    assert!(!advertisable("127.0.0.1:8000"));
+    assert!(!advertisable("192.168.1.1:8000"));
+    assert!(!advertisable("[fe80::1]:8000"));
+}
+
+/// Check that IPv4-mapped IPv6 addresses are converted back to IPv4
+#[test]
+fn p2p_sessions_canonical_address() {
+    let canonical = |address: &str| canonical_address(address.parse().unwrap()).to_string();
+
+    assert_eq!(canonical("[::ffff:1.2.3.4]:8000"), "1.2.3.4:8000");
+    assert_eq!(canonical("1.2.3.4:8000"), "1.2.3.4:8000");
+    assert_eq!(canonical("[2001:db8::1]:8000"), "[2001:db8::1]:8000");
+    assert_eq!(canonical("[::1]:8000"), "[::1]:8000");
+}
+
 /// Check if the sum of all outbound sessions (consolidated and unconsolidated) is returned
 #[test]
 fn p2p_sessions_get_num_outbound_sessions() {
@@ -227,6 +354,188 @@ fn p2p_sessions_is_outbound_bootstrap_needed() {
     assert!(!sessions.is_outbound_bootstrap_needed());
 }
 
+/// Check the number of outbound sessions missing to reach the outbound limit
+#[test]
+fn p2p_sessions_get_num_missing_outbound_sessions() {
+    // Create sessions struct (outbound unlimited by default)
+    let mut sessions = Sessions::<String>::default();
+
+    // One more session is always missing when there is no limit
+    assert_eq!(sessions.get_num_missing_outbound_sessions(), 1);
+
+    // Register two sessions and consolidate one of them
+    for i in 1..3 {
+        let outbound_address = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8000 + i);
+        sessions
+            .register_session(
+                SessionType::Outbound,
+                outbound_address,
+                "reference1".to_string(),
+            )
+            .unwrap();
+    }
+    sessions
+        .consolidate_session(
+            SessionType::Outbound,
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8001),
+        )
+        .unwrap();
+
+    // Both consolidated and unconsolidated sessions count towards the limit
+    sessions.set_limits(1, 8);
+    assert_eq!(sessions.get_num_missing_outbound_sessions(), 6);
+    sessions.set_limits(1, 1);
+    assert_eq!(sessions.get_num_missing_outbound_sessions(), 0);
+}
+
+/// Check the inbound limit and the choice of the inbound session to evict
+#[test]
+fn p2p_sessions_inbound_eviction() {
+    // Create sessions struct (inbound unlimited by default)
+    let mut sessions = Sessions::<String>::default();
+    let address = |port| SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), port);
+
+    // There is nothing to evict when there are no consolidated inbound sessions
+    assert_eq!(sessions.get_inbound_session_to_evict(), None);
+
+    // Register three inbound sessions and consolidate two of them
+    for port in 8001..8004 {
+        sessions
+            .register_session(SessionType::Inbound, address(port), port.to_string())
+            .unwrap();
+    }
+    for port in 8001..8003 {
+        sessions
+            .consolidate_session(SessionType::Inbound, address(port))
+            .unwrap();
+    }
+    assert!(!sessions.is_inbound_full());
+
+    // Unconsolidated sessions also count towards the limit
+    sessions.set_limits(4, 1);
+    assert!(!sessions.is_inbound_full());
+    sessions.set_limits(3, 1);
+    assert!(sessions.is_inbound_full());
+
+    // The consolidated session with the worst quality is evicted
+    sessions.decrease_peer_quality(address(8002), 1);
+    sessions.decrease_peer_quality(address(8003), 2);
+    assert_eq!(
+        sessions.get_inbound_session_to_evict(),
+        Some((address(8002), "8002".to_string()))
+    );
+
+    // Among the sessions with the worst quality, the one with the highest latency is evicted
+    sessions.decrease_peer_quality(address(8001), 1);
+    let latency = |smoothed_rtt| LatencyStats {
+        smoothed_rtt: Some(smoothed_rtt),
+        ..LatencyStats::default()
+    };
+    sessions.set_peer_latency(address(8001), latency(300));
+    sessions.set_peer_latency(address(8002), latency(100));
+    assert_eq!(
+        sessions.get_inbound_session_to_evict(),
+        Some((address(8001), "8001".to_string()))
+    );
+
+    // Peers with a preferred user agent are only evicted if all the others are preferred too
+    sessions.set_preferred_peer(address(8001), None);
+    assert_eq!(
+        sessions.get_inbound_session_to_evict(),
+        Some((address(8002), "8002".to_string()))
+    );
+    sessions.set_preferred_peer(address(8002), None);
+    assert_eq!(
+        sessions.get_inbound_session_to_evict(),
+        Some((address(8001), "8001".to_string()))
+    );
+}
+
+/// Check that the preferred peers are dialed while they are eligible as outbound peers
+#[test]
+fn p2p_sessions_preferred_outbound_candidates() {
+    let mut sessions = Sessions::<String>::default();
+    let address = |port| SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), port);
+
+    // Only the listening address of an inbound peer can be dialed
+    sessions
+        .register_session(SessionType::Inbound, address(50000), "inbound".to_string())
+        .unwrap();
+    sessions.set_preferred_peer(address(50000), Some(address(21337)));
+    assert!(sessions.is_preferred_session(address(50000)));
+    assert_eq!(
+        sessions.get_preferred_outbound_candidates(),
+        vec![address(21337)]
+    );
+
+    // A preferred peer is not dialed while there is already an outbound session with it
+    sessions
+        .register_session(
+            SessionType::Outbound,
+            address(21337),
+            "outbound".to_string(),
+        )
+        .unwrap();
+    sessions.set_preferred_peer(address(21337), Some(address(21337)));
+    assert_eq!(sessions.get_preferred_outbound_candidates(), vec![]);
+
+    // The session is no longer preferred once it is closed, but the peer is still dialed
+    sessions
+        .unregister_session(
+            SessionType::Outbound,
+            SessionStatus::Unconsolidated,
+            address(21337),
+        )
+        .unwrap();
+    assert!(!sessions.is_preferred_session(address(21337)));
+    assert_eq!(
+        sessions.get_preferred_outbound_candidates(),
+        vec![address(21337)]
+    );
+}
+
+/// Check that the latency is only kept for the consolidated sessions
+#[test]
+fn p2p_sessions_peer_latency() {
+    let mut sessions = Sessions::<String>::default();
+    let address = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8001);
+    let latency = LatencyStats {
+        smoothed_rtt: Some(120),
+        last_rtt: Some(150),
+        last_seen: Some(1_000),
+        missed_pongs: 0,
+    };
+
+    // The latency of unconsolidated sessions is ignored
+    sessions
+        .register_session(SessionType::Outbound, address, "reference1".to_string())
+        .unwrap();
+    sessions.set_peer_latency(address, latency);
+    assert_eq!(sessions.get_peer_latency(address), None);
+
+    // Consolidated sessions are listed even before their latency is measured
+    sessions
+        .consolidate_session(SessionType::Outbound, address)
+        .unwrap();
+    assert_eq!(
+        sessions.get_consolidated_peers_latency(),
+        vec![(address, LatencyStats::default())]
+    );
+    sessions.set_peer_latency(address, latency);
+    assert_eq!(sessions.get_peer_latency(address), Some(latency));
+    assert_eq!(
+        sessions.get_consolidated_peers_latency(),
+        vec![(address, latency)]
+    );
+
+    // The latency is forgotten when the session is closed
+    sessions
+        .unregister_session(SessionType::Outbound, SessionStatus::Consolidated, address)
+        .unwrap();
+    assert_eq!(sessions.get_peer_latency(address), None);
+    assert!(sessions.get_consolidated_peers_latency().is_empty());
+}
+
 /// Check the function to get a random outbound consolidated session
 #[test]
 fn p2p_sessions_get_random_anycast_session() {
@@ -296,6 +605,51 @@ fn p2p_sessions_get_random_anycast_session() {
     );
 }
 
+/// Check that the random outbound session is chosen among the peers with the best quality
+#[test]
+fn p2p_sessions_get_random_anycast_session_quality() {
+    // Create sessions struct
+    let mut sessions = Sessions::<String>::default();
+
+    // Register and consolidate two outbound sessions
+    let slow_address = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8001);
+    let fast_address = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8002);
+    for (address, reference) in &[(slow_address, "slow"), (fast_address, "fast")] {
+        assert!(sessions
+            .register_session(SessionType::Outbound, *address, reference.to_string())
+            .is_ok());
+        assert!(sessions
+            .consolidate_session(SessionType::Outbound, *address)
+            .is_ok());
+    }
+
+    // Penalize the slow peer
+    assert_eq!(sessions.get_peer_quality(slow_address), 0);
+    sessions.decrease_peer_quality(slow_address, SLOW_PEER_PENALTY);
+    assert_eq!(sessions.get_peer_quality(slow_address), -SLOW_PEER_PENALTY);
+
+    // The slow peer is never chosen while the other peer is available
+    for _ in 0..100 {
+        assert_eq!(
+            sessions.get_random_anycast_session(false),
+            Some("fast".to_string())
+        );
+    }
+
+    // The slow peer is chosen if it is the only one
+    assert!(sessions
+        .unregister_session(
+            SessionType::Outbound,
+            SessionStatus::Consolidated,
+            fast_address
+        )
+        .is_ok());
+    assert_eq!(
+        sessions.get_random_anycast_session(false),
+        Some("slow".to_string())
+    );
+}
+
 /// Check the registration of sessions
 #[test]
 fn p2p_sessions_register() {
@@ -0,0 +1,92 @@
+use std::net::SocketAddr;
+
+use witnet_p2p::socks5::*;
+
+#[test]
+fn p2p_socks5_parse_host() {
+    let address: SocketAddr = "127.0.0.1:21337".parse().unwrap();
+    assert_eq!(
+        Target::parse_host("127.0.0.1:21337"),
+        Ok(Target::Address(address))
+    );
+    assert_eq!(
+        Target::parse_host("seed.witnet.io:21337"),
+        Ok(Target::Host("seed.witnet.io".to_string(), 21337))
+    );
+
+    for host in &[
+        "seed.witnet.io",
+        ":21337",
+        "seed.witnet.io:port",
+        "::1:21337",
+    ] {
+        assert_eq!(
+            Target::parse_host(host),
+            Err(Socks5Error::InvalidHost {
+                host: host.to_string()
+            })
+        );
+    }
+}
+
+#[test]
+fn p2p_socks5_is_onion_host() {
+    assert!(is_onion_host(
+        "witnetkq4fq6m2gzhpwzuxfpdhzqkp7yqbqmmw4lqp4hmtxazdjf5qyd.onion:21337"
+    ));
+    assert!(is_onion_host("WITNET.ONION:21337"));
+    assert!(!is_onion_host("seed.witnet.io:21337"));
+    assert!(!is_onion_host("witnet.onion"));
+    assert!(!is_onion_host("127.0.0.1:21337"));
+}
+
+#[test]
+fn p2p_socks5_greeting() {
+    assert_eq!(greeting(), vec![5, 1, 0]);
+
+    assert_eq!(parse_method_selection(&[5, 0]), Ok(()));
+    assert_eq!(
+        parse_method_selection(&[5, 0xff]),
+        Err(Socks5Error::UnsupportedAuthentication)
+    );
+    assert_eq!(parse_method_selection(&[4, 0]), Err(Socks5Error::Malformed));
+}
+
+#[test]
+fn p2p_socks5_connect_request() {
+    assert_eq!(
+        connect_request(&Target::Address("203.0.113.7:21337".parse().unwrap())),
+        vec![5, 1, 0, 1, 203, 0, 113, 7, 0x53, 0x59]
+    );
+    assert_eq!(
+        connect_request(&Target::Address("[::1]:21337".parse().unwrap())),
+        vec![5, 1, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0x53, 0x59]
+    );
+    assert_eq!(
+        connect_request(&Target::Host("a.onion".to_string(), 21337)),
+        vec![5, 1, 0, 3, 7, b'a', b'.', b'o', b'n', b'i', b'o', b'n', 0x53, 0x59]
+    );
+}
+
+#[test]
+fn p2p_socks5_reply() {
+    assert_eq!(parse_reply_header(&[5, 0, 0, 1, 127]), Ok(10));
+    assert_eq!(parse_reply_header(&[5, 0, 0, 4, 0]), Ok(22));
+    assert_eq!(parse_reply_header(&[5, 0, 0, 3, 7]), Ok(14));
+
+    assert_eq!(
+        parse_reply_header(&[5, 5, 0, 1, 0]),
+        Err(Socks5Error::ConnectFailed {
+            code: 5,
+            reason: "connection refused"
+        })
+    );
+    assert_eq!(
+        parse_reply_header(&[5, 0, 0, 1]),
+        Err(Socks5Error::Malformed)
+    );
+    assert_eq!(
+        parse_reply_header(&[5, 0, 0, 2, 0]),
+        Err(Socks5Error::Malformed)
+    );
+}
@@ -0,0 +1,60 @@
+use witnet_p2p::user_agents::*;
+
+#[test]
+fn p2p_user_agents_pattern_matches() {
+    let user_agent = "full-node-desktop-edition/0.2.0-1a2b3c4d5e6f";
+
+    // Exact match
+    assert!(pattern_matches(user_agent, user_agent));
+    assert!(!pattern_matches("full-node-desktop-edition", user_agent));
+
+    // Wildcards
+    assert!(pattern_matches("*", user_agent));
+    assert!(pattern_matches("full-node-desktop-edition/*", user_agent));
+    assert!(pattern_matches("*/0.2.0-*", user_agent));
+    assert!(pattern_matches("*5e6f", user_agent));
+    assert!(pattern_matches("full-*-edition/0.2.*", user_agent));
+    assert!(!pattern_matches(
+        "full-node-desktop-edition/0.1.*",
+        user_agent
+    ));
+    assert!(!pattern_matches("*0.3*", user_agent));
+
+    // Wildcards do not overlap
+    assert!(!pattern_matches("ab*ba", "aba"));
+}
+
+#[test]
+fn p2p_user_agents_filter() {
+    let mut filter = UserAgentFilter::new(
+        vec!["buggy/*".to_string()],
+        vec!["buggy/0.2.1".to_string(), "good/*".to_string()],
+    );
+
+    assert_eq!(filter.check("buggy/0.2.0"), UserAgentMatch::Avoided);
+    // Preferred patterns take precedence over the ones to avoid
+    assert_eq!(filter.check("buggy/0.2.1"), UserAgentMatch::Preferred);
+    assert_eq!(filter.check("good/1.0.0"), UserAgentMatch::Preferred);
+    assert_eq!(filter.check("other/1.0.0"), UserAgentMatch::Neutral);
+
+    assert_eq!(
+        filter.metrics(),
+        UserAgentMetrics {
+            avoided: 1,
+            preferred: 2,
+            neutral: 1,
+        }
+    );
+
+    // Classifying does not update the metrics
+    assert_eq!(filter.classify("buggy/0.2.0"), UserAgentMatch::Avoided);
+    assert_eq!(filter.metrics().avoided, 1);
+}
+
+#[test]
+fn p2p_user_agents_default_filter() {
+    let mut filter = UserAgentFilter::default();
+
+    assert_eq!(filter.check("anything"), UserAgentMatch::Neutral);
+    assert_eq!(filter.metrics().neutral, 1);
+}
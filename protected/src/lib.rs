@@ -2,6 +2,13 @@
 //!
 //! Protected set of bytes that will be zeroed out when the value of
 //! type [`Protected`](Protected) containing them is dropped.
+//!
+//! Secrets must never be compared with `==` on plain bytes, as the
+//! comparison returns as soon as a byte differs and leaks through its
+//! timing how many leading bytes matched. [`Protected`](Protected) is
+//! compared in constant time, and
+//! [`constant_time_eq`](constant_time_eq) does the same for any other
+//! secret byte slices.
 
 #![deny(rust_2018_idioms)]
 #![deny(non_upper_case_globals)]
@@ -13,9 +20,10 @@
 use std::ops::{Deref, DerefMut};
 
 use memzero::Memzero;
+use subtle::ConstantTimeEq;
 
 /// Protected set of bytes
-#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Clone)]
 pub struct Protected(Memzero<Vec<u8>>);
 
 impl<T: Into<Vec<u8>>> From<T> for Protected {
@@ -57,8 +65,55 @@ impl Protected {
     }
 }
 
+impl PartialEq for Protected {
+    fn eq(&self, other: &Self) -> bool {
+        constant_time_eq(self, other)
+    }
+}
+
+impl Eq for Protected {}
+
 impl std::fmt::Debug for Protected {
     fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(fmt, "Protected(***)")
     }
 }
+
+/// Compare two byte slices in constant time.
+///
+/// The time taken only depends on the length of the slices, not on their
+/// contents, so it is safe to use with secrets.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && bool::from(a.ct_eq(b))
+}
+
+/// Overwrite a set of bytes with zeros.
+///
+/// Unlike a plain assignment, the writes are volatile so they are not
+/// optimized away even if the bytes are never read again, e.g. when
+/// called from a `Drop` implementation.
+pub fn zero_out(bytes: &mut [u8]) {
+    for byte in bytes.iter_mut() {
+        unsafe { std::ptr::write_volatile(byte, 0) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn protected_eq() {
+        assert_eq!(Protected::new(vec![1, 2, 3]), Protected::new(vec![1, 2, 3]));
+        assert_ne!(Protected::new(vec![1, 2, 3]), Protected::new(vec![1, 2, 4]));
+        assert_ne!(Protected::new(vec![1, 2, 3]), Protected::new(vec![1, 2]));
+    }
+
+    #[test]
+    fn zero_out_bytes() {
+        let mut bytes = [0xab; 32];
+        zero_out(&mut bytes);
+
+        assert_eq!(bytes, [0; 32]);
+    }
+}
@@ -24,6 +24,22 @@ pub enum RadError {
     /// The given key is not present in a RadonMap
     #[fail(display = "Failed to get key `{}` from RadonMap", key)]
     MapKeyNotFound { key: String },
+    /// The given index is not present in a RadonArray
+    #[fail(
+        display = "Failed to get index `{}` from RadonArray of length {}",
+        index, length
+    )]
+    ArrayIndexOutOfBounds { index: u64, length: usize },
+    /// A step of a path does not match the type of the value it is applied to
+    #[fail(
+        display = "Expected {} at path `{}` but found {}",
+        expected, path, found
+    )]
+    PathTypeMismatch {
+        path: String,
+        expected: String,
+        found: String,
+    },
     /// Failed to parse a Value from a MessagePack buffer
     #[fail(
         display = "Failed to parse a Value from a MessagePack buffer. Error message: {}",
@@ -83,12 +99,99 @@ pub enum RadError {
         message
     )]
     Http { message: String },
+    /// The HTTP request did not complete within the timeout of the retrieval
+    #[fail(display = "HTTP request timed out after {} milliseconds", timeout_ms)]
+    HttpTimeout { timeout_ms: u64 },
+    /// The server answered the HTTP request with an unsuccessful status code
+    #[fail(display = "HTTP request failed with status code {}", status_code)]
+    HttpStatus { status_code: u16 },
+    /// The HTTP request was redirected more times than allowed, or into a loop
+    #[fail(
+        display = "HTTP request exceeded the maximum of {} redirects",
+        max_redirects
+    )]
+    TooManyRedirects { max_redirects: usize },
+    /// The response of a retrieval is larger than the maximum size declared in the RAD request
+    #[fail(
+        display = "HTTP response is larger than the maximum of {} bytes",
+        max_size
+    )]
+    ResponseTooLarge { max_size: u64 },
+    /// The content type of a response does not match the one declared in the RAD request
+    #[fail(
+        display = "Expected HTTP response with content type `{}` but got `{}`",
+        expected, found
+    )]
+    UnexpectedContentType { expected: String, found: String },
+    /// The TLS certificate of the server does not match the fingerprint declared in the RAD request
+    #[fail(
+        display = "TLS certificate with SHA-256 fingerprint `{}` does not match the pinned `{}`",
+        found, expected
+    )]
+    CertificateMismatch { expected: String, found: String },
+    /// Failed to check the TLS certificate of the server
+    #[fail(
+        display = "Failed to check the pinned TLS certificate with error message: {}",
+        message
+    )]
+    CertificatePinning { message: String },
     /// Failed to convert string to float
     #[fail(
         display = "Failed to convert string to float with error message: {}",
         message
     )]
     ParseFloat { message: String },
+    /// Failed to convert string to integer
+    #[fail(
+        display = "Failed to convert string to integer with error message: {}",
+        message
+    )]
+    ParseInt { message: String },
+    /// The string is not a key of the categories of a match, which has no default value
+    #[fail(display = "No category matches `{}` and there is no default", input)]
+    MatchNotFound { input: String },
+    /// The offsets of a slice are out of the bounds of the string or not at character boundaries
+    #[fail(
+        display = "Failed to slice bytes {}..{} of string of length {}",
+        start, end, length
+    )]
+    SliceOutOfBounds { start: u64, end: u64, length: usize },
+    /// Subscripts are nested deeper than allowed
+    #[fail(display = "Subscripts are nested deeper than {} levels", max_depth)]
+    SubscriptTooDeep { max_depth: usize },
+    /// A predicate subscript returned something other than a boolean
+    #[fail(display = "Expected a boolean from a predicate but found `{}`", found)]
+    PredicateNotBoolean { found: String },
+    /// The keys used to sort an array are not all integers, all floats or all strings
+    #[fail(
+        display = "Failed to sort by key `{}`, keys must be all integers, all floats or all strings",
+        found
+    )]
+    UnsortableKey { found: String },
+    /// The operator cannot be applied to an empty array
+    #[fail(
+        display = "Operator `{}` cannot be applied to an empty array",
+        operator
+    )]
+    EmptyArray { operator: String },
+    /// The array has items, but none of them is an integer or a finite float
+    #[fail(
+        display = "Operator `{}` found no integers or finite floats in the array",
+        operator
+    )]
+    NoNumericItems { operator: String },
+    /// Several values are the most frequent in the array, so there is no clear mode
+    #[fail(
+        display = "There is no mode, as {} values appear {} times each",
+        values, count
+    )]
+    ModeTie { values: usize, count: usize },
+    /// The result of an arithmetic operator does not fit in its output type
+    #[fail(display = "Operator `{}` overflowed with input `{}`", operator, input)]
+    Overflow { operator: String, input: String },
+    /// An operator was applied to, or resulted in, NaN or an infinite float
+    #[fail(display = "Operator `{}` involved a NaN or infinite float", operator)]
+    NonFiniteFloat { operator: String },
 }
 
 impl From<reqwest::Error> for RadError {
@@ -106,3 +209,11 @@ impl From<std::num::ParseFloatError> for RadError {
         }
     }
 }
+
+impl From<std::num::ParseIntError> for RadError {
+    fn from(err: std::num::ParseIntError) -> RadError {
+        RadError::ParseInt {
+            message: err.to_string(),
+        }
+    }
+}
@@ -0,0 +1,224 @@
+//! HTTP client used to retrieve the sources of data requests.
+
+use std::{error::Error as StdError, io, thread, time::Duration};
+
+use log::debug;
+use reqwest::{
+    header::{CONTENT_TYPE, USER_AGENT},
+    Client, RedirectPolicy, Response,
+};
+
+use crate::error::RadError;
+
+/// Media type of the bodies of `HTTP-POST` retrievals which do not declare one
+pub const DEFAULT_BODY_CONTENT_TYPE: &str = "application/json";
+
+/// Upper bound of the delay between two attempts to retrieve a source
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Options of the HTTP client used by the retrievals
+#[derive(Clone, Debug, PartialEq)]
+pub struct HttpOptions {
+    /// Timeout of every attempt to retrieve a source which does not declare its own timeout
+    pub timeout: Duration,
+    /// Upper bound of the timeout of every attempt, including the timeouts declared by sources
+    pub max_timeout: Duration,
+    /// Maximum number of times a retrieval is retried after a transient error
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled on every subsequent one
+    pub retry_backoff: Duration,
+    /// Maximum number of redirects followed by a retrieval
+    pub max_redirects: usize,
+    /// Value of the `User-Agent` header of the requests
+    pub user_agent: String,
+}
+
+impl Default for HttpOptions {
+    fn default() -> Self {
+        HttpOptions {
+            timeout: Duration::from_secs(10),
+            max_timeout: Duration::from_secs(60),
+            max_retries: 2,
+            retry_backoff: Duration::from_millis(500),
+            max_redirects: 5,
+            user_agent: format!("witnet-rad/{}", env!("CARGO_PKG_VERSION")),
+        }
+    }
+}
+
+impl HttpOptions {
+    /// Delay before the given retry, starting at `1`
+    pub fn backoff(&self, retry: u32) -> Duration {
+        2u32.checked_pow(retry.saturating_sub(1))
+            .and_then(|factor| self.retry_backoff.checked_mul(factor))
+            .map_or(MAX_BACKOFF, |backoff| std::cmp::min(backoff, MAX_BACKOFF))
+    }
+}
+
+/// Send a GET request, retrying it with an exponential backoff after connection errors, timeouts
+/// and server errors. `timeout` overrides the timeout of the options for every attempt.
+pub fn get(
+    url: &str,
+    timeout: Option<Duration>,
+    options: &HttpOptions,
+) -> Result<Response, RadError> {
+    send(url, None, timeout, options)
+}
+
+/// Send a POST request with the given body, retrying it like `get`. Retrieval sources are
+/// expected to answer POST requests without side effects, so they are safe to retry.
+pub fn post(
+    url: &str,
+    body: &[u8],
+    content_type: &str,
+    timeout: Option<Duration>,
+    options: &HttpOptions,
+) -> Result<Response, RadError> {
+    send(url, Some((body, content_type)), timeout, options)
+}
+
+/// Send a GET request, or a POST one if there is a body along with its media type
+fn send(
+    url: &str,
+    body: Option<(&[u8], &str)>,
+    timeout: Option<Duration>,
+    options: &HttpOptions,
+) -> Result<Response, RadError> {
+    let timeout = timeout.unwrap_or(options.timeout);
+    let client = Client::builder()
+        .timeout(timeout)
+        .redirect(RedirectPolicy::limited(options.max_redirects))
+        .build()
+        .map_err(RadError::from)?;
+
+    let mut retry = 0;
+    loop {
+        let request = match body {
+            Some((body, content_type)) => client
+                .post(url)
+                .header(CONTENT_TYPE, content_type)
+                .body(body.to_vec()),
+            None => client.get(url),
+        };
+        let result = request
+            .header(USER_AGENT, options.user_agent.as_str())
+            .send()
+            .map_err(|e| map_error(&e, timeout, options.max_redirects))
+            .and_then(check_status);
+
+        match result {
+            Err(ref e) if is_transient(e) && retry < options.max_retries => {
+                retry += 1;
+                let backoff = options.backoff(retry);
+                debug!("Retrying GET {} in {:?} after error: {}", url, backoff, e);
+                thread::sleep(backoff);
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Fail on responses whose status code is not a success
+fn check_status(response: Response) -> Result<Response, RadError> {
+    let status = response.status();
+    if status.is_success() {
+        Ok(response)
+    } else {
+        Err(RadError::HttpStatus {
+            status_code: status.as_u16(),
+        })
+    }
+}
+
+/// Whether retrying a request may succeed after the given error
+pub(crate) fn is_transient(error: &RadError) -> bool {
+    match error {
+        RadError::Http { .. } | RadError::HttpTimeout { .. } => true,
+        // Too Many Requests and server errors
+        RadError::HttpStatus { status_code } => *status_code == 429 || *status_code >= 500,
+        _ => false,
+    }
+}
+
+/// Map the errors of the HTTP client into RAD errors
+fn map_error(error: &reqwest::Error, timeout: Duration, max_redirects: usize) -> RadError {
+    if error.is_redirect() {
+        RadError::TooManyRedirects { max_redirects }
+    } else if is_timeout(error) {
+        RadError::HttpTimeout {
+            timeout_ms: timeout.as_secs() * 1000 + u64::from(timeout.subsec_millis()),
+        }
+    } else {
+        RadError::Http {
+            message: error.to_string(),
+        }
+    }
+}
+
+/// Whether the cause of an error of the HTTP client is a timeout
+fn is_timeout(error: &reqwest::Error) -> bool {
+    let mut source = error
+        .get_ref()
+        .map(|cause| cause as &(dyn StdError + 'static));
+    while let Some(cause) = source {
+        if let Some(io_error) = cause.downcast_ref::<io::Error>() {
+            if io_error.kind() == io::ErrorKind::TimedOut {
+                return true;
+            }
+        }
+        source = cause.source();
+    }
+
+    false
+}
+
+#[test]
+fn test_backoff() {
+    let options = HttpOptions {
+        retry_backoff: Duration::from_millis(500),
+        ..HttpOptions::default()
+    };
+
+    assert_eq!(options.backoff(1), Duration::from_millis(500));
+    assert_eq!(options.backoff(2), Duration::from_secs(1));
+    assert_eq!(options.backoff(3), Duration::from_secs(2));
+    assert_eq!(options.backoff(10), MAX_BACKOFF);
+    assert_eq!(options.backoff(100), MAX_BACKOFF);
+}
+
+#[test]
+fn test_is_transient() {
+    assert!(is_transient(&RadError::HttpTimeout { timeout_ms: 1000 }));
+    assert!(is_transient(&RadError::Http {
+        message: "connection refused".to_string()
+    }));
+    assert!(is_transient(&RadError::HttpStatus { status_code: 503 }));
+    assert!(is_transient(&RadError::HttpStatus { status_code: 429 }));
+    assert!(!is_transient(&RadError::HttpStatus { status_code: 404 }));
+    assert!(!is_transient(&RadError::TooManyRedirects {
+        max_redirects: 5
+    }));
+}
+
+#[test]
+fn test_get_invalid_url() {
+    let options = HttpOptions {
+        max_retries: 0,
+        ..HttpOptions::default()
+    };
+
+    match get("not a url", None, &options) {
+        Err(RadError::Http { .. }) => {}
+        result => panic!("Expected an HTTP error, got {:?}", result.map(|_| ())),
+    }
+    match post(
+        "not a url",
+        b"{}",
+        DEFAULT_BODY_CONTENT_TYPE,
+        None,
+        &options,
+    ) {
+        Err(RadError::Http { .. }) => {}
+        result => panic!("Expected an HTTP error, got {:?}", result.map(|_| ())),
+    }
+}
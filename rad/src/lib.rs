@@ -1,6 +1,11 @@
 //! # RAD Engine
 
-use reqwest;
+use std::{
+    io::Read,
+    time::{Duration, Instant},
+};
+
+use reqwest::header::CONTENT_TYPE;
 
 use witnet_data_structures::{
     chain::{RADRetrieve, RADType},
@@ -8,61 +13,216 @@ use witnet_data_structures::{
 };
 
 use crate::error::RadError;
-use crate::script::{execute_radon_script, unpack_radon_script};
+use crate::http::HttpOptions;
+use crate::report::{RadonReport, Stage};
+use crate::script::{trace_radon_script, unpack_radon_script};
 use crate::types::{array::RadonArray, string::RadonString, RadonTypes};
 
 pub mod error;
 pub mod hash_functions;
+pub mod http;
 pub mod operators;
+pub mod pinning;
 pub mod reducers;
+pub mod report;
 pub mod script;
 pub mod types;
 
-/// Run retrieval stage of a data request.
+/// Run retrieval stage of a data request with the default HTTP options.
 pub fn run_retrieval(retrieve: RADRetrieve) -> Result<RadonTypes, RadError> {
-    match retrieve.kind {
-        RADType::HttpGet => {
-            let response = reqwest::get(&retrieve.url)
-                .map_err(RadError::from)?
-                .text()
-                .map_err(RadError::from)?;
+    run_retrieval_report(retrieve, &HttpOptions::default()).result
+}
 
-            let input = RadonTypes::from(RadonString::from(response));
-            let radon_script = unpack_radon_script(&retrieve.script)?;
+/// Run retrieval stage of a data request, reporting the execution of its script.
+pub fn run_retrieval_report(retrieve: RADRetrieve, options: &HttpOptions) -> RadonReport {
+    let start = Instant::now();
+    let (result, calls) = match retrieve_source(&retrieve, options) {
+        Ok(input) => match unpack_radon_script(&retrieve.script) {
+            Ok(radon_script) => trace_radon_script(input, &radon_script),
+            Err(error) => (Err(error), vec![]),
+        },
+        Err(error) => (Err(error), vec![]),
+    };
+
+    RadonReport {
+        stage: Stage::Retrieval,
+        calls,
+        result,
+        running_time: start.elapsed(),
+    }
+}
+
+/// Retrieve the response of the source of a retrieval stage.
+fn retrieve_source(retrieve: &RADRetrieve, options: &HttpOptions) -> Result<RadonTypes, RadError> {
+    let timeout = retrieval_timeout(retrieve, options);
+    if !retrieve.certificate_fingerprint.is_empty() {
+        return retrieve_pinned_source(retrieve, timeout.unwrap_or(options.timeout), options);
+    }
+
+    let mut response = match retrieve.kind {
+        RADType::HttpGet => http::get(&retrieve.url, timeout, options)?,
+        RADType::HttpPost => http::post(
+            &retrieve.url,
+            &retrieve.body,
+            body_content_type(retrieve),
+            timeout,
+            options,
+        )?,
+    };
 
-            execute_radon_script(input, &radon_script)
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok());
+    check_content_type(&retrieve.content_type, content_type)?;
+
+    let response = if retrieve.max_response_size == 0 {
+        response.text().map_err(RadError::from)?
+    } else {
+        // Fail early if the server already announces a response which is too large
+        if response.content_length().unwrap_or(0) > retrieve.max_response_size {
+            Err(RadError::ResponseTooLarge {
+                max_size: retrieve.max_response_size,
+            })?
         }
+        let body = read_limited(&mut response, retrieve.max_response_size)?;
+
+        String::from_utf8_lossy(&body).into_owned()
+    };
+
+    Ok(RadonTypes::from(RadonString::from(response)))
+}
+
+/// Retrieve the response of a source with a pinned certificate, over the connection whose
+/// certificate was checked
+fn retrieve_pinned_source(
+    retrieve: &RADRetrieve,
+    timeout: Duration,
+    options: &HttpOptions,
+) -> Result<RadonTypes, RadError> {
+    let body = match retrieve.kind {
+        RADType::HttpGet => None,
+        RADType::HttpPost => Some((retrieve.body.as_slice(), body_content_type(retrieve))),
+    };
+    let response = pinning::send(
+        &retrieve.url,
+        body,
+        &retrieve.certificate_fingerprint,
+        timeout,
+        retrieve.max_response_size,
+        options,
+    )?;
+    check_content_type(
+        &retrieve.content_type,
+        response.content_type.as_ref().map(String::as_str),
+    )?;
+
+    Ok(RadonTypes::from(RadonString::from(
+        String::from_utf8_lossy(&response.body).into_owned(),
+    )))
+}
+
+/// Timeout declared by a retrieval, if any, capped by the maximum timeout of the options so that
+/// sources cannot keep a witness waiting longer than it is willing to
+fn retrieval_timeout(retrieve: &RADRetrieve, options: &HttpOptions) -> Option<Duration> {
+    match retrieve.timeout {
+        0 => None,
+        millis => Some(std::cmp::min(
+            Duration::from_millis(millis),
+            options.max_timeout,
+        )),
     }
 }
 
-/// Run aggregate stage of a data request.
-pub fn run_aggregation(
-    radon_types_vec: Vec<RadonTypes>,
-    script: Vec<u8>,
-) -> Result<Vec<u8>, RadError> {
-    let radon_script = unpack_radon_script(&script)?;
+/// Media type of the body of an `HTTP-POST` retrieval, JSON unless it declares another one
+fn body_content_type(retrieve: &RADRetrieve) -> &str {
+    if retrieve.body_content_type.is_empty() {
+        http::DEFAULT_BODY_CONTENT_TYPE
+    } else {
+        &retrieve.body_content_type
+    }
+}
+
+/// Check that the media type of a response matches the expected one, ignoring its parameters
+/// (e.g. `charset`) and case. An empty expected content type accepts any response.
+fn check_content_type(expected: &str, found: Option<&str>) -> Result<(), RadError> {
+    if expected.is_empty() {
+        return Ok(());
+    }
 
-    let radon_array = RadonArray::from(radon_types_vec);
+    let found = found.unwrap_or_default();
+    let media_type = found.split(';').next().unwrap_or_default().trim();
+    if media_type.eq_ignore_ascii_case(expected.trim()) {
+        Ok(())
+    } else {
+        Err(RadError::UnexpectedContentType {
+            expected: expected.to_string(),
+            found: found.to_string(),
+        })
+    }
+}
 
-    let rad_aggregation: RadonTypes =
-        execute_radon_script(RadonTypes::from(radon_array), &radon_script)?;
+/// Read a response body, failing as soon as it is larger than `max_size` bytes
+fn read_limited<R: Read>(reader: R, max_size: u64) -> Result<Vec<u8>, RadError> {
+    let mut body = vec![];
+    reader
+        .take(max_size.saturating_add(1))
+        .read_to_end(&mut body)
+        .map_err(|e| RadError::Http {
+            message: e.to_string(),
+        })?;
 
-    rad_aggregation.try_into().map_err(Into::into)
+    if body.len() as u64 > max_size {
+        Err(RadError::ResponseTooLarge { max_size })
+    } else {
+        Ok(body)
+    }
 }
 
-/// Run consensus stage of a data request.
-pub fn run_consensus(
+/// Run aggregate stage of a data request.
+pub fn run_aggregation(
     radon_types_vec: Vec<RadonTypes>,
     script: Vec<u8>,
 ) -> Result<Vec<u8>, RadError> {
-    let radon_script = unpack_radon_script(&script)?;
+    run_aggregation_report(radon_types_vec, &script)
+        .result?
+        .try_into()
+        .map_err(Into::into)
+}
+
+/// Run aggregate stage of a data request, reporting the execution of its script.
+pub fn run_aggregation_report(radon_types_vec: Vec<RadonTypes>, script: &[u8]) -> RadonReport {
+    run_array_stage(Stage::Aggregation, radon_types_vec, script)
+}
 
-    let radon_array = RadonArray::from(radon_types_vec);
+/// Run tally stage of a data request.
+pub fn run_tally(radon_types_vec: Vec<RadonTypes>, script: Vec<u8>) -> Result<Vec<u8>, RadError> {
+    run_tally_report(radon_types_vec, &script)
+        .result?
+        .try_into()
+        .map_err(Into::into)
+}
 
-    let rad_consensus: RadonTypes =
-        execute_radon_script(RadonTypes::from(radon_array), &radon_script)?;
+/// Run tally stage of a data request, reporting the execution of its script.
+pub fn run_tally_report(radon_types_vec: Vec<RadonTypes>, script: &[u8]) -> RadonReport {
+    run_array_stage(Stage::Tally, radon_types_vec, script)
+}
+
+/// Run a stage whose script is applied to the array of the results of the previous stage.
+fn run_array_stage(stage: Stage, radon_types_vec: Vec<RadonTypes>, script: &[u8]) -> RadonReport {
+    let start = Instant::now();
+    let input = RadonTypes::from(RadonArray::from(radon_types_vec));
+    let (result, calls) = match unpack_radon_script(script) {
+        Ok(radon_script) => trace_radon_script(input, &radon_script),
+        Err(error) => (Err(error), vec![]),
+    };
 
-    rad_consensus.try_into().map_err(Into::into)
+    RadonReport {
+        stage,
+        calls,
+        result,
+        running_time: start.elapsed(),
+    }
 }
 
 /// Run deliver clauses of a data request.
@@ -78,7 +238,13 @@ fn test_run_retrieval() {
     let retrieve = RADRetrieve {
         kind: RADType::HttpGet,
         url: "https://openweathermap.org/data/2.5/weather?id=2950159&appid=b6907d289e10d714a6e88b30761fae22".to_string(),
-        script
+        script,
+        max_response_size: 0,
+        content_type: String::new(),
+        certificate_fingerprint: vec![],
+        timeout: 0,
+        body: vec![],
+        body_content_type: String::new(),
     };
 
     let result = run_retrieval(retrieve).unwrap();
@@ -90,7 +256,58 @@ fn test_run_retrieval() {
 }
 
 #[test]
-fn test_run_consensus_and_aggregation() {
+fn test_check_content_type() {
+    assert!(check_content_type("", None).is_ok());
+    assert!(check_content_type("", Some("text/html")).is_ok());
+    assert!(check_content_type("application/json", Some("application/json")).is_ok());
+    assert!(
+        check_content_type("application/json", Some("Application/JSON; charset=utf-8")).is_ok()
+    );
+    assert_eq!(
+        check_content_type("application/json", Some("text/html")),
+        Err(RadError::UnexpectedContentType {
+            expected: "application/json".to_string(),
+            found: "text/html".to_string(),
+        })
+    );
+    assert_eq!(
+        check_content_type("application/json", None),
+        Err(RadError::UnexpectedContentType {
+            expected: "application/json".to_string(),
+            found: "".to_string(),
+        })
+    );
+}
+
+#[test]
+fn test_body_content_type() {
+    let mut retrieve = RADRetrieve {
+        kind: RADType::HttpPost,
+        body: br#"{"symbol":"BTCUSD"}"#.to_vec(),
+        ..RADRetrieve::default()
+    };
+    assert_eq!(body_content_type(&retrieve), "application/json");
+
+    retrieve.body_content_type = "application/x-www-form-urlencoded".to_string();
+    assert_eq!(
+        body_content_type(&retrieve),
+        "application/x-www-form-urlencoded"
+    );
+}
+
+#[test]
+fn test_read_limited() {
+    let body = [7u8; 16];
+
+    assert_eq!(read_limited(&body[..], 16), Ok(body.to_vec()));
+    assert_eq!(
+        read_limited(&body[..], 15),
+        Err(RadError::ResponseTooLarge { max_size: 15 })
+    );
+}
+
+#[test]
+fn test_run_tally_and_aggregation() {
     use crate::types::float::RadonFloat;
 
     let f_1 = RadonTypes::Float(RadonFloat::from(1f64).into());
@@ -102,13 +319,90 @@ fn test_run_consensus_and_aggregation() {
 
     let expected = RadonTypes::Float(RadonFloat::from(2f64)).try_into().ok();
 
-    let output_consensus = run_consensus(radon_types_vec.clone(), packed_script.clone()).ok();
+    let output_tally = run_tally(radon_types_vec.clone(), packed_script.clone()).ok();
     let output_aggregate = run_aggregation(radon_types_vec, packed_script).ok();
 
-    assert_eq!(output_consensus, expected);
+    assert_eq!(output_tally, expected);
     assert_eq!(output_aggregate, expected);
 }
 
+#[test]
+fn test_run_tally_skips_invalid_reveals() {
+    use crate::types::{float::RadonFloat, string::RadonString};
+    use witnet_data_structures::serializers::decoders::TryFrom;
+
+    // Reveals are decoded from their serialized form, as the RAD Manager does
+    let reveal = |value: RadonTypes| -> Vec<u8> { value.try_into().unwrap() };
+    let reveals = vec![
+        reveal(RadonFloat::from(1f64).into()),
+        reveal(RadonString::from("not a number").into()),
+        reveal(RadonFloat::from(3f64).into()),
+        vec![0xc0],
+    ];
+    let radon_types_vec: Vec<RadonTypes> = reveals
+        .iter()
+        .filter_map(|reveal| RadonTypes::try_from(reveal.as_slice()).ok())
+        .collect();
+
+    // Mean, median and standard deviation ignore the invalid reveals
+    for (reducer, expected) in &[(0x20, 2f64), (0x22, 2f64), (0x30, 1f64)] {
+        let script = vec![145, 146, 102, *reducer];
+        let expected: Vec<u8> = RadonTypes::from(RadonFloat::from(*expected))
+            .try_into()
+            .unwrap();
+
+        assert_eq!(run_tally(radon_types_vec.clone(), script), Ok(expected));
+    }
+
+    // The mode ignores a single reveal of another type
+    let radon_types_vec = vec![
+        RadonFloat::from(1f64).into(),
+        RadonString::from("1").into(),
+        RadonFloat::from(1f64).into(),
+    ];
+    let expected: Vec<u8> = RadonTypes::from(RadonFloat::from(1f64)).try_into().unwrap();
+    assert_eq!(
+        run_tally(radon_types_vec, vec![145, 146, 102, 0x10]),
+        Ok(expected)
+    );
+}
+
+#[test]
+fn test_run_tally_report() {
+    use crate::operators::RadonOpCodes;
+    use crate::types::float::RadonFloat;
+
+    let radon_types_vec = vec![
+        RadonTypes::Float(RadonFloat::from(1f64)),
+        RadonTypes::Float(RadonFloat::from(3f64)),
+    ];
+    let report = run_tally_report(radon_types_vec.clone(), &[145, 146, 102, 32]);
+
+    assert_eq!(report.stage, Stage::Tally);
+    assert_eq!(report.result, Ok(RadonTypes::Float(RadonFloat::from(2f64))));
+    assert_eq!(report.calls.len(), 1);
+    assert_eq!(report.calls[0].operator, RadonOpCodes::Reduce);
+    assert_eq!(report.failed_call(), None);
+
+    // Arrays cannot be converted into maps
+    let report = run_tally_report(radon_types_vec, &[145, 204, 132]);
+
+    assert!(!report.is_ok());
+    assert_eq!(
+        report.failed_call().map(|call| call.operator),
+        Some(RadonOpCodes::ToMap)
+    );
+
+    let report = run_aggregation_report(vec![], &[0xc0]);
+
+    assert_eq!(report.stage, Stage::Aggregation);
+    assert!(report.calls.is_empty());
+    match report.result {
+        Err(RadError::ScriptNotArray { .. }) => {}
+        result => panic!("Expected a script error, got {:?}", result),
+    }
+}
+
 #[test]
 #[ignore]
 fn test_run_retrieval_random_api() {
@@ -119,6 +413,12 @@ fn test_run_retrieval_random_api() {
         kind: RADType::HttpGet,
         url: "http://qrng.anu.edu.au/API/jsonI.php?length=1&type=uint8".to_string(),
         script,
+        max_response_size: 0,
+        content_type: String::new(),
+        certificate_fingerprint: vec![],
+        timeout: 0,
+        body: vec![],
+        body_content_type: String::new(),
     };
 
     let result = run_retrieval(retrieve).unwrap();
@@ -128,3 +428,25 @@ fn test_run_retrieval_random_api() {
         err => panic!("Error in run_retrieval: {:?}", err),
     }
 }
+
+#[test]
+fn test_retrieval_timeout() {
+    let options = HttpOptions {
+        max_timeout: Duration::from_secs(30),
+        ..HttpOptions::default()
+    };
+    let retrieve = |timeout| RADRetrieve {
+        timeout,
+        ..RADRetrieve::default()
+    };
+
+    assert_eq!(retrieval_timeout(&retrieve(0), &options), None);
+    assert_eq!(
+        retrieval_timeout(&retrieve(1500), &options),
+        Some(Duration::from_millis(1500))
+    );
+    assert_eq!(
+        retrieval_timeout(&retrieve(u64::max_value()), &options),
+        Some(Duration::from_secs(30))
+    );
+}
@@ -1,9 +1,13 @@
 use crate::error::RadError;
 use crate::reducers::{self, RadonReducers};
+use crate::script::{execute_radon_script, unpack_subscript, RadonScript};
 use crate::types::{array::RadonArray, RadonType, RadonTypes};
 use num_traits::FromPrimitive;
 use rmpv::Value;
+use std::cmp::Ordering;
 
+/// Reduce the array with a predefined reducer, or with a subscript that is applied to a
+/// `[accumulated, item]` array for every item after the first one
 pub fn reduce(input: &RadonArray, args: &[Value]) -> Result<RadonTypes, RadError> {
     let error = || RadError::WrongArguments {
         input_type: "RadonArray".to_string(),
@@ -11,21 +15,153 @@ pub fn reduce(input: &RadonArray, args: &[Value]) -> Result<RadonTypes, RadError
         args: args.to_vec(),
     };
 
+    if let [subscript @ Value::Array(_)] = args {
+        let subscript = unpack_subscript(subscript)?;
+        let mut items = input.value().into_iter();
+        let first = items.next().ok_or_else(|| RadError::EmptyArray {
+            operator: "Reduce".to_string(),
+        })?;
+
+        return items.try_fold(first, |accumulated, item| {
+            execute_radon_script(RadonArray::from(vec![accumulated, item]).into(), &subscript)
+        });
+    }
+
     let reducer_integer = args.first().ok_or_else(error)?.as_i64().ok_or_else(error)?;
     let reducer_code = RadonReducers::from_i64(reducer_integer).ok_or_else(error)?;
 
     reducers::reduce(input, reducer_code)
 }
 
+/// Apply a subscript to every item, failing if it fails on any of them
+pub fn map(input: &RadonArray, args: &[Value]) -> Result<RadonArray, RadError> {
+    let subscript = subscript_argument(input, "ArrayMap", args)?;
+
+    input
+        .value()
+        .into_iter()
+        .map(|item| execute_radon_script(item, &subscript))
+        .collect::<Result<Vec<RadonTypes>, RadError>>()
+        .map(RadonArray::from)
+}
+
+/// Keep the items for which a predicate subscript returns `true`, failing if it fails or
+/// returns something other than a boolean for any of them
+pub fn filter(input: &RadonArray, args: &[Value]) -> Result<RadonArray, RadError> {
+    let predicate = subscript_argument(input, "ArrayFilter", args)?;
+
+    let mut kept = vec![];
+    for item in input.value() {
+        match execute_radon_script(item.clone(), &predicate)? {
+            RadonTypes::Mixed(ref mixed) if mixed.value().is_bool() => {
+                if mixed.value().as_bool() == Some(true) {
+                    kept.push(item);
+                }
+            }
+            other => Err(RadError::PredicateNotBoolean {
+                found: other.to_string(),
+            })?,
+        }
+    }
+
+    Ok(RadonArray::from(kept))
+}
+
+/// Sort the items by the keys computed by a subscript, in ascending order unless the optional
+/// second argument is `false`. Keys must be all integers, all floats or all strings, which are
+/// compared byte by byte. The sort is stable: items with equal keys keep their order.
+pub fn sort(input: &RadonArray, args: &[Value]) -> Result<RadonArray, RadError> {
+    let (subscript_args, ascending) = match args {
+        [subscript, ascending] => (
+            std::slice::from_ref(subscript),
+            ascending
+                .as_bool()
+                .ok_or_else(|| RadError::WrongArguments {
+                    input_type: input.to_string(),
+                    operator: "ArraySort".to_string(),
+                    args: args.to_vec(),
+                })?,
+        ),
+        _ => (args, true),
+    };
+    let subscript = subscript_argument(input, "ArraySort", subscript_args)?;
+
+    let mut keyed = input
+        .value()
+        .into_iter()
+        .map(|item| execute_radon_script(item.clone(), &subscript).map(|key| (key, item)))
+        .collect::<Result<Vec<(RadonTypes, RadonTypes)>, RadError>>()?;
+    if let Some((first, _)) = keyed.first() {
+        for (key, _) in &keyed {
+            compare_keys(first, key)?;
+        }
+    }
+
+    // All the keys are comparable after the check above
+    keyed.sort_by(|(a, _), (b, _)| {
+        let ordering = compare_keys(a, b).unwrap_or(Ordering::Equal);
+        if ascending {
+            ordering
+        } else {
+            ordering.reverse()
+        }
+    });
+
+    Ok(RadonArray::from(
+        keyed.into_iter().map(|(_, item)| item).collect::<Vec<_>>(),
+    ))
+}
+
+fn compare_keys(a: &RadonTypes, b: &RadonTypes) -> Result<Ordering, RadError> {
+    match (a, b) {
+        (RadonTypes::Integer(a), RadonTypes::Integer(b)) => Ok(a.value().cmp(&b.value())),
+        (RadonTypes::Float(a), RadonTypes::Float(b)) => a
+            .value()
+            .partial_cmp(&b.value())
+            .ok_or_else(|| RadError::NonFiniteFloat {
+                operator: "ArraySort".to_string(),
+            }),
+        (RadonTypes::String(a), RadonTypes::String(b)) => Ok(a.value().cmp(&b.value())),
+        (RadonTypes::Integer(_), other)
+        | (RadonTypes::Float(_), other)
+        | (RadonTypes::String(_), other) => Err(RadError::UnsortableKey {
+            found: other.to_string(),
+        }),
+        (other, _) => Err(RadError::UnsortableKey {
+            found: other.to_string(),
+        }),
+    }
+}
+
+/// Decode the subscript given as the only argument of an operator
+fn subscript_argument(
+    input: &RadonArray,
+    operator: &str,
+    args: &[Value],
+) -> Result<RadonScript, RadError> {
+    match args {
+        [subscript] => unpack_subscript(subscript),
+        _ => Err(RadError::WrongArguments {
+            input_type: input.to_string(),
+            operator: operator.to_string(),
+            args: args.to_vec(),
+        }),
+    }
+}
+
 pub fn get(input: &RadonArray, args: &[Value]) -> Result<RadonTypes, RadError> {
     let key = args.first().map(|ref value| value.as_u64()).unwrap_or(None);
     match key {
-        Some(key_str) => match input.value().get(key_str as usize) {
-            Some(value) => Ok(value.clone()),
-            None => Err(RadError::MapKeyNotFound {
-                key: key_str.to_string(),
-            }),
-        },
+        Some(index) => {
+            let value = input.value();
+            value
+                .get(index as usize)
+                .cloned()
+                .ok_or_else(|| RadError::ArrayIndexOutOfBounds {
+                    index,
+                    length: value.len(),
+                })
+        }
         None => Err(RadError::WrongArguments {
             input_type: input.to_string(),
             operator: "Get".to_string(),
@@ -34,6 +170,28 @@ pub fn get(input: &RadonArray, args: &[Value]) -> Result<RadonTypes, RadError> {
     }
 }
 
+#[test]
+fn test_array_get() {
+    use crate::types::float::RadonFloat;
+
+    let input = &RadonArray::from(vec![
+        RadonFloat::from(1f64).into(),
+        RadonFloat::from(2f64).into(),
+    ]);
+
+    assert_eq!(
+        get(input, &[Value::from(1)]),
+        Ok(RadonFloat::from(2f64).into())
+    );
+    assert_eq!(
+        get(input, &[Value::from(2)]),
+        Err(RadError::ArrayIndexOutOfBounds {
+            index: 2,
+            length: 2
+        })
+    );
+}
+
 #[test]
 fn test_reduce_no_args() {
     use crate::types::float::RadonFloat;
@@ -103,3 +261,151 @@ fn test_reduce_average_mean_float() {
 
     assert_eq!(output, expected);
 }
+
+#[cfg(test)]
+fn pack_call(op_code: crate::operators::RadonOpCodes, args: Vec<Value>) -> Value {
+    Value::Array(
+        std::iter::once(Value::from(op_code as i64))
+            .chain(args)
+            .collect(),
+    )
+}
+
+#[test]
+fn test_map() {
+    use crate::operators::RadonOpCodes;
+    use crate::types::{float::RadonFloat, integer::RadonInteger};
+
+    let input = &RadonArray::from(vec![
+        RadonFloat::from(1.5f64).into(),
+        RadonFloat::from(-2.5f64).into(),
+    ]);
+    let subscript = Value::Array(vec![Value::from(RadonOpCodes::FloatRound as i64)]);
+
+    assert_eq!(
+        map(input, &[subscript]),
+        Ok(RadonArray::from(vec![
+            RadonInteger::from(2).into(),
+            RadonInteger::from(-3).into(),
+        ]))
+    );
+    // The identity subscript
+    assert_eq!(map(input, &[Value::Array(vec![])]), Ok(input.clone()));
+    // Failing on any item fails the whole map
+    let failing = Value::Array(vec![Value::from(RadonOpCodes::ParseJson as i64)]);
+    assert!(map(input, &[failing]).is_err());
+    assert!(map(input, &[]).is_err());
+}
+
+#[test]
+fn test_filter() {
+    use crate::operators::RadonOpCodes;
+    use crate::types::string::RadonString;
+
+    let input = &RadonArray::from(vec![
+        RadonString::from("up").into(),
+        RadonString::from("down").into(),
+        RadonString::from("up").into(),
+    ]);
+    let categories = Value::Array(vec![
+        Value::Array(vec![Value::from("up"), Value::from(true)]),
+        Value::Array(vec![Value::from("down"), Value::from(false)]),
+    ]);
+    let predicate = Value::Array(vec![pack_call(RadonOpCodes::StringMatch, vec![categories])]);
+
+    assert_eq!(
+        filter(input, &[predicate]),
+        Ok(RadonArray::from(vec![
+            RadonString::from("up").into(),
+            RadonString::from("up").into(),
+        ]))
+    );
+    assert_eq!(
+        filter(input, &[Value::Array(vec![])]),
+        Err(RadError::PredicateNotBoolean {
+            found: RadonTypes::from(RadonString::from("up")).to_string()
+        })
+    );
+}
+
+#[test]
+fn test_sort() {
+    use crate::operators::RadonOpCodes;
+    use crate::types::{float::RadonFloat, map::RadonMap, mixed::RadonMixed, string::RadonString};
+
+    let item = |symbol: &str, price: f64| -> RadonTypes {
+        RadonMap::from(
+            vec![
+                ("symbol".to_string(), RadonMixed::from(Value::from(symbol))),
+                ("price".to_string(), RadonMixed::from(Value::from(price))),
+            ]
+            .into_iter()
+            .collect::<std::collections::HashMap<_, _>>(),
+        )
+        .into()
+    };
+    let input = &RadonArray::from(vec![
+        item("ETH", 135.2),
+        item("BTC", 3914.5),
+        item("XRP", 0.3),
+    ]);
+    let by_price = Value::Array(vec![
+        pack_call(RadonOpCodes::Get, vec![Value::from("price")]),
+        Value::from(RadonOpCodes::ToFloat as i64),
+    ]);
+
+    assert_eq!(
+        sort(input, &[by_price.clone()]),
+        Ok(RadonArray::from(vec![
+            item("XRP", 0.3),
+            item("ETH", 135.2),
+            item("BTC", 3914.5)
+        ]))
+    );
+    assert_eq!(
+        sort(input, &[by_price, Value::from(false)]),
+        Ok(RadonArray::from(vec![
+            item("BTC", 3914.5),
+            item("ETH", 135.2),
+            item("XRP", 0.3)
+        ]))
+    );
+
+    // Stable, and keys of different types are not comparable
+    let input = &RadonArray::from(vec![
+        RadonString::from("b").into(),
+        RadonString::from("a").into(),
+        RadonFloat::from(1f64).into(),
+    ]);
+    assert_eq!(
+        sort(input, &[Value::Array(vec![])]),
+        Err(RadError::UnsortableKey {
+            found: RadonTypes::from(RadonFloat::from(1f64)).to_string()
+        })
+    );
+}
+
+#[test]
+fn test_reduce_subscript() {
+    use crate::operators::RadonOpCodes;
+    use crate::types::integer::RadonInteger;
+
+    let input = &RadonArray::from(vec![
+        RadonInteger::from(1).into(),
+        RadonInteger::from(2).into(),
+        RadonInteger::from(3).into(),
+    ]);
+    // Keep the last item of every `[accumulated, item]` pair
+    let last = Value::Array(vec![pack_call(RadonOpCodes::Get, vec![Value::from(1)])]);
+
+    assert_eq!(
+        reduce(input, &[last.clone()]),
+        Ok(RadonInteger::from(3).into())
+    );
+    assert_eq!(
+        reduce(&RadonArray::from(vec![]), &[last]),
+        Err(RadError::EmptyArray {
+            operator: "Reduce".to_string()
+        })
+    );
+}
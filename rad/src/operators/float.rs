@@ -0,0 +1,160 @@
+use rmpv::Value;
+
+use crate::error::RadError;
+use crate::types::{float::RadonFloat, integer::RadonInteger, RadonType};
+
+/// Lower bound of the floats which can be converted into an integer: -2^63
+const MIN_INTEGER_FLOAT: f64 = -9_223_372_036_854_775_808.0;
+/// Upper bound, excluded, of the floats which can be converted into an integer: 2^63
+const MAX_INTEGER_FLOAT: f64 = 9_223_372_036_854_775_808.0;
+
+pub fn absolute(input: &RadonFloat) -> Result<RadonFloat, RadError> {
+    finite("FloatAbsolute", input.value().abs())
+}
+
+/// Multiplication by a float or integer factor
+pub fn multiply(input: &RadonFloat, args: &[Value]) -> Result<RadonFloat, RadError> {
+    let factor = match args.first() {
+        Some(Value::F64(factor)) => Some(*factor),
+        Some(Value::F32(factor)) => Some(f64::from(*factor)),
+        Some(Value::Integer(factor)) => factor.as_f64(),
+        _ => None,
+    }
+    .ok_or_else(|| RadError::WrongArguments {
+        input_type: input.to_string(),
+        operator: "FloatMultiply".to_string(),
+        args: args.to_vec(),
+    })?;
+
+    finite("FloatMultiply", input.value() * factor)
+}
+
+pub fn negate(input: &RadonFloat) -> Result<RadonFloat, RadError> {
+    finite("FloatNegate", -input.value())
+}
+
+/// Round to the nearest integer, with halfway cases rounded away from zero
+pub fn round(input: &RadonFloat) -> Result<RadonInteger, RadError> {
+    to_integer("FloatRound", input, input.value().round())
+}
+
+/// Truncate the fractional part, i.e. round towards zero
+pub fn truncate(input: &RadonFloat) -> Result<RadonInteger, RadError> {
+    to_integer("FloatTruncate", input, input.value().trunc())
+}
+
+/// Fail if an operator involves NaN or an infinity, whose representation and propagation may
+/// differ between platforms
+fn finite(operator: &str, value: f64) -> Result<RadonFloat, RadError> {
+    if value.is_finite() {
+        Ok(RadonFloat::from(value))
+    } else {
+        Err(RadError::NonFiniteFloat {
+            operator: operator.to_string(),
+        })
+    }
+}
+
+/// Convert an integral float into an integer, failing instead of saturating if it is out of range
+fn to_integer(operator: &str, input: &RadonFloat, value: f64) -> Result<RadonInteger, RadError> {
+    if !value.is_finite() {
+        Err(RadError::NonFiniteFloat {
+            operator: operator.to_string(),
+        })
+    } else if value < MIN_INTEGER_FLOAT || value >= MAX_INTEGER_FLOAT {
+        Err(RadError::Overflow {
+            operator: operator.to_string(),
+            input: input.to_string(),
+        })
+    } else {
+        Ok(RadonInteger::from(value as i64))
+    }
+}
+
+#[test]
+fn test_absolute() {
+    assert_eq!(absolute(&RadonFloat::from(-1.5)), Ok(RadonFloat::from(1.5)));
+    assert_eq!(
+        absolute(&RadonFloat::from(std::f64::NAN)),
+        Err(RadError::NonFiniteFloat {
+            operator: "FloatAbsolute".to_string()
+        })
+    );
+}
+
+#[test]
+fn test_multiply() {
+    let input = RadonFloat::from(0.1);
+
+    // IEEE 754 double precision product, bit for bit
+    let product = multiply(&input, &[Value::from(3.0)]).unwrap();
+    assert_eq!(product.value().to_bits(), 0x3FD3_3333_3333_3334);
+    assert_eq!(
+        multiply(&input, &[Value::from(3)]),
+        Ok(RadonFloat::from(0.1 * 3.0))
+    );
+    assert!(multiply(&input, &[Value::from("3")]).is_err());
+    assert!(multiply(&RadonFloat::from(std::f64::MAX), &[Value::from(2.0)]).is_err());
+    assert!(multiply(&input, &[Value::from(std::f64::NAN)]).is_err());
+}
+
+#[test]
+fn test_negate() {
+    assert_eq!(negate(&RadonFloat::from(1.5)), Ok(RadonFloat::from(-1.5)));
+    assert!(negate(&RadonFloat::from(std::f64::INFINITY)).is_err());
+}
+
+#[test]
+fn test_round() {
+    let cases = [
+        (2.5, 3),
+        (-2.5, -3),
+        (0.5, 1),
+        (-0.5, -1),
+        (1.4999999999999998, 1),
+        (-2.7, -3),
+        (-0.4, 0),
+    ];
+    for (input, expected) in cases.iter() {
+        assert_eq!(
+            round(&RadonFloat::from(*input)),
+            Ok(RadonInteger::from(*expected))
+        );
+    }
+}
+
+#[test]
+fn test_truncate() {
+    let cases = [
+        (2.7, 2),
+        (-2.7, -2),
+        (-0.9, 0),
+        (MIN_INTEGER_FLOAT, i64::min_value()),
+    ];
+    for (input, expected) in cases.iter() {
+        assert_eq!(
+            truncate(&RadonFloat::from(*input)),
+            Ok(RadonInteger::from(*expected))
+        );
+    }
+}
+
+#[test]
+fn test_to_integer_out_of_range() {
+    let input = RadonFloat::from(MAX_INTEGER_FLOAT);
+
+    assert_eq!(
+        truncate(&input),
+        Err(RadError::Overflow {
+            operator: "FloatTruncate".to_string(),
+            input: input.to_string(),
+        })
+    );
+    assert!(round(&RadonFloat::from(-1e19)).is_err());
+    assert_eq!(
+        round(&RadonFloat::from(std::f64::NAN)),
+        Err(RadError::NonFiniteFloat {
+            operator: "FloatRound".to_string()
+        })
+    );
+}
@@ -0,0 +1,96 @@
+use rmpv::Value;
+
+use crate::error::RadError;
+use crate::types::{float::RadonFloat, integer::RadonInteger, RadonType};
+
+/// Absolute value, failing for the minimum integer, whose absolute value does not fit
+pub fn absolute(input: &RadonInteger) -> Result<RadonInteger, RadError> {
+    input
+        .value()
+        .checked_abs()
+        .map(RadonInteger::from)
+        .ok_or_else(|| overflow("IntegerAbsolute", input))
+}
+
+/// Multiplication by an integer factor, failing if the product overflows
+pub fn multiply(input: &RadonInteger, args: &[Value]) -> Result<RadonInteger, RadError> {
+    let factor = args
+        .first()
+        .and_then(Value::as_i64)
+        .ok_or_else(|| RadError::WrongArguments {
+            input_type: input.to_string(),
+            operator: "IntegerMultiply".to_string(),
+            args: args.to_vec(),
+        })?;
+
+    input
+        .value()
+        .checked_mul(factor)
+        .map(RadonInteger::from)
+        .ok_or_else(|| overflow("IntegerMultiply", input))
+}
+
+/// Negation, failing for the minimum integer, whose negation does not fit
+pub fn negate(input: &RadonInteger) -> Result<RadonInteger, RadError> {
+    input
+        .value()
+        .checked_neg()
+        .map(RadonInteger::from)
+        .ok_or_else(|| overflow("IntegerNegate", input))
+}
+
+/// Convert into a float, rounding to the nearest float the integers beyond 2^53, as every
+/// platform does under IEEE 754
+pub fn to_float(input: &RadonInteger) -> RadonFloat {
+    RadonFloat::from(input.value() as f64)
+}
+
+fn overflow(operator: &str, input: &RadonInteger) -> RadError {
+    RadError::Overflow {
+        operator: operator.to_string(),
+        input: input.to_string(),
+    }
+}
+
+#[test]
+fn test_absolute() {
+    assert_eq!(absolute(&RadonInteger::from(-7)), Ok(RadonInteger::from(7)));
+    assert_eq!(absolute(&RadonInteger::from(7)), Ok(RadonInteger::from(7)));
+    assert_eq!(
+        absolute(&RadonInteger::from(i64::min_value())),
+        Err(RadError::Overflow {
+            operator: "IntegerAbsolute".to_string(),
+            input: "RadonInteger(-9223372036854775808)".to_string(),
+        })
+    );
+}
+
+#[test]
+fn test_multiply() {
+    let input = RadonInteger::from(-21);
+
+    assert_eq!(
+        multiply(&input, &[Value::from(2)]),
+        Ok(RadonInteger::from(-42))
+    );
+    assert!(multiply(&RadonInteger::from(i64::max_value()), &[Value::from(2)]).is_err());
+    // Float factors are not implicitly truncated
+    assert!(multiply(&input, &[Value::from(2.0)]).is_err());
+    assert!(multiply(&input, &[]).is_err());
+}
+
+#[test]
+fn test_negate() {
+    assert_eq!(negate(&RadonInteger::from(7)), Ok(RadonInteger::from(-7)));
+    assert_eq!(negate(&RadonInteger::from(0)), Ok(RadonInteger::from(0)));
+    assert!(negate(&RadonInteger::from(i64::min_value())).is_err());
+}
+
+#[test]
+fn test_to_float() {
+    assert_eq!(to_float(&RadonInteger::from(-21)), RadonFloat::from(-21.0));
+    assert_eq!(
+        to_float(&RadonInteger::from(i64::max_value())),
+        RadonFloat::from(9_223_372_036_854_775_808.0)
+    );
+}
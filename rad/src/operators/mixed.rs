@@ -1,14 +1,25 @@
+use rmpv::Value;
 use witnet_data_structures::serializers::decoders::TryFrom;
 
 use crate::error::RadError;
 use crate::types::{
-    array::RadonArray, float::RadonFloat, map::RadonMap, mixed::RadonMixed, RadonType,
+    array::RadonArray,
+    float::RadonFloat,
+    integer::RadonInteger,
+    map::RadonMap,
+    mixed::{RadonMixed, RADON_MIXED_TYPE_NAME},
+    RadonType,
 };
 
 pub fn to_float(input: RadonMixed) -> Result<RadonFloat, RadError> {
     RadonFloat::try_from(input.value())
 }
 
+/// Convert into an integer, failing for floats instead of rounding them
+pub fn to_integer(input: RadonMixed) -> Result<RadonInteger, RadError> {
+    RadonInteger::try_from(input.value())
+}
+
 pub fn to_map(input: RadonMixed) -> Result<RadonMap, RadError> {
     RadonMap::try_from(input.value())
 }
@@ -16,6 +27,75 @@ pub fn to_array(input: RadonMixed) -> Result<RadonArray, RadError> {
     RadonArray::try_from(input.value())
 }
 
+/// Traverse nested maps and arrays, e.g. a parsed JSON document. Every argument is a step of the
+/// path: strings are keys of maps and natural numbers are indexes of arrays.
+pub fn get_path(input: RadonMixed, args: &[Value]) -> Result<RadonMixed, RadError> {
+    if args.is_empty() {
+        Err(RadError::WrongArguments {
+            input_type: RADON_MIXED_TYPE_NAME.to_string(),
+            operator: "GetPath".to_string(),
+            args: args.to_vec(),
+        })?
+    }
+
+    let mut path = String::new();
+    args.iter()
+        .try_fold(input.value(), |value, step| {
+            if let Some(key) = step.as_str() {
+                path.push_str(&format!(".{}", key));
+                match value {
+                    Value::Map(entries) => entries
+                        .into_iter()
+                        .find(|(entry_key, _)| entry_key.as_str() == Some(key))
+                        .map(|(_, entry_value)| entry_value)
+                        .ok_or_else(|| RadError::MapKeyNotFound {
+                            key: path.trim_start_matches('.').to_string(),
+                        }),
+                    other => Err(path_type_mismatch(&path, "a map", &other)),
+                }
+            } else if let Some(index) = step.as_u64() {
+                path.push_str(&format!("[{}]", index));
+                match value {
+                    Value::Array(mut items) => {
+                        let length = items.len();
+                        if (index as usize) < length {
+                            Ok(items.swap_remove(index as usize))
+                        } else {
+                            Err(RadError::ArrayIndexOutOfBounds { index, length })
+                        }
+                    }
+                    other => Err(path_type_mismatch(&path, "an array", &other)),
+                }
+            } else {
+                Err(RadError::WrongArguments {
+                    input_type: RADON_MIXED_TYPE_NAME.to_string(),
+                    operator: "GetPath".to_string(),
+                    args: args.to_vec(),
+                })
+            }
+        })
+        .map(RadonMixed::from)
+}
+
+fn path_type_mismatch(path: &str, expected: &str, found: &Value) -> RadError {
+    let found = match found {
+        Value::Nil => "null",
+        Value::Boolean(_) => "a boolean",
+        Value::Integer(_) | Value::F32(_) | Value::F64(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Binary(_) => "binary data",
+        Value::Array(_) => "an array",
+        Value::Map(_) => "a map",
+        Value::Ext(..) => "an extension",
+    };
+
+    RadError::PathTypeMismatch {
+        path: path.trim_start_matches('.').to_string(),
+        expected: expected.to_string(),
+        found: found.to_string(),
+    }
+}
+
 #[test]
 fn test_as_float() {
     use rmpv::Value;
@@ -30,3 +110,65 @@ fn test_as_float() {
         "Failed to decode RadonFloat from rmpv::Value"
     );
 }
+
+#[test]
+fn test_as_integer() {
+    assert_eq!(
+        to_integer(RadonMixed::from(Value::from(-21))),
+        Ok(RadonInteger::from(-21))
+    );
+    assert!(to_integer(RadonMixed::from(Value::from(2.0))).is_err());
+    assert!(to_integer(RadonMixed::from(Value::from(u64::max_value()))).is_err());
+}
+
+#[test]
+fn test_get_path() {
+    use crate::operators::string::parse_json;
+    use crate::types::string::RadonString;
+
+    let input = parse_json(&RadonString::from(
+        r#"{"main":{"temp":-4},"weather":[{"id":600,"main":"Snow"}]}"#,
+    ))
+    .unwrap();
+    let get = |args: Vec<Value>| get_path(input.clone(), &args);
+
+    assert_eq!(
+        get(vec![Value::from("main"), Value::from("temp")]).map(to_float),
+        Ok(Ok(RadonFloat::from(-4f64)))
+    );
+    assert_eq!(
+        get(vec![
+            Value::from("weather"),
+            Value::from(0),
+            Value::from("main")
+        ]),
+        Ok(RadonMixed::from(Value::from("Snow")))
+    );
+    assert_eq!(
+        get(vec![Value::from("main"), Value::from("wind")]),
+        Err(RadError::MapKeyNotFound {
+            key: "main.wind".to_string()
+        })
+    );
+    assert_eq!(
+        get(vec![Value::from("weather"), Value::from(1)]),
+        Err(RadError::ArrayIndexOutOfBounds {
+            index: 1,
+            length: 1
+        })
+    );
+    assert_eq!(
+        get(vec![
+            Value::from("main"),
+            Value::from("temp"),
+            Value::from(0)
+        ]),
+        Err(RadError::PathTypeMismatch {
+            path: "main.temp[0]".to_string(),
+            expected: "an array".to_string(),
+            found: "a number".to_string(),
+        })
+    );
+    assert!(get(vec![]).is_err());
+    assert!(get(vec![Value::from(-1)]).is_err());
+}
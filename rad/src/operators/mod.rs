@@ -9,11 +9,13 @@ use num_derive::FromPrimitive;
 use std::fmt;
 
 pub mod array;
+pub mod float;
+pub mod integer;
 pub mod map;
 pub mod mixed;
 pub mod string;
 
-#[derive(Debug, FromPrimitive, PartialEq)]
+#[derive(Clone, Copy, Debug, FromPrimitive, PartialEq)]
 pub enum RadonOpCodes {
     /// Only for the sake of allowing catch-alls when matching
     Fail = -1,
@@ -22,21 +24,61 @@ pub enum RadonOpCodes {
     Identity = 0x00,
     /// Array::get, Map::get, Result::get
     Get = 0x01,
+    /// Mixed::getPath, traverses nested maps and arrays
+    GetPath = 0x02,
     // Boolean operator codes start at 0x10
     // Integer operator codes start at 0x20
+    /// Absolute value of an integer, failing on overflow
+    IntegerAbsolute = 0x20,
+    /// Multiply an integer by an integer factor, failing on overflow
+    IntegerMultiply = 0x23,
+    /// Negate an integer, failing on overflow
+    IntegerNegate = 0x24,
     // Float operator codes start at 0x30
+    /// Absolute value of a float
+    FloatAbsolute = 0x30,
+    /// Multiply a float by a numeric factor
+    FloatMultiply = 0x34,
+    /// Negate a float
+    FloatNegate = 0x35,
+    /// Round a float to the nearest integer, with halfway cases away from zero
+    FloatRound = 0x38,
+    /// Truncate a float to an integer, towards zero
+    FloatTruncate = 0x3b,
     // Null operator codes start at 0x40
     // String operator codes start at 0x50
     /// Compute the hash of a string
     Hash = 0x50,
+    /// Number of bytes of a string
+    StringLength = 0x51,
+    /// Look a string up in a map of categories
+    StringMatch = 0x52,
     /// Parse Mixed from JSON string
     ParseJson = 0x53,
+    /// Parse a float from a string
+    StringParseFloat = 0x56,
+    /// Parse an integer from a string, in an optional base
+    StringParseInt = 0x57,
+    /// Lowercase the ASCII letters of a string
+    StringToLowerCase = 0x58,
+    /// Uppercase the ASCII letters of a string
+    StringToUpperCase = 0x59,
+    /// Substring between two byte offsets
+    StringSlice = 0x5a,
     // Array operator codes start at 0x60
+    /// Keep the items of an array for which a predicate subscript returns true
+    ArrayFilter = 0x62,
+    /// Apply a subscript to every item of an array
+    ArrayMap = 0x65,
+    /// Reduce an array with a reducer or a subscript
     Reduce = 0x66,
+    /// Sort an array by the keys computed by a subscript
+    ArraySort = 0x68,
     // Map operator codes start at 0x70
     // Mixed operator codes start at 0x80
     ToArray = 0x80,
     ToFloat = 0x82,
+    ToInteger = 0x83,
     ToMap = 0x84,
     // Result operator codes start at 0x90
 }
@@ -55,6 +97,7 @@ pub fn operate(input: RadonTypes, call: &RadonCall) -> Result<RadonTypes, RadErr
     match input {
         RadonTypes::Array(radon_array) => radon_array.operate(call),
         RadonTypes::Float(radon_float) => radon_float.operate(call),
+        RadonTypes::Integer(radon_integer) => radon_integer.operate(call),
         RadonTypes::Map(radon_map) => radon_map.operate(call),
         RadonTypes::String(radon_string) => radon_string.operate(call),
         RadonTypes::Mixed(radon_mixed) => radon_mixed.operate(call),
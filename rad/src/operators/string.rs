@@ -1,11 +1,18 @@
 use crate::error::RadError;
 use crate::hash_functions::{self, RadonHashFunctions};
-use crate::types::{float::RadonFloat, mixed::RadonMixed, string::RadonString, RadonType};
+use crate::types::{
+    float::RadonFloat, integer::RadonInteger, mixed::RadonMixed, string::RadonString, RadonType,
+    RadonTypes,
+};
 
 use json;
 use num_traits::FromPrimitive;
 use rmpv::{self, Value};
 use std::{error::Error, str::FromStr};
+use witnet_data_structures::serializers::decoders::TryFrom;
+
+/// Bases accepted by `parse_int`
+const INTEGER_BASES: [u32; 4] = [2, 8, 10, 16];
 
 pub fn parse_json(input: &RadonString) -> Result<RadonMixed, RadError> {
     match json::parse(&input.value()) {
@@ -18,12 +25,124 @@ pub fn parse_json(input: &RadonString) -> Result<RadonMixed, RadError> {
         }),
     }
 }
-pub fn to_float(input: &RadonString) -> Result<RadonFloat, RadError> {
-    f64::from_str(&input.value())
-        .map(RadonFloat::from)
+
+/// Parse a float, failing for `NaN` and infinities even if they are spelled out
+pub fn parse_float(input: &RadonString) -> Result<RadonFloat, RadError> {
+    let value = f64::from_str(&input.value())?;
+    if value.is_finite() {
+        Ok(RadonFloat::from(value))
+    } else {
+        Err(RadError::NonFiniteFloat {
+            operator: "StringParseFloat".to_string(),
+        })
+    }
+}
+
+/// Parse an integer in base 2, 8, 10 or 16, which is given by the optional argument and
+/// defaults to 10. The digits may be preceded by a sign, but not by a prefix such as `0x`.
+pub fn parse_int(input: &RadonString, args: Option<&[Value]>) -> Result<RadonInteger, RadError> {
+    let base = match args {
+        None | Some([]) => Some(10),
+        Some([base]) => base
+            .as_u64()
+            .filter(|base| INTEGER_BASES.iter().any(|valid| u64::from(*valid) == *base))
+            .map(|base| base as u32),
+        Some(_) => None,
+    }
+    .ok_or_else(|| RadError::WrongArguments {
+        input_type: input.to_string(),
+        operator: "StringParseInt".to_string(),
+        args: args.unwrap_or_default().to_vec(),
+    })?;
+
+    i64::from_str_radix(&input.value(), base)
+        .map(RadonInteger::from)
         .map_err(Into::into)
 }
 
+/// Number of bytes of the UTF-8 encoding of the string
+pub fn length(input: &RadonString) -> RadonInteger {
+    RadonInteger::from(input.value().len() as i64)
+}
+
+/// Look the string up in the categories of the first argument, given either as a map or as an
+/// array of `[key, value]` pairs, returning the value of the first matching key. The optional
+/// second argument is returned if no key matches.
+pub fn string_match(input: &RadonString, args: &[Value]) -> Result<RadonTypes, RadError> {
+    let wrong_args = || RadError::WrongArguments {
+        input_type: input.to_string(),
+        operator: "StringMatch".to_string(),
+        args: args.to_vec(),
+    };
+
+    let (categories, default) = match args {
+        [categories] => (categories, None),
+        [categories, default] => (categories, Some(default)),
+        _ => Err(wrong_args())?,
+    };
+    let categories = match categories {
+        Value::Map(entries) => entries.clone(),
+        Value::Array(pairs) => pairs
+            .iter()
+            .map(|pair| match pair.as_array().map(Vec::as_slice) {
+                Some([key, value]) => Ok((key.clone(), value.clone())),
+                _ => Err(wrong_args()),
+            })
+            .collect::<Result<_, _>>()?,
+        _ => Err(wrong_args())?,
+    };
+
+    let string = input.value();
+    categories
+        .into_iter()
+        .find(|(key, _)| key.as_str() == Some(string.as_str()))
+        .map(|(_, value)| value)
+        .or_else(|| default.cloned())
+        .ok_or_else(|| RadError::MatchNotFound { input: string })
+        .and_then(RadonTypes::try_from)
+}
+
+/// Bytes from the first argument up to the optional second one, which defaults to the end of the
+/// string. Both are byte offsets of the UTF-8 encoding, and must lie at character boundaries.
+pub fn slice(input: &RadonString, args: &[Value]) -> Result<RadonString, RadError> {
+    let offsets = match args {
+        [start] => start.as_u64().map(|start| (start, None)),
+        [start, end] => start
+            .as_u64()
+            .and_then(|start| end.as_u64().map(|end| (start, Some(end)))),
+        _ => None,
+    };
+    let (start, end) = offsets.ok_or_else(|| RadError::WrongArguments {
+        input_type: input.to_string(),
+        operator: "StringSlice".to_string(),
+        args: args.to_vec(),
+    })?;
+
+    let string = input.value();
+    let length = string.len();
+    let end = end.unwrap_or(length as u64);
+    let out_of_bounds = || RadError::SliceOutOfBounds { start, end, length };
+    if end > length as u64 {
+        Err(out_of_bounds())?
+    }
+
+    string
+        .get(start as usize..end as usize)
+        .map(RadonString::from)
+        .ok_or_else(out_of_bounds)
+}
+
+/// Lowercase ASCII letters, leaving any other character untouched. Unicode case mappings are
+/// avoided because they change between Unicode versions.
+pub fn to_lowercase(input: &RadonString) -> RadonString {
+    RadonString::from(input.value().to_ascii_lowercase())
+}
+
+/// Uppercase ASCII letters, leaving any other character untouched
+pub fn to_uppercase(input: &RadonString) -> RadonString {
+    RadonString::from(input.value().to_ascii_uppercase())
+}
+
 pub fn hash(input: &RadonString, args: &[Value]) -> Result<RadonString, RadError> {
     let error = || RadError::WrongArguments {
         input_type: "RadonString".to_string(),
@@ -115,3 +234,118 @@ fn test_hash() {
         "Hash function `RadonHashFunctions::Fail` is not implemented"
     );
 }
+
+#[test]
+fn test_parse_float() {
+    assert_eq!(
+        parse_float(&RadonString::from("-3914.5")),
+        Ok(RadonFloat::from(-3914.5))
+    );
+    assert!(
+        if let Err(RadError::ParseFloat { .. }) = parse_float(&RadonString::from("3,914")) {
+            true
+        } else {
+            false
+        }
+    );
+    assert_eq!(
+        parse_float(&RadonString::from("NaN")),
+        Err(RadError::NonFiniteFloat {
+            operator: "StringParseFloat".to_string()
+        })
+    );
+    assert!(parse_float(&RadonString::from("inf")).is_err());
+}
+
+#[test]
+fn test_parse_int() {
+    let input = RadonString::from("-42");
+
+    assert_eq!(parse_int(&input, None), Ok(RadonInteger::from(-42)));
+    assert_eq!(
+        parse_int(&RadonString::from("BEEF"), Some(&[Value::from(16)])),
+        Ok(RadonInteger::from(48879))
+    );
+    assert_eq!(
+        parse_int(&RadonString::from("1011"), Some(&[Value::from(2)])),
+        Ok(RadonInteger::from(11))
+    );
+    assert!(parse_int(&RadonString::from("0xBEEF"), Some(&[Value::from(16)])).is_err());
+    assert!(parse_int(&RadonString::from("4.2"), None).is_err());
+    assert!(parse_int(&RadonString::from("9223372036854775808"), None).is_err());
+    assert!(parse_int(&input, Some(&[Value::from(36)])).is_err());
+}
+
+#[test]
+fn test_length() {
+    assert_eq!(length(&RadonString::from("")), RadonInteger::from(0));
+    // Bytes, not characters
+    assert_eq!(length(&RadonString::from("5 €")), RadonInteger::from(5));
+}
+
+#[test]
+fn test_string_match() {
+    let input = RadonString::from("sunny");
+    let pairs = Value::Array(vec![
+        Value::Array(vec![Value::from("rainy"), Value::from(0)]),
+        Value::Array(vec![Value::from("sunny"), Value::from(1)]),
+    ]);
+    let map = Value::Map(vec![(Value::from("sunny"), Value::from("yes"))]);
+
+    assert_eq!(
+        string_match(&input, &[pairs.clone()]),
+        Ok(RadonTypes::from(RadonInteger::from(1)))
+    );
+    assert_eq!(
+        string_match(&input, &[map]),
+        Ok(RadonTypes::from(RadonString::from("yes")))
+    );
+    assert_eq!(
+        string_match(
+            &RadonString::from("stormy"),
+            &[pairs.clone(), Value::from(2)]
+        ),
+        Ok(RadonTypes::from(RadonInteger::from(2)))
+    );
+    assert_eq!(
+        string_match(&RadonString::from("stormy"), &[pairs]),
+        Err(RadError::MatchNotFound {
+            input: "stormy".to_string()
+        })
+    );
+    assert!(string_match(&input, &[Value::from("sunny")]).is_err());
+}
+
+#[test]
+fn test_slice() {
+    let input = RadonString::from("BTC: 5 €");
+
+    assert_eq!(
+        slice(&input, &[Value::from(5)]),
+        Ok(RadonString::from("5 €"))
+    );
+    assert_eq!(
+        slice(&input, &[Value::from(0), Value::from(3)]),
+        Ok(RadonString::from("BTC"))
+    );
+    assert_eq!(
+        slice(&input, &[Value::from(0), Value::from(11)]),
+        Err(RadError::SliceOutOfBounds {
+            start: 0,
+            end: 11,
+            length: 10,
+        })
+    );
+    // The euro sign takes 3 bytes, starting at 7
+    assert!(slice(&input, &[Value::from(8)]).is_err());
+    assert!(slice(&input, &[Value::from(4), Value::from(3)]).is_err());
+    assert!(slice(&input, &[Value::from(-1)]).is_err());
+}
+
+#[test]
+fn test_case() {
+    let input = RadonString::from("Straße ÉTÉ");
+
+    assert_eq!(to_lowercase(&input), RadonString::from("straße ÉtÉ"));
+    assert_eq!(to_uppercase(&input), RadonString::from("STRAßE ÉTÉ"));
+}
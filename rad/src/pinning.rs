@@ -0,0 +1,421 @@
+//! Retrieval of the sources with a pinned TLS certificate.
+//!
+//! The HTTP client does not expose the certificates of the connections it opens, so checking the
+//! certificate on a connection of its own would not prove anything about the connection the
+//! source is retrieved from. Instead, pinned sources are retrieved with a single `HTTP/1.0`
+//! request sent over the very TLS connection whose certificate was checked. Redirects are not
+//! followed, since the pin only applies to the server of the URL: they fail with their status code.
+
+use std::{
+    io::{self, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    thread,
+    time::{Duration, Instant},
+};
+
+use log::debug;
+use native_tls::TlsConnector;
+use reqwest::Url;
+
+use witnet_crypto::hash::calculate_sha256;
+
+use crate::error::RadError;
+use crate::http::{is_transient, HttpOptions};
+
+/// Length of the SHA-256 fingerprints of the pinned certificates
+pub const FINGERPRINT_LENGTH: usize = 32;
+
+/// Maximum size of the status line and the headers of a response
+const MAX_HEADERS_SIZE: u64 = 64 * 1024;
+
+/// Response of a source with a pinned certificate
+#[derive(Debug, PartialEq)]
+pub struct PinnedResponse {
+    /// Value of the `Content-Type` header, if any
+    pub content_type: Option<String>,
+    /// Body of the response
+    pub body: Vec<u8>,
+}
+
+/// Send a GET request, or a POST one if there is a body along with its media type, to a server
+/// whose certificate must match the SHA-256 `fingerprint`. The request is retried like the ones
+/// of the [`http`](crate::http) module, and responses larger than `max_size` bytes fail, unless
+/// `max_size` is `0`.
+pub fn send(
+    url: &str,
+    body: Option<(&[u8], &str)>,
+    fingerprint: &[u8],
+    timeout: Duration,
+    max_size: u64,
+    options: &HttpOptions,
+) -> Result<PinnedResponse, RadError> {
+    if fingerprint.len() != FINGERPRINT_LENGTH {
+        Err(pinning_error(format!(
+            "The pinned fingerprint has {} bytes instead of {}",
+            fingerprint.len(),
+            FINGERPRINT_LENGTH
+        )))?
+    }
+    let url = Url::parse(url).map_err(|e| pinning_error(e.to_string()))?;
+    if url.scheme() != "https" {
+        Err(pinning_error(format!(
+            "Certificates can only be pinned for HTTPS URLs, got `{}`",
+            url.scheme()
+        )))?
+    }
+
+    let mut retry = 0;
+    loop {
+        let result = send_once(&url, body, fingerprint, timeout, max_size, options);
+        match result {
+            Err(ref e) if is_transient(e) && retry < options.max_retries => {
+                retry += 1;
+                let backoff = options.backoff(retry);
+                debug!(
+                    "Retrying pinned {} in {:?} after error: {}",
+                    url, backoff, e
+                );
+                thread::sleep(backoff);
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Open a TLS connection to the server of `url`, check its certificate and send the request over
+/// it. The `timeout` applies to the whole exchange, like the one of the HTTP client, rather than
+/// to each read and write, so that a server trickling its response cannot hold the retrieval.
+fn send_once(
+    url: &Url,
+    body: Option<(&[u8], &str)>,
+    fingerprint: &[u8],
+    timeout: Duration,
+    max_size: u64,
+    options: &HttpOptions,
+) -> Result<PinnedResponse, RadError> {
+    let host = url
+        .host_str()
+        .ok_or_else(|| pinning_error("URL without host".to_string()))?;
+    let port = url.port_or_known_default().unwrap_or(443);
+    let io_error = |e: io::Error| map_io_error(e, timeout);
+    let deadline = Instant::now() + timeout;
+
+    let address = (host, port)
+        .to_socket_addrs()
+        .map_err(io_error)?
+        .next()
+        .ok_or_else(|| RadError::Http {
+            message: format!("Failed to resolve {}", host),
+        })?;
+    let stream = TcpStream::connect_timeout(&address, timeout).map_err(io_error)?;
+    set_timeouts(&stream, deadline).map_err(io_error)?;
+    let mut tls_stream = TlsConnector::new()
+        .map_err(|e| pinning_error(e.to_string()))?
+        .connect(host, stream)
+        .map_err(|e| pinning_error(e.to_string()))?;
+    let certificate = tls_stream
+        .peer_certificate()
+        .map_err(|e| pinning_error(e.to_string()))?
+        .ok_or_else(|| pinning_error("The server did not send a certificate".to_string()))?
+        .to_der()
+        .map_err(|e| pinning_error(e.to_string()))?;
+    compare_fingerprint(fingerprint, &certificate)?;
+
+    set_timeouts(tls_stream.get_ref(), deadline).map_err(io_error)?;
+    tls_stream
+        .write_all(&build_request(url, body, &options.user_agent))
+        .and_then(|()| tls_stream.flush())
+        .map_err(io_error)?;
+
+    let limit = if max_size == 0 {
+        u64::max_value()
+    } else {
+        MAX_HEADERS_SIZE.saturating_add(max_size).saturating_add(1)
+    };
+    let mut response = vec![];
+    let mut buffer = [0; 8 * 1024];
+    while (response.len() as u64) < limit {
+        set_timeouts(tls_stream.get_ref(), deadline).map_err(io_error)?;
+        let read_size = std::cmp::min(buffer.len() as u64, limit - response.len() as u64);
+        match tls_stream.read(&mut buffer[..read_size as usize]) {
+            Ok(0) => break,
+            Ok(size) => response.extend_from_slice(&buffer[..size]),
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+            // Servers often close TLS connections without a close notification, which is not an
+            // error for an `HTTP/1.0` response delimited by the end of the connection
+            Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof && !response.is_empty() => break,
+            Err(e) => Err(io_error(e))?,
+        }
+    }
+
+    parse_response(&response, max_size)
+}
+
+/// Limit the next reads and writes of a connection to the time left until the `deadline`
+fn set_timeouts(stream: &TcpStream, deadline: Instant) -> io::Result<()> {
+    let now = Instant::now();
+    if now >= deadline {
+        return Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            "The retrieval timed out",
+        ));
+    }
+    let remaining = deadline - now;
+
+    stream
+        .set_read_timeout(Some(remaining))
+        .and_then(|()| stream.set_write_timeout(Some(remaining)))
+}
+
+/// Serialize an `HTTP/1.0` request, so that the server answers without chunked encoding and
+/// closes the connection after the response
+fn build_request(url: &Url, body: Option<(&[u8], &str)>, user_agent: &str) -> Vec<u8> {
+    let mut target = url.path().to_string();
+    if let Some(query) = url.query() {
+        target.push('?');
+        target.push_str(query);
+    }
+    let host = match url.port() {
+        Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+        None => url.host_str().unwrap_or_default().to_string(),
+    };
+    let method = if body.is_some() { "POST" } else { "GET" };
+
+    let mut request = format!(
+        "{} {} HTTP/1.0\r\nHost: {}\r\nUser-Agent: {}\r\nConnection: close\r\n",
+        method, target, host, user_agent
+    );
+    if let Some((body, content_type)) = body {
+        request.push_str(&format!(
+            "Content-Type: {}\r\nContent-Length: {}\r\n",
+            content_type,
+            body.len()
+        ));
+    }
+    request.push_str("\r\n");
+
+    let mut request = request.into_bytes();
+    if let Some((body, _)) = body {
+        request.extend_from_slice(body);
+    }
+
+    request
+}
+
+/// Parse an HTTP response, failing on unsuccessful status codes, including redirects
+fn parse_response(response: &[u8], max_size: u64) -> Result<PinnedResponse, RadError> {
+    let malformed = |reason: &str| RadError::Http {
+        message: format!("Malformed HTTP response: {}", reason),
+    };
+
+    let headers_end = response
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .ok_or_else(|| malformed("the headers do not end"))?;
+    if headers_end as u64 > MAX_HEADERS_SIZE {
+        Err(malformed("the headers are too large"))?
+    }
+    let headers = String::from_utf8_lossy(&response[..headers_end]);
+    let mut lines = headers.split("\r\n");
+
+    let status_code = lines
+        .next()
+        .filter(|status_line| status_line.starts_with("HTTP/"))
+        .and_then(|status_line| status_line.split_whitespace().nth(1))
+        .and_then(|status_code| status_code.parse::<u16>().ok())
+        .ok_or_else(|| malformed("invalid status line"))?;
+    if status_code < 200 || status_code > 299 {
+        Err(RadError::HttpStatus { status_code })?
+    }
+
+    let mut content_type = None;
+    let mut content_length = None;
+    for line in lines {
+        let mut parts = line.splitn(2, ':');
+        let name = parts.next().unwrap_or_default().trim();
+        let value = parts.next().unwrap_or_default().trim();
+        if name.eq_ignore_ascii_case("content-type") {
+            content_type = Some(value.to_string());
+        } else if name.eq_ignore_ascii_case("content-length") {
+            content_length = Some(
+                value
+                    .parse::<usize>()
+                    .map_err(|_| malformed("invalid content length"))?,
+            );
+        } else if name.eq_ignore_ascii_case("transfer-encoding")
+            && !value.eq_ignore_ascii_case("identity")
+        {
+            Err(malformed("unsupported transfer encoding"))?
+        }
+    }
+
+    let mut body = &response[headers_end + 4..];
+    if let Some(content_length) = content_length {
+        if body.len() < content_length {
+            Err(malformed("the body is shorter than its content length"))?
+        }
+        body = &body[..content_length];
+    }
+    if max_size != 0 && body.len() as u64 > max_size {
+        Err(RadError::ResponseTooLarge { max_size })?
+    }
+
+    Ok(PinnedResponse {
+        content_type,
+        body: body.to_vec(),
+    })
+}
+
+/// Compare the SHA-256 fingerprint of a DER encoded certificate with the expected one
+fn compare_fingerprint(expected: &[u8], certificate: &[u8]) -> Result<(), RadError> {
+    let found = calculate_sha256(certificate).0;
+    if found[..] == expected[..] {
+        Ok(())
+    } else {
+        Err(RadError::CertificateMismatch {
+            expected: hex::encode(expected),
+            found: hex::encode(found),
+        })
+    }
+}
+
+fn pinning_error(message: String) -> RadError {
+    RadError::CertificatePinning { message }
+}
+
+/// Map the errors of the connection into RAD errors
+fn map_io_error(error: io::Error, timeout: Duration) -> RadError {
+    match error.kind() {
+        io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => RadError::HttpTimeout {
+            timeout_ms: timeout.as_secs() * 1000 + u64::from(timeout.subsec_millis()),
+        },
+        _ => RadError::Http {
+            message: error.to_string(),
+        },
+    }
+}
+
+#[test]
+fn test_compare_fingerprint() {
+    let certificate = b"DER encoded certificate";
+    let fingerprint = calculate_sha256(certificate).0;
+
+    assert_eq!(compare_fingerprint(&fingerprint, certificate), Ok(()));
+    assert_eq!(
+        compare_fingerprint(&[0; 32], certificate),
+        Err(RadError::CertificateMismatch {
+            expected: hex::encode([0; 32]),
+            found: hex::encode(fingerprint),
+        })
+    );
+}
+
+#[test]
+fn test_send_checks_url_and_fingerprint() {
+    let pinned = |url, fingerprint: &[u8]| {
+        send(
+            url,
+            None,
+            fingerprint,
+            Duration::from_secs(1),
+            0,
+            &HttpOptions::default(),
+        )
+    };
+
+    match pinned("http://example.com", &[0; 32]) {
+        Err(RadError::CertificatePinning { .. }) => {}
+        result => panic!("Expected a certificate pinning error, got {:?}", result),
+    }
+    for length in &[0, 20, 31, 33, 64] {
+        match pinned("https://example.com", &vec![0; *length]) {
+            Err(RadError::CertificatePinning { message }) => {
+                assert!(message.contains("bytes instead of 32"))
+            }
+            result => panic!("Expected a certificate pinning error, got {:?}", result),
+        }
+    }
+}
+
+#[test]
+fn test_build_request() {
+    let url = Url::parse("https://example.com:8443/prices?symbol=BTC").unwrap();
+
+    assert_eq!(
+        String::from_utf8(build_request(&url, None, "witnet-rad")).unwrap(),
+        "GET /prices?symbol=BTC HTTP/1.0\r\nHost: example.com:8443\r\n\
+         User-Agent: witnet-rad\r\nConnection: close\r\n\r\n"
+    );
+    assert_eq!(
+        String::from_utf8(build_request(
+            &Url::parse("https://example.com/prices").unwrap(),
+            Some((b"{}", "application/json")),
+            "witnet-rad"
+        ))
+        .unwrap(),
+        "POST /prices HTTP/1.0\r\nHost: example.com\r\nUser-Agent: witnet-rad\r\n\
+         Connection: close\r\nContent-Type: application/json\r\nContent-Length: 2\r\n\r\n{}"
+    );
+}
+
+#[test]
+fn test_parse_response() {
+    let response = b"HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\
+                     content-length: 2\r\n\r\n{}trailing";
+
+    assert_eq!(
+        parse_response(response, 0),
+        Ok(PinnedResponse {
+            content_type: Some("application/json".to_string()),
+            body: b"{}".to_vec(),
+        })
+    );
+    assert_eq!(
+        parse_response(response, 1),
+        Err(RadError::ResponseTooLarge { max_size: 1 })
+    );
+    assert_eq!(
+        parse_response(b"HTTP/1.0 200 OK\r\n\r\nuntil the end", 0).map(|response| response.body),
+        Ok(b"until the end".to_vec())
+    );
+}
+
+#[test]
+fn test_parse_response_does_not_follow_redirects() {
+    let response = b"HTTP/1.1 301 Moved Permanently\r\nLocation: https://evil.example\r\n\r\n";
+
+    assert_eq!(
+        parse_response(response, 0),
+        Err(RadError::HttpStatus { status_code: 301 })
+    );
+}
+
+#[test]
+fn test_parse_malformed_response() {
+    assert!(parse_response(b"HTTP/1.1 200 OK\r\n", 0).is_err());
+    assert!(parse_response(b"SSH-2.0\r\n\r\n", 0).is_err());
+    assert!(parse_response(b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nshort", 0).is_err());
+    assert!(parse_response(
+        b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n2\r\n{}\r\n0\r\n\r\n",
+        0
+    )
+    .is_err());
+}
+
+#[test]
+fn test_set_timeouts_until_deadline() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+
+    let timeout = Duration::from_secs(10);
+    assert!(set_timeouts(&stream, Instant::now() + timeout).is_ok());
+    let read_timeout = stream.read_timeout().unwrap().unwrap();
+    assert!(read_timeout > Duration::from_secs(0) && read_timeout <= timeout);
+
+    // Once the deadline is over, the retrieval times out instead of waiting for another read
+    let error = set_timeouts(&stream, Instant::now()).unwrap_err();
+    assert_eq!(
+        map_io_error(error, timeout),
+        RadError::HttpTimeout { timeout_ms: 10_000 }
+    );
+}
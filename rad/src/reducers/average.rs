@@ -1,24 +1,84 @@
 use crate::error::RadError;
-use crate::types::{array::RadonArray, float::RadonFloat, RadonType, RadonTypes};
+use crate::reducers::{float_result, numeric_values, RadonReducers};
+use crate::types::{array::RadonArray, RadonTypes};
 
-use std::ops::Div;
+use std::cmp::Ordering;
 
+/// Arithmetic mean of the items, summed in the order of the array
 pub fn mean(input: &RadonArray) -> Result<RadonTypes, RadError> {
-    let value = input.value();
-
-    // Sum all numeric values
-    let (sum, count) = value
-        .iter()
-        .fold((0f64, 0f64), |(sum, count), item| match item {
-            RadonTypes::Float(f64_value) => (sum + f64_value.value(), count + 1f64),
-            // TODO: implement RadonInteger branch here
-            // RadonTypes::Integer(i64_value) => (sum + acc + i64_value.value() as f64, count + 1f64),
-            // Skip any non-numeric RadonType
-            _ => (sum, count),
-        });
-
-    // Divide sum by the count of numeric values that were summed
-    let mean_value: f64 = sum.div(count);
-
-    Ok(RadonTypes::from(RadonFloat::from(mean_value)))
+    let values = numeric_values(input, RadonReducers::AverageMean)?;
+    let sum: f64 = values.iter().sum();
+
+    float_result(sum / values.len() as f64, RadonReducers::AverageMean)
+}
+
+/// Middle item once sorted, or the mean of the two middle items if there is an even number
+pub fn median(input: &RadonArray) -> Result<RadonTypes, RadError> {
+    let mut values = numeric_values(input, RadonReducers::AverageMedian)?;
+    // Values are finite, hence totally ordered
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+
+    let middle = values.len() / 2;
+    let median = if values.len() % 2 == 1 {
+        values[middle]
+    } else {
+        // Halving before adding cannot overflow
+        values[middle - 1] / 2.0 + values[middle] / 2.0
+    };
+
+    float_result(median, RadonReducers::AverageMedian)
+}
+
+#[test]
+fn test_mean() {
+    use crate::types::{float::RadonFloat, integer::RadonInteger};
+
+    let input = RadonArray::from(vec![
+        RadonInteger::from(1).into(),
+        RadonFloat::from(2.5).into(),
+        RadonInteger::from(6).into(),
+    ]);
+    assert_eq!(
+        mean(&input),
+        Ok(RadonFloat::from(3.166_666_666_666_666_5).into())
+    );
+
+    let input = RadonArray::from(vec![
+        RadonFloat::from(std::f64::MAX).into(),
+        RadonFloat::from(std::f64::MAX).into(),
+    ]);
+    assert_eq!(
+        mean(&input),
+        Err(RadError::NonFiniteFloat {
+            operator: "RadonReducers::AverageMean".to_string()
+        })
+    );
+}
+
+#[test]
+fn test_median() {
+    use crate::types::{float::RadonFloat, integer::RadonInteger};
+
+    let odd = RadonArray::from(vec![
+        RadonFloat::from(3.0).into(),
+        RadonFloat::from(-1.0).into(),
+        RadonFloat::from(100.0).into(),
+    ]);
+    assert_eq!(median(&odd), Ok(RadonFloat::from(3.0).into()));
+
+    let even = RadonArray::from(vec![
+        RadonInteger::from(4).into(),
+        RadonInteger::from(1).into(),
+        RadonInteger::from(3).into(),
+        RadonInteger::from(2).into(),
+    ]);
+    assert_eq!(median(&even), Ok(RadonFloat::from(2.5).into()));
+
+    let large = RadonArray::from(vec![
+        RadonFloat::from(std::f64::MAX).into(),
+        RadonFloat::from(std::f64::MAX).into(),
+    ]);
+    assert_eq!(median(&large), Ok(RadonFloat::from(std::f64::MAX).into()));
+
+    assert!(median(&RadonArray::from(vec![])).is_err());
 }
@@ -0,0 +1,40 @@
+use crate::error::RadError;
+use crate::reducers::{float_result, numeric_values, RadonReducers};
+use crate::types::{array::RadonArray, RadonTypes};
+
+/// Population standard deviation of the items, i.e. dividing by their count
+pub fn standard(input: &RadonArray) -> Result<RadonTypes, RadError> {
+    let values = numeric_values(input, RadonReducers::DeviationStandard)?;
+    let count = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / count;
+    let variance = values
+        .iter()
+        .map(|value| (value - mean) * (value - mean))
+        .sum::<f64>()
+        / count;
+
+    float_result(variance.sqrt(), RadonReducers::DeviationStandard)
+}
+
+#[test]
+fn test_standard() {
+    use crate::types::{float::RadonFloat, integer::RadonInteger};
+
+    let input = RadonArray::from(
+        [2, 4, 4, 4, 5, 5, 7, 9]
+            .iter()
+            .map(|value| RadonInteger::from(*value).into())
+            .collect::<Vec<_>>(),
+    );
+    assert_eq!(standard(&input), Ok(RadonFloat::from(2.0).into()));
+
+    let single = RadonArray::from(vec![RadonFloat::from(3914.5).into()]);
+    assert_eq!(standard(&single), Ok(RadonFloat::from(0.0).into()));
+
+    assert_eq!(
+        standard(&RadonArray::from(vec![])),
+        Err(RadError::EmptyArray {
+            operator: "RadonReducers::DeviationStandard".to_string()
+        })
+    );
+}
@@ -2,14 +2,16 @@
 #![allow(clippy::useless_attribute)]
 
 use crate::error::RadError;
-use crate::types::{array::RadonArray, RadonTypes};
+use crate::types::{array::RadonArray, float::RadonFloat, RadonType, RadonTypes};
 
 mod average;
+mod deviation;
+mod mode;
 
 use num_derive::FromPrimitive;
 use std::fmt;
 
-#[derive(Debug, FromPrimitive, PartialEq)]
+#[derive(Clone, Copy, Debug, FromPrimitive, PartialEq)]
 pub enum RadonReducers {
     Identity = 0x00,
     Mode = 0x10,
@@ -37,12 +39,95 @@ pub fn reduce(input: &RadonArray, reducer_code: RadonReducers) -> Result<RadonTy
         })
     };
 
-    if input.is_homogeneous() {
-        match reducer_code {
-            RadonReducers::AverageMean => average::mean(input),
-            _ => error(),
-        }
+    match reducer_code {
+        RadonReducers::AverageMean => average::mean(input),
+        RadonReducers::AverageMedian => average::median(input),
+        RadonReducers::DeviationStandard => deviation::standard(input),
+        RadonReducers::Mode => mode::mode(input),
+        _ => error(),
+    }
+}
+
+/// The values of the integers and floats of an array, as floats. Any other item, as well as NaN
+/// and infinite floats, is skipped: in the tally stage every item is the reveal of a different
+/// witness, and none of them should be able to make the whole reduction fail. Fails if the array
+/// is empty, or if no numbers remain.
+fn numeric_values(input: &RadonArray, reducer_code: RadonReducers) -> Result<Vec<f64>, RadError> {
+    let items = input.value();
+    if items.is_empty() {
+        Err(RadError::EmptyArray {
+            operator: reducer_code.to_string(),
+        })?
+    }
+    let values: Vec<f64> = items
+        .iter()
+        .filter_map(|item| match item {
+            RadonTypes::Integer(integer) => Some(integer.value() as f64),
+            RadonTypes::Float(float) if float.value().is_finite() => Some(float.value()),
+            _ => None,
+        })
+        .collect();
+
+    if values.is_empty() {
+        Err(RadError::NoNumericItems {
+            operator: reducer_code.to_string(),
+        })
+    } else {
+        Ok(values)
+    }
+}
+
+/// Wrap the result of a reducer, failing if it is NaN or infinite
+fn float_result(value: f64, reducer_code: RadonReducers) -> Result<RadonTypes, RadError> {
+    if value.is_finite() {
+        Ok(RadonFloat::from(value).into())
     } else {
-        error()
+        Err(RadError::NonFiniteFloat {
+            operator: reducer_code.to_string(),
+        })
     }
 }
+
+#[test]
+fn test_numeric_values() {
+    use crate::types::{integer::RadonInteger, string::RadonString};
+
+    let input = RadonArray::from(vec![
+        RadonInteger::from(1).into(),
+        RadonFloat::from(2.5).into(),
+    ]);
+    assert_eq!(
+        numeric_values(&input, RadonReducers::AverageMean),
+        Ok(vec![1.0, 2.5])
+    );
+
+    assert_eq!(
+        numeric_values(&RadonArray::from(vec![]), RadonReducers::AverageMean),
+        Err(RadError::EmptyArray {
+            operator: "RadonReducers::AverageMean".to_string()
+        })
+    );
+
+    let input = RadonArray::from(vec![
+        RadonFloat::from(1.0).into(),
+        RadonString::from("2").into(),
+        RadonFloat::from(std::f64::NAN).into(),
+        RadonFloat::from(std::f64::INFINITY).into(),
+        RadonInteger::from(3).into(),
+    ]);
+    assert_eq!(
+        numeric_values(&input, RadonReducers::AverageMedian),
+        Ok(vec![1.0, 3.0])
+    );
+
+    let input = RadonArray::from(vec![
+        RadonString::from("2").into(),
+        RadonFloat::from(std::f64::NAN).into(),
+    ]);
+    assert_eq!(
+        numeric_values(&input, RadonReducers::DeviationStandard),
+        Err(RadError::NoNumericItems {
+            operator: "RadonReducers::DeviationStandard".to_string()
+        })
+    );
+}
@@ -0,0 +1,95 @@
+use crate::error::RadError;
+use crate::reducers::RadonReducers;
+use crate::types::{array::RadonArray, RadonType, RadonTypes};
+
+/// Most frequent item. Items of any type are counted, so an item of an unexpected type is just
+/// one more value, which can only win if it is the most frequent one. NaN floats are skipped, and
+/// an array of only NaN floats fails. Fails if several items are the most frequent, as there is no
+/// clear mode.
+pub fn mode(input: &RadonArray) -> Result<RadonTypes, RadError> {
+    let items = input.value();
+    if items.is_empty() {
+        Err(RadError::EmptyArray {
+            operator: RadonReducers::Mode.to_string(),
+        })?
+    }
+    let items: Vec<RadonTypes> = items
+        .into_iter()
+        .filter(|item| match item {
+            RadonTypes::Float(float) => !float.value().is_nan(),
+            _ => true,
+        })
+        .collect();
+    if items.is_empty() {
+        Err(RadError::NonFiniteFloat {
+            operator: RadonReducers::Mode.to_string(),
+        })?
+    }
+
+    // Distinct items along with their count, in order of first appearance
+    let mut counts: Vec<(RadonTypes, usize)> = vec![];
+    for item in items {
+        match counts.iter_mut().find(|(value, _)| *value == item) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((item, 1)),
+        }
+    }
+
+    let max_count = counts.iter().map(|(_, count)| *count).max().unwrap_or(0);
+    let mut modes = counts.into_iter().filter(|(_, count)| *count == max_count);
+    match (modes.next(), modes.count()) {
+        (Some((value, _)), 0) => Ok(value),
+        (_, others) => Err(RadError::ModeTie {
+            values: others + 1,
+            count: max_count,
+        }),
+    }
+}
+
+#[test]
+fn test_mode() {
+    use crate::types::{float::RadonFloat, integer::RadonInteger, string::RadonString};
+
+    let input = RadonArray::from(vec![
+        RadonString::from("sunny").into(),
+        RadonString::from("rainy").into(),
+        RadonString::from("sunny").into(),
+    ]);
+    assert_eq!(mode(&input), Ok(RadonString::from("sunny").into()));
+
+    let input = RadonArray::from(vec![
+        RadonFloat::from(1.0).into(),
+        RadonFloat::from(2.0).into(),
+    ]);
+    assert_eq!(
+        mode(&input),
+        Err(RadError::ModeTie {
+            values: 2,
+            count: 1
+        })
+    );
+
+    // A single item of another type does not prevent finding the mode
+    let input = RadonArray::from(vec![
+        RadonInteger::from(1).into(),
+        RadonString::from("1").into(),
+        RadonInteger::from(1).into(),
+        RadonFloat::from(std::f64::NAN).into(),
+    ]);
+    assert_eq!(mode(&input), Ok(RadonInteger::from(1).into()));
+
+    assert_eq!(
+        mode(&RadonArray::from(vec![])),
+        Err(RadError::EmptyArray {
+            operator: "RadonReducers::Mode".to_string()
+        })
+    );
+    assert_eq!(
+        mode(&RadonArray::from(vec![
+            RadonFloat::from(std::f64::NAN).into()
+        ])),
+        Err(RadError::NonFiniteFloat {
+            operator: "RadonReducers::Mode".to_string()
+        })
+    );
+}
@@ -0,0 +1,54 @@
+//! Structured reports of the execution of the stages of a data request.
+
+use std::time::Duration;
+
+use crate::error::RadError;
+use crate::operators::RadonOpCodes;
+use crate::types::RadonTypes;
+
+/// Stage of a data request executed by the RAD engine
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Stage {
+    /// Retrieval of a single source, followed by its script
+    Retrieval,
+    /// Aggregation of the results of every source
+    Aggregation,
+    /// Tally of the results revealed by the witnesses
+    Tally,
+}
+
+/// Execution of a single call of a RADON script
+#[derive(Clone, Debug, PartialEq)]
+pub struct CallReport {
+    /// Operator of the call
+    pub operator: RadonOpCodes,
+    /// Type of the value the operator was applied to
+    pub input_type: String,
+    /// Type of the value returned by the operator, or `None` if the call failed
+    pub output_type: Option<String>,
+}
+
+/// Report of the execution of a stage of a data request
+#[derive(Debug, PartialEq)]
+pub struct RadonReport {
+    /// Executed stage
+    pub stage: Stage,
+    /// Calls of the script in execution order, up to the first one that failed
+    pub calls: Vec<CallReport>,
+    /// Result of the stage
+    pub result: Result<RadonTypes, RadError>,
+    /// Time spent executing the stage, including the retrieval of the source
+    pub running_time: Duration,
+}
+
+impl RadonReport {
+    /// Whether the stage was executed successfully
+    pub fn is_ok(&self) -> bool {
+        self.result.is_ok()
+    }
+
+    /// Call that made the stage fail, if any
+    pub fn failed_call(&self) -> Option<&CallReport> {
+        self.calls.last().filter(|call| call.output_type.is_none())
+    }
+}
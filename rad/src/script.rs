@@ -1,5 +1,6 @@
 use crate::error::RadError;
 use crate::operators::{operate, RadonOpCodes};
+use crate::report::CallReport;
 use crate::types::RadonTypes;
 
 use log::error;
@@ -11,6 +12,9 @@ pub type RadonCall = (RadonOpCodes, Option<Vec<Value>>);
 
 pub type RadonScript = Vec<RadonCall>;
 
+/// Maximum nesting of subscripts, counting the outermost one
+pub const MAX_SUBSCRIPT_DEPTH: usize = 4;
+
 /// Run any RADON script on given input data.
 pub fn execute_radon_script(
     input: RadonTypes,
@@ -19,6 +23,28 @@ pub fn execute_radon_script(
     script.iter().try_fold(input, operate)
 }
 
+/// Run any RADON script on given input data, reporting the types flowing through every call up
+/// to the first one that fails.
+pub fn trace_radon_script(
+    input: RadonTypes,
+    script: &[RadonCall],
+) -> (Result<RadonTypes, RadError>, Vec<CallReport>) {
+    let mut calls = Vec::with_capacity(script.len());
+    let result = script.iter().try_fold(input, |input, call| {
+        let input_type = input.radon_type_name();
+        let output = operate(input, call);
+        calls.push(CallReport {
+            operator: call.0,
+            input_type,
+            output_type: output.as_ref().ok().map(RadonTypes::radon_type_name),
+        });
+
+        output
+    });
+
+    (result, calls)
+}
+
 pub fn unpack_radon_script(packed: &[u8]) -> Result<RadonScript, RadError> {
     let reader = &mut Cursor::new(packed);
 
@@ -36,6 +62,77 @@ pub fn unpack_radon_script(packed: &[u8]) -> Result<RadonScript, RadError> {
     }
 }
 
+/// Decode a subscript, i.e. an argument of a call holding an array of calls, making sure that
+/// the subscripts nested in it do not exceed `MAX_SUBSCRIPT_DEPTH`.
+pub fn unpack_subscript(packed: &Value) -> Result<RadonScript, RadError> {
+    unpack_nested_subscript(packed, 1)
+}
+
+fn unpack_nested_subscript(packed: &Value, depth: usize) -> Result<RadonScript, RadError> {
+    if depth > MAX_SUBSCRIPT_DEPTH {
+        Err(errorify(RadError::SubscriptTooDeep {
+            max_depth: MAX_SUBSCRIPT_DEPTH,
+        }))?
+    }
+
+    let subscript = match packed {
+        Value::Array(array) => array
+            .iter()
+            .map(unpack_radon_call)
+            .collect::<Result<RadonScript, RadError>>()?,
+        other => Err(errorify(RadError::ScriptNotArray {
+            input_type: other.to_string(),
+        }))?,
+    };
+    for (op_code, arguments) in &subscript {
+        let nested = arguments
+            .as_ref()
+            .and_then(|arguments| arguments.first())
+            .filter(|argument| takes_subscript(*op_code) && argument.is_array());
+        if let Some(nested) = nested {
+            unpack_nested_subscript(nested, depth + 1)?;
+        }
+    }
+
+    Ok(subscript)
+}
+
+/// Whether the first argument of an operator can be a subscript
+fn takes_subscript(op_code: RadonOpCodes) -> bool {
+    match op_code {
+        RadonOpCodes::ArrayFilter
+        | RadonOpCodes::ArrayMap
+        | RadonOpCodes::ArraySort
+        | RadonOpCodes::Reduce => true,
+        _ => false,
+    }
+}
+
+/// Encode a RADON script with MessagePack, the inverse of `unpack_radon_script`. Calls without
+/// arguments are encoded as the bare operator code, and calls with arguments as an array whose
+/// first item is the operator code.
+pub fn pack_radon_script(script: &[RadonCall]) -> Vec<u8> {
+    let calls = script.iter().map(pack_radon_call).collect();
+    let mut packed = vec![];
+    rmpv::encode::write_value(&mut packed, &Value::Array(calls))
+        .expect("Writing MessagePack into a vector never fails");
+
+    packed
+}
+
+fn pack_radon_call((op_code, arguments): &RadonCall) -> Value {
+    let code = Value::from(*op_code as i64);
+
+    match arguments {
+        Some(arguments) => Value::Array(
+            std::iter::once(code)
+                .chain(arguments.iter().cloned())
+                .collect(),
+        ),
+        None => code,
+    }
+}
+
 fn unpack_radon_call(packed_call: &Value) -> Result<RadonCall, RadError> {
     match packed_call {
         Value::Array(array) => unpack_compound_call(array),
@@ -98,6 +195,120 @@ fn test_execute_radon_script() {
     assert_eq!(output, expected)
 }
 
+#[test]
+fn test_execute_radon_script_get_path() {
+    use crate::types::{float::RadonFloat, string::RadonString};
+
+    let input = RadonString::from(r#"{"data":{"prices":[{"symbol":"BTCUSD","price":"3914.5"},{"symbol":"ETHUSD","price":135.2}]}}"#).into();
+    let script = vec![
+        (RadonOpCodes::ParseJson, None),
+        (
+            RadonOpCodes::GetPath,
+            Some(vec![
+                Value::from("data"),
+                Value::from("prices"),
+                Value::from(1),
+                Value::from("price"),
+            ]),
+        ),
+        (RadonOpCodes::ToFloat, None),
+    ];
+    let output = execute_radon_script(input, &script).unwrap();
+
+    assert_eq!(output, RadonTypes::Float(RadonFloat::from(135.2f64)))
+}
+
+#[test]
+fn test_execute_radon_script_numeric() {
+    use crate::types::{integer::RadonInteger, string::RadonString};
+
+    let input = RadonString::from(r#"{"price":3914.5,"volume":-21}"#).into();
+    let script = vec![
+        (RadonOpCodes::ParseJson, None),
+        (RadonOpCodes::GetPath, Some(vec![Value::from("price")])),
+        (RadonOpCodes::ToFloat, None),
+        (RadonOpCodes::FloatMultiply, Some(vec![Value::from(100)])),
+        (RadonOpCodes::FloatNegate, None),
+        (RadonOpCodes::FloatRound, None),
+        (RadonOpCodes::IntegerAbsolute, None),
+    ];
+    let output = execute_radon_script(input, &script).unwrap();
+
+    assert_eq!(output, RadonTypes::Integer(RadonInteger::from(391_450)));
+
+    let input = RadonString::from(r#"{"price":3914.5,"volume":-21}"#).into();
+    let script = vec![
+        (RadonOpCodes::ParseJson, None),
+        (RadonOpCodes::GetPath, Some(vec![Value::from("volume")])),
+        (RadonOpCodes::ToInteger, None),
+        (RadonOpCodes::IntegerMultiply, Some(vec![Value::from(2)])),
+        (RadonOpCodes::IntegerNegate, None),
+    ];
+    let output = execute_radon_script(input, &script).unwrap();
+
+    assert_eq!(output, RadonTypes::Integer(RadonInteger::from(42)));
+}
+
+#[test]
+fn test_execute_radon_script_array_item_to_float() {
+    use crate::types::{float::RadonFloat, integer::RadonInteger, string::RadonString};
+
+    // The items of the array are decoded into integers and floats rather than mixed values
+    let script = vec![
+        (RadonOpCodes::ParseJson, None),
+        (RadonOpCodes::ToArray, None),
+        (RadonOpCodes::Get, Some(vec![Value::from(0)])),
+        (RadonOpCodes::ToFloat, None),
+    ];
+    let output = execute_radon_script(RadonString::from("[1,2]").into(), &script).unwrap();
+    assert_eq!(output, RadonTypes::Float(RadonFloat::from(1f64)));
+
+    let output = execute_radon_script(RadonString::from("[1.5,2]").into(), &script).unwrap();
+    assert_eq!(output, RadonTypes::Float(RadonFloat::from(1.5f64)));
+
+    let script = vec![
+        (RadonOpCodes::ParseJson, None),
+        (RadonOpCodes::ToArray, None),
+        (RadonOpCodes::Get, Some(vec![Value::from(1)])),
+        (RadonOpCodes::ToInteger, None),
+    ];
+    let output = execute_radon_script(RadonString::from("[1,2]").into(), &script).unwrap();
+    assert_eq!(output, RadonTypes::Integer(RadonInteger::from(2)));
+}
+
+#[test]
+fn test_trace_radon_script() {
+    use crate::types::{string::RadonString, RadonType};
+
+    let input: RadonTypes = RadonString::from(r#"{"main":{"temp":-4}}"#).into();
+    let script = vec![
+        (RadonOpCodes::ParseJson, None),
+        (RadonOpCodes::ToMap, None),
+        (RadonOpCodes::Get, Some(vec![Value::from("wind")])),
+        (RadonOpCodes::ToFloat, None),
+    ];
+    let (result, calls) = trace_radon_script(input, &script);
+
+    assert_eq!(
+        result,
+        Err(RadError::MapKeyNotFound {
+            key: "wind".to_string()
+        })
+    );
+    assert_eq!(
+        calls
+            .iter()
+            .map(|call| (call.operator, call.output_type.is_some()))
+            .collect::<Vec<_>>(),
+        vec![
+            (RadonOpCodes::ParseJson, true),
+            (RadonOpCodes::ToMap, true),
+            (RadonOpCodes::Get, false),
+        ]
+    );
+    assert_eq!(calls[0].input_type, RadonString::radon_type_name());
+}
+
 #[test]
 fn test_unpack_radon_script() {
     let packed = [
@@ -117,3 +328,69 @@ fn test_unpack_radon_script() {
 
     assert_eq!(output, expected)
 }
+
+#[test]
+fn test_unpack_subscript() {
+    let subscript = Value::Array(vec![
+        Value::from(RadonOpCodes::ToMap as i64),
+        Value::Array(vec![
+            Value::from(RadonOpCodes::Get as i64),
+            Value::from("price"),
+        ]),
+    ]);
+
+    assert_eq!(
+        unpack_subscript(&subscript).unwrap(),
+        vec![
+            (RadonOpCodes::ToMap, None),
+            (RadonOpCodes::Get, Some(vec![Value::from("price")])),
+        ]
+    );
+    assert_eq!(unpack_subscript(&Value::Array(vec![])).unwrap(), vec![]);
+    assert!(unpack_subscript(&Value::from(0x84)).is_err());
+}
+
+#[test]
+fn test_unpack_subscript_depth() {
+    // An `ArrayMap` call nesting the given subscript
+    let nest = |subscript: Value| {
+        Value::Array(vec![Value::Array(vec![
+            Value::from(RadonOpCodes::ArrayMap as i64),
+            subscript,
+        ])])
+    };
+    let mut subscript = Value::Array(vec![]);
+    for _ in 1..MAX_SUBSCRIPT_DEPTH {
+        subscript = nest(subscript);
+    }
+
+    assert!(unpack_subscript(&subscript).is_ok());
+    assert_eq!(
+        unpack_subscript(&nest(subscript)),
+        Err(RadError::SubscriptTooDeep {
+            max_depth: MAX_SUBSCRIPT_DEPTH
+        })
+    );
+}
+
+#[test]
+fn test_pack_radon_script() {
+    let script = vec![
+        (RadonOpCodes::ParseJson, None),
+        (RadonOpCodes::ToMap, None),
+        (RadonOpCodes::Get, Some(vec![Value::from("main")])),
+        (RadonOpCodes::ToMap, None),
+        (RadonOpCodes::Get, Some(vec![Value::from("temp")])),
+        (RadonOpCodes::ToFloat, None),
+    ];
+    let expected = [
+        150, 83, 204, 132, 146, 1, 164, 109, 97, 105, 110, 204, 132, 146, 1, 164, 116, 101, 109,
+        112, 204, 130,
+    ];
+
+    let packed = pack_radon_script(&script);
+
+    assert_eq!(packed, expected);
+    assert_eq!(unpack_radon_script(&packed).unwrap(), script);
+    assert_eq!(pack_radon_script(&[]), [144]);
+}
@@ -109,6 +109,15 @@ impl Operable for RadonArray {
             (RadonOpCodes::Identity, None) => identity(self.into()),
             (RadonOpCodes::Reduce, Some(args)) => array_operators::reduce(&self, args.as_slice()),
             (RadonOpCodes::Get, Some(args)) => array_operators::get(&self, args.as_slice()),
+            (RadonOpCodes::ArrayFilter, Some(args)) => {
+                array_operators::filter(&self, args.as_slice()).map(Into::into)
+            }
+            (RadonOpCodes::ArrayMap, Some(args)) => {
+                array_operators::map(&self, args.as_slice()).map(Into::into)
+            }
+            (RadonOpCodes::ArraySort, Some(args)) => {
+                array_operators::sort(&self, args.as_slice()).map(Into::into)
+            }
             (op_code, args) => Err(RadError::UnsupportedOperator {
                 input_type: RADON_ARRAY_TYPE_NAME.to_string(),
                 operator: op_code.to_string(),
@@ -5,7 +5,7 @@ use rmpv::Value;
 use witnet_data_structures::serializers::decoders::{TryFrom, TryInto};
 
 use crate::error::RadError;
-use crate::operators::{identity, Operable, RadonOpCodes};
+use crate::operators::{float as float_operators, identity, Operable, RadonOpCodes};
 use crate::script::RadonCall;
 use crate::types::{RadonType, RadonTypes};
 
@@ -62,6 +62,18 @@ impl<'a> Operable for RadonFloat {
         match call {
             // Identity
             (RadonOpCodes::Identity, None) => identity(RadonTypes::Float(self)),
+            // Items of arrays are decoded into floats, so the conversion of mixed values also
+            // applies to them
+            (RadonOpCodes::ToFloat, None) => identity(RadonTypes::Float(self)),
+            // Arithmetic
+            (RadonOpCodes::FloatAbsolute, None) => float_operators::absolute(&self).map(Into::into),
+            (RadonOpCodes::FloatMultiply, Some(args)) => {
+                float_operators::multiply(&self, args.as_slice()).map(Into::into)
+            }
+            (RadonOpCodes::FloatNegate, None) => float_operators::negate(&self).map(Into::into),
+            // Conversion into integers
+            (RadonOpCodes::FloatRound, None) => float_operators::round(&self).map(Into::into),
+            (RadonOpCodes::FloatTruncate, None) => float_operators::truncate(&self).map(Into::into),
             // Unsupported / unimplemented
             (op_code, args) => Err(RadError::UnsupportedOperator {
                 input_type: RADON_FLOAT_TYPE_NAME.to_string(),
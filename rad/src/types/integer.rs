@@ -0,0 +1,109 @@
+use std::fmt;
+
+use rmpv::Value;
+
+use witnet_data_structures::serializers::decoders::{TryFrom, TryInto};
+
+use crate::error::RadError;
+use crate::operators::{identity, integer as integer_operators, Operable, RadonOpCodes};
+use crate::script::RadonCall;
+use crate::types::{RadonType, RadonTypes};
+
+pub const RADON_INTEGER_TYPE_NAME: &str = "RadonInteger";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RadonInteger {
+    value: i64,
+}
+
+impl RadonType<i64> for RadonInteger {
+    fn value(&self) -> i64 {
+        self.value
+    }
+
+    fn radon_type_name() -> String {
+        RADON_INTEGER_TYPE_NAME.to_string()
+    }
+}
+
+impl TryFrom<Value> for RadonInteger {
+    type Error = RadError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        value
+            .as_i64()
+            .map(Self::from)
+            .ok_or_else(|| RadError::Decode {
+                from: "rmpv::Value".to_string(),
+                to: RADON_INTEGER_TYPE_NAME.to_string(),
+            })
+    }
+}
+
+impl TryInto<Value> for RadonInteger {
+    type Error = RadError;
+
+    fn try_into(self) -> Result<Value, Self::Error> {
+        Ok(Value::from(self.value()))
+    }
+}
+
+impl From<i64> for RadonInteger {
+    fn from(value: i64) -> Self {
+        RadonInteger { value }
+    }
+}
+
+impl Operable for RadonInteger {
+    fn operate(self, call: &RadonCall) -> Result<RadonTypes, RadError> {
+        match call {
+            (RadonOpCodes::Identity, None) => identity(RadonTypes::Integer(self)),
+            (RadonOpCodes::IntegerAbsolute, None) => {
+                integer_operators::absolute(&self).map(Into::into)
+            }
+            (RadonOpCodes::IntegerMultiply, Some(args)) => {
+                integer_operators::multiply(&self, args.as_slice()).map(Into::into)
+            }
+            (RadonOpCodes::IntegerNegate, None) => integer_operators::negate(&self).map(Into::into),
+            // Items of arrays are decoded into integers, so the conversions of mixed values also
+            // apply to them
+            (RadonOpCodes::ToFloat, None) => Ok(integer_operators::to_float(&self).into()),
+            (RadonOpCodes::ToInteger, None) => identity(RadonTypes::Integer(self)),
+            (op_code, args) => Err(RadError::UnsupportedOperator {
+                input_type: RADON_INTEGER_TYPE_NAME.to_string(),
+                operator: op_code.to_string(),
+                args: args.to_owned(),
+            }),
+        }
+    }
+}
+
+impl fmt::Display for RadonInteger {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}({})", RADON_INTEGER_TYPE_NAME, self.value)
+    }
+}
+
+#[test]
+fn test_operate_unimplemented() {
+    let input = RadonInteger::from(42);
+
+    let call = (RadonOpCodes::ParseJson, None);
+    let result = input.operate(&call);
+
+    assert!(if let Err(RadError::UnsupportedOperator { .. }) = result {
+        true
+    } else {
+        false
+    });
+}
+
+#[test]
+fn test_from_vector() {
+    let input: &[u8] = &[0xd0, 0x9c]; // -100
+
+    let expected = RadonTypes::from(RadonInteger::from(-100));
+    let result = RadonTypes::try_from(input).unwrap();
+
+    assert_eq!(result, expected);
+}
@@ -55,6 +55,10 @@ impl Operable for RadonMixed {
             (RadonOpCodes::ToFloat, None) => mixed_operators::to_float(self)
                 .map(RadonTypes::from)
                 .map_err(Into::into),
+            // To Integer
+            (RadonOpCodes::ToInteger, None) => {
+                mixed_operators::to_integer(self).map(RadonTypes::from)
+            }
             // To Array
             (RadonOpCodes::ToArray, None) => mixed_operators::to_array(self)
                 .map(RadonTypes::from)
@@ -63,6 +67,10 @@ impl Operable for RadonMixed {
             (RadonOpCodes::ToMap, None) => mixed_operators::to_map(self)
                 .map(RadonTypes::from)
                 .map_err(Into::into),
+            // Get Path
+            (RadonOpCodes::GetPath, Some(args)) => {
+                mixed_operators::get_path(self, args.as_slice()).map(RadonTypes::from)
+            }
             // Unsupported / unimplemented
             (op_code, args) => Err(RadError::UnsupportedOperator {
                 input_type: RADON_MIXED_TYPE_NAME.to_string(),
@@ -1,6 +1,7 @@
 use crate::error::RadError;
 use crate::types::array::RadonArray;
 use crate::types::float::RadonFloat;
+use crate::types::integer::RadonInteger;
 use crate::types::map::RadonMap;
 use crate::types::mixed::RadonMixed;
 use crate::types::string::RadonString;
@@ -15,6 +16,7 @@ use witnet_data_structures::{
 
 pub mod array;
 pub mod float;
+pub mod integer;
 pub mod map;
 pub mod mixed;
 pub mod string;
@@ -32,6 +34,7 @@ where
 pub enum RadonTypes {
     Array(RadonArray),
     Float(RadonFloat),
+    Integer(RadonInteger),
     Map(RadonMap),
     Mixed(RadonMixed),
     String(RadonString),
@@ -45,10 +48,11 @@ impl RadonTypes {
             .map_err(|_| RadError::Hash)
     }
 
-    pub fn radon_type_name(self) -> String {
+    pub fn radon_type_name(&self) -> String {
         match self {
             RadonTypes::Array(_) => RadonArray::radon_type_name(),
             RadonTypes::Float(_) => RadonFloat::radon_type_name(),
+            RadonTypes::Integer(_) => RadonInteger::radon_type_name(),
             RadonTypes::Map(_) => RadonMap::radon_type_name(),
             RadonTypes::Mixed(_) => RadonMixed::radon_type_name(),
             RadonTypes::String(_) => RadonString::radon_type_name(),
@@ -61,6 +65,7 @@ impl fmt::Display for RadonTypes {
         match self {
             RadonTypes::Array(inner) => write!(f, "RadonTypes::{}", inner),
             RadonTypes::Float(inner) => write!(f, "RadonTypes::{}", inner),
+            RadonTypes::Integer(inner) => write!(f, "RadonTypes::{}", inner),
             RadonTypes::Map(inner) => write!(f, "RadonTypes::{}", inner),
             RadonTypes::Mixed(inner) => write!(f, "RadonTypes::{}", inner),
             RadonTypes::String(inner) => write!(f, "RadonTypes::{}", inner),
@@ -80,6 +85,12 @@ impl From<RadonFloat> for RadonTypes {
     }
 }
 
+impl From<RadonInteger> for RadonTypes {
+    fn from(integer: RadonInteger) -> Self {
+        RadonTypes::Integer(integer)
+    }
+}
+
 impl From<RadonMap> for RadonTypes {
     fn from(map: RadonMap) -> Self {
         RadonTypes::Map(map)
@@ -105,6 +116,10 @@ impl TryFrom<Value> for RadonTypes {
         match value {
             Value::Array(_) => RadonArray::try_from(value).map(Into::into),
             Value::F64(_) => RadonFloat::try_from(value).map(Into::into),
+            // Integers beyond the range of i64 are left as mixed values
+            Value::Integer(ref integer) if integer.as_i64().is_some() => {
+                RadonInteger::try_from(value).map(Into::into)
+            }
             Value::Map(_) => RadonMap::try_from(value).map(Into::into),
             Value::String(_) => RadonString::try_from(value).map(Into::into),
             _ => RadonMixed::try_from(value).map(Into::into),
@@ -119,6 +134,7 @@ impl TryInto<Value> for RadonTypes {
         match self {
             RadonTypes::Array(radon_array) => radon_array.try_into(),
             RadonTypes::Float(radon_float) => radon_float.try_into(),
+            RadonTypes::Integer(radon_integer) => radon_integer.try_into(),
             RadonTypes::Map(radon_map) => radon_map.try_into(),
             RadonTypes::Mixed(radon_mixed) => radon_mixed.try_into(),
             RadonTypes::String(radon_string) => radon_string.try_into(),
@@ -65,9 +65,26 @@ impl Operable for RadonString {
             (RadonOpCodes::ParseJson, None) => {
                 string_operators::parse_json(&self).map(RadonTypes::Mixed)
             }
-            (RadonOpCodes::ToFloat, None) => string_operators::to_float(&self)
-                .map(RadonTypes::from)
-                .map_err(Into::into),
+            (RadonOpCodes::ToFloat, None) | (RadonOpCodes::StringParseFloat, None) => {
+                string_operators::parse_float(&self).map(RadonTypes::from)
+            }
+            (RadonOpCodes::StringParseInt, args) => {
+                string_operators::parse_int(&self, args.as_ref().map(Vec::as_slice))
+                    .map(RadonTypes::from)
+            }
+            (RadonOpCodes::StringLength, None) => Ok(string_operators::length(&self).into()),
+            (RadonOpCodes::StringMatch, Some(args)) => {
+                string_operators::string_match(&self, args.as_slice())
+            }
+            (RadonOpCodes::StringSlice, Some(args)) => {
+                string_operators::slice(&self, args.as_slice()).map(RadonTypes::from)
+            }
+            (RadonOpCodes::StringToLowerCase, None) => {
+                Ok(string_operators::to_lowercase(&self).into())
+            }
+            (RadonOpCodes::StringToUpperCase, None) => {
+                Ok(string_operators::to_uppercase(&self).into())
+            }
             (op_code, args) => Err(RadError::UnsupportedOperator {
                 input_type: RADON_STRING_TYPE_NAME.to_string(),
                 operator: op_code.to_string(),
@@ -147,3 +164,20 @@ fn test_serialize_radon_string() {
 
     assert_eq!(output, expected);
 }
+
+#[test]
+fn test_operate_extract_number() {
+    use crate::types::integer::RadonInteger;
+
+    let input = RadonString::from("Volume: 1234 BTC");
+
+    let slice = (
+        RadonOpCodes::StringSlice,
+        Some(vec![Value::from(8), Value::from(12)]),
+    );
+    let output = input.operate(&slice).unwrap();
+    assert_eq!(output, RadonTypes::from(RadonString::from("1234")));
+
+    let output = crate::operators::operate(output, &(RadonOpCodes::StringParseInt, None)).unwrap();
+    assert_eq!(output, RadonTypes::from(RadonInteger::from(1234)));
+}
@@ -13,13 +13,18 @@ use directories;
 use failure;
 use structopt::{clap::AppSettings, StructOpt};
 
+#[cfg(feature = "jsonrpc")]
 use super::json_rpc_client;
+use super::rad_repl;
 use crate::node::actors;
 
 /// Witnet network
 #[derive(Debug, StructOpt)]
 #[structopt(raw(global_settings = "&[AppSettings::AllowNegativeNumbers]"))]
 pub(crate) struct Cli {
+    /// Print the output and the errors as JSON, for scripts
+    #[structopt(long = "json", raw(global = "true"))]
+    pub(crate) json: bool,
     /// `witnet cmd ...`
     #[structopt(subcommand)]
     pub(crate) cmd: Command,
@@ -50,6 +55,7 @@ pub(crate) enum Command {
         // #[structopt(name = "background", short = "b")]
         // background: bool,
     },
+    #[cfg(feature = "jsonrpc")]
     #[structopt(name = "cli", about = "Run JSON-RPC requests")]
     Cli {
         // Config file path
@@ -65,8 +71,23 @@ pub(crate) enum Command {
         #[structopt(subcommand)]
         cmd: CliCommand,
     },
+    #[structopt(name = "rad", about = "Develop and test RAD scripts")]
+    Rad {
+        #[structopt(subcommand)]
+        cmd: RadCommand,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+pub(crate) enum RadCommand {
+    #[structopt(
+        name = "repl",
+        about = "Build a RADON script interactively, one operator at a time"
+    )]
+    Repl,
 }
 
+#[cfg(feature = "jsonrpc")]
 #[derive(Debug, StructOpt)]
 pub(crate) enum CliCommand {
     #[structopt(
@@ -114,6 +135,21 @@ pub(crate) enum CliCommand {
         #[structopt(name = "hash", help = "SHA256 block hash in string format")]
         hash: String,
     },
+    #[structopt(
+        name = "getVersion",
+        about = "Get the version and build information of the node"
+    )]
+    GetVersion {
+        // Config file path
+        #[structopt(
+            name = "config",
+            long = "config",
+            short = "c",
+            help = "Path to the configuration file"
+        )]
+        #[structopt(parse(from_os_str))]
+        config: Option<PathBuf>,
+    },
     #[structopt(name = "getOutput", about = "Get an output of a transaction")]
     GetOutput {
         // Config file path
@@ -133,21 +169,26 @@ pub(crate) enum CliCommand {
     },
 }
 
-pub(crate) fn exec(command: Command) -> Result<(), failure::Error> {
+#[cfg_attr(not(feature = "jsonrpc"), allow(unused_variables))]
+pub(crate) fn exec(command: Command, json: bool) -> Result<(), failure::Error> {
     match command {
         Command::Node { config } => {
             let fallback_config = find_config_file();
-            actors::node::run(config, fallback_config, || {
-                // FIXME(#72): decide what to do when interrupt signals are received
+            actors::node::run(config, fallback_config, |shutdown| {
+                // The node is closed gracefully on the first signal, and immediately on the next
                 ctrlc::set_handler(move || {
-                    actors::node::close();
+                    shutdown.close();
                 })
                 .expect("Error setting handler for both SIGINT (Ctrl+C) and SIGTERM (kill)");
             })?;
         }
+        #[cfg(feature = "jsonrpc")]
         Command::Cli { config, cmd } => {
-            json_rpc_client::run(config, cmd)?;
+            json_rpc_client::run(config, cmd, json)?;
         }
+        Command::Rad { cmd } => match cmd {
+            RadCommand::Repl => rad_repl::run()?,
+        },
     }
     Ok(())
 }
@@ -0,0 +1,223 @@
+//! Errors shown to the user
+//!
+//! Errors are printed with their causes and, when the user can do something about them, a hint on
+//! how to fix the problem. With `--json` the same information is printed as a single JSON object,
+//! so that scripts do not need to parse the human readable messages.
+use std::io;
+
+use failure::Fail;
+use serde::Serialize;
+
+use witnet_config::loaders::toml;
+use witnet_data_structures::error::OutputPointerParseError;
+
+#[cfg(feature = "jsonrpc")]
+use super::json_rpc_client::{NodeUnreachable, ProtocolError, ServerDisabled, ServerError};
+
+/// JSON-RPC error code for unknown methods
+#[cfg(feature = "jsonrpc")]
+const METHOD_NOT_FOUND: i32 = -32601;
+/// JSON-RPC error code for invalid method parameters
+#[cfg(feature = "jsonrpc")]
+const INVALID_PARAMS: i32 = -32602;
+
+/// Error ready to be shown to the user
+#[derive(Debug, PartialEq, Serialize)]
+pub(crate) struct Report {
+    /// Stable identifier of the kind of error, meant for scripts
+    pub kind: &'static str,
+    /// Description of the error
+    pub message: String,
+    /// Descriptions of the errors that caused this one, outermost first
+    pub causes: Vec<String>,
+    /// What the user can do to fix the error, if anything
+    pub hint: Option<String>,
+}
+
+impl Report {
+    /// Build the report of an error, classifying it by the first error of its chain that is known
+    pub fn new(error: &failure::Error) -> Self {
+        let (kind, hint) = error
+            .iter_chain()
+            .find_map(classify)
+            .unwrap_or(("other", None));
+
+        Report {
+            kind,
+            message: error.to_string(),
+            causes: error.iter_causes().map(ToString::to_string).collect(),
+            hint,
+        }
+    }
+
+    /// Print the report to stderr, as JSON if `json` is set
+    pub fn print(&self, json: bool) {
+        if json {
+            let report = serde_json::json!({ "error": self });
+            eprintln!("{}", report);
+        } else {
+            eprintln!("Error: {}", self.message);
+            for cause in &self.causes {
+                eprintln!("Cause: {}", cause);
+            }
+            if let Some(hint) = &self.hint {
+                eprintln!("Hint: {}", hint);
+            }
+        }
+    }
+}
+
+/// Kind of a known error and the hint to fix it
+fn classify(error: &dyn Fail) -> Option<(&'static str, Option<String>)> {
+    if let Some(error) = error.downcast_ref::<toml::Error>() {
+        return Some(match error {
+            toml::Error::IOError(e) if e.kind() == io::ErrorKind::NotFound => (
+                "config_not_found",
+                Some(
+                    "Create a witnet.toml file in the current directory, or pass the path of the \
+                     configuration file with -c/--config"
+                        .to_string(),
+                ),
+            ),
+            toml::Error::IOError(_) => (
+                "config_unreadable",
+                Some("Check the permissions of the configuration file".to_string()),
+            ),
+            toml::Error::ParseError(_) => (
+                "config_invalid",
+                Some(
+                    "Fix the syntax of the configuration file, the example witnet.toml in the \
+                     repository lists all the settings"
+                        .to_string(),
+                ),
+            ),
+        });
+    }
+    if error.downcast_ref::<OutputPointerParseError>().is_some() {
+        return Some((
+            "invalid_argument",
+            Some(
+                "Output pointers are written as <transaction id>:<output index>, where the \
+                 transaction id is 64 hexadecimal digits"
+                    .to_string(),
+            ),
+        ));
+    }
+
+    #[cfg(feature = "jsonrpc")]
+    {
+        if let Some(error) = error.downcast_ref::<NodeUnreachable>() {
+            return Some((
+                "node_not_running",
+                Some(format!(
+                    "Is the node running? Start it with `witnet node`, or check that \
+                     `server_address` in the [jsonrpc] section of witnet.toml matches the \
+                     address of the node ({})",
+                    error.address
+                )),
+            ));
+        }
+        if error.downcast_ref::<ServerDisabled>().is_some() {
+            return Some((
+                "jsonrpc_disabled",
+                Some(
+                    "Set `enabled = true` in the [jsonrpc] section of witnet.toml and restart the \
+                     node"
+                        .to_string(),
+                ),
+            ));
+        }
+        if let Some(error) = error.downcast_ref::<ServerError>() {
+            let hint = match error.code {
+                METHOD_NOT_FOUND => Some(
+                    "The node does not support this command, it may be running an older version. \
+                     Check it with `witnet cli getVersion`"
+                        .to_string(),
+                ),
+                INVALID_PARAMS => {
+                    Some("Check the arguments of the command with --help".to_string())
+                }
+                _ => None,
+            };
+            return Some(("server_error", hint));
+        }
+        if error.downcast_ref::<ProtocolError>().is_some() {
+            return Some((
+                "protocol_error",
+                Some(
+                    "The node speaks an incompatible version of the JSON-RPC protocol, use a CLI \
+                     of the same version as the node"
+                        .to_string(),
+                ),
+            ));
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_unknown_error() {
+        let error = failure::err_msg("something went wrong");
+        let report = Report::new(&error);
+
+        assert_eq!(
+            report,
+            Report {
+                kind: "other",
+                message: "something went wrong".to_string(),
+                causes: vec![],
+                hint: None,
+            }
+        );
+    }
+
+    #[test]
+    fn report_config_not_found() {
+        let error: failure::Error =
+            toml::from_file(std::path::Path::new("/nonexistent/witnet.toml"))
+                .unwrap_err()
+                .into();
+        let report = Report::new(&error);
+
+        assert_eq!(report.kind, "config_not_found");
+        assert!(report.message.starts_with("Error reading config file"));
+        assert!(report.hint.is_some());
+    }
+
+    #[cfg(feature = "jsonrpc")]
+    #[test]
+    fn report_node_not_running() {
+        let error: failure::Error = NodeUnreachable {
+            address: "127.0.0.1:21338".parse().unwrap(),
+            error: io::ErrorKind::ConnectionRefused.into(),
+        }
+        .into();
+        let report = Report::new(&error);
+
+        assert_eq!(report.kind, "node_not_running");
+        assert!(report.hint.unwrap().contains("witnet node"));
+        assert_eq!(
+            serde_json::to_value(&Report::new(&error)).unwrap()["kind"],
+            "node_not_running"
+        );
+    }
+
+    #[cfg(feature = "jsonrpc")]
+    #[test]
+    fn report_server_error() {
+        let error: failure::Error = ServerError {
+            code: METHOD_NOT_FOUND,
+            message: "Method not found".to_string(),
+        }
+        .into();
+        let report = Report::new(&error);
+
+        assert_eq!(report.kind, "server_error");
+        assert!(report.hint.unwrap().contains("getVersion"));
+    }
+}
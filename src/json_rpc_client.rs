@@ -6,7 +6,7 @@ use std::str::FromStr;
 use std::{
     fmt,
     io::{self, BufRead, BufReader, Read, Write},
-    net::TcpStream,
+    net::{SocketAddr, TcpStream},
     path::PathBuf,
 };
 use witnet_config::config::Config;
@@ -14,7 +14,11 @@ use witnet_config::loaders::toml;
 use witnet_data_structures::chain::OutputPointer;
 use witnet_node::actors::json_rpc::json_rpc_methods::GetBlockChainParams;
 
-pub(crate) fn run(last_config: Option<PathBuf>, cmd: CliCommand) -> Result<(), failure::Error> {
+pub(crate) fn run(
+    last_config: Option<PathBuf>,
+    cmd: CliCommand,
+    json: bool,
+) -> Result<(), failure::Error> {
     match cmd {
         CliCommand::Raw { config } => {
             // The -c/--config argument can come both after and before getBlockChain:
@@ -59,8 +63,12 @@ pub(crate) fn run(last_config: Option<PathBuf>, cmd: CliCommand) -> Result<(), f
             info!("{}", response);
             let block_chain: ResponseBlockChain<'_> = parse_response(&response)?;
 
-            for (epoch, hash) in block_chain {
-                println!("Block for epoch #{} had digest {}", epoch, hash);
+            if json {
+                println!("{}", serde_json::to_string(&block_chain)?);
+            } else {
+                for (epoch, hash) in block_chain {
+                    println!("Block for epoch #{} had digest {}", epoch, hash);
+                }
             }
 
             Ok(())
@@ -78,6 +86,16 @@ pub(crate) fn run(last_config: Option<PathBuf>, cmd: CliCommand) -> Result<(), f
 
             Ok(())
         }
+        CliCommand::GetVersion { config } => {
+            let config = config.or(last_config);
+            let mut stream = start_client(config)?;
+            let request = r#"{"jsonrpc": "2.0","method": "getVersion", "id": "1"}"#;
+            let response = send_request(&mut stream, request)?;
+
+            println!("{}", response);
+
+            Ok(())
+        }
         CliCommand::GetOutput {
             config,
             output_index,
@@ -133,17 +151,25 @@ enum Id<'a> {
 
 /// A failed request returns an error with code and message
 #[derive(Debug, Deserialize, Fail)]
-struct ServerError {
-    code: i32,
+pub(crate) struct ServerError {
+    pub(crate) code: i32,
     // This cannot be a &str because the error may outlive the current function
-    message: String,
+    pub(crate) message: String,
 }
 
 #[derive(Debug, Fail)]
-struct ServerDisabled;
+pub(crate) struct ServerDisabled;
 
 #[derive(Debug, Fail)]
-struct ProtocolError(String);
+pub(crate) struct ProtocolError(String);
+
+/// The connection to the JSON-RPC server failed
+#[derive(Debug, Fail)]
+pub(crate) struct NodeUnreachable {
+    pub(crate) address: SocketAddr,
+    #[cause]
+    pub(crate) error: io::Error,
+}
 
 // Required for Fail derive
 impl fmt::Display for ServerDisabled {
@@ -156,8 +182,11 @@ impl fmt::Display for ServerDisabled {
 // Required for Fail derive
 impl fmt::Display for ServerError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.write_str(&format!("{:?}", self))?;
-        Ok(())
+        write!(
+            f,
+            "The node replied with error {}: {}",
+            self.code, self.message
+        )
     }
 }
 
@@ -172,6 +201,17 @@ impl fmt::Display for ProtocolError {
     }
 }
 
+// Required for Fail derive
+impl fmt::Display for NodeUnreachable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Cannot connect to the JSON-RPC server at {}",
+            self.address
+        )
+    }
+}
+
 fn start_client(config_path: Option<PathBuf>) -> Result<TcpStream, failure::Error> {
     let config_file = config_path.unwrap_or_else(|| PathBuf::from("witnet.toml"));
     let config = Config::from_partial(&toml::from_file(&config_file)?);
@@ -182,7 +222,13 @@ fn start_client(config_path: Option<PathBuf>) -> Result<TcpStream, failure::Erro
     info!("Connecting to JSON-RPC server at {}", addr);
     let stream = TcpStream::connect(addr);
 
-    stream.map_err(Into::into)
+    stream.map_err(|error| {
+        NodeUnreachable {
+            address: addr,
+            error,
+        }
+        .into()
+    })
 }
 
 fn send_request<S: Read + Write>(stream: &mut S, request: &str) -> Result<String, io::Error> {
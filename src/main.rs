@@ -14,28 +14,31 @@ use structopt::StructOpt;
 use witnet_node as node;
 
 mod cli;
+mod errors;
+#[cfg(feature = "jsonrpc")]
 mod json_rpc_client;
+mod rad_repl;
 
 fn main() {
-    init_logger();
+    let cli_args = cli::Cli::from_args();
+    let json = cli_args.json;
+    init_logger(json);
 
-    if let Err(e) = run() {
-        eprintln!("Error: {}", e);
-        for cause in e.iter_causes() {
-            eprintln!("Cause: {}", cause);
-        }
+    if let Err(e) = run(cli_args) {
+        errors::Report::new(&e).print(json);
         exit(1);
     }
 }
 
-fn run() -> Result<(), failure::Error> {
-    let cli_args = cli::Cli::from_args();
-    cli::exec(cli_args.cmd)?;
+fn run(cli_args: cli::Cli) -> Result<(), failure::Error> {
+    cli::exec(cli_args.cmd, cli_args.json)?;
     Ok(())
 }
 
-fn init_logger() {
-    let env = env_logger::Env::default().default_filter_or("info");
+fn init_logger(json: bool) {
+    // Scripts using the JSON output only get warnings and errors in stderr, unless RUST_LOG is set
+    let default_filter = if json { "warn" } else { "info" };
+    let env = env_logger::Env::default().default_filter_or(default_filter);
     let mut logger = env_logger::Builder::from_env(env);
 
     logger.init();
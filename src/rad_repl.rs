@@ -0,0 +1,351 @@
+//! Interactive REPL for developing RADON scripts
+//!
+//! The script is built one operator at a time against an input, which can be fetched from a URL
+//! or pasted. After each operator the intermediate values are shown, and the final script can be
+//! exported as hex, ready to be embedded in a data request.
+use std::io::{self, BufRead, Write};
+
+use failure::Fail;
+use rmpv::Value;
+use witnet_data_structures::chain::{RADRetrieve, RADType};
+use witnet_rad::{
+    self as rad,
+    error::RadError,
+    operators::RadonOpCodes,
+    script::{execute_radon_script, pack_radon_script, RadonCall, RadonScript},
+    types::{string::RadonString, RadonTypes},
+};
+
+const PROMPT: &str = "rad> ";
+
+const HELP: &str = "Commands:
+  fetch <url>                 Retrieve the input from a URL with an HTTP GET request
+  input <text>                Use the pasted text as input
+  push <operator> [<args>]    Append an operator, arguments are a JSON array: push get [\"main\"]
+  pop                         Remove the last operator
+  show                        Show the input and the value after each operator
+  reset                       Remove all the operators
+  export                      Print the script as hex, ready to be embedded in a data request
+  help                        Show this help
+  quit                        Exit the REPL";
+
+/// The error type for the commands of the REPL
+#[derive(Debug, Fail)]
+enum ReplError {
+    #[fail(
+        display = "Unknown command `{}`, type `help` to list the commands",
+        command
+    )]
+    UnknownCommand { command: String },
+    #[fail(display = "Missing argument: {}", argument)]
+    MissingArgument { argument: &'static str },
+    #[fail(display = "Unknown operator `{}`", operator)]
+    UnknownOperator { operator: String },
+    #[fail(display = "Operator arguments must be a JSON array: {}", message)]
+    InvalidArguments { message: String },
+    #[fail(display = "The script is empty")]
+    EmptyScript,
+    #[fail(display = "The operator was not added: {}", error)]
+    OperatorFailed { error: RadError },
+}
+
+/// Run the REPL, reading commands from stdin until it is closed or `quit` is typed
+pub(crate) fn run() -> Result<(), failure::Error> {
+    let mut session = Session::default();
+    let stdin = io::stdin();
+    let mut stdin = stdin.lock();
+    let mut line = String::new();
+
+    println!("RADON script REPL, type `help` to list the commands");
+    loop {
+        print!("{}", PROMPT);
+        io::stdout().flush()?;
+
+        line.clear();
+        if stdin.read_line(&mut line)? == 0 {
+            break Ok(());
+        }
+        let line = line.trim();
+        match line {
+            "" => continue,
+            "quit" | "exit" => break Ok(()),
+            _ => match session.execute(line) {
+                Ok(output) => println!("{}", output),
+                Err(e) => println!("Error: {}", e),
+            },
+        }
+    }
+}
+
+/// State of the REPL: the input and the operators added so far
+#[derive(Debug, Default)]
+struct Session {
+    input: Option<RadonTypes>,
+    script: RadonScript,
+}
+
+impl Session {
+    /// Execute one command, returning the text to show to the user
+    fn execute(&mut self, line: &str) -> Result<String, failure::Error> {
+        let (command, argument) = split_command(line);
+
+        match command {
+            "help" => Ok(HELP.to_string()),
+            "fetch" => {
+                let url = required(argument, "url")?;
+                let retrieve = RADRetrieve {
+                    kind: RADType::HttpGet,
+                    url: url.to_string(),
+                    script: pack_radon_script(&[]),
+                    ..RADRetrieve::default()
+                };
+                self.set_input(rad::run_retrieval(retrieve)?)
+            }
+            "input" => {
+                let text = required(argument, "text")?;
+                self.set_input(RadonString::from(text).into())
+            }
+            "push" => {
+                let call = parse_call(required(argument, "operator")?)?;
+                self.push(call)
+            }
+            "pop" => match self.script.pop() {
+                Some(call) => Ok(format!("Removed {}", format_call(&call))),
+                None => Err(ReplError::EmptyScript)?,
+            },
+            "show" => Ok(self.show()),
+            "reset" => {
+                self.script.clear();
+                Ok("Removed all the operators".to_string())
+            }
+            "export" => {
+                if self.script.is_empty() {
+                    Err(ReplError::EmptyScript)?
+                }
+                Ok(to_hex(&pack_radon_script(&self.script)))
+            }
+            other => Err(ReplError::UnknownCommand {
+                command: other.to_string(),
+            })?,
+        }
+    }
+
+    fn set_input(&mut self, input: RadonTypes) -> Result<String, failure::Error> {
+        self.input = Some(input);
+
+        Ok(self.show())
+    }
+
+    /// Append an operator. If there is an input, the operator is only added if it can be applied
+    /// to the output of the previous operators.
+    fn push(&mut self, call: RadonCall) -> Result<String, failure::Error> {
+        let description = format_call(&call);
+        self.script.push(call);
+
+        match &self.input {
+            None => Ok(format!("Added {}", description)),
+            Some(input) => match execute_radon_script(input.clone(), &self.script) {
+                Ok(output) => Ok(format!("{} -> {}", description, output)),
+                Err(error) => {
+                    self.script.pop();
+                    Err(ReplError::OperatorFailed { error })?
+                }
+            },
+        }
+    }
+
+    /// Describe the input and the intermediate value after each operator
+    fn show(&self) -> String {
+        let input = match &self.input {
+            Some(input) => input.clone(),
+            None => {
+                return format!(
+                    "No input, use `fetch` or `input`. Operators: {}",
+                    self.script
+                        .iter()
+                        .map(format_call)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            }
+        };
+        let mut lines = vec![format!("input -> {}", input)];
+        let mut value = Ok(input);
+        for (index, call) in self.script.iter().enumerate() {
+            value = value.and_then(|value| rad::operators::operate(value, call));
+            let output = match &value {
+                Ok(value) => value.to_string(),
+                Err(error) => format!("Error: {}", error),
+            };
+            lines.push(format!("{}. {} -> {}", index, format_call(call), output));
+        }
+
+        lines.join("\n")
+    }
+}
+
+/// Split a line into the command and the rest of the line
+fn split_command(line: &str) -> (&str, Option<&str>) {
+    let mut parts = line.splitn(2, char::is_whitespace);
+    let command = parts.next().unwrap_or_default();
+    let argument = parts
+        .next()
+        .map(str::trim)
+        .filter(|argument| !argument.is_empty());
+
+    (command, argument)
+}
+
+fn required<'a>(argument: Option<&'a str>, name: &'static str) -> Result<&'a str, ReplError> {
+    argument.ok_or(ReplError::MissingArgument { argument: name })
+}
+
+/// Parse an operator, given by name or by code, and its optional JSON array of arguments
+fn parse_call(text: &str) -> Result<RadonCall, ReplError> {
+    let (operator, arguments) = split_command(text);
+    let op_code = parse_op_code(operator).ok_or_else(|| ReplError::UnknownOperator {
+        operator: operator.to_string(),
+    })?;
+    let arguments = match arguments {
+        Some(arguments) => match serde_json::from_str(arguments) {
+            Ok(serde_json::Value::Array(arguments)) => {
+                Some(arguments.into_iter().map(json_to_value).collect())
+            }
+            Ok(other) => Err(ReplError::InvalidArguments {
+                message: format!("got `{}`", other),
+            })?,
+            Err(e) => Err(ReplError::InvalidArguments {
+                message: e.to_string(),
+            })?,
+        },
+        None => None,
+    };
+
+    Ok((op_code, arguments))
+}
+
+/// Operator codes can be given by name, ignoring case, or by number, in decimal or in hex
+fn parse_op_code(operator: &str) -> Option<RadonOpCodes> {
+    use num_traits::FromPrimitive;
+
+    let code = if operator.starts_with("0x") {
+        u64::from_str_radix(&operator[2..], 16).ok()
+    } else {
+        operator.parse::<u64>().ok()
+    };
+
+    match code {
+        Some(code) => RadonOpCodes::from_u64(code),
+        None => (0..=0xff)
+            .filter_map(RadonOpCodes::from_u64)
+            .find(|op_code| op_code.to_string().eq_ignore_ascii_case(operator)),
+    }
+}
+
+/// Convert the JSON arguments of an operator into MessagePack values
+fn json_to_value(json: serde_json::Value) -> Value {
+    match json {
+        serde_json::Value::Null => Value::Nil,
+        serde_json::Value::Bool(boolean) => Value::from(boolean),
+        serde_json::Value::Number(number) => {
+            if let Some(integer) = number.as_u64() {
+                Value::from(integer)
+            } else if let Some(integer) = number.as_i64() {
+                Value::from(integer)
+            } else {
+                Value::from(number.as_f64().unwrap_or_default())
+            }
+        }
+        serde_json::Value::String(string) => Value::from(string),
+        serde_json::Value::Array(array) => {
+            Value::Array(array.into_iter().map(json_to_value).collect())
+        }
+        serde_json::Value::Object(map) => Value::Map(
+            map.into_iter()
+                .map(|(key, value)| (Value::from(key), json_to_value(value)))
+                .collect(),
+        ),
+    }
+}
+
+fn format_call((op_code, arguments): &RadonCall) -> String {
+    match arguments {
+        Some(arguments) => format!(
+            "{}({})",
+            op_code,
+            arguments
+                .iter()
+                .map(Value::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        None => op_code.to_string(),
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WEATHER: &str = r#"{"main":{"temp":-4,"pressure":1013},"name":"Berlin"}"#;
+
+    #[test]
+    fn parse_operators() {
+        assert_eq!(parse_op_code("ParseJson"), Some(RadonOpCodes::ParseJson));
+        assert_eq!(parse_op_code("parsejson"), Some(RadonOpCodes::ParseJson));
+        assert_eq!(parse_op_code("0x53"), Some(RadonOpCodes::ParseJson));
+        assert_eq!(parse_op_code("132"), Some(RadonOpCodes::ToMap));
+        assert_eq!(parse_op_code("nope"), None);
+
+        assert_eq!(
+            parse_call(r#"get ["main"]"#).unwrap(),
+            (RadonOpCodes::Get, Some(vec![Value::from("main")]))
+        );
+        assert_eq!(
+            parse_call("tofloat").unwrap(),
+            (RadonOpCodes::ToFloat, None)
+        );
+        assert!(parse_call(r#"get "main""#).is_err());
+    }
+
+    #[test]
+    fn build_and_export_script() {
+        let mut session = Session::default();
+        assert!(session.execute("export").is_err());
+
+        session.execute(&format!("input {}", WEATHER)).unwrap();
+        for operator in &[
+            "push ParseJson",
+            "push ToMap",
+            r#"push Get ["main"]"#,
+            "push ToMap",
+            r#"push Get ["temp"]"#,
+        ] {
+            session.execute(operator).unwrap();
+        }
+        assert_eq!(
+            session.execute("push ToFloat").unwrap(),
+            "ToFloat -> RadonTypes::RadonFloat(-4)"
+        );
+
+        // Operators that fail on the current value are not added
+        assert!(session.execute(r#"push Get ["temp"]"#).is_err());
+        assert_eq!(session.script.len(), 6);
+        assert_eq!(session.show().lines().count(), 7);
+
+        assert_eq!(
+            session.execute("export").unwrap(),
+            "9653cc849201a46d61696ecc849201a474656d70cc82"
+        );
+
+        session.execute("pop").unwrap();
+        assert_eq!(session.script.len(), 5);
+        session.execute("reset").unwrap();
+        assert!(session.script.is_empty());
+        assert!(session.execute("frobnicate").is_err());
+    }
+}
@@ -0,0 +1,147 @@
+//! # Chaos storage backend
+//!
+//! High-order storage backend for chaos testing that randomly fails
+//! operations before forwarding them to the inner backend. The
+//! failures are transient: a failed operation has no effect, and
+//! retrying it may succeed.
+use std::sync::Arc;
+
+use failure::Fail;
+use witnet_util::chaos::Chaos;
+
+use crate::storage::{Result, Storage, StorageIterator, WriteBatch};
+
+/// Error injected by the chaos backend
+#[derive(Debug, Fail)]
+#[fail(display = "Transient storage error injected by chaos testing")]
+pub struct ChaosError;
+
+/// Backend that randomly fails operations.
+pub struct Backend<T> {
+    backend: T,
+    chaos: Arc<Chaos>,
+}
+
+impl<T: Storage> Backend<T> {
+    /// Create chaos backend which will use `backend` as the actual
+    /// storage backend and draw failures from `chaos`
+    pub fn new(backend: T, chaos: Arc<Chaos>) -> Self {
+        Backend { backend, chaos }
+    }
+
+    /// Get a reference to the inner storage backend
+    pub fn inner(&self) -> &T {
+        &self.backend
+    }
+
+    fn check(&self) -> Result<()> {
+        if self.chaos.storage_error() {
+            Err(ChaosError)?
+        }
+
+        Ok(())
+    }
+}
+
+impl<T: Storage> Storage for Backend<T> {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        self.check()?;
+        self.backend.get(key)
+    }
+
+    fn put(&mut self, key: Vec<u8>, value: Vec<u8>) -> Result<()> {
+        self.check()?;
+        self.backend.put(key, value)
+    }
+
+    fn delete(&mut self, key: &[u8]) -> Result<()> {
+        self.check()?;
+        self.backend.delete(key)
+    }
+
+    fn write_batch(&mut self, batch: WriteBatch) -> Result<()> {
+        self.check()?;
+        self.backend.write_batch(batch)
+    }
+
+    fn prefix_iterator<'a>(&'a self, prefix: &'a [u8]) -> Result<StorageIterator<'a>> {
+        self.check()?;
+        self.backend.prefix_iterator(prefix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use witnet_util::chaos::ChaosConfig;
+
+    use super::*;
+    use crate::backends::hashmap;
+
+    fn chaos(storage_error_probability: f64) -> Arc<Chaos> {
+        Arc::new(Chaos::new(ChaosConfig {
+            seed: 42,
+            drop_probability: 0.0,
+            max_delay: Duration::from_millis(0),
+            storage_error_probability,
+            max_clock_skew: 0,
+        }))
+    }
+
+    #[test]
+    fn test_no_errors() {
+        let mut backend = Backend::new(hashmap::Backend::new(), chaos(0.0));
+
+        assert_eq!(None, backend.get(b"name").unwrap());
+        assert_eq!((), backend.put("name".into(), "johnny".into()).unwrap());
+        assert_eq!(Some("johnny".into()), backend.get(b"name").unwrap());
+    }
+
+    #[test]
+    fn test_always_errors() {
+        let mut backend = Backend::new(hashmap::Backend::new(), chaos(1.0));
+
+        assert!(backend.get(b"name").is_err());
+        assert!(backend.put("name".into(), "johnny".into()).is_err());
+        assert!(backend.delete(b"name").is_err());
+        let mut batch = WriteBatch::default();
+        batch.put("name".into(), "johnny".into());
+        assert!(backend.write_batch(batch).is_err());
+        assert!(backend.prefix_iterator(b"na").is_err());
+        assert!(backend.inner().is_empty());
+    }
+
+    #[test]
+    fn test_recovers_retrying() {
+        // Apply the same operations to a backend that fails half of the time, retrying them until
+        // they succeed, and to a reliable one. Both must end with the same contents
+        let mut backend = Backend::new(hashmap::Backend::new(), chaos(0.5));
+        let mut expected = hashmap::Backend::new();
+        let mut errors = 0;
+
+        for i in 0..500u32 {
+            let key = (i % 17).to_be_bytes().to_vec();
+            let value = i.to_be_bytes().to_vec();
+            loop {
+                let result = if i % 5 == 0 {
+                    backend.delete(&key)
+                } else {
+                    backend.put(key.clone(), value.clone())
+                };
+                match result {
+                    Ok(()) => break,
+                    Err(_) => errors += 1,
+                }
+            }
+            if i % 5 == 0 {
+                expected.remove(&key);
+            } else {
+                expected.insert(key, value);
+            }
+        }
+
+        assert!(errors > 0);
+        assert_eq!(backend.inner(), &expected);
+    }
+}
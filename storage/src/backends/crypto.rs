@@ -2,7 +2,9 @@
 //!
 //! High-order storage backend that hashes the key and
 //! encrypts/decrypts the value when putting/getting it.
-use crate::storage::{Result, Storage};
+use failure::bail;
+
+use crate::storage::{Result, Storage, StorageIterator, WriteBatch, WriteOp};
 use witnet_crypto::{cipher, hash::calculate_sha256, pbkdf2::pbkdf2_sha256};
 use witnet_protected::Protected;
 
@@ -28,6 +30,20 @@ impl<T: Storage> Backend<T> {
     pub fn inner(&self) -> &T {
         &self.backend
     }
+
+    /// Hash the key and encrypt the value to be stored in the inner backend
+    fn encrypt(&self, key: &[u8], value: &[u8]) -> Result<(Vec<u8>, Vec<u8>)> {
+        let hash_key = calculate_sha256(key);
+        let iv = cipher::generate_random(IV_LENGTH)?;
+        let salt = cipher::generate_random(SALT_LENGTH)?;
+        let secret = get_secret(&self.password, &salt);
+        let encrypted = cipher::encrypt_aes_cbc(&secret, value, iv.as_ref())?;
+        let mut final_value = iv.clone();
+        final_value.extend(encrypted);
+        final_value.extend(salt);
+
+        Ok((hash_key.as_ref().to_vec(), final_value))
+    }
 }
 
 impl<T: Storage> Storage for Backend<T> {
@@ -53,20 +69,36 @@ impl<T: Storage> Storage for Backend<T> {
     }
 
     fn put(&mut self, key: Vec<u8>, value: Vec<u8>) -> Result<()> {
-        let hash_key = calculate_sha256(key.as_ref());
-        let iv = cipher::generate_random(IV_LENGTH)?;
-        let salt = cipher::generate_random(SALT_LENGTH)?;
-        let secret = get_secret(&self.password, &salt);
-        let encrypted = cipher::encrypt_aes_cbc(&secret, value.as_ref(), iv.as_ref())?;
-        let mut final_value = iv.clone();
-        final_value.extend(encrypted);
-        final_value.extend(salt);
+        let (hash_key, final_value) = self.encrypt(&key, &value)?;
 
-        self.backend.put(hash_key.as_ref().to_vec(), final_value)
+        self.backend.put(hash_key, final_value)
     }
 
     fn delete(&mut self, key: &[u8]) -> Result<()> {
-        self.backend.delete(key)
+        let hash_key = calculate_sha256(key);
+
+        self.backend.delete(hash_key.as_ref())
+    }
+
+    fn write_batch(&mut self, batch: WriteBatch) -> Result<()> {
+        let mut encrypted_batch = WriteBatch::default();
+        for op in batch {
+            match op {
+                WriteOp::Put(key, value) => {
+                    let (hash_key, final_value) = self.encrypt(&key, &value)?;
+                    encrypted_batch.put(hash_key, final_value);
+                }
+                WriteOp::Delete(key) => {
+                    encrypted_batch.delete(calculate_sha256(&key).as_ref().to_vec())
+                }
+            }
+        }
+
+        self.backend.write_batch(encrypted_batch)
+    }
+
+    fn prefix_iterator<'a>(&'a self, _prefix: &'a [u8]) -> Result<StorageIterator<'a>> {
+        bail!("The keys of the encrypted storage are hashed, so they cannot be iterated by prefix")
     }
 }
 
@@ -87,6 +119,24 @@ mod tests {
         assert_eq!(None, backend.get(b"name").unwrap());
         assert_eq!((), backend.put("name".into(), "johnny".into()).unwrap());
         assert_eq!(Some("johnny".into()), backend.get(b"name").unwrap());
+        assert_eq!((), backend.delete(b"name").unwrap());
+        assert_eq!(None, backend.get(b"name").unwrap());
+    }
+
+    #[test]
+    fn test_encrypted_write_batch() {
+        let password = "".into();
+        let mut backend = Backend::new(password, hashmap::Backend::new());
+        backend.put("name".into(), "johnny".into()).unwrap();
+
+        let mut batch = WriteBatch::default();
+        batch.put("surname".into(), "doe".into());
+        batch.delete("name".into());
+        backend.write_batch(batch).unwrap();
+
+        assert_eq!(None, backend.get(b"name").unwrap());
+        assert_eq!(Some("doe".into()), backend.get(b"surname").unwrap());
+        assert_eq!(1, backend.inner().len());
     }
 
     #[test]
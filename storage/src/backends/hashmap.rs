@@ -3,7 +3,7 @@
 //! Storage backend that keeps data in a heap-allocated HashMap.
 use std::collections::HashMap;
 
-use crate::storage::{Result, Storage};
+use crate::storage::{Result, Storage, StorageIterator, WriteBatch, WriteOp};
 
 /// HashMap backend
 pub type Backend = HashMap<Vec<u8>, Vec<u8>>;
@@ -22,6 +22,27 @@ impl Storage for Backend {
         Backend::remove(self, key);
         Ok(())
     }
+
+    fn write_batch(&mut self, batch: WriteBatch) -> Result<()> {
+        for op in batch {
+            match op {
+                WriteOp::Put(key, value) => Backend::insert(self, key, value),
+                WriteOp::Delete(key) => Backend::remove(self, &key),
+            };
+        }
+        Ok(())
+    }
+
+    fn prefix_iterator<'a>(&'a self, prefix: &'a [u8]) -> Result<StorageIterator<'a>> {
+        let mut pairs: Vec<_> = self
+            .iter()
+            .filter(|(key, _)| key.starts_with(prefix))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        pairs.sort();
+
+        Ok(Box::new(pairs.into_iter()))
+    }
 }
 
 #[cfg(test)]
@@ -42,4 +63,28 @@ mod tests {
         assert_eq!((), storage.delete(b"name").unwrap());
         assert_eq!(None, storage.get(b"name").unwrap());
     }
+
+    #[test]
+    fn test_hashmap_write_batch() {
+        let mut storage = backend();
+        storage.put(b"name".to_vec(), b"john".to_vec()).unwrap();
+
+        let mut batch = WriteBatch::default();
+        batch.put(b"user_2".to_vec(), b"jane".to_vec());
+        batch.put(b"user_1".to_vec(), b"john".to_vec());
+        batch.delete(b"name".to_vec());
+        storage.write_batch(batch).unwrap();
+
+        assert_eq!(None, storage.get(b"name").unwrap());
+        assert_eq!(
+            vec![
+                (b"user_1".to_vec(), b"john".to_vec()),
+                (b"user_2".to_vec(), b"jane".to_vec()),
+            ],
+            storage
+                .prefix_iterator(b"user_")
+                .unwrap()
+                .collect::<Vec<_>>()
+        );
+    }
 }
@@ -4,6 +4,8 @@
 //! containing state for specific storage solutions (databases,
 //! volatile memory, flat files, etc.).
 
+#[cfg(feature = "chaos-backend")]
+pub mod chaos;
 #[cfg(feature = "crypto-backend")]
 pub mod crypto;
 pub mod hashmap;
@@ -1,21 +1,99 @@
 //! # Rocksdb storage backend
 //!
 //! Storage backend that persists data in the file system using a RocksDB database.
+//!
+//! The [`ColumnFamilyBackend`](ColumnFamilyBackend) keeps the keys in separate column families,
+//! so that data with different access patterns (e.g. blocks and peers) can be tuned and compacted
+//! independently. A router chooses the column family of every key.
+use std::{collections::BTreeMap, path::Path};
+
 use failure::Fail;
 #[cfg(not(test))]
 use rocksdb;
 #[cfg(test)]
 use rocksdb_mock as rocksdb;
 
-use crate::storage::{Result, Storage};
+use crate::storage::{Result, Storage, StorageIterator, WriteBatch, WriteOp};
 
 /// Rocksdb backend
 pub type Backend = rocksdb::DB;
 
+/// Column family of a key, `None` for the default column family
+pub type Router = fn(&[u8]) -> Option<&'static str>;
+
 #[derive(Debug, Fail)]
 #[fail(display = "RocksDB error")]
 struct Error(#[fail(cause)] rocksdb::Error);
 
+#[derive(Debug, Fail)]
+#[fail(display = "Column family {} is not open in the RocksDB database", _0)]
+struct UnknownColumnFamily(&'static str);
+
+/// Compaction styles of the database
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CompactionStyle {
+    /// Sorted runs of increasing size by level, optimized for reads
+    Level,
+    /// Sorted runs merged when similar in size, optimized for writes
+    Universal,
+    /// The oldest files are deleted when the database reaches its size limit
+    Fifo,
+}
+
+/// Tuning options of the database
+#[derive(Clone, Debug, PartialEq)]
+pub struct Options {
+    /// Size of the cache of uncompressed blocks, shared by all the column families
+    pub block_cache_bytes: usize,
+    /// Size of the memory buffer of each column family, which is written to disk when full
+    pub write_buffer_bytes: usize,
+    /// Compaction style of the column families
+    pub compaction_style: CompactionStyle,
+    /// Maximum number of concurrent background compactions
+    pub max_background_compactions: u32,
+}
+
+/// Rocksdb backend storing every key in the column family chosen by a router
+pub struct ColumnFamilyBackend {
+    db: rocksdb::DB,
+    column_families: Vec<&'static str>,
+    router: Router,
+}
+
+impl ColumnFamilyBackend {
+    /// Open the database of `path` with the given column families, creating any of them that
+    /// does not exist yet
+    pub fn open<P: AsRef<Path>>(
+        path: P,
+        options: &Options,
+        column_families: &[&'static str],
+        router: Router,
+    ) -> Result<Self> {
+        let mut block_options = rocksdb::BlockBasedOptions::default();
+        block_options.set_lru_cache(options.block_cache_bytes);
+
+        let mut db_options = rocksdb::Options::default();
+        db_options.create_if_missing(true);
+        db_options.create_missing_column_families(true);
+        db_options.set_block_based_table_factory(&block_options);
+        db_options.set_write_buffer_size(options.write_buffer_bytes);
+        db_options.set_compaction_style(match options.compaction_style {
+            CompactionStyle::Level => rocksdb::DBCompactionStyle::Level,
+            CompactionStyle::Universal => rocksdb::DBCompactionStyle::Universal,
+            CompactionStyle::Fifo => rocksdb::DBCompactionStyle::Fifo,
+        });
+        db_options.set_max_background_compactions(options.max_background_compactions as i32);
+
+        let db = rocksdb::DB::open_cf(&db_options, path, column_families).map_err(Error)?;
+
+        Ok(ColumnFamilyBackend {
+            db,
+            column_families: column_families.to_vec(),
+            router,
+        })
+    }
+}
+
 impl Storage for Backend {
     fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
         let result = Backend::get(self, &key)
@@ -33,6 +111,118 @@ impl Storage for Backend {
         Backend::delete(self, &key).map_err(Error)?;
         Ok(())
     }
+
+    fn write_batch(&mut self, batch: WriteBatch) -> Result<()> {
+        let mut rocksdb_batch = rocksdb::WriteBatch::default();
+        for op in batch {
+            match op {
+                WriteOp::Put(key, value) => rocksdb_batch.put(key, value).map_err(Error)?,
+                WriteOp::Delete(key) => rocksdb_batch.delete(key).map_err(Error)?,
+            }
+        }
+        Backend::write(self, rocksdb_batch).map_err(Error)?;
+        Ok(())
+    }
+
+    fn prefix_iterator<'a>(&'a self, prefix: &'a [u8]) -> Result<StorageIterator<'a>> {
+        let iter = Backend::iterator(self, from_prefix(prefix));
+
+        Ok(Box::new(with_prefix(iter, prefix)))
+    }
+}
+
+impl Storage for ColumnFamilyBackend {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        if let Some(name) = (self.router)(key) {
+            let cf = self.db.cf_handle(name).ok_or(UnknownColumnFamily(name))?;
+            if let Some(value) = self.db.get_cf(cf, key).map_err(Error)? {
+                return Ok(Some(value.to_vec()));
+            }
+        }
+
+        // Keys written before they were routed to a column family are in the default one
+        let result = self
+            .db
+            .get(&key)
+            .map(|opt| opt.map(|dbvec| dbvec.to_vec()))
+            .map_err(Error)?;
+        Ok(result)
+    }
+
+    fn put(&mut self, key: Vec<u8>, value: Vec<u8>) -> Result<()> {
+        match (self.router)(&key) {
+            Some(name) => {
+                let cf = self.db.cf_handle(name).ok_or(UnknownColumnFamily(name))?;
+                self.db.put_cf(cf, key, value).map_err(Error)?;
+            }
+            None => self.db.put(key, value).map_err(Error)?,
+        }
+        Ok(())
+    }
+
+    fn delete(&mut self, key: &[u8]) -> Result<()> {
+        if let Some(name) = (self.router)(key) {
+            let cf = self.db.cf_handle(name).ok_or(UnknownColumnFamily(name))?;
+            self.db.delete_cf(cf, key).map_err(Error)?;
+        }
+        self.db.delete(&key).map_err(Error)?;
+        Ok(())
+    }
+
+    fn write_batch(&mut self, batch: WriteBatch) -> Result<()> {
+        let mut rocksdb_batch = rocksdb::WriteBatch::default();
+        for op in batch {
+            match op {
+                WriteOp::Put(key, value) => match (self.router)(&key) {
+                    Some(name) => {
+                        let cf = self.db.cf_handle(name).ok_or(UnknownColumnFamily(name))?;
+                        rocksdb_batch.put_cf(cf, key, value).map_err(Error)?;
+                    }
+                    None => rocksdb_batch.put(key, value).map_err(Error)?,
+                },
+                WriteOp::Delete(key) => {
+                    if let Some(name) = (self.router)(&key) {
+                        let cf = self.db.cf_handle(name).ok_or(UnknownColumnFamily(name))?;
+                        rocksdb_batch.delete_cf(cf, &key).map_err(Error)?;
+                    }
+                    rocksdb_batch.delete(key).map_err(Error)?;
+                }
+            }
+        }
+        self.db.write(rocksdb_batch).map_err(Error)?;
+        Ok(())
+    }
+
+    fn prefix_iterator<'a>(&'a self, prefix: &'a [u8]) -> Result<StorageIterator<'a>> {
+        // The keys of a prefix can be in any column family. Keys written to the default column
+        // family before they were routed to another one are superseded by the latter
+        let mut pairs = BTreeMap::new();
+        pairs.extend(with_prefix(self.db.iterator(from_prefix(prefix)), prefix));
+        for name in &self.column_families {
+            let cf = self.db.cf_handle(name).ok_or(UnknownColumnFamily(name))?;
+            let iter = self
+                .db
+                .iterator_cf(cf, from_prefix(prefix))
+                .map_err(Error)?;
+            pairs.extend(with_prefix(iter, prefix));
+        }
+
+        Ok(Box::new(pairs.into_iter()))
+    }
+}
+
+/// Iterator mode starting at the first key with the given prefix
+fn from_prefix(prefix: &[u8]) -> rocksdb::IteratorMode<'_> {
+    rocksdb::IteratorMode::From(prefix, rocksdb::Direction::Forward)
+}
+
+/// Take the key/value pairs of a RocksDB iterator while their key has the given prefix
+fn with_prefix<'a, I>(iter: I, prefix: &'a [u8]) -> impl Iterator<Item = (Vec<u8>, Vec<u8>)> + 'a
+where
+    I: Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a,
+{
+    iter.take_while(move |(key, _)| key.starts_with(prefix))
+        .map(|(key, value)| (key.to_vec(), value.to_vec()))
 }
 
 #[cfg(test)]
@@ -43,6 +233,25 @@ mod tests {
         Box::new(Backend::new())
     }
 
+    fn router(key: &[u8]) -> Option<&'static str> {
+        match key.first() {
+            Some(b'b') => Some("blocks"),
+            Some(b'x') => Some("missing"),
+            _ => None,
+        }
+    }
+
+    fn column_family_backend() -> ColumnFamilyBackend {
+        let options = Options {
+            block_cache_bytes: 1024,
+            write_buffer_bytes: 1024,
+            compaction_style: CompactionStyle::Level,
+            max_background_compactions: 1,
+        };
+
+        ColumnFamilyBackend::open("db", &options, &["blocks"], router).unwrap()
+    }
+
     #[test]
     fn test_rocksdb() {
         let mut storage = backend();
@@ -53,21 +262,219 @@ mod tests {
         assert_eq!((), storage.delete(b"name").unwrap());
         assert_eq!(None, storage.get(b"name").unwrap());
     }
+
+    #[test]
+    fn test_rocksdb_column_families() {
+        let mut storage = column_family_backend();
+
+        storage.put(b"block".to_vec(), b"1".to_vec()).unwrap();
+        storage.put(b"name".to_vec(), b"john".to_vec()).unwrap();
+        assert_eq!(Some("1".into()), storage.get(b"block").unwrap());
+        assert_eq!(Some("john".into()), storage.get(b"name").unwrap());
+
+        // Every key is stored in its own column family
+        let cf = storage.db.cf_handle("blocks").unwrap();
+        assert_eq!(Some("1".into()), storage.db.get_cf(cf, b"block").unwrap());
+        assert_eq!(None, storage.db.get_cf(cf, b"name").unwrap());
+        assert_eq!(None, storage.db.get(&b"block").unwrap());
+
+        storage.delete(b"block").unwrap();
+        assert_eq!(None, storage.get(b"block").unwrap());
+    }
+
+    #[test]
+    fn test_rocksdb_column_families_fallback() {
+        let mut storage = column_family_backend();
+
+        // A key stored in the default column family before being routed to another one
+        storage.db.put(b"block", b"1").unwrap();
+        assert_eq!(Some("1".into()), storage.get(b"block").unwrap());
+
+        storage.delete(b"block").unwrap();
+        assert_eq!(None, storage.get(b"block").unwrap());
+    }
+
+    #[test]
+    fn test_rocksdb_write_batch() {
+        let mut storage = backend();
+        storage.put(b"name".to_vec(), b"john".to_vec()).unwrap();
+
+        let mut batch = WriteBatch::default();
+        batch.put(b"user_2".to_vec(), b"jane".to_vec());
+        batch.put(b"user_1".to_vec(), b"john".to_vec());
+        batch.delete(b"name".to_vec());
+        storage.write_batch(batch).unwrap();
+
+        assert_eq!(None, storage.get(b"name").unwrap());
+        assert_eq!(
+            vec![
+                (b"user_1".to_vec(), b"john".to_vec()),
+                (b"user_2".to_vec(), b"jane".to_vec()),
+            ],
+            storage
+                .prefix_iterator(b"user_")
+                .unwrap()
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_rocksdb_column_families_write_batch() {
+        let mut storage = column_family_backend();
+        // A key stored in the default column family before being routed to another one
+        storage.db.put(b"block_1", b"old").unwrap();
+        storage.db.put(b"block_2", b"2").unwrap();
+
+        let mut batch = WriteBatch::default();
+        batch.put(b"block_1".to_vec(), b"1".to_vec());
+        batch.put(b"block_3".to_vec(), b"3".to_vec());
+        batch.put(b"name".to_vec(), b"john".to_vec());
+        batch.delete(b"block_2".to_vec());
+        storage.write_batch(batch).unwrap();
+
+        assert_eq!(Some("john".into()), storage.get(b"name").unwrap());
+        assert_eq!(
+            vec![
+                (b"block_1".to_vec(), b"1".to_vec()),
+                (b"block_3".to_vec(), b"3".to_vec()),
+            ],
+            storage
+                .prefix_iterator(b"block_")
+                .unwrap()
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_rocksdb_unknown_column_family() {
+        let mut storage = column_family_backend();
+
+        assert!(storage.put(b"x".to_vec(), b"1".to_vec()).is_err());
+        assert!(storage.get(b"x").is_err());
+    }
 }
 
 #[cfg(test)]
 mod rocksdb_mock {
+    use std::{cell::RefCell, collections::HashMap, path::Path};
+
     use super::*;
 
     pub type Error = failure::Error;
 
+    type ColumnFamilyData = HashMap<(String, Vec<u8>), Vec<u8>>;
+
     pub struct DB {
         data: Vec<(Vec<u8>, Vec<u8>)>,
+        column_families: Vec<String>,
+        column_family_data: RefCell<ColumnFamilyData>,
+    }
+
+    #[derive(Clone, Copy)]
+    pub struct ColumnFamily<'a>(&'a str);
+
+    pub enum Direction {
+        Forward,
+    }
+
+    pub enum IteratorMode<'a> {
+        From(&'a [u8], Direction),
+    }
+
+    type KVBytes = (Box<[u8]>, Box<[u8]>);
+
+    /// Column family, key and value (`None` to delete the key)
+    type BatchOp = (Option<String>, Vec<u8>, Option<Vec<u8>>);
+
+    #[derive(Default)]
+    pub struct WriteBatch {
+        ops: Vec<BatchOp>,
+    }
+
+    impl WriteBatch {
+        pub fn put<K: AsRef<[u8]>, V: AsRef<[u8]>>(&mut self, key: K, value: V) -> Result<()> {
+            self.ops
+                .push((None, key.as_ref().to_vec(), Some(value.as_ref().to_vec())));
+            Ok(())
+        }
+
+        pub fn put_cf<K: AsRef<[u8]>, V: AsRef<[u8]>>(
+            &mut self,
+            cf: ColumnFamily<'_>,
+            key: K,
+            value: V,
+        ) -> Result<()> {
+            self.ops.push((
+                Some(cf.0.to_string()),
+                key.as_ref().to_vec(),
+                Some(value.as_ref().to_vec()),
+            ));
+            Ok(())
+        }
+
+        pub fn delete<K: AsRef<[u8]>>(&mut self, key: K) -> Result<()> {
+            self.ops.push((None, key.as_ref().to_vec(), None));
+            Ok(())
+        }
+
+        pub fn delete_cf<K: AsRef<[u8]>>(&mut self, cf: ColumnFamily<'_>, key: K) -> Result<()> {
+            self.ops
+                .push((Some(cf.0.to_string()), key.as_ref().to_vec(), None));
+            Ok(())
+        }
+    }
+
+    pub enum DBCompactionStyle {
+        Level,
+        Universal,
+        Fifo,
+    }
+
+    #[derive(Default)]
+    pub struct BlockBasedOptions {}
+
+    impl BlockBasedOptions {
+        pub fn set_lru_cache(&mut self, _size: usize) {}
+    }
+
+    #[derive(Default)]
+    pub struct Options {}
+
+    impl Options {
+        pub fn create_if_missing(&mut self, _create_if_missing: bool) {}
+
+        pub fn create_missing_column_families(&mut self, _create_missing_cfs: bool) {}
+
+        pub fn set_block_based_table_factory(&mut self, _factory: &BlockBasedOptions) {}
+
+        pub fn set_write_buffer_size(&mut self, _size: usize) {}
+
+        pub fn set_compaction_style(&mut self, _style: DBCompactionStyle) {}
+
+        pub fn set_max_background_compactions(&mut self, _n: i32) {}
     }
 
     impl DB {
         pub fn new() -> Self {
-            DB { data: Vec::new() }
+            DB {
+                data: Vec::new(),
+                column_families: Vec::new(),
+                column_family_data: RefCell::new(HashMap::new()),
+            }
+        }
+
+        pub fn open_cf<P: AsRef<Path>>(_opts: &Options, _path: P, cfs: &[&str]) -> Result<DB> {
+            let mut db = DB::new();
+            db.column_families = cfs.iter().map(|cf| cf.to_string()).collect();
+
+            Ok(db)
+        }
+
+        pub fn cf_handle(&self, name: &str) -> Option<ColumnFamily<'_>> {
+            self.column_families
+                .iter()
+                .find(|cf| *cf == name)
+                .map(|cf| ColumnFamily(cf))
         }
 
         fn search<K: AsRef<[u8]>>(&self, key: &K) -> Option<usize> {
@@ -97,5 +504,86 @@ mod rocksdb_mock {
             self.search(key).map(|idx| self.data.remove(idx));
             Ok(())
         }
+
+        pub fn get_cf<K: AsRef<[u8]>>(
+            &self,
+            cf: ColumnFamily<'_>,
+            key: K,
+        ) -> Result<Option<Vec<u8>>> {
+            Ok(self
+                .column_family_data
+                .borrow()
+                .get(&(cf.0.to_string(), key.as_ref().to_vec()))
+                .cloned())
+        }
+
+        pub fn put_cf<K: AsRef<[u8]>, V: AsRef<[u8]>>(
+            &self,
+            cf: ColumnFamily<'_>,
+            key: K,
+            value: V,
+        ) -> Result<()> {
+            self.column_family_data.borrow_mut().insert(
+                (cf.0.to_string(), key.as_ref().to_vec()),
+                value.as_ref().to_vec(),
+            );
+            Ok(())
+        }
+
+        pub fn delete_cf<K: AsRef<[u8]>>(&self, cf: ColumnFamily<'_>, key: K) -> Result<()> {
+            self.column_family_data
+                .borrow_mut()
+                .remove(&(cf.0.to_string(), key.as_ref().to_vec()));
+            Ok(())
+        }
+
+        pub fn write(&mut self, batch: WriteBatch) -> Result<()> {
+            for (cf, key, value) in batch.ops {
+                match (cf, value) {
+                    (None, Some(value)) => self.put(key, value)?,
+                    (None, None) => self.delete(&key)?,
+                    (Some(cf), Some(value)) => self.put_cf(ColumnFamily(&cf), key, value)?,
+                    (Some(cf), None) => self.delete_cf(ColumnFamily(&cf), key)?,
+                }
+            }
+            Ok(())
+        }
+
+        pub fn iterator(&self, mode: IteratorMode<'_>) -> std::vec::IntoIter<KVBytes> {
+            sorted_from(self.data.iter().cloned(), mode)
+        }
+
+        pub fn iterator_cf(
+            &self,
+            cf: ColumnFamily<'_>,
+            mode: IteratorMode<'_>,
+        ) -> Result<std::vec::IntoIter<KVBytes>> {
+            let pairs: Vec<_> = self
+                .column_family_data
+                .borrow()
+                .iter()
+                .filter(|((name, _), _)| name == cf.0)
+                .map(|((_, key), value)| (key.clone(), value.clone()))
+                .collect();
+
+            Ok(sorted_from(pairs.into_iter(), mode))
+        }
+    }
+
+    fn sorted_from<I>(
+        pairs: I,
+        IteratorMode::From(start, _): IteratorMode<'_>,
+    ) -> std::vec::IntoIter<KVBytes>
+    where
+        I: Iterator<Item = (Vec<u8>, Vec<u8>)>,
+    {
+        let mut pairs: Vec<_> = pairs.filter(|(key, _)| key.as_slice() >= start).collect();
+        pairs.sort();
+
+        pairs
+            .into_iter()
+            .map(|(key, value)| (key.into_boxed_slice(), value.into_boxed_slice()))
+            .collect::<Vec<_>>()
+            .into_iter()
     }
 }
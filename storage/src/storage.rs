@@ -9,6 +9,9 @@ use failure;
 /// Result with error set to `failure::Error`
 pub type Result<T> = result::Result<T, failure::Error>;
 
+/// Iterator over key/value pairs of the storage, sorted by key
+pub type StorageIterator<'a> = Box<dyn Iterator<Item = (Vec<u8>, Vec<u8>)> + 'a>;
+
 /// Generic trait that exposes a very simple key/value CRUD API for data storage.
 ///
 /// This trait can be easily implemented for any specific storage
@@ -22,4 +25,78 @@ pub trait Storage {
 
     /// Delete a value from the storage
     fn delete(&mut self, key: &[u8]) -> Result<()>;
+
+    /// Apply all the writes of a batch, or none of them if it fails
+    fn write_batch(&mut self, batch: WriteBatch) -> Result<()>;
+
+    /// Iterate over the key/value pairs whose key starts with `prefix`, sorted by key
+    fn prefix_iterator<'a>(&'a self, prefix: &'a [u8]) -> Result<StorageIterator<'a>>;
+}
+
+impl<T: Storage + ?Sized> Storage for Box<T> {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        (**self).get(key)
+    }
+
+    fn put(&mut self, key: Vec<u8>, value: Vec<u8>) -> Result<()> {
+        (**self).put(key, value)
+    }
+
+    fn delete(&mut self, key: &[u8]) -> Result<()> {
+        (**self).delete(key)
+    }
+
+    fn write_batch(&mut self, batch: WriteBatch) -> Result<()> {
+        (**self).write_batch(batch)
+    }
+
+    fn prefix_iterator<'a>(&'a self, prefix: &'a [u8]) -> Result<StorageIterator<'a>> {
+        (**self).prefix_iterator(prefix)
+    }
+}
+
+/// Write operation of a batch
+#[derive(Clone, Debug, PartialEq)]
+pub enum WriteOp {
+    /// Put a value in the storage
+    Put(Vec<u8>, Vec<u8>),
+    /// Delete a value from the storage
+    Delete(Vec<u8>),
+}
+
+/// Batch of write operations applied atomically, in order
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WriteBatch {
+    ops: Vec<WriteOp>,
+}
+
+impl WriteBatch {
+    /// Add a put operation to the batch
+    pub fn put(&mut self, key: Vec<u8>, value: Vec<u8>) {
+        self.ops.push(WriteOp::Put(key, value));
+    }
+
+    /// Add a delete operation to the batch
+    pub fn delete(&mut self, key: Vec<u8>) {
+        self.ops.push(WriteOp::Delete(key));
+    }
+
+    /// Number of operations of the batch
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    /// Whether the batch has no operations
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+}
+
+impl IntoIterator for WriteBatch {
+    type Item = WriteOp;
+    type IntoIter = std::vec::IntoIter<WriteOp>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.ops.into_iter()
+    }
 }
@@ -0,0 +1,217 @@
+//! Fault injection for chaos testing
+//!
+//! Only available with the `chaos` feature. Chaos testing is enabled at run time by setting the
+//! `WITNET_CHAOS_SEED` environment variable. Then, the layers that support it randomly drop and
+//! delay network messages, return transient storage errors and skew the clock, so that the error
+//! paths of the node are exercised in integration tests. The faults are drawn from a random
+//! number generator seeded with `WITNET_CHAOS_SEED`, so a failing run can be reproduced.
+//!
+//! The rest of the settings are optional:
+//!
+//! | Environment variable                      | Default | Description                                      |
+//! |-------------------------------------------|---------|--------------------------------------------------|
+//! | `WITNET_CHAOS_DROP_PROBABILITY`           | `0.01`  | Probability of dropping a network message        |
+//! | `WITNET_CHAOS_MAX_DELAY_MS`               | `500`   | Maximum delay of a network message               |
+//! | `WITNET_CHAOS_STORAGE_ERROR_PROBABILITY`  | `0.01`  | Probability of a storage operation failing       |
+//! | `WITNET_CHAOS_MAX_CLOCK_SKEW_SECONDS`     | `5`     | Maximum skew of the clock, in either direction   |
+use std::{env, str::FromStr, sync::Arc, sync::Mutex, time::Duration};
+
+use lazy_static::lazy_static;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+lazy_static! {
+    static ref GLOBAL: Option<Arc<Chaos>> =
+        ChaosConfig::from_env().map(|config| Arc::new(Chaos::new(config)));
+}
+
+/// Chaos testing settings
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChaosConfig {
+    /// Seed of the random number generator the faults are drawn from
+    pub seed: u64,
+    /// Probability of dropping a network message
+    pub drop_probability: f64,
+    /// Maximum delay of a network message
+    pub max_delay: Duration,
+    /// Probability of a storage operation failing
+    pub storage_error_probability: f64,
+    /// Maximum skew of the clock in seconds, in either direction
+    pub max_clock_skew: i64,
+}
+
+impl ChaosConfig {
+    /// Read the settings from the environment, if `WITNET_CHAOS_SEED` is set
+    pub fn from_env() -> Option<Self> {
+        let seed = env_var("WITNET_CHAOS_SEED")?;
+
+        Some(ChaosConfig {
+            seed,
+            drop_probability: probability(env_var("WITNET_CHAOS_DROP_PROBABILITY").unwrap_or(0.01)),
+            max_delay: Duration::from_millis(env_var("WITNET_CHAOS_MAX_DELAY_MS").unwrap_or(500)),
+            storage_error_probability: probability(
+                env_var("WITNET_CHAOS_STORAGE_ERROR_PROBABILITY").unwrap_or(0.01),
+            ),
+            max_clock_skew: env_var::<i64>("WITNET_CHAOS_MAX_CLOCK_SKEW_SECONDS")
+                .unwrap_or(5)
+                .abs(),
+        })
+    }
+}
+
+/// Source of the faults to inject
+#[derive(Debug)]
+pub struct Chaos {
+    config: ChaosConfig,
+    rng: Mutex<StdRng>,
+    clock_skew: i64,
+}
+
+impl Chaos {
+    /// Create a fault source. The clock skew is drawn once, so that the skewed clock is still
+    /// monotonic.
+    pub fn new(config: ChaosConfig) -> Self {
+        let mut rng = StdRng::seed_from_u64(config.seed);
+        let clock_skew = rng.gen_range(-config.max_clock_skew, config.max_clock_skew + 1);
+
+        Chaos {
+            config,
+            rng: Mutex::new(rng),
+            clock_skew,
+        }
+    }
+
+    /// Fault source read from the environment, shared by all the layers. `None` unless
+    /// `WITNET_CHAOS_SEED` is set.
+    pub fn global() -> Option<Arc<Chaos>> {
+        GLOBAL.clone()
+    }
+
+    /// Settings of this fault source
+    pub fn config(&self) -> &ChaosConfig {
+        &self.config
+    }
+
+    /// Whether to drop the next network message
+    pub fn drop_message(&self) -> bool {
+        self.gen_bool(self.config.drop_probability)
+    }
+
+    /// Delay of the next network message, if any
+    pub fn message_delay(&self) -> Option<Duration> {
+        let max_delay = self.config.max_delay.as_secs() * 1000
+            + u64::from(self.config.max_delay.subsec_millis());
+        if max_delay == 0 {
+            return None;
+        }
+
+        let delay = self.rng.lock().unwrap().gen_range(0, max_delay + 1);
+        if delay == 0 {
+            None
+        } else {
+            Some(Duration::from_millis(delay))
+        }
+    }
+
+    /// Whether the next storage operation should fail
+    pub fn storage_error(&self) -> bool {
+        self.gen_bool(self.config.storage_error_probability)
+    }
+
+    /// Seconds to add to the clock
+    pub fn clock_skew(&self) -> i64 {
+        self.clock_skew
+    }
+
+    fn gen_bool(&self, probability: f64) -> bool {
+        self.rng.lock().unwrap().gen_bool(probability)
+    }
+}
+
+fn env_var<T: FromStr>(name: &str) -> Option<T> {
+    env::var(name).ok()?.parse().ok()
+}
+
+fn probability(p: f64) -> f64 {
+    if p.is_nan() || p < 0.0 {
+        0.0
+    } else if p > 1.0 {
+        1.0
+    } else {
+        p
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(seed: u64) -> ChaosConfig {
+        ChaosConfig {
+            seed,
+            drop_probability: 0.5,
+            max_delay: Duration::from_millis(100),
+            storage_error_probability: 0.5,
+            max_clock_skew: 10,
+        }
+    }
+
+    fn faults(chaos: &Chaos) -> Vec<(bool, Option<Duration>, bool)> {
+        (0..100)
+            .map(|_| {
+                (
+                    chaos.drop_message(),
+                    chaos.message_delay(),
+                    chaos.storage_error(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_same_faults() {
+        let chaos = Chaos::new(config(1));
+        let same_seed = Chaos::new(config(1));
+        let other_seed = Chaos::new(config(2));
+
+        assert_eq!(faults(&chaos), faults(&same_seed));
+        assert_ne!(faults(&chaos), faults(&other_seed));
+    }
+
+    #[test]
+    fn faults_within_bounds() {
+        for seed in 0..20 {
+            let chaos = Chaos::new(config(seed));
+
+            assert!(chaos.clock_skew().abs() <= 10);
+            for (_, delay, _) in faults(&chaos) {
+                if let Some(delay) = delay {
+                    assert!(delay <= Duration::from_millis(100));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn no_faults() {
+        let chaos = Chaos::new(ChaosConfig {
+            seed: 1,
+            drop_probability: 0.0,
+            max_delay: Duration::from_millis(0),
+            storage_error_probability: 0.0,
+            max_clock_skew: 0,
+        });
+
+        assert_eq!(chaos.clock_skew(), 0);
+        assert!(faults(&chaos)
+            .into_iter()
+            .all(|fault| fault == (false, None, false)));
+    }
+
+    #[test]
+    fn probability_is_clamped() {
+        assert_eq!(probability(-1.0), 0.0);
+        assert_eq!(probability(0.25), 0.25);
+        assert_eq!(probability(2.0), 1.0);
+        assert_eq!(probability("NaN".parse().unwrap()), 0.0);
+    }
+}
@@ -0,0 +1,36 @@
+use std::{io, path::Path};
+
+/// Function to get the number of bytes available to unprivileged users in the filesystem
+/// containing `path`
+#[cfg(unix)]
+pub fn available_space(path: &Path) -> io::Result<u64> {
+    use std::{ffi::CString, mem, os::unix::ffi::OsStrExt};
+
+    let path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let mut stat: libc::statvfs = unsafe { mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    // The type of the fields depends on the platform
+    #[allow(clippy::unnecessary_cast)]
+    Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+/// Function to get the number of bytes available to unprivileged users in the filesystem
+/// containing `path`
+#[cfg(not(unix))]
+pub fn available_space(_path: &Path) -> io::Result<u64> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "Available disk space is only supported in unix systems",
+    ))
+}
+
+#[cfg(unix)]
+#[test]
+fn available_space_test() {
+    assert!(available_space(Path::new(".")).is_ok());
+    assert!(available_space(Path::new("/this/path/does/not/exist")).is_err());
+}
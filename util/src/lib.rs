@@ -8,6 +8,16 @@
 #![deny(unused_mut)]
 #![deny(missing_docs)]
 
+/// Fault injection for chaos testing
+#[cfg(feature = "chaos")]
+pub mod chaos;
+
+/// Disk usage utilities
+pub mod disk;
+
+/// Clock offset measurement with SNTP
+pub mod ntp;
+
 /// Parse utilities
 pub mod parser;
 
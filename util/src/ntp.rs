@@ -0,0 +1,240 @@
+//! Library for measuring the offset of the local clock with SNTP
+//!
+//! With SNTP (RFC 4330), the client sends a request carrying the time it was sent, `t1`, and the
+//! server answers with that same time, the time it received the request, `t2`, and the time it
+//! sent the response, `t3`. With the time the response arrived, `t4`, the client estimates the
+//! offset of its clock as `((t2 - t1) + (t3 - t4)) / 2`, assuming that the request and the
+//! response take the same time to travel. Only the client side of the protocol is implemented.
+use failure::Fail;
+
+/// UDP port where the servers listen for NTP requests
+pub const NTP_PORT: u16 = 123;
+
+/// Length of the NTP packets without extensions
+pub const NTP_PACKET_LEN: usize = 48;
+
+/// Seconds between the NTP era 0 (1900-01-01) and the Unix epoch (1970-01-01)
+const NTP_UNIX_OFFSET: i64 = 2_208_988_800;
+
+const VERSION: u8 = 4;
+const MODE_CLIENT: u8 = 3;
+const MODE_SERVER: u8 = 4;
+/// Leap indicator of the servers whose clock is not synchronized
+const LEAP_ALARM: u8 = 3;
+
+/// The error type for the NTP responses
+#[derive(Debug, PartialEq, Fail)]
+pub enum NtpError {
+    /// The response is too short
+    #[fail(display = "Malformed NTP response")]
+    Malformed,
+    /// The response was not sent by a server
+    #[fail(display = "Unexpected NTP response with mode {}", mode)]
+    UnexpectedMode {
+        /// Mode of the response
+        mode: u8,
+    },
+    /// The response does not belong to the request
+    #[fail(display = "NTP response does not match the request")]
+    OriginMismatch,
+    /// The clock of the server is not synchronized, or the server asks the client to stop
+    /// sending requests (kiss-o'-death)
+    #[fail(display = "NTP server is not synchronized (stratum {})", stratum)]
+    Unsynchronized {
+        /// Stratum of the server, 0 for kiss-o'-death responses
+        stratum: u8,
+    },
+}
+
+/// Offset of the local clock measured with one server
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NtpSample {
+    /// Milliseconds to add to the local clock to get the time of the server
+    pub offset_millis: i64,
+    /// Round trip time of the request, in milliseconds
+    pub delay_millis: i64,
+}
+
+/// Build a request sent at `transmit_millis`, a Unix timestamp in milliseconds of the local clock
+pub fn request(transmit_millis: i64) -> Vec<u8> {
+    let mut packet = vec![0; NTP_PACKET_LEN];
+    packet[0] = (VERSION << 3) | MODE_CLIENT;
+    packet[40..48].copy_from_slice(&to_ntp_timestamp(transmit_millis).to_be_bytes());
+
+    packet
+}
+
+/// Parse the response to the request sent at `origin_millis`, which arrived at
+/// `destination_millis`, both Unix timestamps in milliseconds of the local clock
+pub fn parse_response(
+    response: &[u8],
+    origin_millis: i64,
+    destination_millis: i64,
+) -> Result<NtpSample, NtpError> {
+    if response.len() < NTP_PACKET_LEN {
+        Err(NtpError::Malformed)?
+    }
+    let mode = response[0] & 0x07;
+    if mode != MODE_SERVER {
+        Err(NtpError::UnexpectedMode { mode })?
+    }
+    let leap = response[0] >> 6;
+    let stratum = response[1];
+    if leap == LEAP_ALARM || stratum == 0 {
+        Err(NtpError::Unsynchronized { stratum })?
+    }
+    // The server copies the transmit timestamp of the request into the originate timestamp
+    if read_u64(&response[24..32]) != to_ntp_timestamp(origin_millis) {
+        Err(NtpError::OriginMismatch)?
+    }
+
+    let receive_millis = from_ntp_timestamp(read_u64(&response[32..40]));
+    let transmit_millis = from_ntp_timestamp(read_u64(&response[40..48]));
+
+    Ok(NtpSample {
+        offset_millis: ((receive_millis - origin_millis) + (transmit_millis - destination_millis))
+            / 2,
+        delay_millis: (destination_millis - origin_millis) - (transmit_millis - receive_millis),
+    })
+}
+
+/// Offset of the local clock according to several servers: the median of their samples, so
+/// that a minority of wrong servers cannot move the clock. `None` if there are no samples
+pub fn median_offset(samples: &[NtpSample]) -> Option<i64> {
+    let mut offsets: Vec<i64> = samples.iter().map(|sample| sample.offset_millis).collect();
+    offsets.sort();
+
+    match offsets.len() {
+        0 => None,
+        len if len % 2 == 1 => Some(offsets[len / 2]),
+        len => Some((offsets[len / 2 - 1] + offsets[len / 2]) / 2),
+    }
+}
+
+fn read_u64(bytes: &[u8]) -> u64 {
+    let mut buf = [0; 8];
+    buf.copy_from_slice(bytes);
+
+    u64::from_be_bytes(buf)
+}
+
+/// NTP timestamp: seconds since 1900 in the high 32 bits and the fraction of second in the low
+/// 32 bits
+fn to_ntp_timestamp(unix_millis: i64) -> u64 {
+    let seconds = (unix_millis / 1000 + NTP_UNIX_OFFSET) as u64;
+    // Rounded up, so that converting it back gives the same milliseconds
+    let fraction = ((((unix_millis % 1000) as u64) << 32) + 999) / 1000;
+
+    (seconds << 32) | fraction
+}
+
+fn from_ntp_timestamp(timestamp: u64) -> i64 {
+    let seconds = (timestamp >> 32) as i64 - NTP_UNIX_OFFSET;
+    let millis = ((timestamp & 0xffff_ffff) * 1000) >> 32;
+
+    seconds * 1000 + millis as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Response of a server whose clock is `offset` milliseconds ahead, to a request sent at
+    /// `origin`, which took `latency` milliseconds to arrive and to be answered
+    fn response(origin: i64, offset: i64, latency: i64) -> Vec<u8> {
+        let mut packet = vec![0; NTP_PACKET_LEN];
+        packet[0] = (VERSION << 3) | MODE_SERVER;
+        packet[1] = 2;
+        packet[24..32].copy_from_slice(&request(origin)[40..48]);
+        packet[32..40].copy_from_slice(&to_ntp_timestamp(origin + latency + offset).to_be_bytes());
+        packet[40..48].copy_from_slice(&to_ntp_timestamp(origin + latency + offset).to_be_bytes());
+
+        packet
+    }
+
+    #[test]
+    fn ntp_timestamps_roundtrip() {
+        let millis = 1_560_000_000_123;
+
+        assert_eq!(from_ntp_timestamp(to_ntp_timestamp(millis)), millis);
+        assert_eq!(to_ntp_timestamp(0) >> 32, NTP_UNIX_OFFSET as u64);
+    }
+
+    #[test]
+    fn request_format() {
+        let packet = request(1_560_000_000_500);
+
+        assert_eq!(packet.len(), NTP_PACKET_LEN);
+        assert_eq!(packet[0], 0x23);
+        assert_eq!(
+            from_ntp_timestamp(read_u64(&packet[40..48])),
+            1_560_000_000_500
+        );
+    }
+
+    #[test]
+    fn offset_and_delay() {
+        let origin = 1_560_000_000_000;
+        // The server is 1.5 seconds ahead, and each way takes 100 ms
+        let sample = parse_response(&response(origin, 1500, 100), origin, origin + 200).unwrap();
+
+        assert_eq!(
+            sample,
+            NtpSample {
+                offset_millis: 1500,
+                delay_millis: 200,
+            }
+        );
+
+        // The server is behind
+        let sample = parse_response(&response(origin, -700, 50), origin, origin + 100).unwrap();
+        assert_eq!(sample.offset_millis, -700);
+    }
+
+    #[test]
+    fn invalid_responses() {
+        let origin = 1_560_000_000_000;
+        let valid = response(origin, 0, 10);
+
+        assert_eq!(
+            parse_response(&valid[..40], origin, origin),
+            Err(NtpError::Malformed)
+        );
+        assert_eq!(
+            parse_response(&request(origin), origin, origin),
+            Err(NtpError::UnexpectedMode { mode: MODE_CLIENT })
+        );
+        assert_eq!(
+            parse_response(&valid, origin + 1000, origin + 1020),
+            Err(NtpError::OriginMismatch)
+        );
+
+        let mut kiss_of_death = valid.clone();
+        kiss_of_death[1] = 0;
+        assert_eq!(
+            parse_response(&kiss_of_death, origin, origin),
+            Err(NtpError::Unsynchronized { stratum: 0 })
+        );
+        let mut alarm = valid;
+        alarm[0] |= LEAP_ALARM << 6;
+        assert_eq!(
+            parse_response(&alarm, origin, origin),
+            Err(NtpError::Unsynchronized { stratum: 2 })
+        );
+    }
+
+    #[test]
+    fn median_of_samples() {
+        let samples: Vec<NtpSample> = [30, -10, 5000, 20]
+            .iter()
+            .map(|offset_millis| NtpSample {
+                offset_millis: *offset_millis,
+                delay_millis: 0,
+            })
+            .collect();
+
+        assert_eq!(median_offset(&[]), None);
+        assert_eq!(median_offset(&samples[..3]), Some(30));
+        assert_eq!(median_offset(&samples), Some(25));
+    }
+}
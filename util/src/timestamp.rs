@@ -1,21 +1,67 @@
+use std::sync::atomic::{AtomicI64, Ordering};
+
 use chrono::prelude::*;
 
+/// Milliseconds added to the clock of the system, as measured with NTP
+static CLOCK_OFFSET_MILLIS: AtomicI64 = AtomicI64::new(0);
+
+/// Function to set the offset of the clock of the system, in milliseconds, which is added to
+/// every timestamp from now on
+pub fn set_clock_offset_millis(offset: i64) {
+    CLOCK_OFFSET_MILLIS.store(offset, Ordering::Relaxed);
+}
+
+/// Function to get the offset of the clock of the system, in milliseconds
+pub fn clock_offset_millis() -> i64 {
+    CLOCK_OFFSET_MILLIS.load(Ordering::Relaxed)
+}
+
+/// Current UTC datetime, corrected with the clock offset
+fn now() -> DateTime<Utc> {
+    Utc::now() + chrono::Duration::milliseconds(clock_offset_millis())
+}
+
 /// Function to get timestamp from system as UTC Unix timestamp, seconds since Unix epoch
 pub fn get_timestamp() -> i64 {
     // Get UTC current datetime
-    let utc: DateTime<Utc> = Utc::now();
+    let utc: DateTime<Utc> = now();
 
     // Return number of non-leap seconds since Unix epoch
-    utc.timestamp()
+    utc.timestamp() + clock_skew()
 }
 
 /// Function to get timestamp from system as UTC Unix timestamp, seconds and nanoseconds since Unix epoch
 pub fn get_timestamp_nanos() -> (i64, u32) {
     // Get UTC current datetime
-    let utc: DateTime<Utc> = Utc::now();
+    let utc: DateTime<Utc> = now();
 
     // Return number of non-leap seconds since Unix epoch and the number of nanoseconds since the last second boundary
-    (utc.timestamp(), utc.timestamp_subsec_nanos())
+    (utc.timestamp() + clock_skew(), utc.timestamp_subsec_nanos())
+}
+
+/// Function to get timestamp from system as UTC Unix timestamp, milliseconds since Unix epoch
+pub fn get_timestamp_millis() -> i64 {
+    let (seconds, nanoseconds) = get_timestamp_nanos();
+
+    seconds * 1000 + i64::from(nanoseconds / 1_000_000)
+}
+
+/// Function to get timestamp from the clock of the system, without the clock offset, as UTC Unix
+/// timestamp, milliseconds since Unix epoch. This is the clock whose offset is measured
+pub fn get_local_timestamp_millis() -> i64 {
+    Utc::now().timestamp_millis() + clock_skew() * 1000
+}
+
+/// Seconds the clock is skewed by chaos testing
+#[cfg(feature = "chaos")]
+fn clock_skew() -> i64 {
+    crate::chaos::Chaos::global().map_or(0, |chaos| chaos.clock_skew())
+}
+
+/// Seconds the clock is skewed by chaos testing, which is disabled
+#[cfg(not(feature = "chaos"))]
+fn clock_skew() -> i64 {
+    0
 }
 
 /// Function for pretty printing a timestamp as a human friendly date and time
@@ -1,24 +1,30 @@
 use witnet_crypto::{
     hash::Sha256,
-    merkle::{merkle_tree_root as crypto_merkle_tree_root, ProgressiveMerkleTree},
-    signature::verify,
+    merkle::merkle_tree_root as crypto_merkle_tree_root,
+    signature::{verify, verify_tagged, BLOCK_DOMAIN, TRANSACTION_DOMAIN},
+    signature_cache::SignatureCache,
 };
 
 use std::collections::HashMap;
 use witnet_data_structures::{
     chain::{
-        Block, BlockInChain, CheckpointBeacon, Epoch, Hash, Hashable, Input, KeyedSignature,
-        Output, OutputPointer, RADRequest, Transaction, TransactionBody, TransactionType,
-        TransactionsPool, UnspentOutputsPool,
+        Block, BlockHeader, BlockInChain, CheckpointBeacon, Epoch, Hash, Hashable, Input,
+        KeyedSignature, Output, OutputPointer, PublicKey, PublicKeyHash, RADRequest, RADType,
+        SignedCheckpoint, Transaction, TransactionBody, TransactionType, TransactionsPool,
+        UnspentOutputsPool,
     },
-    data_request::DataRequestPool,
-    error::{BlockError, TransactionError},
+    data_request::{calculate_data_request_poe, DataRequestPool},
+    error::{BlockError, BlockValidationError, CheckpointError, TransactionError},
+    reputation::ReputationEngine,
     serializers::decoders::{TryFrom, TryInto},
+    wit::Wit,
 };
 
 use log;
 
-use witnet_rad::{run_consensus, script::unpack_radon_script, types::RadonTypes};
+use witnet_rad::{
+    pinning::FINGERPRINT_LENGTH, run_tally, script::unpack_radon_script, types::RadonTypes,
+};
 
 /// Calculate the sum of the values of the outputs pointed by the
 /// inputs of a transaction. If an input pointed-output is not
@@ -27,8 +33,8 @@ use witnet_rad::{run_consensus, script::unpack_radon_script, types::RadonTypes};
 pub fn transaction_inputs_sum(
     tx: &TransactionBody,
     pool: &UnspentOutputsPool,
-) -> Result<u64, failure::Error> {
-    let mut total_value = 0;
+) -> Result<Wit, failure::Error> {
+    let mut total_value = Wit::default();
 
     match transaction_tag(tx) {
         TransactionType::Commit => {
@@ -41,8 +47,10 @@ pub fn transaction_inputs_sum(
                     .ok_or_else(|| TransactionError::OutputNotFound {
                         output: input.output_pointer(),
                     })?
-                    .value();
-                total_value += pointed_value;
+                    .value()?;
+                total_value = total_value
+                    .checked_add(pointed_value)
+                    .ok_or(TransactionError::ValueOverflow)?;
             }
         }
     }
@@ -53,7 +61,7 @@ pub fn transaction_inputs_sum(
 fn calculate_commit_input(
     tx: &TransactionBody,
     pool: &UnspentOutputsPool,
-) -> Result<u64, failure::Error> {
+) -> Result<Wit, failure::Error> {
     match &tx.inputs[0] {
         Input::DataRequest(dr_input) => {
             // Get DataRequest information
@@ -65,7 +73,10 @@ fn calculate_commit_input(
                 })?;
 
             match dr_output {
-                Output::DataRequest(dr_state) => Ok(dr_state.value / u64::from(dr_state.witnesses)),
+                Output::DataRequest(dr_state) => Ok(dr_state
+                    .value
+                    .checked_div(u64::from(dr_state.witnesses))
+                    .ok_or(TransactionError::InsufficientWitnesses)?),
                 _ => Err(TransactionError::InvalidCommitTransaction)?,
             }
         }
@@ -74,8 +85,14 @@ fn calculate_commit_input(
 }
 
 /// Calculate the sum of the values of the outputs of a transaction.
-pub fn transaction_outputs_sum(tx: &TransactionBody) -> u64 {
-    tx.outputs.iter().map(Output::value).sum()
+pub fn transaction_outputs_sum(tx: &TransactionBody) -> Result<Wit, TransactionError> {
+    let values = tx
+        .outputs
+        .iter()
+        .map(Output::value)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Wit::checked_sum(values).ok_or(TransactionError::ValueOverflow)
 }
 
 /// Returns the fee of a transaction.
@@ -87,15 +104,13 @@ pub fn transaction_outputs_sum(tx: &TransactionBody) -> u64 {
 pub fn transaction_fee(
     tx: &TransactionBody,
     pool: &UnspentOutputsPool,
-) -> Result<u64, failure::Error> {
+) -> Result<Wit, failure::Error> {
     let in_value = transaction_inputs_sum(tx, pool)?;
-    let out_value = transaction_outputs_sum(tx);
+    let out_value = transaction_outputs_sum(tx)?;
 
-    if out_value > in_value {
-        Err(TransactionError::NegativeFee)?
-    } else {
-        Ok(in_value - out_value)
-    }
+    Ok(in_value
+        .checked_sub(out_value)
+        .ok_or(TransactionError::NegativeFee)?)
 }
 
 /// Returns `true` if the transaction classifies as a _mint
@@ -108,14 +123,14 @@ pub fn transaction_is_mint(tx: &TransactionBody) -> bool {
 /// Function to validate a mint transaction
 pub fn validate_mint_transaction(
     tx: &TransactionBody,
-    total_fees: u64,
-    block_reward: u64,
+    total_fees: Wit,
+    block_reward: Wit,
 ) -> Result<(), failure::Error> {
-    let mint_value = transaction_outputs_sum(tx);
+    let mint_value = transaction_outputs_sum(tx)?;
 
     if !transaction_is_mint(tx) {
         Err(TransactionError::InvalidMintTransaction)?
-    } else if mint_value != total_fees + block_reward {
+    } else if Some(mint_value) != total_fees.checked_add(block_reward) {
         Err(BlockError::MismatchedMintValue {
             mint_value,
             fees_value: total_fees,
@@ -130,6 +145,18 @@ pub fn validate_mint_transaction(
 pub fn validate_rad_request(rad_request: &RADRequest) -> Result<(), failure::Error> {
     let retrieval_paths = &rad_request.retrieve;
     for path in retrieval_paths {
+        if path.kind == RADType::HttpGet && !path.body.is_empty() {
+            Err(TransactionError::BodyInGetRetrieval {
+                url: path.url.clone(),
+            })?
+        }
+        let fingerprint_length = path.certificate_fingerprint.len();
+        if fingerprint_length != 0 && fingerprint_length != FINGERPRINT_LENGTH {
+            Err(TransactionError::InvalidCertificateFingerprint {
+                url: path.url.clone(),
+                length: fingerprint_length,
+            })?
+        }
         unpack_radon_script(path.script.as_slice())?;
     }
 
@@ -153,7 +180,7 @@ pub fn validate_consensus(
         .filter_map(|input| RadonTypes::try_from(input.as_slice()).ok())
         .collect();
 
-    let local_tally = run_consensus(radon_types_vec, tally_stage)?;
+    let local_tally = run_tally(radon_types_vec, tally_stage)?;
 
     if local_tally == miner_tally {
         Ok(())
@@ -182,23 +209,25 @@ pub fn validate_dr_transaction(tx: &TransactionBody) -> Result<(), failure::Erro
             Err(TransactionError::InsufficientWitnesses)?
         }
 
-        let witnesses = i64::from(dr_output.witnesses);
-        let dr_value = dr_output.value as i64;
-        let commit_fee = dr_output.commit_fee as i64;
-        let reveal_fee = dr_output.reveal_fee as i64;
-        let tally_fee = dr_output.tally_fee as i64;
-
-        if ((dr_value - tally_fee) % witnesses) != 0 {
-            Err(TransactionError::InvalidDataRequestValue {
-                dr_value,
-                witnesses,
-            })?
-        }
-
-        let witness_reward = ((dr_value - tally_fee) / witnesses) - commit_fee - reveal_fee;
-        if witness_reward <= 0 {
+        // The value left after paying the tally fee must be evenly split among the witnesses
+        let witnesses = u64::from(dr_output.witnesses);
+        let witnesses_value = match dr_output.value.checked_sub(dr_output.tally_fee) {
+            Some(value) if value.nanowits() % witnesses == 0 => value,
+            _ => Err(TransactionError::InvalidDataRequestValue {
+                dr_value: dr_output.value,
+                witnesses: dr_output.witnesses,
+            })?,
+        };
+
+        let witness_reward = witnesses_value.checked_div(witnesses).unwrap_or_default();
+        let fees = dr_output
+            .commit_fee
+            .checked_add(dr_output.reveal_fee)
+            .ok_or(TransactionError::ValueOverflow)?;
+        if witness_reward <= fees {
             Err(TransactionError::InvalidDataRequestReward {
                 reward: witness_reward,
+                fees,
             })?
         }
 
@@ -229,12 +258,13 @@ pub struct WitnessesCount {
 }
 pub type WitnessesCounter<S> = HashMap<OutputPointer, WitnessesCount, S>;
 
-/// Function to validate a commit transaction
+/// Function to validate a commit transaction signed by the identity `pkh`
 pub fn validate_commit_transaction<S: ::std::hash::BuildHasher>(
     tx: &TransactionBody,
+    pkh: &PublicKeyHash,
     dr_pool: &DataRequestPool,
     block_commits: &mut WitnessesCounter<S>,
-    fee: u64,
+    fee: Wit,
 ) -> Result<(), failure::Error> {
     if (tx.inputs.len() != 1) || (tx.outputs.len() != 1) {
         Err(TransactionError::InvalidCommitTransaction)?
@@ -242,12 +272,6 @@ pub fn validate_commit_transaction<S: ::std::hash::BuildHasher>(
 
     match &tx.inputs[0] {
         Input::DataRequest(dr_input) => {
-            // TODO: Complete PoE validation
-            let _poe = dr_input.poe;
-            if !verify_poe_data_request() {
-                Err(TransactionError::InvalidDataRequestPoe)?
-            }
-
             // Get DataRequest information
             let dr_pointer = dr_input.output_pointer();
             let dr_state = dr_pool.data_request_pool.get(&dr_pointer).ok_or(
@@ -256,6 +280,16 @@ pub fn validate_commit_transaction<S: ::std::hash::BuildHasher>(
                 },
             )?;
 
+            if !verify_poe_data_request(
+                &dr_pool.reputation_engine,
+                &dr_pointer,
+                dr_state.data_request.witnesses,
+                pkh,
+                dr_input.poe,
+            ) {
+                Err(TransactionError::InvalidDataRequestPoe)?
+            }
+
             // Validate fee
             let expected_commit_fee = dr_state.data_request.commit_fee;
             if fee != expected_commit_fee {
@@ -282,7 +316,7 @@ pub fn validate_commit_transaction<S: ::std::hash::BuildHasher>(
 pub fn validate_reveal_transaction(
     tx: &TransactionBody,
     dr_pool: &DataRequestPool,
-    fee: u64,
+    fee: Wit,
 ) -> Result<(), failure::Error> {
     if (tx.inputs.len() != 1) || (tx.outputs.len() != 1) {
         Err(TransactionError::InvalidRevealTransaction)?
@@ -325,7 +359,7 @@ pub fn validate_tally_transaction(
     tx: &TransactionBody,
     dr_pool: &DataRequestPool,
     utxo: &UnspentOutputsPool,
-    fee: u64,
+    fee: Wit,
 ) -> Result<(()), failure::Error> {
     if (tx.outputs.len() - tx.inputs.len()) != 1 {
         Err(TransactionError::InvalidTallyTransaction)?
@@ -409,10 +443,32 @@ pub fn validate_block_signature(block: &Block) -> Result<(), failure::Error> {
 
     let Hash::SHA256(message) = block.block_header.beacon.hash();
 
-    verify(&public_key, &message, &signature)
+    verify_tagged(&public_key, BLOCK_DOMAIN, &message, &signature)
         .map_err(|_| BlockError::VerifySignatureFail { hash: block.hash() }.into())
 }
 
+/// Function to validate the signature of a checkpoint, which must be signed by one of the
+/// `trusted_keys`
+pub fn validate_checkpoint_signature(
+    signed_checkpoint: &SignedCheckpoint,
+    trusted_keys: &[PublicKey],
+) -> Result<(), failure::Error> {
+    let keyed_signature = &signed_checkpoint.signature;
+    let checkpoint = signed_checkpoint.beacon.checkpoint;
+
+    if !trusted_keys.contains(&keyed_signature.public_key) {
+        Err(CheckpointError::UntrustedSigner { checkpoint })?
+    }
+
+    let signature = keyed_signature.signature.clone().try_into()?;
+    let public_key = keyed_signature.public_key.clone().try_into()?;
+
+    let Hash::SHA256(message) = signed_checkpoint.beacon.hash();
+
+    verify(&public_key, &message, &signature)
+        .map_err(|_| CheckpointError::VerifySignatureFail { checkpoint }.into())
+}
+
 /// Function to validate a pkh signature
 pub fn validate_pkh_signature(
     _input: &Input,
@@ -423,8 +479,12 @@ pub fn validate_pkh_signature(
     Ok(())
 }
 
-/// Function to validate transaction signatures
-pub fn validate_transaction_signatures(transaction: &Transaction) -> Result<(), failure::Error> {
+/// Function to validate transaction signatures. Successful verifications are remembered in
+/// `signature_cache`, so that they are not repeated when the transaction is validated again
+pub fn validate_transaction_signatures(
+    transaction: &Transaction,
+    signature_cache: &mut SignatureCache,
+) -> Result<(), failure::Error> {
     let signatures = &transaction.signatures;
     let inputs = &transaction.body.inputs;
 
@@ -441,12 +501,12 @@ pub fn validate_transaction_signatures(transaction: &Transaction) -> Result<(),
         let public_key = tx_keyed_signature.public_key.clone().try_into()?;
         let Hash::SHA256(message) = transaction.hash();
 
-        verify(&public_key, &message, &signature).map_err(|_| {
-            TransactionError::VerifyTransactionSignatureFail {
+        signature_cache
+            .verify_tagged(&public_key, TRANSACTION_DOMAIN, &message, &signature)
+            .map_err(|_| TransactionError::VerifyTransactionSignatureFail {
                 hash: transaction.hash(),
                 index: 0,
-            }
-        })?;
+            })?;
     } else {
         Err(TransactionError::SignatureNotFound)?
     }
@@ -464,8 +524,9 @@ pub fn validate_transaction<S: ::std::hash::BuildHasher>(
     utxo_set: &UnspentOutputsPool,
     dr_pool: &DataRequestPool,
     block_commits: &mut WitnessesCounter<S>,
-) -> Result<u64, failure::Error> {
-    validate_transaction_signatures(&transaction)?;
+    signature_cache: &mut SignatureCache,
+) -> Result<Wit, failure::Error> {
+    validate_transaction_signatures(&transaction, signature_cache)?;
 
     match transaction_tag(&transaction.body) {
         TransactionType::Mint => Err(TransactionError::UnexpectedMint)?,
@@ -488,7 +549,13 @@ pub fn validate_transaction<S: ::std::hash::BuildHasher>(
             log::debug!("Commit Transaction validation");
             let fee = transaction_fee(&transaction.body, utxo_set)?;
 
-            validate_commit_transaction(&transaction.body, dr_pool, block_commits, fee)?;
+            // The signature has already been validated, so this is the identity of the witness
+            let pkh = transaction
+                .signatures
+                .get(0)
+                .map(|signature| PublicKeyHash::from_public_key(&signature.public_key))
+                .ok_or(TransactionError::SignatureNotFound)?;
+            validate_commit_transaction(&transaction.body, &pkh, dr_pool, block_commits, fee)?;
             Ok(fee)
         }
         TransactionType::Reveal => {
@@ -508,87 +575,77 @@ pub fn validate_transaction<S: ::std::hash::BuildHasher>(
     }
 }
 
-/// Function to validate transactions in a block and update a utxo_set and a `TransactionsPool`
+/// Function to validate transactions in a block and update a `DataRequestPool`.
+/// The unspent outputs are updated on a copy of `utxo_set`, because transactions can spend
+/// outputs created by previous transactions of the same block.
 pub fn validate_transactions(
     utxo_set: &UnspentOutputsPool,
     _txn_pool: &TransactionsPool,
     data_request_pool: &DataRequestPool,
     block: &Block,
-) -> Result<BlockInChain, failure::Error> {
-    // Init Progressive merkle tree
-    let mut mt = ProgressiveMerkleTree::sha256();
-
-    match block.txns.get(0).map(|tx| {
-        let Hash::SHA256(sha) = tx.hash();
-        mt.push(Sha256(sha));
-        transaction_tag(&tx.body)
-    }) {
+    signature_cache: &mut SignatureCache,
+) -> Result<BlockInChain, BlockValidationError> {
+    match block.txns.get(0).map(|tx| transaction_tag(&tx.body)) {
         Some(TransactionType::Mint) => (),
-        _ => Err(BlockError::NoMint)?,
+        _ => Err(BlockValidationError::Mint(BlockError::NoMint.into()))?,
     }
 
     let mut utxo_set = utxo_set.clone();
     let mut data_request_pool = data_request_pool.clone();
-    let mut remove_later = vec![];
 
     let mut commits_number: WitnessesCounter<_> = HashMap::new();
 
     // Init total fee
-    let mut total_fee = 0;
+    let mut total_fee = Wit::default();
 
-    // TODO: replace for loop with a try_fold
-    for transaction in &block.txns[1..] {
-        match validate_transaction(
+    for (index, transaction) in block.txns.iter().enumerate().skip(1) {
+        let txn_hash = transaction.hash();
+        let invalid_transaction = |error| BlockValidationError::Transaction {
+            index,
+            hash: txn_hash,
+            error,
+        };
+
+        let fee = validate_transaction(
             &transaction,
             &utxo_set,
             &data_request_pool,
             &mut commits_number,
-        ) {
-            Ok(fee) => {
-                // Add transaction fee
-                total_fee += fee;
-
-                // Add new hash to merkle tree
-                let txn_hash = transaction.hash();
-                let Hash::SHA256(sha) = txn_hash;
-                mt.push(Sha256(sha));
-
-                for input in &transaction.body.inputs {
-                    // Obtain the OuputPointer of each input and remove it from the utxo_set
-                    let output_pointer = input.output_pointer();
-                    match input {
-                        Input::DataRequest(..) => {
-                            remove_later.push(output_pointer);
-                        }
-                        _ => {
-                            utxo_set.remove(&output_pointer);
-                        }
-                    }
-                }
-
-                for (index, output) in transaction.body.outputs.iter().enumerate() {
-                    // Add the new outputs to the utxo_set
-                    let output_pointer = OutputPointer {
-                        transaction_id: txn_hash,
-                        output_index: index as u32,
-                    };
+            signature_cache,
+        )
+        .map_err(invalid_transaction)?;
+
+        // Add transaction fee
+        total_fee = total_fee
+            .checked_add(fee)
+            .ok_or_else(|| invalid_transaction(TransactionError::ValueOverflow.into()))?;
+
+        for input in &transaction.body.inputs {
+            // Obtain the OuputPointer of each input and remove it from the utxo_set.
+            // Data request outputs are spent by several commits of the same block, so
+            // they are kept until the block is applied
+            if let Input::DataRequest(..) = input {
+                continue;
+            }
+            utxo_set.remove(&input.output_pointer());
+        }
 
-                    utxo_set.insert(output_pointer, output.clone());
-                }
+        for (index, output) in transaction.body.outputs.iter().enumerate() {
+            // Add the new outputs to the utxo_set
+            let output_pointer = OutputPointer {
+                transaction_id: txn_hash,
+                output_index: index as u32,
+            };
 
-                // Add DataRequests from the block into the data_request_pool
-                data_request_pool.process_transaction(
-                    transaction,
-                    block.block_header.beacon.checkpoint,
-                    &block.hash(),
-                );
-            }
-            Err(e) => Err(e)?,
+            utxo_set.insert(output_pointer, output.clone());
         }
-    }
 
-    for output_pointer in remove_later {
-        utxo_set.remove(&output_pointer);
+        // Add DataRequests from the block into the data_request_pool
+        data_request_pool.process_transaction(
+            transaction,
+            block.block_header.beacon.checkpoint,
+            &block.hash(),
+        );
     }
 
     // Validate mint
@@ -596,49 +653,35 @@ pub fn validate_transactions(
         &block.txns[0].body,
         total_fee,
         block_reward(block.block_header.beacon.checkpoint),
-    )?;
-
-    // Insert mint in utxo
-    let mint_output_pointer = OutputPointer {
-        transaction_id: block.txns[0].hash(),
-        output_index: 0,
-    };
-    let mint_output = block.txns[0].body.outputs[0].clone();
-    utxo_set.insert(mint_output_pointer, mint_output);
+    )
+    .map_err(BlockValidationError::Mint)?;
 
     // Validate commits number
     for WitnessesCount { current, target } in commits_number.values() {
         if current != target {
-            Err(BlockError::MismatchingCommitsNumber {
-                commits: *current,
-                rf: *target,
-            })?
+            Err(BlockValidationError::Commits(
+                BlockError::MismatchingCommitsNumber {
+                    commits: *current,
+                    rf: *target,
+                },
+            ))?
         }
     }
 
-    // Validate Merkle Root
-    let Hash::SHA256(mr) = block.block_header.hash_merkle_root;
-    if mt.root() != Sha256(mr) {
-        Err(BlockError::NotValidMerkleTree)?
-    }
-
     Ok(BlockInChain {
         block: block.clone(),
-        utxo_set,
         data_request_pool,
     })
 }
 
-/// Function to validate a block
-pub fn validate_block(
+/// Function to validate that the beacon of a block extends `chain_beacon`, the beacon of the tip
+/// of the chain, no later than `current_epoch`
+pub fn validate_block_beacon(
     block: &Block,
     current_epoch: Epoch,
     chain_beacon: CheckpointBeacon,
     genesis_block_hash: Hash,
-    utxo_set: &UnspentOutputsPool,
-    txn_pool: &TransactionsPool,
-    data_request_pool: &DataRequestPool,
-) -> Result<BlockInChain, failure::Error> {
+) -> Result<(), BlockError> {
     let block_epoch = block.block_header.beacon.checkpoint;
     let hash_prev_block = block.block_header.beacon.hash_prev_block;
 
@@ -646,40 +689,152 @@ pub fn validate_block(
         Err(BlockError::BlockFromFuture {
             block_epoch,
             current_epoch,
-        })?
+        })
     } else if chain_beacon.checkpoint > block_epoch {
         Err(BlockError::BlockOlderThanTip {
             chain_epoch: chain_beacon.checkpoint,
             block_epoch,
-        })?
+        })
     } else if hash_prev_block != genesis_block_hash
         && chain_beacon.hash_prev_block != hash_prev_block
     {
         Err(BlockError::PreviousHashNotKnown {
             hash: hash_prev_block,
-        })?
-    } else if !verify_poe_block() {
-        Err(BlockError::NotValidPoe)?
+        })
     } else {
-        validate_block_signature(&block)?;
+        Ok(())
+    }
+}
+
+/// Function to validate the parts of a block which do not depend on the state of the chain: the
+/// leadership proof and the merkle root of the transactions. Returns the output of the VRF of the
+/// leadership proof, which tells whether the miner was eligible given the state of the chain
+pub fn validate_block_integrity(block: &Block) -> Result<Hash, BlockValidationError> {
+    let vrf_output = verify_vrf_block(block).map_err(BlockValidationError::LeadershipProof)?;
+    validate_block_signature(block).map_err(BlockValidationError::LeadershipProof)?;
+
+    let expected = merkle_tree_root(&block.txns);
+    let found = block.block_header.hash_merkle_root;
+    if expected != found {
+        Err(BlockValidationError::MerkleRoot { expected, found })?
+    }
+
+    Ok(vrf_output)
+}
+
+/// Function to validate a block. The validation goes through these stages, from the cheapest
+/// one to the most expensive one, and stops at the first one that fails:
+///
+/// 1. The beacon extends the tip of the chain, no later than the current epoch
+/// 2. The leadership proof is valid
+/// 3. The merkle root of the header commits to the transactions of the block
+/// 4. The leadership proof makes the miner eligible, according to the active identities and their
+///    reputation in the reputation engine of `data_request_pool`
+/// 5. Every transaction is valid, spending outputs of the UTXO set or of the previous
+///    transactions of the block
+/// 6. The mint transaction creates the block reward plus the fees of the transactions, and the
+///    data requests have the commits they require
+pub fn validate_block(
+    block: &Block,
+    current_epoch: Epoch,
+    chain_beacon: CheckpointBeacon,
+    genesis_block_hash: Hash,
+    utxo_set: &UnspentOutputsPool,
+    txn_pool: &TransactionsPool,
+    data_request_pool: &DataRequestPool,
+    signature_cache: &mut SignatureCache,
+) -> Result<BlockInChain, BlockValidationError> {
+    validate_block_beacon(block, current_epoch, chain_beacon, genesis_block_hash)
+        .map_err(BlockValidationError::Beacon)?;
+
+    let vrf_output = validate_block_integrity(block)?;
+    let miner = PublicKeyHash::from_public_key(&block.proof.block_sig.public_key);
+    let target = calculate_block_target(&data_request_pool.reputation_engine, &miner);
+    if !is_block_eligible(vrf_output, target) {
+        Err(BlockValidationError::Eligibility(BlockError::NotEligible))?
+    }
 
-        validate_transactions(&utxo_set, &txn_pool, &data_request_pool, &block)
+    validate_transactions(
+        &utxo_set,
+        &txn_pool,
+        &data_request_pool,
+        &block,
+        signature_cache,
+    )
+}
+
+/// Function to validate a block header downloaded during the synchronization, before its block.
+/// The header must extend `prev_beacon`, the beacon of the previous block of the chain, from a
+/// later epoch. Only the block following the genesis block, which has no epoch of its own, can be
+/// at epoch 0. The leadership proof is not part of the header, so it is validated with the rest
+/// of the block.
+pub fn validate_block_header(
+    header: &BlockHeader,
+    current_epoch: Epoch,
+    prev_beacon: CheckpointBeacon,
+    genesis_block_hash: Hash,
+) -> Result<(), failure::Error> {
+    let block_epoch = header.beacon.checkpoint;
+    let hash_prev_block = header.beacon.hash_prev_block;
+    let extends_genesis = prev_beacon.hash_prev_block == genesis_block_hash;
+
+    if block_epoch > current_epoch {
+        Err(BlockError::BlockFromFuture {
+            block_epoch,
+            current_epoch,
+        })?
+    } else if prev_beacon.checkpoint > block_epoch
+        || (prev_beacon.checkpoint == block_epoch && !extends_genesis)
+    {
+        Err(BlockError::BlockOlderThanTip {
+            chain_epoch: prev_beacon.checkpoint,
+            block_epoch,
+        })?
+    } else if prev_beacon.hash_prev_block != hash_prev_block {
+        Err(BlockError::PreviousHashNotKnown {
+            hash: hash_prev_block,
+        })?
+    } else {
+        Ok(())
     }
 }
 
-/// Function to validate a block candidate
-pub fn validate_candidate(block: &Block, current_epoch: Epoch) -> Result<(), failure::Error> {
+/// Function to validate a chain of block headers extending `chain_beacon`. Returns the beacon of
+/// the last header, which the next headers must extend
+pub fn validate_header_chain(
+    headers: &[BlockHeader],
+    current_epoch: Epoch,
+    chain_beacon: CheckpointBeacon,
+    genesis_block_hash: Hash,
+) -> Result<CheckpointBeacon, failure::Error> {
+    headers
+        .iter()
+        .try_fold(chain_beacon, |prev_beacon, header| {
+            validate_block_header(header, current_epoch, prev_beacon, genesis_block_hash)?;
+
+            Ok(CheckpointBeacon {
+                checkpoint: header.beacon.checkpoint,
+                hash_prev_block: header.hash(),
+            })
+        })
+}
+
+/// Function to validate a block candidate, which must extend the tip of the chain whose reputation
+/// engine is `reputation_engine`
+pub fn validate_candidate(
+    block: &Block,
+    current_epoch: Epoch,
+    reputation_engine: &ReputationEngine,
+) -> Result<(), failure::Error> {
     let block_epoch = block.block_header.beacon.checkpoint;
 
-    if !verify_poe_block() {
-        Err(BlockError::NotValidPoe)?
-    } else if block_epoch != current_epoch {
+    if block_epoch != current_epoch {
         Err(BlockError::CandidateFromDifferentEpoch {
             block_epoch,
             current_epoch,
         })?
     } else {
-        Ok(())
+        verify_poe_block(block, reputation_engine)
     }
 }
 
@@ -724,53 +879,722 @@ pub fn validate_merkle_tree(block: &Block) -> bool {
     merkle_tree == merkle_tree_root(transactions)
 }
 
-/// 1 satowit is the minimal unit of value
-/// 1 wit = 100_000_000 satowits
-pub const SATOWITS_PER_WIT: u64 = 100_000_000;
+/// Block mining reward before the first halving, in nanowits. It keeps the raw amount it had when
+/// amounts were counted in units of 10^-8 wits, so the emission is not changed by the units.
+pub const INITIAL_BLOCK_REWARD: u64 = 50_000_000_000;
 
 /// Calculate the block mining reward.
-/// Returns "satowits", where 1 wit = 100_000_000 satowits.
-pub fn block_reward(epoch: Epoch) -> u64 {
-    let initial_reward: u64 = 500 * SATOWITS_PER_WIT;
+pub fn block_reward(epoch: Epoch) -> Wit {
     let halvings = epoch / 1_750_000;
     if halvings < 64 {
-        initial_reward >> halvings
+        Wit::from_nanowits(INITIAL_BLOCK_REWARD >> halvings)
+    } else {
+        Wit::default()
+    }
+}
+
+/// Number of identities expected to be eligible to mine the block of every epoch
+pub const MINING_REPLICATION_FACTOR: u64 = 4;
+
+/// Number of identities expected to be eligible to commit to a data request for every witness it
+/// requires, so that it gets enough commitments even if some of them fail to commit
+pub const WITNESS_REPLICATION_FACTOR: u64 = 2;
+
+/// Weight of the identity `pkh` in the eligibility, out of the total weight of the active
+/// identities: one for being active, plus its reputation if it is active, so that every identity
+/// has some chance of being eligible but reputed identities are more likely to be
+fn eligibility_weight(reputation_engine: &ReputationEngine, pkh: &PublicKeyHash) -> (u64, u64) {
+    let active_identities = reputation_engine.active_identities() as u64;
+    let total_weight =
+        active_identities.saturating_add(reputation_engine.total_active_reputation());
+    let weight = if reputation_engine.is_active(pkh) {
+        reputation_engine.reputation(pkh).saturating_add(1)
     } else {
-        0
+        1
+    };
+
+    (weight, total_weight)
+}
+
+/// Function to calculate the target which the outputs of the VRF of the identity `pkh` must not
+/// exceed to make it eligible to mine a block, so that about `MINING_REPLICATION_FACTOR` of the
+/// active identities of `reputation_engine` are eligible in every epoch, weighted by their
+/// reputation. While there are no more active identities than that, e.g. before the first data
+/// requests are resolved, every identity is eligible
+pub fn calculate_block_target(reputation_engine: &ReputationEngine, pkh: &PublicKeyHash) -> u64 {
+    let (weight, total_weight) = eligibility_weight(reputation_engine, pkh);
+
+    eligibility_target(weight, total_weight, MINING_REPLICATION_FACTOR)
+}
+
+/// Function to calculate the target which the proof of eligibility of the identity `pkh` must not
+/// exceed to make it eligible to commit to a data request requiring `witnesses`, so that about
+/// `WITNESS_REPLICATION_FACTOR` identities are eligible for every witness, weighted by their
+/// reputation
+pub fn calculate_data_request_target(
+    reputation_engine: &ReputationEngine,
+    pkh: &PublicKeyHash,
+    witnesses: u16,
+) -> u64 {
+    let (weight, total_weight) = eligibility_weight(reputation_engine, pkh);
+
+    eligibility_target(
+        weight,
+        total_weight,
+        u64::from(witnesses) * WITNESS_REPLICATION_FACTOR,
+    )
+}
+
+/// Target for an identity with `weight` out of `total_weight`, so that about
+/// `replication_factor` identities are eligible. The target saturates, so the identity is always
+/// eligible if `weight * replication_factor` is at least `total_weight`
+fn eligibility_target(weight: u64, total_weight: u64, replication_factor: u64) -> u64 {
+    let target = u128::from(u64::max_value()) * u128::from(weight) * u128::from(replication_factor)
+        / u128::from(total_weight.max(1));
+
+    target.min(u128::from(u64::max_value())) as u64
+}
+
+/// Function to check if the output of the VRF of a leadership proof makes the miner eligible:
+/// its first 8 bytes, as a big endian integer, must not exceed `target`
+pub fn is_block_eligible(vrf_output: Hash, target: u64) -> bool {
+    let Hash::SHA256(bytes) = vrf_output;
+
+    is_below_target(&bytes, target)
+}
+
+/// Whether the first 8 bytes of `bytes`, as a big endian integer, do not exceed `target`
+fn is_below_target(bytes: &[u8; 32], target: u64) -> bool {
+    let mut prefix = [0; 8];
+    prefix.copy_from_slice(&bytes[..8]);
+
+    u64::from_be_bytes(prefix) <= target
+}
+
+/// Function to verify the VRF proof of the leadership proof of a block: a valid VRF proof of the
+/// hash of its beacon, made with the key which signed the block. Returns the output of the VRF
+pub fn verify_vrf_block(block: &Block) -> Result<Hash, failure::Error> {
+    let vrf_proof = &block.proof.proof;
+    if vrf_proof.public_key != block.proof.block_sig.public_key {
+        Err(BlockError::NotValidPoe)?
     }
+
+    let Hash::SHA256(message) = block.block_header.beacon.hash();
+    let vrf_output = vrf_proof
+        .verify(&message)
+        .map_err(|_| BlockError::NotValidPoe)?;
+
+    Ok(vrf_output)
 }
 
-/// Function to check poe validation for blocks
-// TODO: Implement logic for this function
-pub fn verify_poe_block() -> bool {
-    true
+/// Function to check the proof of eligibility of a block: a valid VRF proof whose output makes
+/// the miner eligible, according to the active identities of `reputation_engine` and their
+/// reputation
+pub fn verify_poe_block(
+    block: &Block,
+    reputation_engine: &ReputationEngine,
+) -> Result<(), failure::Error> {
+    let vrf_output = verify_vrf_block(block)?;
+    let miner = PublicKeyHash::from_public_key(&block.proof.block_sig.public_key);
+    let target = calculate_block_target(reputation_engine, &miner);
+    if !is_block_eligible(vrf_output, target) {
+        Err(BlockError::NotEligible)?
+    }
+
+    Ok(())
 }
 
-/// Function to check poe validation for data requests
-// TODO: Implement logic for this function
-pub fn verify_poe_data_request() -> bool {
-    true
+/// Function to check the proof of eligibility `poe` of the identity `pkh` to commit to the data
+/// request of `dr_pointer`, which requires `witnesses`: it must be the one of that identity for
+/// that data request, and its first 8 bytes, as a big endian integer, must not exceed the target
+/// of the identity according to `reputation_engine`
+pub fn verify_poe_data_request(
+    reputation_engine: &ReputationEngine,
+    dr_pointer: &OutputPointer,
+    witnesses: u16,
+    pkh: &PublicKeyHash,
+    poe: [u8; 32],
+) -> bool {
+    if poe != calculate_data_request_poe(dr_pointer, pkh) {
+        return false;
+    }
+
+    let target = calculate_data_request_target(reputation_engine, pkh, witnesses);
+
+    is_below_target(&poe, target)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use secp256k1::{
+        PublicKey as Secp256k1_PublicKey, Secp256k1, SecretKey, Signature as Secp256k1_Signature,
+    };
+    use witnet_crypto::signature::{sign, sign_tagged};
+    use witnet_data_structures::chain::{
+        LeadershipProof, ValueTransferInput, ValueTransferOutput, VrfProof,
+    };
 
     #[test]
     fn test_block_reward() {
-        // Satowits per wit
+        // Units of 10^-8 wits, in which the raw amounts of the reward were defined
         let spw = 100_000_000;
+        let reward = Wit::from_nanowits;
+
+        assert_eq!(block_reward(0), reward(500 * spw));
+        assert_eq!(block_reward(1), reward(500 * spw));
+        assert_eq!(block_reward(1_749_999), reward(500 * spw));
+        assert_eq!(block_reward(1_750_000), reward(250 * spw));
+        assert_eq!(block_reward(3_499_999), reward(250 * spw));
+        assert_eq!(block_reward(3_500_000), reward(125 * spw));
+        assert_eq!(block_reward(1_750_000 * 35), reward(1));
+        assert_eq!(block_reward(1_750_000 * 36), Wit::default());
+        assert_eq!(block_reward(1_750_000 * 63), Wit::default());
+        assert_eq!(block_reward(1_750_000 * 64), Wit::default());
+        assert_eq!(block_reward(1_750_000 * 100), Wit::default());
+    }
+
+    #[test]
+    fn test_block_eligibility() {
+        let output = |first_byte| {
+            let mut bytes = [0xff; 32];
+            bytes[0] = first_byte;
+            Hash::SHA256(bytes)
+        };
+
+        let newcomer = identity(0xff);
+
+        // Every identity is eligible while there are few active identities
+        let target = calculate_block_target(&active_identities(0), &newcomer);
+        assert_eq!(target, u64::max_value());
+        assert!(is_block_eligible(output(0xff), target));
+        let reputation_engine = active_identities(MINING_REPLICATION_FACTOR as u8);
+        let target = calculate_block_target(&reputation_engine, &newcomer);
+        assert!(is_block_eligible(output(0xff), target));
+
+        // And then about `MINING_REPLICATION_FACTOR` of them
+        let target = calculate_block_target(&active_identities(8), &newcomer);
+        assert!(is_block_eligible(output(0x00), target));
+        assert!(is_block_eligible(output(0x7f), target));
+        assert!(!is_block_eligible(output(0x80), target));
+        let target = calculate_block_target(&active_identities(128), &newcomer);
+        assert!(is_block_eligible(output(0x07), target));
+        assert!(!is_block_eligible(output(0x08), target));
+
+        // Reputed identities are more likely to be eligible: with a total weight of 16, the
+        // identity with a reputation of 8 is always eligible, and the others a quarter of the time
+        let mut reputation_engine = active_identities(8);
+        for epoch in 0..8 {
+            reputation_engine.apply_tally(epoch, &[identity(0)], &[]);
+        }
+        let target = calculate_block_target(&reputation_engine, &identity(0));
+        assert_eq!(target, u64::max_value());
+        for pkh in &[identity(1), newcomer] {
+            let target = calculate_block_target(&reputation_engine, pkh);
+            assert!(is_block_eligible(output(0x3f), target));
+            assert!(!is_block_eligible(output(0x40), target));
+        }
+    }
+
+    #[test]
+    fn test_data_request_eligibility() {
+        let dr_pointer = OutputPointer {
+            transaction_id: Hash::SHA256([1; 32]),
+            output_index: 0,
+        };
+        let verify = |reputation_engine: &ReputationEngine, pkh: &PublicKeyHash, poe| {
+            verify_poe_data_request(reputation_engine, &dr_pointer, 2, pkh, poe)
+        };
+
+        // Every identity is eligible while there are few active identities
+        let reputation_engine = active_identities(2 * WITNESS_REPLICATION_FACTOR as u8);
+        for pkh in (0..32).map(identity) {
+            let poe = calculate_data_request_poe(&dr_pointer, &pkh);
+            assert!(verify(&reputation_engine, &pkh, poe));
+        }
+
+        // And then about `WITNESS_REPLICATION_FACTOR` of them for every witness
+        let reputation_engine = active_identities(32);
+        let (eligible, not_eligible): (Vec<PublicKeyHash>, Vec<PublicKeyHash>) =
+            (0..32).map(identity).partition(|pkh| {
+                let poe = calculate_data_request_poe(&dr_pointer, pkh);
+                verify(&reputation_engine, pkh, poe)
+            });
+        assert!(!eligible.is_empty());
+        assert!(!not_eligible.is_empty());
+
+        // The proof of eligibility is bound to the data request and to the identity
+        let poe = calculate_data_request_poe(&dr_pointer, &eligible[0]);
+        assert!(!verify(&reputation_engine, &not_eligible[0], poe));
+        assert!(!verify(&reputation_engine, &eligible[0], [0; 32]));
+        let other_dr_pointer = OutputPointer {
+            transaction_id: Hash::SHA256([1; 32]),
+            output_index: 1,
+        };
+        assert_ne!(
+            calculate_data_request_poe(&other_dr_pointer, &eligible[0]),
+            poe
+        );
+    }
+
+    #[test]
+    fn test_validate_header_chain() {
+        let genesis_beacon = CheckpointBeacon {
+            checkpoint: 0,
+            hash_prev_block: Hash::SHA256([0; 32]),
+        };
+        let header = |checkpoint, hash_prev_block| BlockHeader {
+            beacon: CheckpointBeacon {
+                checkpoint,
+                hash_prev_block,
+            },
+            ..BlockHeader::default()
+        };
+        let first = header(1, genesis_beacon.hash_prev_block);
+        let second = header(3, first.hash());
+        let third = header(4, second.hash());
+
+        let genesis_hash = genesis_beacon.hash_prev_block;
+        let tip = validate_header_chain(
+            &[first.clone(), second.clone(), third.clone()],
+            5,
+            genesis_beacon,
+            genesis_hash,
+        )
+        .unwrap();
+        assert_eq!(
+            tip,
+            CheckpointBeacon {
+                checkpoint: 4,
+                hash_prev_block: third.hash(),
+            }
+        );
+        // An empty chain extends nothing
+        assert_eq!(
+            validate_header_chain(&[], 5, tip, genesis_hash).unwrap(),
+            tip
+        );
+
+        // Headers must be linked to the previous one
+        let error = validate_header_chain(
+            &[first.clone(), third.clone()],
+            5,
+            genesis_beacon,
+            genesis_hash,
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.downcast::<BlockError>().unwrap(),
+            BlockError::PreviousHashNotKnown {
+                hash: second.hash()
+            }
+        );
+
+        // Headers cannot go back in time nor come from the future
+        let backwards = header(0, first.hash());
+        let error =
+            validate_header_chain(&[first.clone(), backwards], 5, genesis_beacon, genesis_hash)
+                .unwrap_err();
+        assert_eq!(
+            error.downcast::<BlockError>().unwrap(),
+            BlockError::BlockOlderThanTip {
+                chain_epoch: 1,
+                block_epoch: 0,
+            }
+        );
+        let error =
+            validate_header_chain(&[first.clone(), second], 2, genesis_beacon, genesis_hash)
+                .unwrap_err();
+        assert_eq!(
+            error.downcast::<BlockError>().unwrap(),
+            BlockError::BlockFromFuture {
+                current_epoch: 2,
+                block_epoch: 3,
+            }
+        );
+
+        // Each header must be from a later epoch than the previous one, except for the first
+        // block, which can share the epoch 0 with the genesis block
+        let same_epoch = header(1, first.hash());
+        let error = validate_header_chain(&[first, same_epoch], 5, genesis_beacon, genesis_hash)
+            .unwrap_err();
+        assert_eq!(
+            error.downcast::<BlockError>().unwrap(),
+            BlockError::BlockOlderThanTip {
+                chain_epoch: 1,
+                block_epoch: 1,
+            }
+        );
+        let first_at_genesis_epoch = header(0, genesis_hash);
+        let second_at_genesis_epoch = header(0, first_at_genesis_epoch.hash());
+        assert!(validate_header_chain(
+            &[first_at_genesis_epoch.clone()],
+            5,
+            genesis_beacon,
+            genesis_hash
+        )
+        .is_ok());
+        assert!(validate_header_chain(
+            &[first_at_genesis_epoch, second_at_genesis_epoch],
+            5,
+            genesis_beacon,
+            genesis_hash
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_validate_checkpoint_signature() {
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[0xcd; 32]).unwrap();
+        let public_key = PublicKey::from(Secp256k1_PublicKey::from_secret_key(&secp, &secret_key));
+        let other_secret_key = SecretKey::from_slice(&[0xab; 32]).unwrap();
+        let other_public_key = PublicKey::from(Secp256k1_PublicKey::from_secret_key(
+            &secp,
+            &other_secret_key,
+        ));
+
+        let beacon = CheckpointBeacon {
+            checkpoint: 7,
+            hash_prev_block: Hash::SHA256([7; 32]),
+        };
+        let Hash::SHA256(message) = beacon.hash();
+        let signed_checkpoint = SignedCheckpoint {
+            beacon,
+            signature: KeyedSignature {
+                signature: sign(secret_key, &message).unwrap().into(),
+                public_key: public_key.clone(),
+            },
+        };
+
+        assert!(validate_checkpoint_signature(&signed_checkpoint, &[public_key.clone()]).is_ok());
+
+        // Valid signature from a public key which is not trusted
+        let error =
+            validate_checkpoint_signature(&signed_checkpoint, &[other_public_key]).unwrap_err();
+        assert_eq!(
+            error.downcast::<CheckpointError>().unwrap(),
+            CheckpointError::UntrustedSigner { checkpoint: 7 }
+        );
+
+        // Signature of a different beacon
+        let mut tampered_checkpoint = signed_checkpoint.clone();
+        tampered_checkpoint.beacon.hash_prev_block = Hash::SHA256([8; 32]);
+        let error = validate_checkpoint_signature(&tampered_checkpoint, &[public_key]).unwrap_err();
+        assert_eq!(
+            error.downcast::<CheckpointError>().unwrap(),
+            CheckpointError::VerifySignatureFail { checkpoint: 7 }
+        );
+    }
+
+    #[test]
+    fn test_transaction_malleability() {
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[0xcd; 32]).unwrap();
+        let public_key = PublicKey::from(Secp256k1_PublicKey::from_secret_key(&secp, &secret_key));
+
+        let body = TransactionBody::new(
+            0,
+            vec![Input::ValueTransfer(ValueTransferInput {
+                transaction_id: Hash::SHA256([1; 32]),
+                output_index: 0,
+            })],
+            vec![],
+        );
+        let Hash::SHA256(message) = body.hash();
+        let signature = sign_tagged(secret_key, TRANSACTION_DOMAIN, &message);
+        let mut compact = signature.serialize_compact();
+        let transaction = Transaction::new(
+            body,
+            vec![KeyedSignature {
+                signature: signature.into(),
+                public_key,
+            }],
+        );
+        let mut signature_cache = SignatureCache::default();
+        assert!(validate_transaction_signatures(&transaction, &mut signature_cache).is_ok());
+
+        // Same signature with `s` replaced by `n - s`, which is also valid for the original
+        // ECDSA equation but must be rejected
+        let order = [
+            0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+            0xFF, 0xFE, 0xBA, 0xAE, 0xDC, 0xE6, 0xAF, 0x48, 0xA0, 0x3B, 0xBF, 0xD2, 0x5E, 0x8C,
+            0xD0, 0x36, 0x41, 0x41u8,
+        ];
+        let mut borrow = 0;
+        for (digit, order) in compact[32..].iter_mut().zip(order.iter()).rev() {
+            let difference = i16::from(*order) - i16::from(*digit) - borrow;
+            borrow = if difference < 0 { 1 } else { 0 };
+            *digit = (difference + 256 * borrow) as u8;
+        }
+        let high_s = Secp256k1_Signature::from_compact(&compact).unwrap();
+        let mut malleated = transaction.clone();
+        malleated.signatures[0].signature = high_s.into();
+        assert!(validate_transaction_signatures(&malleated, &mut signature_cache).is_err());
+
+        // Changing the signatures does not change the transaction id nor the merkle root
+        assert_eq!(transaction.hash(), malleated.hash());
+        assert_ne!(transaction.full_hash(), malleated.full_hash());
+        assert_eq!(
+            merkle_tree_root(&[transaction]),
+            merkle_tree_root(&[malleated])
+        );
+    }
+
+    /// Block with just a mint transaction, mined with `secret_key` on top of `beacon`
+    fn mined_block(secret_key: SecretKey, beacon: CheckpointBeacon) -> Block {
+        let secp = Secp256k1::new();
+        let public_key = PublicKey::from(Secp256k1_PublicKey::from_secret_key(&secp, &secret_key));
+
+        let epoch = beacon.checkpoint + 1;
+        let beacon = CheckpointBeacon {
+            checkpoint: epoch,
+            hash_prev_block: beacon.hash_prev_block,
+        };
+        let mint = Transaction::new(
+            TransactionBody::new(
+                0,
+                vec![],
+                vec![Output::ValueTransfer(ValueTransferOutput {
+                    pkh: PublicKeyHash::default(),
+                    value: block_reward(epoch),
+                })],
+            ),
+            vec![],
+        );
+        let Hash::SHA256(message) = beacon.hash();
+
+        Block {
+            block_header: BlockHeader {
+                version: 0,
+                beacon,
+                hash_merkle_root: merkle_tree_root(&[mint.clone()]),
+            },
+            proof: LeadershipProof {
+                block_sig: KeyedSignature {
+                    signature: sign_tagged(secret_key, BLOCK_DOMAIN, &message).into(),
+                    public_key,
+                },
+                proof: VrfProof::new(&secret_key, &message).unwrap(),
+            },
+            txns: vec![mint],
+        }
+    }
+
+    /// Public key hash of the `i`th identity of the tests
+    fn identity(i: u8) -> PublicKeyHash {
+        PublicKeyHash::from_public_key(&PublicKey {
+            compressed: 2,
+            bytes: [i; 32],
+        })
+    }
+
+    /// Reputation engine with `n` active identities, from the first one
+    fn active_identities(n: u8) -> ReputationEngine {
+        let mut reputation_engine = ReputationEngine::default();
+        for i in 0..n {
+            reputation_engine.record_activity(identity(i), 0);
+        }
+
+        reputation_engine
+    }
+
+    #[test]
+    fn test_block_eligibility_threshold() {
+        let chain_beacon = CheckpointBeacon {
+            checkpoint: 1,
+            hash_prev_block: Hash::SHA256([1; 32]),
+        };
+        let mut data_request_pool = DataRequestPool::default();
+        data_request_pool.reputation_engine = active_identities(8);
+        let validate = |block: &Block, data_request_pool: &DataRequestPool| {
+            validate_block(
+                block,
+                2,
+                chain_beacon,
+                Hash::SHA256([0; 32]),
+                &UnspentOutputsPool::default(),
+                &TransactionsPool::new(),
+                data_request_pool,
+                &mut SignatureCache::default(),
+            )
+        };
+
+        // With 8 active identities, about half of the miners are eligible
+        let (eligible, not_eligible): (Vec<Block>, Vec<Block>) = (1..=32)
+            .map(|i| mined_block(SecretKey::from_slice(&[i; 32]).unwrap(), chain_beacon))
+            .partition(|block| {
+                verify_poe_block(block, &data_request_pool.reputation_engine).is_ok()
+            });
+        assert!(!eligible.is_empty());
+        assert!(!not_eligible.is_empty());
+
+        for block in &eligible {
+            assert!(validate(block, &data_request_pool).is_ok());
+        }
+        for block in &not_eligible {
+            match verify_poe_block(block, &data_request_pool.reputation_engine)
+                .unwrap_err()
+                .downcast::<BlockError>()
+            {
+                Ok(BlockError::NotEligible) => {}
+                error => panic!("Unexpected error: {:?}", error),
+            }
+            // The block may be valid on another chain, so it is not malformed
+            match validate(block, &data_request_pool).unwrap_err() {
+                error @ BlockValidationError::Eligibility(BlockError::NotEligible) => {
+                    assert!(!error.is_malformed())
+                }
+                error => panic!("Unexpected error: {}", error),
+            }
+
+            // Every miner is eligible while there are few active identities
+            assert!(validate(block, &DataRequestPool::default()).is_ok());
+        }
+    }
 
-        assert_eq!(block_reward(0), 500 * spw);
-        assert_eq!(block_reward(1), 500 * spw);
-        assert_eq!(block_reward(1_749_999), 500 * spw);
-        assert_eq!(block_reward(1_750_000), 250 * spw);
-        assert_eq!(block_reward(3_499_999), 250 * spw);
-        assert_eq!(block_reward(3_500_000), 125 * spw);
-        assert_eq!(block_reward(1_750_000 * 35), 1);
-        assert_eq!(block_reward(1_750_000 * 36), 0);
-        assert_eq!(block_reward(1_750_000 * 63), 0);
-        assert_eq!(block_reward(1_750_000 * 64), 0);
-        assert_eq!(block_reward(1_750_000 * 100), 0);
+    #[test]
+    fn test_validate_block_stages() {
+        let chain_beacon = CheckpointBeacon {
+            checkpoint: 1,
+            hash_prev_block: Hash::SHA256([1; 32]),
+        };
+        let block = mined_block(SecretKey::from_slice(&[0xcd; 32]).unwrap(), chain_beacon);
+        let Hash::SHA256(message) = block.block_header.beacon.hash();
+        let validate = |block: &Block, current_epoch| {
+            validate_block(
+                block,
+                current_epoch,
+                chain_beacon,
+                Hash::SHA256([0; 32]),
+                &UnspentOutputsPool::default(),
+                &TransactionsPool::new(),
+                &DataRequestPool::default(),
+                &mut SignatureCache::default(),
+            )
+        };
+
+        assert!(validate_block_integrity(&block).is_ok());
+        assert!(validate(&block, 2).is_ok());
+
+        // A block from the future may be valid later, so it is not malformed
+        match validate(&block, 1).unwrap_err() {
+            error @ BlockValidationError::Beacon(BlockError::BlockFromFuture { .. }) => {
+                assert!(!error.is_malformed())
+            }
+            error => panic!("Unexpected error: {}", error),
+        }
+
+        // A block whose transactions do not match its merkle root is malformed
+        let mut tampered = block.clone();
+        tampered.block_header.hash_merkle_root = Hash::SHA256([2; 32]);
+        let error = validate(&tampered, 2).unwrap_err();
+        match &error {
+            BlockValidationError::MerkleRoot { found, .. } => {
+                assert_eq!(*found, Hash::SHA256([2; 32]))
+            }
+            error => panic!("Unexpected error: {}", error),
+        }
+        assert!(error.is_malformed());
+
+        // And so is a block whose proof does not sign its beacon
+        let mut tampered = block.clone();
+        tampered.block_header.beacon.checkpoint = 1;
+        match validate_block_integrity(&tampered).unwrap_err() {
+            error @ BlockValidationError::LeadershipProof(_) => assert!(error.is_malformed()),
+            error => panic!("Unexpected error: {}", error),
+        }
+
+        // And so is a block whose VRF proof was made with another key
+        let mut tampered = block.clone();
+        tampered.proof.proof =
+            VrfProof::new(&SecretKey::from_slice(&[0xab; 32]).unwrap(), &message).unwrap();
+        match validate_block_integrity(&tampered).unwrap_err() {
+            error @ BlockValidationError::LeadershipProof(_) => assert!(error.is_malformed()),
+            error => panic!("Unexpected error: {}", error),
+        }
+
+        // The mint must create exactly the block reward plus the fees
+        let mut tampered = block;
+        tampered.txns[0].body.outputs[0] = Output::ValueTransfer(ValueTransferOutput {
+            pkh: PublicKeyHash::default(),
+            value: Wit::from_nanowits(1),
+        });
+        tampered.block_header.hash_merkle_root = merkle_tree_root(&tampered.txns);
+        match validate(&tampered, 2).unwrap_err() {
+            error @ BlockValidationError::Mint(_) => assert!(!error.is_malformed()),
+            error => panic!("Unexpected error: {}", error),
+        }
+    }
+
+    #[test]
+    fn test_validate_rad_request_body() {
+        use witnet_data_structures::chain::{RADAggregate, RADConsensus, RADRetrieve};
+
+        // An empty RADON script
+        let script = vec![0x90];
+        let rad_request = |kind| RADRequest {
+            retrieve: vec![RADRetrieve {
+                kind,
+                url: "https://example.com/prices".to_string(),
+                script: script.clone(),
+                body: br#"{"symbol":"BTCUSD"}"#.to_vec(),
+                ..RADRetrieve::default()
+            }],
+            aggregate: RADAggregate {
+                script: script.clone(),
+            },
+            consensus: RADConsensus {
+                script: script.clone(),
+            },
+            ..RADRequest::default()
+        };
+
+        assert!(validate_rad_request(&rad_request(RADType::HttpPost)).is_ok());
+        match validate_rad_request(&rad_request(RADType::HttpGet))
+            .unwrap_err()
+            .downcast::<TransactionError>()
+        {
+            Ok(TransactionError::BodyInGetRetrieval { url }) => {
+                assert_eq!(url, "https://example.com/prices")
+            }
+            result => panic!("Unexpected result: {:?}", result),
+        }
+    }
+    #[test]
+    fn test_validate_rad_request_certificate_fingerprint() {
+        use witnet_data_structures::chain::{RADAggregate, RADConsensus, RADRetrieve};
+
+        // An empty RADON script
+        let script = vec![0x90];
+        let rad_request = |certificate_fingerprint| RADRequest {
+            retrieve: vec![RADRetrieve {
+                kind: RADType::HttpGet,
+                url: "https://example.com/prices".to_string(),
+                script: script.clone(),
+                certificate_fingerprint,
+                ..RADRetrieve::default()
+            }],
+            aggregate: RADAggregate {
+                script: script.clone(),
+            },
+            consensus: RADConsensus {
+                script: script.clone(),
+            },
+            ..RADRequest::default()
+        };
+
+        assert!(validate_rad_request(&rad_request(vec![])).is_ok());
+        assert!(validate_rad_request(&rad_request(vec![0xab; 32])).is_ok());
+        match validate_rad_request(&rad_request(vec![0xab; 20]))
+            .unwrap_err()
+            .downcast::<TransactionError>()
+        {
+            Ok(TransactionError::InvalidCertificateFingerprint { url, length }) => {
+                assert_eq!(url, "https://example.com/prices");
+                assert_eq!(length, 20);
+            }
+            result => panic!("Unexpected result: {:?}", result),
+        }
     }
 }
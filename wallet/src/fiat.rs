@@ -0,0 +1,263 @@
+//! Fiat valuation of wallet balances
+//!
+//! The price of the wit in a fiat currency is obtained by periodically running a price data
+//! request locally with the RAD engine. The data request is never sent to the network: its
+//! retrieval and aggregation stages are executed by the wallet itself, and the resulting price
+//! is cached until the next update.
+
+use std::{fs, path::Path, time::Duration};
+
+use actix::{Actor, AsyncContext, Context, Handler, Message, Supervised, SystemService};
+use failure::Fail;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use witnet_data_structures::{
+    chain::RADRequest,
+    serializers::decoders::TryFrom,
+    wit::{Wit, NANOWITS_PER_WIT},
+};
+use witnet_rad::{
+    self as rad,
+    types::{RadonType, RadonTypes},
+};
+use witnet_util::timestamp::get_timestamp;
+
+/// Environment variable with the path of the fiat valuation configuration file
+pub const FIAT_CONFIG_ENV: &str = "WITNET_WALLET_FIAT_CONFIG";
+
+/// The error type for fiat valuations
+#[derive(Debug, PartialEq, Fail)]
+pub enum FiatError {
+    #[fail(display = "None of the sources of the price data request could be retrieved")]
+    /// All the retrievals of the price data request failed
+    NoRetrievals,
+    #[fail(display = "Error running the price data request: {}", message)]
+    /// The RAD engine failed to run the price data request
+    Rad {
+        /// Error returned by the RAD engine
+        message: String,
+    },
+    #[fail(display = "Price data request returned a {}, expected a float", found)]
+    /// The result of the price data request is not a number
+    NotAFloat {
+        /// Type of the result
+        found: String,
+    },
+    #[fail(display = "Invalid fiat valuation configuration: {}", message)]
+    /// The configuration file could not be read or parsed
+    Config {
+        /// Reason why the configuration is not valid
+        message: String,
+    },
+}
+
+/// Configuration of the fiat valuation of balances
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct FiatConfig {
+    /// Fiat currency in which the balances are valued, e.g. `USD`
+    pub currency: String,
+    /// Data request whose result is the price of one wit in `currency`
+    pub price_request: RADRequest,
+    /// Period between price updates, in seconds
+    pub update_period: u64,
+}
+
+impl FiatConfig {
+    /// Read the configuration from a JSON file
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, FiatError> {
+        let config_error = |message: String| FiatError::Config { message };
+
+        let contents = fs::read_to_string(path).map_err(|e| config_error(e.to_string()))?;
+
+        serde_json::from_str(&contents).map_err(|e| config_error(e.to_string()))
+    }
+}
+
+/// Price of one wit in a fiat currency
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct FiatPrice {
+    /// Fiat currency of the price
+    pub currency: String,
+    /// Price of one wit
+    pub price: f64,
+    /// Timestamp of the price update, seconds since Unix epoch
+    pub timestamp: i64,
+}
+
+/// Value of an amount of wits in a fiat currency
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct FiatValue {
+    /// Fiat currency of the value
+    pub currency: String,
+    /// Value of the amount
+    pub value: f64,
+    /// Price of one wit used for the valuation
+    pub price: f64,
+    /// Timestamp of the price, seconds since Unix epoch
+    pub timestamp: i64,
+}
+
+impl FiatPrice {
+    /// Value an amount of wits with this price
+    pub fn value_of(&self, amount: Wit) -> FiatValue {
+        FiatValue {
+            currency: self.currency.clone(),
+            value: amount.nanowits() as f64 / NANOWITS_PER_WIT as f64 * self.price,
+            price: self.price,
+            timestamp: self.timestamp,
+        }
+    }
+}
+
+/// Run the retrieval and aggregation stages of a price data request, without any interaction
+/// with the network, and return the resulting price
+pub fn run_price_request(request: &RADRequest) -> Result<f64, FiatError> {
+    let rad_error = |e: rad::error::RadError| FiatError::Rad {
+        message: e.to_string(),
+    };
+
+    let retrievals: Vec<RadonTypes> = request
+        .retrieve
+        .iter()
+        .cloned()
+        .filter_map(|retrieve| {
+            rad::run_retrieval(retrieve)
+                .map_err(|e| debug!("Price source failed: {}", e))
+                .ok()
+        })
+        .collect();
+    if retrievals.is_empty() {
+        Err(FiatError::NoRetrievals)?
+    }
+
+    let result =
+        rad::run_aggregation(retrievals, request.aggregate.script.clone()).map_err(rad_error)?;
+
+    price_from_result(&result)
+}
+
+/// Decode the serialized result of a price data request into a price
+fn price_from_result(result: &[u8]) -> Result<f64, FiatError> {
+    match RadonTypes::try_from(result).map_err(|e| FiatError::Rad {
+        message: e.to_string(),
+    })? {
+        RadonTypes::Float(price) => Ok(price.value()),
+        other => Err(FiatError::NotAFloat {
+            found: other.radon_type_name(),
+        }),
+    }
+}
+
+/// Actor in charge of keeping the price of the wit in a fiat currency up to date
+#[derive(Debug, Default)]
+pub struct PriceFeed {
+    /// Configuration of the valuation, the feed is disabled when it is `None`
+    config: Option<FiatConfig>,
+    /// Last price obtained from the price data request
+    last_price: Option<FiatPrice>,
+}
+
+impl PriceFeed {
+    /// Create a price feed which is updated according to `config`
+    pub fn new(config: FiatConfig) -> Self {
+        PriceFeed {
+            config: Some(config),
+            last_price: None,
+        }
+    }
+
+    /// Run the price data request and cache its result. On error, the last price is kept.
+    fn update(&mut self) {
+        let config = match &self.config {
+            Some(config) => config,
+            None => return,
+        };
+
+        match run_price_request(&config.price_request) {
+            Ok(price) => {
+                debug!("Updated price: 1 wit = {} {}", price, config.currency);
+                self.last_price = Some(FiatPrice {
+                    currency: config.currency.clone(),
+                    price,
+                    timestamp: get_timestamp(),
+                });
+            }
+            Err(e) => warn!("Cannot update the price in {}: {}", config.currency, e),
+        }
+    }
+}
+
+impl Actor for PriceFeed {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        if let Some(config) = &self.config {
+            debug!("PriceFeed actor has been started for {}", config.currency);
+            let update_period = Duration::from_secs(config.update_period);
+
+            self.update();
+            ctx.run_interval(update_period, |act, _ctx| act.update());
+        }
+    }
+}
+
+/// Required traits for being able to retrieve actor address from registry
+impl Supervised for PriceFeed {}
+impl SystemService for PriceFeed {}
+
+/// Get the last cached price, if any
+pub struct GetFiatPrice;
+
+impl Message for GetFiatPrice {
+    type Result = Option<FiatPrice>;
+}
+
+impl Handler<GetFiatPrice> for PriceFeed {
+    type Result = Option<FiatPrice>;
+
+    fn handle(&mut self, _msg: GetFiatPrice, _ctx: &mut Self::Context) -> Self::Result {
+        self.last_price.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use witnet_data_structures::serializers::decoders::TryInto;
+    use witnet_rad::types::{float::RadonFloat, string::RadonString};
+
+    #[test]
+    fn test_price_from_result() {
+        let result: Vec<u8> = RadonTypes::from(RadonFloat::from(0.25)).try_into().unwrap();
+        match price_from_result(&result) {
+            Ok(price) => assert!((price - 0.25).abs() < std::f64::EPSILON),
+            other => panic!("Unexpected result: {:?}", other),
+        }
+
+        let result: Vec<u8> = RadonTypes::from(RadonString::from("0.25"))
+            .try_into()
+            .unwrap();
+        assert_eq!(
+            price_from_result(&result),
+            Err(FiatError::NotAFloat {
+                found: "RadonString".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_value_of() {
+        let price = FiatPrice {
+            currency: "USD".to_string(),
+            price: 0.5,
+            timestamp: 1_000,
+        };
+
+        let value = price.value_of(Wit::from_nanowits(3_000_000_000));
+        assert_eq!(value.currency, "USD");
+        assert_eq!(value.timestamp, 1_000);
+        assert!((value.value - 1.5).abs() < std::f64::EPSILON);
+        assert!(price.value_of(Wit::default()).value.abs() < std::f64::EPSILON);
+    }
+}
@@ -7,4 +7,6 @@
 #![deny(unused_mut)]
 #![deny(missing_docs)]
 
+#[cfg(feature = "fiat")]
+pub mod fiat;
 pub mod server;
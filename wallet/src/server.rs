@@ -92,6 +92,7 @@ fn start_ws_jsonrpc_server(
         ("createWallet", create_wallet),
         ("unlockWallet", unlock_wallet),
         ("getTransactions", get_transactions),
+        ("getBalance", get_balance),
         ("sendVTT", send_vtt),
         ("generateAddress", generate_address),
         ("createDataRequest", create_data_request),
@@ -401,6 +402,68 @@ fn get_transactions(
     })))
 }
 
+#[derive(Debug, Deserialize)]
+struct GetBalanceParams {
+    wallet_id: String,
+}
+
+/// Balance of a wallet, in nanowits
+#[derive(Debug, Serialize)]
+struct Balance {
+    amount: u64,
+    /// Value of the balance in a fiat currency, if a price is available
+    #[cfg(feature = "fiat")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fiat: Option<crate::fiat::FiatValue>,
+}
+
+#[cfg(not(feature = "fiat"))]
+fn get_balance(
+    _registry: &SystemRegistry,
+    params: jsonrpc_core::Result<GetBalanceParams>,
+) -> impl Future<Item = Value, Error = jsonrpc_core::Error> {
+    let _params = match params {
+        Ok(x) => x,
+        Err(e) => return Box::new(futures::failed(e)),
+    };
+
+    let x = Balance { amount: 0 };
+    Box::new(futures::done(serde_json::to_value(x).map_err(|e| {
+        let mut err = jsonrpc_core::Error::internal_error();
+        err.message = e.to_string();
+        err
+    })))
+}
+
+/// Get the balance of a wallet, valued with the last price cached by the `PriceFeed`
+#[cfg(feature = "fiat")]
+fn get_balance(
+    registry: &SystemRegistry,
+    params: jsonrpc_core::Result<GetBalanceParams>,
+) -> impl Future<Item = Value, Error = jsonrpc_core::Error> {
+    use crate::fiat::{GetFiatPrice, PriceFeed};
+    use witnet_data_structures::wit::Wit;
+
+    let price_feed = registry.get::<PriceFeed>();
+    futures::done(params).and_then(move |_params| {
+        let amount = 0;
+        price_feed.send(GetFiatPrice).then(move |price| {
+            let fiat = price
+                .map_err(|e| warn!("Cannot get the fiat price: {}", e))
+                .ok()
+                .and_then(|price| price)
+                .map(|price| price.value_of(Wit::from_nanowits(amount)));
+            let x = Balance { amount, fiat };
+
+            serde_json::to_value(x).map_err(|e| {
+                let mut err = jsonrpc_core::Error::internal_error();
+                err.message = e.to_string();
+                err
+            })
+        })
+    })
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 struct UnlockWalletParams {
     id: String,
@@ -691,6 +754,19 @@ pub fn websockets_actix_poc() {
     let jsonrpc_ws_client = JsonRpcClient::new("127.0.0.1:1234");
     s.registry().set(jsonrpc_ws_client.start());
 
+    // Fiat valuation of balances is only enabled when it is configured
+    #[cfg(feature = "fiat")]
+    {
+        use crate::fiat::{FiatConfig, PriceFeed, FIAT_CONFIG_ENV};
+
+        if let Ok(path) = std::env::var(FIAT_CONFIG_ENV) {
+            match FiatConfig::from_file(&path) {
+                Ok(config) => s.registry().set(PriceFeed::new(config).start()),
+                Err(e) => warn!("Fiat valuation disabled: {}", e),
+            }
+        }
+    }
+
     // Because system.run() blocks
     let code = system.run();
     info!("Done, system exited with code {}", code);
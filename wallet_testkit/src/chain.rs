@@ -0,0 +1,384 @@
+//! Scriptable chain
+//!
+//! The `MockChain` keeps a single main chain with one block per epoch, and a pool of pending
+//! transactions. Tests drive it explicitly: blocks are only mined when requested, so the
+//! behavior seen by the wallet is deterministic.
+use std::collections::HashSet;
+
+use witnet_crypto::{hash::Sha256, merkle::merkle_tree_root};
+use witnet_data_structures::{
+    chain::{
+        Block, BlockHeader, CheckpointBeacon, Hash, Hashable, LeadershipProof, Output,
+        OutputPointer, PublicKeyHash, Transaction, TransactionBody, ValueTransferOutput,
+    },
+    wit::{Wit, NANOWITS_PER_WIT},
+};
+
+/// Reward of the mint transaction of every block, in wits
+pub const BLOCK_REWARD: u64 = 500;
+
+/// Transaction waiting to be included in a block
+#[derive(Clone, Debug)]
+struct PendingTransaction {
+    transaction: Transaction,
+    /// First epoch in which the transaction can be included
+    confirm_at: u32,
+}
+
+/// Simulated chain of a mock node
+#[derive(Debug)]
+pub struct MockChain {
+    /// Blocks of the main chain, the block at position `i` closes epoch `i`
+    blocks: Vec<Block>,
+    /// Blocks removed from the main chain by a reorganization
+    orphaned: Vec<Block>,
+    /// Transactions waiting to be included in a block
+    pending: Vec<PendingTransaction>,
+    /// Transactions dropped because they spend outputs already spent in the main chain
+    rejected: Vec<Transaction>,
+    /// Number of reorganizations so far, used to mine blocks different from the orphaned ones
+    forks: u8,
+}
+
+impl Default for MockChain {
+    fn default() -> Self {
+        let mut chain = MockChain {
+            blocks: vec![],
+            orphaned: vec![],
+            pending: vec![],
+            rejected: vec![],
+            forks: 0,
+        };
+        chain.mine_block();
+
+        chain
+    }
+}
+
+impl MockChain {
+    /// Create a new chain containing only the genesis block
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Epoch of the last block of the main chain
+    pub fn epoch(&self) -> u32 {
+        self.blocks.len() as u32 - 1
+    }
+
+    /// Last block of the main chain
+    pub fn tip(&self) -> &Block {
+        self.blocks
+            .last()
+            .expect("The chain always has a genesis block")
+    }
+
+    /// Blocks of the main chain, starting with the genesis block
+    pub fn blocks(&self) -> &[Block] {
+        &self.blocks
+    }
+
+    /// Get a block by hash. Blocks orphaned by a reorganization can still be retrieved, as a
+    /// real node keeps them in its inventory.
+    pub fn block(&self, hash: &Hash) -> Option<&Block> {
+        self.blocks
+            .iter()
+            .chain(self.orphaned.iter())
+            .find(|block| block.hash() == *hash)
+    }
+
+    /// List of `(epoch, block_hash)` pairs of the main chain, with the same semantics as the
+    /// `getBlockChain` method of the node: a negative `epoch` is relative to the current epoch,
+    /// and a `limit` of 0 returns all the blocks.
+    pub fn block_chain(&self, epoch: i64, limit: u32) -> Vec<(u32, Hash)> {
+        let first = if epoch >= 0 {
+            epoch
+        } else {
+            (i64::from(self.epoch()) + epoch).max(0)
+        } as usize;
+        let limit = if limit == 0 {
+            self.blocks.len()
+        } else {
+            limit as usize
+        };
+
+        self.blocks
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(limit)
+            .map(|(epoch, block)| (epoch as u32, block.hash()))
+            .collect()
+    }
+
+    /// Get an output of a transaction included in the main chain
+    pub fn output(&self, output_pointer: &OutputPointer) -> Option<&Output> {
+        self.blocks
+            .iter()
+            .flat_map(|block| block.txns.iter())
+            .find(|transaction| transaction.hash() == output_pointer.transaction_id)
+            .and_then(|transaction| {
+                transaction
+                    .body
+                    .outputs
+                    .get(output_pointer.output_index as usize)
+            })
+    }
+
+    /// Number of blocks confirming a transaction: 1 when it is included in the last block, or
+    /// `None` if it is not included in the main chain
+    pub fn confirmations(&self, transaction_hash: &Hash) -> Option<u32> {
+        self.blocks
+            .iter()
+            .position(|block| {
+                block
+                    .txns
+                    .iter()
+                    .any(|transaction| transaction.hash() == *transaction_hash)
+            })
+            .map(|epoch| self.epoch() + 1 - epoch as u32)
+    }
+
+    /// Transactions waiting to be included in a block
+    pub fn pending_transactions(&self) -> Vec<&Transaction> {
+        self.pending
+            .iter()
+            .map(|pending| &pending.transaction)
+            .collect()
+    }
+
+    /// Transactions dropped because they conflict with a transaction of the main chain
+    pub fn rejected_transactions(&self) -> &[Transaction] {
+        &self.rejected
+    }
+
+    /// Add a transaction which will be included in the next block
+    pub fn add_transaction(&mut self, transaction: Transaction) {
+        self.add_transaction_with_delay(transaction, 0);
+    }
+
+    /// Add a transaction which will not be included in a block until `delay` more blocks have
+    /// been mined
+    pub fn add_transaction_with_delay(&mut self, transaction: Transaction, delay: u32) {
+        self.pending.push(PendingTransaction {
+            transaction,
+            confirm_at: self.epoch() + 1 + delay,
+        });
+    }
+
+    /// Mine a new block on top of the main chain, including all the pending transactions which
+    /// are due. Transactions spending outputs already spent in the main chain are rejected.
+    pub fn mine_block(&mut self) -> Block {
+        let epoch = self.blocks.len() as u32;
+        let mut spent = self.spent_outputs();
+
+        let (due, pending): (Vec<_>, Vec<_>) = self
+            .pending
+            .drain(..)
+            .partition(|pending| pending.confirm_at <= epoch);
+        self.pending = pending;
+
+        let mut txns = vec![self.mint(epoch)];
+        for PendingTransaction { transaction, .. } in due {
+            let inputs: Vec<_> = transaction
+                .body
+                .inputs
+                .iter()
+                .map(|input| input.output_pointer())
+                .collect();
+            if inputs.iter().any(|input| spent.contains(input)) {
+                log::debug!("Rejecting conflicting transaction {}", transaction.hash());
+                self.rejected.push(transaction);
+            } else {
+                spent.extend(inputs);
+                txns.push(transaction);
+            }
+        }
+
+        let hashes: Vec<Sha256> = txns
+            .iter()
+            .map(|transaction| match transaction.hash() {
+                Hash::SHA256(x) => Sha256(x),
+            })
+            .collect();
+        let hash_prev_block = self.blocks.last().map(Hashable::hash).unwrap_or_default();
+        let block = Block {
+            block_header: BlockHeader {
+                version: 0,
+                beacon: CheckpointBeacon {
+                    checkpoint: epoch,
+                    hash_prev_block,
+                },
+                hash_merkle_root: Hash::from(merkle_tree_root(&hashes)),
+            },
+            proof: LeadershipProof::default(),
+            txns,
+        };
+        self.blocks.push(block.clone());
+
+        block
+    }
+
+    /// Mine `blocks` blocks on top of the main chain
+    pub fn mine_blocks(&mut self, blocks: usize) -> Vec<Block> {
+        (0..blocks).map(|_| self.mine_block()).collect()
+    }
+
+    /// Replace the last `depth` blocks of the main chain with `length` new blocks, returning the
+    /// new blocks.
+    ///
+    /// The transactions of the orphaned blocks go back to the pending transactions, and they are
+    /// included again in the first new block unless they conflict with other transactions.
+    pub fn reorg(&mut self, depth: usize, length: usize) -> Vec<Block> {
+        // The genesis block is never reorganized
+        let depth = depth.min(self.blocks.len() - 1);
+        let first_orphaned = self.blocks.len() - depth;
+        let orphaned: Vec<Block> = self.blocks.drain(first_orphaned..).collect();
+        self.forks = self.forks.wrapping_add(1);
+
+        let mut pending: Vec<_> = orphaned
+            .iter()
+            .flat_map(|block| block.txns.iter().skip(1))
+            .map(|transaction| PendingTransaction {
+                transaction: transaction.clone(),
+                confirm_at: 0,
+            })
+            .collect();
+        pending.append(&mut self.pending);
+        self.pending = pending;
+        self.orphaned.extend(orphaned);
+
+        self.mine_blocks(length)
+    }
+
+    /// Add two transactions spending the same outputs. `winner` is included in the next block,
+    /// while `loser` is delayed by `delay` blocks and rejected when it becomes due.
+    pub fn double_spend(&mut self, winner: Transaction, loser: Transaction, delay: u32) {
+        self.add_transaction(winner);
+        self.add_transaction_with_delay(loser, delay.max(1));
+    }
+
+    /// Outputs spent by the transactions of the main chain
+    fn spent_outputs(&self) -> HashSet<OutputPointer> {
+        self.blocks
+            .iter()
+            .flat_map(|block| block.txns.iter())
+            .flat_map(|transaction| transaction.body.inputs.iter())
+            .map(|input| input.output_pointer())
+            .collect()
+    }
+
+    /// Mint transaction of a block. The epoch is used as the version of the transaction, and the
+    /// miner changes after every reorganization, so that every block has a different hash.
+    fn mint(&self, epoch: u32) -> Transaction {
+        let output = Output::ValueTransfer(ValueTransferOutput {
+            pkh: miner_pkh(self.forks),
+            value: Wit::from_nanowits(BLOCK_REWARD * NANOWITS_PER_WIT),
+        });
+
+        Transaction::new(TransactionBody::new(epoch, vec![], vec![output]), vec![])
+    }
+}
+
+fn miner_pkh(fork: u8) -> PublicKeyHash {
+    PublicKeyHash::from_bytes([fork; 20])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use witnet_data_structures::chain::{Input, ValueTransferInput};
+
+    fn spend(output_pointer: &OutputPointer, pkh: u8) -> Transaction {
+        let input = Input::ValueTransfer(ValueTransferInput {
+            transaction_id: output_pointer.transaction_id,
+            output_index: output_pointer.output_index,
+        });
+        let output = Output::ValueTransfer(ValueTransferOutput {
+            pkh: PublicKeyHash::from_bytes([pkh; 20]),
+            value: Wit::from_nanowits(1),
+        });
+
+        Transaction::new(TransactionBody::new(0, vec![input], vec![output]), vec![])
+    }
+
+    fn genesis_output(chain: &MockChain) -> OutputPointer {
+        OutputPointer {
+            transaction_id: chain.blocks()[0].txns[0].hash(),
+            output_index: 0,
+        }
+    }
+
+    #[test]
+    fn mine_blocks() {
+        let mut chain = MockChain::new();
+        let blocks = chain.mine_blocks(2);
+
+        assert_eq!(chain.epoch(), 2);
+        assert_eq!(chain.tip(), &blocks[1]);
+        assert_eq!(
+            blocks[1].block_header.beacon.hash_prev_block,
+            blocks[0].hash()
+        );
+        assert_eq!(chain.block_chain(0, 0).len(), 3);
+        assert_eq!(
+            chain.block_chain(-1, 0),
+            vec![(1, blocks[0].hash()), (2, blocks[1].hash())]
+        );
+        assert_eq!(chain.block_chain(1, 1), vec![(1, blocks[0].hash())]);
+    }
+
+    #[test]
+    fn delayed_confirmations() {
+        let mut chain = MockChain::new();
+        let transaction = spend(&genesis_output(&chain), 1);
+        chain.add_transaction_with_delay(transaction.clone(), 2);
+
+        chain.mine_blocks(2);
+        assert_eq!(chain.confirmations(&transaction.hash()), None);
+        assert_eq!(chain.pending_transactions(), vec![&transaction]);
+
+        chain.mine_block();
+        assert_eq!(chain.confirmations(&transaction.hash()), Some(1));
+        chain.mine_block();
+        assert_eq!(chain.confirmations(&transaction.hash()), Some(2));
+        assert!(chain.pending_transactions().is_empty());
+    }
+
+    #[test]
+    fn reorg_returns_transactions_to_pending() {
+        let mut chain = MockChain::new();
+        let transaction = spend(&genesis_output(&chain), 1);
+        chain.add_transaction(transaction.clone());
+        let orphaned = chain.mine_block();
+        chain.mine_block();
+
+        let new_blocks = chain.reorg(2, 3);
+        assert_eq!(chain.epoch(), 3);
+        assert_ne!(new_blocks[0].hash(), orphaned.hash());
+        assert_eq!(chain.block(&orphaned.hash()), Some(&orphaned));
+        // The transaction is included again in the first block of the new fork
+        assert_eq!(chain.confirmations(&transaction.hash()), Some(3));
+    }
+
+    #[test]
+    fn conflicting_spends() {
+        let mut chain = MockChain::new();
+        let output_pointer = genesis_output(&chain);
+        let winner = spend(&output_pointer, 1);
+        let loser = spend(&output_pointer, 2);
+        chain.double_spend(winner.clone(), loser.clone(), 1);
+
+        chain.mine_blocks(2);
+        assert_eq!(chain.confirmations(&winner.hash()), Some(2));
+        assert_eq!(chain.confirmations(&loser.hash()), None);
+        assert_eq!(chain.rejected_transactions(), &[loser][..]);
+        assert_eq!(
+            chain.output(&OutputPointer {
+                transaction_id: winner.hash(),
+                output_index: 0,
+            }),
+            winner.body.outputs.get(0)
+        );
+    }
+}
@@ -0,0 +1,27 @@
+//! Test kit for the integration tests of wallets
+//!
+//! Provides a mock node serving a scriptable chain through the same JSON-RPC interface as the
+//! node, so wallets can be tested deterministically against reorganizations, delayed
+//! confirmations and conflicting spends:
+//!
+//! ```no_run
+//! use witnet_wallet_testkit::MockNode;
+//!
+//! let node = MockNode::start("127.0.0.1:0").unwrap();
+//! // Point the wallet to `node.addr()`, then script the scenario
+//! node.mine_blocks(3);
+//! node.reorg(2, 3);
+//! ```
+
+#![deny(rust_2018_idioms)]
+#![deny(non_upper_case_globals)]
+#![deny(non_camel_case_types)]
+#![deny(non_snake_case)]
+#![deny(unused_mut)]
+#![deny(missing_docs)]
+
+pub mod chain;
+pub mod node;
+
+pub use self::chain::MockChain;
+pub use self::node::MockNode;
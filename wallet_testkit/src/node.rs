@@ -0,0 +1,348 @@
+//! Mock node JSON-RPC server
+//!
+//! The `MockNode` serves a `MockChain` using the same transport as the JSON-RPC server of the
+//! node: newline-delimited JSON-RPC 2.0 messages over TCP. The supported methods are
+//! `getBlockChain`, `getBlock`, `getOutput`, `inventory` (transactions only) and the
+//! `newBlocks` subscription.
+//!
+//! Every connection is served in its own thread, but the chain only changes when the test calls
+//! one of the scenario methods of the `MockNode`, which notify the `newBlocks` subscribers before
+//! returning.
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+    thread,
+};
+
+use serde_json::{json, Value};
+
+use witnet_data_structures::chain::{Block, Hash, InventoryItem, OutputPointer, Transaction};
+
+use crate::chain::MockChain;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+
+/// Writing half of a connection, shared with the subscriptions of the connection so that
+/// notifications are not interleaved with responses
+type Writer = Arc<Mutex<TcpStream>>;
+
+/// Client subscribed to new blocks
+struct Subscriber {
+    id: String,
+    writer: Writer,
+}
+
+/// State shared between the `MockNode` and the threads serving its connections
+#[derive(Default)]
+struct Shared {
+    chain: Mutex<MockChain>,
+    subscribers: Mutex<Vec<Subscriber>>,
+    next_subscription: AtomicUsize,
+    stopped: AtomicBool,
+}
+
+/// JSON-RPC error
+#[derive(Debug)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new<T: ToString>(code: i64, message: T) -> Self {
+        RpcError {
+            code,
+            message: message.to_string(),
+        }
+    }
+}
+
+/// Mock node serving a scriptable chain through JSON-RPC. The server is stopped when the
+/// `MockNode` is dropped.
+pub struct MockNode {
+    addr: SocketAddr,
+    shared: Arc<Shared>,
+}
+
+impl MockNode {
+    /// Start a mock node with a new chain, listening on `addr`. Use port 0 to let the operating
+    /// system choose a free port, and [`addr`](MockNode::addr) to find it out.
+    pub fn start<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        Self::start_with_chain(addr, MockChain::new())
+    }
+
+    /// Start a mock node serving `chain`, listening on `addr`
+    pub fn start_with_chain<A: ToSocketAddrs>(addr: A, chain: MockChain) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let addr = listener.local_addr()?;
+        let shared = Arc::new(Shared {
+            chain: Mutex::new(chain),
+            next_subscription: AtomicUsize::new(1),
+            ..Shared::default()
+        });
+
+        let server_shared = Arc::clone(&shared);
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                if server_shared.stopped.load(Ordering::SeqCst) {
+                    break;
+                }
+                match stream {
+                    Ok(stream) => {
+                        let shared = Arc::clone(&server_shared);
+                        thread::spawn(move || {
+                            if let Err(e) = serve_connection(&shared, stream) {
+                                log::debug!("Mock node connection closed: {}", e);
+                            }
+                        });
+                    }
+                    Err(e) => log::warn!("Mock node failed to accept connection: {}", e),
+                }
+            }
+        });
+
+        Ok(MockNode { addr, shared })
+    }
+
+    /// Address of the JSON-RPC server
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Access the chain of the node. Changes made directly to the chain are not notified to the
+    /// subscribers.
+    pub fn chain(&self) -> MutexGuard<'_, MockChain> {
+        self.shared.chain.lock().unwrap()
+    }
+
+    /// Add a transaction which will be included in the next block
+    pub fn add_transaction(&self, transaction: Transaction) {
+        self.chain().add_transaction(transaction);
+    }
+
+    /// Add a transaction which will not be included in a block until `delay` more blocks have
+    /// been mined
+    pub fn add_transaction_with_delay(&self, transaction: Transaction, delay: u32) {
+        self.chain().add_transaction_with_delay(transaction, delay);
+    }
+
+    /// Add two transactions spending the same outputs, see
+    /// [`MockChain::double_spend`](crate::chain::MockChain::double_spend)
+    pub fn double_spend(&self, winner: Transaction, loser: Transaction, delay: u32) {
+        self.chain().double_spend(winner, loser, delay);
+    }
+
+    /// Mine a new block and notify it to the subscribers
+    pub fn mine_block(&self) -> Block {
+        let block = self.chain().mine_block();
+        self.notify_blocks(&[block.clone()]);
+
+        block
+    }
+
+    /// Mine `blocks` new blocks and notify them to the subscribers
+    pub fn mine_blocks(&self, blocks: usize) -> Vec<Block> {
+        let blocks = self.chain().mine_blocks(blocks);
+        self.notify_blocks(&blocks);
+
+        blocks
+    }
+
+    /// Replace the last `depth` blocks with `length` new blocks, and notify the new blocks to
+    /// the subscribers
+    pub fn reorg(&self, depth: usize, length: usize) -> Vec<Block> {
+        let blocks = self.chain().reorg(depth, length);
+        self.notify_blocks(&blocks);
+
+        blocks
+    }
+
+    fn notify_blocks(&self, blocks: &[Block]) {
+        let mut subscribers = self.shared.subscribers.lock().unwrap();
+        for block in blocks {
+            let block = serde_json::to_value(block).unwrap();
+            // Subscribers whose connection is closed are removed
+            subscribers.retain(|subscriber| {
+                let notification = json!({
+                    "jsonrpc": "2.0",
+                    "method": "witnet_subscription",
+                    "params": {
+                        "result": block,
+                        "subscription": subscriber.id,
+                    },
+                });
+                write_message(&subscriber.writer, &notification).is_ok()
+            });
+        }
+    }
+}
+
+impl Drop for MockNode {
+    fn drop(&mut self) {
+        self.shared.stopped.store(true, Ordering::SeqCst);
+        // Wake up the listener thread so that it can finish
+        TcpStream::connect(self.addr).ok();
+    }
+}
+
+fn serve_connection(shared: &Shared, stream: TcpStream) -> io::Result<()> {
+    let reader = BufReader::new(stream.try_clone()?);
+    let writer = Arc::new(Mutex::new(stream));
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if shared.stopped.load(Ordering::SeqCst) {
+            break;
+        }
+
+        let response = match serde_json::from_str::<Value>(&line) {
+            Ok(request) => handle_request(shared, &writer, &request),
+            Err(e) => Some(error_response(
+                Value::Null,
+                RpcError::new(PARSE_ERROR, format!("Parse error: {}", e)),
+            )),
+        };
+        if let Some(response) = response {
+            write_message(&writer, &response)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Handle a request, returning the response or `None` for notifications
+fn handle_request(shared: &Shared, writer: &Writer, request: &Value) -> Option<Value> {
+    let id = request.get("id").cloned();
+    let result = match request.get("method").and_then(Value::as_str) {
+        Some(method) => {
+            let params = request.get("params").cloned().unwrap_or(Value::Null);
+            call_method(shared, writer, method, params)
+        }
+        None => Err(RpcError::new(INVALID_REQUEST, "Invalid request")),
+    };
+
+    id.map(|id| match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "result": result, "id": id }),
+        Err(error) => error_response(id, error),
+    })
+}
+
+fn call_method(
+    shared: &Shared,
+    writer: &Writer,
+    method: &str,
+    params: Value,
+) -> Result<Value, RpcError> {
+    let chain = || shared.chain.lock().unwrap();
+
+    match method {
+        "getBlockChain" => {
+            let params = match params {
+                Value::Null => Value::Object(Default::default()),
+                params => params,
+            };
+            let epoch = params.get("epoch").and_then(Value::as_i64).unwrap_or(0);
+            let limit = params.get("limit").and_then(Value::as_u64).unwrap_or(0) as u32;
+            let block_chain: Vec<(u32, String)> = chain()
+                .block_chain(epoch, limit)
+                .into_iter()
+                .map(|(epoch, hash)| (epoch, hash.to_string()))
+                .collect();
+
+            Ok(json!(block_chain))
+        }
+        "getBlock" => {
+            let (hash,): (Hash,) = parse_params(params)?;
+            let chain = chain();
+            let block = chain
+                .block(&hash)
+                .ok_or_else(|| RpcError::new(INTERNAL_ERROR, format!("Unknown block {}", hash)))?;
+
+            serde_json::to_value(block).map_err(|e| RpcError::new(INTERNAL_ERROR, e))
+        }
+        "getOutput" => {
+            let (output_pointer,): (String,) = parse_params(params)?;
+            let output_pointer = OutputPointer::from_str(&output_pointer)
+                .map_err(|e| RpcError::new(INVALID_PARAMS, format!("{:?}", e)))?;
+            let chain = chain();
+            let output = chain.output(&output_pointer).ok_or_else(|| {
+                RpcError::new(INTERNAL_ERROR, format!("Unknown output {}", output_pointer))
+            })?;
+
+            serde_json::to_value(output).map_err(|e| RpcError::new(INTERNAL_ERROR, e))
+        }
+        "inventory" => match parse_params(params)? {
+            InventoryItem::Transaction(transaction) => {
+                chain().add_transaction(transaction);
+
+                Ok(Value::Bool(true))
+            }
+            InventoryItem::Block(_) => {
+                Err(RpcError::new(INVALID_PARAMS, "Item type not implemented"))
+            }
+        },
+        "witnet_subscribe" => {
+            let params: Vec<Value> = parse_params(params)?;
+            match params.get(0).and_then(Value::as_str) {
+                Some("newBlocks") => {
+                    let id = shared
+                        .next_subscription
+                        .fetch_add(1, Ordering::SeqCst)
+                        .to_string();
+                    shared.subscribers.lock().unwrap().push(Subscriber {
+                        id: id.clone(),
+                        writer: Arc::clone(writer),
+                    });
+
+                    Ok(Value::String(id))
+                }
+                other => Err(RpcError::new(
+                    INVALID_PARAMS,
+                    format!("Unknown subscription: {:?}", other),
+                )),
+            }
+        }
+        "witnet_unsubscribe" => {
+            let (id,): (String,) = parse_params(params)?;
+            let mut subscribers = shared.subscribers.lock().unwrap();
+            let len = subscribers.len();
+            subscribers.retain(|subscriber| subscriber.id != id);
+
+            Ok(Value::Bool(subscribers.len() != len))
+        }
+        method => Err(RpcError::new(
+            METHOD_NOT_FOUND,
+            format!("Method not found: {}", method),
+        )),
+    }
+}
+
+fn parse_params<T: serde::de::DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e))
+}
+
+fn error_response(id: Value, error: RpcError) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "error": { "code": error.code, "message": error.message },
+        "id": id,
+    })
+}
+
+fn write_message(writer: &Writer, message: &Value) -> io::Result<()> {
+    let mut bytes = serde_json::to_vec(message)?;
+    bytes.push(b'\n');
+
+    writer.lock().unwrap().write_all(&bytes)
+}
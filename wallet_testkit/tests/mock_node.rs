@@ -0,0 +1,109 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::TcpStream,
+};
+
+use serde_json::{json, Value};
+use witnet_data_structures::chain::{Hashable, InventoryItem, Transaction};
+use witnet_wallet_testkit::MockNode;
+
+struct Client {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Client {
+    fn connect(node: &MockNode) -> Self {
+        let writer = TcpStream::connect(node.addr()).unwrap();
+        let reader = BufReader::new(writer.try_clone().unwrap());
+
+        Client { reader, writer }
+    }
+
+    fn read(&mut self) -> Value {
+        let mut line = String::new();
+        self.reader.read_line(&mut line).unwrap();
+
+        serde_json::from_str(&line).unwrap()
+    }
+
+    fn call(&mut self, method: &str, params: Value) -> Value {
+        let request = json!({ "jsonrpc": "2.0", "method": method, "params": params, "id": 1 });
+        writeln!(self.writer, "{}", request).unwrap();
+
+        self.read()
+    }
+}
+
+#[test]
+fn get_block_chain_and_blocks() {
+    let node = MockNode::start("127.0.0.1:0").unwrap();
+    let blocks = node.mine_blocks(2);
+    let mut client = Client::connect(&node);
+
+    let response = client.call("getBlockChain", json!({ "epoch": -1 }));
+    assert_eq!(
+        response["result"],
+        json!([
+            [1, blocks[0].hash().to_string()],
+            [2, blocks[1].hash().to_string()],
+        ])
+    );
+
+    let response = client.call("getBlock", json!([blocks[1].hash().to_string()]));
+    assert_eq!(
+        response["result"],
+        serde_json::to_value(&blocks[1]).unwrap()
+    );
+
+    let response = client.call("getBlock", json!(["00".repeat(32)]));
+    assert_eq!(response["error"]["code"], -32603);
+
+    let response = client.call("unknownMethod", Value::Null);
+    assert_eq!(response["error"]["code"], -32601);
+}
+
+#[test]
+fn new_blocks_subscription_follows_reorgs() {
+    let node = MockNode::start("127.0.0.1:0").unwrap();
+    let mut client = Client::connect(&node);
+
+    let response = client.call("witnet_subscribe", json!(["newBlocks"]));
+    let subscription = response["result"].clone();
+
+    let block = node.mine_block();
+    let notification = client.read();
+    assert_eq!(notification["method"], "witnet_subscription");
+    assert_eq!(notification["params"]["subscription"], subscription);
+    assert_eq!(
+        notification["params"]["result"],
+        serde_json::to_value(&block).unwrap()
+    );
+
+    let new_blocks = node.reorg(1, 2);
+    for new_block in new_blocks {
+        let notification = client.read();
+        assert_eq!(
+            notification["params"]["result"],
+            serde_json::to_value(&new_block).unwrap()
+        );
+    }
+
+    let response = client.call("witnet_unsubscribe", json!([subscription]));
+    assert_eq!(response["result"], true);
+}
+
+#[test]
+fn inventory_transactions_are_mined() {
+    let node = MockNode::start("127.0.0.1:0").unwrap();
+    let mut client = Client::connect(&node);
+    let transaction = Transaction::default();
+
+    let item = InventoryItem::Transaction(transaction.clone());
+    let response = client.call("inventory", serde_json::to_value(&item).unwrap());
+    assert_eq!(response["result"], true);
+    assert_eq!(node.chain().pending_transactions(), vec![&transaction]);
+
+    node.mine_block();
+    assert_eq!(node.chain().confirmations(&transaction.hash()), Some(1));
+}